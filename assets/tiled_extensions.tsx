@@ -0,0 +1,14 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="extensions" tilewidth="32" tileheight="32" tilecount="84" columns="14" xmlns:editor="https://example.com/editor" editor:color="#ff0000">
+ <image source="tilesheet.png" width="448" height="192"/>
+ <tile id="3" editor:locked="1">
+  <objectgroup draworder="index" id="2">
+   <object id="1" x="2" y="4" width="8" height="6">
+    <editor:hint kind="solid">Blocks <![CDATA[<everything>]]></editor:hint>
+   </object>
+  </objectgroup>
+ </tile>
+ <editor:notes>
+  <editor:note author="someone">Keep the grass tiles together</editor:note>
+ </editor:notes>
+</tileset>
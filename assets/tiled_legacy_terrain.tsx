@@ -0,0 +1,14 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.2" tiledversion="1.2.4" name="legacy_terrain" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+ <image source="tilesheet.png" width="448" height="192"/>
+ <terraintypes>
+  <terrain name="Grass" tile="14"/>
+  <terrain name="Water" tile="-1">
+   <properties>
+    <property name="swimmable" type="bool" value="true"/>
+   </properties>
+  </terrain>
+ </terraintypes>
+ <tile id="0" terrain="0,0,0,0"/>
+ <tile id="1" terrain="0,1,,1" probability="0.5"/>
+</tileset>
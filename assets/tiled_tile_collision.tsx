@@ -0,0 +1,25 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="collision" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+ <image source="tilesheet.png" width="448" height="192"/>
+ <tile id="3">
+  <objectgroup draworder="index" id="2">
+   <object id="1" x="2" y="4" width="8" height="6"/>
+   <object id="2" x="16" y="0" width="10" height="4" rotation="90"/>
+   <object id="3" x="4" y="12" width="6" height="8">
+    <ellipse/>
+   </object>
+   <object id="4" x="20" y="20">
+    <polygon points="0,0 4,0 4,4"/>
+   </object>
+   <object id="5" x="1" y="30">
+    <polyline points="0,0 10,-2"/>
+   </object>
+   <object id="6" x="16" y="16">
+    <point/>
+   </object>
+   <object id="7" x="0" y="0" width="32" height="8">
+    <text wrap="1">Not a shape</text>
+   </object>
+  </objectgroup>
+ </tile>
+</tileset>
@@ -0,0 +1,10 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="tall_trees" tilewidth="32" tileheight="64" tilecount="2" columns="0">
+ <tileoffset x="-4" y="16"/>
+ <tile id="0">
+  <image width="32" height="64" source="tilesheet.png"/>
+ </tile>
+ <tile id="1">
+  <image width="32" height="64" source="tilesheet.png"/>
+ </tile>
+</tileset>
@@ -0,0 +1,8 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="isometric_props" tilewidth="64" tileheight="64" tilecount="1" columns="0" objectalignment="bottom" tilerendersize="grid" fillmode="preserve-aspect-fit">
+ <grid orientation="isometric" width="64" height="32"/>
+ <transformations hflip="1" vflip="0" rotate="1" preferuntransformed="1"/>
+ <tile id="0">
+  <image width="64" height="64" source="tilesheet.png" trans="ff00ff"/>
+ </tile>
+</tileset>
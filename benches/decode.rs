@@ -0,0 +1,110 @@
+//! Measures how long loading maps with large tile layers takes, to compare the regular parser of
+//! CSV tile data with the faster one enabled by the `fast-csv` feature. Base64 data is measured
+//! as well for reference, since the feature doesn't change how it is decoded:
+//!
+//! ```sh
+//! cargo bench --bench decode
+//! cargo bench --bench decode --features fast-csv
+//! ```
+
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use base64::Engine;
+use tiled::{DefaultResourceCache, Loader, MemoryResourceReader, TileLayer};
+
+/// The width and height of the layers, in tiles.
+const SIZE: usize = 1024;
+const RUNS: u32 = 10;
+
+/// Returns a map with a single layer of the size above, encoded as given.
+fn map(encoding: &str, compression: Option<&str>) -> Vec<u8> {
+    let gids: Vec<u32> = (0..SIZE * SIZE).map(|i| (i % 85) as u32).collect();
+    let bytes: Vec<u8> = gids.iter().flat_map(|gid| gid.to_le_bytes()).collect();
+    let data = match compression {
+        _ if encoding == "csv" => gids
+            .chunks(SIZE)
+            .map(|row| row.iter().map(u32::to_string).collect::<Vec<_>>().join(","))
+            .collect::<Vec<_>>()
+            .join(",\n"),
+        None => base64::engine::general_purpose::STANDARD.encode(&bytes),
+        Some("zlib") => {
+            let mut encoder = libflate::zlib::Encoder::new(Vec::new()).unwrap();
+            encoder.write_all(&bytes).unwrap();
+            base64::engine::general_purpose::STANDARD
+                .encode(encoder.finish().into_result().unwrap())
+        }
+        Some(compression) => panic!("unsupported compression {}", compression),
+    };
+    let compression = compression
+        .map(|compression| format!(r#" compression="{}""#, compression))
+        .unwrap_or_default();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="{size}" height="{size}" tilewidth="32" tileheight="32" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tileset>
+ <layer id="1" name="layer" width="{size}" height="{size}">
+  <data encoding="{encoding}"{compression}>
+{data}
+  </data>
+ </layer>
+</map>"#,
+        size = SIZE,
+        encoding = encoding,
+        compression = compression,
+        data = data,
+    )
+    .into_bytes()
+}
+
+fn main() {
+    println!(
+        "fast-csv {}",
+        if cfg!(feature = "fast-csv") {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    for (name, encoding, compression) in [
+        ("csv", "csv", None),
+        ("base64", "base64", None),
+        ("base64 + zlib", "base64", Some("zlib")),
+    ] {
+        let reader = MemoryResourceReader::new().with_file("map.tmx", map(encoding, compression));
+        let mut loader = Loader::with_cache_and_reader(DefaultResourceCache::new(), reader);
+        loader.set_defer_tile_data(true);
+        let map = loader.load_tmx_map("map.tmx").unwrap();
+
+        // Loading the map reads the encoded data out of the XML, and decoding it is deferred to
+        // be measured on its own.
+        let (mut load, mut decode) = (Duration::ZERO, Duration::ZERO);
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            loader.load_tmx_map("map.tmx").unwrap();
+            load += start.elapsed();
+
+            let map = map.clone();
+            let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+                TileLayer::Finite(layer) => layer,
+                TileLayer::Infinite(_) => unreachable!(),
+            };
+            let start = Instant::now();
+            layer.ensure_decoded().unwrap();
+            decode += start.elapsed();
+        }
+        let millis = |total: Duration| total.as_secs_f64() * 1000.0 / f64::from(RUNS);
+        println!(
+            "{:>14}: {:7.2} ms to load, {:6.2} ms to decode {}x{} tiles",
+            name,
+            millis(load),
+            millis(decode),
+            SIZE,
+            SIZE
+        );
+    }
+}
@@ -1,10 +1,15 @@
 //! Structures related to tile animations.
 
-use xml::attribute::OwnedAttribute;
+use std::io::Write;
+
+use xml::{attribute::OwnedAttribute, EventWriter};
 
 use crate::{
     error::{Error, Result},
-    util::{get_attrs, parse_tag, XmlEventResult},
+    util::{
+        get_attrs, parse_tag, write_empty_element, write_end_element, write_start_element,
+        XmlEventResult,
+    },
 };
 
 /// A structure describing a [frame] of a [TMX tile animation].
@@ -12,6 +17,7 @@ use crate::{
 /// [frame]: https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tmx-frame
 /// [TMX tile animation]: https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#animation
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Frame {
     /// The local ID of a tile within the parent tileset.
     pub tile_id: u32,
@@ -32,6 +38,97 @@ impl Frame {
     }
 }
 
+/// The playback state of a tile animation, keeping track of which of its frames should be
+/// displayed as time passes. Animations loop once their last frame is over.
+///
+/// ## Example
+/// ```
+/// use tiled::{AnimationState, Frame};
+///
+/// let frames = [
+///     Frame { tile_id: 4, duration: 100 },
+///     Frame { tile_id: 7, duration: 50 },
+/// ];
+/// let mut state = AnimationState::new(&frames);
+/// assert_eq!(state.current_tile_id(), Some(4));
+///
+/// state.advance(120);
+/// assert_eq!(state.current_tile_id(), Some(7));
+///
+/// // Loops back to the first frame.
+/// state.advance(40);
+/// assert_eq!(state.current_tile_id(), Some(4));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct AnimationState {
+    frames: Vec<Frame>,
+    /// The index of the frame currently displayed.
+    current: usize,
+    /// How long (in milliseconds) the current frame has been displayed for.
+    elapsed: u64,
+}
+
+impl AnimationState {
+    /// Creates the state of an animation with the frames given, starting at its first frame.
+    ///
+    /// Usually, the frames are the [`animation`](crate::TileData::animation) of a tile.
+    pub fn new(frames: &[Frame]) -> Self {
+        Self {
+            frames: frames.to_vec(),
+            current: 0,
+            elapsed: 0,
+        }
+    }
+
+    /// Advances the animation by the time given, in milliseconds, looping back to the first frame
+    /// as many times as needed.
+    ///
+    /// Animations whose frames all have a duration of 0 never advance.
+    pub fn advance(&mut self, delta_ms: u32) {
+        let duration = |frame: &Frame| frame.duration as u64;
+        let total: u64 = self.frames.iter().map(duration).sum();
+        if total == 0 {
+            return;
+        }
+        // The position within the loop, measured from the start of the first frame.
+        let start: u64 = self.frames[..self.current].iter().map(duration).sum();
+        let mut position = (start + self.elapsed + delta_ms as u64) % total;
+        self.current = 0;
+        while position >= duration(&self.frames[self.current]) {
+            position -= duration(&self.frames[self.current]);
+            self.current += 1;
+        }
+        self.elapsed = position;
+    }
+
+    /// Returns the index of the frame currently displayed, within the animation's frames.
+    pub fn current_frame_index(&self) -> usize {
+        self.current
+    }
+
+    /// Returns the frame currently displayed, or [`None`] if the animation has no frames.
+    pub fn current_frame(&self) -> Option<&Frame> {
+        self.frames.get(self.current)
+    }
+
+    /// Returns the local ID of the tile currently displayed, or [`None`] if the animation has no
+    /// frames.
+    pub fn current_tile_id(&self) -> Option<u32> {
+        self.current_frame().map(|frame| frame.tile_id)
+    }
+
+    /// Returns the animation's frames.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Restarts the animation from its first frame.
+    pub fn reset(&mut self) {
+        self.current = 0;
+        self.elapsed = 0;
+    }
+}
+
 pub(crate) fn parse_animation(
     parser: &mut impl Iterator<Item = XmlEventResult>,
 ) -> Result<Vec<Frame>> {
@@ -44,3 +141,35 @@ pub(crate) fn parse_animation(
     });
     Ok(animation)
 }
+
+#[cfg(feature = "json")]
+pub(crate) fn parse_json_animation(frames: &[serde_json::Value]) -> Result<Vec<Frame>> {
+    use crate::parse::json::{as_objects, as_u32, JsonObjectExt};
+
+    as_objects(frames, "animation")?
+        .map(|frame| {
+            Ok(Frame {
+                tile_id: frame.req("tileid", as_u32)?,
+                duration: frame.req("duration", as_u32)?,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn write_animation(
+    writer: &mut EventWriter<impl Write>,
+    animation: &[Frame],
+) -> Result<()> {
+    write_start_element(writer, "animation", &[])?;
+    for frame in animation {
+        write_empty_element(
+            writer,
+            "frame",
+            &[
+                ("tileid", frame.tile_id.to_string()),
+                ("duration", frame.duration.to_string()),
+            ],
+        )?;
+    }
+    write_end_element(writer)
+}
@@ -0,0 +1,95 @@
+use std::{
+    io::{Cursor, Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use zip::{result::ZipError, ZipArchive};
+
+use crate::{util::normalize_path, ResourceReader};
+
+/// A [`ResourceReader`] that reads files out of a zip archive. Requires the `zip` feature.
+///
+/// Paths are looked up relative to the root of the archive, after resolving any `.` and `..`
+/// components, so the files referenced by a map or tileset are found as long as the archive keeps
+/// the same directory layout they had when saved. Image sources are resolved the same way, so
+/// their contents can be read with [`ResourceReader::read_from`] as well.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use std::fs::File;
+///
+/// use tiled::{DefaultResourceCache, Loader, ZipResourceReader};
+///
+/// let archive = File::open("assets/tiled_archive.zip").unwrap();
+/// let reader = ZipResourceReader::new(archive).unwrap();
+/// let mut loader = Loader::with_cache_and_reader(DefaultResourceCache::new(), reader);
+/// let map = loader.load_tmx_map("maps/external.tmx")?;
+/// assert_eq!(map.tilesets()[0].name, "tilesheet");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ZipResourceReader<R> {
+    archive: ZipArchive<R>,
+}
+
+impl<R: Read + Seek> ZipResourceReader<R> {
+    /// Opens the zip archive contained in `reader`.
+    pub fn new(reader: R) -> std::io::Result<Self> {
+        ZipArchive::new(reader)
+            .map(|archive| Self { archive })
+            .map_err(into_io_error)
+    }
+
+    /// Consumes the reader and returns the reader of the archive.
+    pub fn into_inner(self) -> R {
+        self.archive.into_inner()
+    }
+}
+
+/// Converts a path into the name of an entry of the archive, or returns [`None`] if the path
+/// points outside of it.
+fn entry_name(path: &Path) -> Option<String> {
+    let path = normalize_path(path)?;
+    let components: Option<Vec<&str>> = path.iter().map(|name| name.to_str()).collect();
+    Some(components?.join("/"))
+}
+
+fn into_io_error(err: ZipError) -> std::io::Error {
+    match err {
+        ZipError::Io(err) => err,
+        ZipError::FileNotFound => std::io::Error::new(std::io::ErrorKind::NotFound, err),
+        err => std::io::Error::new(std::io::ErrorKind::InvalidData, err),
+    }
+}
+
+impl<R: Read + Seek> ResourceReader for ZipResourceReader<R> {
+    type Resource = Cursor<Vec<u8>>;
+    type Error = std::io::Error;
+
+    fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+        let name = entry_name(path).ok_or_else(|| into_io_error(ZipError::FileNotFound))?;
+        let mut file = self.archive.by_name(&name).map_err(into_io_error)?;
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+        Ok(Cursor::new(data))
+    }
+
+    fn list_files(&mut self, directory: &Path) -> std::result::Result<Vec<PathBuf>, Self::Error> {
+        let directory = match entry_name(directory) {
+            Some(directory) if directory.is_empty() => directory,
+            Some(directory) => directory + "/",
+            None => return Ok(Vec::new()),
+        };
+        let mut files: Vec<PathBuf> = self
+            .archive
+            .file_names()
+            .filter_map(|name| name.strip_prefix(directory.as_str()))
+            .filter(|name| !name.is_empty() && !name.contains('/'))
+            .map(|name| Path::new(&directory).join(name))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
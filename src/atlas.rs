@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use crate::{ResourceReader, Result, TileId, Tileset};
+
+/// The images of a tileset packed into a single image, usually to be uploaded as one texture.
+///
+/// Created with [`Tileset::pack_atlas()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureAtlas {
+    /// The packed image, with the [transparent colors](crate::Image::transparent_colour) of the
+    /// original images applied.
+    pub image: image_crate::RgbaImage,
+    /// The rectangle `(x, y, width, height)` in pixels occupied by the image of each tile.
+    pub rects: HashMap<TileId, (u32, u32, u32, u32)>,
+}
+
+impl TextureAtlas {
+    /// Returns the rectangle `(x, y, width, height)` in pixels occupied by the image of a tile.
+    pub fn get_rect(&self, id: TileId) -> Option<(u32, u32, u32, u32)> {
+        self.rects.get(&id).copied()
+    }
+
+    /// Returns the texture coordinates `(u0, v0, u1, v1)` of the image of a tile, between 0 and 1,
+    /// from its top-left corner to its bottom-right corner.
+    pub fn get_uv_rect(&self, id: TileId) -> Option<(f32, f32, f32, f32)> {
+        let (x, y, width, height) = self.get_rect(id)?;
+        let (atlas_width, atlas_height) = (self.image.width() as f32, self.image.height() as f32);
+        Some((
+            x as f32 / atlas_width,
+            y as f32 / atlas_height,
+            (x + width) as f32 / atlas_width,
+            (y + height) as f32 / atlas_height,
+        ))
+    }
+}
+
+impl Tileset {
+    /// Reads and decodes the images of the tileset using `reader`, and packs them into a single
+    /// [`TextureAtlas`], leaving `padding` pixels between them.
+    ///
+    /// The images of image collection tilesets are packed in rows, from the tallest to the
+    /// shortest, into an atlas whose width is a power of two. Tilesets based on a single image
+    /// already are an atlas: Their image is returned as is, along with the
+    /// [rectangles](Self::tile_image_rect) of their tiles, and `padding` is ignored.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{FilesystemResourceReader, Loader};
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    /// let atlas = tileset.pack_atlas(&mut FilesystemResourceReader, 0)?;
+    /// assert_eq!(atlas.get_rect(15), Some((32, 32, 32, 32)));
+    /// assert_eq!(atlas.get_uv_rect(0), Some((0.0, 0.0, 32.0 / 448.0, 32.0 / 192.0)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pack_atlas(
+        &self,
+        reader: &mut impl ResourceReader,
+        padding: u32,
+    ) -> Result<TextureAtlas> {
+        if let Some(image) = &self.image {
+            return Ok(TextureAtlas {
+                image: image.load_pixels(reader)?,
+                rects: (0..self.tilecount)
+                    .filter_map(|id| Some((id, self.tile_image_rect(id)?)))
+                    .collect(),
+            });
+        }
+
+        let mut images = Vec::new();
+        for (id, tile) in self.tiles() {
+            if let Some(image) = &tile.image {
+                images.push((id, image.load_pixels(reader)?));
+            }
+        }
+        // Tallest first, so that the rows waste as little space as possible.
+        images.sort_by_key(|(id, pixels)| (std::cmp::Reverse(pixels.height()), *id));
+
+        let area: u64 = images
+            .iter()
+            .map(|(_, pixels)| {
+                u64::from(pixels.width() + padding) * u64::from(pixels.height() + padding)
+            })
+            .sum();
+        let widest = images.iter().map(|(_, pixels)| pixels.width()).max();
+        let width = match widest {
+            Some(widest) => widest
+                .max((area as f64).sqrt().ceil() as u32)
+                .next_power_of_two(),
+            None => 0,
+        };
+
+        let mut rects = HashMap::new();
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        for (id, pixels) in &images {
+            if x > 0 && x + pixels.width() > width {
+                x = 0;
+                y += row_height + padding;
+                row_height = 0;
+            }
+            rects.insert(*id, (x, y, pixels.width(), pixels.height()));
+            x += pixels.width() + padding;
+            row_height = row_height.max(pixels.height());
+        }
+
+        let mut atlas = image_crate::RgbaImage::new(width, y + row_height);
+        for (id, pixels) in &images {
+            let (x, y, _, _) = rects[id];
+            image_crate::imageops::replace(&mut atlas, pixels, x.into(), y.into());
+        }
+        Ok(TextureAtlas {
+            image: atlas,
+            rects,
+        })
+    }
+}
@@ -0,0 +1,254 @@
+//! Places the tiles of a [Wang set](WangSet) automatically, so that procedurally generated levels
+//! follow the terrain rules set up by artists in Tiled.
+//!
+//! An [`Autotiler`] turns a grid telling which cells are filled with a color of a Wang set into
+//! tiles, the same way painting those cells with the terrain brush of Tiled would: Each corner and
+//! edge of a cell takes the color if any of the cells sharing it is filled, and the tile whose
+//! [`WangId`] matches is placed in the cell. Only the corners of corner sets and the edges of edge
+//! sets are taken into account.
+//!
+//! When several tiles match, one is picked at random according to their
+//! [probabilities](crate::TileData::probability), using a seeded generator so that the result is
+//! reproducible. Flipped and rotated tiles are considered if the
+//! [transformations](crate::Tileset::transformations) of the tileset allow it. When no tile
+//! matches exactly, the ones with the fewest mismatching corners and edges are used instead.
+//!
+//! ## Example
+//! ```
+//! # fn main() -> tiled::Result<()> {
+//! use tiled::{autotile::Autotiler, FiniteTileLayerData, Loader};
+//!
+//! let tileset = Loader::new().load_tsx_tileset("assets/tilesheet_wangsets.tsx")?;
+//! let wang_set = &tileset.wang_sets[0];
+//!
+//! // A 3x3 block in the middle of a 5x5 layer.
+//! let filled: Vec<bool> = (0..25).map(|i| (1..4).contains(&(i % 5)) && (1..4).contains(&(i / 5))).collect();
+//! let mut layer = FiniteTileLayerData::empty(5, 5);
+//! Autotiler::new(&tileset, 0, wang_set, 1).fill(&mut layer, &filled);
+//!
+//! let center = layer.get_tile_data(2, 2).unwrap();
+//! assert_eq!(wang_set.wang_tiles[&center.id()].wang_id.0, [1; 8]);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{FiniteTileLayerData, LayerTileData, TileId, Tileset, WangId, WangSet, WangSetType};
+
+/// Selects the tiles of a Wang set matching the cells of a grid. See the
+/// [module documentation](self) for details.
+#[derive(Debug, Clone)]
+pub struct Autotiler<'a> {
+    tileset: &'a Tileset,
+    tileset_index: usize,
+    wang_set: &'a WangSet,
+    color: u8,
+    seed: u64,
+}
+
+impl<'a> Autotiler<'a> {
+    /// Creates an autotiler filling cells with `color`, a 1-based index into the
+    /// [colors](WangSet::wang_colors) of `wang_set`, which must be one of the Wang sets of
+    /// `tileset`. The tiles placed refer to the tileset with the index `tileset_index` within the
+    /// map the layer belongs to.
+    pub fn new(
+        tileset: &'a Tileset,
+        tileset_index: usize,
+        wang_set: &'a WangSet,
+        color: u8,
+    ) -> Self {
+        Self {
+            tileset,
+            tileset_index,
+            wang_set,
+            color,
+            seed: 0,
+        }
+    }
+
+    /// Sets the seed used to pick tiles at random when several match. Defaults to 0.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Writes the tiles matching `filled`, a grid of the size of `layer` whose cells are arranged
+    /// in rows, into `layer`. Cells without any colored corner or edge are cleared, and cells no
+    /// tile matches at all are left as they are.
+    ///
+    /// ## Panics
+    /// Panics if the length of `filled` isn't the amount of tiles of the layer.
+    pub fn fill(&self, layer: &mut FiniteTileLayerData, filled: &[bool]) {
+        let (width, height) = (layer.width() as i32, layer.height() as i32);
+        assert_eq!(
+            filled.len(),
+            (width * height) as usize,
+            "the grid must have the size of the layer"
+        );
+        let is_filled = |x: i32, y: i32| {
+            (0..width).contains(&x) && (0..height).contains(&y) && filled[(x + y * width) as usize]
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let wang_id = self.wang_id_at(&is_filled, x, y);
+                if wang_id.0 == [0; 8] {
+                    layer.set_tile(x, y, None);
+                } else if let Some(tile) = self.select(wang_id, x, y) {
+                    layer.set_tile(x, y, Some(tile));
+                }
+            }
+        }
+    }
+
+    /// Returns the tile to place at the position given for the corners and edges of `wang_id`, if
+    /// any tile of the Wang set comes close. The position only serves to pick a tile at random.
+    pub fn select(&self, wang_id: WangId, x: i32, y: i32) -> Option<LayerTileData> {
+        let mut best = Vec::new();
+        let mut best_score = usize::MAX;
+        for (id, wang_tile) in &self.wang_set.wang_tiles {
+            for &(flip_h, flip_v, flip_d) in self.transforms() {
+                let transformed = transform(wang_tile.wang_id, flip_h, flip_v, flip_d);
+                let score = self
+                    .relevant_indices()
+                    .filter(|&index| transformed.0[index] != wang_id.0[index])
+                    .count();
+                if score < best_score {
+                    best.clear();
+                    best_score = score;
+                }
+                if score == best_score {
+                    best.push((*id, flip_h, flip_v, flip_d));
+                }
+            }
+        }
+        if self.tileset.transformations.prefer_untransformed
+            && best.iter().any(|&(_, h, v, d)| !(h || v || d))
+        {
+            best.retain(|&(_, h, v, d)| !(h || v || d));
+        }
+        // Sorted for the result not to depend on the iteration order of the Wang tiles.
+        best.sort_by_key(|&(id, h, v, d)| (id, h, v, d));
+
+        let probability = |id: TileId| {
+            self.tileset
+                .get_tile(id)
+                .map_or(1.0, |tile| tile.probability)
+        };
+        let total: f32 = best.iter().map(|&(id, ..)| probability(id)).sum();
+        let mut target = random(self.seed, x, y) * total;
+        let &(id, flip_h, flip_v, flip_d) = best
+            .iter()
+            .find(|&&(id, ..)| {
+                target -= probability(id);
+                target < 0.0
+            })
+            .or_else(|| best.last())?;
+
+        let mut tile = LayerTileData::new(self.tileset_index, id);
+        tile.flip_h = flip_h;
+        tile.flip_v = flip_v;
+        tile.flip_d = flip_d;
+        Some(tile)
+    }
+
+    /// Returns the Wang ID of the cell at the position given, each corner and edge of which has
+    /// the color if any of the cells sharing it is filled.
+    fn wang_id_at(&self, is_filled: &impl Fn(i32, i32) -> bool, x: i32, y: i32) -> WangId {
+        // The cells sharing each index in clockwise order from the top edge, besides this one.
+        const NEIGHBORS: [&[(i32, i32)]; 8] = [
+            &[(0, -1)],
+            &[(0, -1), (1, -1), (1, 0)],
+            &[(1, 0)],
+            &[(1, 0), (1, 1), (0, 1)],
+            &[(0, 1)],
+            &[(0, 1), (-1, 1), (-1, 0)],
+            &[(-1, 0)],
+            &[(-1, 0), (-1, -1), (0, -1)],
+        ];
+        let mut wang_id = WangId([0; 8]);
+        for index in self.relevant_indices() {
+            if is_filled(x, y)
+                || NEIGHBORS[index]
+                    .iter()
+                    .any(|(dx, dy)| is_filled(x + dx, y + dy))
+            {
+                wang_id.0[index] = self.color;
+            }
+        }
+        wang_id
+    }
+
+    /// Returns the indices of the Wang IDs that the type of the Wang set takes into account.
+    fn relevant_indices(&self) -> impl Iterator<Item = usize> {
+        let (start, step) = match self.wang_set.wang_set_type {
+            WangSetType::Corner => (1, 2),
+            WangSetType::Edge => (0, 2),
+            WangSetType::Mixed => (0, 1),
+        };
+        (start..8).step_by(step)
+    }
+
+    /// Returns the flip flags of the transformations the tileset allows, the untransformed tile
+    /// first.
+    fn transforms(&self) -> &'static [(bool, bool, bool)] {
+        const IDENTITY: (bool, bool, bool) = (false, false, false);
+        const FLIP_H: (bool, bool, bool) = (true, false, false);
+        const FLIP_V: (bool, bool, bool) = (false, true, false);
+        const ROTATE_90: (bool, bool, bool) = (true, false, true);
+        const ROTATE_180: (bool, bool, bool) = (true, true, false);
+        const ROTATE_270: (bool, bool, bool) = (false, true, true);
+        let transformations = self.tileset.transformations;
+        match (
+            transformations.flip_h,
+            transformations.flip_v,
+            transformations.rotate,
+        ) {
+            (true, _, true) | (_, true, true) => &[
+                IDENTITY,
+                FLIP_H,
+                FLIP_V,
+                ROTATE_90,
+                ROTATE_180,
+                ROTATE_270,
+                (false, false, true),
+                (true, true, true),
+            ],
+            (false, false, true) => &[IDENTITY, ROTATE_90, ROTATE_180, ROTATE_270],
+            (true, true, false) => &[IDENTITY, FLIP_H, FLIP_V, ROTATE_180],
+            (true, false, false) => &[IDENTITY, FLIP_H],
+            (false, true, false) => &[IDENTITY, FLIP_V],
+            (false, false, false) => &[IDENTITY],
+        }
+    }
+}
+
+/// Returns the Wang ID of a tile once drawn with the flip flags given, which are applied like
+/// those of GIDs: Diagonally first, then horizontally and vertically.
+fn transform(wang_id: WangId, flip_h: bool, flip_v: bool, flip_d: bool) -> WangId {
+    // Each maps an index of the drawn tile to the index of the original tile it shows.
+    const DIAGONAL: [usize; 8] = [6, 5, 4, 3, 2, 1, 0, 7];
+    const HORIZONTAL: [usize; 8] = [0, 7, 6, 5, 4, 3, 2, 1];
+    const VERTICAL: [usize; 8] = [4, 3, 2, 1, 0, 7, 6, 5];
+    let apply = |wang_id: WangId, map: [usize; 8]| WangId(map.map(|index| wang_id.0[index]));
+    let mut wang_id = wang_id;
+    if flip_d {
+        wang_id = apply(wang_id, DIAGONAL);
+    }
+    if flip_h {
+        wang_id = apply(wang_id, HORIZONTAL);
+    }
+    if flip_v {
+        wang_id = apply(wang_id, VERTICAL);
+    }
+    wang_id
+}
+
+/// Returns a number between 0 and 1 derived from the seed and position given.
+fn random(seed: u64, x: i32, y: i32) -> f32 {
+    // SplitMix64, seeded with the seed and position.
+    let mut z = seed ^ ((x as u32 as u64) << 32 | y as u32 as u64);
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
@@ -1,10 +1,16 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
 };
 
-use crate::{Template, Tileset};
+use crate::{
+    Frame, Image, ObjectData, ObjectShape, Properties, PropertyValue, Template, TileData, TileId,
+    Tileset, VirtualPath, WangColor, WangSet, WangTile,
+};
 
 /// A reference type that is used to refer to a resource. For the owned variant, see [`ResourcePathBuf`].
 pub type ResourcePath = Path;
@@ -47,9 +53,20 @@ pub trait ResourceCache {
     fn get_template(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Template>>;
     /// Insert a new template into the cache.
     fn insert_template(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Template>);
+    /// Removes the tileset and template at the given path from the cache, if there are any, so
+    /// that they are loaded again the next time they are needed.
+    ///
+    /// The `MapWatcher` of the `watch` feature relies on this to load the resources that changed
+    /// again.
+    fn invalidate(&mut self, path: impl AsRef<ResourcePath>);
+    /// Removes all the resources from the cache.
+    fn clear(&mut self);
 }
 
 /// A cache that identifies resources by their path, storing them in a [`HashMap`].
+///
+/// Paths are [normalized](VirtualPath) before being used as keys, so that all the ways of
+/// referring to a resource find it.
 #[derive(Debug, Default)]
 pub struct DefaultResourceCache {
     /// The tilesets cached until now.
@@ -70,18 +87,423 @@ impl DefaultResourceCache {
 
 impl ResourceCache for DefaultResourceCache {
     fn get_tileset(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Tileset>> {
-        self.tilesets.get(path.as_ref()).map(Clone::clone)
+        self.tilesets.get(&key(path)).map(Clone::clone)
     }
 
     fn insert_tileset(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Tileset>) {
-        self.tilesets.insert(path.as_ref().to_path_buf(), tileset);
+        self.tilesets.insert(key(path), tileset);
     }
 
     fn get_template(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Template>> {
-        self.templates.get(path.as_ref()).map(Clone::clone)
+        self.templates.get(&key(path)).map(Clone::clone)
     }
 
     fn insert_template(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Template>) {
-        self.templates.insert(path.as_ref().to_path_buf(), tileset);
+        self.templates.insert(key(path), tileset);
+    }
+
+    fn invalidate(&mut self, path: impl AsRef<ResourcePath>) {
+        let path = key(path);
+        self.tilesets.remove(&path);
+        self.templates.remove(&path);
+    }
+
+    fn clear(&mut self) {
+        self.tilesets.clear();
+        self.templates.clear();
+    }
+}
+
+/// How much a [`LruCache`] can hold before it starts evicting resources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LruCapacity {
+    /// The maximum amount of tilesets and templates, counted together.
+    Entries(usize),
+    /// The maximum amount of memory used by the resources, in bytes. The size of each resource
+    /// is estimated from the data it owns, such as its strings, properties and tiles, so it is
+    /// only approximate. The tileset of a template is counted along with the template.
+    Bytes(usize),
+}
+
+/// A cache that holds a limited amount of resources, evicting the least recently used ones once
+/// it is full.
+///
+/// Unlike [`DefaultResourceCache`], which keeps everything it is given, this cache doesn't grow
+/// indefinitely when loading many different maps over time. Resources are shared through
+/// [`Arc`]s, so evicting one doesn't affect the maps that are using it; It is simply loaded again
+/// the next time it is needed. Paths are [normalized](VirtualPath) like in
+/// [`DefaultResourceCache`].
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{FilesystemResourceReader, Loader, LruCache, ResourceCache};
+///
+/// let mut loader = Loader::with_cache_and_reader(LruCache::new(1), FilesystemResourceReader);
+/// loader.load_tmx_map("assets/tiled_base64_external.tmx")?;
+/// assert!(loader.cache().get_tileset("assets/tilesheet.tsx").is_some());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LruCache {
+    capacity: LruCapacity,
+    tilesets: HashMap<ResourcePathBuf, LruEntry<Tileset>>,
+    templates: HashMap<ResourcePathBuf, LruEntry<Template>>,
+    /// Increased every time a resource is used, to keep track of which was used last.
+    clock: AtomicU64,
+}
+
+#[derive(Debug)]
+struct LruEntry<T> {
+    resource: Arc<T>,
+    size: usize,
+    last_used: AtomicU64,
+}
+
+impl LruCache {
+    /// Creates an empty cache that holds up to `max_entries` tilesets and templates.
+    pub fn new(max_entries: usize) -> Self {
+        Self::with_capacity(LruCapacity::Entries(max_entries))
+    }
+
+    /// Creates an empty cache with the given capacity.
+    pub fn with_capacity(capacity: LruCapacity) -> Self {
+        Self {
+            capacity,
+            tilesets: HashMap::new(),
+            templates: HashMap::new(),
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the capacity of the cache.
+    pub fn capacity(&self) -> LruCapacity {
+        self.capacity
+    }
+
+    /// Returns the amount of tilesets and templates in the cache.
+    pub fn len(&self) -> usize {
+        self.tilesets.len() + self.templates.len()
+    }
+
+    /// Returns whether the cache holds no resources.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the estimated amount of memory used by the resources in the cache, in bytes.
+    pub fn estimated_bytes(&self) -> usize {
+        let tilesets = self.tilesets.values().map(|entry| entry.size);
+        let templates = self.templates.values().map(|entry| entry.size);
+        tilesets.chain(templates).sum()
+    }
+
+    /// Removes all the resources from the cache.
+    pub fn clear(&mut self) {
+        self.tilesets.clear();
+        self.templates.clear();
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn entry<T>(&self, resource: Arc<T>, size: usize) -> LruEntry<T> {
+        LruEntry {
+            resource,
+            size,
+            last_used: AtomicU64::new(self.tick()),
+        }
+    }
+
+    fn is_over_capacity(&self) -> bool {
+        match self.capacity {
+            LruCapacity::Entries(max) => self.len() > max,
+            LruCapacity::Bytes(max) => self.estimated_bytes() > max,
+        }
+    }
+
+    /// Evicts the least recently used resources until the cache fits its capacity again, except
+    /// for the one at `keep` which was just inserted.
+    fn evict(&mut self, keep: &Path) {
+        while self.is_over_capacity() {
+            let oldest_tileset = self
+                .tilesets
+                .iter()
+                .filter(|(path, _)| *path != keep)
+                .map(|(path, entry)| (entry.last_used.load(Ordering::Relaxed), path))
+                .min();
+            let oldest_template = self
+                .templates
+                .iter()
+                .filter(|(path, _)| *path != keep)
+                .map(|(path, entry)| (entry.last_used.load(Ordering::Relaxed), path))
+                .min();
+            match (oldest_tileset, oldest_template) {
+                (Some(tileset), Some(template)) if template < tileset => {
+                    let path = template.1.clone();
+                    self.templates.remove(&path);
+                }
+                (Some((_, path)), _) => {
+                    let path = path.clone();
+                    self.tilesets.remove(&path);
+                }
+                (None, Some((_, path))) => {
+                    let path = path.clone();
+                    self.templates.remove(&path);
+                }
+                (None, None) => break,
+            }
+        }
+    }
+}
+
+impl ResourceCache for LruCache {
+    fn get_tileset(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Tileset>> {
+        self.tilesets.get(&key(path)).map(|entry| {
+            entry.last_used.store(self.tick(), Ordering::Relaxed);
+            entry.resource.clone()
+        })
+    }
+
+    fn insert_tileset(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Tileset>) {
+        let path = key(path);
+        let entry = self.entry(tileset.clone(), estimate_tileset_size(&tileset));
+        self.tilesets.insert(path.clone(), entry);
+        self.evict(&path);
+    }
+
+    fn get_template(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Template>> {
+        self.templates.get(&key(path)).map(|entry| {
+            entry.last_used.store(self.tick(), Ordering::Relaxed);
+            entry.resource.clone()
+        })
+    }
+
+    fn insert_template(&mut self, path: impl AsRef<ResourcePath>, template: Arc<Template>) {
+        let path = key(path);
+        let size = estimate_template_size(&template);
+        let entry = self.entry(template, size);
+        self.templates.insert(path.clone(), entry);
+        self.evict(&path);
+    }
+
+    fn invalidate(&mut self, path: impl AsRef<ResourcePath>) {
+        let path = key(path);
+        self.tilesets.remove(&path);
+        self.templates.remove(&path);
+    }
+
+    fn clear(&mut self) {
+        LruCache::clear(self);
+    }
+}
+
+/// Returns the key a resource is cached with, which is its [normalized](VirtualPath) path.
+fn key(path: impl AsRef<ResourcePath>) -> ResourcePathBuf {
+    VirtualPath::new(path).into()
+}
+
+/// Estimates the memory used by a tileset, including the heap data it owns: Its strings,
+/// properties, images, tiles along with their collision shapes and animations, and Wang sets.
+///
+/// Property names are shared between the properties loaded together, but are counted for every
+/// property using them.
+fn estimate_tileset_size(tileset: &Tileset) -> usize {
+    std::mem::size_of::<Tileset>() + tileset_heap_size(tileset)
+}
+
+/// Estimates the memory used by a template, including its object and the tileset it uses, which
+/// it keeps alive even once the tileset is evicted.
+fn estimate_template_size(template: &Template) -> usize {
+    std::mem::size_of::<Template>()
+        + template.tileset.as_deref().map_or(0, estimate_tileset_size)
+        + template
+            .tileset_source
+            .as_ref()
+            .map_or(0, PathBuf::capacity)
+        + object_heap_size(&template.object)
+}
+
+fn tileset_heap_size(tileset: &Tileset) -> usize {
+    let tiles: usize = tileset
+        .tiles()
+        .map(|(_, tile)| std::mem::size_of::<(TileId, TileData)>() + tile_heap_size(&tile))
+        .sum();
+    let wang_sets: usize = tileset
+        .wang_sets
+        .iter()
+        .map(|set| std::mem::size_of::<WangSet>() + wang_set_heap_size(set))
+        .sum();
+    tileset.name.capacity()
+        + string_heap_size(&tileset.user_type)
+        + tileset.image.as_ref().map_or(0, image_heap_size)
+        + tiles
+        + wang_sets
+        + properties_heap_size(&tileset.properties)
+        + tileset.extensions.heap_size()
+}
+
+fn tile_heap_size(tile: &TileData) -> usize {
+    let collision = tile.collision.as_ref().map_or(0, |collision| {
+        collision
+            .object_data()
+            .iter()
+            .map(|object| std::mem::size_of::<ObjectData>() + object_heap_size(object))
+            .sum()
+    });
+    let animation = tile
+        .animation
+        .as_ref()
+        .map_or(0, |frames| frames.capacity() * std::mem::size_of::<Frame>());
+    tile.image.as_ref().map_or(0, image_heap_size)
+        + properties_heap_size(&tile.properties)
+        + collision
+        + animation
+        + string_heap_size(&tile.user_type)
+        + tile.extensions.heap_size()
+}
+
+fn wang_set_heap_size(set: &WangSet) -> usize {
+    let colors: usize = set
+        .wang_colors
+        .iter()
+        .map(|color| {
+            std::mem::size_of::<WangColor>()
+                + color.name.capacity()
+                + properties_heap_size(&color.properties)
+        })
+        .sum();
+    set.name.capacity()
+        + colors
+        + set.wang_tiles.capacity() * std::mem::size_of::<(TileId, WangTile)>()
+        + properties_heap_size(&set.properties)
+}
+
+fn object_heap_size(object: &ObjectData) -> usize {
+    let shape = match &object.shape {
+        ObjectShape::Polyline { points } | ObjectShape::Polygon { points } => {
+            points.capacity() * std::mem::size_of::<(f32, f32)>()
+        }
+        ObjectShape::Text {
+            text, font_family, ..
+        } => text.capacity() + font_family.capacity(),
+        _ => 0,
+    };
+    object.name.capacity()
+        + string_heap_size(&object.user_type)
+        + shape
+        + properties_heap_size(&object.properties)
+        + object.extensions.heap_size()
+}
+
+fn image_heap_size(image: &Image) -> usize {
+    image.source.capacity()
+}
+
+fn properties_heap_size(properties: &Properties) -> usize {
+    properties.capacity() * std::mem::size_of::<(Arc<str>, PropertyValue)>()
+        + properties
+            .iter()
+            .map(|(name, value)| name.len() + property_heap_size(value))
+            .sum::<usize>()
+}
+
+fn property_heap_size(value: &PropertyValue) -> usize {
+    match value {
+        PropertyValue::StringValue(value) | PropertyValue::FileValue(value) => value.capacity(),
+        PropertyValue::ClassValue {
+            property_type,
+            members,
+        } => property_type.capacity() + properties_heap_size(members),
+        PropertyValue::EnumValue {
+            type_name,
+            value,
+            flags,
+        } => {
+            type_name.capacity()
+                + value.capacity()
+                + flags.capacity() * std::mem::size_of::<String>()
+                + flags.iter().map(String::capacity).sum::<usize>()
+        }
+        _ => 0,
+    }
+}
+
+fn string_heap_size(string: &Option<String>) -> usize {
+    string.as_ref().map_or(0, String::capacity)
+}
+
+/// A cache that can be shared between loaders, including loaders used on different threads.
+///
+/// Cloning a [`SharedCache`] gives another handle to the same resources, so a resource loaded
+/// through any of the handles is reused by all the others.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use std::sync::Arc;
+///
+/// use tiled::{FilesystemResourceReader, Loader, SharedCache};
+///
+/// let cache = SharedCache::new();
+/// let map = Loader::with_cache_and_reader(cache.clone(), FilesystemResourceReader)
+///     .load_tmx_map("assets/tiled_base64_external.tmx")?;
+/// // The tileset loaded above is reused by the other thread.
+/// let other_map = std::thread::spawn(move || {
+///     Loader::with_cache_and_reader(cache, FilesystemResourceReader)
+///         .load_tmx_map("assets/tiled_base64_external.tmx")
+/// })
+/// .join()
+/// .unwrap()?;
+/// assert!(Arc::ptr_eq(&map.tilesets()[0], &other_map.tilesets()[0]));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SharedCache {
+    inner: Arc<RwLock<DefaultResourceCache>>,
+}
+
+impl SharedCache {
+    /// Creates an empty [`SharedCache`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, DefaultResourceCache> {
+        // The cache is never left in an inconsistent state, so a panic elsewhere doesn't matter.
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, DefaultResourceCache> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl ResourceCache for SharedCache {
+    fn get_tileset(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Tileset>> {
+        self.read().get_tileset(path)
+    }
+
+    fn insert_tileset(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Tileset>) {
+        self.write().insert_tileset(path, tileset);
+    }
+
+    fn get_template(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Template>> {
+        self.read().get_template(path)
+    }
+
+    fn insert_template(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Template>) {
+        self.write().insert_template(path, tileset);
+    }
+
+    fn invalidate(&mut self, path: impl AsRef<ResourcePath>) {
+        self.write().invalidate(path);
+    }
+
+    fn clear(&mut self) {
+        self.write().clear();
     }
 }
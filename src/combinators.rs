@@ -0,0 +1,165 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::ResourceReader;
+
+/// A [`ResourceReader`] that reads files from a primary reader, falling back to another one for
+/// the files the primary reader fails to read. Created by [`ResourceReader::or_else()`].
+///
+/// This allows layering readers on top of each other, such as a directory of mods over the base
+/// assets of a game.
+#[derive(Debug, Clone)]
+pub struct FallbackReader<A, B> {
+    primary: A,
+    fallback: B,
+}
+
+impl<A, B> FallbackReader<A, B> {
+    pub(crate) fn new(primary: A, fallback: B) -> Self {
+        Self { primary, fallback }
+    }
+
+    /// Consumes the reader and returns the primary and fallback readers.
+    pub fn into_inner(self) -> (A, B) {
+        (self.primary, self.fallback)
+    }
+}
+
+/// A resource read by a [`FallbackReader`].
+#[derive(Debug)]
+pub enum FallbackResource<A, B> {
+    /// The resource was read by the primary reader.
+    Primary(A),
+    /// The resource was read by the fallback reader.
+    Fallback(B),
+}
+
+impl<A: Read, B: Read> Read for FallbackResource<A, B> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            FallbackResource::Primary(resource) => resource.read(buf),
+            FallbackResource::Fallback(resource) => resource.read(buf),
+        }
+    }
+}
+
+impl<A: ResourceReader, B: ResourceReader> ResourceReader for FallbackReader<A, B> {
+    type Resource = FallbackResource<A::Resource, B::Resource>;
+    /// Only the error of the fallback reader is reported, since the primary reader failing is
+    /// expected.
+    type Error = B::Error;
+
+    fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+        match self.primary.read_from(path) {
+            Ok(resource) => Ok(FallbackResource::Primary(resource)),
+            Err(_) => self
+                .fallback
+                .read_from(path)
+                .map(FallbackResource::Fallback),
+        }
+    }
+
+    /// Lists the files of both readers, without duplicates. Failing to list the files of the
+    /// primary reader is ignored.
+    fn list_files(&mut self, directory: &Path) -> std::result::Result<Vec<PathBuf>, Self::Error> {
+        let mut files = self.primary.list_files(directory).unwrap_or_default();
+        for file in self.fallback.list_files(directory)? {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// A [`ResourceReader`] that rewrites the beginning of paths before passing them to another
+/// reader.
+///
+/// When several prefixes match a path, the longest one is used. Paths that match none are passed
+/// through unchanged.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{DefaultResourceCache, FilesystemResourceReader, Loader, PrefixMappedReader};
+///
+/// let reader = PrefixMappedReader::new(FilesystemResourceReader).with_mapping("game", "assets");
+/// let mut loader = Loader::with_cache_and_reader(DefaultResourceCache::new(), reader);
+/// let map = loader.load_tmx_map("game/tiled_base64_external.tmx")?;
+/// assert_eq!(map.tilesets()[0].name, "tilesheet");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PrefixMappedReader<R> {
+    inner: R,
+    mappings: Vec<(PathBuf, PathBuf)>,
+}
+
+impl<R: ResourceReader> PrefixMappedReader<R> {
+    /// Creates a reader passing paths to `inner`, with no mappings.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            mappings: Vec::new(),
+        }
+    }
+
+    /// Makes paths starting with `from` start with `to` instead, replacing any previous mapping
+    /// of `from`.
+    pub fn add_mapping(&mut self, from: impl Into<PathBuf>, to: impl Into<PathBuf>) {
+        let from = from.into();
+        let to = to.into();
+        match self.mappings.iter_mut().find(|(prefix, _)| *prefix == from) {
+            Some(mapping) => mapping.1 = to,
+            None => self.mappings.push((from, to)),
+        }
+    }
+
+    /// Adds a mapping and returns the reader. See [`PrefixMappedReader::add_mapping()`].
+    pub fn with_mapping(mut self, from: impl Into<PathBuf>, to: impl Into<PathBuf>) -> Self {
+        self.add_mapping(from, to);
+        self
+    }
+
+    /// Returns the path `path` is read from.
+    pub fn map_path(&self, path: &Path) -> PathBuf {
+        self.mappings
+            .iter()
+            .filter_map(|(from, to)| Some((from, to, path.strip_prefix(from).ok()?)))
+            .max_by_key(|(from, _, _)| from.components().count())
+            .map_or_else(|| path.to_owned(), |(_, to, rest)| to.join(rest))
+    }
+
+    /// Consumes the reader and returns the reader it passes paths to.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: ResourceReader> ResourceReader for PrefixMappedReader<R> {
+    type Resource = R::Resource;
+    type Error = R::Error;
+
+    fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+        let path = self.map_path(path);
+        self.inner.read_from(&path)
+    }
+
+    /// Lists the files of the directory `directory` is mapped to, returning their paths as if
+    /// they were in `directory`.
+    fn list_files(&mut self, directory: &Path) -> std::result::Result<Vec<PathBuf>, Self::Error> {
+        let mapped = self.map_path(directory);
+        Ok(self
+            .inner
+            .list_files(&mapped)?
+            .into_iter()
+            .map(|file| match file.strip_prefix(&mapped) {
+                Ok(name) => directory.join(name),
+                Err(_) => file,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,225 @@
+use std::{fmt, str::FromStr};
+
+use crate::{decoder::DataDecoders, Layer, LayerType, Map, TileLayer};
+
+/// A version number of the TMX format or of Tiled, such as `1.10` or `1.10.2`.
+///
+/// Versions compare by their major, minor and patch numbers, in that order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Version {
+    /// The major version number.
+    pub major: u32,
+    /// The minor version number.
+    pub minor: u32,
+    /// The patch version number, 0 if the version doesn't have one.
+    pub patch: u32,
+}
+
+impl Version {
+    /// The oldest version of the TMX format supported by the crate.
+    pub const OLDEST_FORMAT: Version = Version::new(0, 13, 0);
+    /// The newest version of the TMX format supported by the crate.
+    pub const NEWEST_FORMAT: Version = Version::new(1, 10, 0);
+    /// The newest version of Tiled whose features are supported by the crate, including its patch
+    /// releases.
+    pub const NEWEST_TILED: Version = Version::new(1, 10, 0);
+
+    /// Creates a version from its major, minor and patch numbers.
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if self.patch != 0 {
+            write!(f, ".{}", self.patch)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+/// An error arising from trying to parse a [`Version`] that is not valid.
+pub struct VersionParseError {
+    /// The invalid string found.
+    pub str_found: String,
+}
+
+impl fmt::Display for VersionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to parse version, expected up to three numbers separated by dots but got `{}` instead",
+            self.str_found
+        )
+    }
+}
+
+impl std::error::Error for VersionParseError {}
+
+impl FromStr for Version {
+    type Err = VersionParseError;
+
+    /// Parses a version made of one to three numbers separated by dots, optionally followed by a
+    /// suffix starting with `-`, such as `1.11.0-beta`, which is ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || VersionParseError {
+            str_found: s.to_owned(),
+        };
+        let numbers = s.split('-').next().unwrap_or_default();
+        let mut parts = [0; 3];
+        let mut split = numbers.split('.');
+        for (part, number) in parts.iter_mut().zip(split.by_ref()) {
+            if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(err());
+            }
+            *part = number.parse().map_err(|_| err())?;
+        }
+        if split.next().is_some() {
+            return Err(err());
+        }
+        Ok(Version::new(parts[0], parts[1], parts[2]))
+    }
+}
+
+/// A feature of a [`Map`] or of its tilesets that the crate may not support, found by
+/// [`Loader::check_compatibility()`](crate::Loader::check_compatibility()).
+///
+/// Unlike [`Warning`](crate::Warning)s, which report the elements and attributes that were ignored
+/// while loading a file, these concern what a loaded map says about the files it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompatibilityIssue {
+    /// The map or one of its tilesets was saved in a version of the TMX format older than
+    /// [`Version::OLDEST_FORMAT`] or newer than [`Version::NEWEST_FORMAT`], so some of its content
+    /// may have been read wrong or ignored.
+    UnsupportedFormatVersion {
+        /// The name of the tileset, or `None` for the map itself.
+        tileset: Option<String>,
+        /// The version of the format the file was saved in.
+        version: Version,
+    },
+    /// The map or one of its tilesets was saved by a version of Tiled newer than
+    /// [`Version::NEWEST_TILED`], whose new features may have been ignored. Development snapshots,
+    /// which are versioned by their date, aren't reported.
+    NewerTiledVersion {
+        /// The name of the tileset, or `None` for the map itself.
+        tileset: Option<String>,
+        /// The version of Tiled that saved the file.
+        version: Version,
+    },
+    /// The data of a tile layer is compressed with a method the loader has no decoder for. Loading
+    /// fails on such layers, unless their decoding was
+    /// [deferred](crate::Loader::set_defer_tile_data()) or they were loaded by another loader.
+    UnsupportedCompression {
+        /// The id of the tile layer.
+        layer: u32,
+        /// The name of the compression method.
+        compression: String,
+    },
+}
+
+impl fmt::Display for CompatibilityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = |tileset: &Option<String>| match tileset {
+            Some(tileset) => format!("tileset '{tileset}'"),
+            None => "the map".to_owned(),
+        };
+        match self {
+            CompatibilityIssue::UnsupportedFormatVersion { tileset, version } => write!(
+                f,
+                "{} was saved in version {version} of the TMX format, which is not supported",
+                file(tileset)
+            ),
+            CompatibilityIssue::NewerTiledVersion { tileset, version } => write!(
+                f,
+                "{} was saved by Tiled {version}, which is newer than the versions supported",
+                file(tileset)
+            ),
+            CompatibilityIssue::UnsupportedCompression { layer, compression } => write!(
+                f,
+                "the data of layer {layer} is compressed with '{compression}', which has no decoder"
+            ),
+        }
+    }
+}
+
+/// Checks a map for the issues described by [`CompatibilityIssue`], given the decoders of the
+/// loader checking it. See [`Loader::check_compatibility()`](crate::Loader::check_compatibility()).
+pub(crate) fn check_map(map: &Map, decoders: &DataDecoders) -> Vec<CompatibilityIssue> {
+    let mut issues = Vec::new();
+    check_versions(Some(map.version()), map.tiled_version, None, &mut issues);
+    // Embedded tilesets share the versions of the map.
+    for tileset in map
+        .tilesets()
+        .iter()
+        .filter(|tileset| tileset.source_path().is_some())
+    {
+        check_versions(
+            tileset.version,
+            tileset.tiled_version,
+            Some(&tileset.name),
+            &mut issues,
+        );
+    }
+    for layer in map.layers() {
+        check_layer(layer, decoders, &mut issues);
+    }
+    issues
+}
+
+fn check_layer(layer: Layer, decoders: &DataDecoders, issues: &mut Vec<CompatibilityIssue>) {
+    let compression = match layer.layer_type() {
+        LayerType::Tiles(TileLayer::Finite(tiles)) => tiles.data.compression(),
+        LayerType::Tiles(TileLayer::Infinite(tiles)) => tiles.data.compression(),
+        LayerType::Group(group) => {
+            for layer in group.layers() {
+                check_layer(layer, decoders, issues);
+            }
+            None
+        }
+        LayerType::Objects(_) | LayerType::Image(_) => None,
+    };
+    if let Some(compression) = compression {
+        if decoders.get(compression).is_none() {
+            issues.push(CompatibilityIssue::UnsupportedCompression {
+                layer: layer.id(),
+                compression: compression.to_owned(),
+            });
+        }
+    }
+}
+
+fn check_versions(
+    version: Option<Version>,
+    tiled_version: Option<Version>,
+    tileset: Option<&String>,
+    issues: &mut Vec<CompatibilityIssue>,
+) {
+    if let Some(version) = version {
+        if version < Version::OLDEST_FORMAT || version > Version::NEWEST_FORMAT {
+            issues.push(CompatibilityIssue::UnsupportedFormatVersion {
+                tileset: tileset.cloned(),
+                version,
+            });
+        }
+    }
+    if let Some(version) = tiled_version {
+        let newest = Version::NEWEST_TILED;
+        // Development snapshots of Tiled are versioned by their date, such as 2020.05.20.
+        let snapshot = version.major >= 2000;
+        if !snapshot && (version.major, version.minor) > (newest.major, newest.minor) {
+            issues.push(CompatibilityIssue::NewerTiledVersion {
+                tileset: tileset.cloned(),
+                version,
+            });
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Coordinate conversions for hexagonal maps.
+//!
+//! Hexagonal maps lay out their tiles in rows (or columns) of which every other one is shifted by
+//! half a tile along the [stagger axis](crate::StaggerAxis). The [stagger index](crate::StaggerIndex)
+//! determines whether the odd or the even ones are shifted, and the
+//! [side length](crate::Map::hex_side_length) is the length of the hexagon edges perpendicular to
+//! the stagger axis.
+//!
+//! Unlike the conversions for other orientations, these work with whole tiles only, since
+//! hexagons don't map to a continuous tile coordinate space.
+//!
+//! ## Example
+//! ```
+//! use tiled::{coords::hex::HexLayout, MapBuilder, Orientation, StaggerAxis, StaggerIndex};
+//!
+//! let map = MapBuilder::new(Orientation::Hexagonal, 10, 10, 28, 32)
+//!     .hex_side_length(16)
+//!     .stagger_axis(StaggerAxis::Y)
+//!     .stagger_index(StaggerIndex::Odd)
+//!     .build()
+//!     .unwrap();
+//! let layout = HexLayout::from_map(&map);
+//!
+//! // Odd rows are shifted right by half a tile.
+//! assert_eq!(layout.tile_to_pixel(2, 1), (70.0, 24.0));
+//! // The center of a tile is within it.
+//! assert_eq!(layout.pixel_to_tile(70.0 + 14.0, 24.0 + 16.0), (2, 1));
+//! ```
+
+use crate::{Map, StaggerAxis, StaggerIndex};
+
+/// The parameters of a hexagonal map that are needed to convert between tile and pixel
+/// coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexLayout {
+    /// The width of the tiles, in pixels.
+    pub tile_width: u32,
+    /// The height of the tiles, in pixels.
+    pub tile_height: u32,
+    /// The length of the hexagon edges perpendicular to the stagger axis, in pixels.
+    pub side_length: u32,
+    /// The axis along which every other row or column is shifted.
+    pub stagger_axis: StaggerAxis,
+    /// Whether the odd or the even rows or columns are shifted.
+    pub stagger_index: StaggerIndex,
+}
+
+/// The intermediate values Tiled's hexagonal (and staggered) renderer derives from a
+/// [`HexLayout`].
+pub(super) struct RenderParams {
+    pub tile_width: i32,
+    pub tile_height: i32,
+    pub side_length_x: i32,
+    pub side_length_y: i32,
+    pub side_offset_x: i32,
+    pub side_offset_y: i32,
+    pub column_width: i32,
+    pub row_height: i32,
+    pub stagger_x: bool,
+    pub stagger_even: bool,
+}
+
+impl RenderParams {
+    pub(super) fn new(layout: &HexLayout) -> Self {
+        // Tiled ignores the lowest bit of the tile size so that tiles can be split in halves.
+        let tile_width = layout.tile_width as i32 & !1;
+        let tile_height = layout.tile_height as i32 & !1;
+        let stagger_x = layout.stagger_axis == StaggerAxis::X;
+        let (side_length_x, side_length_y) = if stagger_x {
+            (layout.side_length as i32, 0)
+        } else {
+            (0, layout.side_length as i32)
+        };
+        let side_offset_x = (tile_width - side_length_x) / 2;
+        let side_offset_y = (tile_height - side_length_y) / 2;
+
+        Self {
+            tile_width,
+            tile_height,
+            side_length_x,
+            side_length_y,
+            side_offset_x,
+            side_offset_y,
+            column_width: side_offset_x + side_length_x,
+            row_height: side_offset_y + side_length_y,
+            stagger_x,
+            stagger_even: layout.stagger_index == StaggerIndex::Even,
+        }
+    }
+
+    /// Whether the row or column with the index given along the stagger axis is shifted.
+    pub(super) fn is_staggered(&self, index: i32) -> bool {
+        (index & 1 != 0) ^ self.stagger_even
+    }
+}
+
+impl HexLayout {
+    /// Gets the layout of a map, presumably a hexagonal one.
+    pub fn from_map(map: &Map) -> Self {
+        Self {
+            tile_width: map.tile_width,
+            tile_height: map.tile_height,
+            side_length: map.hex_side_length,
+            stagger_axis: map.stagger_axis,
+            stagger_index: map.stagger_index,
+        }
+    }
+
+    /// Returns the pixel position of the top-left corner of the bounding box of the tile at the
+    /// position given.
+    pub fn tile_to_pixel(&self, x: i32, y: i32) -> (f32, f32) {
+        let p = RenderParams::new(self);
+        let (pixel_x, pixel_y) = if p.stagger_x {
+            let mut pixel_y = y * (p.tile_height + p.side_length_y);
+            if p.is_staggered(x) {
+                pixel_y += p.row_height;
+            }
+            (x * p.column_width, pixel_y)
+        } else {
+            let mut pixel_x = x * (p.tile_width + p.side_length_x);
+            if p.is_staggered(y) {
+                pixel_x += p.column_width;
+            }
+            (pixel_x, y * p.row_height)
+        };
+        (pixel_x as f32, pixel_y as f32)
+    }
+
+    /// Returns the position of the tile containing the pixel position given.
+    pub fn pixel_to_tile(&self, x: f32, y: f32) -> (i32, i32) {
+        let p = RenderParams::new(self);
+        let (mut x, mut y) = (x, y);
+        if p.stagger_x {
+            x -= if p.stagger_even {
+                p.tile_width
+            } else {
+                p.side_offset_x
+            } as f32;
+        } else {
+            y -= if p.stagger_even {
+                p.tile_height
+            } else {
+                p.side_offset_y
+            } as f32;
+        }
+
+        // Start with the coordinates of a grid-aligned tile.
+        let grid_width = (p.column_width * 2) as f32;
+        let grid_height = (p.row_height * 2) as f32;
+        let mut reference = (
+            (x / grid_width).floor() as i32,
+            (y / grid_height).floor() as i32,
+        );
+
+        // The position relative to the base square of the grid-aligned tile.
+        let rel = (
+            x - reference.0 as f32 * grid_width,
+            y - reference.1 as f32 * grid_height,
+        );
+
+        // Adjust the reference point to the correct tile coordinates.
+        let stagger_axis_index = if p.stagger_x {
+            &mut reference.0
+        } else {
+            &mut reference.1
+        };
+        *stagger_axis_index *= 2;
+        if p.stagger_even {
+            *stagger_axis_index += 1;
+        }
+
+        // Determine the nearest hexagon tile by the distance to its center.
+        let (centers, offsets) = if p.stagger_x {
+            let left = p.side_length_x / 2;
+            let center_x = left + p.column_width;
+            let center_y = p.tile_height / 2;
+            (
+                [
+                    (left, center_y),
+                    (center_x, center_y - p.row_height),
+                    (center_x, center_y + p.row_height),
+                    (center_x + p.column_width, center_y),
+                ],
+                [(0, 0), (1, -1), (1, 0), (2, 0)],
+            )
+        } else {
+            let top = p.side_length_y / 2;
+            let center_x = p.tile_width / 2;
+            let center_y = top + p.row_height;
+            (
+                [
+                    (center_x, top),
+                    (center_x - p.column_width, center_y),
+                    (center_x + p.column_width, center_y),
+                    (center_x, center_y + p.row_height),
+                ],
+                [(0, 0), (-1, 1), (0, 1), (0, 2)],
+            )
+        };
+        let mut nearest = 0;
+        let mut min_distance = f32::MAX;
+        for (i, center) in centers.iter().enumerate() {
+            let dx = center.0 as f32 - rel.0;
+            let dy = center.1 as f32 - rel.1;
+            let distance = dx * dx + dy * dy;
+            if distance < min_distance {
+                min_distance = distance;
+                nearest = i;
+            }
+        }
+
+        (
+            reference.0 + offsets[nearest].0,
+            reference.1 + offsets[nearest].1,
+        )
+    }
+}
@@ -0,0 +1,35 @@
+//! Coordinate conversions for isometric maps.
+//!
+//! The screen position of a tile position is the top corner of that tile's diamond. Since tiles
+//! with negative screen X coordinates would otherwise be cut off, Tiled shifts the whole map to the
+//! right by half its height in tiles, which is why these functions need the map's height.
+
+/// Converts a position in tiles into a position on the screen (in pixels), for an isometric map
+/// with the height in tiles and tile size given.
+pub fn iso_to_screen(
+    map_height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    x: f32,
+    y: f32,
+) -> (f32, f32) {
+    let half_width = tile_width as f32 / 2.0;
+    let half_height = tile_height as f32 / 2.0;
+    let origin_x = map_height as f32 * half_width;
+    ((x - y) * half_width + origin_x, (x + y) * half_height)
+}
+
+/// Converts a position on the screen (in pixels) into a position in tiles, for an isometric map
+/// with the height in tiles and tile size given. The inverse of [`iso_to_screen`].
+pub fn screen_to_iso(
+    map_height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    x: f32,
+    y: f32,
+) -> (f32, f32) {
+    let x = x - map_height as f32 * tile_width as f32 / 2.0;
+    let tile_x = x / tile_width as f32;
+    let tile_y = y / tile_height as f32;
+    (tile_y + tile_x, tile_y - tile_x)
+}
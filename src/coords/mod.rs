@@ -0,0 +1,15 @@
+//! Conversions between tile and pixel coordinates, following the rules of Tiled's renderers.
+//!
+//! Pixel coordinates are relative to the top-left corner of the map as Tiled renders it, without
+//! taking layer offsets into account. For [orthogonal](ortho) and [isometric](iso) maps, tile
+//! coordinates are fractional: The integer part identifies a tile and the fractional part a
+//! position within it. Hexagonal and staggered maps, handled by [`hex`] and [`staggered`], work
+//! with whole tiles only.
+//!
+//! [`Map::project`](crate::Map::project) and [`Map::unproject`](crate::Map::unproject) choose the
+//! right conversion for the orientation of a map.
+
+pub mod hex;
+pub mod iso;
+pub mod ortho;
+pub mod staggered;
@@ -0,0 +1,15 @@
+//! Coordinate conversions for orthogonal maps.
+
+/// Converts a position in tiles into a position in pixels, for an orthogonal map with the tile
+/// size given.
+///
+/// The pixel position of a whole tile position is the top-left corner of that tile.
+pub fn tile_to_pixel(tile_width: u32, tile_height: u32, x: f32, y: f32) -> (f32, f32) {
+    (x * tile_width as f32, y * tile_height as f32)
+}
+
+/// Converts a position in pixels into a position in tiles, for an orthogonal map with the tile
+/// size given. The inverse of [`tile_to_pixel`].
+pub fn pixel_to_tile(tile_width: u32, tile_height: u32, x: f32, y: f32) -> (f32, f32) {
+    (x / tile_width as f32, y / tile_height as f32)
+}
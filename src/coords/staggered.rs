@@ -0,0 +1,150 @@
+//! Coordinate conversions for staggered maps.
+//!
+//! Staggered maps are isometric maps whose tiles are laid out in rows (or columns) of which every
+//! other one is shifted by half a tile along the [stagger axis](crate::StaggerAxis), the
+//! [stagger index](crate::StaggerIndex) determining whether the odd or the even ones are. They
+//! are rendered like [hexagonal](super::hex) maps whose hexagons have no side length, but tiles
+//! are diamonds instead of hexagons.
+//!
+//! Like the conversions for hexagonal maps, these work with whole tiles only.
+//!
+//! ## Example
+//! ```
+//! use tiled::{coords::staggered::StaggeredLayout, MapBuilder, Orientation, StaggerAxis};
+//!
+//! let map = MapBuilder::new(Orientation::Staggered, 10, 10, 64, 32)
+//!     .stagger_axis(StaggerAxis::Y)
+//!     .build()
+//!     .unwrap();
+//! let layout = StaggeredLayout::from_map(&map);
+//!
+//! // Odd rows are shifted right by half a tile, and rows overlap by half their height.
+//! assert_eq!(layout.tile_to_pixel(1, 1), (96.0, 16.0));
+//! assert_eq!(layout.pixel_to_tile(96.0 + 32.0, 16.0 + 16.0), (1, 1));
+//! ```
+
+use super::hex::{HexLayout, RenderParams};
+use crate::{Map, StaggerAxis, StaggerIndex};
+
+/// The parameters of a staggered map that are needed to convert between tile and pixel
+/// coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaggeredLayout {
+    /// The width of the tiles, in pixels.
+    pub tile_width: u32,
+    /// The height of the tiles, in pixels.
+    pub tile_height: u32,
+    /// The axis along which every other row or column is shifted.
+    pub stagger_axis: StaggerAxis,
+    /// Whether the odd or the even rows or columns are shifted.
+    pub stagger_index: StaggerIndex,
+}
+
+impl StaggeredLayout {
+    /// Gets the layout of a map, presumably a staggered one.
+    pub fn from_map(map: &Map) -> Self {
+        Self {
+            tile_width: map.tile_width,
+            tile_height: map.tile_height,
+            stagger_axis: map.stagger_axis,
+            stagger_index: map.stagger_index,
+        }
+    }
+
+    fn as_hex(&self) -> HexLayout {
+        HexLayout {
+            tile_width: self.tile_width,
+            tile_height: self.tile_height,
+            side_length: 0,
+            stagger_axis: self.stagger_axis,
+            stagger_index: self.stagger_index,
+        }
+    }
+
+    /// Returns the pixel position of the top-left corner of the bounding box of the tile at the
+    /// position given.
+    pub fn tile_to_pixel(&self, x: i32, y: i32) -> (f32, f32) {
+        self.as_hex().tile_to_pixel(x, y)
+    }
+
+    /// Returns the position of the tile containing the pixel position given.
+    pub fn pixel_to_tile(&self, x: f32, y: f32) -> (i32, i32) {
+        let p = RenderParams::new(&self.as_hex());
+        let (mut x, mut y) = (x, y);
+        if p.stagger_x {
+            if p.stagger_even {
+                x -= p.side_offset_x as f32;
+            }
+        } else if p.stagger_even {
+            y -= p.side_offset_y as f32;
+        }
+
+        // Start with the coordinates of a grid-aligned tile.
+        let mut reference = (
+            (x / p.tile_width as f32).floor() as i32,
+            (y / p.tile_height as f32).floor() as i32,
+        );
+
+        // The position relative to the base square of the grid-aligned tile.
+        let rel = (
+            x - (reference.0 * p.tile_width) as f32,
+            y - (reference.1 * p.tile_height) as f32,
+        );
+
+        // Adjust the reference point to the correct tile coordinates.
+        let stagger_axis_index = if p.stagger_x {
+            &mut reference.0
+        } else {
+            &mut reference.1
+        };
+        *stagger_axis_index *= 2;
+        if p.stagger_even {
+            *stagger_axis_index += 1;
+        }
+
+        // Check whether the position is in any of the corners, which belong to neighboring tiles.
+        let y_pos = rel.0 * (p.tile_height as f32 / p.tile_width as f32);
+        let side_offset_y = p.side_offset_y as f32;
+        let (x, y) = reference;
+        // Whether the reference tile is shifted, which determines the position of its neighbors.
+        let shifted = p.is_staggered(if p.stagger_x { x } else { y });
+        let neighbor = |dx_shifted: i32, dy_shifted: i32, dx: i32, dy: i32| {
+            if shifted {
+                (x + dx_shifted, y + dy_shifted)
+            } else {
+                (x + dx, y + dy)
+            }
+        };
+        if side_offset_y - y_pos > rel.1 {
+            // Top left
+            if p.stagger_x {
+                neighbor(-1, 0, -1, -1)
+            } else {
+                neighbor(0, -1, -1, -1)
+            }
+        } else if -side_offset_y + y_pos > rel.1 {
+            // Top right
+            if p.stagger_x {
+                neighbor(1, 0, 1, -1)
+            } else {
+                neighbor(1, -1, 0, -1)
+            }
+        } else if side_offset_y + y_pos < rel.1 {
+            // Bottom left
+            if p.stagger_x {
+                neighbor(-1, 1, -1, 0)
+            } else {
+                neighbor(0, 1, -1, 1)
+            }
+        } else if side_offset_y * 3.0 - y_pos < rel.1 {
+            // Bottom right
+            if p.stagger_x {
+                neighbor(1, 1, 1, 0)
+            } else {
+                neighbor(1, 1, 0, 1)
+            }
+        } else {
+            reference
+        }
+    }
+}
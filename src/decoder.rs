@@ -0,0 +1,180 @@
+use std::{collections::HashMap, io::Read, sync::Arc};
+
+use crate::{Error, LoadLimit, Result};
+
+/// A compression algorithm that the data of tile layers can be compressed with.
+///
+/// Decoders are registered on a [`Loader`](crate::Loader) under the name used by the
+/// `compression` attribute of the data, using
+/// [`Loader::register_decoder()`](crate::Loader::register_decoder()). The loader comes with
+/// decoders for `zlib`, `gzip` and, if the `zstd` feature is enabled, `zstd`; Registering a
+/// decoder under one of these names replaces the built-in one.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use std::io::Read;
+///
+/// use tiled::{DataDecoder, Loader};
+///
+/// /// Decompresses zlib data using a different implementation than the default one.
+/// struct MyZlibDecoder;
+///
+/// impl DataDecoder for MyZlibDecoder {
+///     fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+///         let mut decompressed = Vec::new();
+///         libflate::zlib::Decoder::new(data)?.read_to_end(&mut decompressed)?;
+///         Ok(decompressed)
+///     }
+/// }
+///
+/// let mut loader = Loader::new();
+/// loader.register_decoder("zlib", MyZlibDecoder);
+/// let map = loader.load_tmx_map("assets/tiled_base64_zlib.tmx")?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait DataDecoder: Send + Sync {
+    /// Decompresses the given data, which has already been decoded from base64.
+    fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>>;
+
+    /// Decompresses the given data like [`DataDecoder::decompress()`], but may stop once more
+    /// than `limit` bytes have been decompressed, returning what has been decompressed so far.
+    ///
+    /// Used instead of [`DataDecoder::decompress()`] when the loader has a
+    /// [`max_decompressed_bytes`](crate::LoadLimits::max_decompressed_bytes) limit, which is
+    /// checked against the size of the data returned. By default, all of the data is
+    /// decompressed before being checked; Streaming decoders should stop early so that data
+    /// decompressing to huge sizes can't exhaust memory.
+    fn decompress_limited(&self, data: &[u8], limit: usize) -> std::io::Result<Vec<u8>> {
+        let _ = limit;
+        self.decompress(data)
+    }
+}
+
+/// Reads all of the data of `reader`, or only up to one byte past `limit` if there is one.
+fn read_all(mut reader: impl Read, limit: Option<usize>) -> std::io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    match limit {
+        Some(limit) => (&mut reader)
+            .take((limit as u64).saturating_add(1))
+            .read_to_end(&mut data)?,
+        None => reader.read_to_end(&mut data)?,
+    };
+    Ok(data)
+}
+
+struct ZlibDecoder;
+
+impl DataDecoder for ZlibDecoder {
+    fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        read_all(libflate::zlib::Decoder::new(data)?, None)
+    }
+
+    fn decompress_limited(&self, data: &[u8], limit: usize) -> std::io::Result<Vec<u8>> {
+        read_all(libflate::zlib::Decoder::new(data)?, Some(limit))
+    }
+}
+
+struct GzipDecoder;
+
+impl DataDecoder for GzipDecoder {
+    fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        read_all(libflate::gzip::Decoder::new(data)?, None)
+    }
+
+    fn decompress_limited(&self, data: &[u8], limit: usize) -> std::io::Result<Vec<u8>> {
+        read_all(libflate::gzip::Decoder::new(data)?, Some(limit))
+    }
+}
+
+#[cfg(feature = "zstd")]
+struct ZstdDecoder;
+
+#[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+impl DataDecoder for ZstdDecoder {
+    fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        read_all(zstd::stream::read::Decoder::with_buffer(data)?, None)
+    }
+
+    fn decompress_limited(&self, data: &[u8], limit: usize) -> std::io::Result<Vec<u8>> {
+        read_all(zstd::stream::read::Decoder::with_buffer(data)?, Some(limit))
+    }
+}
+
+/// The zstd library can't be built for WASM without a C toolchain targeting it, so a decoder
+/// written in Rust is used there instead.
+#[cfg(all(feature = "zstd", target_arch = "wasm32"))]
+impl DataDecoder for ZstdDecoder {
+    fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        read_all(ruzstd_decoder(data)?, None)
+    }
+
+    fn decompress_limited(&self, data: &[u8], limit: usize) -> std::io::Result<Vec<u8>> {
+        read_all(ruzstd_decoder(data)?, Some(limit))
+    }
+}
+
+#[cfg(all(feature = "zstd", target_arch = "wasm32"))]
+fn ruzstd_decoder(data: &[u8]) -> std::io::Result<impl Read + '_> {
+    ruzstd::StreamingDecoder::new(data)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+/// The decoders known to a loader, indexed by the name of their compression.
+#[derive(Clone)]
+pub(crate) struct DataDecoders {
+    decoders: HashMap<String, Arc<dyn DataDecoder>>,
+    /// See [`LoadLimits::max_decompressed_bytes`](crate::LoadLimits::max_decompressed_bytes).
+    pub(crate) max_decompressed_bytes: Option<usize>,
+}
+
+impl DataDecoders {
+    /// Decompresses data with the decoder given, enforcing the decompressed size limit, if any.
+    pub(crate) fn decompress(&self, decoder: &dyn DataDecoder, data: &[u8]) -> Result<Vec<u8>> {
+        let max = match self.max_decompressed_bytes {
+            Some(max) => max,
+            None => return decoder.decompress(data).map_err(Error::DecompressingError),
+        };
+        let data = decoder
+            .decompress_limited(data, max)
+            .map_err(Error::DecompressingError)?;
+        if data.len() > max {
+            return Err(Error::LimitExceeded {
+                limit: LoadLimit::DecompressedBytes,
+                max: max as u64,
+            });
+        }
+        Ok(data)
+    }
+
+    pub(crate) fn insert(&mut self, compression: String, decoder: Arc<dyn DataDecoder>) {
+        self.decoders.insert(compression, decoder);
+    }
+
+    pub(crate) fn get(&self, compression: &str) -> Option<&dyn DataDecoder> {
+        self.decoders.get(compression).map(Arc::as_ref)
+    }
+}
+
+impl Default for DataDecoders {
+    fn default() -> Self {
+        let mut decoders = Self {
+            decoders: HashMap::new(),
+            max_decompressed_bytes: None,
+        };
+        decoders.insert("zlib".to_owned(), Arc::new(ZlibDecoder));
+        decoders.insert("gzip".to_owned(), Arc::new(GzipDecoder));
+        #[cfg(feature = "zstd")]
+        decoders.insert("zstd".to_owned(), Arc::new(ZstdDecoder));
+        decoders
+    }
+}
+
+impl std::fmt::Debug for DataDecoders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<&String> = self.decoders.keys().collect();
+        names.sort();
+        f.debug_set().entries(names).finish()
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    LayerType, Loader, Map, ResourceCache, ResourcePath, ResourceReader, Result, Template, Tileset,
+};
+
+/// A file a map refers to, along with the external tileset or template that has to be loaded
+/// again if the file changes, if any.
+pub(crate) type Dependency = (PathBuf, Option<PathBuf>);
+
+impl<Cache: ResourceCache, Reader: ResourceReader> Loader<Cache, Reader> {
+    /// Returns the files the map at the path given refers to: Its external tilesets, the templates
+    /// its objects are created from along with their tilesets, and the images of all of these and
+    /// of its image layers. Each file is listed once, in the order it is found, and the map file
+    /// itself isn't included.
+    ///
+    /// The data of the tile layers of the map isn't decoded, which makes this quicker than loading
+    /// the map when only its files are needed, such as to tell which ones a build system has to
+    /// bundle along with it. The paths are the ones the files are read from, relative to the
+    /// working directory if `path` is.
+    ///
+    /// Tilesets and templates used by the map are cached like when loading it. Maps in the JSON
+    /// format are recognized by their extension if the `json` feature is enabled; Otherwise the
+    /// map is parsed as a TMX file.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use std::path::Path;
+    ///
+    /// let files = tiled::Loader::new().scan_dependencies("assets/tiled_base64_external.tmx")?;
+    /// assert_eq!(
+    ///     files,
+    ///     [Path::new("assets/tilesheet.tsx"), Path::new("assets/tilesheet.png")]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn scan_dependencies(&mut self, path: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let defer_tile_data = self.defer_tile_data();
+        self.set_defer_tile_data(true);
+        let (map, dependencies) = self.load_map_with_dependencies(path.as_ref());
+        self.set_defer_tile_data(defer_tile_data);
+        map?;
+        Ok(dependencies.into_iter().map(|(path, _)| path).collect())
+    }
+
+    /// Loads a map, recognizing the JSON format by its extension, and returns the files it refers
+    /// to as listed by [`Loader::scan_dependencies()`]. If the map can't be loaded, the files found
+    /// until then are returned.
+    pub(crate) fn load_map_with_dependencies(
+        &mut self,
+        path: &Path,
+    ) -> (Result<Map>, Vec<Dependency>) {
+        let (map, recorder) = self.load_map_with_cache(path, |cache| RecordingCache {
+            cache,
+            resources: RefCell::new(Vec::new()),
+        });
+
+        let mut dependencies = Vec::new();
+        for resource in recorder.resources.into_inner() {
+            match resource {
+                Resource::Tileset(path, tileset) => {
+                    add(&mut dependencies, &path, Some(&path));
+                    add_images(&mut dependencies, &tileset, Some(&path));
+                }
+                Resource::Template(path, template) => {
+                    // Listed after their tileset, which is loaded first if it isn't cached.
+                    let owner = template.tileset_source.as_deref().unwrap_or(&path);
+                    add(&mut dependencies, owner, Some(owner));
+                    if let Some(tileset) = &template.tileset {
+                        add_images(&mut dependencies, tileset, Some(owner));
+                    }
+                    add(&mut dependencies, &path, Some(&path));
+                }
+            }
+        }
+        if let Ok(map) = &map {
+            for tileset in map.tilesets() {
+                add_images(&mut dependencies, tileset, None);
+            }
+            for layer in map.flattened_layers() {
+                if let LayerType::Image(image) = layer.layer.layer_type() {
+                    if let Some(image) = &image.image {
+                        add(&mut dependencies, &image.source, None);
+                    }
+                }
+            }
+        }
+        (map, dependencies)
+    }
+}
+
+fn add(dependencies: &mut Vec<Dependency>, path: &Path, owner: Option<&Path>) {
+    if !path.as_os_str().is_empty() && !dependencies.iter().any(|(known, _)| known == path) {
+        dependencies.push((path.to_owned(), owner.map(Path::to_owned)));
+    }
+}
+
+fn add_images(dependencies: &mut Vec<Dependency>, tileset: &Tileset, owner: Option<&Path>) {
+    if let Some(image) = &tileset.image {
+        add(dependencies, &image.source, owner);
+    }
+    for (_, tile) in tileset.tiles() {
+        if let Some(image) = &tile.image {
+            add(dependencies, &image.source, owner);
+        }
+    }
+}
+
+enum Resource {
+    Tileset(PathBuf, Arc<Tileset>),
+    Template(PathBuf, Arc<Template>),
+}
+
+/// Wraps the cache of a loader while loading a map, recording the tilesets and templates the map
+/// uses in the order they are needed, whether they were cached already or not.
+struct RecordingCache<'a, C> {
+    cache: &'a mut C,
+    resources: RefCell<Vec<Resource>>,
+}
+
+impl<C: ResourceCache> ResourceCache for RecordingCache<'_, C> {
+    fn get_tileset(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Tileset>> {
+        let tileset = self.cache.get_tileset(&path)?;
+        self.resources
+            .borrow_mut()
+            .push(Resource::Tileset(path.as_ref().to_owned(), tileset.clone()));
+        Some(tileset)
+    }
+
+    fn insert_tileset(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Tileset>) {
+        self.resources
+            .get_mut()
+            .push(Resource::Tileset(path.as_ref().to_owned(), tileset.clone()));
+        self.cache.insert_tileset(path, tileset);
+    }
+
+    fn get_template(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Template>> {
+        let template = self.cache.get_template(&path)?;
+        self.resources.borrow_mut().push(Resource::Template(
+            path.as_ref().to_owned(),
+            template.clone(),
+        ));
+        Some(template)
+    }
+
+    fn insert_template(&mut self, path: impl AsRef<ResourcePath>, template: Arc<Template>) {
+        self.resources.get_mut().push(Resource::Template(
+            path.as_ref().to_owned(),
+            template.clone(),
+        ));
+        self.cache.insert_template(path, template);
+    }
+
+    fn invalidate(&mut self, path: impl AsRef<ResourcePath>) {
+        self.cache.invalidate(path);
+    }
+
+    fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{Layer, LayerTile, LayerTileData, LayerType, Map, Object, Properties, PropertyValue};
+
+/// The differences between two versions of a map, as found by [`diff()`].
+///
+/// Layers and objects are matched by id between both maps, so those without one (an id of 0) are
+/// ignored. Ids of layers and objects which only exist in the old map refer to that map, and the
+/// other ones to the new map.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MapDiff {
+    /// The ids of the layers only found in the new map.
+    pub added_layers: Vec<u32>,
+    /// The ids of the layers only found in the old map.
+    pub removed_layers: Vec<u32>,
+    /// The tiles that differ between the tile layers found in both maps.
+    pub tiles: Vec<TileChange>,
+    /// The ids of the objects only found in the new map.
+    pub added_objects: Vec<u32>,
+    /// The ids of the objects only found in the old map.
+    pub removed_objects: Vec<u32>,
+    /// The ids of the objects found in both maps whose data differs, not counting their
+    /// properties, which are listed in [`properties`](Self::properties) instead.
+    pub changed_objects: Vec<u32>,
+    /// The custom properties that differ between the maps, and between the layers and objects
+    /// found in both maps.
+    pub properties: Vec<PropertyChange>,
+}
+
+impl MapDiff {
+    /// Returns `true` if no differences were found.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// A tile that differs between two versions of a tile layer. Part of a [`MapDiff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileChange {
+    /// The id of the tile layer.
+    pub layer: u32,
+    /// The x coordinate of the tile, in tiles.
+    pub x: i32,
+    /// The y coordinate of the tile, in tiles.
+    pub y: i32,
+    /// The tile in the old map, relative to the tilesets of that map, or [`None`] if it was empty.
+    pub old: Option<LayerTileData>,
+    /// The tile in the new map, relative to the tilesets of that map, or [`None`] if it is empty.
+    pub new: Option<LayerTileData>,
+}
+
+/// A custom property that differs between two versions of a map. Part of a [`MapDiff`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyChange {
+    /// What the property belongs to.
+    pub owner: PropertyOwner,
+    /// The name of the property.
+    pub name: String,
+    /// The value of the property in the old map, if it was set.
+    pub old: Option<PropertyValue>,
+    /// The value of the property in the new map, if it is set.
+    pub new: Option<PropertyValue>,
+}
+
+/// What a [`PropertyChange`] applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PropertyOwner {
+    /// The map itself.
+    Map,
+    /// The layer with the id given.
+    Layer(u32),
+    /// The object with the id given.
+    Object(u32),
+}
+
+/// Finds the differences between two versions of a map: The layers, tiles and objects added,
+/// removed or changed, and the custom properties that differ.
+///
+/// Tiles are compared by the name of their tileset, their local id and their flip flags, so that
+/// reordering the tilesets of a map doesn't change all of its tiles. Tilesets themselves aren't
+/// compared.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{LayerTileData, Loader};
+///
+/// let old = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+/// let mut new = old.clone();
+/// new.get_layer_mut(0)
+///     .unwrap()
+///     .as_finite_tile_layer_mut()
+///     .unwrap()
+///     .set_tile(0, 0, Some(LayerTileData::new(0, 5)));
+///
+/// let diff = tiled::diff(&old, &new);
+/// assert_eq!(diff.tiles.len(), 1);
+/// assert_eq!((diff.tiles[0].x, diff.tiles[0].y), (0, 0));
+/// assert_eq!(diff.tiles[0].new.unwrap().id(), 5);
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff(old: &Map, new: &Map) -> MapDiff {
+    let mut diff = MapDiff::default();
+    diff_properties(
+        PropertyOwner::Map,
+        &old.properties,
+        &new.properties,
+        &mut diff.properties,
+    );
+
+    let (old_layers, new_layers) = (layers_by_id(old), layers_by_id(new));
+    diff.removed_layers = removed(&old_layers, &new_layers);
+    diff.added_layers = removed(&new_layers, &old_layers);
+    for (id, new_layer) in &new_layers {
+        let old_layer = match old_layers.get(id) {
+            Some(layer) => layer,
+            None => continue,
+        };
+        diff_properties(
+            PropertyOwner::Layer(*id),
+            &old_layer.properties,
+            &new_layer.properties,
+            &mut diff.properties,
+        );
+        if let (LayerType::Tiles(old_tiles), LayerType::Tiles(new_tiles)) =
+            (old_layer.layer_type(), new_layer.layer_type())
+        {
+            let old_tiles: HashMap<_, _> = old_tiles.all_tiles().collect();
+            let new_tiles: HashMap<_, _> = new_tiles.all_tiles().collect();
+            let mut positions: Vec<_> = old_tiles.keys().chain(new_tiles.keys()).collect();
+            positions.sort_by_key(|(x, y)| (*y, *x));
+            positions.dedup();
+            for &(x, y) in positions {
+                let (old_tile, new_tile) = (old_tiles.get(&(x, y)), new_tiles.get(&(x, y)));
+                if tile_key(old_tile) != tile_key(new_tile) {
+                    diff.tiles.push(TileChange {
+                        layer: *id,
+                        x,
+                        y,
+                        old: old_tile.map(|tile| **tile),
+                        new: new_tile.map(|tile| **tile),
+                    });
+                }
+            }
+        }
+    }
+
+    let (old_objects, new_objects) = (objects_by_id(&old_layers), objects_by_id(&new_layers));
+    diff.removed_objects = removed(&old_objects, &new_objects);
+    diff.added_objects = removed(&new_objects, &old_objects);
+    for (id, new_object) in &new_objects {
+        let old_object = match old_objects.get(id) {
+            Some(object) => object,
+            None => continue,
+        };
+        diff_properties(
+            PropertyOwner::Object(*id),
+            &old_object.properties,
+            &new_object.properties,
+            &mut diff.properties,
+        );
+        let (mut old_data, mut new_data) = ((**old_object).clone(), (**new_object).clone());
+        old_data.properties.clear();
+        new_data.properties.clear();
+        if old_data != new_data {
+            diff.changed_objects.push(*id);
+        }
+    }
+    diff
+}
+
+/// Returns what identifies a tile independently of the order of the tilesets of its map.
+fn tile_key<'a>(tile: Option<&'a LayerTile>) -> Option<(&'a str, u32, bool, bool, bool)> {
+    tile.map(|tile| {
+        (
+            tile.get_tileset().name.as_str(),
+            tile.id(),
+            tile.flip_h,
+            tile.flip_v,
+            tile.flip_d,
+        )
+    })
+}
+
+fn layers_by_id(map: &Map) -> BTreeMap<u32, Layer> {
+    fn add<'map>(layer: Layer<'map>, layers: &mut BTreeMap<u32, Layer<'map>>) {
+        if let LayerType::Group(group) = layer.layer_type() {
+            for child in group.layers() {
+                add(child, layers);
+            }
+        }
+        if layer.id() != 0 {
+            layers.insert(layer.id(), layer);
+        }
+    }
+    let mut layers = BTreeMap::new();
+    for layer in map.layers() {
+        add(layer, &mut layers);
+    }
+    layers
+}
+
+fn objects_by_id<'map>(layers: &BTreeMap<u32, Layer<'map>>) -> BTreeMap<u32, Object<'map>> {
+    layers
+        .values()
+        .filter_map(|layer| layer.as_object_layer())
+        .flat_map(|layer| layer.objects())
+        .filter(|object| object.id() != 0)
+        .map(|object| (object.id(), object))
+        .collect()
+}
+
+/// Returns the keys of `old` missing from `new`, in order.
+fn removed<A, B>(old: &BTreeMap<u32, A>, new: &BTreeMap<u32, B>) -> Vec<u32> {
+    old.keys()
+        .filter(|id| !new.contains_key(id))
+        .copied()
+        .collect()
+}
+
+fn diff_properties(
+    owner: PropertyOwner,
+    old: &Properties,
+    new: &Properties,
+    changes: &mut Vec<PropertyChange>,
+) {
+    let mut names: Vec<_> = old.keys().chain(new.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let (old_value, new_value) = (old.get(name), new.get(name));
+        if old_value != new_value {
+            changes.push(PropertyChange {
+                owner,
+                name: name.to_string(),
+                old: old_value.cloned(),
+                new: new_value.cloned(),
+            });
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    collections::HashMap,
+    iter::FromIterator,
+    path::{Path, PathBuf},
+};
+
+use crate::{util::normalize_path, ResourceReader};
+
+/// A [`ResourceReader`] that serves files embedded in the binary, for platforms where there is no
+/// filesystem to read them from, such as consoles or the web.
+///
+/// Files are registered along with the path they are loaded with, usually the path they have
+/// relative to the map; `.` and `..` components are resolved before looking them up.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{DefaultResourceCache, EmbeddedResourceReader, Loader};
+///
+/// let reader = EmbeddedResourceReader::new()
+///     .with_file(
+///         "map.tmx",
+///         include_bytes!("../assets/tiled_base64_external.tmx"),
+///     )
+///     .with_file("tilesheet.tsx", include_bytes!("../assets/tilesheet.tsx"));
+/// let mut loader = Loader::with_cache_and_reader(DefaultResourceCache::new(), reader);
+/// let map = loader.load_tmx_map("map.tmx")?;
+/// assert_eq!(map.tilesets()[0].name, "tilesheet");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedResourceReader {
+    files: HashMap<PathBuf, &'static [u8]>,
+}
+
+impl EmbeddedResourceReader {
+    /// Creates a reader with no files in it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file to the reader, replacing any file previously added at the same path.
+    pub fn insert(&mut self, path: impl AsRef<Path>, data: &'static [u8]) {
+        if let Some(path) = normalize_path(path.as_ref()) {
+            self.files.insert(path, data);
+        }
+    }
+
+    /// Adds a file to the reader and returns it. See [`EmbeddedResourceReader::insert()`].
+    pub fn with_file(mut self, path: impl AsRef<Path>, data: &'static [u8]) -> Self {
+        self.insert(path, data);
+        self
+    }
+}
+
+impl<P: AsRef<Path>> FromIterator<(P, &'static [u8])> for EmbeddedResourceReader {
+    fn from_iter<I: IntoIterator<Item = (P, &'static [u8])>>(iter: I) -> Self {
+        let mut reader = Self::new();
+        for (path, data) in iter {
+            reader.insert(path, data);
+        }
+        reader
+    }
+}
+
+impl ResourceReader for EmbeddedResourceReader {
+    type Resource = &'static [u8];
+    type Error = std::io::Error;
+
+    fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+        normalize_path(path)
+            .and_then(|path| self.files.get(&path).copied())
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "file is not embedded")
+            })
+    }
+
+    fn list_files(&mut self, directory: &Path) -> std::result::Result<Vec<PathBuf>, Self::Error> {
+        let directory = match normalize_path(directory) {
+            Some(directory) => directory,
+            None => return Ok(Vec::new()),
+        };
+        let mut files: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(|path| path.parent() == Some(&directory))
+            .cloned()
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
@@ -14,6 +14,20 @@ pub enum Error {
     Base64DecodingError(base64::DecodeError),
     /// An error occured when parsing a XML file, such as a TMX or TSX file.
     XmlDecodingError(xml::reader::Error),
+    /// An error occured when parsing a JSON file, such as a TMJ or TSJ file.
+    #[cfg(feature = "json")]
+    JsonDecodingError(serde_json::Error),
+    /// An error occured when writing a XML file, such as a TSX file.
+    XmlEncodingError(xml::writer::Error),
+    /// An error occured when decoding an image file with the
+    /// [image](https://github.com/image-rs/image) crate.
+    #[cfg(feature = "image")]
+    ImageDecodingError {
+        /// The path to the image file.
+        path: PathBuf,
+        /// The error that occured when decoding the file.
+        err: image_crate::ImageError,
+    },
     /// The XML stream ended before the document was fully parsed.
     PrematureEnd(String),
     /// The path given is invalid because it isn't contained in any folder.
@@ -26,6 +40,13 @@ pub enum Error {
         /// The error that occured when trying to open the file.
         err: Box<dyn std::error::Error + Send + Sync + 'static>,
     },
+    /// An error occured while trying to create a file to write a resource to.
+    ResourceWritingError {
+        /// The path to the file that was unable to be created.
+        path: PathBuf,
+        /// The error that occured when trying to create the file.
+        err: std::io::Error,
+    },
     /// There was an invalid tile in the map parsed.
     InvalidTileFound,
     /// Unknown encoding or compression format or invalid combination of both (for tile layers)
@@ -57,6 +78,72 @@ pub enum Error {
         /// Stores the wrongly parsed String.
         read_string: String,
     },
+    /// A property needed to build a value from [`Properties`](crate::Properties) was missing.
+    MissingProperty {
+        /// The name of the property.
+        name: String,
+    },
+    /// A property had a different type than the one needed to build a value from
+    /// [`Properties`](crate::Properties).
+    PropertyTypeMismatch {
+        /// The name of the property.
+        name: String,
+        /// The name of the type expected, as used in the `type` attribute of properties.
+        expected: &'static str,
+        /// The name of the type of the property's value.
+        found: &'static str,
+    },
+    /// An element that isn't part of the format was found in [strict
+    /// mode](crate::Loader::set_strict()).
+    UnknownElement {
+        /// The name of the element.
+        element: String,
+        /// The name of the element containing it.
+        parent: String,
+    },
+    /// An attribute that isn't part of the format was found in [strict
+    /// mode](crate::Loader::set_strict()).
+    UnknownAttribute {
+        /// The name of the element the attribute belongs to.
+        element: String,
+        /// The name of the attribute.
+        attribute: String,
+    },
+    /// Loading was stopped because one of the [`LoadLimits`](crate::LoadLimits) of the loader was
+    /// exceeded.
+    LimitExceeded {
+        /// The limit that was exceeded.
+        limit: crate::LoadLimit,
+        /// The value of the limit.
+        max: u64,
+    },
+    /// An error occured while parsing an element of an XML file, such as a TMX or TSX file.
+    ///
+    /// Errors that already tell where they happened, such as [`Error::XmlDecodingError`] and
+    /// [`Error::ResourceLoadingError`], are not wrapped in this variant. Use [`Error::inner()`]
+    /// to get the error without its location.
+    ElementError {
+        /// The path of the file the element is in.
+        path: PathBuf,
+        /// The name of the innermost element being parsed when the error occured.
+        element: String,
+        /// The line of the element's start tag, starting from 1.
+        line: u64,
+        /// The column of the element's start tag, starting from 1.
+        column: u64,
+        /// The error that occured.
+        err: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Returns the error without the location added by [`Error::ElementError`], if any.
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::ElementError { err, .. } => err,
+            err => err,
+        }
+    }
 }
 
 /// A result with an error variant of [`crate::Error`].
@@ -69,6 +156,16 @@ impl fmt::Display for Error {
             Error::DecompressingError(e) => write!(fmt, "{}", e),
             Error::Base64DecodingError(e) => write!(fmt, "{}", e),
             Error::XmlDecodingError(e) => write!(fmt, "{}", e),
+            #[cfg(feature = "json")]
+            Error::JsonDecodingError(e) => write!(fmt, "{}", e),
+            Error::XmlEncodingError(e) => write!(fmt, "{}", e),
+            #[cfg(feature = "image")]
+            Error::ImageDecodingError { path, err } => write!(
+                fmt,
+                "Could not decode image '{}'. Error: {}",
+                path.to_string_lossy(),
+                err
+            ),
             Error::PrematureEnd(e) => write!(fmt, "{}", e),
             Error::PathIsNotFile => {
                 write!(
@@ -84,12 +181,26 @@ impl fmt::Display for Error {
                     err
                 )
             }
+            Error::ResourceWritingError { path, err } => {
+                write!(
+                    fmt,
+                    "Could not write to '{}'. Error: {}",
+                    path.to_string_lossy(),
+                    err
+                )
+            }
             Error::InvalidTileFound => write!(fmt, "Invalid tile found in map being parsed"),
             Error::InvalidEncodingFormat { encoding: None, compression: None } =>
                 write!(
                     fmt,
                     "Deprecated combination of encoding and compression"
                 ),
+            #[cfg(not(feature = "zstd"))]
+            Error::InvalidEncodingFormat { compression: Some(compression), .. } if compression == "zstd" =>
+                write!(
+                    fmt,
+                    "Tile layer data is compressed using zstd, which requires the `zstd` feature"
+                ),
             Error::InvalidEncodingFormat { encoding, compression } =>
                 write!(
                     fmt,
@@ -104,6 +215,29 @@ impl fmt::Display for Error {
             Error::TemplateHasNoObject => write!(fmt, "A template was found with no object element"),
             Error::InvalidWangIdEncoding{read_string} =>
                 write!(fmt, "\"{}\" is not a valid WangId format", read_string),
+            Error::MissingProperty { name } => write!(fmt, "Missing property '{}'", name),
+            Error::PropertyTypeMismatch { name, expected, found } =>
+                write!(
+                    fmt,
+                    "Property '{}' has type '{}', but '{}' was expected",
+                    name, found, expected
+                ),
+            Error::UnknownElement { element, parent } =>
+                write!(fmt, "Unknown element <{}> in <{}> element", element, parent),
+            Error::UnknownAttribute { element, attribute } =>
+                write!(fmt, "Unknown attribute '{}' in <{}> element", attribute, element),
+            Error::LimitExceeded { limit, max } =>
+                write!(fmt, "The {} limit of {} was exceeded", limit, max),
+            Error::ElementError { path, element, line, column, err } =>
+                write!(
+                    fmt,
+                    "{} (in <{}> at {}:{}:{})",
+                    err,
+                    element,
+                    path.to_string_lossy(),
+                    line,
+                    column
+                ),
         }
     }
 }
@@ -114,7 +248,14 @@ impl std::error::Error for Error {
             Error::DecompressingError(e) => Some(e as &dyn std::error::Error),
             Error::Base64DecodingError(e) => Some(e as &dyn std::error::Error),
             Error::XmlDecodingError(e) => Some(e as &dyn std::error::Error),
+            #[cfg(feature = "json")]
+            Error::JsonDecodingError(e) => Some(e as &dyn std::error::Error),
+            Error::XmlEncodingError(e) => Some(e as &dyn std::error::Error),
+            #[cfg(feature = "image")]
+            Error::ImageDecodingError { err, .. } => Some(err as &dyn std::error::Error),
             Error::ResourceLoadingError { err, .. } => Some(err.as_ref()),
+            Error::ResourceWritingError { err, .. } => Some(err as &dyn std::error::Error),
+            Error::ElementError { err, .. } => Some(err.as_ref() as &dyn std::error::Error),
             _ => None,
         }
     }
@@ -0,0 +1,565 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use xml::{attribute::OwnedAttribute, reader::XmlEvent};
+
+use crate::{
+    layers::{check_gids, decode_data_gids, gids_to_tiles, LayerData, LayerDataType},
+    parse::{load_external_tileset, xml::TrackedEvents, LoadContext},
+    properties::parse_properties,
+    util::get_attrs,
+    warning::DocumentGuard,
+    Color, EmbeddedParseResultType, Error, ImageLayerData, LayerTileData, Loader, Map,
+    MapTilesetGid, ObjectData, Properties, ResourceCache, ResourceReader, Result, Tileset, Version,
+};
+
+/// Something found while streaming a map with [`MapEvents`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapEvent {
+    /// The map started. Always the first event. The map holds the attributes and custom
+    /// properties of the map, but none of its tilesets or layers, which are sent afterwards.
+    MapStarted(Map),
+    /// A tileset of the map. Tilesets are sent before the layers using them, and tiles refer to
+    /// them by their index in the order they are sent.
+    Tileset {
+        /// The GID of the first tile of the tileset within the map.
+        first_gid: u32,
+        /// The tileset, which is shared with the cache of the loader if it is external.
+        tileset: Arc<Tileset>,
+    },
+    /// A layer started. It is followed by its contents, then by [`MapEvent::LayerEnded`]. The
+    /// events of the children of group layers are sent in between.
+    LayerStarted(LayerInfo),
+    /// A row of tiles of the current tile layer.
+    TileRow(TileRow),
+    /// An object of the current object layer.
+    ObjectParsed(ObjectData),
+    /// The custom properties of the current layer, or of the map if no layer is open. Only sent
+    /// if the file lists them after the contents of their owner, which Tiled never does;
+    /// Properties found first are part of [`MapEvent::MapStarted`] and [`MapEvent::LayerStarted`].
+    Properties(Properties),
+    /// The current layer ended.
+    LayerEnded,
+}
+
+/// The attributes and custom properties of a layer, sent by [`MapEvent::LayerStarted`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerInfo {
+    /// The layer's id. Unique within the parent map. Valid only if greater than 0.
+    pub id: u32,
+    /// The name of the layer, set by the user.
+    pub name: String,
+    /// Whether this layer should be visible or not.
+    pub visible: bool,
+    /// The layer's x offset (in pixels).
+    pub offset_x: f32,
+    /// The layer's y offset (in pixels).
+    pub offset_y: f32,
+    /// The layer's x parallax factor.
+    pub parallax_x: f32,
+    /// The layer's y parallax factor.
+    pub parallax_y: f32,
+    /// The layer's opacity.
+    pub opacity: f32,
+    /// The layer's tint color, if any.
+    pub tint_color: Option<Color>,
+    /// The layer's custom properties, as arbitrarily set by the user.
+    pub properties: Properties,
+    /// The layer's class, which is arbitrarily set by the user.
+    pub user_type: Option<String>,
+    /// What the layer contains.
+    pub kind: LayerKind,
+}
+
+impl LayerInfo {
+    fn new(attrs: &[OwnedAttribute], kind: LayerKind) -> Result<Self> {
+        let layer = LayerData::from_xml_attrs(attrs, LayerDataType::Group(Default::default()))?;
+        Ok(Self {
+            id: layer.id(),
+            name: layer.name,
+            visible: layer.visible,
+            offset_x: layer.offset_x,
+            offset_y: layer.offset_y,
+            parallax_x: layer.parallax_x,
+            parallax_y: layer.parallax_y,
+            opacity: layer.opacity,
+            tint_color: layer.tint_color,
+            properties: layer.properties,
+            user_type: layer.user_type,
+            kind,
+        })
+    }
+}
+
+/// The kind of a layer sent by [`MapEvent::LayerStarted`], along with what isn't streamed of its
+/// contents.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayerKind {
+    /// A tile layer, whose tiles are sent as [`MapEvent::TileRow`]s. The size is the one of the
+    /// layer in tiles, which doesn't bound the tiles of infinite maps.
+    Tiles {
+        /// The width of the layer, in tiles.
+        width: u32,
+        /// The height of the layer, in tiles.
+        height: u32,
+    },
+    /// An object layer, whose objects are sent as [`MapEvent::ObjectParsed`].
+    Objects {
+        /// The color used in the editor to display objects in this layer.
+        colour: Option<Color>,
+    },
+    /// An image layer, which is sent whole.
+    Image(ImageLayerData),
+    /// A group layer, whose children are sent as their own layers.
+    Group,
+}
+
+/// A horizontal run of tiles of a tile layer, sent by [`MapEvent::TileRow`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileRow {
+    /// The x coordinate of the first tile of the row, in tiles. Always 0 in finite maps.
+    pub x: i32,
+    /// The y coordinate of the row, in tiles.
+    pub y: i32,
+    /// The tiles of the row, from left to right.
+    pub tiles: Vec<Option<LayerTileData>>,
+}
+
+/// Streams the contents of a TMX map as [`MapEvent`]s, without building the whole [`Map`] in
+/// memory. Created with [`Loader::map_events()`].
+///
+/// Tile layers are sent row by row and object layers object by object as the file is read, so
+/// that huge maps can be moved into other data structures while only keeping a row of tiles in
+/// memory at a time, besides the raw GIDs of the layer or chunk being read. Infinite maps send
+/// the rows of each of their chunks, in the order the chunks appear in the file.
+///
+/// The iterator stops after the first error, which is located like those of
+/// [`Loader::load_tmx_map()`]. Tilesets and templates are loaded through the loader and cached
+/// like when loading the map, and warnings are collected by the loader as well. The settings of
+/// the loader about the data of tile layers don't apply, since tiles are always decoded as they
+/// are streamed, and object references aren't checked.
+pub struct MapEvents<'l, Cache: ResourceCache, Reader: ResourceReader> {
+    events: TrackedEvents<'l, Reader::Resource>,
+    ctx: LoadContext<'l, Reader, Cache>,
+    path: PathBuf,
+    infinite: bool,
+    /// The versions the map was saved with, which its embedded tilesets share.
+    versions: (Version, Option<Version>),
+    tilesets: Vec<MapTilesetGid>,
+    /// The elements currently open which have yet to be closed.
+    frames: Vec<Frame>,
+    /// The GIDs of the `<tile>` elements read so far in the data or chunk being streamed, for the
+    /// maps old enough to list tiles that way, as CSV.
+    xml_tiles: String,
+    /// The GIDs of the finite layer or chunk being sent, turned into rows one at a time.
+    rows: Option<PendingRows>,
+    /// The map or layer that started but wasn't sent yet, in case its properties come next.
+    pending: Option<MapEvent>,
+    queue: VecDeque<MapEvent>,
+    finished: bool,
+    // Dropped last, so that the document is left once the events are done with.
+    _document: DocumentGuard<'l>,
+}
+
+/// The decoded GIDs of a finite layer or chunk, along with the next row to send.
+struct PendingRows {
+    x: i32,
+    y: i32,
+    width: usize,
+    gids: Vec<u32>,
+    next_row: usize,
+}
+
+/// An element open in the map being streamed.
+enum Frame {
+    Map,
+    TileLayer {
+        width: u32,
+    },
+    ObjectLayer,
+    GroupLayer,
+    Data {
+        encoding: Option<String>,
+        compression: Option<String>,
+    },
+    Chunk {
+        x: i32,
+        y: i32,
+        width: u32,
+    },
+    /// An element whose contents aren't streamed.
+    Ignored,
+}
+
+impl<Cache: ResourceCache, Reader: ResourceReader> Loader<Cache, Reader> {
+    /// Starts streaming the TMX map at the path given. See [`MapEvents`].
+    ///
+    /// Maps in the JSON format can't be streamed, and have to be loaded whole.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Loader, MapEvent};
+    ///
+    /// let mut loader = Loader::new();
+    /// let mut filled = 0;
+    /// for event in loader.map_events("assets/tiled_base64.tmx")? {
+    ///     if let MapEvent::TileRow(row) = event? {
+    ///         filled += row.tiles.iter().flatten().count();
+    ///     }
+    /// }
+    /// assert_eq!(filled, 161);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn map_events(&mut self, path: impl AsRef<Path>) -> Result<MapEvents<'_, Cache, Reader>> {
+        MapEvents::new(path.as_ref(), self.context())
+    }
+}
+
+impl<'l, Cache: ResourceCache, Reader: ResourceReader> MapEvents<'l, Cache, Reader> {
+    fn new(path: &Path, ctx: LoadContext<'l, Reader, Cache>) -> Result<Self> {
+        let reader = ctx
+            .reader
+            .read_from(path)
+            .map_err(|err| Error::ResourceLoadingError {
+                path: path.to_owned(),
+                err: Box::new(err),
+            })?;
+        let diagnostics = ctx.diagnostics;
+        let document = diagnostics.enter_document(path);
+        let mut events = TrackedEvents::new(reader, diagnostics);
+        let map = loop {
+            match events.next() {
+                Some(Ok(XmlEvent::StartElement {
+                    name, attributes, ..
+                })) if name.local_name == "map" => {
+                    break Map::from_xml_attrs(&attributes, path)
+                        .map_err(|err| diagnostics.locate(err))?;
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(Error::XmlDecodingError(err)),
+                None => {
+                    return Err(events.take_error().unwrap_or_else(|| {
+                        Error::PrematureEnd(
+                            "Document ended before the map element was parsed".to_owned(),
+                        )
+                    }))
+                }
+            }
+        };
+        Ok(Self {
+            events,
+            ctx,
+            path: path.to_owned(),
+            infinite: map.infinite(),
+            versions: (map.version(), map.tiled_version),
+            tilesets: Vec::new(),
+            frames: vec![Frame::Map],
+            xml_tiles: String::new(),
+            rows: None,
+            pending: Some(MapEvent::MapStarted(map)),
+            queue: VecDeque::new(),
+            finished: false,
+            _document: document,
+        })
+    }
+
+    /// Reads the next XML event of the map, queueing the map events it results in.
+    fn advance(&mut self) -> Result<()> {
+        let event = match self.events.next() {
+            Some(event) => event.map_err(Error::XmlDecodingError)?,
+            None => return Err(Error::PrematureEnd("Ran out of XML data".to_owned())),
+        };
+        match event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => self.start_element(&name.local_name, attributes),
+            XmlEvent::Characters(data) => self.characters(&data),
+            XmlEvent::EndElement { .. } => self.end_element(),
+            XmlEvent::EndDocument => Err(Error::PrematureEnd(
+                "Document ended before the map element was closed".to_owned(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn start_element(&mut self, name: &str, attrs: Vec<OwnedAttribute>) -> Result<()> {
+        let is_container = matches!(self.frames.last(), Some(Frame::Map | Frame::GroupLayer));
+        let is_layer = matches!(
+            self.frames.last(),
+            Some(Frame::TileLayer { .. } | Frame::ObjectLayer | Frame::GroupLayer)
+        );
+        match name {
+            "properties" if is_container || is_layer => {
+                let properties = parse_properties(
+                    &mut self.events,
+                    self.ctx.project,
+                    &self.ctx.interner,
+                    self.ctx.diagnostics,
+                )?;
+                match &mut self.pending {
+                    Some(MapEvent::MapStarted(map)) => map.properties = properties,
+                    Some(MapEvent::LayerStarted(layer)) => layer.properties = properties,
+                    _ => self.queue.push_back(MapEvent::Properties(properties)),
+                }
+            }
+            "tileset" if matches!(self.frames.last(), Some(Frame::Map)) => {
+                self.flush();
+                let result =
+                    Tileset::parse_xml_in_map(&mut self.events, &attrs, &self.path, &mut self.ctx)?;
+                let tileset = match result.result_type {
+                    EmbeddedParseResultType::ExternalReference { tileset_path } => {
+                        // The end of the reference is left to be read.
+                        self.frames.push(Frame::Ignored);
+                        load_external_tileset(&tileset_path, &mut self.ctx)?
+                    }
+                    EmbeddedParseResultType::Embedded { tileset } => {
+                        let (version, tiled_version) = self.versions;
+                        Arc::new(tileset.embedded_in(version, tiled_version))
+                    }
+                };
+                self.tilesets.push(MapTilesetGid {
+                    first_gid: result.first_gid,
+                    tileset: tileset.clone(),
+                });
+                self.queue.push_back(MapEvent::Tileset {
+                    first_gid: result.first_gid.0,
+                    tileset,
+                });
+            }
+            "layer" if is_container => {
+                let info = LayerInfo::new(&attrs, LayerKind::Group)?;
+                let (width, height) = get_attrs!(
+                    for v in attrs {
+                        "width" => width ?= v.parse::<u32>(),
+                        "height" => height ?= v.parse::<u32>(),
+                    }
+                    (width, height)
+                );
+                if !self.infinite {
+                    self.ctx.tiles.take(width, height)?;
+                }
+                let kind = LayerKind::Tiles { width, height };
+                self.start_layer(LayerInfo { kind, ..info }, Frame::TileLayer { width });
+            }
+            "objectgroup" if is_container => {
+                let info = LayerInfo::new(&attrs, LayerKind::Group)?;
+                let colour = get_attrs!(
+                    for v in attrs {
+                        Some("color") => color ?= v.parse(),
+                    }
+                    color
+                );
+                let kind = LayerKind::Objects { colour };
+                self.start_layer(LayerInfo { kind, ..info }, Frame::ObjectLayer);
+            }
+            "group" if is_container => {
+                self.ctx.enter_group()?;
+                self.start_layer(LayerInfo::new(&attrs, LayerKind::Group)?, Frame::GroupLayer);
+            }
+            "imagelayer" if is_container => {
+                self.flush();
+                // Image layers are small, so they are parsed whole.
+                let mut info = LayerInfo::new(&attrs, LayerKind::Group)?;
+                let (image, properties) =
+                    ImageLayerData::new(&mut self.events, &attrs, &self.path, &mut self.ctx)?;
+                info.kind = LayerKind::Image(image);
+                info.properties = properties;
+                self.queue.push_back(MapEvent::LayerStarted(info));
+                self.queue.push_back(MapEvent::LayerEnded);
+            }
+            "data" if matches!(self.frames.last(), Some(Frame::TileLayer { .. })) => {
+                self.flush();
+                let (encoding, compression) = get_attrs!(
+                    for v in attrs {
+                        Some("encoding") => encoding = v,
+                        Some("compression") => compression = v,
+                    }
+                    (encoding, compression)
+                );
+                self.frames.push(Frame::Data {
+                    encoding,
+                    compression,
+                });
+            }
+            "chunk" if self.infinite && matches!(self.frames.last(), Some(Frame::Data { .. })) => {
+                let (x, y, width, height) = get_attrs!(
+                    for v in attrs {
+                        "x" => x ?= v.parse::<i32>(),
+                        "y" => y ?= v.parse::<i32>(),
+                        "width" => width ?= v.parse::<u32>(),
+                        "height" => height ?= v.parse::<u32>(),
+                    }
+                    (x, y, width, height)
+                );
+                self.ctx.tiles.take(width, height)?;
+                self.frames.push(Frame::Chunk { x, y, width });
+            }
+            "tile"
+                if matches!(
+                    self.frames.last(),
+                    Some(Frame::Data { .. } | Frame::Chunk { .. })
+                ) =>
+            {
+                let gid = get_attrs!(
+                    for v in attrs {
+                        Some("gid") => gid = v,
+                    }
+                    gid
+                );
+                if !self.xml_tiles.is_empty() {
+                    self.xml_tiles.push(',');
+                }
+                self.xml_tiles.push_str(gid.as_deref().unwrap_or("0"));
+                self.frames.push(Frame::Ignored);
+            }
+            "object" if matches!(self.frames.last(), Some(Frame::ObjectLayer)) => {
+                self.flush();
+                let object = ObjectData::new(
+                    &mut self.events,
+                    attrs,
+                    Some(&self.tilesets),
+                    None,
+                    self.path.parent().ok_or(Error::PathIsNotFile)?,
+                    &mut self.ctx,
+                )?;
+                self.queue.push_back(MapEvent::ObjectParsed(object));
+            }
+            _ => self.frames.push(Frame::Ignored),
+        }
+        Ok(())
+    }
+
+    /// Decodes the data of a finite tile layer or of a chunk, whose rows are then sent one by one.
+    fn characters(&mut self, data: &str) -> Result<()> {
+        let (x, y, width, data_frame) = match self.frames.as_slice() {
+            [.., Frame::TileLayer { width }, data @ Frame::Data { .. }] if !self.infinite => {
+                (0, 0, *width, data)
+            }
+            [.., data @ Frame::Data { .. }, Frame::Chunk { x, y, width }] => (*x, *y, *width, data),
+            _ => return Ok(()),
+        };
+        let (encoding, compression) = match data_frame {
+            Frame::Data {
+                encoding,
+                compression,
+            } => (encoding.as_deref(), compression.as_deref()),
+            _ => unreachable!(),
+        };
+        let gids = decode_data_gids(encoding, compression, data, self.ctx.decoders)?;
+        check_gids(&gids, &self.tilesets, self.ctx.diagnostics);
+        if width == 0 {
+            return Ok(());
+        }
+        self.rows = Some(PendingRows {
+            x,
+            y,
+            width: width as usize,
+            gids,
+            next_row: 0,
+        });
+        Ok(())
+    }
+
+    /// Turns the next row of the finite layer or chunk being sent into tiles, if any is left.
+    fn next_row(&mut self) -> Option<TileRow> {
+        let rows = self.rows.as_mut()?;
+        let start = rows.next_row * rows.width;
+        if start >= rows.gids.len() {
+            self.rows = None;
+            return None;
+        }
+        let end = (start + rows.width).min(rows.gids.len());
+        let row = TileRow {
+            x: rows.x,
+            y: rows.y + rows.next_row as i32,
+            tiles: gids_to_tiles(&rows.gids[start..end], &self.tilesets),
+        };
+        rows.next_row += 1;
+        Some(row)
+    }
+
+    fn end_element(&mut self) -> Result<()> {
+        if matches!(
+            self.frames.last(),
+            Some(Frame::Data { .. } | Frame::Chunk { .. })
+        ) && !self.xml_tiles.is_empty()
+        {
+            let tiles = std::mem::take(&mut self.xml_tiles);
+            self.characters(&tiles)?;
+        }
+        match self.frames.pop() {
+            Some(Frame::Map) => {
+                self.flush();
+                self.finished = true;
+            }
+            Some(Frame::TileLayer { .. } | Frame::ObjectLayer) => {
+                self.flush();
+                self.queue.push_back(MapEvent::LayerEnded);
+            }
+            Some(Frame::GroupLayer) => {
+                self.flush();
+                self.ctx.group_depth -= 1;
+                self.queue.push_back(MapEvent::LayerEnded);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Opens a layer, which is only sent once its properties are known.
+    fn start_layer(&mut self, info: LayerInfo, frame: Frame) {
+        self.flush();
+        self.pending = Some(MapEvent::LayerStarted(info));
+        self.frames.push(frame);
+    }
+
+    /// Queues the map or layer waiting for its properties, if any.
+    fn flush(&mut self) {
+        if let Some(event) = self.pending.take() {
+            self.queue.push_back(event);
+        }
+    }
+}
+
+impl<Cache: ResourceCache, Reader: ResourceReader> Iterator for MapEvents<'_, Cache, Reader> {
+    type Item = Result<MapEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Rows are decoded while nothing else is queued, and come before what follows them.
+            if let Some(row) = self.next_row() {
+                return Some(Ok(MapEvent::TileRow(row)));
+            }
+            if let Some(event) = self.queue.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.finished {
+                return None;
+            }
+            let result = self.advance();
+            let err = match (self.events.take_error(), result) {
+                (Some(err), _) => err,
+                (None, Err(err)) => self.ctx.diagnostics.locate(err),
+                (None, Ok(())) => continue,
+            };
+            self.finished = true;
+            self.rows = None;
+            self.queue.clear();
+            return Some(Err(err));
+        }
+    }
+}
+
+impl<Cache: ResourceCache, Reader: ResourceReader> fmt::Debug for MapEvents<'_, Cache, Reader> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapEvents")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,277 @@
+use std::io::Write;
+
+use xml::{
+    attribute::OwnedAttribute,
+    name::{Name, OwnedName},
+    writer::XmlEvent as XmlWriteEvent,
+    EventWriter,
+};
+
+use crate::{Error, Result};
+
+/// The elements and attributes of an XML element that aren't part of the TMX format, such as
+/// those added by other tools, kept so that writing the file back doesn't strip them.
+///
+/// Extensions are only kept if the loader
+/// [preserves them](crate::Loader::set_preserve_extensions()). They are kept for maps,
+/// tilesets, the tiles of tilesets and objects, including those of templates, and written back by
+/// [`Tileset::write_tsx()`](crate::Tileset::write_tsx()) and
+/// [`Template::write_tx()`](crate::Template::write_tx()). Unknown elements are written after the
+/// known ones.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::Loader;
+///
+/// let mut loader = Loader::new();
+/// loader.set_preserve_extensions(true);
+/// let tileset = loader.load_tsx_tileset("assets/tiled_extensions.tsx")?;
+/// assert_eq!(tileset.extensions.attribute("editor:color"), Some("#ff0000"));
+/// assert_eq!(tileset.extensions.element_names().collect::<Vec<_>>(), ["editor:notes"]);
+///
+/// let mut tsx = Vec::new();
+/// tileset.write_tsx(&mut tsx, "assets/tiled_extensions.tsx")?;
+/// let tsx = String::from_utf8(tsx).unwrap();
+/// assert!(tsx.contains(r##"editor:color="#ff0000""##));
+/// assert!(tsx.contains("<editor:notes>"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XmlExtensions {
+    attributes: Vec<(ExtensionName, String)>,
+    elements: Vec<ExtensionNode>,
+}
+
+impl XmlExtensions {
+    /// Returns whether there are no unknown elements or attributes.
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.elements.is_empty()
+    }
+
+    /// Returns the value of the unknown attribute with the given name, prefixed with its
+    /// namespace prefix if it has one, such as `editor:color`.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes()
+            .find(|(qualified, _)| *qualified == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns an iterator over the names and values of the unknown attributes, in the order they
+    /// were found. Names are prefixed with their namespace prefix, if any.
+    pub fn attributes(&self) -> impl Iterator<Item = (String, &str)> {
+        self.attributes
+            .iter()
+            .map(|(name, value)| (name.qualified(), value.as_str()))
+    }
+
+    /// Returns an iterator over the names of the unknown elements, in the order they were found.
+    /// Names are prefixed with their namespace prefix, if any.
+    pub fn element_names(&self) -> impl Iterator<Item = String> + '_ {
+        self.elements.iter().filter_map(|node| match node {
+            ExtensionNode::Element(element) => Some(element.name.qualified()),
+            _ => None,
+        })
+    }
+
+    /// Returns the estimated amount of memory owned by the extensions, in bytes.
+    pub(crate) fn heap_size(&self) -> usize {
+        attributes_heap_size(&self.attributes) + nodes_heap_size(&self.elements)
+    }
+
+    /// Records the attributes of an element that aren't in `known`, as well as all of those in a
+    /// namespace.
+    pub(crate) fn from_attributes(attributes: &[OwnedAttribute], known: &[&str]) -> Self {
+        let attributes = attributes
+            .iter()
+            .filter(|attribute| {
+                attribute.name.prefix.is_some()
+                    || !known.contains(&attribute.name.local_name.as_str())
+            })
+            .map(|attribute| {
+                (
+                    ExtensionName::from(&attribute.name),
+                    attribute.value.clone(),
+                )
+            })
+            .collect();
+        Self {
+            attributes,
+            elements: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push_element(&mut self, element: ExtensionElement) {
+        self.elements.push(ExtensionNode::Element(element));
+    }
+
+    /// Writes the start tag of an element with the given known attributes, followed by the
+    /// unknown ones.
+    pub(crate) fn write_start_element(
+        &self,
+        writer: &mut EventWriter<impl Write>,
+        name: &str,
+        attrs: &[(&str, String)],
+    ) -> Result<()> {
+        let mut element = XmlWriteEvent::start_element(name);
+        for (attr, value) in attrs {
+            element = element.attr(*attr, value);
+        }
+        for (name, value) in &self.attributes {
+            element = name.declare(element).attr(name.as_name(), value);
+        }
+        writer.write(element).map_err(Error::XmlEncodingError)
+    }
+
+    /// Writes the unknown elements, which are meant to come last in the element they belong to.
+    pub(crate) fn write_elements(&self, writer: &mut EventWriter<impl Write>) -> Result<()> {
+        self.elements
+            .iter()
+            .try_for_each(|element| element.write(writer))
+    }
+}
+
+/// The name of an unknown element or attribute, along with its namespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct ExtensionName {
+    local_name: String,
+    prefix: Option<String>,
+    namespace: Option<String>,
+}
+
+impl ExtensionName {
+    fn heap_size(&self) -> usize {
+        self.local_name.capacity()
+            + self.prefix.as_ref().map_or(0, String::capacity)
+            + self.namespace.as_ref().map_or(0, String::capacity)
+    }
+
+    fn qualified(&self) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{}:{}", prefix, self.local_name),
+            None => self.local_name.clone(),
+        }
+    }
+
+    fn as_name(&self) -> Name<'_> {
+        Name {
+            local_name: &self.local_name,
+            namespace: self.namespace.as_deref(),
+            prefix: self.prefix.as_deref(),
+        }
+    }
+
+    /// Declares the namespace of the name on the element being written, so that the name is
+    /// bound even if the element declaring it originally isn't written.
+    fn declare<'a>(
+        &self,
+        element: xml::writer::events::StartElementBuilder<'a>,
+    ) -> xml::writer::events::StartElementBuilder<'a> {
+        match (&self.prefix, &self.namespace) {
+            (Some(prefix), Some(namespace)) => element.ns(prefix.as_str(), namespace.as_str()),
+            _ => element,
+        }
+    }
+}
+
+impl From<&OwnedName> for ExtensionName {
+    fn from(name: &OwnedName) -> Self {
+        Self {
+            local_name: name.local_name.clone(),
+            prefix: name.prefix.clone(),
+            namespace: name.namespace.clone(),
+        }
+    }
+}
+
+/// An unknown element, along with everything it contains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct ExtensionElement {
+    name: ExtensionName,
+    attributes: Vec<(ExtensionName, String)>,
+    children: Vec<ExtensionNode>,
+}
+
+impl ExtensionElement {
+    pub(crate) fn new(name: &OwnedName, attributes: &[OwnedAttribute]) -> Self {
+        Self {
+            name: name.into(),
+            attributes: attributes
+                .iter()
+                .map(|attribute| ((&attribute.name).into(), attribute.value.clone()))
+                .collect(),
+            children: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push_child(&mut self, child: ExtensionElement) {
+        self.children.push(ExtensionNode::Element(child));
+    }
+
+    pub(crate) fn push_text(&mut self, text: String) {
+        self.children.push(ExtensionNode::Text(text));
+    }
+
+    pub(crate) fn push_cdata(&mut self, text: String) {
+        self.children.push(ExtensionNode::CData(text));
+    }
+}
+
+/// The contents of an unknown element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum ExtensionNode {
+    Element(ExtensionElement),
+    Text(String),
+    CData(String),
+}
+
+fn attributes_heap_size(attributes: &Vec<(ExtensionName, String)>) -> usize {
+    attributes.capacity() * std::mem::size_of::<(ExtensionName, String)>()
+        + attributes
+            .iter()
+            .map(|(name, value)| name.heap_size() + value.capacity())
+            .sum::<usize>()
+}
+
+fn nodes_heap_size(nodes: &Vec<ExtensionNode>) -> usize {
+    nodes.capacity() * std::mem::size_of::<ExtensionNode>()
+        + nodes
+            .iter()
+            .map(|node| match node {
+                ExtensionNode::Element(element) => {
+                    element.name.heap_size()
+                        + attributes_heap_size(&element.attributes)
+                        + nodes_heap_size(&element.children)
+                }
+                ExtensionNode::Text(text) | ExtensionNode::CData(text) => text.capacity(),
+            })
+            .sum::<usize>()
+}
+
+impl ExtensionNode {
+    fn write(&self, writer: &mut EventWriter<impl Write>) -> Result<()> {
+        let event: XmlWriteEvent = match self {
+            ExtensionNode::Element(element) => {
+                let mut start = element
+                    .name
+                    .declare(XmlWriteEvent::start_element(element.name.as_name()));
+                for (name, value) in &element.attributes {
+                    start = name.declare(start).attr(name.as_name(), value);
+                }
+                writer.write(start).map_err(Error::XmlEncodingError)?;
+                for child in &element.children {
+                    child.write(writer)?;
+                }
+                XmlWriteEvent::end_element().into()
+            }
+            ExtensionNode::Text(text) => XmlWriteEvent::characters(text),
+            ExtensionNode::CData(text) => XmlWriteEvent::cdata(text),
+        };
+        writer.write(event).map_err(Error::XmlEncodingError)
+    }
+}
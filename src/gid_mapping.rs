@@ -0,0 +1,91 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{TileId, Tileset};
+
+/// Describes how the tiles of a map move when its tilesets are reordered, replaced or merged.
+/// Applied with [`Map::remap_gids()`](crate::Map::remap_gids()).
+///
+/// The crate stores tiles by the index of their tileset within the map and their local id rather
+/// than by GID, so the mapping is expressed in those terms; GIDs are recomputed from the new
+/// tilesets. A mapping holds the new tilesets of the map, and says where the tiles of each old
+/// tileset go, either all at once or one by one. Tiles without a mapping keep their tileset index
+/// and id.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{GidMapping, Loader};
+///
+/// let mut map = Loader::new().load_tmx_map("assets/tiled_multiple_external_tilesets.tmx")?;
+///
+/// // Swap the first two tilesets of the map.
+/// let mut tilesets = map.tilesets().to_vec();
+/// tilesets.swap(0, 1);
+/// let mapping = GidMapping::new(tilesets).tileset(0, 1).tileset(1, 0);
+/// map.remap_gids(&mapping)?;
+///
+/// let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+/// let tile = layer.get_tile(0, 0).unwrap();
+/// assert_eq!(tile.tileset_index(), 1);
+/// assert_eq!(tile.get_tileset().name, "tilesheet");
+/// assert_eq!(tile.gid(), 85);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct GidMapping {
+    tilesets: Vec<Arc<Tileset>>,
+    /// The new index of each old tileset and the offset added to the ids of its tiles.
+    tileset_moves: HashMap<usize, (usize, TileId)>,
+    tile_moves: HashMap<(usize, TileId), (usize, TileId)>,
+}
+
+impl GidMapping {
+    /// Creates a mapping which replaces the tilesets of the map with the ones given, without
+    /// moving any tile.
+    pub fn new(tilesets: Vec<Arc<Tileset>>) -> Self {
+        Self {
+            tilesets,
+            tileset_moves: HashMap::new(),
+            tile_moves: HashMap::new(),
+        }
+    }
+
+    /// Moves the tiles of the old tileset at index `old_index` to the new tileset at index
+    /// `new_index`, keeping their local ids.
+    pub fn tileset(self, old_index: usize, new_index: usize) -> Self {
+        self.merge_tileset(old_index, new_index, 0)
+    }
+
+    /// Moves the tiles of the old tileset at index `old_index` to the new tileset at index
+    /// `new_index`, adding `id_offset` to their local ids, such as when the old tileset was
+    /// appended to another one.
+    pub fn merge_tileset(mut self, old_index: usize, new_index: usize, id_offset: TileId) -> Self {
+        self.tileset_moves.insert(old_index, (new_index, id_offset));
+        self
+    }
+
+    /// Moves a single tile, given by its old tileset index and local id, to the new tileset index
+    /// and local id given. Takes precedence over the mapping of its tileset.
+    pub fn tile(mut self, old: (usize, TileId), new: (usize, TileId)) -> Self {
+        self.tile_moves.insert(old, new);
+        self
+    }
+
+    /// Returns the new tilesets of the map.
+    pub fn tilesets(&self) -> &[Arc<Tileset>] {
+        &self.tilesets
+    }
+
+    /// Returns the new tileset index and local id of the tile with the old tileset index and local
+    /// id given.
+    pub fn get(&self, tileset_index: usize, id: TileId) -> (usize, TileId) {
+        if let Some(&new) = self.tile_moves.get(&(tileset_index, id)) {
+            return new;
+        }
+        match self.tileset_moves.get(&tileset_index) {
+            Some(&(new_index, id_offset)) => (new_index, id + id_offset),
+            None => (tileset_index, id),
+        }
+    }
+}
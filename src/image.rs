@@ -1,7 +1,15 @@
-use std::path::{Path, PathBuf};
+use std::{
+    any::Any,
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use xml::attribute::OwnedAttribute;
+use xml::{attribute::OwnedAttribute, EventWriter};
 
+#[cfg(feature = "image")]
+use crate::ResourceReader;
 use crate::{
     error::{Error, Result},
     properties::Color,
@@ -9,7 +17,8 @@ use crate::{
 };
 
 /// A reference to an image stored somewhere within the filesystem.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Image {
     /// The **uncanonicalized** filepath of the image, starting from the path given to load the file
     /// this image is in. See the example for more details.
@@ -63,10 +72,71 @@ pub struct Image {
     pub width: i32,
     /// The height in pixels of the image.
     pub height: i32,
-    /// A color that should be interpreted as transparent (0 alpha), if any.
+    /// A color that should be interpreted as transparent (0 alpha), if any, read from the `trans`
+    /// attribute of the image.
+    ///
+    /// Pixels of this color are meant to be drawn fully transparent, which renderers can do by
+    /// applying it as a color key once the image is decoded. Only its red, green and blue channels
+    /// are relevant. With the `image` feature enabled, `Image::apply_transparent_colour()` does
+    /// this for images decoded by the [`image`](https://docs.rs/image) crate.
     pub transparent_colour: Option<Color>,
+    /// The value returned for this image by the hook set with
+    /// [`Loader::on_image_loaded()`](crate::Loader::on_image_loaded()), if any, such as the
+    /// handle of a texture.
+    ///
+    /// Handles don't take part in the equality of images, so the same image compares equal
+    /// whether it was loaded with a hook or not.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub handle: Option<ImageHandle>,
+}
+
+impl PartialEq for Image {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+            && self.width == other.width
+            && self.height == other.height
+            && self.transparent_colour == other.transparent_colour
+    }
+}
+
+impl Eq for Image {}
+
+/// A value attached to an [`Image`] by the hook set with
+/// [`Loader::on_image_loaded()`](crate::Loader::on_image_loaded()).
+///
+/// Handles are compared by identity: Two handles are only equal if they are clones of each other.
+#[derive(Clone)]
+pub struct ImageHandle(Arc<dyn Any + Send + Sync>);
+
+impl ImageHandle {
+    /// Wraps a value into a handle.
+    pub fn new<T: Any + Send + Sync>(value: T) -> Self {
+        Self(Arc::new(value))
+    }
+
+    /// Returns the value of the handle if it is of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
 }
 
+impl PartialEq for ImageHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ImageHandle {}
+
+impl fmt::Debug for ImageHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageHandle").finish_non_exhaustive()
+    }
+}
+
+/// The hook set with [`Loader::on_image_loaded()`](crate::Loader::on_image_loaded()).
+pub(crate) type ImageHook = dyn Fn(&Image) -> ImageHandle + Send + Sync;
+
 impl Image {
     pub(crate) fn new(
         parser: &mut impl Iterator<Item = XmlEventResult>,
@@ -85,10 +155,138 @@ impl Image {
 
         parse_tag!(parser, "image", {});
         Ok(Image {
-            source: path_relative_to.as_ref().join(s),
+            source: join_reference(path_relative_to.as_ref(), &s),
             width: w,
             height: h,
             transparent_colour: c,
+            handle: None,
         })
     }
+
+    /// Attaches the handle returned by `hook` to the image, unless it already has one.
+    pub(crate) fn attach_handle(&mut self, hook: &ImageHook) {
+        if self.handle.is_none() {
+            self.handle = Some(hook(self));
+        }
+    }
+}
+
+impl Image {
+    /// Reads an image from the `image`, `imagewidth`, `imageheight` and `transparentcolor` members
+    /// of a JSON object, if it has one.
+    #[cfg(feature = "json")]
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        path_relative_to: impl AsRef<Path>,
+    ) -> Result<Option<Image>> {
+        use crate::parse::json::JsonObjectExt;
+
+        match object.opt_str("image")? {
+            None | Some("") => Ok(None),
+            Some(source) => Ok(Some(Image {
+                source: join_reference(path_relative_to.as_ref(), source),
+                width: object.opt_i32("imagewidth")?.unwrap_or(0),
+                height: object.opt_i32("imageheight")?.unwrap_or(0),
+                transparent_colour: object.opt_color("transparentcolor")?,
+                handle: None,
+            })),
+        }
+    }
+
+    /// Reads the file at [`Image::source`] with `reader` and decodes it with the
+    /// [`image`](https://crates.io/crates/image) crate, then applies the
+    /// [transparent color](Self::transparent_colour) of the image to its pixels.
+    ///
+    /// PNG, JPEG, GIF and BMP files can be decoded. Other formats can be supported by enabling the
+    /// matching features of the `image` crate.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{FilesystemResourceReader, Loader};
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    /// let image = tileset.image.as_ref().unwrap();
+    /// let pixels = image.load_pixels(&mut FilesystemResourceReader)?;
+    /// assert_eq!(pixels.dimensions(), (image.width as u32, image.height as u32));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn load_pixels(&self, reader: &mut impl ResourceReader) -> Result<image_crate::RgbaImage> {
+        use std::io::Read;
+
+        let loading_error = |err| Error::ResourceLoadingError {
+            path: self.source.clone(),
+            err,
+        };
+        let mut resource = reader
+            .read_from(&self.source)
+            .map_err(|err| loading_error(Box::new(err)))?;
+        let mut bytes = Vec::new();
+        resource
+            .read_to_end(&mut bytes)
+            .map_err(|err| loading_error(Box::new(err)))?;
+        let mut pixels = image_crate::load_from_memory(&bytes)
+            .map_err(|err| Error::ImageDecodingError {
+                path: self.source.clone(),
+                err,
+            })?
+            .into_rgba8();
+        self.apply_transparent_colour(&mut pixels);
+        Ok(pixels)
+    }
+
+    /// Makes the pixels of `pixels` matching the [transparent color](Self::transparent_colour) of
+    /// this image fully transparent. Does nothing if the image has no transparent color.
+    ///
+    /// `pixels` is usually the result of decoding the file at [`Image::source`].
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{Color, Image};
+    /// # use image_crate as image;
+    ///
+    /// let tiled_image = Image {
+    ///     source: "sprites.png".into(),
+    ///     width: 2,
+    ///     height: 1,
+    ///     transparent_colour: Some(Color { alpha: 0xff, red: 0xff, green: 0x00, blue: 0xff }),
+    ///     handle: None,
+    /// };
+    /// let mut pixels = image::RgbaImage::from_raw(2, 1, vec![255, 0, 255, 255, 10, 20, 30, 255])
+    ///     .unwrap();
+    /// tiled_image.apply_transparent_colour(&mut pixels);
+    /// assert_eq!(pixels.into_raw(), vec![255, 0, 255, 0, 10, 20, 30, 255]);
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn apply_transparent_colour(&self, pixels: &mut image_crate::RgbaImage) {
+        if let Some(key) = self.transparent_colour {
+            for pixel in pixels.pixels_mut() {
+                let [red, green, blue, _] = pixel.0;
+                if [red, green, blue] == [key.red, key.green, key.blue] {
+                    pixel.0[3] = 0;
+                }
+            }
+        }
+    }
+
+    /// Writes this image as an `<image>` element, with its source relative to `path_relative_to`.
+    pub(crate) fn write_xml(
+        &self,
+        writer: &mut EventWriter<impl Write>,
+        path_relative_to: &Path,
+    ) -> Result<()> {
+        let mut attrs = vec![("source", relative_path(path_relative_to, &self.source))];
+        if let Some(c) = self.transparent_colour {
+            // Tiled stores the transparent color as plain RRGGBB.
+            attrs.push((
+                "trans",
+                format!("{:02x}{:02x}{:02x}", c.red, c.green, c.blue),
+            ));
+        }
+        attrs.push(("width", self.width.to_string()));
+        attrs.push(("height", self.height.to_string()));
+        write_empty_element(writer, "image", &attrs)
+    }
 }
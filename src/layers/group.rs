@@ -1,15 +1,17 @@
-use std::{collections::HashMap, path::Path, sync::Arc};
+use std::collections::HashMap;
 
 use crate::{
     error::Result,
-    layers::{LayerData, LayerTag},
+    layers::{LayerData, LayerInputs, LayerTag},
+    parse::LoadContext,
     properties::{parse_properties, Properties},
     util::*,
-    Error, Layer, MapTilesetGid, ResourceCache, ResourceReader, Tileset,
+    Error, Layer, ResourceCache, ResourceReader,
 };
 
 /// The raw data of a [`GroupLayer`]. Does not include a reference to its parent [`Map`](crate::Map).
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GroupLayerData {
     layers: Vec<LayerData>,
 }
@@ -17,70 +19,30 @@ pub struct GroupLayerData {
 impl GroupLayerData {
     pub(crate) fn new(
         parser: &mut impl Iterator<Item = XmlEventResult>,
-        infinite: bool,
-        map_path: &Path,
-        tilesets: &[MapTilesetGid],
-        for_tileset: Option<Arc<Tileset>>,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        inputs: &LayerInputs,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<(Self, Properties)> {
         let mut properties = HashMap::new();
         let mut layers = Vec::new();
         parse_tag!(parser, "group", {
             "layer" => |attrs| {
-                layers.push(LayerData::new(
-                    parser,
-                    attrs,
-                    LayerTag::Tiles,
-                    infinite,
-                    map_path,
-                    tilesets,
-                    for_tileset.as_ref().cloned(),reader,
-                    cache
-                )?);
+                layers.push(LayerData::new(parser, attrs, LayerTag::Tiles, inputs, ctx)?);
                 Ok(())
             },
             "imagelayer" => |attrs| {
-                layers.push(LayerData::new(
-                    parser,
-                    attrs,
-                    LayerTag::Image,
-                    infinite,
-                    map_path,
-                    tilesets,
-                    for_tileset.as_ref().cloned(),reader,
-                    cache
-                )?);
+                layers.push(LayerData::new(parser, attrs, LayerTag::Image, inputs, ctx)?);
                 Ok(())
             },
             "objectgroup" => |attrs| {
-                layers.push(LayerData::new(
-                    parser,
-                    attrs,
-                    LayerTag::Objects,
-                    infinite,
-                    map_path,
-                    tilesets,
-                    for_tileset.as_ref().cloned(),reader,
-                    cache
-                )?);
+                layers.push(LayerData::new(parser, attrs, LayerTag::Objects, inputs, ctx)?);
                 Ok(())
             },
             "group" => |attrs| {
-                layers.push(LayerData::new(
-                    parser,
-                    attrs,
-                    LayerTag::Group,
-                    infinite,
-                    map_path,
-                    tilesets,
-                    for_tileset.as_ref().cloned(),reader,
-                    cache
-                )?);
+                layers.push(LayerData::new(parser, attrs, LayerTag::Group, inputs, ctx)?);
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, ctx.project, &ctx.interner, ctx.diagnostics)?;
                 Ok(())
             },
         });
@@ -88,6 +50,73 @@ impl GroupLayerData {
     }
 }
 
+#[cfg(feature = "json")]
+impl GroupLayerData {
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        inputs: &LayerInputs,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<Self> {
+        use crate::parse::json::{as_objects, JsonObjectExt};
+
+        let layers = match object.opt_array("layers")? {
+            Some(layers) => as_objects(layers, "layers")?
+                .map(|layer| LayerData::parse_json(layer, inputs, ctx))
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+        Ok(Self { layers })
+    }
+}
+
+impl GroupLayerData {
+    pub(crate) fn layers(&self) -> impl ExactSizeIterator<Item = &LayerData> {
+        self.layers.iter()
+    }
+
+    /// Get an iterator over mutable references to the data of the layers present in this group,
+    /// in display order.
+    #[inline]
+    pub fn layers_mut(&mut self) -> impl ExactSizeIterator<Item = &mut LayerData> {
+        self.layers.iter_mut()
+    }
+
+    /// Returns a mutable reference to the data of the layer that has the specified index within
+    /// this group, if it exists.
+    pub fn get_layer_mut(&mut self, index: usize) -> Option<&mut LayerData> {
+        self.layers.get_mut(index)
+    }
+
+    /// Returns the list of layers of the group for modification.
+    pub(crate) fn layer_vec_mut(&mut self) -> &mut Vec<LayerData> {
+        &mut self.layers
+    }
+
+    /// Appends a layer to the group, placing it above all the others.
+    pub fn add_layer(&mut self, layer: LayerData) {
+        self.layers.push(layer);
+    }
+
+    /// Inserts a layer at the specified index within the group, shifting all the layers above
+    /// it.
+    ///
+    /// ## Panics
+    /// Panics if `index` is greater than the amount of layers in the group.
+    pub fn insert_layer(&mut self, index: usize, layer: LayerData) {
+        self.layers.insert(index, layer);
+    }
+
+    /// Removes the layer that has the specified index within the group and returns it, if it
+    /// exists.
+    pub fn remove_layer(&mut self, index: usize) -> Option<LayerData> {
+        if index < self.layers.len() {
+            Some(self.layers.remove(index))
+        } else {
+            None
+        }
+    }
+}
+
 map_wrapper!(
     #[doc = "A group layer, used to organize the layers of the map in a hierarchy."]
     #[doc = "\nAlso see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#group)."]
@@ -1,23 +1,40 @@
 use std::{collections::HashMap, path::Path};
 
+use xml::attribute::OwnedAttribute;
+
 use crate::{
+    parse::LoadContext,
     parse_properties,
-    util::{map_wrapper, parse_tag, XmlEventResult},
-    Error, Image, Properties, Result,
+    util::{get_attrs, map_wrapper, parse_tag, XmlEventResult},
+    Error, Image, Properties, ResourceCache, ResourceReader, Result,
 };
 
 /// The raw data of an [`ImageLayer`]. Does not include a reference to its parent [`Map`](crate::Map).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ImageLayerData {
     /// The single image this layer contains, if it exists.
     pub image: Option<Image>,
+    /// Whether the image should be repeated horizontally to fill the whole map.
+    pub repeat_x: bool,
+    /// Whether the image should be repeated vertically to fill the whole map.
+    pub repeat_y: bool,
 }
 
 impl ImageLayerData {
     pub(crate) fn new(
         parser: &mut impl Iterator<Item = XmlEventResult>,
+        attrs: &[OwnedAttribute],
         map_path: &Path,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<(Self, Properties)> {
+        let (repeat_x, repeat_y) = get_attrs!(
+            for v in attrs {
+                Some("repeatx") => repeat_x ?= v.parse().map(|x: i32| x == 1),
+                Some("repeaty") => repeat_y ?= v.parse().map(|x: i32| x == 1),
+            }
+            (repeat_x, repeat_y)
+        );
         let mut image: Option<Image> = None;
         let mut properties = HashMap::new();
 
@@ -29,11 +46,43 @@ impl ImageLayerData {
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, ctx.project, &ctx.interner, ctx.diagnostics)?;
                 Ok(())
             },
         });
-        Ok((ImageLayerData { image }, properties))
+        if let (Some(image), Some(hook)) = (&mut image, ctx.image_hook) {
+            image.attach_handle(hook);
+        }
+        Ok((
+            ImageLayerData {
+                image,
+                repeat_x: repeat_x.unwrap_or(false),
+                repeat_y: repeat_y.unwrap_or(false),
+            },
+            properties,
+        ))
+    }
+}
+
+#[cfg(feature = "json")]
+impl ImageLayerData {
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        map_path: &Path,
+        ctx: &LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<Self> {
+        use crate::parse::json::JsonObjectExt;
+
+        let path_relative_to = map_path.parent().ok_or(Error::PathIsNotFile)?;
+        let mut image = Image::parse_json(object, path_relative_to)?;
+        if let (Some(image), Some(hook)) = (&mut image, ctx.image_hook) {
+            image.attach_handle(hook);
+        }
+        Ok(ImageLayerData {
+            image,
+            repeat_x: object.opt_bool("repeatx")?.unwrap_or(false),
+            repeat_y: object.opt_bool("repeaty")?.unwrap_or(false),
+        })
     }
 }
 
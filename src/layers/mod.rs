@@ -1,10 +1,16 @@
-use std::{path::Path, sync::Arc};
+use std::{collections::HashMap, io::Write, path::Path, sync::Arc};
 
-use xml::attribute::OwnedAttribute;
+use xml::{attribute::OwnedAttribute, EventWriter};
 
 use crate::{
-    error::Result, properties::Properties, util::*, Color, Map, MapTilesetGid, ResourceCache,
-    ResourceReader, Tileset,
+    error::Result,
+    parse::LoadContext,
+    properties::{
+        collect_object_references, remap_object_references, write_properties, Properties,
+    },
+    util::*,
+    Color, GidMapping, Map, MapTilesetGid, ObjectData, ResourceCache, ResourceReader, TileId,
+    Tileset, TilesetLocation, WriteOptions,
 };
 
 mod image;
@@ -17,6 +23,7 @@ mod group;
 pub use group::*;
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum LayerDataType {
     Tiles(TileLayerData),
     Objects(ObjectLayerData),
@@ -24,6 +31,18 @@ pub(crate) enum LayerDataType {
     Group(GroupLayerData),
 }
 
+/// What the layers of a map are parsed against, which is the same for all of its layers.
+pub(crate) struct LayerInputs<'a> {
+    /// Whether the map is infinite, which decides how tile layers are read.
+    pub infinite: bool,
+    /// The path of the file the layers are in, which their images are relative to.
+    pub map_path: &'a Path,
+    /// The tilesets of the map that were read before the layer.
+    pub tilesets: &'a [MapTilesetGid],
+    /// The tileset the layers belong to, for the collision layers of tiles.
+    pub for_tileset: Option<Arc<Tileset>>,
+}
+
 #[derive(Clone, Copy)]
 pub(crate) enum LayerTag {
     Tiles,
@@ -34,6 +53,7 @@ pub(crate) enum LayerTag {
 
 /// The raw data of a [`Layer`]. Does not include a reference to its parent [`Map`](crate::Map).
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LayerData {
     /// The layer's name, set arbitrarily by the user.
     pub name: String,
@@ -44,17 +64,31 @@ pub struct LayerData {
     pub offset_x: f32,
     /// The layer's y offset (in pixels).
     pub offset_y: f32,
-    /// The layer's x parallax factor.
+    /// The layer's x parallax factor, 1.0 by default.
+    ///
+    /// The parallax factor determines how fast the layer scrolls relative to the camera: A layer
+    /// with a factor of 0.5 moves at half the speed of the camera, while one with a factor of 0.0
+    /// stays fixed on screen. Tiled applies it by moving the layer by
+    /// `(camera_x - map.parallax_origin_x) * (1.0 - parallax_x)` pixels, where `camera_x` is the
+    /// horizontal position of the center of the camera, so that layers are at their usual position
+    /// when the camera is centered on the map's
+    /// [parallax origin](crate::Map::parallax_origin_x). The factors of group layers multiply with
+    /// the ones of their children.
     pub parallax_x: f32,
-    /// The layer's y parallax factor.
+    /// The layer's y parallax factor, 1.0 by default. See [`LayerData::parallax_x`] for details.
     pub parallax_y: f32,
     /// The layer's opacity.
     pub opacity: f32,
-    /// The layer's tint color.
+    /// The layer's tint color, if any.
+    ///
+    /// The colors of the layer's tiles, objects or image should be multiplied with the tint color,
+    /// as done by [`Color::multiply`]. The tint colors of group layers apply to their children as
+    /// well, multiplied with the children's own tint colors.
     pub tint_color: Option<Color>,
     /// The layer's custom properties, as arbitrarily set by the user.
     pub properties: Properties,
-    /// The layer's type, which is arbitrarily setby the user.
+    /// The layer's class, which is arbitrarily set by the user. Read from the `class` attribute of
+    /// the layer.
     pub user_type: Option<String>,
     layer_type: LayerDataType,
 }
@@ -67,16 +101,526 @@ impl LayerData {
         self.id
     }
 
-    pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
-        attrs: Vec<OwnedAttribute>,
-        tag: LayerTag,
-        infinite: bool,
-        map_path: &Path,
-        tilesets: &[MapTilesetGid],
-        for_tileset: Option<Arc<Tileset>>,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+    /// Creates a new, empty finite tile layer with the specified name and size in tiles.
+    ///
+    /// The layer is visible, has no offset, parallax or tint, full opacity and an id of 0.
+    pub fn new_finite_tile_layer(name: impl Into<String>, width: u32, height: u32) -> Self {
+        Self::with_layer_type(
+            name.into(),
+            LayerDataType::Tiles(TileLayerData::Finite(FiniteTileLayerData::empty(
+                width, height,
+            ))),
+        )
+    }
+
+    /// Creates a new, empty infinite tile layer with the specified name.
+    ///
+    /// The layer is visible, has no offset, parallax or tint, full opacity and an id of 0.
+    pub fn new_infinite_tile_layer(name: impl Into<String>) -> Self {
+        Self::with_layer_type(
+            name.into(),
+            LayerDataType::Tiles(TileLayerData::Infinite(InfiniteTileLayerData::default())),
+        )
+    }
+
+    /// Creates a new object layer with the specified name and no objects.
+    ///
+    /// The layer is visible, has no offset, parallax or tint, full opacity and an id of 0.
+    pub fn new_object_layer(name: impl Into<String>) -> Self {
+        Self::with_layer_type(name.into(), LayerDataType::Objects(Default::default()))
+    }
+
+    /// Creates a new image layer with the specified name and image.
+    ///
+    /// The layer is visible, has no offset, parallax or tint, full opacity and an id of 0.
+    pub fn new_image_layer(name: impl Into<String>, image: Option<crate::Image>) -> Self {
+        Self::with_layer_type(
+            name.into(),
+            LayerDataType::Image(ImageLayerData {
+                image,
+                repeat_x: false,
+                repeat_y: false,
+            }),
+        )
+    }
+
+    /// Creates a new group layer with the specified name and no child layers.
+    ///
+    /// The layer is visible, has no offset, parallax or tint, full opacity and an id of 0.
+    pub fn new_group_layer(name: impl Into<String>) -> Self {
+        Self::with_layer_type(name.into(), LayerDataType::Group(Default::default()))
+    }
+
+    /// Checks that the layer and its children can be part of a map with the specified amount of
+    /// tilesets and infiniteness.
+    pub(crate) fn validate(&self, tileset_count: usize, infinite: bool) -> Result<()> {
+        let tiles: Box<dyn Iterator<Item = LayerTileData>> = match &self.layer_type {
+            LayerDataType::Tiles(TileLayerData::Finite(_)) if infinite => {
+                return Err(crate::Error::MalformedAttributes(format!(
+                    "Finite tile layer '{}' in an infinite map",
+                    self.name
+                )))
+            }
+            LayerDataType::Tiles(TileLayerData::Infinite(_)) if !infinite => {
+                return Err(crate::Error::MalformedAttributes(format!(
+                    "Infinite tile layer '{}' in a finite map",
+                    self.name
+                )))
+            }
+            LayerDataType::Tiles(TileLayerData::Finite(data)) => Box::new(data.tile_data()),
+            LayerDataType::Tiles(TileLayerData::Infinite(data)) => {
+                Box::new(data.tile_data().copied())
+            }
+            LayerDataType::Group(group) => {
+                for layer in group.layers() {
+                    layer.validate(tileset_count, infinite)?;
+                }
+                return Ok(());
+            }
+            LayerDataType::Objects(layer) => {
+                for object in layer.object_data() {
+                    if let Some(tile) = object.tile_data() {
+                        match tile.tileset_location() {
+                            TilesetLocation::Map(index) if *index >= tileset_count => {
+                                return Err(crate::Error::InvalidTileFound)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            LayerDataType::Image(_) => return Ok(()),
+        };
+        for tile in tiles {
+            if tile.tileset_index() >= tileset_count {
+                return Err(crate::Error::InvalidTileFound);
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves every tile of the layer and its children, including those of tile objects, according
+    /// to `mapping`.
+    pub(crate) fn remap_tiles(&mut self, mapping: &GidMapping) {
+        match &mut self.layer_type {
+            LayerDataType::Tiles(TileLayerData::Finite(data)) => data.remap_tiles(mapping),
+            LayerDataType::Tiles(TileLayerData::Infinite(data)) => data.remap_tiles(mapping),
+            LayerDataType::Objects(layer) => {
+                for object in layer.object_data_mut() {
+                    object.remap_tile(mapping);
+                }
+            }
+            LayerDataType::Group(group) => {
+                for layer in group.layers_mut() {
+                    layer.remap_tiles(mapping);
+                }
+            }
+            LayerDataType::Image(_) => {}
+        }
+    }
+
+    /// Gives an id to the layer and its children if they don't have one, starting with `next_id`
+    /// and incrementing it for each layer.
+    pub(crate) fn assign_ids(&mut self, next_id: &mut u32) {
+        if self.id == 0 {
+            self.id = *next_id;
+            *next_id += 1;
+        }
+        if let Some(group) = self.as_group_layer_mut() {
+            for layer in group.layers_mut() {
+                layer.assign_ids(next_id);
+            }
+        }
+    }
+
+    /// Moves the layer and its children by `offset` in tiles, or `pixel_offset` in pixels for their
+    /// objects and images. Tile layers become finite with the size given, or infinite if there is
+    /// none, dropping the tiles that end up outside of them.
+    pub(crate) fn translate(
+        &mut self,
+        offset: (i32, i32),
+        pixel_offset: (f32, f32),
+        size: Option<(u32, u32)>,
+    ) {
+        match &mut self.layer_type {
+            LayerDataType::Tiles(data) => *data = data.translated(offset, size),
+            LayerDataType::Objects(layer) => {
+                for object in layer.object_data_mut() {
+                    object.x += pixel_offset.0;
+                    object.y += pixel_offset.1;
+                }
+            }
+            LayerDataType::Image(_) => {
+                self.offset_x += pixel_offset.0;
+                self.offset_y += pixel_offset.1;
+            }
+            LayerDataType::Group(group) => {
+                for layer in group.layers_mut() {
+                    layer.translate(offset, pixel_offset, size);
+                }
+            }
+        }
+    }
+
+    /// Keeps only the objects of the layer and its children for which `keep` returns `true`.
+    pub(crate) fn retain_objects(&mut self, keep: &mut impl FnMut(&ObjectData) -> bool) {
+        match &mut self.layer_type {
+            LayerDataType::Objects(layer) => layer.retain_objects(keep),
+            LayerDataType::Group(group) => {
+                for layer in group.layers_mut() {
+                    layer.retain_objects(keep);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Calls `f` with the tileset index and local id of each tile of the layer and its children,
+    /// including the tiles of tile objects whose tileset belongs to the map.
+    pub(crate) fn for_each_tile(&self, f: &mut impl FnMut(usize, TileId)) {
+        match &self.layer_type {
+            LayerDataType::Tiles(TileLayerData::Finite(data)) => {
+                for tile in data.tile_data() {
+                    f(tile.tileset_index(), tile.id());
+                }
+            }
+            LayerDataType::Tiles(TileLayerData::Infinite(data)) => {
+                for tile in data.tile_data() {
+                    f(tile.tileset_index(), tile.id());
+                }
+            }
+            LayerDataType::Objects(layer) => {
+                for tile in layer.object_data().iter().filter_map(ObjectData::tile_data) {
+                    if let TilesetLocation::Map(index) = tile.tileset_location() {
+                        f(*index, tile.id());
+                    }
+                }
+            }
+            LayerDataType::Group(group) => {
+                for layer in group.layers() {
+                    layer.for_each_tile(f);
+                }
+            }
+            LayerDataType::Image(_) => {}
+        }
+    }
+
+    /// Gives new ids to the layer, its children and their objects, starting with `next_layer_id`
+    /// and `next_object_id`, and updates the object properties referring to the objects. Objects
+    /// without an id are left without one.
+    pub(crate) fn renumber(&mut self, next_layer_id: &mut u32, next_object_id: &mut u32) {
+        let mut object_ids = HashMap::new();
+        self.renumber_ids(next_layer_id, next_object_id, &mut object_ids);
+        self.remap_object_references(&object_ids);
+    }
+
+    fn renumber_ids(
+        &mut self,
+        next_layer_id: &mut u32,
+        next_object_id: &mut u32,
+        object_ids: &mut HashMap<u32, u32>,
+    ) {
+        self.id = *next_layer_id;
+        *next_layer_id += 1;
+        match &mut self.layer_type {
+            LayerDataType::Objects(layer) => {
+                for object in layer.object_data_mut() {
+                    if object.id() != 0 {
+                        object_ids.insert(object.id(), *next_object_id);
+                        object.set_id(*next_object_id);
+                        *next_object_id += 1;
+                    }
+                }
+            }
+            LayerDataType::Group(group) => {
+                for layer in group.layers_mut() {
+                    layer.renumber_ids(next_layer_id, next_object_id, object_ids);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn remap_object_references(&mut self, object_ids: &HashMap<u32, u32>) {
+        remap_object_references(&mut self.properties, object_ids);
+        match &mut self.layer_type {
+            LayerDataType::Objects(layer) => {
+                for object in layer.object_data_mut() {
+                    remap_object_references(&mut object.properties, object_ids);
+                }
+            }
+            LayerDataType::Group(group) => {
+                for layer in group.layers_mut() {
+                    layer.remap_object_references(object_ids);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the greatest id among the objects of the layer and its children.
+    pub(crate) fn max_object_id(&self) -> u32 {
+        match &self.layer_type {
+            LayerDataType::Objects(layer) => layer
+                .object_data()
+                .iter()
+                .map(ObjectData::id)
+                .max()
+                .unwrap_or(0),
+            LayerDataType::Group(group) => group
+                .layers()
+                .map(LayerData::max_object_id)
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Returns the greatest id among the layer and its children.
+    pub(crate) fn max_id(&self) -> u32 {
+        match &self.layer_type {
+            LayerDataType::Group(group) => group
+                .layers()
+                .map(LayerData::max_id)
+                .fold(self.id, u32::max),
+            _ => self.id,
+        }
+    }
+
+    /// Returns the data of the object that has the specified id in this layer or its children, if
+    /// any, along with the data of the object layer containing it.
+    pub(crate) fn find_object(&self, id: u32) -> Option<(&LayerData, &ObjectData)> {
+        match &self.layer_type {
+            LayerDataType::Objects(layer) => layer
+                .object_data()
+                .iter()
+                .find(|object| object.id() == id)
+                .map(|object| (self, object)),
+            LayerDataType::Group(group) => group.layers().find_map(|layer| layer.find_object(id)),
+            _ => None,
+        }
+    }
+
+    /// Returns this layer or the one among its children that has the specified id, if any.
+    pub(crate) fn find_layer(&self, id: u32) -> Option<&LayerData> {
+        if self.id == id {
+            return Some(self);
+        }
+        match &self.layer_type {
+            LayerDataType::Group(group) => group.layers().find_map(|layer| layer.find_layer(id)),
+            _ => None,
+        }
+    }
+
+    /// Returns this layer or the one among its children which has the specified id, if any, for
+    /// modification.
+    pub(crate) fn find_layer_mut(&mut self, id: u32) -> Option<&mut LayerData> {
+        if self.id == id {
+            return Some(self);
+        }
+        match &mut self.layer_type {
+            LayerDataType::Group(group) => group
+                .layers_mut()
+                .find_map(|layer| layer.find_layer_mut(id)),
+            _ => None,
+        }
+    }
+
+    /// Returns this layer if its name is the first of `names`, or the layer the remaining names
+    /// lead to within its children.
+    pub(crate) fn find_by_path(&self, names: &[&str]) -> Option<&LayerData> {
+        let (name, names) = names.split_first()?;
+        if self.name != *name {
+            return None;
+        }
+        if names.is_empty() {
+            return Some(self);
+        }
+        match &self.layer_type {
+            LayerDataType::Group(group) => {
+                group.layers().find_map(|layer| layer.find_by_path(names))
+            }
+            _ => None,
+        }
+    }
+
+    /// Adds the object references held by the properties of the layer, its objects and its
+    /// children to `references`.
+    pub(crate) fn collect_object_references(&self, references: &mut Vec<(String, u32)>) {
+        collect_object_references(&self.properties, references);
+        match &self.layer_type {
+            LayerDataType::Objects(layer) => {
+                for object in layer.object_data() {
+                    collect_object_references(&object.properties, references);
+                }
+            }
+            LayerDataType::Group(group) => {
+                for layer in group.layers() {
+                    layer.collect_object_references(references);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Writes the layer and its children as the elements matching their types, the way Tiled
+    /// does. Paths are written relative to `path_relative_to`.
+    pub(crate) fn write_xml(
+        &self,
+        writer: &mut EventWriter<impl Write>,
+        map: &Map,
+        path_relative_to: &Path,
+        options: WriteOptions,
+    ) -> Result<()> {
+        let mut attrs = Vec::new();
+        if self.id != 0 {
+            attrs.push(("id", self.id.to_string()));
+        }
+        if !self.name.is_empty() {
+            attrs.push(("name", self.name.clone()));
+        }
+        if let Some(user_type) = &self.user_type {
+            attrs.push(("class", user_type.clone()));
+        }
+        if let LayerDataType::Tiles(data) = &self.layer_type {
+            let (width, height) = match data {
+                TileLayerData::Finite(data) => (data.width(), data.height()),
+                TileLayerData::Infinite(_) => (map.width, map.height),
+            };
+            attrs.push(("width", width.to_string()));
+            attrs.push(("height", height.to_string()));
+        }
+        if !self.visible {
+            attrs.push(("visible", "0".to_owned()));
+        }
+        if self.opacity != 1.0 {
+            attrs.push(("opacity", self.opacity.to_string()));
+        }
+        if let Some(tint_color) = self.tint_color {
+            attrs.push(("tintcolor", tint_color.to_short_string()));
+        }
+        if self.offset_x != 0.0 || self.offset_y != 0.0 {
+            attrs.push(("offsetx", self.offset_x.to_string()));
+            attrs.push(("offsety", self.offset_y.to_string()));
+        }
+        if self.parallax_x != 1.0 {
+            attrs.push(("parallaxx", self.parallax_x.to_string()));
+        }
+        if self.parallax_y != 1.0 {
+            attrs.push(("parallaxy", self.parallax_y.to_string()));
+        }
+
+        match &self.layer_type {
+            LayerDataType::Tiles(data) => {
+                write_start_element(writer, "layer", &attrs)?;
+                write_properties(writer, &self.properties)?;
+                match data {
+                    TileLayerData::Finite(data) => {
+                        data.write_xml(writer, map.first_gids(), options)?
+                    }
+                    TileLayerData::Infinite(data) => {
+                        data.write_xml(writer, map.first_gids(), options)?
+                    }
+                }
+                write_end_element(writer)
+            }
+            LayerDataType::Objects(data) => data.write_xml(
+                writer,
+                attrs,
+                map.first_gids(),
+                path_relative_to,
+                |writer| write_properties(writer, &self.properties),
+            ),
+            LayerDataType::Image(data) => {
+                if data.repeat_x {
+                    attrs.push(("repeatx", "1".to_owned()));
+                }
+                if data.repeat_y {
+                    attrs.push(("repeaty", "1".to_owned()));
+                }
+                write_start_element(writer, "imagelayer", &attrs)?;
+                if let Some(image) = &data.image {
+                    image.write_xml(writer, path_relative_to)?;
+                }
+                write_properties(writer, &self.properties)?;
+                write_end_element(writer)
+            }
+            LayerDataType::Group(data) => {
+                write_start_element(writer, "group", &attrs)?;
+                write_properties(writer, &self.properties)?;
+                for layer in data.layers() {
+                    layer.write_xml(writer, map, path_relative_to, options)?;
+                }
+                write_end_element(writer)
+            }
+        }
+    }
+
+    fn with_layer_type(name: String, layer_type: LayerDataType) -> Self {
+        Self {
+            name,
+            id: 0,
+            visible: true,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            parallax_x: 1.0,
+            parallax_y: 1.0,
+            opacity: 1.0,
+            tint_color: None,
+            properties: Properties::new(),
+            user_type: None,
+            layer_type,
+        }
+    }
+
+    /// Returns a mutable reference to this layer's data as a finite tile layer, only if it is
+    /// one.
+    pub fn as_finite_tile_layer_mut(&mut self) -> Option<&mut FiniteTileLayerData> {
+        match &mut self.layer_type {
+            LayerDataType::Tiles(TileLayerData::Finite(x)) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to this layer's data as an infinite tile layer, only if it is
+    /// one.
+    pub fn as_infinite_tile_layer_mut(&mut self) -> Option<&mut InfiniteTileLayerData> {
+        match &mut self.layer_type {
+            LayerDataType::Tiles(TileLayerData::Infinite(x)) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to this layer's data as an object group, only if it is one.
+    pub fn as_object_layer_mut(&mut self) -> Option<&mut ObjectLayerData> {
+        match &mut self.layer_type {
+            LayerDataType::Objects(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to this layer's data as an image layer, only if it is one.
+    pub fn as_image_layer_mut(&mut self) -> Option<&mut ImageLayerData> {
+        match &mut self.layer_type {
+            LayerDataType::Image(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to this layer's data as a group layer, only if it is one.
+    pub fn as_group_layer_mut(&mut self) -> Option<&mut GroupLayerData> {
+        match &mut self.layer_type {
+            LayerDataType::Group(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Creates a layer of the type given from the attributes of its element, without any
+    /// property.
+    pub(crate) fn from_xml_attrs(
+        attrs: &[OwnedAttribute],
+        layer_type: LayerDataType,
     ) -> Result<Self> {
         let (
             opacity,
@@ -107,54 +651,124 @@ impl LayerData {
             (opacity, tint_color, visible, offset_x, offset_y, parallax_x, parallax_y, name, id, user_type, user_class)
         );
 
+        Ok(Self {
+            visible: visible.unwrap_or(true),
+            offset_x: offset_x.unwrap_or(0.0),
+            offset_y: offset_y.unwrap_or(0.0),
+            parallax_x: parallax_x.unwrap_or(1.0),
+            parallax_y: parallax_y.unwrap_or(1.0),
+            opacity: opacity.unwrap_or(1.0),
+            tint_color,
+            name: name.unwrap_or_default(),
+            id: id.unwrap_or(0),
+            user_type: user_type.or(user_class),
+            properties: Properties::new(),
+            layer_type,
+        })
+    }
+
+    pub(crate) fn new(
+        parser: &mut impl Iterator<Item = XmlEventResult>,
+        attrs: Vec<OwnedAttribute>,
+        tag: LayerTag,
+        inputs: &LayerInputs,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<Self> {
+        let map_path = inputs.map_path;
+        // Parsed first, so that errors in the attributes are reported before those in the contents.
+        let header = Self::from_xml_attrs(&attrs, LayerDataType::Group(Default::default()))?;
         let (ty, properties) = match tag {
             LayerTag::Tiles => {
-                let (ty, properties) = TileLayerData::new(parser, attrs, infinite, tilesets)?;
+                let (ty, properties) =
+                    TileLayerData::new(parser, attrs, inputs.infinite, inputs.tilesets, ctx)?;
                 (LayerDataType::Tiles(ty), properties)
             }
             LayerTag::Objects => {
                 let (ty, properties) = ObjectLayerData::new(
                     parser,
                     attrs,
-                    Some(tilesets),
-                    for_tileset,
+                    Some(inputs.tilesets),
+                    inputs.for_tileset.clone(),
                     map_path.parent().ok_or(crate::Error::PathIsNotFile)?,
-                    reader,
-                    cache,
+                    ctx,
                 )?;
                 (LayerDataType::Objects(ty), properties)
             }
             LayerTag::Image => {
-                let (ty, properties) = ImageLayerData::new(parser, map_path)?;
+                let (ty, properties) = ImageLayerData::new(parser, &attrs, map_path, ctx)?;
                 (LayerDataType::Image(ty), properties)
             }
             LayerTag::Group => {
-                let (ty, properties) = GroupLayerData::new(
-                    parser,
-                    infinite,
-                    map_path,
-                    tilesets,
-                    for_tileset,
-                    reader,
-                    cache,
-                )?;
+                let (ty, properties) =
+                    ctx.in_group(|ctx| GroupLayerData::new(parser, inputs, ctx))?;
                 (LayerDataType::Group(ty), properties)
             }
         };
 
         Ok(Self {
-            visible: visible.unwrap_or(true),
-            offset_x: offset_x.unwrap_or(0.0),
-            offset_y: offset_y.unwrap_or(0.0),
-            parallax_x: parallax_x.unwrap_or(1.0),
-            parallax_y: parallax_y.unwrap_or(1.0),
-            opacity: opacity.unwrap_or(1.0),
-            tint_color,
-            name: name.unwrap_or_default(),
-            id: id.unwrap_or(0),
-            user_type: user_type.or(user_class),
             properties,
             layer_type: ty,
+            ..header
+        })
+    }
+}
+
+#[cfg(feature = "json")]
+impl LayerData {
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        inputs: &LayerInputs,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<Self> {
+        use crate::parse::json::JsonObjectExt;
+
+        let map_path = inputs.map_path;
+        let layer_type = match object.req("type", serde_json::Value::as_str)? {
+            "tilelayer" => LayerDataType::Tiles(TileLayerData::parse_json(
+                object,
+                inputs.infinite,
+                inputs.tilesets,
+                ctx.tile_data_options(),
+            )?),
+            "objectgroup" => LayerDataType::Objects(ObjectLayerData::parse_json(
+                object,
+                Some(inputs.tilesets),
+                inputs.for_tileset.clone(),
+                map_path.parent().ok_or(crate::Error::PathIsNotFile)?,
+                ctx,
+            )?),
+            "imagelayer" => {
+                LayerDataType::Image(ImageLayerData::parse_json(object, map_path, ctx)?)
+            }
+            "group" => LayerDataType::Group(
+                ctx.in_group(|ctx| GroupLayerData::parse_json(object, inputs, ctx))?,
+            ),
+            other => {
+                return Err(crate::Error::MalformedAttributes(format!(
+                    "Unknown layer type '{}'",
+                    other
+                )))
+            }
+        };
+
+        Ok(Self {
+            visible: object.opt_bool("visible")?.unwrap_or(true),
+            offset_x: object.opt_f32("offsetx")?.unwrap_or(0.0),
+            offset_y: object.opt_f32("offsety")?.unwrap_or(0.0),
+            parallax_x: object.opt_f32("parallaxx")?.unwrap_or(1.0),
+            parallax_y: object.opt_f32("parallaxy")?.unwrap_or(1.0),
+            opacity: object.opt_f32("opacity")?.unwrap_or(1.0),
+            tint_color: object.opt_color("tintcolor")?,
+            name: object.opt_str("name")?.unwrap_or_default().to_owned(),
+            id: object.opt_u32("id")?.unwrap_or(0),
+            user_type: object.opt_str("class")?.map(str::to_owned),
+            properties: crate::properties::parse_json_properties(
+                object,
+                ctx.project,
+                &ctx.interner,
+                ctx.diagnostics,
+            )?,
+            layer_type,
         })
     }
 }
@@ -165,6 +779,25 @@ map_wrapper!(
 );
 
 impl<'map> Layer<'map> {
+    /// Returns the pixel position of the top-left corner of the tile at the position given within
+    /// this layer, treating the map as orthogonal and taking the layer's offset into account.
+    ///
+    /// ## Note
+    /// The offsets of the group layers containing this layer aren't taken into account.
+    pub fn tile_to_pixel(&self, x: i32, y: i32) -> (f32, f32) {
+        let (x, y) = self.map.tile_to_pixel(x, y);
+        (x + self.offset_x, y + self.offset_y)
+    }
+
+    /// Returns the position of the tile within this layer containing the pixel position given,
+    /// treating the map as orthogonal and taking the layer's offset into account.
+    ///
+    /// ## Note
+    /// The offsets of the group layers containing this layer aren't taken into account.
+    pub fn pixel_to_tile(&self, x: f32, y: f32) -> (i32, i32) {
+        self.map.pixel_to_tile(x - self.offset_x, y - self.offset_y)
+    }
+
     /// Get the layer's type.
     #[inline]
     pub fn layer_type(&self) -> LayerType<'map> {
@@ -240,6 +873,80 @@ impl<'map> Layer<'map> {
     }
 }
 
+/// A layer that isn't a group layer, along with the values it ends up with once those of the group
+/// layers containing it are applied. Returned by [`Map::flattened_layers()`].
+///
+/// The layer's own values are still available through [`FlattenedLayer::layer`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FlattenedLayer<'map> {
+    /// The layer itself.
+    pub layer: Layer<'map>,
+    /// The horizontal offset of the layer in pixels, summed with the ones of its ancestors.
+    pub offset_x: f32,
+    /// The vertical offset of the layer in pixels, summed with the ones of its ancestors.
+    pub offset_y: f32,
+    /// The opacity of the layer, multiplied with the ones of its ancestors.
+    pub opacity: f32,
+    /// The tint color of the layer, multiplied with the ones of its ancestors, if any of them has
+    /// one.
+    pub tint_color: Option<Color>,
+    /// The horizontal parallax factor of the layer, multiplied with the ones of its ancestors.
+    pub parallax_x: f32,
+    /// The vertical parallax factor of the layer, multiplied with the ones of its ancestors.
+    pub parallax_y: f32,
+    /// Whether the layer and all of its ancestors are visible.
+    pub visible: bool,
+}
+
+impl<'map> FlattenedLayer<'map> {
+    /// Applies the values of `parent`, if any, to `layer`.
+    fn new(layer: Layer<'map>, parent: Option<&FlattenedLayer<'map>>) -> Self {
+        let own = FlattenedLayer {
+            layer,
+            offset_x: layer.offset_x,
+            offset_y: layer.offset_y,
+            opacity: layer.opacity,
+            tint_color: layer.tint_color,
+            parallax_x: layer.parallax_x,
+            parallax_y: layer.parallax_y,
+            visible: layer.visible,
+        };
+        match parent {
+            None => own,
+            Some(parent) => FlattenedLayer {
+                offset_x: parent.offset_x + own.offset_x,
+                offset_y: parent.offset_y + own.offset_y,
+                opacity: parent.opacity * own.opacity,
+                tint_color: match (parent.tint_color, own.tint_color) {
+                    (Some(a), Some(b)) => Some(a.multiply(b)),
+                    (a, b) => a.or(b),
+                },
+                parallax_x: parent.parallax_x * own.parallax_x,
+                parallax_y: parent.parallax_y * own.parallax_y,
+                visible: parent.visible && own.visible,
+                ..own
+            },
+        }
+    }
+
+    /// Pushes `layer` to `layers`, or the layers it contains if it is a group layer.
+    pub(crate) fn flatten(
+        layer: Layer<'map>,
+        parent: Option<&FlattenedLayer<'map>>,
+        layers: &mut Vec<FlattenedLayer<'map>>,
+    ) {
+        let flattened = FlattenedLayer::new(layer, parent);
+        match layer.as_group_layer() {
+            Some(group) => {
+                for child in group.layers() {
+                    FlattenedLayer::flatten(child, Some(&flattened), layers);
+                }
+            }
+            None => layers.push(flattened),
+        }
+    }
+}
+
 /// Represents some kind of map layer.
 #[derive(Debug)]
 pub enum LayerType<'map> {
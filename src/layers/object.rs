@@ -1,16 +1,20 @@
-use std::{collections::HashMap, path::Path, sync::Arc};
+use std::{collections::HashMap, io::Write, path::Path, sync::Arc};
 
-use xml::attribute::OwnedAttribute;
+use xml::{attribute::OwnedAttribute, EventWriter};
 
 use crate::{
+    parse::LoadContext,
     parse_properties,
-    util::{get_attrs, map_wrapper, parse_tag, XmlEventResult},
-    Color, Error, MapTilesetGid, Object, ObjectData, Properties, ResourceCache, ResourceReader,
-    Result, Tileset,
+    util::{
+        get_attrs, map_wrapper, parse_tag, write_end_element, write_start_element, XmlEventResult,
+    },
+    Color, Error, Gid, MapTilesetGid, Object, ObjectData, Properties, ResourceCache,
+    ResourceReader, Result, Tileset,
 };
 
 /// Raw data referring to a map object layer or tile collision data.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ObjectLayerData {
     objects: Vec<ObjectData>,
     /// The color used in the editor to display objects in this layer.
@@ -27,8 +31,7 @@ impl ObjectLayerData {
         for_tileset: Option<Arc<Tileset>>,
         // path_relative_to is a directory to which all other files are relative to
         path_relative_to: &Path,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<(ObjectLayerData, Properties)> {
         let c = get_attrs!(
             for v in attrs {
@@ -40,23 +43,102 @@ impl ObjectLayerData {
         let mut properties = HashMap::new();
         parse_tag!(parser, "objectgroup", {
             "object" => |attrs| {
-                objects.push(ObjectData::new(parser, attrs, tilesets, for_tileset.as_ref().cloned(), path_relative_to, reader, cache)?);
+                objects.push(ObjectData::new(parser, attrs, tilesets, for_tileset.as_ref().cloned(), path_relative_to, ctx)?);
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, ctx.project, &ctx.interner, ctx.diagnostics)?;
                 Ok(())
             },
         });
         Ok((ObjectLayerData { objects, colour: c }, properties))
     }
 
+    #[cfg(feature = "json")]
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        tilesets: Option<&[MapTilesetGid]>,
+        for_tileset: Option<Arc<Tileset>>,
+        path_relative_to: &Path,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<ObjectLayerData> {
+        use crate::parse::json::{as_objects, JsonObjectExt};
+
+        let objects = match object.opt_array("objects")? {
+            Some(objects) => as_objects(objects, "objects")?
+                .map(|object| {
+                    ObjectData::parse_json(
+                        object,
+                        tilesets,
+                        for_tileset.clone(),
+                        path_relative_to,
+                        ctx,
+                    )
+                })
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+        Ok(ObjectLayerData {
+            objects,
+            colour: object.opt_color("color")?,
+        })
+    }
+
+    /// Writes an `<objectgroup>` element containing this layer's objects. `attrs` are the attributes
+    /// of the element other than the color, and `write_children` is called right after the element
+    /// is opened, before the objects are written. Paths are written relative to
+    /// `path_relative_to`.
+    pub(crate) fn write_xml<W: Write>(
+        &self,
+        writer: &mut EventWriter<W>,
+        mut attrs: Vec<(&str, String)>,
+        first_gids: &[Gid],
+        path_relative_to: &Path,
+        write_children: impl FnOnce(&mut EventWriter<W>) -> Result<()>,
+    ) -> Result<()> {
+        if let Some(colour) = self.colour {
+            attrs.insert(0, ("color", colour.to_short_string()));
+        }
+        write_start_element(writer, "objectgroup", &attrs)?;
+        write_children(writer)?;
+        for object in &self.objects {
+            object.write_xml(writer, first_gids, path_relative_to)?;
+        }
+        write_end_element(writer)
+    }
+
     /// Returns the data belonging to the objects contained within the layer, in the order they were
     /// declared in the TMX file.
     #[inline]
     pub fn object_data(&self) -> &[ObjectData] {
         self.objects.as_ref()
     }
+
+    /// Returns mutable references to the data belonging to the objects contained within the layer.
+    #[inline]
+    pub fn object_data_mut(&mut self) -> &mut [ObjectData] {
+        self.objects.as_mut()
+    }
+
+    /// Appends an object to the layer.
+    pub fn add_object(&mut self, object: ObjectData) {
+        self.objects.push(object);
+    }
+
+    /// Keeps only the objects for which `keep` returns `true`.
+    pub(crate) fn retain_objects(&mut self, keep: &mut impl FnMut(&ObjectData) -> bool) {
+        self.objects.retain(|object| keep(object));
+    }
+
+    /// Removes the object that has the specified index within the layer and returns it, if it
+    /// exists.
+    pub fn remove_object(&mut self, index: usize) -> Option<ObjectData> {
+        if index < self.objects.len() {
+            Some(self.objects.remove(index))
+        } else {
+            None
+        }
+    }
 }
 
 map_wrapper!(
@@ -92,7 +174,7 @@ impl<'map> ObjectLayer<'map> {
     ///         _ => None,
     ///     })
     ///     .flat_map(|layer| layer.objects())
-    ///     .filter(|object| object.user_type == "spawn")
+    ///     .filter(|object| object.user_type.as_deref() == Some("spawn"))
     ///     .collect();
     ///
     /// dbg!(spawnpoints);
@@ -106,4 +188,25 @@ impl<'map> ObjectLayer<'map> {
             .iter()
             .map(move |object| Object::new(map, object))
     }
+
+    /// Returns an iterator over the objects of this layer whose
+    /// [class](crate::ObjectData::user_type) is the one given, in the order they were declared in.
+    #[inline]
+    pub fn objects_with_class<'a>(&self, class: &'a str) -> impl Iterator<Item = Object<'map>> + 'a
+    where
+        'map: 'a,
+    {
+        self.objects()
+            .filter(move |object| object.user_type.as_deref() == Some(class))
+    }
+
+    /// Returns an iterator over the objects of this layer that have the name given, in the order
+    /// they were declared in.
+    #[inline]
+    pub fn objects_with_name<'a>(&self, name: &'a str) -> impl Iterator<Item = Object<'map>> + 'a
+    where
+        'map: 'a,
+    {
+        self.objects().filter(move |object| object.name == name)
+    }
 }
@@ -0,0 +1,38 @@
+//! A faster parser for the CSV data of tile layers, used when the `fast-csv` feature is enabled.
+//!
+//! It is a plain scalar parser that reads the data in a single pass, instead of splitting it and
+//! parsing each GID separately. It only handles well-formed data, returning `None` otherwise so
+//! that the regular parser can report the error.
+
+/// Parses comma-separated GIDs, which may be surrounded by whitespace, in a single pass over the
+/// bytes.
+pub(crate) fn decode_csv(data: &str) -> Option<Vec<u32>> {
+    let mut gids = Vec::with_capacity(data.len() / 2);
+    let mut gid = 0u64;
+    let mut digits = 0;
+    let mut ended = false;
+    for &byte in data.as_bytes() {
+        match byte {
+            b'0'..=b'9' if !ended => {
+                gid = gid * 10 + u64::from(byte - b'0');
+                if gid > u64::from(u32::MAX) {
+                    return None;
+                }
+                digits += 1;
+            }
+            b',' if digits > 0 => {
+                gids.push(gid as u32);
+                gid = 0;
+                digits = 0;
+                ended = false;
+            }
+            b' ' | b'\t' | b'\n' | b'\r' => ended = digits > 0,
+            _ => return None,
+        }
+    }
+    if digits == 0 {
+        return None;
+    }
+    gids.push(gid as u32);
+    Some(gids)
+}
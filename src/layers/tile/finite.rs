@@ -1,11 +1,22 @@
-use xml::attribute::OwnedAttribute;
+use std::{io::Write, sync::OnceLock};
+
+use xml::{attribute::OwnedAttribute, EventWriter};
 
 use crate::{
-    util::{get_attrs, map_wrapper, XmlEventResult},
-    LayerTile, LayerTileData, MapTilesetGid, Result,
+    util::{
+        get_attrs, map_wrapper, write_characters, write_end_element, write_start_element,
+        XmlEventResult,
+    },
+    write::SourceInfo,
+    Gid, GidMapping, LayerTile, LayerTileData, MapTilesetGid, Result, WriteOptions,
 };
 
-use super::util::parse_data_line;
+#[cfg(feature = "json")]
+use super::util::decode_json_gids;
+use super::util::{
+    check_gids, decode_data_gids, gids_to_tiles, read_data_line, DataEncoding, DataWriter,
+    EncodedTiles, LazyTiles, SourceEncoding, TileDataOptions,
+};
 
 /// The raw data of a [`FiniteTileLayer`]. Does not include a reference to its parent [`Map`](crate::Map).
 #[derive(PartialEq, Clone, Default)]
@@ -13,7 +24,167 @@ pub struct FiniteTileLayerData {
     width: u32,
     height: u32,
     /// The tiles are arranged in rows.
-    tiles: Vec<Option<LayerTileData>>,
+    tiles: LazyTiles<EncodedFiniteData>,
+    source: SourceInfo<SourceEncoding>,
+}
+
+/// The decoded tiles of a finite tile layer.
+#[derive(Clone)]
+pub(crate) enum FiniteTiles {
+    /// Every tile already resolved to the tileset it belongs to.
+    Full(Vec<Option<LayerTileData>>),
+    Compact(Box<CompactTiles>),
+}
+
+/// The raw GIDs of the tiles of a layer, resolved as they are accessed. Resolving all of them at
+/// once is only needed to hand out references to them, see
+/// [`FiniteTileLayerData::get_tile_data()`].
+#[derive(Clone)]
+pub(crate) struct CompactTiles {
+    gids: Vec<u32>,
+    tilesets: Vec<MapTilesetGid>,
+    resolved: OnceLock<Vec<Option<LayerTileData>>>,
+}
+
+impl CompactTiles {
+    fn resolve(&self) -> Vec<Option<LayerTileData>> {
+        gids_to_tiles(&self.gids, &self.tilesets)
+    }
+}
+
+impl FiniteTiles {
+    fn from_gids(gids: Vec<u32>, tilesets: &[MapTilesetGid], compact: bool) -> Self {
+        if compact {
+            Self::Compact(Box::new(CompactTiles {
+                gids,
+                tilesets: tilesets.to_vec(),
+                resolved: OnceLock::new(),
+            }))
+        } else {
+            Self::Full(gids_to_tiles(&gids, tilesets))
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Full(tiles) => tiles.len(),
+            Self::Compact(compact) => compact.gids.len(),
+        }
+    }
+
+    fn get(&self, index: usize) -> Option<LayerTileData> {
+        match self {
+            Self::Full(tiles) => tiles.get(index).copied().flatten(),
+            Self::Compact(compact) => compact
+                .gids
+                .get(index)
+                .and_then(|&bits| LayerTileData::from_bits(bits, &compact.tilesets)),
+        }
+    }
+
+    fn resolved(&self) -> &[Option<LayerTileData>] {
+        match self {
+            Self::Full(tiles) => tiles,
+            Self::Compact(compact) => compact.resolved.get_or_init(|| compact.resolve()),
+        }
+    }
+
+    /// Returns the resolved tiles for modification, resolving them for good if they were compact.
+    fn resolved_mut(&mut self) -> &mut Vec<Option<LayerTileData>> {
+        if let Self::Compact(compact) = self {
+            let tiles = compact.resolved.take().unwrap_or_else(|| compact.resolve());
+            *self = Self::Full(tiles);
+        }
+        match self {
+            Self::Full(tiles) => tiles,
+            Self::Compact(_) => unreachable!("compact tiles were just resolved"),
+        }
+    }
+}
+
+impl Default for FiniteTiles {
+    fn default() -> Self {
+        Self::Full(Vec::new())
+    }
+}
+
+impl PartialEq for FiniteTiles {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && (0..self.len()).all(|index| self.get(index) == other.get(index))
+    }
+}
+
+/// The still-encoded data of a finite tile layer.
+#[derive(Clone)]
+pub(crate) struct EncodedFiniteData {
+    encoding: DataEncoding,
+    data: String,
+    compact: bool,
+}
+
+impl EncodedTiles for EncodedFiniteData {
+    type Output = FiniteTiles;
+
+    fn decode(&self) -> Result<Self::Output> {
+        self.encoding
+            .decode_gids(&self.data)
+            .map(|gids| FiniteTiles::from_gids(gids, &self.encoding.tilesets, self.compact))
+    }
+}
+
+/// Layers are serialized with their tiles decoded, which fails if they can't be.
+#[cfg(feature = "serde")]
+impl serde::Serialize for FiniteTileLayerData {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeStruct};
+
+        self.ensure_decoded().map_err(S::Error::custom)?;
+        let mut state = serializer.serialize_struct("FiniteTileLayerData", 3)?;
+        state.serialize_field("width", &self.width)?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field("tiles", self.tiles.get().resolved())?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FiniteTileLayerData {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "FiniteTileLayerData")]
+        struct Data {
+            width: u32,
+            height: u32,
+            tiles: Vec<Option<LayerTileData>>,
+        }
+
+        let Data {
+            width,
+            height,
+            tiles,
+        } = Data::deserialize(deserializer)?;
+        if tiles.len() != width as usize * height as usize {
+            return Err(serde::de::Error::custom(format!(
+                "expected {} tiles for a {}x{} layer, found {}",
+                width as usize * height as usize,
+                width,
+                height,
+                tiles.len()
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            tiles: LazyTiles::decoded(FiniteTiles::Full(tiles)),
+            source: SourceInfo::default(),
+        })
+    }
 }
 
 impl std::fmt::Debug for FiniteTileLayerData {
@@ -38,14 +209,28 @@ impl FiniteTileLayerData {
         self.height
     }
 
+    /// Creates a new layer data with the size given, in tiles, and no tiles in it.
+    pub fn empty(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            tiles: LazyTiles::decoded(FiniteTiles::Full(vec![
+                None;
+                width as usize * height as usize
+            ])),
+            source: SourceInfo::default(),
+        }
+    }
+
     pub(crate) fn new(
         parser: &mut impl Iterator<Item = XmlEventResult>,
         attrs: Vec<OwnedAttribute>,
         width: u32,
         height: u32,
         tilesets: &[MapTilesetGid],
+        options: TileDataOptions,
     ) -> Result<Self> {
-        let (e, c) = get_attrs!(
+        let (encoding, compression) = get_attrs!(
             for v in attrs {
                 Some("encoding") => encoding = v,
                 Some("compression") => compression = v,
@@ -53,12 +238,74 @@ impl FiniteTileLayerData {
             (encoding, compression)
         );
 
-        let tiles = parse_data_line(e, c, parser, tilesets)?;
+        options.tiles.take(width, height)?;
+        let data = read_data_line(parser, "data")?;
+        let mut source =
+            SourceEncoding::new(encoding.clone(), compression.clone(), options.decoders);
+        if let Some(data) = &data {
+            source.record((0, 0), data);
+        }
+        let tiles = match data {
+            Some(data) if options.defer => LazyTiles::deferred(EncodedFiniteData {
+                encoding: DataEncoding {
+                    encoding,
+                    compression,
+                    tilesets: tilesets.to_vec(),
+                    decoders: options.decoders.clone(),
+                },
+                data,
+                compact: options.compact,
+            }),
+            Some(data) => {
+                let gids = decode_data_gids(
+                    encoding.as_deref(),
+                    compression.as_deref(),
+                    &data,
+                    options.decoders,
+                )?;
+                check_gids(&gids, tilesets, options.diagnostics);
+                LazyTiles::decoded(FiniteTiles::from_gids(gids, tilesets, options.compact))
+            }
+            None => LazyTiles::decoded(FiniteTiles::default()),
+        };
 
         Ok(Self {
             width,
             height,
             tiles,
+            source: SourceInfo::new(source),
+        })
+    }
+
+    #[cfg(feature = "json")]
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        encoding: &str,
+        compression: Option<&str>,
+        tilesets: &[MapTilesetGid],
+        options: TileDataOptions,
+    ) -> Result<Self> {
+        use crate::parse::json::{as_u32, JsonObjectExt};
+
+        let width = object.req("width", as_u32)?;
+        let height = object.req("height", as_u32)?;
+        options.tiles.take(width, height)?;
+        let data = object.req("data", Some)?;
+        let gids = decode_json_gids(data, encoding, compression, options.decoders)?;
+        check_gids(&gids, tilesets, options.diagnostics);
+        let mut source = SourceEncoding::new(
+            Some(encoding.to_owned()),
+            compression.map(str::to_owned),
+            options.decoders,
+        );
+        if let Some(data) = data.as_str() {
+            source.record((0, 0), data);
+        }
+        Ok(Self {
+            width,
+            height,
+            tiles: LazyTiles::decoded(FiniteTiles::from_gids(gids, tilesets, options.compact)),
+            source: SourceInfo::new(source),
         })
     }
 
@@ -67,13 +314,95 @@ impl FiniteTileLayerData {
     /// If the position given is invalid or the position is empty, this function will return [`None`].
     ///
     /// If you want to get a [`Tile`](`crate::Tile`) instead, use [`FiniteTileLayer::get_tile()`].
+    ///
+    /// If the layer's data has not been decoded yet, it is decoded by this call. See
+    /// [`FiniteTileLayerData::ensure_decoded()`].
+    ///
+    /// If the layer was loaded with compact tile data (see
+    /// [`Loader::set_compact_tile_data()`](crate::Loader::set_compact_tile_data())), this resolves
+    /// all of its tiles the first time it is called, which gives up on the memory savings. Use
+    /// [`FiniteTileLayer::get_tile()`] instead to avoid that.
     pub fn get_tile_data(&self, x: i32, y: i32) -> Option<&LayerTileData> {
+        self.index(x, y)
+            .and_then(|index| self.tiles.get().resolved().get(index))
+            .and_then(Option::as_ref)
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
         if x < self.width as i32 && y < self.height as i32 && x >= 0 && y >= 0 {
-            self.tiles[x as usize + y as usize * self.width as usize].as_ref()
+            Some(x as usize + y as usize * self.width as usize)
         } else {
             None
         }
     }
+
+    /// Iterates over the non-empty tiles of the layer.
+    pub(crate) fn tile_data(&self) -> impl Iterator<Item = LayerTileData> + '_ {
+        let tiles = self.tiles.get();
+        (0..tiles.len()).filter_map(move |index| tiles.get(index))
+    }
+
+    /// Moves every tile of the layer according to `mapping`.
+    pub(crate) fn remap_tiles(&mut self, mapping: &GidMapping) {
+        for tile in self.tiles.get_mut().resolved_mut().iter_mut().flatten() {
+            tile.remap(mapping);
+        }
+    }
+
+    /// Sets the tile data present at the position given, or clears it if `tile` is [`None`].
+    ///
+    /// Returns `false` without modifying the layer if the position is outside of it.
+    pub fn set_tile(&mut self, x: i32, y: i32, tile: Option<LayerTileData>) -> bool {
+        if x < self.width as i32 && y < self.height as i32 && x >= 0 && y >= 0 {
+            let index = x as usize + y as usize * self.width as usize;
+            let tiles = self.tiles.get_mut().resolved_mut();
+            if tiles.len() <= index {
+                tiles.resize(self.width as usize * self.height as usize, None);
+            }
+            tiles[index] = tile;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Decodes the layer's data if it hasn't been decoded yet, which is only the case if it was
+    /// loaded by a [`Loader`](crate::Loader) that defers decoding (see
+    /// [`Loader::set_defer_tile_data()`](crate::Loader::set_defer_tile_data())).
+    ///
+    /// Accessing the tiles of a layer decodes it as well, but a layer whose data fails to decode
+    /// will simply appear empty; Call this function beforehand to get the error instead.
+    pub fn ensure_decoded(&self) -> Result<()> {
+        self.tiles.ensure_decoded()
+    }
+
+    /// Returns whether the layer's data has already been decoded.
+    pub fn is_decoded(&self) -> bool {
+        self.tiles.is_decoded()
+    }
+
+    /// The compression of the data the layer was loaded from, if any.
+    pub(crate) fn compression(&self) -> Option<&str> {
+        self.source.get()?.compression.as_deref()
+    }
+
+    /// Writes the `<data>` element of the layer, given the first GIDs of the map's tilesets.
+    pub(crate) fn write_xml(
+        &self,
+        writer: &mut EventWriter<impl Write>,
+        first_gids: &[Gid],
+        options: WriteOptions,
+    ) -> Result<()> {
+        self.ensure_decoded()?;
+        let tiles = self.tiles.get();
+        let gids: Vec<u32> = (0..self.width as usize * self.height as usize)
+            .map(|index| tiles.get(index).map_or(0, |tile| tile.to_bits(first_gids)))
+            .collect();
+        let data = DataWriter::new(self.source.get(), options);
+        write_start_element(writer, "data", &data.attributes())?;
+        write_characters(writer, &data.encode((0, 0), &gids, self.width)?)?;
+        write_end_element(writer)
+    }
 }
 
 map_wrapper!(
@@ -87,7 +416,45 @@ impl<'map> FiniteTileLayer<'map> {
     /// If the position given is invalid or the position is empty, this function will return [`None`].
     pub fn get_tile(&self, x: i32, y: i32) -> Option<LayerTile<'map>> {
         self.data
-            .get_tile_data(x, y)
+            .index(x, y)
+            .and_then(|index| self.data.tiles.get().get(index))
             .map(|data| LayerTile::new(self.map(), data))
     }
+
+    /// Returns an iterator over the non-empty tiles within the rectangle of tiles given, along with
+    /// their position, row by row. The rectangle is clipped to the bounds of the layer.
+    ///
+    /// ## Example
+    /// ```
+    /// # use tiled::{Loader, LayerType, TileLayer};
+    /// # let map = Loader::new()
+    /// #     .load_tmx_map("assets/tiled_base64_zlib.tmx")
+    /// #     .unwrap();
+    /// # let layer = match map.get_layer(0).unwrap().layer_type() {
+    /// #     LayerType::Tiles(TileLayer::Finite(layer)) => layer,
+    /// #     _ => panic!(),
+    /// # };
+    /// // Only go through the tiles visible by a camera showing 20x15 tiles.
+    /// for ((x, y), tile) in layer.tiles_in_rect(-2, 3, 20, 15) {
+    ///     assert!((0..18).contains(&x) && (3..18).contains(&y));
+    /// #   let _ = tile;
+    /// }
+    /// ```
+    pub fn tiles_in_rect(
+        &self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> impl Iterator<Item = ((i32, i32), LayerTile<'map>)> + 'map {
+        let layer = *self;
+        let end = |start: i32, size: u32, layer_size: u32| {
+            (start as i64 + size as i64).min(layer_size as i64) as i32
+        };
+        let (x_end, y_end) = (end(x, width, self.width()), end(y, height, self.height()));
+        let x = x.max(0);
+        (y.max(0)..y_end).flat_map(move |y| {
+            (x..x_end).filter_map(move |x| layer.get_tile(x, y).map(|tile| ((x, y), tile)))
+        })
+    }
 }
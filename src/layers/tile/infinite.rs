@@ -1,18 +1,102 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Write,
+};
 
-use xml::attribute::OwnedAttribute;
+use xml::{attribute::OwnedAttribute, EventWriter};
 
 use crate::{
-    util::{floor_div, get_attrs, map_wrapper, parse_tag, XmlEventResult},
-    Error, LayerTile, LayerTileData, MapTilesetGid, Result,
+    decoder::DataDecoders,
+    util::{
+        floor_div, get_attrs, map_wrapper, parse_tag, write_characters, write_end_element,
+        write_start_element, XmlEventResult,
+    },
+    warning::Diagnostics,
+    write::SourceInfo,
+    Error, Gid, GidMapping, LayerTile, LayerTileData, MapTilesetGid, Result, WriteOptions,
 };
 
-use super::util::parse_data_line;
+#[cfg(feature = "json")]
+use super::util::decode_json_gids;
+use super::util::{
+    check_gids, decode_data_gids, gids_to_tiles, read_data_line, DataEncoding, DataWriter,
+    EncodedTiles, LazyTiles, SourceEncoding, TileDataOptions,
+};
 
 /// The raw data of a [`InfiniteTileLayer`]. Does not include a reference to its parent [`Map`](crate::Map).
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Clone, Default)]
 pub struct InfiniteTileLayerData {
-    chunks: HashMap<(i32, i32), ChunkData>,
+    chunks: LazyTiles<EncodedChunks>,
+    source: SourceInfo<SourceEncoding>,
+}
+
+/// The still-encoded chunks of an infinite tile layer.
+#[derive(Clone)]
+pub(crate) struct EncodedChunks {
+    encoding: DataEncoding,
+    chunks: Vec<EncodedChunk>,
+}
+
+impl EncodedTiles for EncodedChunks {
+    type Output = HashMap<(i32, i32), ChunkData>;
+
+    fn decode(&self) -> Result<Self::Output> {
+        let mut chunks = HashMap::new();
+        for chunk in &self.chunks {
+            chunk
+                .decode(
+                    self.encoding.encoding.as_deref(),
+                    self.encoding.compression.as_deref(),
+                    &self.encoding.tilesets,
+                    &self.encoding.decoders,
+                    None,
+                )?
+                .merge_into(&mut chunks);
+        }
+        Ok(chunks)
+    }
+}
+
+/// Layers are serialized with their chunks decoded, which fails if they can't be. Chunks are
+/// written in order of their position, row by row.
+#[cfg(feature = "serde")]
+impl serde::Serialize for InfiniteTileLayerData {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeStruct};
+
+        self.ensure_decoded().map_err(S::Error::custom)?;
+        let mut chunks: Vec<&ChunkData> = self.chunks.get().values().collect();
+        chunks.sort_by_key(|chunk| (chunk.position.1, chunk.position.0));
+        let mut state = serializer.serialize_struct("InfiniteTileLayerData", 1)?;
+        state.serialize_field("chunks", &chunks)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for InfiniteTileLayerData {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "InfiniteTileLayerData")]
+        struct Data {
+            chunks: Vec<ChunkData>,
+        }
+
+        let chunks = Data::deserialize(deserializer)?
+            .chunks
+            .into_iter()
+            .map(|chunk| (chunk.position, chunk))
+            .collect();
+        Ok(Self {
+            chunks: LazyTiles::decoded(chunks),
+            source: SourceInfo::default(),
+        })
+    }
 }
 
 impl std::fmt::Debug for InfiniteTileLayerData {
@@ -26,8 +110,9 @@ impl InfiniteTileLayerData {
         parser: &mut impl Iterator<Item = XmlEventResult>,
         attrs: Vec<OwnedAttribute>,
         tilesets: &[MapTilesetGid],
+        options: TileDataOptions,
     ) -> Result<Self> {
-        let (e, c) = get_attrs!(
+        let (encoding, compression) = get_attrs!(
             for v in attrs {
                 Some("encoding") => encoding = v,
                 Some("compression") => compression = v,
@@ -36,35 +121,115 @@ impl InfiniteTileLayerData {
         );
 
         let mut chunks = HashMap::<(i32, i32), ChunkData>::new();
+        let mut encoded_chunks = Vec::new();
+        let mut source =
+            SourceEncoding::new(encoding.clone(), compression.clone(), options.decoders);
         parse_tag!(parser, "data", {
             "chunk" => |attrs| {
-                let chunk = InternalChunk::new(parser, attrs, e.clone(), c.clone(), tilesets)?;
-                for x in chunk.x..chunk.x + chunk.width as i32 {
-                    for y in chunk.y..chunk.y + chunk.height as i32 {
-                        let chunk_pos = ChunkData::tile_to_chunk_pos(x, y);
-                        let relative_pos = (x - chunk_pos.0 * ChunkData::WIDTH as i32, y - chunk_pos.1 * ChunkData::HEIGHT as i32);
-                        let chunk_index = (relative_pos.0 + relative_pos.1 * ChunkData::WIDTH as i32) as usize;
-                        let internal_pos = (x - chunk.x, y - chunk.y);
-                        let internal_index = (internal_pos.0 + internal_pos.1 * chunk.width as i32) as usize;
-
-                        chunks.entry(chunk_pos).or_insert_with(ChunkData::new).tiles[chunk_index] = chunk.tiles[internal_index];
-                    }
+                let chunk = EncodedChunk::new(parser, attrs)?;
+                options.tiles.take(chunk.width, chunk.height)?;
+                source.chunk_size.get_or_insert((chunk.width, chunk.height));
+                if let Some(data) = &chunk.data {
+                    source.record((chunk.x, chunk.y), data);
+                }
+                if options.defer {
+                    encoded_chunks.push(chunk);
+                } else {
+                    chunk
+                        .decode(
+                            encoding.as_deref(),
+                            compression.as_deref(),
+                            tilesets,
+                            options.decoders,
+                            Some(options.diagnostics),
+                        )?
+                        .merge_into(&mut chunks);
                 }
                 Ok(())
             }
         });
 
-        Ok(Self { chunks })
+        let chunks = if options.defer {
+            LazyTiles::deferred(EncodedChunks {
+                encoding: DataEncoding {
+                    encoding,
+                    compression,
+                    tilesets: tilesets.to_vec(),
+                    decoders: options.decoders.clone(),
+                },
+                chunks: encoded_chunks,
+            })
+        } else {
+            LazyTiles::decoded(chunks)
+        };
+        Ok(Self {
+            chunks,
+            source: SourceInfo::new(source),
+        })
+    }
+
+    #[cfg(feature = "json")]
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        encoding: &str,
+        compression: Option<&str>,
+        tilesets: &[MapTilesetGid],
+        options: TileDataOptions,
+    ) -> Result<Self> {
+        use crate::parse::json::{as_i32, as_objects, as_u32, JsonObjectExt};
+
+        let mut chunks = HashMap::<(i32, i32), ChunkData>::new();
+        let mut source = SourceEncoding::new(
+            Some(encoding.to_owned()),
+            compression.map(str::to_owned),
+            options.decoders,
+        );
+        if let Some(json_chunks) = object.opt_array("chunks")? {
+            for chunk in as_objects(json_chunks, "chunks")? {
+                let (x, y) = (chunk.req("x", as_i32)?, chunk.req("y", as_i32)?);
+                let width = chunk.req("width", as_u32)?;
+                let height = chunk.req("height", as_u32)?;
+                options.tiles.take(width, height)?;
+                source.chunk_size.get_or_insert((width, height));
+                let data = chunk.req("data", Some)?;
+                if let Some(data) = data.as_str() {
+                    source.record((x, y), data);
+                }
+                let gids = decode_json_gids(data, encoding, compression, options.decoders)?;
+                check_gids(&gids, tilesets, options.diagnostics);
+                InternalChunk {
+                    x,
+                    y,
+                    width,
+                    height,
+                    tiles: gids_to_tiles(&gids, tilesets),
+                }
+                .merge_into(&mut chunks);
+            }
+        }
+
+        Ok(Self {
+            chunks: LazyTiles::decoded(chunks),
+            source: SourceInfo::new(source),
+        })
     }
 
     /// Obtains the tile data present at the position given.
     ///
+    /// Infinite layers extend in every direction, so negative coordinates are valid and refer to
+    /// tiles left of or above the map's origin, exactly as shown in Tiled. Any position outside
+    /// of the layer's [`bounds()`](Self::bounds) is simply empty.
+    ///
     /// If the position given is invalid or the position is empty, this function will return [`None`].
     ///
     /// If you want to get a [`Tile`](`crate::Tile`) instead, use [`InfiniteTileLayer::get_tile()`].
+    ///
+    /// If the layer's data has not been decoded yet, it is decoded by this call. See
+    /// [`InfiniteTileLayerData::ensure_decoded()`].
     pub fn get_tile_data(&self, x: i32, y: i32) -> Option<&LayerTileData> {
         let chunk_pos = ChunkData::tile_to_chunk_pos(x, y);
         self.chunks
+            .get()
             .get(&chunk_pos)
             .and_then(|chunk| {
                 let relative_pos = (
@@ -87,7 +252,7 @@ impl InfiniteTileLayerData {
     /// This iterator doesn't have any particular order.
     #[inline]
     pub fn chunk_data(&self) -> impl ExactSizeIterator<Item = ((i32, i32), &ChunkData)> {
-        self.chunks.iter().map(|(pos, chunk)| (*pos, chunk))
+        self.chunks.get().iter().map(|(pos, chunk)| (*pos, chunk))
     }
 
     /// Obtains a chunk's data by its position. To obtain the position of the chunk that contains a
@@ -98,7 +263,135 @@ impl InfiniteTileLayerData {
     /// the map-wrapped [`LayerTile`]), and you are in dire need for that extra bit of performance.
     #[inline]
     pub fn get_chunk_data(&self, x: i32, y: i32) -> Option<&ChunkData> {
-        self.chunks.get(&(x, y))
+        self.chunks.get().get(&(x, y))
+    }
+
+    /// Returns the smallest and largest tile coordinates of the non-empty tiles of this layer,
+    /// as `((min_x, min_y), (max_x, max_y))`. Both corners are inclusive, and may be negative.
+    ///
+    /// Returns [`None`] if the layer has no tiles at all.
+    ///
+    /// If the layer's data has not been decoded yet, it is decoded by this call. See
+    /// [`InfiniteTileLayerData::ensure_decoded()`].
+    pub fn bounds(&self) -> Option<((i32, i32), (i32, i32))> {
+        self.chunks
+            .get()
+            .values()
+            .flat_map(|chunk| chunk.tile_data())
+            .map(|(pos, _)| pos)
+            .fold(None, |bounds, (x, y)| match bounds {
+                None => Some(((x, y), (x, y))),
+                Some(((min_x, min_y), (max_x, max_y))) => {
+                    Some(((min_x.min(x), min_y.min(y)), (max_x.max(x), max_y.max(y))))
+                }
+            })
+    }
+
+    /// Iterates over the non-empty tiles of the layer.
+    pub(crate) fn tile_data(&self) -> impl Iterator<Item = &LayerTileData> {
+        self.chunks
+            .get()
+            .values()
+            .flat_map(|chunk| chunk.tiles.iter().flatten())
+    }
+
+    /// Moves every tile of the layer according to `mapping`.
+    pub(crate) fn remap_tiles(&mut self, mapping: &GidMapping) {
+        for chunk in self.chunks.get_mut().values_mut() {
+            for tile in chunk.tiles.iter_mut().flatten() {
+                tile.remap(mapping);
+            }
+        }
+    }
+
+    /// Sets the tile data present at the position given, or clears it if `tile` is [`None`].
+    ///
+    /// Chunks are created as needed, but never removed, even if they end up empty.
+    pub fn set_tile(&mut self, x: i32, y: i32, tile: Option<LayerTileData>) {
+        let chunk_pos = ChunkData::tile_to_chunk_pos(x, y);
+        let relative_pos = (
+            x - chunk_pos.0 * ChunkData::WIDTH as i32,
+            y - chunk_pos.1 * ChunkData::HEIGHT as i32,
+        );
+        let chunk_index = (relative_pos.0 + relative_pos.1 * ChunkData::WIDTH as i32) as usize;
+        self.chunks
+            .get_mut()
+            .entry(chunk_pos)
+            .or_insert_with(|| ChunkData::new(chunk_pos))
+            .tiles[chunk_index] = tile;
+    }
+
+    /// Decodes the layer's chunks if they haven't been decoded yet, which is only the case if
+    /// the layer was loaded by a [`Loader`](crate::Loader) that defers decoding (see
+    /// [`Loader::set_defer_tile_data()`](crate::Loader::set_defer_tile_data())).
+    ///
+    /// Accessing the chunks of a layer decodes them as well, but a layer whose data fails to
+    /// decode will simply appear empty; Call this function beforehand to get the error instead.
+    pub fn ensure_decoded(&self) -> Result<()> {
+        self.chunks.ensure_decoded()
+    }
+
+    /// Returns whether the layer's chunks have already been decoded.
+    pub fn is_decoded(&self) -> bool {
+        self.chunks.is_decoded()
+    }
+
+    /// The compression of the data the layer was loaded from, if any.
+    pub(crate) fn compression(&self) -> Option<&str> {
+        self.source.get()?.compression.as_deref()
+    }
+
+    /// Writes the `<data>` element of the layer, given the first GIDs of the map's tilesets.
+    pub(crate) fn write_xml(
+        &self,
+        writer: &mut EventWriter<impl Write>,
+        first_gids: &[Gid],
+        options: WriteOptions,
+    ) -> Result<()> {
+        self.ensure_decoded()?;
+        let data = DataWriter::new(self.source.get(), options);
+        let (width, height) = data.chunk_size();
+        // The chunks to write, by the position of their top-left tile, row by row. Every chunk
+        // that's stored is covered, even if it's empty, so that the layer reads back the same.
+        let (width, height) = (width as i32, height as i32);
+        let mut origins = BTreeSet::new();
+        for &(chunk_x, chunk_y) in self.chunks.get().keys() {
+            let (start_x, start_y) = (
+                chunk_x * ChunkData::WIDTH as i32,
+                chunk_y * ChunkData::HEIGHT as i32,
+            );
+            let end_x = start_x + ChunkData::WIDTH as i32 - 1;
+            let end_y = start_y + ChunkData::HEIGHT as i32 - 1;
+            for y in floor_div(start_y, height)..=floor_div(end_y, height) {
+                for x in floor_div(start_x, width)..=floor_div(end_x, width) {
+                    origins.insert((y * height, x * width));
+                }
+            }
+        }
+
+        write_start_element(writer, "data", &data.attributes())?;
+        for (y, x) in origins {
+            let gids: Vec<u32> = (y..y + height)
+                .flat_map(|y| (x..x + width).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    self.get_tile_data(x, y)
+                        .map_or(0, |tile| tile.to_bits(first_gids))
+                })
+                .collect();
+            write_start_element(
+                writer,
+                "chunk",
+                &[
+                    ("x", x.to_string()),
+                    ("y", y.to_string()),
+                    ("width", width.to_string()),
+                    ("height", height.to_string()),
+                ],
+            )?;
+            write_characters(writer, &data.encode((x, y), &gids, width as u32)?)?;
+            write_end_element(writer)?;
+        }
+        write_end_element(writer)
     }
 }
 
@@ -108,9 +401,46 @@ impl InfiniteTileLayerData {
 /// In 99.99% of cases you'll actually want to use [`Chunk`].
 #[derive(Debug, PartialEq, Clone)]
 pub struct ChunkData {
+    position: (i32, i32),
     tiles: Box<[Option<LayerTileData>; Self::TILE_COUNT]>,
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for ChunkData {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("ChunkData", 2)?;
+        state.serialize_field("position", &self.position)?;
+        state.serialize_field("tiles", &self.tiles[..])?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ChunkData {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "ChunkData")]
+        struct Data {
+            position: (i32, i32),
+            tiles: Vec<Option<LayerTileData>>,
+        }
+
+        let Data { position, tiles } = Data::deserialize(deserializer)?;
+        let len = tiles.len();
+        let tiles = std::convert::TryInto::try_into(tiles.into_boxed_slice()).map_err(|_| {
+            serde::de::Error::invalid_length(len, &"as many tiles as chunks contain")
+        })?;
+        Ok(Self { position, tiles })
+    }
+}
+
 impl ChunkData {
     /// Infinite layer chunk width. This constant might change between versions, not counting as a
     /// breaking change.
@@ -122,12 +452,53 @@ impl ChunkData {
     /// as a breaking change.
     pub const TILE_COUNT: usize = Self::WIDTH as usize * Self::HEIGHT as usize;
 
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(position: (i32, i32)) -> Self {
         Self {
+            position,
             tiles: Box::new([None; Self::TILE_COUNT]),
         }
     }
 
+    /// Returns the position of this chunk, in chunk coordinates.
+    #[inline]
+    pub fn position(&self) -> (i32, i32) {
+        self.position
+    }
+
+    /// Returns the position of this chunk's top-left-most tile, in tile coordinates.
+    #[inline]
+    pub fn origin(&self) -> (i32, i32) {
+        Self::chunk_to_tile_pos(self.position.0, self.position.1)
+    }
+
+    /// Returns the width and height of this chunk in tiles, which are always [`ChunkData::WIDTH`]
+    /// and [`ChunkData::HEIGHT`].
+    #[inline]
+    pub fn size(&self) -> (u32, u32) {
+        (Self::WIDTH, Self::HEIGHT)
+    }
+
+    /// Returns true if none of the tiles of this chunk are set.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.iter().all(Option::is_none)
+    }
+
+    /// Returns an iterator over the non-empty tiles of this chunk along with their position in
+    /// tile coordinates, relative to the layer rather than the chunk.
+    ///
+    /// If you want to get [`LayerTile`](`crate::LayerTile`)s instead, use [`Chunk::tiles()`].
+    pub fn tile_data(&self) -> impl Iterator<Item = ((i32, i32), &LayerTileData)> {
+        let origin = self.origin();
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, tile)| {
+                let x = origin.0 + (index % Self::WIDTH as usize) as i32;
+                let y = origin.1 + (index / Self::WIDTH as usize) as i32;
+                tile.as_ref().map(|tile| ((x, y), tile))
+            })
+    }
+
     /// Obtains the tile data present at the position given relative to the chunk's top-left-most tile.
     ///
     /// If the position given is invalid or the position is empty, this function will return [`None`].
@@ -148,6 +519,11 @@ impl ChunkData {
             floor_div(y, ChunkData::HEIGHT as i32),
         )
     }
+
+    /// Returns the position of the top-left-most tile of the chunk at the given chunk position.
+    pub fn chunk_to_tile_pos(x: i32, y: i32) -> (i32, i32) {
+        (x * ChunkData::WIDTH as i32, y * ChunkData::HEIGHT as i32)
+    }
 }
 
 map_wrapper!(
@@ -162,30 +538,33 @@ impl<'map> Chunk<'map> {
     pub fn get_tile(&self, x: i32, y: i32) -> Option<LayerTile<'map>> {
         self.data
             .get_tile_data(x, y)
-            .map(|data| LayerTile::new(self.map(), data))
+            .map(|data| LayerTile::new(self.map(), *data))
+    }
+
+    /// Returns an iterator over the non-empty tiles of this chunk along with their position in
+    /// tile coordinates, relative to the layer rather than the chunk.
+    pub fn tiles(&self) -> impl Iterator<Item = ((i32, i32), LayerTile<'map>)> + 'map {
+        let map: &'map crate::Map = self.map;
+        self.data
+            .tile_data()
+            .map(move |(pos, data)| (pos, LayerTile::new(map, *data)))
     }
 }
 
+/// A chunk as found in the TMX format, whose data hasn't been decoded yet.
 #[derive(Debug, PartialEq, Clone)]
-struct InternalChunk {
-    /// The X coordinate of the top-left-most tile in the chunk.
-    /// Corresponds to the `x` attribute in the TMX format.
+struct EncodedChunk {
     x: i32,
-    /// The Y coordinate of the top-left-most tile in the chunk.
-    /// Corresponds to the `y` attribute in the TMX format.
     y: i32,
     width: u32,
     height: u32,
-    tiles: Vec<Option<LayerTileData>>,
+    data: Option<String>,
 }
 
-impl InternalChunk {
-    pub(crate) fn new(
+impl EncodedChunk {
+    fn new(
         parser: &mut impl Iterator<Item = XmlEventResult>,
         attrs: Vec<OwnedAttribute>,
-        encoding: Option<String>,
-        compression: Option<String>,
-        tilesets: &[MapTilesetGid],
     ) -> Result<Self> {
         let (x, y, width, height) = get_attrs!(
             for v in attrs {
@@ -197,18 +576,81 @@ impl InternalChunk {
             (x, y, width, height)
         );
 
-        let tiles = parse_data_line(encoding, compression, parser, tilesets)?;
-
-        Ok(InternalChunk {
+        Ok(Self {
             x,
             y,
             width,
             height,
+            data: read_data_line(parser, "chunk")?,
+        })
+    }
+
+    fn decode(
+        &self,
+        encoding: Option<&str>,
+        compression: Option<&str>,
+        tilesets: &[MapTilesetGid],
+        decoders: &DataDecoders,
+        diagnostics: Option<&Diagnostics>,
+    ) -> Result<InternalChunk> {
+        let tiles = match &self.data {
+            Some(data) => {
+                let gids = decode_data_gids(encoding, compression, data, decoders)?;
+                if let Some(diagnostics) = diagnostics {
+                    check_gids(&gids, tilesets, diagnostics);
+                }
+                gids_to_tiles(&gids, tilesets)
+            }
+            None => Vec::new(),
+        };
+
+        Ok(InternalChunk {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
             tiles,
         })
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
+struct InternalChunk {
+    /// The X coordinate of the top-left-most tile in the chunk.
+    /// Corresponds to the `x` attribute in the TMX format.
+    x: i32,
+    /// The Y coordinate of the top-left-most tile in the chunk.
+    /// Corresponds to the `y` attribute in the TMX format.
+    y: i32,
+    width: u32,
+    height: u32,
+    tiles: Vec<Option<LayerTileData>>,
+}
+
+impl InternalChunk {
+    /// Copies the tiles of this chunk into the fixed-size chunks they belong to.
+    fn merge_into(self, chunks: &mut HashMap<(i32, i32), ChunkData>) {
+        for x in self.x..self.x + self.width as i32 {
+            for y in self.y..self.y + self.height as i32 {
+                let chunk_pos = ChunkData::tile_to_chunk_pos(x, y);
+                let relative_pos = (
+                    x - chunk_pos.0 * ChunkData::WIDTH as i32,
+                    y - chunk_pos.1 * ChunkData::HEIGHT as i32,
+                );
+                let chunk_index =
+                    (relative_pos.0 + relative_pos.1 * ChunkData::WIDTH as i32) as usize;
+                let internal_pos = (x - self.x, y - self.y);
+                let internal_index = (internal_pos.0 + internal_pos.1 * self.width as i32) as usize;
+
+                chunks
+                    .entry(chunk_pos)
+                    .or_insert_with(|| ChunkData::new(chunk_pos))
+                    .tiles[chunk_index] = self.tiles[internal_index];
+            }
+        }
+    }
+}
+
 map_wrapper!(
     #[doc = "A [`TileLayer`](super::TileLayer) with no bounds, internally stored using [`Chunk`]s."]
     InfiniteTileLayer => InfiniteTileLayerData
@@ -217,11 +659,12 @@ map_wrapper!(
 impl<'map> InfiniteTileLayer<'map> {
     /// Obtains the tile present at the position given.
     ///
-    /// If the position is empty, this function will return [`None`].
+    /// Negative coordinates are valid, see [`InfiniteTileLayerData::get_tile_data()`]. If the
+    /// position is empty, this function will return [`None`].
     pub fn get_tile(&self, x: i32, y: i32) -> Option<LayerTile<'map>> {
         self.data
             .get_tile_data(x, y)
-            .map(|data| LayerTile::new(self.map, data))
+            .map(|data| LayerTile::new(self.map, *data))
     }
 
     /// Returns an iterator over different parts of this map called [`Chunk`]s.
@@ -267,10 +710,63 @@ impl<'map> InfiniteTileLayer<'map> {
         let map: &'map crate::Map = self.map;
         self.data
             .chunks
+            .get()
             .iter()
             .map(move |(pos, chunk)| (*pos, Chunk::new(map, chunk)))
     }
 
+    /// Returns an iterator over the non-empty tiles within the rectangle of tiles given, along with
+    /// their position.
+    ///
+    /// Only the chunks overlapping the rectangle are visited, which they are in order of their
+    /// position, row by row. The tiles of each chunk are also yielded row by row.
+    pub fn tiles_in_rect(
+        &self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> impl Iterator<Item = ((i32, i32), LayerTile<'map>)> + 'map {
+        let map: &'map crate::Map = self.map;
+        let all_chunks = self.data.chunks.get();
+        let last = |start: i32, size: u32| (start as i64 + size as i64 - 1).min(i32::MAX as i64);
+        let (last_x, last_y) = (last(x, width), last(y, height));
+
+        let mut chunks = Vec::new();
+        if width > 0 && height > 0 {
+            let (x0, y0) = ChunkData::tile_to_chunk_pos(x, y);
+            let (x1, y1) = ChunkData::tile_to_chunk_pos(last_x as i32, last_y as i32);
+            let overlapped = (x1 - x0 + 1) as u64 * (y1 - y0 + 1) as u64;
+            // Look up the overlapped chunks, unless there are fewer chunks in the whole layer.
+            if overlapped <= all_chunks.len() as u64 {
+                chunks.extend(
+                    (y0..=y1)
+                        .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+                        .filter_map(|pos| all_chunks.get(&pos)),
+                );
+            } else {
+                chunks.extend(all_chunks.iter().filter_map(|(&(cx, cy), chunk)| {
+                    ((x0..=x1).contains(&cx) && (y0..=y1).contains(&cy)).then_some(chunk)
+                }));
+                chunks.sort_by_key(|chunk| (chunk.position.1, chunk.position.0));
+            }
+        }
+
+        chunks.into_iter().flat_map(move |chunk| {
+            let (origin_x, origin_y) = chunk.origin();
+            let xs =
+                x.max(origin_x) as i64..=last_x.min(origin_x as i64 + ChunkData::WIDTH as i64 - 1);
+            let ys =
+                y.max(origin_y) as i64..=last_y.min(origin_y as i64 + ChunkData::HEIGHT as i64 - 1);
+            ys.flat_map(move |y| xs.clone().map(move |x| (x as i32, y as i32)))
+                .filter_map(move |(x, y)| {
+                    chunk
+                        .get_tile_data(x - origin_x, y - origin_y)
+                        .map(|data| ((x, y), LayerTile::new(map, *data)))
+                })
+        })
+    }
+
     /// Obtains a chunk by its position. To obtain the position of the chunk that contains a tile,
     /// use [`ChunkData::tile_to_chunk_pos()`].
     #[inline]
@@ -3,20 +3,26 @@ use std::collections::HashMap;
 use xml::attribute::OwnedAttribute;
 
 use crate::{
+    parse::LoadContext,
     parse_properties,
-    util::{get_attrs, map_wrapper, parse_tag, XmlEventResult},
-    Error, Gid, Map, MapTilesetGid, Properties, Result, Tile, TileId, Tileset,
+    util::{get_attrs, parse_tag, XmlEventResult},
+    Error, Gid, GidMapping, Map, MapTilesetGid, Properties, ResourceCache, ResourceReader, Result,
+    Tile, TileId, Tileset,
 };
 
+#[cfg(feature = "fast-csv")]
+mod fast_csv;
 mod finite;
 mod infinite;
 mod util;
 
 pub use finite::*;
 pub use infinite::*;
+pub(crate) use util::{check_gids, decode_data_gids, gids_to_tiles, TileDataOptions};
 
 /// Stores the internal tile gid about a layer tile, along with how it is flipped.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LayerTileData {
     /// The index of the tileset this tile's in, relative to the tile's map. Guaranteed to be a
     /// valid index of the map tileset container, but **isn't guaranteed to actually contain
@@ -44,12 +50,73 @@ impl LayerTileData {
         self.tileset_index
     }
 
+    /// Creates a new, unflipped layer tile referring to the tile with the local id given, within
+    /// the tileset at the specified index of the map's tilesets.
+    ///
+    /// ## Note
+    /// The tileset index must be valid within the map the tile is placed in; Otherwise, accessing
+    /// the tile's tileset through a [`LayerTile`] will panic.
+    pub fn new(tileset_index: usize, id: TileId) -> Self {
+        Self {
+            tileset_index,
+            id,
+            flip_h: false,
+            flip_v: false,
+            flip_d: false,
+        }
+    }
+
     /// Get the layer tile's local id within its parent tileset.
     #[inline]
     pub fn id(&self) -> TileId {
         self.id
     }
 
+    /// Moves the tile to the tileset index and local id given by `mapping`, keeping its flips.
+    pub(crate) fn remap(&mut self, mapping: &GidMapping) {
+        (self.tileset_index, self.id) = mapping.get(self.tileset_index, self.id);
+    }
+
+    /// Returns the rotation and mirroring equivalent to this tile's flip flags.
+    ///
+    /// Tiled applies the diagonal flip first, then the horizontal and vertical ones, which
+    /// combined always amount to an optional horizontal mirroring followed by a clockwise rotation
+    /// by a multiple of 90 degrees.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{LayerTileData, TileTransform};
+    ///
+    /// // Tiled represents a clockwise rotation by flipping the tile diagonally and horizontally.
+    /// let mut tile = LayerTileData::new(0, 0);
+    /// tile.flip_d = true;
+    /// tile.flip_h = true;
+    /// assert_eq!(tile.transform(), TileTransform { rotation: 90, flip_h: false });
+    /// ```
+    pub fn transform(&self) -> TileTransform {
+        TileTransform::from_flags(self.flip_h, self.flip_v, self.flip_d)
+    }
+
+    /// Returns the 2×3 affine matrix that maps a position within the tile's image to the position
+    /// it is drawn at, both normalized to the `[0, 1]` range, with Y pointing down.
+    ///
+    /// That is, a position `(x, y)` is drawn at `(m[0][0] * x + m[0][1] * y + m[0][2],
+    /// m[1][0] * x + m[1][1] * y + m[1][2])`.
+    pub fn transform_matrix(&self) -> [[f32; 3]; 2] {
+        let mut x = [1.0, 0.0, 0.0];
+        let mut y = [0.0, 1.0, 0.0];
+        if self.flip_d {
+            std::mem::swap(&mut x, &mut y);
+        }
+        if self.flip_h {
+            x = [-x[0], -x[1], 1.0 - x[2]];
+        }
+        if self.flip_v {
+            y = [-y[0], -y[1], 1.0 - y[2]];
+        }
+        [x, y]
+    }
+
     const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x80000000;
     const FLIPPED_VERTICALLY_FLAG: u32 = 0x40000000;
     const FLIPPED_DIAGONALLY_FLAG: u32 = 0x20000000;
@@ -80,6 +147,52 @@ impl LayerTileData {
             })
         }
     }
+
+    /// Returns the GID of this tile plus its flipping bits, given the first GIDs of the map's
+    /// tilesets.
+    pub(crate) fn to_bits(self, first_gids: &[Gid]) -> u32 {
+        let mut bits = first_gids[self.tileset_index].0 + self.id;
+        if self.flip_h {
+            bits |= Self::FLIPPED_HORIZONTALLY_FLAG;
+        }
+        if self.flip_v {
+            bits |= Self::FLIPPED_VERTICALLY_FLAG;
+        }
+        if self.flip_d {
+            bits |= Self::FLIPPED_DIAGONALLY_FLAG;
+        }
+        bits
+    }
+}
+
+/// The rotation and mirroring of a [`LayerTileData`], as returned by
+/// [`LayerTileData::transform`].
+///
+/// The tile is first mirrored horizontally if [`flip_h`](Self::flip_h) is set, and then rotated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileTransform {
+    /// The clockwise rotation of the tile, in degrees. Either 0, 90, 180 or 270.
+    pub rotation: u32,
+    /// Whether the tile is mirrored horizontally before being rotated.
+    pub flip_h: bool,
+}
+
+impl TileTransform {
+    /// Converts the flip flags of a GID into the equivalent rotation and mirroring.
+    pub(crate) fn from_flags(flip_h: bool, flip_v: bool, flip_d: bool) -> Self {
+        let (rotation, flip_h) = match (flip_d, flip_h, flip_v) {
+            (false, false, false) => (0, false),
+            (false, true, false) => (0, true),
+            (false, false, true) => (180, true),
+            (false, true, true) => (180, false),
+            (true, false, false) => (270, true),
+            (true, true, false) => (90, false),
+            (true, false, true) => (270, false),
+            (true, true, true) => (90, true),
+        };
+        TileTransform { rotation, flip_h }
+    }
 }
 
 /// The raw data of a [`TileLayer`]. Does not include a reference to its parent [`Map`](crate::Map).
@@ -88,6 +201,7 @@ impl LayerTileData {
 /// dereference [`TileLayer`] into this structure, and even if we could, it wouldn't make much
 /// sense, since we can already deref from the finite/infinite tile layers themselves.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum TileLayerData {
     Finite(FiniteTileLayerData),
     Infinite(InfiniteTileLayerData),
@@ -99,6 +213,7 @@ impl TileLayerData {
         attrs: Vec<OwnedAttribute>,
         infinite: bool,
         tilesets: &[MapTilesetGid],
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<(Self, Properties)> {
         let (width, height) = get_attrs!(
             for v in attrs {
@@ -112,26 +227,117 @@ impl TileLayerData {
         parse_tag!(parser, "layer", {
             "data" => |attrs| {
                 if infinite {
-                    result = Self::Infinite(InfiniteTileLayerData::new(parser, attrs, tilesets)?);
+                    result = Self::Infinite(InfiniteTileLayerData::new(parser, attrs, tilesets, ctx.tile_data_options())?);
                 } else {
-                    result = Self::Finite(FiniteTileLayerData::new(parser, attrs, width, height, tilesets)?);
+                    result = Self::Finite(FiniteTileLayerData::new(parser, attrs, width, height, tilesets, ctx.tile_data_options())?);
                 }
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, ctx.project, &ctx.interner, ctx.diagnostics)?;
                 Ok(())
             },
         });
 
         Ok((result, properties))
     }
+
+    /// Returns the tiles of the layer along with their position.
+    pub(crate) fn positioned_tiles(&self) -> Vec<((i32, i32), LayerTileData)> {
+        match self {
+            Self::Finite(data) => (0..data.height() as i32)
+                .flat_map(|y| (0..data.width() as i32).map(move |x| (x, y)))
+                .filter_map(|(x, y)| Some(((x, y), *data.get_tile_data(x, y)?)))
+                .collect(),
+            Self::Infinite(data) => data
+                .chunk_data()
+                .flat_map(|(_, chunk)| chunk.tile_data())
+                .map(|(pos, tile)| (pos, *tile))
+                .collect(),
+        }
+    }
+
+    /// Returns a copy of the layer with its tiles moved by `offset`, which is finite with the size
+    /// given or infinite if there is none. Tiles that end up outside of a finite layer are dropped.
+    pub(crate) fn translated(&self, offset: (i32, i32), size: Option<(u32, u32)>) -> Self {
+        let tiles = self
+            .positioned_tiles()
+            .into_iter()
+            .map(|((x, y), tile)| ((x + offset.0, y + offset.1), tile));
+        match size {
+            Some((width, height)) => {
+                let mut data = FiniteTileLayerData::empty(width, height);
+                for ((x, y), tile) in tiles {
+                    data.set_tile(x, y, Some(tile));
+                }
+                Self::Finite(data)
+            }
+            None => {
+                let mut data = InfiniteTileLayerData::default();
+                for ((x, y), tile) in tiles {
+                    data.set_tile(x, y, Some(tile));
+                }
+                Self::Infinite(data)
+            }
+        }
+    }
 }
 
-map_wrapper!(
-    #[doc = "An instance of a [`Tile`] present in a [`TileLayer`]."]
-    LayerTile => LayerTileData
-);
+#[cfg(feature = "json")]
+impl TileLayerData {
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        infinite: bool,
+        tilesets: &[MapTilesetGid],
+        options: TileDataOptions,
+    ) -> Result<Self> {
+        use crate::parse::json::JsonObjectExt;
+
+        let encoding = object.opt_str("encoding")?.unwrap_or("csv");
+        // Uncompressed data is marked with an empty string.
+        let compression = object.opt_str("compression")?.filter(|c| !c.is_empty());
+        if infinite {
+            InfiniteTileLayerData::parse_json(object, encoding, compression, tilesets, options)
+                .map(Self::Infinite)
+        } else {
+            FiniteTileLayerData::parse_json(object, encoding, compression, tilesets, options)
+                .map(Self::Finite)
+        }
+    }
+}
+
+/// An instance of a [`Tile`] present in a [`TileLayer`].
+///
+/// Unlike other map-wrapped types, it holds a copy of its [`LayerTileData`], so that it can be
+/// created for layers that don't store their tiles as such (see
+/// [`Loader::set_compact_tile_data()`](crate::Loader::set_compact_tile_data())).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LayerTile<'map> {
+    pub(crate) map: &'map Map,
+    pub(crate) data: LayerTileData,
+}
+
+impl<'map> LayerTile<'map> {
+    #[inline]
+    pub(crate) fn new(map: &'map Map, data: LayerTileData) -> Self {
+        Self { map, data }
+    }
+
+    /// Get the map this object is from.
+    #[inline]
+    pub fn map(&self) -> &'map Map {
+        self.map
+    }
+}
+
+impl<'map> std::ops::Deref for LayerTile<'map> {
+    type Target = LayerTileData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
 
 impl<'map> LayerTile<'map> {
     /// Get a reference to the layer tile's referenced tile, if it exists.
@@ -145,6 +351,12 @@ impl<'map> LayerTile<'map> {
         // SAFETY: `tileset_index` is guaranteed to be valid
         &self.map.tilesets()[self.data.tileset_index]
     }
+
+    /// Returns the GID of the layer tile, with the flags of its flips set, as it is stored in map
+    /// files. See [`Map::get_first_gid()`].
+    pub fn gid(&self) -> u32 {
+        self.data.to_bits(self.map.first_gids())
+    }
 }
 
 /// A map layer containing tiles in some way. May be finite or infinite.
@@ -174,6 +386,129 @@ impl<'map> TileLayer<'map> {
         }
     }
 
+    /// Returns an iterator over the non-empty tiles within the rectangle of tiles given, along with
+    /// their position, which is what renderers culling tiles outside of the camera need.
+    ///
+    /// See [`FiniteTileLayer::tiles_in_rect()`] and [`InfiniteTileLayer::tiles_in_rect()`] for the
+    /// order the tiles are yielded in.
+    pub fn tiles_in_rect(
+        &self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> impl Iterator<Item = ((i32, i32), LayerTile<'map>)> + 'map {
+        let (finite, infinite) = match self {
+            TileLayer::Finite(finite) => (Some(finite.tiles_in_rect(x, y, width, height)), None),
+            TileLayer::Infinite(infinite) => {
+                (None, Some(infinite.tiles_in_rect(x, y, width, height)))
+            }
+        };
+        finite
+            .into_iter()
+            .flatten()
+            .chain(infinite.into_iter().flatten())
+    }
+
+    /// Returns an iterator over all the non-empty tiles of this layer along with their position.
+    pub(crate) fn all_tiles(
+        &self,
+    ) -> Box<dyn Iterator<Item = ((i32, i32), LayerTile<'map>)> + 'map> {
+        match self {
+            TileLayer::Finite(finite) => {
+                Box::new(finite.tiles_in_rect(0, 0, finite.width(), finite.height()))
+            }
+            TileLayer::Infinite(infinite) => {
+                Box::new(infinite.chunks().flat_map(|(_, chunk)| chunk.tiles()))
+            }
+        }
+    }
+
+    /// Returns the GIDs of the tiles of this layer in the CSV layout of TMX files, with 0 standing
+    /// for empty tiles: One line per row of tiles, each GID followed by a comma except the last
+    /// one. See [`LayerTile::gid()`].
+    ///
+    /// Infinite layers are written from the top-left corner of their
+    /// [bounds](InfiniteTileLayerData::bounds()), which the result doesn't tell.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Image, LayerData, LayerTileData, MapBuilder, Orientation, TilesetBuilder};
+    ///
+    /// let image = Image {
+    ///     source: "terrain.png".into(),
+    ///     width: 64,
+    ///     height: 16,
+    ///     transparent_colour: None,
+    ///     handle: None,
+    /// };
+    /// let mut layer = LayerData::new_finite_tile_layer("ground", 3, 2);
+    /// let tiles = layer.as_finite_tile_layer_mut().unwrap();
+    /// tiles.set_tile(0, 0, Some(LayerTileData::new(0, 3)));
+    /// tiles.set_tile(2, 1, Some(LayerTileData::new(0, 0)));
+    /// let map = MapBuilder::new(Orientation::Orthogonal, 3, 2, 16, 16)
+    ///     .tileset(TilesetBuilder::new("terrain", 16, 16).image(image).build())
+    ///     .layer(layer)
+    ///     .build()?;
+    ///
+    /// let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    /// assert_eq!(layer.to_csv(), "4,0,0,\n0,0,1\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_csv(&self) -> String {
+        use std::fmt::Write;
+
+        let (x, y, width, height) = match self {
+            TileLayer::Finite(finite) => (0, 0, finite.width(), finite.height()),
+            TileLayer::Infinite(infinite) => match infinite.bounds() {
+                Some(((min_x, min_y), (max_x, max_y))) => (
+                    min_x,
+                    min_y,
+                    (max_x - min_x + 1) as u32,
+                    (max_y - min_y + 1) as u32,
+                ),
+                None => return String::new(),
+            },
+        };
+        let mut csv = String::new();
+        for row in 0..height as i32 {
+            for column in 0..width as i32 {
+                let gid = self
+                    .get_tile(x + column, y + row)
+                    .map_or(0, |tile| tile.gid());
+                write!(csv, "{}", gid).unwrap();
+                if column + 1 < width as i32 || row + 1 < height as i32 {
+                    csv.push(',');
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Decodes the layer's data if it hasn't been decoded yet, which is only the case if it was
+    /// loaded by a [`Loader`](crate::Loader) that defers decoding (see
+    /// [`Loader::set_defer_tile_data()`](crate::Loader::set_defer_tile_data())).
+    ///
+    /// Accessing the tiles of a layer decodes it as well, but a layer whose data fails to decode
+    /// will simply appear empty; Call this function beforehand to get the error instead.
+    pub fn ensure_decoded(&self) -> Result<()> {
+        match self {
+            TileLayer::Finite(finite) => finite.ensure_decoded(),
+            TileLayer::Infinite(infinite) => infinite.ensure_decoded(),
+        }
+    }
+
+    /// Returns whether the layer's data has already been decoded.
+    pub fn is_decoded(&self) -> bool {
+        match self {
+            TileLayer::Finite(finite) => finite.is_decoded(),
+            TileLayer::Infinite(infinite) => infinite.is_decoded(),
+        }
+    }
+
     /// The width of this layer, if finite, or `None` if infinite.
     ///
     /// ## Example
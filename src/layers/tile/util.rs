@@ -1,96 +1,486 @@
-use std::{convert::TryInto, io::Read};
+use std::{collections::HashMap, convert::TryInto, io::Write, sync::OnceLock};
 
 use base64::Engine;
 use xml::reader::XmlEvent;
 
-use crate::{util::XmlEventResult, Error, LayerTileData, MapTilesetGid, Result};
+use crate::{
+    decoder::DataDecoders,
+    limits::TileBudget,
+    util::{get_tileset_for_gid, XmlEventResult},
+    warning::Diagnostics,
+    ChunkData, Error, Gid, LayerTileData, MapTilesetGid, Result, WarningKind, WriteOptions,
+};
 
-pub(crate) fn parse_data_line(
-    encoding: Option<String>,
-    compression: Option<String>,
+/// Reads the still-encoded contents of a `<data>` or `<chunk>` element, returning `None` if it
+/// is empty.
+pub(crate) fn read_data_line(
     parser: &mut impl Iterator<Item = XmlEventResult>,
-    tilesets: &[MapTilesetGid],
-) -> Result<Vec<Option<LayerTileData>>> {
-    match (encoding.as_deref(), compression.as_deref()) {
-        (Some("csv"), None) => decode_csv(parser, tilesets),
-
-        (Some("base64"), None) => parse_base64(parser).map(|v| convert_to_tiles(&v, tilesets)),
-        (Some("base64"), Some("zlib")) => parse_base64(parser)
-            .and_then(|data| process_decoder(libflate::zlib::Decoder::new(&data[..])))
-            .map(|v| convert_to_tiles(&v, tilesets)),
-        (Some("base64"), Some("gzip")) => parse_base64(parser)
-            .and_then(|data| process_decoder(libflate::gzip::Decoder::new(&data[..])))
-            .map(|v| convert_to_tiles(&v, tilesets)),
-        #[cfg(feature = "zstd")]
-        (Some("base64"), Some("zstd")) => parse_base64(parser)
-            .and_then(|data| process_decoder(zstd::stream::read::Decoder::with_buffer(&data[..])))
-            .map(|v| convert_to_tiles(&v, tilesets)),
+    element: &str,
+) -> Result<Option<String>> {
+    // Very old maps list their tiles as `<tile gid="..."/>` elements instead, which are gathered
+    // as CSV since they have no encoding.
+    let mut tiles: Option<String> = None;
+    for next in parser {
+        match next.map_err(Error::XmlDecodingError)? {
+            XmlEvent::Characters(s) if tiles.is_none() => return Ok(Some(s)),
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } if name.local_name == "tile" => {
+                let gid = attributes
+                    .iter()
+                    .find(|attr| attr.name.local_name == "gid")
+                    .map_or("0", |attr| attr.value.as_str());
+                let tiles = tiles.get_or_insert_with(String::new);
+                if !tiles.is_empty() {
+                    tiles.push(',');
+                }
+                tiles.push_str(gid);
+            }
+            XmlEvent::EndElement { name, .. } if name.local_name == element => {
+                return Ok(tiles);
+            }
+            _ => {}
+        }
+    }
+    Err(Error::PrematureEnd("Ran out of XML data".to_owned()))
+}
+
+/// Decodes the contents of a tile layer's (or chunk's) data, encoded as specified, into raw GIDs.
+/// Compressed data is decompressed using the matching decoder in `decoders`.
+pub(crate) fn decode_data_gids(
+    encoding: Option<&str>,
+    compression: Option<&str>,
+    data: &str,
+    decoders: &DataDecoders,
+) -> Result<Vec<u32>> {
+    match (encoding, compression.map(|c| (c, decoders.get(c)))) {
+        (Some("csv"), None) => decode_csv(data),
+        // Tiles listed as XML elements, gathered as CSV by `read_data_line()`.
+        (None, None) => decode_csv(data),
+
+        (Some("base64"), None) => decode_base64(data).map(|v| convert_to_gids(&v)),
+        (Some("base64"), Some((_, Some(decoder)))) => decode_base64(data)
+            .and_then(|data| decoders.decompress(decoder, &data))
+            .map(|v| convert_to_gids(&v)),
 
         _ => Err(Error::InvalidEncodingFormat {
+            encoding: encoding.map(str::to_owned),
+            compression: compression.map(str::to_owned),
+        }),
+    }
+}
+
+/// Reports the GIDs that don't refer to a tile of any of the tilesets given as warnings. Each
+/// invalid GID is only reported once.
+pub(crate) fn check_gids(gids: &[u32], tilesets: &[MapTilesetGid], diagnostics: &Diagnostics) {
+    let mut reported = Vec::new();
+    for &bits in gids {
+        let gid = Gid(bits & !LayerTileData::ALL_FLIP_FLAGS);
+        if gid == Gid::EMPTY || reported.contains(&gid) {
+            continue;
+        }
+        let valid = get_tileset_for_gid(tilesets, gid)
+            .is_some_and(|(_, ts)| ts.tileset.get_tile(gid.0 - ts.first_gid.0).is_some());
+        if !valid {
+            reported.push(gid);
+            diagnostics.warn(WarningKind::InvalidGid { gid: gid.0 });
+        }
+    }
+}
+
+/// Resolves raw GIDs into the tiles they refer to.
+pub(crate) fn gids_to_tiles(
+    gids: &[u32],
+    tilesets: &[MapTilesetGid],
+) -> Vec<Option<LayerTileData>> {
+    gids.iter()
+        .map(|&bits| LayerTileData::from_bits(bits, tilesets))
+        .collect()
+}
+
+/// How the data of tile layers should be parsed, as configured on the [`Loader`](crate::Loader).
+#[derive(Clone, Copy)]
+pub(crate) struct TileDataOptions<'a> {
+    /// Whether to keep the data encoded until it is first needed.
+    pub defer: bool,
+    /// Whether finite layers should keep their tiles as raw GIDs.
+    pub compact: bool,
+    pub decoders: &'a DataDecoders,
+    /// Counts the tiles of the map being loaded.
+    pub tiles: &'a TileBudget,
+    /// Where invalid GIDs found while parsing are reported.
+    pub diagnostics: &'a Diagnostics,
+}
+
+/// How the data of a tile layer is encoded, along with the tilesets needed to make sense of the
+/// decoded GIDs. Kept around when decoding is deferred.
+#[derive(Clone)]
+pub(crate) struct DataEncoding {
+    pub encoding: Option<String>,
+    pub compression: Option<String>,
+    pub tilesets: Vec<MapTilesetGid>,
+    pub decoders: DataDecoders,
+}
+
+impl DataEncoding {
+    pub(crate) fn decode_gids(&self, data: &str) -> Result<Vec<u32>> {
+        decode_data_gids(
+            self.encoding.as_deref(),
+            self.compression.as_deref(),
+            data,
+            &self.decoders,
+        )
+    }
+}
+
+/// How the data of a tile layer was encoded in the file it was loaded from, kept to write it back
+/// the same way.
+#[derive(Debug, Clone)]
+pub(crate) struct SourceEncoding {
+    pub encoding: Option<String>,
+    pub compression: Option<String>,
+    /// The size of the chunks of infinite layers.
+    pub chunk_size: Option<(u32, u32)>,
+    /// The contents of compressed data as read, by the position of the top-left tile of the
+    /// chunk they belong to, or `(0, 0)` for finite layers. Compressors don't all produce the same
+    /// output, so data is written back as is as long as its tiles haven't changed.
+    pub compressed: HashMap<(i32, i32), String>,
+    pub decoders: DataDecoders,
+}
+
+impl SourceEncoding {
+    pub(crate) fn new(
+        encoding: Option<String>,
+        compression: Option<String>,
+        decoders: &DataDecoders,
+    ) -> Self {
+        Self {
             encoding,
             compression,
-        }),
+            chunk_size: None,
+            compressed: HashMap::new(),
+            decoders: decoders.clone(),
+        }
+    }
+
+    /// Records the contents of the data or chunk at `position`, if compressed.
+    pub(crate) fn record(&mut self, position: (i32, i32), data: &str) {
+        if self.compression.is_some() {
+            self.compressed.insert(position, data.trim().to_owned());
+        }
     }
 }
 
-fn parse_base64(parser: &mut impl Iterator<Item = XmlEventResult>) -> Result<Vec<u8>> {
-    for next in parser {
-        match next.map_err(Error::XmlDecodingError)? {
-            XmlEvent::Characters(s) => {
-                return base64::engine::GeneralPurpose::new(
-                    &base64::alphabet::STANDARD,
-                    base64::engine::general_purpose::PAD,
-                )
-                .decode(s.trim().as_bytes())
-                .map_err(Error::Base64DecodingError)
-            }
-            XmlEvent::EndElement { name, .. } if name.local_name == "data" => {
-                return Ok(Vec::new());
+/// Encodes the data of a tile layer when writing it, with the encoding of the layer's source if
+/// preserved, or with the encoding of the write options otherwise.
+pub(crate) struct DataWriter<'a> {
+    encoding: &'a str,
+    compression: Option<&'a str>,
+    chunk_size: (u32, u32),
+    source: Option<&'a SourceEncoding>,
+}
+
+impl<'a> DataWriter<'a> {
+    pub(crate) fn new(source: Option<&'a SourceEncoding>, options: WriteOptions) -> Self {
+        let source = source.filter(|_| options.preserve_formatting);
+        let chunk_size = source
+            .and_then(|source| source.chunk_size)
+            .unwrap_or((ChunkData::WIDTH, ChunkData::HEIGHT));
+        let encodable = source.and_then(|source| {
+            let encoding = source.encoding.as_deref()?;
+            let compression = source.compression.as_deref();
+            can_encode(encoding, compression).then_some((encoding, compression))
+        });
+        match encodable {
+            Some((encoding, compression)) => Self {
+                encoding,
+                compression,
+                chunk_size,
+                source,
+            },
+            None => Self {
+                encoding: options.tile_data_encoding.encoding(),
+                compression: options.tile_data_encoding.compression(),
+                chunk_size,
+                source: None,
+            },
+        }
+    }
+
+    /// The attributes of the `<data>` element.
+    pub(crate) fn attributes(&self) -> Vec<(&'static str, String)> {
+        let mut attrs = vec![("encoding", self.encoding.to_owned())];
+        if let Some(compression) = self.compression {
+            attrs.push(("compression", compression.to_owned()));
+        }
+        attrs
+    }
+
+    /// The width and height of the chunks of infinite layers.
+    pub(crate) fn chunk_size(&self) -> (u32, u32) {
+        self.chunk_size
+    }
+
+    /// Returns the contents of the `<data>` or `<chunk>` element holding the GIDs given, `width`
+    /// tiles wide, whose top-left tile is at `position`.
+    pub(crate) fn encode(&self, position: (i32, i32), gids: &[u32], width: u32) -> Result<String> {
+        let source = self
+            .source
+            .and_then(|source| Some((source, source.compressed.get(&position)?)));
+        if let Some((source, data)) = source {
+            let unchanged = decode_data_gids(
+                Some(self.encoding),
+                self.compression,
+                data,
+                &source.decoders,
+            )
+            .is_ok_and(|source_gids| source_gids == gids);
+            if unchanged {
+                return Ok(format!("\n   {}\n  ", data));
             }
-            _ => {}
         }
+        encode_data_gids(self.encoding, self.compression, gids, width)
     }
-    Err(Error::PrematureEnd("Ran out of XML data".to_owned()))
 }
 
-fn process_decoder(decoder: std::io::Result<impl Read>) -> Result<Vec<u8>> {
-    decoder
-        .and_then(|mut decoder| {
-            let mut data = Vec::new();
-            decoder.read_to_end(&mut data)?;
-            Ok(data)
-        })
-        .map_err(Error::DecompressingError)
+/// Returns whether tile data can be written with the encoding and compression given.
+fn can_encode(encoding: &str, compression: Option<&str>) -> bool {
+    match (encoding, compression) {
+        ("csv", None) | ("base64", None) => true,
+        ("base64", Some(compression)) => COMPRESSIONS.contains(&compression),
+        _ => false,
+    }
 }
 
-fn decode_csv(
-    parser: &mut impl Iterator<Item = XmlEventResult>,
-    tilesets: &[MapTilesetGid],
-) -> Result<Vec<Option<LayerTileData>>> {
-    for next in parser {
-        match next.map_err(Error::XmlDecodingError)? {
-            XmlEvent::Characters(s) => {
-                let tiles = s
-                    .split(',')
-                    .map(|v| v.trim().parse().unwrap())
-                    .map(|bits| LayerTileData::from_bits(bits, tilesets))
-                    .collect();
-                return Ok(tiles);
+/// The compressions tile data can be written with.
+#[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+const COMPRESSIONS: &[&str] = &["zlib", "gzip", "zstd"];
+#[cfg(not(all(feature = "zstd", not(target_arch = "wasm32"))))]
+const COMPRESSIONS: &[&str] = &["zlib", "gzip"];
+
+/// Encodes raw GIDs, `width` tiles per row, the way Tiled formats the contents of `<data>` and
+/// `<chunk>` elements.
+fn encode_data_gids(
+    encoding: &str,
+    compression: Option<&str>,
+    gids: &[u32],
+    width: u32,
+) -> Result<String> {
+    if encoding == "csv" && compression.is_none() {
+        return Ok(encode_csv(gids, width));
+    }
+    let bytes: Vec<u8> = gids.iter().flat_map(|gid| gid.to_le_bytes()).collect();
+    let data = match (encoding, compression) {
+        ("base64", None) => bytes,
+        ("base64", Some(compression)) => compress(compression, &bytes)?,
+        _ => {
+            return Err(Error::InvalidEncodingFormat {
+                encoding: Some(encoding.to_owned()),
+                compression: compression.map(str::to_owned),
+            })
+        }
+    };
+    let data = base64::engine::GeneralPurpose::new(
+        &base64::alphabet::STANDARD,
+        base64::engine::general_purpose::PAD,
+    )
+    .encode(data);
+    Ok(format!("\n   {}\n  ", data))
+}
+
+fn encode_csv(gids: &[u32], width: u32) -> String {
+    let rows: Vec<String> = gids
+        .chunks(width.max(1) as usize)
+        .map(|row| row.iter().map(u32::to_string).collect::<Vec<_>>().join(","))
+        .collect();
+    format!("\n{}\n", rows.join(",\n"))
+}
+
+fn compress(compression: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let io_error = |err| Error::XmlEncodingError(xml::writer::Error::Io(err));
+    match compression {
+        "zlib" => {
+            let mut encoder = libflate::zlib::Encoder::new(Vec::new()).map_err(io_error)?;
+            encoder.write_all(data).map_err(io_error)?;
+            encoder.finish().into_result().map_err(io_error)
+        }
+        "gzip" => {
+            let mut encoder = libflate::gzip::Encoder::new(Vec::new()).map_err(io_error)?;
+            encoder.write_all(data).map_err(io_error)?;
+            encoder.finish().into_result().map_err(io_error)
+        }
+        #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+        "zstd" => zstd::encode_all(data, 0).map_err(io_error),
+        _ => Err(Error::InvalidEncodingFormat {
+            encoding: Some("base64".to_owned()),
+            compression: Some(compression.to_owned()),
+        }),
+    }
+}
+
+/// Still-encoded tile data that can be decoded into [`Self::Output`] on demand.
+pub(crate) trait EncodedTiles {
+    type Output: Default;
+
+    fn decode(&self) -> Result<Self::Output>;
+}
+
+/// Tile data that is either decoded while parsing or, if the loader defers decoding, the first
+/// time it is needed.
+///
+/// A failure to decode is remembered, so that accessing the tiles of a broken layer yields no
+/// tiles while [`LazyTiles::ensure_decoded`] keeps reporting the error.
+pub(crate) struct LazyTiles<E: EncodedTiles> {
+    decoded: OnceLock<(E::Output, bool)>,
+    // Boxed since most layers are decoded while loading, and don't keep their encoded data.
+    encoded: Option<Box<E>>,
+}
+
+impl<E: EncodedTiles> LazyTiles<E> {
+    pub(crate) fn decoded(tiles: E::Output) -> Self {
+        Self {
+            decoded: OnceLock::from((tiles, true)),
+            encoded: None,
+        }
+    }
+
+    pub(crate) fn deferred(encoded: E) -> Self {
+        Self {
+            decoded: OnceLock::new(),
+            encoded: Some(Box::new(encoded)),
+        }
+    }
+
+    /// Returns the decoded tiles, decoding them first if needed.
+    pub(crate) fn get(&self) -> &E::Output {
+        &self
+            .decoded
+            .get_or_init(|| match self.encoded.as_deref().map(E::decode) {
+                Some(Ok(tiles)) => (tiles, true),
+                Some(Err(_)) => (Default::default(), false),
+                None => (Default::default(), true),
+            })
+            .0
+    }
+
+    /// Returns the decoded tiles for modification, decoding them first if needed. The tiles of
+    /// data that fails to decode are replaced by the modified ones.
+    pub(crate) fn get_mut(&mut self) -> &mut E::Output {
+        self.get();
+        self.encoded = None;
+        let decoded = self.decoded.get_mut().expect("tiles were just decoded");
+        decoded.1 = true;
+        &mut decoded.0
+    }
+
+    pub(crate) fn is_decoded(&self) -> bool {
+        self.decoded.get().is_some()
+    }
+
+    pub(crate) fn ensure_decoded(&self) -> Result<()> {
+        let encoded = match (self.decoded.get(), &self.encoded) {
+            (Some((_, true)), _) | (_, None) => return Ok(()),
+            (_, Some(encoded)) => encoded,
+        };
+        match encoded.decode() {
+            Ok(tiles) => {
+                let _ = self.decoded.set((tiles, true));
+                Ok(())
             }
-            XmlEvent::EndElement { name, .. } if name.local_name == "data" => {
-                return Ok(Vec::new());
+            Err(err) => {
+                let _ = self.decoded.set((Default::default(), false));
+                Err(err)
             }
-            _ => {}
         }
     }
-    Err(Error::PrematureEnd("Ran out of XML data".to_owned()))
 }
 
-fn convert_to_tiles(data: &[u8], tilesets: &[MapTilesetGid]) -> Vec<Option<LayerTileData>> {
-    data.chunks_exact(4)
-        .map(|chunk| {
-            let bits = u32::from_le_bytes(chunk.try_into().unwrap());
-            LayerTileData::from_bits(bits, tilesets)
+impl<E: EncodedTiles + Clone> Clone for LazyTiles<E>
+where
+    E::Output: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            decoded: self.decoded.clone(),
+            encoded: self.encoded.clone(),
+        }
+    }
+}
+
+impl<E: EncodedTiles> Default for LazyTiles<E> {
+    fn default() -> Self {
+        Self {
+            decoded: OnceLock::new(),
+            encoded: None,
+        }
+    }
+}
+
+impl<E: EncodedTiles> PartialEq for LazyTiles<E>
+where
+    E::Output: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+/// Decodes the `data` member of a JSON tile layer or chunk into raw GIDs. The data is either an
+/// array of GIDs or a string encoded as specified.
+#[cfg(feature = "json")]
+pub(crate) fn decode_json_gids(
+    data: &serde_json::Value,
+    encoding: &str,
+    compression: Option<&str>,
+    decoders: &DataDecoders,
+) -> Result<Vec<u32>> {
+    match (encoding, data) {
+        ("csv", serde_json::Value::Array(gids)) => gids
+            .iter()
+            .map(|gid| {
+                crate::parse::json::as_u32(gid).ok_or_else(|| {
+                    Error::MalformedAttributes("Error parsing attribute 'data'".to_owned())
+                })
+            })
+            .collect(),
+        ("base64", serde_json::Value::String(data)) => {
+            decode_data_gids(Some(encoding), compression, data, decoders)
+        }
+        _ => Err(Error::InvalidEncodingFormat {
+            encoding: Some(encoding.to_owned()),
+            compression: compression.map(str::to_owned),
+        }),
+    }
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>> {
+    base64::engine::GeneralPurpose::new(
+        &base64::alphabet::STANDARD,
+        base64::engine::general_purpose::PAD,
+    )
+    .decode(data.trim().as_bytes())
+    .map_err(Error::Base64DecodingError)
+}
+
+fn decode_csv(data: &str) -> Result<Vec<u32>> {
+    #[cfg(feature = "fast-csv")]
+    if let Some(gids) = super::fast_csv::decode_csv(data) {
+        return Ok(gids);
+    }
+    data.split(',')
+        .map(|v| {
+            v.trim().parse().map_err(|_| {
+                Error::MalformedAttributes(format!(
+                    "tile layer data contains an invalid GID: '{}'",
+                    v.trim()
+                ))
+            })
         })
         .collect()
 }
+
+fn convert_to_gids(data: &[u8]) -> Vec<u32> {
+    data.chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect()
+}
@@ -6,29 +6,94 @@
 #![deny(missing_debug_implementations)]
 
 mod animation;
+#[cfg(feature = "zip")]
+mod archive;
+#[cfg(feature = "atlas")]
+mod atlas;
+pub mod autotile;
 mod cache;
+mod combinators;
+mod compatibility;
+pub mod coords;
+mod decoder;
+mod dependencies;
+mod diff;
+mod embedded;
 mod error;
+mod events;
+mod extensions;
+mod gid_mapping;
 mod image;
 mod layers;
+mod limits;
 mod loader;
 mod map;
+mod memory;
+#[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+mod mmap;
+mod object_index;
 mod objects;
 mod parse;
+mod project;
 mod properties;
+#[cfg(feature = "http")]
+mod remote;
+#[cfg(feature = "render")]
+pub mod render;
+#[cfg(feature = "async")]
+mod stream;
 mod template;
 mod tile;
 mod tileset;
 mod util;
+mod validate;
+mod virtual_path;
+mod warning;
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(feature = "world")]
+mod world;
+mod write;
 
 pub use animation::*;
+#[cfg(feature = "zip")]
+pub use archive::*;
+#[cfg(feature = "atlas")]
+pub use atlas::*;
 pub use cache::*;
+pub use combinators::*;
+pub use compatibility::*;
+pub use decoder::DataDecoder;
+pub use diff::*;
+pub use embedded::*;
 pub use error::*;
+pub use events::*;
+pub use extensions::XmlExtensions;
+pub use gid_mapping::*;
 pub use image::*;
 pub use layers::*;
+pub use limits::{LoadLimit, LoadLimits};
 pub use loader::*;
 pub use map::*;
+pub use memory::*;
+#[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+pub use mmap::*;
+pub use object_index::*;
 pub use objects::*;
+pub use project::*;
 pub use properties::*;
+#[cfg(feature = "http")]
+pub use remote::*;
 pub use template::*;
 pub use tile::*;
+#[cfg(feature = "derive")]
+pub use tiled_derive::FromProperties;
 pub use tileset::*;
+pub use validate::*;
+pub use virtual_path::*;
+pub use warning::{ElementLocation, ErrorPolicy, Warning, WarningKind};
+#[cfg(feature = "watch")]
+pub use watch::*;
+#[cfg(feature = "world")]
+pub use world::*;
+pub use write::{TileDataEncoding, WriteOptions};
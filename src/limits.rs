@@ -0,0 +1,98 @@
+use std::{cell::Cell, fmt};
+
+use crate::{Error, Result};
+
+/// Limits on the resources a [`Loader`](crate::Loader) may use to load a map, which guard
+/// against maps made to exhaust the memory of the program, such as maps uploaded by users.
+///
+/// Every limit is disabled by default. Loading stops with [`Error::LimitExceeded`] as soon as
+/// one of the limits set is exceeded.
+///
+/// ## Example
+/// ```
+/// use tiled::{Error, Loader, LoadLimit, LoadLimits};
+///
+/// let mut loader = Loader::new();
+/// loader.set_limits(LoadLimits {
+///     max_tiles: Some(1_000),
+///     ..LoadLimits::default()
+/// });
+/// // The map has a 100x100 tile layer.
+/// let err = loader.load_tmx_map("assets/tiled_base64_zlib.tmx").unwrap_err();
+/// assert!(matches!(
+///     err.inner(),
+///     Error::LimitExceeded { limit: LoadLimit::Tiles, max: 1_000 }
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LoadLimits {
+    /// The largest amount of bytes the compressed data of a single tile layer or chunk may
+    /// decompress to.
+    ///
+    /// The built-in decoders stop decompressing once the limit is exceeded, so compressed data
+    /// expanding to far more than its size can't exhaust memory. Decoders registered with
+    /// [`Loader::register_decoder()`](crate::Loader::register_decoder()) only do so if they
+    /// implement [`DataDecoder::decompress_limited()`](crate::DataDecoder::decompress_limited()).
+    pub max_decompressed_bytes: Option<usize>,
+    /// The largest amount of tiles a map may have, which is the sum of the sizes of its finite
+    /// tile layers and of the chunks of its infinite ones.
+    pub max_tiles: Option<u64>,
+    /// The deepest group layers may be nested, group layers at the root of a map being at a depth
+    /// of one. Parsing group layers is recursive, so deeply nested groups could otherwise
+    /// overflow the stack.
+    pub max_group_depth: Option<usize>,
+}
+
+/// One of the [`LoadLimits`], as reported by [`Error::LimitExceeded`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LoadLimit {
+    /// [`LoadLimits::max_decompressed_bytes`].
+    DecompressedBytes,
+    /// [`LoadLimits::max_tiles`].
+    Tiles,
+    /// [`LoadLimits::max_group_depth`].
+    GroupDepth,
+}
+
+impl fmt::Display for LoadLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LoadLimit::DecompressedBytes => "decompressed tile data size",
+            LoadLimit::Tiles => "tile count",
+            LoadLimit::GroupDepth => "group layer depth",
+        })
+    }
+}
+
+/// Counts the tiles of the map being loaded against [`LoadLimits::max_tiles`].
+#[derive(Debug, Default)]
+pub(crate) struct TileBudget {
+    max: Option<u64>,
+    used: Cell<u64>,
+}
+
+impl TileBudget {
+    pub(crate) fn new(max: Option<u64>) -> Self {
+        Self {
+            max,
+            used: Cell::new(0),
+        }
+    }
+
+    /// Counts the tiles of a layer or chunk of the size given.
+    pub(crate) fn take(&self, width: u32, height: u32) -> Result<()> {
+        let used = self
+            .used
+            .get()
+            .saturating_add(u64::from(width) * u64::from(height));
+        self.used.set(used);
+        match self.max {
+            Some(max) if used > max => Err(Error::LimitExceeded {
+                limit: LoadLimit::Tiles,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,6 +1,18 @@
-use std::{fs::File, io::Read, path::Path};
+use std::{
+    any::Any,
+    collections::HashSet,
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use crate::{DefaultResourceCache, Map, ResourceCache, Result, Tileset};
+use crate::{
+    compatibility::CompatibilityIssue, decoder::DataDecoders, image::ImageHook, limits::TileBudget,
+    parse::LoadContext, warning::Diagnostics, DataDecoder, DefaultResourceCache, ErrorPolicy,
+    Image, ImageHandle, LoadLimits, Map, Project, ResourceCache, ResourcePath, Result, Template,
+    Tileset, Warning,
+};
 
 /// A trait defining types that can load data from a [`ResourcePath`](crate::ResourcePath).
 ///
@@ -28,7 +40,7 @@ use crate::{DefaultResourceCache, Map, ResourceCache, Result, Tileset};
 /// ```
 pub trait ResourceReader {
     /// The type of the resource that the reader provides. For example, for
-    /// [`FilesystemResourceReader`], this is defined as [`File`].
+    /// [`FilesystemResourceReader`], this is defined as [`File`](std::fs::File).
     type Resource: Read;
     /// The type that is returned if [`read_from()`](Self::read_from()) fails. For example, for
     /// [`FilesystemResourceReader`], this is defined as [`std::io::Error`].
@@ -36,27 +48,93 @@ pub trait ResourceReader {
 
     /// Try to return a reader object from a path into the resources filesystem.
     fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error>;
+
+    /// Try to list the paths of the files contained directly within a directory of the resources
+    /// filesystem.
+    ///
+    /// This is used to find the maps matching the patterns of a world. The default implementation
+    /// lists no files at all, so readers that cannot enumerate their contents don't need to
+    /// implement it.
+    fn list_files(&mut self, directory: &Path) -> std::result::Result<Vec<PathBuf>, Self::Error> {
+        let _ = directory;
+        Ok(Vec::new())
+    }
+
+    /// Returns a reader that reads files using this reader, and using `fallback` for the files
+    /// this reader fails to read.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{
+    ///     DefaultResourceCache, EmbeddedResourceReader, FilesystemResourceReader, Loader,
+    ///     ResourceReader,
+    /// };
+    ///
+    /// // A mod replacing the tileset used by the map.
+    /// let mod_files = EmbeddedResourceReader::new().with_file(
+    ///     "assets/tilesheet.tsx",
+    ///     include_bytes!("../assets/tilesheet_template.tsx"),
+    /// );
+    /// let reader = mod_files.or_else(FilesystemResourceReader);
+    /// let mut loader = Loader::with_cache_and_reader(DefaultResourceCache::new(), reader);
+    /// let map = loader.load_tmx_map("assets/tiled_base64_external.tmx")?;
+    /// assert_eq!(map.tilesets()[0].name, "tilesheet_template");
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn or_else<R: ResourceReader>(self, fallback: R) -> crate::FallbackReader<Self, R>
+    where
+        Self: Sized,
+    {
+        crate::FallbackReader::new(self, fallback)
+    }
 }
 
-/// A [`ResourceReader`] that reads from [`File`] handles.
+/// A [`ResourceReader`] that reads from [`File`](std::fs::File) handles.
+///
+/// Not available on `wasm32-unknown-unknown`, which has no filesystem; Use
+/// [`MemoryResourceReader`](crate::MemoryResourceReader) or
+/// [`EmbeddedResourceReader`](crate::EmbeddedResourceReader) there instead.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FilesystemResourceReader;
 
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 impl FilesystemResourceReader {
     fn new() -> Self {
         Self
     }
 }
 
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 impl ResourceReader for FilesystemResourceReader {
-    type Resource = File;
+    type Resource = std::fs::File;
     type Error = std::io::Error;
 
     fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
         std::fs::File::open(path)
     }
+
+    fn list_files(&mut self, directory: &Path) -> std::result::Result<Vec<PathBuf>, Self::Error> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(directory)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                files.push(entry.path());
+            }
+        }
+        Ok(files)
+    }
 }
 
+/// The reader used by [`Loader`] unless another one is given: [`FilesystemResourceReader`], or
+/// [`MemoryResourceReader`](crate::MemoryResourceReader) on `wasm32-unknown-unknown`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+type DefaultResourceReader = FilesystemResourceReader;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+type DefaultResourceReader = crate::MemoryResourceReader;
+
 /// A type used for loading [`Map`]s and [`Tileset`]s.
 ///
 /// Internally, it holds a [`ResourceCache`] that, as its name implies, caches intermediate loading
@@ -72,19 +150,49 @@ impl ResourceReader for FilesystemResourceReader {
 #[derive(Debug, Clone, Default)]
 pub struct Loader<
     Cache: ResourceCache = DefaultResourceCache,
-    Reader: ResourceReader = FilesystemResourceReader,
+    Reader: ResourceReader = DefaultResourceReader,
 > {
     cache: Cache,
     reader: Reader,
+    project: Option<Project>,
+    defer_tile_data: bool,
+    compact_tile_data: bool,
+    resolve_object_references: bool,
+    decoders: DataDecoders,
+    limits: LoadLimits,
+    diagnostics: Diagnostics,
+    image_hook: Option<SharedImageHook>,
 }
 
+/// The hook set with [`Loader::on_image_loaded()`], shared by the clones of a loader.
+#[derive(Clone)]
+struct SharedImageHook(Arc<ImageHook>);
+
+impl fmt::Debug for SharedImageHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedImageHook")
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 impl Loader {
     /// Creates a new loader, creating a default resource cache and reader
     /// ([`DefaultResourceCache`] & [`FilesystemResourceReader`] respectively) in the process.
+    ///
+    /// Not available on `wasm32-unknown-unknown`, see [`FilesystemResourceReader`]; Use
+    /// [`Loader::with_cache_and_reader()`] there instead.
     pub fn new() -> Self {
         Self {
             cache: DefaultResourceCache::new(),
             reader: FilesystemResourceReader::new(),
+            project: None,
+            defer_tile_data: false,
+            compact_tile_data: false,
+            resolve_object_references: false,
+            decoders: DataDecoders::default(),
+            limits: LoadLimits::default(),
+            diagnostics: Diagnostics::default(),
+            image_hook: None,
         }
     }
 }
@@ -128,6 +236,10 @@ impl<Cache: ResourceCache, Reader: ResourceReader> Loader<Cache, Reader> {
     ///         _path: impl AsRef<tiled::ResourcePath>,
     ///         _template: Arc<tiled::Template>
     ///     ) {}
+    ///
+    ///     fn invalidate(&mut self, _path: impl AsRef<tiled::ResourcePath>) {}
+    ///
+    ///     fn clear(&mut self) {}
     /// }
     ///
     /// let mut loader = Loader::with_cache_and_reader(NoopResourceCache, FilesystemResourceReader);
@@ -143,7 +255,18 @@ impl<Cache: ResourceCache, Reader: ResourceReader> Loader<Cache, Reader> {
     /// # }
     /// ```
     pub fn with_cache_and_reader(cache: Cache, reader: Reader) -> Self {
-        Self { cache, reader }
+        Self {
+            cache,
+            reader,
+            project: None,
+            defer_tile_data: false,
+            compact_tile_data: false,
+            resolve_object_references: false,
+            decoders: DataDecoders::default(),
+            limits: LoadLimits::default(),
+            diagnostics: Diagnostics::default(),
+            image_hook: None,
+        }
     }
 
     /// Parses a file hopefully containing a Tiled map and tries to parse it. All external files
@@ -153,7 +276,39 @@ impl<Cache: ResourceCache, Reader: ResourceReader> Loader<Cache, Reader> {
     ///
     /// [internal loader cache]: Loader::cache()
     pub fn load_tmx_map(&mut self, path: impl AsRef<Path>) -> Result<Map> {
-        crate::parse::xml::parse_map(path.as_ref(), &mut self.reader, &mut self.cache)
+        crate::parse::xml::parse_map(path.as_ref(), &mut self.context())
+    }
+
+    /// Parses a Tiled map from any [`Read`] implementation, such as an in-memory buffer or a
+    /// network stream, instead of reading it through the loader's [`ResourceReader`].
+    ///
+    /// The map is treated as if it had been read from `virtual_path`: External files, such as
+    /// tilesets, templates and images, are resolved relative to it and still read using the
+    /// loader's [`ResourceReader`]. The path itself is never read.
+    ///
+    /// All intermediate objects such as map tilesets will be stored in the [internal loader cache].
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let data = std::fs::read("assets/tiled_base64_external.tmx").unwrap();
+    /// let mut loader = Loader::new();
+    /// // The map references "tilesheet.tsx", which is loaded from "assets/tilesheet.tsx".
+    /// let map = loader.load_tmx_map_from_reader(&data[..], "assets/virtual_map.tmx")?;
+    /// assert_eq!(map.tilesets()[0].name, "tilesheet");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [internal loader cache]: Loader::cache()
+    pub fn load_tmx_map_from_reader(
+        &mut self,
+        reader: impl Read,
+        virtual_path: impl AsRef<Path>,
+    ) -> Result<Map> {
+        crate::parse::xml::parse_map_from(reader, virtual_path.as_ref(), &mut self.context())
     }
 
     /// Parses a file hopefully containing a Tiled tileset and tries to parse it. All external files
@@ -166,7 +321,472 @@ impl<Cache: ResourceCache, Reader: ResourceReader> Loader<Cache, Reader> {
     /// This function will **not** cache the tileset inside the internal [`ResourceCache`], since
     /// in this context it is not an intermediate object.
     pub fn load_tsx_tileset(&mut self, path: impl AsRef<Path>) -> Result<Tileset> {
-        crate::parse::xml::parse_tileset(path.as_ref(), &mut self.reader, &mut self.cache)
+        crate::parse::xml::parse_tileset(path.as_ref(), &mut self.context())
+    }
+
+    /// Parses a Tiled tileset from any [`Read`] implementation, treating it as if it had been read
+    /// from `virtual_path`. See [`Loader::load_tmx_map_from_reader`] for details.
+    ///
+    /// ## Note
+    /// This function will **not** cache the tileset inside the internal [`ResourceCache`], since
+    /// in this context it is not an intermediate object.
+    pub fn load_tsx_tileset_from_reader(
+        &mut self,
+        reader: impl Read,
+        virtual_path: impl AsRef<Path>,
+    ) -> Result<Tileset> {
+        crate::parse::xml::parse_tileset_from(reader, virtual_path.as_ref(), &mut self.context())
+    }
+
+    /// Parses a file hopefully containing a Tiled map in the JSON format (usually with a `.tmj`
+    /// extension) and tries to parse it. All external files will be loaded relative to the path
+    /// given.
+    ///
+    /// External tilesets and templates referenced by the map may be in either the XML or the JSON
+    /// format, which is determined by their file extension.
+    ///
+    /// All intermediate objects such as map tilesets will be stored in the [internal loader cache].
+    ///
+    /// [internal loader cache]: Loader::cache()
+    #[cfg(feature = "json")]
+    pub fn load_json_map(&mut self, path: impl AsRef<Path>) -> Result<Map> {
+        crate::parse::json::parse_map(path.as_ref(), &mut self.context())
+    }
+
+    /// Loads a map again, along with all the tilesets and templates it references, even if they
+    /// are already cached. The cached ones are replaced by the reloaded ones, so that maps loaded
+    /// afterwards use them as well. This is useful to pick up changes to files being edited
+    /// while a game runs.
+    ///
+    /// Maps in the JSON format are recognized by their extension if the `json` feature is enabled;
+    /// Otherwise the map is parsed as a TMX file.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use std::sync::Arc;
+    ///
+    /// use tiled::Loader;
+    ///
+    /// let mut loader = Loader::new();
+    /// let map = loader.load_tmx_map("assets/tiled_base64_external.tmx")?;
+    /// let reloaded = loader.reload_map("assets/tiled_base64_external.tmx")?;
+    /// assert!(!Arc::ptr_eq(&map.tilesets()[0], &reloaded.tilesets()[0]));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reload_map(&mut self, path: impl AsRef<Path>) -> Result<Map> {
+        self.load_map_with_cache(path.as_ref(), |cache| RefreshingCache {
+            cache,
+            refreshed: HashSet::new(),
+        })
+        .0
+    }
+
+    /// Loads a map, recognizing the JSON format by its extension, with the cache of the loader
+    /// wrapped by `wrap`. Returns the wrapper along with the map.
+    pub(crate) fn load_map_with_cache<'a, C: ResourceCache + 'a>(
+        &'a mut self,
+        path: &Path,
+        wrap: impl FnOnce(&'a mut Cache) -> C,
+    ) -> (Result<Map>, C) {
+        let mut cache = wrap(&mut self.cache);
+        let mut ctx = LoadContext {
+            reader: &mut self.reader,
+            cache: &mut cache,
+            project: self.project.as_ref(),
+            defer_tile_data: self.defer_tile_data,
+            compact_tile_data: self.compact_tile_data,
+            resolve_object_references: self.resolve_object_references,
+            decoders: &self.decoders,
+            limits: self.limits,
+            tiles: TileBudget::new(self.limits.max_tiles),
+            group_depth: 0,
+            interner: Default::default(),
+            diagnostics: &self.diagnostics,
+            image_hook: self.image_hook.as_ref().map(|hook| &*hook.0),
+        };
+        let map = crate::parse::parse_map(path, &mut ctx);
+        (map, cache)
+    }
+
+    /// Parses a file hopefully containing a Tiled tileset in the JSON format (usually with a
+    /// `.tsj` extension) and tries to parse it. All external files will be loaded relative to the
+    /// path given.
+    ///
+    /// ## Note
+    /// This function will **not** cache the tileset inside the internal [`ResourceCache`], since
+    /// in this context it is not an intermediate object.
+    #[cfg(feature = "json")]
+    pub fn load_json_tileset(&mut self, path: impl AsRef<Path>) -> Result<Tileset> {
+        crate::parse::json::parse_tileset(path.as_ref(), &mut self.context())
+    }
+
+    /// Parses a file hopefully containing a Tiled world and tries to parse it. The maps of the
+    /// world are not loaded; Use [`Loader::load_tmx_map`] on their [`WorldMap::source`] paths to do
+    /// so.
+    ///
+    /// Maps matched by the world's [patterns](crate::World::patterns) are found by listing the files
+    /// next to the world file using [`ResourceReader::list_files`], and are included in
+    /// [`World::maps`](crate::World::maps) after the ones listed explicitly.
+    ///
+    /// [`WorldMap::source`]: crate::WorldMap::source
+    #[cfg(feature = "world")]
+    pub fn load_world(&mut self, path: impl AsRef<Path>) -> Result<crate::World> {
+        crate::parse::json::parse_world(path.as_ref(), &mut self.reader)
+    }
+
+    /// Parses a `.tiled-project` file and keeps it within the loader, replacing the previously
+    /// loaded project if any. Returns a reference to the loaded project.
+    ///
+    /// The project's [custom property types](crate::Project::property_types) can then be used to
+    /// resolve the custom properties of maps loaded by this loader.
+    #[cfg(feature = "json")]
+    pub fn load_project(&mut self, path: impl AsRef<Path>) -> Result<&Project> {
+        let project = crate::parse::json::parse_project(path.as_ref(), &mut self.reader)?;
+        Ok(self.project.insert(project))
+    }
+
+    /// Returns the project loaded with `Loader::load_project`, if any.
+    pub fn project(&self) -> Option<&Project> {
+        self.project.as_ref()
+    }
+
+    /// Sets whether the `<data>` of the tile layers of maps loaded from now on should be kept
+    /// encoded at parse time instead of being decoded right away. Disabled by default.
+    ///
+    /// Deferred layers are decoded the first time their tiles are accessed, or when calling
+    /// [`TileLayer::ensure_decoded()`](crate::TileLayer::ensure_decoded()). This makes loading
+    /// large maps with many layers cheaper when only a few of them are ever read.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{LayerType, Loader};
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.set_defer_tile_data(true);
+    /// let map = loader.load_tmx_map("assets/tiled_base64_zlib.tmx")?;
+    ///
+    /// if let LayerType::Tiles(layer) = map.get_layer(0).unwrap().layer_type() {
+    ///     assert!(!layer.is_decoded());
+    ///     layer.ensure_decoded()?;
+    ///     assert!(layer.get_tile(0, 0).is_some());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_defer_tile_data(&mut self, defer: bool) {
+        self.defer_tile_data = defer;
+    }
+
+    /// Returns whether the loader defers decoding the data of tile layers. See
+    /// [`Loader::set_defer_tile_data()`].
+    pub fn defer_tile_data(&self) -> bool {
+        self.defer_tile_data
+    }
+
+    /// Sets whether finite tile layers should store their tiles as raw GIDs, which takes a
+    /// fraction of the memory needed otherwise. This is worth it for very large maps.
+    ///
+    /// Tiles are then resolved each time they are accessed through
+    /// [`FiniteTileLayer::get_tile()`](crate::FiniteTileLayer::get_tile()), so that the rest of
+    /// the API works as usual. Borrowing tile data with
+    /// [`FiniteTileLayerData::get_tile_data()`](crate::FiniteTileLayerData::get_tile_data())
+    /// and modifying a layer resolve all of its tiles at once, though.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{LayerType, Loader, TileLayer};
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.set_compact_tile_data(true);
+    /// let map = loader.load_tmx_map("assets/tiled_base64_zlib.tmx")?;
+    ///
+    /// if let LayerType::Tiles(TileLayer::Finite(layer)) = map.get_layer(0).unwrap().layer_type() {
+    ///     assert!(layer.get_tile(0, 0).is_some());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_compact_tile_data(&mut self, compact: bool) {
+        self.compact_tile_data = compact;
+    }
+
+    /// Returns whether the loader stores the tiles of finite tile layers as raw GIDs. See
+    /// [`Loader::set_compact_tile_data()`].
+    pub fn compact_tile_data(&self) -> bool {
+        self.compact_tile_data
+    }
+
+    /// Sets whether the object properties of maps loaded from now on should be resolved right
+    /// after loading them. Disabled by default.
+    ///
+    /// Object properties only hold the id of the object they refer to, which can be resolved at
+    /// any time using [`Map::resolve_object_property()`]. When enabled, all the object properties
+    /// of the map, its layers and its objects are resolved once the map is loaded, and those
+    /// referring to objects that don't exist are reported as
+    /// [`WarningKind::UnresolvedObjectReference`](crate::WarningKind::UnresolvedObjectReference)
+    /// warnings.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.set_resolve_object_references(true);
+    /// let map = loader.load_tmx_map("assets/tiled_object_property.tmx")?;
+    /// assert!(loader.take_warnings().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_resolve_object_references(&mut self, resolve: bool) {
+        self.resolve_object_references = resolve;
+    }
+
+    /// Returns whether the loader resolves the object properties of maps once they are loaded.
+    /// See [`Loader::set_resolve_object_references()`].
+    pub fn resolve_object_references(&self) -> bool {
+        self.resolve_object_references
+    }
+
+    /// Sets the limits on the resources used to load maps from now on, which guard against
+    /// untrusted maps made to exhaust memory. No limits are set by default.
+    ///
+    /// See [`LoadLimits`] for the limits available.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{Error, Loader, LoadLimit, LoadLimits};
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.set_limits(LoadLimits {
+    ///     max_decompressed_bytes: Some(1024),
+    ///     ..LoadLimits::default()
+    /// });
+    /// // The layer of the map decompresses to 100x100 GIDs of 4 bytes each.
+    /// let err = loader.load_tmx_map("assets/tiled_base64_zlib.tmx").unwrap_err();
+    /// assert!(matches!(
+    ///     err.inner(),
+    ///     Error::LimitExceeded { limit: LoadLimit::DecompressedBytes, .. }
+    /// ));
+    /// ```
+    pub fn set_limits(&mut self, limits: LoadLimits) {
+        self.limits = limits;
+        self.decoders.max_decompressed_bytes = limits.max_decompressed_bytes;
+    }
+
+    /// Returns the limits on the resources used to load maps. See [`Loader::set_limits()`].
+    pub fn limits(&self) -> LoadLimits {
+        self.limits
+    }
+
+    /// Registers a decoder for tile layer data compressed with the given `compression`, as
+    /// specified by the `compression` attribute of the data. Replaces the decoder previously
+    /// registered for the same compression, if any, including the built-in ones.
+    ///
+    /// See [`DataDecoder`] for an example.
+    pub fn register_decoder(
+        &mut self,
+        compression: impl Into<String>,
+        decoder: impl DataDecoder + 'static,
+    ) {
+        self.decoders
+            .insert(compression.into(), std::sync::Arc::new(decoder));
+    }
+
+    /// Checks a map for features of the files it was loaded from that the crate, or the decoders
+    /// of this loader, may not support, so that content ignored while loading it doesn't go
+    /// unnoticed. Returns them in the order they were found, or an empty list if there are none.
+    ///
+    /// See [`CompatibilityIssue`] for the issues checked.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let mut loader = tiled::Loader::new();
+    /// let map = loader.load_tmx_map("assets/tiled_base64_zlib.tmx")?;
+    /// assert_eq!(loader.check_compatibility(&map), vec![]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_compatibility(&self, map: &Map) -> Vec<CompatibilityIssue> {
+        crate::compatibility::check_map(map, &self.decoders)
+    }
+
+    /// Sets what the loader does when it finds an issue that only affects part of a file, such as
+    /// a property of an unknown type or a broken tile in a tileset. [`ErrorPolicy::Fail`] by
+    /// default.
+    ///
+    /// Under [`ErrorPolicy::SkipAndWarn`], the items affected are left out of the files loaded,
+    /// and reported as warnings that can be retrieved with [`Loader::take_warnings()`].
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{ErrorPolicy, Loader};
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.set_error_policy(ErrorPolicy::SkipAndWarn);
+    /// let map = loader.load_tmx_map("assets/tiled_base64_external.tmx")?;
+    /// for warning in loader.take_warnings() {
+    ///     println!("{}", warning);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.diagnostics.set_policy(policy);
+    }
+
+    /// Returns what the loader does when it finds an issue that only affects part of a file. See
+    /// [`Loader::set_error_policy()`].
+    pub fn error_policy(&self) -> ErrorPolicy {
+        self.diagnostics.policy()
+    }
+
+    /// Sets whether the loader is strict about the elements and attributes of the XML files it
+    /// loads. Disabled by default.
+    ///
+    /// Elements and attributes that aren't part of the TMX format, such as misspelled ones in
+    /// hand-written files or ones added by newer versions of Tiled, are normally ignored, with
+    /// only the unknown attributes of known elements reported as
+    /// [`WarningKind::UnknownAttribute`](crate::WarningKind::UnknownAttribute) warnings. In
+    /// strict mode, they fail loading with [`Error::UnknownElement`](crate::Error::UnknownElement)
+    /// and [`Error::UnknownAttribute`](crate::Error::UnknownAttribute) errors; Under
+    /// [`ErrorPolicy::SkipAndWarn`], they are reported as
+    /// [`WarningKind::UnknownElement`](crate::WarningKind::UnknownElement) and
+    /// [`WarningKind::UnknownAttribute`](crate::WarningKind::UnknownAttribute) warnings instead.
+    ///
+    /// Attributes in a namespace, and the contents of unknown elements, are never checked. JSON
+    /// files aren't checked either.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Error, ErrorPolicy, Loader, WarningKind};
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.set_strict(true);
+    /// let err = loader.load_tmx_map("assets/tiled_unknown_elements.tmx").unwrap_err();
+    /// assert!(matches!(err.inner(), Error::UnknownElement { element, .. } if element == "layre"));
+    ///
+    /// loader.set_error_policy(ErrorPolicy::SkipAndWarn);
+    /// let map = loader.load_tmx_map("assets/tiled_unknown_elements.tmx")?;
+    /// assert!(loader
+    ///     .take_warnings()
+    ///     .iter()
+    ///     .any(|warning| matches!(&warning.kind, WarningKind::UnknownElement { element, .. } if element == "layre")));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_strict(&mut self, strict: bool) {
+        self.diagnostics.set_strict(strict);
+    }
+
+    /// Returns whether the loader is strict about the elements and attributes of the XML files it
+    /// loads. See [`Loader::set_strict()`].
+    pub fn strict(&self) -> bool {
+        self.diagnostics.strict()
+    }
+
+    /// Sets whether the loader keeps the elements and attributes of the XML files it loads that
+    /// aren't part of the TMX format, such as those added by other tools. Disabled by default.
+    ///
+    /// They are kept in the `extensions` of the maps, tilesets, tiles and objects they were found
+    /// in, and written back by [`Tileset::write_tsx()`](crate::Tileset::write_tsx()) and
+    /// [`Template::write_tx()`](crate::Template::write_tx()). Tilesets already in the cache
+    /// keep the extensions they were loaded with.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.set_preserve_extensions(true);
+    /// let tileset = loader.load_tsx_tileset("assets/tiled_extensions.tsx")?;
+    /// assert_eq!(tileset.extensions.attribute("editor:color"), Some("#ff0000"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_preserve_extensions(&mut self, preserve: bool) {
+        self.diagnostics.set_preserve_extensions(preserve);
+    }
+
+    /// Returns whether the loader keeps the elements and attributes of the XML files it loads
+    /// that aren't part of the TMX format. See [`Loader::set_preserve_extensions()`].
+    pub fn preserve_extensions(&self) -> bool {
+        self.diagnostics.preserve_extensions()
+    }
+
+    /// Returns the warnings found while loading the last file, including the files it references,
+    /// in the order they were found. This includes the warnings found while loading files that
+    /// failed to load.
+    ///
+    /// Warnings are cleared whenever the loader starts loading another file, so that they don't
+    /// accumulate in loaders that are kept around and never asked for them.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.diagnostics.take_warnings()
+    }
+
+    /// Sets a function called on each image of the maps and tilesets loaded from now on, such as
+    /// to upload it as a texture. The value it returns is stored in the image's
+    /// [`handle`](Image::handle), which can later be retrieved with [`ImageHandle::downcast_ref()`].
+    ///
+    /// The function is called once for each image of a tileset, image layer or tile, right after
+    /// the file containing it is loaded. Since tilesets are cached, the images of an external
+    /// tileset shared by several maps are only passed to the function once. The
+    /// [`source`](Image::source) of the images is already relative to the working directory, or
+    /// to the root of the loader's [`ResourceReader`].
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use std::path::PathBuf;
+    ///
+    /// use tiled::Loader;
+    ///
+    /// /// The handle of a texture, in a hypothetical game engine.
+    /// struct Texture(PathBuf);
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.on_image_loaded(|image| Texture(image.source.clone()));
+    /// let map = loader.load_tmx_map("assets/tiled_base64_external.tmx")?;
+    ///
+    /// let image = map.tilesets()[0].image.as_ref().unwrap();
+    /// let texture = image.handle.as_ref().unwrap().downcast_ref::<Texture>().unwrap();
+    /// assert_eq!(texture.0, image.source);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_image_loaded<T: Any + Send + Sync>(
+        &mut self,
+        hook: impl Fn(&Image) -> T + Send + Sync + 'static,
+    ) {
+        self.image_hook = Some(SharedImageHook(Arc::new(move |image: &Image| {
+            ImageHandle::new(hook(image))
+        })));
+    }
+
+    pub(crate) fn context(&mut self) -> LoadContext<'_, Reader, Cache> {
+        LoadContext {
+            reader: &mut self.reader,
+            cache: &mut self.cache,
+            project: self.project.as_ref(),
+            defer_tile_data: self.defer_tile_data,
+            compact_tile_data: self.compact_tile_data,
+            resolve_object_references: self.resolve_object_references,
+            decoders: &self.decoders,
+            limits: self.limits,
+            tiles: TileBudget::new(self.limits.max_tiles),
+            group_depth: 0,
+            interner: Default::default(),
+            diagnostics: &self.diagnostics,
+            image_hook: self.image_hook.as_ref().map(|hook| &*hook.0),
+        }
     }
 
     /// Returns a reference to the loader's internal [`ResourceCache`].
@@ -194,3 +814,46 @@ impl<Cache: ResourceCache, Reader: ResourceReader> Loader<Cache, Reader> {
         (self.cache, self.reader)
     }
 }
+
+/// Wraps the cache of a loader while reloading a map, so that the resources cached beforehand
+/// are loaded again and replaced, while the ones reloaded are reused as usual.
+struct RefreshingCache<'a, C> {
+    cache: &'a mut C,
+    refreshed: HashSet<PathBuf>,
+}
+
+impl<C: ResourceCache> ResourceCache for RefreshingCache<'_, C> {
+    fn get_tileset(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Tileset>> {
+        if self.refreshed.contains(path.as_ref()) {
+            self.cache.get_tileset(path)
+        } else {
+            None
+        }
+    }
+
+    fn insert_tileset(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Tileset>) {
+        self.refreshed.insert(path.as_ref().to_owned());
+        self.cache.insert_tileset(path, tileset);
+    }
+
+    fn get_template(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Template>> {
+        if self.refreshed.contains(path.as_ref()) {
+            self.cache.get_template(path)
+        } else {
+            None
+        }
+    }
+
+    fn insert_template(&mut self, path: impl AsRef<ResourcePath>, template: Arc<Template>) {
+        self.refreshed.insert(path.as_ref().to_owned());
+        self.cache.insert_template(path, template);
+    }
+
+    fn invalidate(&mut self, path: impl AsRef<ResourcePath>) {
+        self.cache.invalidate(path);
+    }
+
+    fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
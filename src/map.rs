@@ -1,18 +1,36 @@
 //! Structures related to Tiled maps.
 
-use std::{collections::HashMap, fmt, path::Path, str::FromStr, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
 
-use xml::attribute::OwnedAttribute;
+use xml::{attribute::OwnedAttribute, reader::XmlEvent, EventWriter};
 
 use crate::{
     error::{Error, Result},
-    layers::{LayerData, LayerTag},
-    properties::{parse_properties, Color, Properties},
+    layers::{LayerData, LayerInputs, LayerTag},
+    parse::{resolve_map_tilesets, LoadContext, MapTilesetEntry},
+    properties::{
+        collect_object_references, parse_properties, write_properties, Color, Properties,
+        PropertyValue,
+    },
     tileset::Tileset,
-    util::{get_attrs, parse_tag, XmlEventResult},
-    EmbeddedParseResultType, Layer, ResourceCache, ResourceReader,
+    util::{
+        get_attrs, parse_tag, relative_path, write_empty_element, write_end_element,
+        write_start_element, XmlEventResult,
+    },
+    warning::{Diagnostics, WarningKind},
+    write::SourceInfo,
+    EmbeddedParseResultType, FlattenedLayer, GidMapping, Layer, Object, ObjectLayer, ResourceCache,
+    ResourceReader, TileId, Version, WriteOptions, XmlExtensions,
 };
 
+#[derive(Clone, PartialEq)]
 pub(crate) struct MapTilesetGid {
     pub first_gid: Gid,
     pub tileset: Arc<Tileset>,
@@ -20,8 +38,12 @@ pub(crate) struct MapTilesetGid {
 
 /// All Tiled map files will be parsed into this. Holds all the layers and tilesets.
 #[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Map {
-    version: String,
+    version: Version,
+    /// The version of Tiled the map was saved with, read from its `tiledversion` attribute, if it
+    /// has one that is a version number.
+    pub tiled_version: Option<Version>,
     /// The way tiles are laid out in the map.
     pub orientation: Orientation,
     /// Width of the map, in tiles.
@@ -52,24 +74,68 @@ pub struct Map {
     pub stagger_axis: StaggerAxis,
     /// The stagger index of Hexagonal/Staggered map.
     pub stagger_index: StaggerIndex,
+    /// The length in pixels of the side of a hexagonal tile's edge that is perpendicular to the
+    /// stagger axis. Only applies to Hexagonal maps, 0 otherwise.
+    pub hex_side_length: u32,
+    /// The x coordinate in pixels of the map's parallax origin, 0.0 by default. See
+    /// [`LayerData::parallax_x`](crate::LayerData::parallax_x) for details.
+    pub parallax_origin_x: f32,
+    /// The y coordinate in pixels of the map's parallax origin, 0.0 by default. See
+    /// [`LayerData::parallax_y`](crate::LayerData::parallax_y) for details.
+    pub parallax_origin_y: f32,
     /// The tilesets present on this map.
     tilesets: Vec<Arc<Tileset>>,
+    /// The first GID of each tileset of the map.
+    first_gids: Vec<Gid>,
     /// The layers present in this map.
     layers: Vec<LayerData>,
     /// The custom properties of this map.
     pub properties: Properties,
-    /// The background color of this map, if any.
+    /// The background color of this map, if any, including its alpha channel.
+    ///
+    /// [`Color`] can be converted into a `[f32; 4]` or a `u32` to hand it to a renderer.
     pub background_color: Option<Color>,
     infinite: bool,
-    /// The type of the map, which is arbitrary and set by the user.
+    /// The class of the map, which is arbitrary and set by the user, such as to tell dungeons apart
+    /// from overworld maps. Read from the `class` attribute of the map, or `type` in files saved
+    /// by older versions of Tiled.
     pub user_type: Option<String>,
+    /// The elements and attributes of the map's `<map>` element that aren't part of the format,
+    /// if the loader [preserves them](crate::Loader::set_preserve_extensions()).
+    pub extensions: XmlExtensions,
+    /// The directory of the file the map was loaded from, which the relative paths it contains are
+    /// relative to.
+    root_path: PathBuf,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: SourceInfo<MapSource>,
+}
+
+/// The parts of the file a map was loaded from that aren't exposed, kept to write the map back
+/// the same way.
+#[derive(Debug, Clone, Default)]
+struct MapSource {
+    /// The `tiledversion` attribute as written, such as `1.10.0` rather than `1.10`.
+    tiled_version: Option<String>,
+    render_order: Option<String>,
+    compression_level: Option<i32>,
+    next_layer_id: u32,
+    next_object_id: u32,
+    /// The children of the map's `<editorsettings>` element, if it has one.
+    editor_settings: Option<Vec<(String, Vec<OwnedAttribute>)>>,
+    /// How many tilesets and layers come before the map's properties.
+    properties_index: usize,
+    /// Whether the lines of the file end with `\r\n`, as in Git checkouts on Windows.
+    crlf: bool,
 }
 
 impl Map {
     /// The TMX format version this map was saved to. Equivalent to the map file's `version`
     /// attribute.
-    pub fn version(&self) -> &str {
-        self.version.as_ref()
+    ///
+    /// See [`Loader::check_compatibility()`](crate::Loader::check_compatibility()) to check
+    /// whether the crate supports it.
+    pub fn version(&self) -> Version {
+        self.version
     }
 
     /// Whether this map is infinite. An infinite map has no fixed size and can grow in all
@@ -78,6 +144,39 @@ impl Map {
     pub fn infinite(&self) -> bool {
         self.infinite
     }
+
+    /// The directory of the file the map was loaded from, which the relative paths it contains are
+    /// relative to. Empty for maps created with a [`MapBuilder`].
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    /// Returns the path a file property of the map, one of its layers or one of its objects refers
+    /// to, resolved against the map's [root path](Self::root_path). Use
+    /// [`Tileset::resolve_file_property()`] for the properties of tilesets and their tiles, which
+    /// may be stored in other directories.
+    ///
+    /// The path is normalized lexically, without accessing the filesystem, so that this works
+    /// with any [`ResourceReader`]. Returns [`None`] if the value isn't a
+    /// [`FileValue`](PropertyValue::FileValue) or is empty.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use std::path::Path;
+    ///
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_file_property.tmx")?;
+    ///
+    /// assert_eq!(
+    ///     map.resolve_file_property(&map.properties["script"]),
+    ///     Some(Path::new("assets/scripts/level.lua").to_owned())
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_file_property(&self, value: &PropertyValue) -> Option<PathBuf> {
+        crate::util::resolve_file_property(&self.root_path, value)
+    }
 }
 
 impl Map {
@@ -122,18 +221,1108 @@ impl Map {
     pub fn get_layer(&self, index: usize) -> Option<Layer> {
         self.layers.get(index).map(|data| Layer::new(self, data))
     }
+
+    /// Returns the layer that has the specified id, if it exists. Layers nested in group layers
+    /// are searched as well.
+    ///
+    /// Unlike their index, the id of a layer doesn't change when layers are added, removed or
+    /// moved, which makes it suitable to refer to a layer across reloads of a map. Layers with an
+    /// id of 0, such as those loaded from files predating layer ids, can't be found this way.
+    pub fn get_layer_by_id(&self, id: u32) -> Option<Layer> {
+        if id == 0 {
+            return None;
+        }
+        self.layers
+            .iter()
+            .find_map(|layer| layer.find_layer(id))
+            .map(|data| Layer::new(self, data))
+    }
+
+    /// Returns the layer found by following a path of layer names separated by slashes, such as
+    /// `"Background/Decor/Trees"`, where every name but the last is that of a group layer, if it
+    /// exists.
+    ///
+    /// If several layers along the path share the same name, the first one leading to a layer is
+    /// used. Layers whose names contain a slash can't be found this way.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_group_layers.tmx")?;
+    ///
+    /// let layer = map.get_layer_by_path("group-2/group-3/tile-3").unwrap();
+    /// assert_eq!(layer.name, "tile-3");
+    /// assert!(map.get_layer_by_path("group-2/tile-3").is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_layer_by_path(&self, path: &str) -> Option<Layer> {
+        let names: Vec<&str> = path.split('/').collect();
+        self.layers
+            .iter()
+            .find_map(|layer| layer.find_by_path(&names))
+            .map(|data| Layer::new(self, data))
+    }
+
+    /// Returns an iterator over all the layers of the map that aren't group layers, including the
+    /// ones nested in group layers, in display order.
+    ///
+    /// Each layer comes with its offset, opacity, tint color, parallax factors and visibility
+    /// combined with the ones of the group layers containing it, which is what renderers need.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_group_layers.tmx")?;
+    ///
+    /// for layer in map.flattened_layers().filter(|layer| layer.visible) {
+    ///     println!(
+    ///         "Drawing {} at ({}, {}) with opacity {}",
+    ///         layer.layer.name, layer.offset_x, layer.offset_y, layer.opacity
+    ///     );
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn flattened_layers(&self) -> impl Iterator<Item = FlattenedLayer> {
+        let mut layers = Vec::new();
+        for layer in self.layers() {
+            FlattenedLayer::flatten(layer, None, &mut layers);
+        }
+        layers.into_iter()
+    }
+
+    /// Returns the GIDs of each tile layer of the map in the CSV layout of TMX files, including
+    /// the tile layers nested in group layers, in display order. See
+    /// [`TileLayer::to_csv()`](crate::TileLayer::to_csv()).
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_csv.tmx")?;
+    ///
+    /// for (layer, csv) in map.tile_layers_to_csv() {
+    ///     println!("{}:\n{}", layer.name, csv);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tile_layers_to_csv(&self) -> Vec<(Layer, String)> {
+        self.flattened_layers()
+            .filter_map(|layer| Some((layer.layer, layer.layer.as_tile_layer()?.to_csv())))
+            .collect()
+    }
+
+    /// Returns how many times each tile is used by the tile layers and tile objects of the map,
+    /// including those nested in group layers, keyed by the index of its tileset and its local id.
+    /// Flipped tiles count as the tile they flip.
+    ///
+    /// Tiles of objects created from templates that have their own tileset aren't counted.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_tile_objects.tmx")?;
+    ///
+    /// let usage = map.tile_usage();
+    /// assert_eq!(usage[&(0, 4)], 2);
+    /// assert_eq!(usage[&(1, 0)], 1);
+    /// assert_eq!(usage.len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tile_usage(&self) -> HashMap<(usize, TileId), usize> {
+        let mut usage = HashMap::new();
+        for layer in &self.layers {
+            layer.for_each_tile(&mut |tileset_index, id| {
+                *usage.entry((tileset_index, id)).or_insert(0) += 1;
+            });
+        }
+        usage
+    }
+
+    /// Returns the indices of the tilesets of the map that none of its tile layers nor tile objects
+    /// use, in ascending order. Such tilesets can usually be removed from the map.
+    pub fn unused_tilesets(&self) -> Vec<usize> {
+        let mut used = vec![false; self.tilesets.len()];
+        for layer in &self.layers {
+            layer.for_each_tile(&mut |tileset_index, _| used[tileset_index] = true);
+        }
+        (0..used.len()).filter(|index| !used[*index]).collect()
+    }
+
+    /// Returns an iterator over the objects of the map whose [class](crate::ObjectData::user_type)
+    /// is the one given, including those in object layers nested in group layers, in display
+    /// order.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_classes.tmx")?;
+    ///
+    /// for spawn in map.objects_with_class("spawn") {
+    ///     println!("Spawning the player at ({}, {})", spawn.x, spawn.y);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn objects_with_class<'a>(&'a self, class: &'a str) -> impl Iterator<Item = Object<'a>> {
+        self.object_layers()
+            .flat_map(move |layer| layer.objects_with_class(class))
+    }
+
+    /// Returns an iterator over the objects of the map that have the name given, including those
+    /// in object layers nested in group layers, in display order.
+    pub fn objects_with_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Object<'a>> {
+        self.object_layers()
+            .flat_map(move |layer| layer.objects_with_name(name))
+    }
+
+    /// Returns an iterator over all the object layers of the map, including the ones nested in
+    /// group layers, in display order.
+    pub(crate) fn object_layers(&self) -> impl Iterator<Item = ObjectLayer> {
+        self.flattened_layers()
+            .filter_map(|layer| layer.layer.as_object_layer())
+    }
+
+    /// Returns the object that has the specified id, if it exists. Object layers nested in group
+    /// layers are searched as well.
+    pub fn get_object_by_id(&self, id: u32) -> Option<Object> {
+        self.get_object_and_layer_by_id(id)
+            .map(|(_, object)| object)
+    }
+
+    /// Returns the object that has the specified id along with the object layer containing it, if
+    /// it exists. Object layers nested in group layers are searched as well.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_object_property.tmx")?;
+    ///
+    /// let (layer, object) = map.get_object_and_layer_by_id(3).unwrap();
+    /// assert_eq!(layer.name, "Object Layer 1");
+    /// assert_eq!(object.id(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_object_and_layer_by_id(&self, id: u32) -> Option<(Layer, Object)> {
+        self.layers
+            .iter()
+            .find_map(|layer| layer.find_object(id))
+            .map(|(layer, object)| (Layer::new(self, layer), Object::new(self, object)))
+    }
+
+    /// Returns the object that an object property refers to, if it exists.
+    ///
+    /// Returns [`None`] if the value isn't an [`ObjectValue`](PropertyValue::ObjectValue), if the
+    /// property is unset (refers to object 0) or if the object it refers to has been removed.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_object_property.tmx")?;
+    ///
+    /// let layer = map.get_layer(1).unwrap().as_object_layer().unwrap();
+    /// let object = layer.get_object(0).unwrap();
+    /// let target = map
+    ///     .resolve_object_property(&object.properties["object property"])
+    ///     .unwrap();
+    /// assert_eq!(target.id(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_object_property(&self, value: &PropertyValue) -> Option<Object> {
+        match value {
+            PropertyValue::ObjectValue(0) => None,
+            PropertyValue::ObjectValue(id) => self.get_object_by_id(*id),
+            _ => None,
+        }
+    }
+
+    /// Reports the object properties of the map, its layers and its objects which refer to objects
+    /// that don't exist.
+    fn check_object_references(&self, diagnostics: &Diagnostics) {
+        for (property, id) in self.unresolved_object_references() {
+            diagnostics.warn(WarningKind::UnresolvedObjectReference { property, id });
+        }
+    }
+
+    /// Returns the name and value of the object properties of the map, its layers and its objects
+    /// which refer to objects that don't exist.
+    pub(crate) fn unresolved_object_references(&self) -> Vec<(String, u32)> {
+        let mut references = Vec::new();
+        collect_object_references(&self.properties, &mut references);
+        for layer in &self.layers {
+            layer.collect_object_references(&mut references);
+        }
+        references.retain(|(_, id)| self.get_object_by_id(*id).is_none());
+        references
+    }
+
+    /// Returns the pixel position of the top-left corner of the tile at the position given,
+    /// treating the map as orthogonal. Layer offsets are not taken into account; Use
+    /// [`Layer::tile_to_pixel`] for that.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_base64_zlib.tmx")?;
+    ///
+    /// assert_eq!(map.tile_to_pixel(2, 3), (2.0 * 32.0, 3.0 * 32.0));
+    /// assert_eq!(map.pixel_to_tile(70.0, 95.9), (2, 2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tile_to_pixel(&self, x: i32, y: i32) -> (f32, f32) {
+        crate::coords::ortho::tile_to_pixel(self.tile_width, self.tile_height, x as f32, y as f32)
+    }
+
+    /// Returns the position of the tile containing the pixel position given, treating the map as
+    /// orthogonal. Layer offsets are not taken into account; Use [`Layer::pixel_to_tile`] for
+    /// that.
+    pub fn pixel_to_tile(&self, x: f32, y: f32) -> (i32, i32) {
+        let (x, y) = crate::coords::ortho::pixel_to_tile(self.tile_width, self.tile_height, x, y);
+        (x.floor() as i32, y.floor() as i32)
+    }
+
+    /// Converts a position in tiles into a position on the screen (in pixels), following Tiled's
+    /// rendering rules for isometric maps. See [`coords::iso`](crate::coords::iso) for details.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{MapBuilder, Orientation};
+    ///
+    /// let map = MapBuilder::new(Orientation::Isometric, 10, 10, 64, 32).build().unwrap();
+    ///
+    /// // The top corner of the top tile is placed at the horizontal center of the map.
+    /// assert_eq!(map.iso_to_screen(0.0, 0.0), (320.0, 0.0));
+    /// assert_eq!(map.iso_to_screen(1.0, 0.0), (352.0, 16.0));
+    /// assert_eq!(map.screen_to_iso(352.0, 16.0), (1.0, 0.0));
+    /// ```
+    pub fn iso_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        crate::coords::iso::iso_to_screen(self.height, self.tile_width, self.tile_height, x, y)
+    }
+
+    /// Converts a position on the screen (in pixels) into a position in tiles, following Tiled's
+    /// rendering rules for isometric maps. The inverse of [`Map::iso_to_screen`].
+    ///
+    /// The tile containing the screen position is found by flooring the result.
+    pub fn screen_to_iso(&self, x: f32, y: f32) -> (f32, f32) {
+        crate::coords::iso::screen_to_iso(self.height, self.tile_width, self.tile_height, x, y)
+    }
+
+    /// Converts a position in tiles into a position in pixels, following Tiled's rendering rules
+    /// for the map's [orientation](Self::orientation). See the [`coords`](crate::coords) module
+    /// for details.
+    ///
+    /// For orthogonal and isometric maps, this is the same as [`Map::tile_to_pixel`] and
+    /// [`Map::iso_to_screen`] respectively. For hexagonal and staggered maps, the position is
+    /// floored to a whole tile and the top-left corner of that tile's bounding box is returned.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{MapBuilder, Orientation};
+    ///
+    /// let orthogonal = MapBuilder::new(Orientation::Orthogonal, 10, 10, 32, 32).build().unwrap();
+    /// let staggered = MapBuilder::new(Orientation::Staggered, 10, 10, 32, 16).build().unwrap();
+    ///
+    /// assert_eq!(orthogonal.project((1.5, 1.0)), (48.0, 32.0));
+    /// assert_eq!(staggered.project((1.5, 1.0)), (48.0, 8.0));
+    /// assert_eq!(staggered.unproject((64.0, 16.0)), (1.0, 1.0));
+    /// ```
+    pub fn project(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        use crate::coords::{hex::HexLayout, staggered::StaggeredLayout};
+
+        match self.orientation {
+            Orientation::Orthogonal => {
+                crate::coords::ortho::tile_to_pixel(self.tile_width, self.tile_height, x, y)
+            }
+            Orientation::Isometric => self.iso_to_screen(x, y),
+            Orientation::Hexagonal => {
+                HexLayout::from_map(self).tile_to_pixel(x.floor() as i32, y.floor() as i32)
+            }
+            Orientation::Staggered => {
+                StaggeredLayout::from_map(self).tile_to_pixel(x.floor() as i32, y.floor() as i32)
+            }
+        }
+    }
+
+    /// Converts a position in pixels into a position in tiles, following Tiled's rendering rules
+    /// for the map's [orientation](Self::orientation). The inverse of [`Map::project`].
+    ///
+    /// For hexagonal and staggered maps, the position of the tile containing the pixel position
+    /// is returned, without a fractional part.
+    pub fn unproject(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        use crate::coords::{hex::HexLayout, staggered::StaggeredLayout};
+
+        let (x, y) = match self.orientation {
+            Orientation::Orthogonal => {
+                return crate::coords::ortho::pixel_to_tile(self.tile_width, self.tile_height, x, y)
+            }
+            Orientation::Isometric => return self.screen_to_iso(x, y),
+            Orientation::Hexagonal => HexLayout::from_map(self).pixel_to_tile(x, y),
+            Orientation::Staggered => StaggeredLayout::from_map(self).pixel_to_tile(x, y),
+        };
+        (x as f32, y as f32)
+    }
+
+    /// Returns the first GID of the tileset at the specified index of the map's tilesets, which is
+    /// the GID referring to its tile with ID 0.
+    ///
+    /// GIDs are how maps files refer to the tiles of their tilesets; The first GIDs are the ones
+    /// from the file the map was loaded from, or follow each other for maps created with a
+    /// [`MapBuilder`]. See also: <https://doc.mapeditor.org/en/latest/reference/global-tile-ids/>
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_tile_objects.tmx")?;
+    ///
+    /// assert_eq!(map.get_first_gid(0), Some(1));
+    /// assert_eq!(map.get_first_gid(1), Some(85));
+    /// assert_eq!(map.get_first_gid(2), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_first_gid(&self, tileset_index: usize) -> Option<u32> {
+        self.first_gids.get(tileset_index).map(|gid| gid.0)
+    }
+
+    pub(crate) fn first_gids(&self) -> &[Gid] {
+        &self.first_gids
+    }
+
+    /// Get a mutable reference to the map's tilesets.
+    ///
+    /// Since tilesets may be shared with other maps and the loader's cache, use
+    /// [`Arc::make_mut`] to modify one of them without affecting the others.
+    ///
+    /// ## Note
+    /// The tiles of the map's layers refer to tilesets by their index; Reordering or removing
+    /// tilesets that are in use will make them refer to the wrong tilesets, or panic when
+    /// accessing them. Use [`Map::remap_gids()`] to do so instead.
+    #[inline]
+    pub fn tilesets_mut(&mut self) -> &mut [Arc<Tileset>] {
+        self.tilesets.as_mut()
+    }
+
+    /// Replaces the tilesets of the map with the ones of `mapping`, and moves the tiles of all
+    /// layers and tile objects accordingly. The GIDs of the tilesets are recomputed the way Tiled
+    /// does, each tileset following the previous one.
+    ///
+    /// Tiles of objects created from templates that have their own tileset aren't affected.
+    ///
+    /// Fails with [`Error::InvalidTileFound`] without modifying the map if a tile would refer to a
+    /// tileset that `mapping` doesn't have.
+    pub fn remap_gids(&mut self, mapping: &GidMapping) -> Result<()> {
+        let mut layers = self.layers.clone();
+        for layer in &mut layers {
+            layer.remap_tiles(mapping);
+            layer.validate(mapping.tilesets().len(), self.infinite)?;
+        }
+        self.layers = layers;
+        self.tilesets = mapping.tilesets().to_vec();
+        self.first_gids = consecutive_first_gids(&self.tilesets, &[]);
+        Ok(())
+    }
+
+    /// Copies the tilesets and layers of `other` into this map, placing them above its own layers
+    /// and moving them by `offset` tiles, such as to stitch rooms designed separately into a
+    /// single map.
+    ///
+    /// Tilesets of `other` that are identical to one of this map are shared rather than copied,
+    /// and the tiles of `other` are remapped to them. Tiles that end up outside of this map are
+    /// dropped if it is finite. Objects and images are moved by the size of `offset` in pixels,
+    /// which is measured in tile heights on both axes in isometric maps like Tiled does.
+    ///
+    /// The copied layers and objects are given new ids following the ones of this map, and the
+    /// object properties of `other` referring to its objects are updated to match. The custom
+    /// properties of `other` itself aren't copied.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Loader, MapBuilder, Orientation};
+    ///
+    /// let room = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let mut world = MapBuilder::new(Orientation::Orthogonal, 200, 100, 32, 32).build()?;
+    /// world.merge(&room, (0, 0));
+    /// world.merge(&room, (100, 0));
+    ///
+    /// // Both rooms share the same tileset.
+    /// assert_eq!(world.tilesets().len(), 1);
+    /// let room_tiles = room.get_layer(0).unwrap().as_tile_layer().unwrap();
+    /// let second_room = world.get_layer(room.layers().len()).unwrap();
+    /// let world_tiles = second_room.as_tile_layer().unwrap();
+    /// assert_eq!(
+    ///     world_tiles.get_tile(100, 0).map(|tile| tile.id()),
+    ///     room_tiles.get_tile(0, 0).map(|tile| tile.id())
+    /// );
+    /// assert_eq!(second_room.id(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn merge(&mut self, other: &Map, offset: (i32, i32)) {
+        let mut tilesets = self.tilesets.clone();
+        let mut tileset_indices = Vec::with_capacity(other.tilesets.len());
+        for tileset in &other.tilesets {
+            let index = tilesets
+                .iter()
+                .position(|existing| Arc::ptr_eq(existing, tileset) || existing == tileset)
+                .unwrap_or_else(|| {
+                    tilesets.push(tileset.clone());
+                    tilesets.len() - 1
+                });
+            tileset_indices.push(index);
+        }
+        let mapping = tileset_indices
+            .into_iter()
+            .enumerate()
+            .fold(GidMapping::new(tilesets), |mapping, (old, new)| {
+                mapping.tileset(old, new)
+            });
+
+        let pixel_offset = self.tiles_to_object_pixels(offset);
+        let size = (!self.infinite).then_some((self.width, self.height));
+        let mut next_layer_id = self.layers.iter().map(LayerData::max_id).max().unwrap_or(0) + 1;
+        let mut next_object_id = self
+            .layers
+            .iter()
+            .map(LayerData::max_object_id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        for layer in &other.layers {
+            let mut layer = layer.clone();
+            layer.remap_tiles(&mapping);
+            layer.translate(offset, pixel_offset, size);
+            layer.renumber(&mut next_layer_id, &mut next_object_id);
+            self.layers.push(layer);
+        }
+        self.tilesets = mapping.tilesets().to_vec();
+        self.first_gids = consecutive_first_gids(&self.tilesets, &self.first_gids);
+    }
+
+    /// Returns a new finite map containing the rectangle of tiles given of this map, such as to
+    /// split a large map into cells that can be streamed in separately.
+    ///
+    /// The layers of the new map keep their ids, and contain the tiles within the rectangle, as
+    /// well as the objects whose position is within it, moved to be relative to its top-left
+    /// corner. Only the tilesets used by the remaining tiles and tile objects are kept. Object
+    /// properties referring to objects outside of the rectangle are left as is.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let cell = map.crop(2, 3, 10, 10);
+    ///
+    /// assert_eq!((cell.width, cell.height), (10, 10));
+    /// let tiles = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    /// let cell_tiles = cell.get_layer(0).unwrap().as_tile_layer().unwrap();
+    /// assert_eq!(
+    ///     cell_tiles.get_tile(0, 0).map(|tile| tile.id()),
+    ///     tiles.get_tile(2, 3).map(|tile| tile.id())
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn crop(&self, x: i32, y: i32, width: u32, height: u32) -> Map {
+        let (pixel_width, pixel_height) =
+            self.tiles_to_object_pixels((width as i32, height as i32));
+        let pixel_offset = self.tiles_to_object_pixels((-x, -y));
+        let mut map = self.clone();
+        for layer in &mut map.layers {
+            layer.translate((-x, -y), pixel_offset, Some((width, height)));
+            layer.retain_objects(&mut |object| {
+                (0.0..pixel_width).contains(&object.x) && (0.0..pixel_height).contains(&object.y)
+            });
+        }
+
+        let unused = map.unused_tilesets();
+        let mut mapping = GidMapping::new(Vec::new());
+        let (mut tilesets, mut first_gids) = (Vec::new(), Vec::new());
+        for (index, tileset) in self.tilesets.iter().enumerate() {
+            if !unused.contains(&index) {
+                mapping = mapping.tileset(index, tilesets.len());
+                tilesets.push(tileset.clone());
+                first_gids.push(self.first_gids[index]);
+            }
+        }
+        for layer in &mut map.layers {
+            layer.remap_tiles(&mapping);
+        }
+
+        map.width = width;
+        map.height = height;
+        map.infinite = false;
+        map.tilesets = tilesets;
+        map.first_gids = first_gids;
+        map
+    }
+
+    /// Changes the size of the map like the Resize Map command of Tiled: Its finite tile layers are
+    /// grown with empty tiles or truncated to the new size, and `anchor` gives the point of the
+    /// current content which stays in place relative to the new bounds. For instance, growing a
+    /// map with an anchor of [`ResizeAnchor::Center`] adds as many tiles on each side of it.
+    ///
+    /// Objects and image layers are moved along with the tiles, including those that end up
+    /// outside of the map.
+    ///
+    /// Infinite maps have no bounds to change, so this does nothing on them.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::ResizeAnchor;
+    ///
+    /// let mut map = tiled::Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    /// let id = layer.get_tile(0, 0).unwrap().id();
+    ///
+    /// // Add two rows and columns of tiles on each side.
+    /// map.resize(104, 104, ResizeAnchor::Center);
+    /// let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    /// assert_eq!(layer.width(), Some(104));
+    /// assert_eq!(layer.get_tile(2, 2).unwrap().id(), id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resize(&mut self, width: u32, height: u32, anchor: ResizeAnchor) {
+        if self.infinite {
+            return;
+        }
+        let (anchor_x, anchor_y) = anchor.fraction();
+        let offset = (
+            ((width as f32 - self.width as f32) * anchor_x).floor() as i32,
+            ((height as f32 - self.height as f32) * anchor_y).floor() as i32,
+        );
+        let pixel_offset = self.tiles_to_object_pixels(offset);
+        for layer in &mut self.layers {
+            layer.translate(offset, pixel_offset, Some((width, height)));
+        }
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Converts an offset in tiles into the pixel offset objects need to be moved by to follow
+    /// it, which is measured in tile heights on both axes in isometric maps like Tiled does.
+    fn tiles_to_object_pixels(&self, (x, y): (i32, i32)) -> (f32, f32) {
+        match self.orientation {
+            Orientation::Isometric => (
+                x as f32 * self.tile_height as f32,
+                y as f32 * self.tile_height as f32,
+            ),
+            _ => self.tile_to_pixel(x, y),
+        }
+    }
+
+    /// Get an iterator over mutable references to the data of all the layers in the map, in
+    /// ascending order of their layer index.
+    #[inline]
+    pub fn layers_mut(&mut self) -> impl ExactSizeIterator<Item = &mut LayerData> {
+        self.layers.iter_mut()
+    }
+
+    /// Returns a mutable reference to the data of the layer that has the specified index, if it
+    /// exists.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let mut map = tiled::Loader::new().load_tmx_map("assets/tiled_base64_zlib.tmx")?;
+    ///
+    /// let layer = map.get_layer_mut(0).unwrap();
+    /// layer.name = "Ground".to_owned();
+    /// let tiles = layer.as_finite_tile_layer_mut().unwrap();
+    /// tiles.set_tile(0, 0, None);
+    ///
+    /// let layer = map.get_layer(0).unwrap();
+    /// assert_eq!(layer.name, "Ground");
+    /// assert!(layer.as_tile_layer().unwrap().get_tile(0, 0).is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_layer_mut(&mut self, index: usize) -> Option<&mut LayerData> {
+        self.layers.get_mut(index)
+    }
+
+    /// Appends a layer to the map, placing it above all the others.
+    ///
+    /// ## Note
+    /// Tiles of the layer refer to the map's tilesets by their index, so the layer must have been
+    /// created for this map (or one with the same tilesets).
+    pub fn add_layer(&mut self, layer: LayerData) {
+        self.layers.push(layer);
+    }
+
+    /// Inserts a layer at the specified index, shifting all the layers above it.
+    ///
+    /// ## Panics
+    /// Panics if `index` is greater than the amount of layers in the map.
+    pub fn insert_layer(&mut self, index: usize, layer: LayerData) {
+        self.layers.insert(index, layer);
+    }
+
+    /// Removes the layer that has the specified index and returns it, if it exists.
+    pub fn remove_layer(&mut self, index: usize) -> Option<LayerData> {
+        if index < self.layers.len() {
+            Some(self.layers.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Moves the layer that has the specified id, which may be nested in group layers, to
+    /// `new_index` within the same parent, shifting the layers in between. Layers keep their ids.
+    ///
+    /// Returns `false` without modifying the map if the layer doesn't exist or `new_index` is past
+    /// the last layer of its parent.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let mut map = tiled::Loader::new().load_tmx_map("assets/tiled_group_layers.tmx")?;
+    /// let id = map.get_layer(0).unwrap().id();
+    ///
+    /// assert!(map.move_layer(id, 2));
+    /// assert_eq!(map.get_layer(2).unwrap().id(), id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn move_layer(&mut self, id: u32, new_index: usize) -> bool {
+        let (layer, parent, index) = match self.take_layer(id) {
+            Some(taken) => taken,
+            None => return false,
+        };
+        let siblings = self
+            .layer_vec_mut(parent)
+            .expect("the parent of a layer is a group");
+        let moved = new_index <= siblings.len();
+        siblings.insert(if moved { new_index } else { index }, layer);
+        moved
+    }
+
+    /// Moves the layer that has the specified id into the group layer with the id `group`, or to
+    /// the top level of the map if it is [`None`], at `index` within its new parent. Layers keep
+    /// their ids.
+    ///
+    /// Returns `false` without modifying the map if either layer doesn't exist, `group` isn't a
+    /// group layer or is the moved layer or one of its children, or `index` is greater than the
+    /// amount of layers of the new parent.
+    pub fn move_layer_into(&mut self, id: u32, group: Option<u32>, index: usize) -> bool {
+        let layer = match self.get_layer_by_id(id) {
+            Some(layer) => layer,
+            None => return false,
+        };
+        if matches!(group, Some(group) if layer.find_layer(group).is_some())
+            || self.layer_vec_mut(group).is_none()
+        {
+            return false;
+        }
+        let (layer, parent, old_index) =
+            self.take_layer(id).expect("the layer was found by its id");
+        let siblings = self
+            .layer_vec_mut(group)
+            .expect("the group isn't part of the moved layer");
+        if index <= siblings.len() {
+            siblings.insert(index, layer);
+            true
+        } else {
+            self.layer_vec_mut(parent)
+                .expect("the parent of a layer is a group")
+                .insert(old_index, layer);
+            false
+        }
+    }
+
+    /// Returns the layers of the map if `group` is [`None`], or those of the group layer with the
+    /// id given if it exists.
+    fn layer_vec_mut(&mut self, group: Option<u32>) -> Option<&mut Vec<LayerData>> {
+        match group {
+            None => Some(&mut self.layers),
+            Some(id) => self
+                .layers
+                .iter_mut()
+                .find_map(|layer| layer.find_layer_mut(id))?
+                .as_group_layer_mut()
+                .map(|group| group.layer_vec_mut()),
+        }
+    }
+
+    /// Removes the layer that has the specified id from its parent, and returns it along with the
+    /// id of the group layer it was in, if any, and its index within it.
+    fn take_layer(&mut self, id: u32) -> Option<(LayerData, Option<u32>, usize)> {
+        fn take(
+            layers: &mut Vec<LayerData>,
+            parent: Option<u32>,
+            id: u32,
+        ) -> Option<(LayerData, Option<u32>, usize)> {
+            if let Some(index) = layers.iter().position(|layer| layer.id() == id) {
+                return Some((layers.remove(index), parent, index));
+            }
+            layers.iter_mut().find_map(|layer| {
+                let parent = layer.id();
+                take(
+                    layer.as_group_layer_mut()?.layer_vec_mut(),
+                    Some(parent),
+                    id,
+                )
+            })
+        }
+        if id == 0 {
+            return None;
+        }
+        take(&mut self.layers, None, id)
+    }
+}
+
+/// A builder for creating [`Map`]s in code rather than loading them from files.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{LayerData, LayerTileData, MapBuilder, Orientation, TilesetBuilder};
+///
+/// let tileset = TilesetBuilder::new("terrain", 32, 32).build();
+/// let mut ground = LayerData::new_finite_tile_layer("ground", 10, 10);
+/// ground
+///     .as_finite_tile_layer_mut()
+///     .unwrap()
+///     .set_tile(2, 3, Some(LayerTileData::new(0, 7)));
+///
+/// let map = MapBuilder::new(Orientation::Orthogonal, 10, 10, 32, 32)
+///     .tileset(tileset)
+///     .layer(ground)
+///     .build()?;
+///
+/// let layer = map.get_layer(0).unwrap();
+/// assert_eq!(layer.id(), 1);
+/// assert_eq!(layer.as_tile_layer().unwrap().get_tile(2, 3).unwrap().id(), 7);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MapBuilder {
+    map: Map,
+}
+
+impl MapBuilder {
+    /// Creates a builder for a finite map with the orientation, size in tiles and tile size in
+    /// pixels given, and no tilesets nor layers.
+    pub fn new(
+        orientation: Orientation,
+        width: u32,
+        height: u32,
+        tile_width: u32,
+        tile_height: u32,
+    ) -> Self {
+        Self {
+            map: Map {
+                version: Version::NEWEST_FORMAT,
+                tiled_version: None,
+                orientation,
+                width,
+                height,
+                tile_width,
+                tile_height,
+                stagger_axis: StaggerAxis::default(),
+                stagger_index: StaggerIndex::default(),
+                hex_side_length: 0,
+                parallax_origin_x: 0.0,
+                parallax_origin_y: 0.0,
+                tilesets: Vec::new(),
+                first_gids: Vec::new(),
+                layers: Vec::new(),
+                properties: Properties::new(),
+                background_color: None,
+                infinite: false,
+                user_type: None,
+                extensions: XmlExtensions::default(),
+                root_path: PathBuf::new(),
+                source: SourceInfo::default(),
+            },
+        }
+    }
+
+    /// Sets whether the map is infinite. All of its tile layers must be infinite as well.
+    pub fn infinite(mut self, infinite: bool) -> Self {
+        self.map.infinite = infinite;
+        self
+    }
+
+    /// Sets the stagger axis of the map, for staggered and hexagonal maps.
+    pub fn stagger_axis(mut self, stagger_axis: StaggerAxis) -> Self {
+        self.map.stagger_axis = stagger_axis;
+        self
+    }
+
+    /// Sets the stagger index of the map, for staggered and hexagonal maps.
+    pub fn stagger_index(mut self, stagger_index: StaggerIndex) -> Self {
+        self.map.stagger_index = stagger_index;
+        self
+    }
+
+    /// Sets the length of the side of a hexagonal tile's edge, for hexagonal maps.
+    pub fn hex_side_length(mut self, hex_side_length: u32) -> Self {
+        self.map.hex_side_length = hex_side_length;
+        self
+    }
+
+    /// Sets the background color of the map.
+    pub fn background_color(mut self, color: Color) -> Self {
+        self.map.background_color = Some(color);
+        self
+    }
+
+    /// Sets the type of the map.
+    pub fn user_type(mut self, user_type: impl Into<String>) -> Self {
+        self.map.user_type = Some(user_type.into());
+        self
+    }
+
+    /// Sets a custom property of the map, replacing the previous value if any.
+    pub fn property(mut self, name: impl Into<Arc<str>>, value: PropertyValue) -> Self {
+        self.map.properties.insert(name.into(), value);
+        self
+    }
+
+    /// Appends a tileset to the map. The tiles of its layers refer to tilesets by their index in
+    /// the order they were added in.
+    pub fn tileset(mut self, tileset: impl Into<Arc<Tileset>>) -> Self {
+        self.map.tilesets.push(tileset.into());
+        self
+    }
+
+    /// Appends a layer to the map, placing it above the ones added before.
+    pub fn layer(mut self, layer: LayerData) -> Self {
+        self.map.layers.push(layer);
+        self
+    }
+
+    /// Builds the map, giving a unique id to each layer that doesn't have one.
+    ///
+    /// Fails with [`Error::InvalidTileFound`] if a tile or a tile object refers to a tileset that
+    /// wasn't added to the map, or with [`Error::MalformedAttributes`] if a tile layer is finite in an infinite
+    /// map or vice versa.
+    pub fn build(mut self) -> Result<Map> {
+        for layer in &self.map.layers {
+            layer.validate(self.map.tilesets.len(), self.map.infinite)?;
+        }
+        let mut next_id = self
+            .map
+            .layers
+            .iter()
+            .map(LayerData::max_id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        for layer in &mut self.map.layers {
+            layer.assign_ids(&mut next_id);
+        }
+        self.map.first_gids = consecutive_first_gids(&self.map.tilesets, &[]);
+        Ok(self.map)
+    }
+}
+
+/// Like Tiled, gives each tileset the GIDs following the ones of the previous tileset, keeping the
+/// `known` first GIDs of the first tilesets.
+fn consecutive_first_gids(tilesets: &[Arc<Tileset>], known: &[Gid]) -> Vec<Gid> {
+    let mut next_gid = 1;
+    tilesets
+        .iter()
+        .enumerate()
+        .map(|(index, tileset)| {
+            let first_gid = known.get(index).copied().unwrap_or(Gid(next_gid));
+            let last_id = tileset.tiles().map(|(id, _)| id + 1).max().unwrap_or(0);
+            next_gid = first_gid.0 + last_id.max(tileset.tilecount);
+            first_gid
+        })
+        .collect()
 }
 
 impl Map {
-    pub(crate) fn parse_xml(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
-        attrs: Vec<OwnedAttribute>,
-        map_path: &Path,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
-    ) -> Result<Map> {
+    /// Writes this map to a new TMX file at the given path, replacing it if it exists.
+    ///
+    /// External tilesets and templates are referred to by their path, written relative to the
+    /// directory the TMX file is written to, and embedded tilesets are written within the map. See
+    /// [`WriteOptions`] for how the map is formatted.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Loader, PropertyValue, WriteOptions};
+    ///
+    /// let mut map = Loader::new().load_tmx_map("assets/tiled_base64_zlib.tmx")?;
+    /// map.properties
+    ///     .insert("edited".into(), PropertyValue::BoolValue(true));
+    /// // Apart from the new property, the file is written the way it was read.
+    /// let options = WriteOptions {
+    ///     preserve_formatting: true,
+    ///     ..Default::default()
+    /// };
+    /// map.write_to_tmx("assets/tiled_base64_zlib.tmx", options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_to_tmx(&self, path: impl AsRef<Path>, options: WriteOptions) -> Result<()> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path).map_err(|err| Error::ResourceWritingError {
+            path: path.to_owned(),
+            err,
+        })?;
+        self.write_tmx(std::io::BufWriter::new(file), path, options)
+    }
+
+    /// Writes this map in the TMX format to an arbitrary writer.
+    ///
+    /// `path` is the path the resulting file is meant to be stored at, and is only used to make the
+    /// paths the map refers to relative to it.
+    ///
+    /// Fails if the data of one of the map's tile layers can't be decoded, see
+    /// [`FiniteTileLayerData::ensure_decoded()`](crate::FiniteTileLayerData::ensure_decoded()).
+    pub fn write_tmx(
+        &self,
+        writer: impl Write,
+        path: impl AsRef<Path>,
+        options: WriteOptions,
+    ) -> Result<()> {
+        crate::write::xml::write_map(self, writer, path.as_ref(), options)
+    }
+
+    /// Whether the map is written with `\r\n` line endings, which is only the case when the file
+    /// it was loaded from used them and formatting is preserved.
+    pub(crate) fn writes_crlf(&self, options: WriteOptions) -> bool {
+        options.preserve_formatting && self.source.get().is_some_and(|source| source.crlf)
+    }
+
+    /// Writes the `<map>` element, with paths relative to `path_relative_to`.
+    pub(crate) fn write_xml<W: Write>(
+        &self,
+        writer: &mut EventWriter<W>,
+        path_relative_to: &Path,
+        options: WriteOptions,
+    ) -> Result<()> {
+        let source = self.source.get().filter(|_| options.preserve_formatting);
+        let next_layer_id = self.layers.iter().map(LayerData::max_id).max().unwrap_or(0) + 1;
+        let next_object_id = self
+            .layers
+            .iter()
+            .map(LayerData::max_object_id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        let mut attrs = vec![("version", self.version.to_string())];
+        if let Some(tiled_version) = self.tiled_version {
+            // Versions are written the way they were read, such as `1.10.0` rather than `1.10`,
+            // unless they were changed.
+            let source_version = source
+                .and_then(|source| source.tiled_version.clone())
+                .filter(|source| source.parse().ok() == Some(tiled_version));
+            attrs.push((
+                "tiledversion",
+                source_version.unwrap_or_else(|| tiled_version.to_string()),
+            ));
+        }
+        if let Some(user_type) = &self.user_type {
+            attrs.push(("class", user_type.clone()));
+        }
+        attrs.push(("orientation", self.orientation.to_string()));
+        let render_order = source.and_then(|source| source.render_order.clone());
+        attrs.push((
+            "renderorder",
+            render_order.unwrap_or_else(|| "right-down".to_owned()),
+        ));
+        if let Some(level) = source.and_then(|source| source.compression_level) {
+            attrs.push(("compressionlevel", level.to_string()));
+        }
+        attrs.push(("width", self.width.to_string()));
+        attrs.push(("height", self.height.to_string()));
+        attrs.push(("tilewidth", self.tile_width.to_string()));
+        attrs.push(("tileheight", self.tile_height.to_string()));
+        attrs.push(("infinite", if self.infinite { "1" } else { "0" }.to_owned()));
+        if self.orientation == Orientation::Hexagonal {
+            attrs.push(("hexsidelength", self.hex_side_length.to_string()));
+        }
+        if matches!(
+            self.orientation,
+            Orientation::Staggered | Orientation::Hexagonal
+        ) {
+            attrs.push(("staggeraxis", self.stagger_axis.as_str().to_owned()));
+            attrs.push(("staggerindex", self.stagger_index.as_str().to_owned()));
+        }
+        if self.parallax_origin_x != 0.0 || self.parallax_origin_y != 0.0 {
+            attrs.push(("parallaxoriginx", self.parallax_origin_x.to_string()));
+            attrs.push(("parallaxoriginy", self.parallax_origin_y.to_string()));
+        }
+        if let Some(color) = self.background_color {
+            attrs.push(("backgroundcolor", color.to_short_string()));
+        }
+        let (next_layer_id, next_object_id) = match source {
+            Some(source) => (
+                next_layer_id.max(source.next_layer_id),
+                next_object_id.max(source.next_object_id),
+            ),
+            None => (next_layer_id, next_object_id),
+        };
+        attrs.push(("nextlayerid", next_layer_id.to_string()));
+        attrs.push(("nextobjectid", next_object_id.to_string()));
+        self.extensions.write_start_element(writer, "map", &attrs)?;
+
+        if let Some(settings) = source.and_then(|source| source.editor_settings.as_ref()) {
+            write_start_element(writer, "editorsettings", &[])?;
+            for (name, attrs) in settings {
+                let attrs: Vec<_> = attrs
+                    .iter()
+                    .map(|attr| (attr.name.local_name.as_str(), attr.value.clone()))
+                    .collect();
+                write_empty_element(writer, name, &attrs)?;
+            }
+            write_end_element(writer)?;
+        }
+
+        // The properties are written before the tileset or layer they came before in the source.
+        let properties_index = source.map_or(0, |source| source.properties_index);
+        let write_properties_before = |writer: &mut EventWriter<W>, index: usize| {
+            if index == properties_index {
+                write_properties(writer, &self.properties)?;
+            }
+            Ok::<_, Error>(())
+        };
+        for (index, (tileset, first_gid)) in self.tilesets.iter().zip(&self.first_gids).enumerate()
+        {
+            write_properties_before(writer, index)?;
+            match tileset.source_path() {
+                Some(source) => write_empty_element(
+                    writer,
+                    "tileset",
+                    &[
+                        ("firstgid", first_gid.0.to_string()),
+                        ("source", relative_path(path_relative_to, source)),
+                    ],
+                )?,
+                None => tileset.write_xml(writer, path_relative_to, Some(*first_gid))?,
+            }
+        }
+        for (index, layer) in self.layers.iter().enumerate() {
+            write_properties_before(writer, self.tilesets.len() + index)?;
+            layer.write_xml(writer, self, path_relative_to, options)?;
+        }
+        if properties_index >= self.tilesets.len() + self.layers.len() {
+            write_properties(writer, &self.properties)?;
+        }
+        self.extensions.write_elements(writer)?;
+        write_end_element(writer)
+    }
+
+    /// Creates a map from the attributes of a `<map>` element, without any tileset, layer or
+    /// property.
+    pub(crate) fn from_xml_attrs(attrs: &[OwnedAttribute], map_path: &Path) -> Result<Map> {
         let (
-            (c, infinite, user_type, user_class, stagger_axis, stagger_index),
+            (c, infinite, user_type, user_class, stagger_axis, stagger_index, hex_side_length),
+            (parallax_origin_x, parallax_origin_y),
             (v, o, w, h, tw, th),
         ) = get_attrs!(
             for v in attrs {
@@ -143,20 +1332,76 @@ impl Map {
                 Some("class") => user_class ?= v.parse(),
                 Some("staggeraxis") => stagger_axis ?= v.parse::<StaggerAxis>(),
                 Some("staggerindex") => stagger_index ?= v.parse::<StaggerIndex>(),
-                "version" => version = v,
+                Some("hexsidelength") => hex_side_length ?= v.parse::<u32>(),
+                Some("parallaxoriginx") => parallax_origin_x ?= v.parse::<f32>(),
+                Some("parallaxoriginy") => parallax_origin_y ?= v.parse::<f32>(),
+                "version" => version ?= v.parse::<Version>(),
                 "orientation" => orientation ?= v.parse::<Orientation>(),
                 "width" => width ?= v.parse::<u32>(),
                 "height" => height ?= v.parse::<u32>(),
                 "tilewidth" => tile_width ?= v.parse::<u32>(),
                 "tileheight" => tile_height ?= v.parse::<u32>(),
             }
-            ((colour, infinite, user_type, user_class, stagger_axis, stagger_index), (version, orientation, width, height, tile_width, tile_height))
+            ((colour, infinite, user_type, user_class, stagger_axis, stagger_index, hex_side_length), (parallax_origin_x, parallax_origin_y), (version, orientation, width, height, tile_width, tile_height))
+        );
+        let (tiled_version, render_order, compression_level, next_layer_id, next_object_id) = get_attrs!(
+            for v in attrs {
+                Some("tiledversion") => tiled_version = v,
+                Some("renderorder") => render_order = v,
+                Some("compressionlevel") => compression_level ?= v.parse::<i32>(),
+                Some("nextlayerid") => next_layer_id ?= v.parse::<u32>(),
+                Some("nextobjectid") => next_object_id ?= v.parse::<u32>(),
+            }
+            (tiled_version, render_order, compression_level, next_layer_id, next_object_id)
         );
 
-        let infinite = infinite.unwrap_or(false);
-        let user_type = user_type.or(user_class);
-        let stagger_axis = stagger_axis.unwrap_or_default();
-        let stagger_index = stagger_index.unwrap_or_default();
+        Ok(Map {
+            version: v,
+            tiled_version: tiled_version.as_deref().and_then(|v| v.parse().ok()),
+            orientation: o,
+            width: w,
+            height: h,
+            tile_width: tw,
+            tile_height: th,
+            stagger_axis: stagger_axis.unwrap_or_default(),
+            stagger_index: stagger_index.unwrap_or_default(),
+            hex_side_length: hex_side_length.unwrap_or(0),
+            parallax_origin_x: parallax_origin_x.unwrap_or(0.0),
+            parallax_origin_y: parallax_origin_y.unwrap_or(0.0),
+            tilesets: Vec::new(),
+            first_gids: Vec::new(),
+            layers: Vec::new(),
+            properties: HashMap::new(),
+            background_color: c,
+            infinite: infinite.unwrap_or(false),
+            user_type: user_type.or(user_class),
+            extensions: XmlExtensions::default(),
+            root_path: map_path.parent().ok_or(Error::PathIsNotFile)?.to_owned(),
+            source: SourceInfo::new(MapSource {
+                tiled_version,
+                render_order,
+                compression_level,
+                next_layer_id: next_layer_id.unwrap_or(0),
+                next_object_id: next_object_id.unwrap_or(0),
+                editor_settings: None,
+                properties_index: 0,
+                crlf: false,
+            }),
+        })
+    }
+
+    pub(crate) fn parse_xml(
+        parser: &mut impl Iterator<Item = XmlEventResult>,
+        attrs: Vec<OwnedAttribute>,
+        map_path: &Path,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<Map> {
+        let mut map = Map::from_xml_attrs(&attrs, map_path)?;
+        let infinite = map.infinite;
+        // The line break right after the opening tag tells which line endings the file uses.
+        let mut parser = parser.peekable();
+        let crlf = matches!(parser.peek(), Some(Ok(XmlEvent::Whitespace(s))) if s.contains("\r\n"));
+        let parser = &mut parser;
 
         // We can only parse sequentally, but tilesets are guaranteed to appear before layers.
         // So we can pass in tileset data to layer construction without worrying about unfinished
@@ -164,115 +1409,207 @@ impl Map {
         let mut layers = Vec::new();
         let mut properties = HashMap::new();
         let mut tilesets = Vec::new();
+        // External tilesets are only loaded once all of them are known, so that they can be loaded
+        // together.
+        let mut pending_tilesets = Vec::new();
+        // How many tilesets and layers were read, to know where the properties were among them.
+        let mut elements = 0;
+        let mut properties_index = 0;
+        let mut editor_settings = None;
 
         parse_tag!(parser, "map", {
+            "editorsettings" => |_| {
+                let mut settings = Vec::new();
+                parse_tag!(parser, "editorsettings", {
+                    "chunksize" => |attrs| {
+                        settings.push(("chunksize".to_owned(), attrs));
+                        Ok::<(), Error>(())
+                    },
+                    "export" => |attrs| {
+                        settings.push(("export".to_owned(), attrs));
+                        Ok(())
+                    },
+                });
+                editor_settings = Some(settings);
+                Ok(())
+            },
             "tileset" => |attrs: Vec<OwnedAttribute>| {
-                let res = Tileset::parse_xml_in_map(parser, &attrs, map_path,  reader, cache)?;
+                elements += 1;
+                let res = Tileset::parse_xml_in_map(parser, &attrs, map_path,  ctx)?;
                 match res.result_type {
                     EmbeddedParseResultType::ExternalReference { tileset_path } => {
-                        let tileset = if let Some(ts) = cache.get_tileset(&tileset_path) {
-                            ts
-                        } else {
-                            let tileset = Arc::new(crate::parse::xml::parse_tileset(&tileset_path,  reader, cache)?);
-                            cache.insert_tileset(tileset_path.clone(), tileset.clone());
-                            tileset
-                        };
-
-                        tilesets.push(MapTilesetGid{first_gid: res.first_gid, tileset});
+                        pending_tilesets.push(MapTilesetEntry::External{first_gid: res.first_gid, path: tileset_path});
                     }
                     EmbeddedParseResultType::Embedded { tileset } => {
-                        tilesets.push(MapTilesetGid{first_gid: res.first_gid, tileset: Arc::new(tileset)});
+                        pending_tilesets.push(MapTilesetEntry::Loaded(MapTilesetGid{first_gid: res.first_gid, tileset: Arc::new(tileset.embedded_in(map.version, map.tiled_version))}));
                     },
                 };
                 Ok(())
             },
             "layer" => |attrs| {
-                layers.push(LayerData::new(
-                    parser,
-                    attrs,
-                    LayerTag::Tiles,
-                    infinite,
-                    map_path,
-                    &tilesets,
-                    None,
-                    reader,
-                    cache
-                )?);
+                elements += 1;
+                resolve_map_tilesets(std::mem::take(&mut pending_tilesets), &mut tilesets, ctx)?;
+                let inputs = LayerInputs { infinite, map_path, tilesets: &tilesets, for_tileset: None };
+                layers.push(LayerData::new(parser, attrs, LayerTag::Tiles, &inputs, ctx)?);
                 Ok(())
             },
             "imagelayer" => |attrs| {
-                layers.push(LayerData::new(
-                    parser,
-                    attrs,
-                    LayerTag::Image,
-                    infinite,
-                    map_path,
-                    &tilesets,
-                    None,
-                    reader,
-                    cache
-                )?);
+                elements += 1;
+                resolve_map_tilesets(std::mem::take(&mut pending_tilesets), &mut tilesets, ctx)?;
+                let inputs = LayerInputs { infinite, map_path, tilesets: &tilesets, for_tileset: None };
+                layers.push(LayerData::new(parser, attrs, LayerTag::Image, &inputs, ctx)?);
                 Ok(())
             },
             "objectgroup" => |attrs| {
-                layers.push(LayerData::new(
-                    parser,
-                    attrs,
-                    LayerTag::Objects,
-                    infinite,
-                    map_path,
-                    &tilesets,
-                    None,
-                    reader,
-                    cache
-                )?);
+                elements += 1;
+                resolve_map_tilesets(std::mem::take(&mut pending_tilesets), &mut tilesets, ctx)?;
+                let inputs = LayerInputs { infinite, map_path, tilesets: &tilesets, for_tileset: None };
+                layers.push(LayerData::new(parser, attrs, LayerTag::Objects, &inputs, ctx)?);
                 Ok(())
             },
             "group" => |attrs| {
-                layers.push(LayerData::new(
-                    parser,
-                    attrs,
-                    LayerTag::Group,
-                    infinite,
-                    map_path,
-                    &tilesets,
-                    None,
-                    reader,
-                    cache
-                )?);
+                elements += 1;
+                resolve_map_tilesets(std::mem::take(&mut pending_tilesets), &mut tilesets, ctx)?;
+                let inputs = LayerInputs { infinite, map_path, tilesets: &tilesets, for_tileset: None };
+                layers.push(LayerData::new(parser, attrs, LayerTag::Group, &inputs, ctx)?);
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties_index = elements;
+                properties = parse_properties(parser, ctx.project, &ctx.interner, ctx.diagnostics)?;
                 Ok(())
             },
         });
+        map.extensions = ctx.diagnostics.take_extensions();
+        if let Some(source) = map.source.get_mut() {
+            source.properties_index = properties_index;
+            source.editor_settings = editor_settings;
+            source.crlf = crlf;
+        }
+
+        resolve_map_tilesets(pending_tilesets, &mut tilesets, ctx)?;
+        let (first_gids, tilesets) = tilesets
+            .into_iter()
+            .map(|ts| (ts.first_gid, ts.tileset))
+            .unzip();
+        map.tilesets = tilesets;
+        map.first_gids = first_gids;
+        map.layers = layers;
+        map.properties = properties;
+        if ctx.resolve_object_references {
+            map.check_object_references(ctx.diagnostics);
+        }
+        Ok(map)
+    }
+}
 
-        // We do not need first GIDs any more
-        let tilesets = tilesets.into_iter().map(|ts| ts.tileset).collect();
+#[cfg(feature = "json")]
+impl Map {
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        map_path: &Path,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<Map> {
+        use crate::parse::json::{as_objects, as_u32, as_version, JsonObjectExt};
 
-        Ok(Map {
-            version: v,
-            orientation: o,
-            width: w,
-            height: h,
-            tile_width: tw,
-            tile_height: th,
-            stagger_axis,
-            stagger_index,
+        let version = object.req("version", as_version)?;
+        let tiled_version = object.opt_str("tiledversion")?.and_then(|v| v.parse().ok());
+        let infinite = object.opt_bool("infinite")?.unwrap_or(false);
+
+        let mut pending_tilesets = Vec::new();
+        if let Some(json_tilesets) = object.opt_array("tilesets")? {
+            for tileset in as_objects(json_tilesets, "tilesets")? {
+                let res = Tileset::parse_json_in_map(tileset, map_path, ctx)?;
+                pending_tilesets.push(match res.result_type {
+                    EmbeddedParseResultType::ExternalReference { tileset_path } => {
+                        MapTilesetEntry::External {
+                            first_gid: res.first_gid,
+                            path: tileset_path,
+                        }
+                    }
+                    EmbeddedParseResultType::Embedded { tileset } => {
+                        MapTilesetEntry::Loaded(MapTilesetGid {
+                            first_gid: res.first_gid,
+                            tileset: Arc::new(tileset.embedded_in(version, tiled_version)),
+                        })
+                    }
+                });
+            }
+        }
+        let mut tilesets = Vec::new();
+        resolve_map_tilesets(pending_tilesets, &mut tilesets, ctx)?;
+
+        let inputs = LayerInputs {
+            infinite,
+            map_path,
+            tilesets: &tilesets,
+            for_tileset: None,
+        };
+        let layers = match object.opt_array("layers")? {
+            Some(layers) => as_objects(layers, "layers")?
+                .map(|layer| LayerData::parse_json(layer, &inputs, ctx))
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+
+        let (first_gids, tilesets) = tilesets
+            .into_iter()
+            .map(|ts| (ts.first_gid, ts.tileset))
+            .unzip();
+
+        let map = Map {
+            version,
+            tiled_version,
+            orientation: object.req("orientation", |v| v.as_str()?.parse().ok())?,
+            width: object.req("width", as_u32)?,
+            height: object.req("height", as_u32)?,
+            tile_width: object.req("tilewidth", as_u32)?,
+            tile_height: object.req("tileheight", as_u32)?,
+            stagger_axis: object
+                .opt("staggeraxis", |v| v.as_str()?.parse().ok())?
+                .unwrap_or_default(),
+            stagger_index: object
+                .opt("staggerindex", |v| v.as_str()?.parse().ok())?
+                .unwrap_or_default(),
+            hex_side_length: object.opt_u32("hexsidelength")?.unwrap_or(0),
+            parallax_origin_x: object.opt_f32("parallaxoriginx")?.unwrap_or(0.0),
+            parallax_origin_y: object.opt_f32("parallaxoriginy")?.unwrap_or(0.0),
             tilesets,
+            first_gids,
             layers,
-            properties,
-            background_color: c,
+            properties: crate::properties::parse_json_properties(
+                object,
+                ctx.project,
+                &ctx.interner,
+                ctx.diagnostics,
+            )?,
+            background_color: object.opt_color("backgroundcolor")?,
             infinite,
-            user_type,
-        })
+            user_type: object.opt_str("class")?.map(str::to_owned),
+            extensions: XmlExtensions::default(),
+            root_path: map_path.parent().ok_or(Error::PathIsNotFile)?.to_owned(),
+            source: SourceInfo::new(MapSource {
+                tiled_version: object.opt_str("tiledversion")?.map(str::to_owned),
+                render_order: object.opt_str("renderorder")?.map(str::to_owned),
+                compression_level: object.opt_i32("compressionlevel")?,
+                next_layer_id: object.opt_u32("nextlayerid")?.unwrap_or(0),
+                next_object_id: object.opt_u32("nextobjectid")?.unwrap_or(0),
+                editor_settings: None,
+                properties_index: 0,
+                crlf: false,
+            }),
+        };
+        if ctx.resolve_object_references {
+            map.check_object_references(ctx.diagnostics);
+        }
+        Ok(map)
     }
 }
 
 // Specifies whether the odd or even rows/columns are shifted half a tile
 // right/down. Only applies to Staggered and Hexagonal map orientations.
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub enum StaggerIndex {
     Even,
@@ -310,9 +1647,19 @@ impl FromStr for StaggerIndex {
     }
 }
 
+impl StaggerIndex {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StaggerIndex::Even => "even",
+            StaggerIndex::Odd => "odd",
+        }
+    }
+}
+
 // Specifies which axis is staggered. Only applies to Staggered and Hexagonal
 // map orientations.
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub enum StaggerAxis {
     X,
@@ -350,8 +1697,18 @@ impl FromStr for StaggerAxis {
     }
 }
 
+impl StaggerAxis {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StaggerAxis::X => "x",
+            StaggerAxis::Y => "y",
+        }
+    }
+}
+
 /// Represents the way tiles are laid out in a map.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub enum Orientation {
     Orthogonal,
@@ -403,6 +1760,48 @@ impl fmt::Display for Orientation {
     }
 }
 
+/// Represents the point of a map's content which stays in place when it is resized with
+/// [`Map::resize`], relative to the bounds of the map.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+pub enum ResizeAnchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl ResizeAnchor {
+    /// Returns the position of the anchor relative to the bounds of the map, as a fraction of
+    /// its width and height.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::ResizeAnchor;
+    ///
+    /// assert_eq!(ResizeAnchor::Bottom.fraction(), (0.5, 1.0));
+    /// ```
+    pub fn fraction(self) -> (f32, f32) {
+        match self {
+            ResizeAnchor::TopLeft => (0.0, 0.0),
+            ResizeAnchor::Top => (0.5, 0.0),
+            ResizeAnchor::TopRight => (1.0, 0.0),
+            ResizeAnchor::Left => (0.0, 0.5),
+            ResizeAnchor::Center => (0.5, 0.5),
+            ResizeAnchor::Right => (1.0, 0.5),
+            ResizeAnchor::BottomLeft => (0.0, 1.0),
+            ResizeAnchor::Bottom => (0.5, 1.0),
+            ResizeAnchor::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
 /// A Tiled global tile ID.
 ///
 /// These are used to identify tiles in a map. Since the map may have more than one tileset, an
@@ -414,6 +1813,7 @@ impl fmt::Display for Orientation {
 ///
 /// See also: <https://doc.mapeditor.org/en/latest/reference/global-tile-ids/>
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct Gid(pub u32);
 
 impl Gid {
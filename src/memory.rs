@@ -0,0 +1,111 @@
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    iter::FromIterator,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{util::normalize_path, ResourceReader};
+
+/// A [`ResourceReader`] that serves files held in memory, such as files downloaded at runtime on
+/// the web, where there is no filesystem to read them from.
+///
+/// Unlike [`EmbeddedResourceReader`](crate::EmbeddedResourceReader), which serves files embedded
+/// in the binary, this reader owns the contents of its files, so they can be added and replaced
+/// at any time. Files are registered along with the path they are loaded with, usually the path
+/// they have relative to the map; Paths are [normalized](crate::VirtualPath) before looking them
+/// up.
+///
+/// ## Example
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use tiled::{DefaultResourceCache, Loader, MemoryResourceReader};
+///
+/// // Typically downloaded, for instance with `fetch()` on the web.
+/// let map = std::fs::read("assets/tiled_base64_external.tmx")?;
+/// let tileset = std::fs::read("assets/tilesheet.tsx")?;
+///
+/// let reader = MemoryResourceReader::new()
+///     .with_file("maps/level.tmx", map)
+///     .with_file("maps/tilesheet.tsx", tileset);
+/// let mut loader = Loader::with_cache_and_reader(DefaultResourceCache::new(), reader);
+/// let map = loader.load_tmx_map("maps/level.tmx")?;
+/// assert_eq!(map.tilesets()[0].name, "tilesheet");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResourceReader {
+    files: HashMap<PathBuf, Arc<[u8]>>,
+}
+
+impl MemoryResourceReader {
+    /// Creates a reader with no files in it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file to the reader, replacing any file previously added at the same path.
+    pub fn insert(&mut self, path: impl AsRef<Path>, data: impl Into<Arc<[u8]>>) {
+        if let Some(path) = normalize_path(path.as_ref()) {
+            self.files.insert(path, data.into());
+        }
+    }
+
+    /// Adds a file to the reader and returns it. See [`MemoryResourceReader::insert()`].
+    pub fn with_file(mut self, path: impl AsRef<Path>, data: impl Into<Arc<[u8]>>) -> Self {
+        self.insert(path, data);
+        self
+    }
+
+    /// Removes the file at the path given from the reader, returning its contents if there was
+    /// one.
+    pub fn remove(&mut self, path: impl AsRef<Path>) -> Option<Arc<[u8]>> {
+        normalize_path(path.as_ref()).and_then(|path| self.files.remove(&path))
+    }
+
+    /// Returns whether the reader has a file at the path given.
+    pub fn contains(&self, path: impl AsRef<Path>) -> bool {
+        normalize_path(path.as_ref()).is_some_and(|path| self.files.contains_key(&path))
+    }
+}
+
+impl<P: AsRef<Path>, D: Into<Arc<[u8]>>> FromIterator<(P, D)> for MemoryResourceReader {
+    fn from_iter<I: IntoIterator<Item = (P, D)>>(iter: I) -> Self {
+        let mut reader = Self::new();
+        for (path, data) in iter {
+            reader.insert(path, data);
+        }
+        reader
+    }
+}
+
+impl ResourceReader for MemoryResourceReader {
+    type Resource = Cursor<Arc<[u8]>>;
+    type Error = std::io::Error;
+
+    fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+        normalize_path(path)
+            .and_then(|path| self.files.get(&path).cloned())
+            .map(Cursor::new)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "file is not in memory")
+            })
+    }
+
+    fn list_files(&mut self, directory: &Path) -> std::result::Result<Vec<PathBuf>, Self::Error> {
+        let directory = match normalize_path(directory) {
+            Some(directory) => directory,
+            None => return Ok(Vec::new()),
+        };
+        let mut files: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(|path| path.parent() == Some(&directory))
+            .cloned()
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
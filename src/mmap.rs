@@ -0,0 +1,100 @@
+use std::{
+    fmt,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use memmap2::Mmap;
+
+use crate::{FilesystemResourceReader, ResourceReader};
+
+/// A [`ResourceReader`] that memory-maps the files it reads from the filesystem instead of reading
+/// them through [`File`](std::fs::File) handles. Requires the `mmap` feature.
+///
+/// Maps, tilesets and templates are then parsed straight from the mapped memory, which saves the
+/// copies made when reading files in small pieces and leaves it to the operating system to page
+/// in very large files as they are parsed.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{DefaultResourceCache, Loader, MmapResourceReader};
+///
+/// // Safety: The assets aren't modified while the program runs.
+/// let reader = unsafe { MmapResourceReader::new() };
+/// let mut loader = Loader::with_cache_and_reader(DefaultResourceCache::new(), reader);
+/// let map = loader.load_tmx_map("assets/tiled_base64_external.tmx")?;
+/// assert_eq!(map.tilesets()[0].name, "tilesheet");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmapResourceReader {
+    _private: (),
+}
+
+impl MmapResourceReader {
+    /// Creates a reader memory-mapping the files it reads.
+    ///
+    /// # Safety
+    /// The files read must not be modified, truncated or removed by this or any other process
+    /// while they are mapped, which lasts until they are done being parsed. The contents of a
+    /// mapped file changing under the parser is undefined behavior, and truncating it may crash
+    /// the program.
+    #[allow(unsafe_code)]
+    pub unsafe fn new() -> Self {
+        Self { _private: () }
+    }
+}
+
+impl ResourceReader for MmapResourceReader {
+    type Resource = MappedFile;
+    type Error = std::io::Error;
+
+    #[allow(unsafe_code)]
+    fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+        let file = std::fs::File::open(path)?;
+        // Safety: Whoever created the reader guaranteed that the files it reads aren't modified
+        // while mapped.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(MappedFile {
+            data: Cursor::new(map),
+        })
+    }
+
+    fn list_files(&mut self, directory: &Path) -> std::result::Result<Vec<PathBuf>, Self::Error> {
+        FilesystemResourceReader.list_files(directory)
+    }
+}
+
+/// A file memory-mapped by a [`MmapResourceReader`], which is unmapped once dropped.
+pub struct MappedFile {
+    data: Cursor<Mmap>,
+}
+
+impl MappedFile {
+    /// Returns the contents of the file.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data.get_ref()
+    }
+}
+
+impl Read for MappedFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.data.read(buf)
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Debug for MappedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedFile")
+            .field("len", &self.as_bytes().len())
+            .finish()
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use crate::{Map, Object, ObjectShape, ResolvedShape};
+
+/// A spatial index over the objects of a [`Map`], which finds the objects in an area without going
+/// through all of them.
+///
+/// Objects are indexed by their bounding rectangle, in the same coordinates as their position:
+/// The offsets and parallax factors of the layers containing them aren't applied. Tile objects
+/// are placed according to the [object alignment](crate::Tileset::object_alignment) of their
+/// tileset, and text objects, which have no geometric shape, aren't indexed.
+///
+/// Queries only test bounding rectangles, so the objects returned may need to be checked against
+/// their [exact shape](crate::ObjectData::resolved_shape) afterwards.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// let map = tiled::Loader::new().load_tmx_map("assets/tiled_object_index.tmx")?;
+/// let index = tiled::ObjectIndex::new(&map);
+///
+/// let names: Vec<_> = index
+///     .query_rect(0.0, 0.0, 64.0, 64.0)
+///     .map(|object| object.name.clone())
+///     .collect();
+/// assert_eq!(names, ["rect"]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ObjectIndex<'map> {
+    cell_size: f32,
+    /// The indexed objects, in display order, along with their bounding rectangles.
+    objects: Vec<(Object<'map>, (f32, f32, f32, f32))>,
+    /// The indices of the objects overlapping each cell of the grid.
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    /// The range of cells `(x0, y0, x1, y1)` containing objects, inclusive.
+    extent: (i32, i32, i32, i32),
+}
+
+impl<'map> ObjectIndex<'map> {
+    /// Indexes all the objects of the map, including those in object layers nested in group
+    /// layers, using grid cells four tiles wide and high.
+    pub fn new(map: &'map Map) -> Self {
+        let cell_size = (map.tile_width.max(map.tile_height) * 4).max(1) as f32;
+        Self::with_cell_size(map, cell_size)
+    }
+
+    /// Indexes all the objects of the map using grid cells of the specified size in pixels.
+    ///
+    /// Cells a few times larger than the typical object work best.
+    ///
+    /// ## Panics
+    /// Panics if `cell_size` isn't a positive number.
+    pub fn with_cell_size(map: &'map Map, cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "cell size must be positive");
+
+        let mut index = ObjectIndex {
+            cell_size,
+            objects: Vec::new(),
+            cells: HashMap::new(),
+            extent: (i32::MAX, i32::MAX, i32::MIN, i32::MIN),
+        };
+        let objects = map.object_layers().flat_map(|layer| layer.objects());
+        for object in objects {
+            if let Some(bounds) = bounding_rect(&object) {
+                let (x0, y0, x1, y1) = index.cell_range(bounds);
+                let extent = &mut index.extent;
+                *extent = (
+                    extent.0.min(x0),
+                    extent.1.min(y0),
+                    extent.2.max(x1),
+                    extent.3.max(y1),
+                );
+                for cell_y in y0..=y1 {
+                    for cell_x in x0..=x1 {
+                        index
+                            .cells
+                            .entry((cell_x, cell_y))
+                            .or_default()
+                            .push(index.objects.len());
+                    }
+                }
+                index.objects.push((object, bounds));
+            }
+        }
+        index
+    }
+
+    /// Returns the number of objects indexed.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Returns whether no object has been indexed.
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Returns the objects whose bounding rectangle intersects the specified rectangle, in display
+    /// order. Rectangles that only touch each other's edges intersect.
+    pub fn query_rect(
+        &self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> impl Iterator<Item = Object<'map>> + '_ {
+        // Only go through the cells that may contain objects, in case the rectangle is huge.
+        let (x0, y0, x1, y1) = self.cell_range((x, y, width, height));
+        let (x0, y0) = (x0.max(self.extent.0), y0.max(self.extent.1));
+        let (x1, y1) = (x1.min(self.extent.2), y1.min(self.extent.3));
+        let mut candidates: Vec<usize> = (y0..=y1)
+            .flat_map(|cell_y| (x0..=x1).map(move |cell_x| (cell_x, cell_y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates.into_iter().filter_map(move |i| {
+            let (object, (ox, oy, ow, oh)) = &self.objects[i];
+            let intersects = *ox <= x + width && x <= ox + ow && *oy <= y + height && y <= oy + oh;
+            intersects.then_some(*object)
+        })
+    }
+
+    /// Returns the objects whose bounding rectangle contains the specified point, in display
+    /// order.
+    pub fn query_point(&self, x: f32, y: f32) -> impl Iterator<Item = Object<'map>> + '_ {
+        self.query_rect(x, y, 0.0, 0.0)
+    }
+
+    /// Returns the range of cells `(x0, y0, x1, y1)` overlapped by a rectangle, inclusive.
+    fn cell_range(&self, (x, y, width, height): (f32, f32, f32, f32)) -> (i32, i32, i32, i32) {
+        let cell = |v: f32| (v / self.cell_size).floor() as i32;
+        (cell(x), cell(y), cell(x + width), cell(y + height))
+    }
+}
+
+/// Returns the bounding rectangle `(x, y, width, height)` of an object, if it has a shape.
+fn bounding_rect(object: &Object) -> Option<(f32, f32, f32, f32)> {
+    match (object.get_tile(), &object.shape) {
+        // Tile objects are positioned by the point given by their tileset's alignment.
+        (Some(_), ObjectShape::Rect { width, height }) => {
+            let points = object.transform().corners(*width, *height).to_vec();
+            Some(ResolvedShape::Polygon { points }.bounding_rect())
+        }
+        _ => object.resolved_shape().map(|shape| shape.bounding_rect()),
+    }
+}
@@ -1,19 +1,32 @@
-use std::{collections::HashMap, path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use xml::attribute::OwnedAttribute;
+use xml::{attribute::OwnedAttribute, reader::XmlEvent, EventWriter};
 
 use crate::{
     error::{Error, Result},
-    properties::{parse_properties, Properties},
-    template::Template,
-    util::{get_attrs, map_wrapper, parse_tag, XmlEventResult},
-    Color, Gid, MapTilesetGid, ResourceCache, ResourceReader, Tile, TileId, Tileset,
+    parse::LoadContext,
+    properties::{parse_properties, write_properties, Properties},
+    util::{
+        get_attrs, join_reference, map_wrapper, parse_tag, write_characters, write_empty_element,
+        write_end_element, write_start_element, XmlEventResult,
+    },
+    Color, FlattenedLayer, Gid, GidMapping, MapTilesetGid, ResourceCache, ResourceReader, Template,
+    Tile, TileId, TileTransform, Tileset, XmlExtensions,
 };
 
+use crate::util::relative_path;
+use crate::write::SourceInfo;
+
 /// The location of the tileset this tile is in
 ///
 /// Tilesets can be contained within either a map or a template.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TilesetLocation {
     /// Index into the Map's tileset list, guaranteed to be a valid index of the map tileset container.
     Map(usize),
@@ -23,6 +36,7 @@ pub enum TilesetLocation {
 
 /// Stores the internal tile gid about a layer tile, along with how it is flipped.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ObjectTileData {
     /// A valid TilesetLocation that points to a tileset that **may or may not contain** this tile.
     tileset_location: TilesetLocation,
@@ -50,6 +64,12 @@ impl ObjectTileData {
         &self.tileset_location
     }
 
+    /// Returns the rotation and mirroring equivalent to this tile's flip flags. See
+    /// [`LayerTileData::transform()`](crate::LayerTileData::transform()).
+    pub fn transform(&self) -> TileTransform {
+        TileTransform::from_flags(self.flip_h, self.flip_v, self.flip_d)
+    }
+
     const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x80000000;
     const FLIPPED_VERTICALLY_FLAG: u32 = 0x40000000;
     const FLIPPED_DIAGONALLY_FLAG: u32 = 0x20000000;
@@ -90,6 +110,26 @@ impl ObjectTileData {
             })
         }
     }
+
+    /// Returns the GID of this tile plus its flipping bits, given the first GIDs of the tilesets
+    /// of the map it is contained in. Template tilesets are assumed to have a first GID of 1.
+    pub(crate) fn to_bits(&self, first_gids: &[Gid]) -> u32 {
+        let first_gid = match self.tileset_location {
+            TilesetLocation::Map(n) => first_gids[n],
+            TilesetLocation::Template(_) => Gid(1),
+        };
+        let mut bits = first_gid.0 + self.id;
+        if self.flip_h {
+            bits |= Self::FLIPPED_HORIZONTALLY_FLAG;
+        }
+        if self.flip_v {
+            bits |= Self::FLIPPED_VERTICALLY_FLAG;
+        }
+        if self.flip_d {
+            bits |= Self::FLIPPED_DIAGONALLY_FLAG;
+        }
+        bits
+    }
 }
 
 map_wrapper!(
@@ -118,6 +158,7 @@ impl<'map> ObjectTile<'map> {
 ///
 /// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tmx-object).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub enum ObjectShape {
     Rect {
@@ -134,24 +175,387 @@ pub enum ObjectShape {
     Polygon {
         points: Vec<(f32, f32)>,
     },
+    /// A point, such as a spawn location or a waypoint, read from objects containing a `<point>`
+    /// element. Holds the position of the object, since points have no size.
+    ///
+    /// Objects without any width or height that aren't points are still rectangles.
     Point(f32, f32),
+    /// A text box. The object's position is the top-left corner of the box.
     Text {
+        /// The text to show, which may span several lines.
+        text: String,
+        /// The width of the box the text is laid out in.
+        width: f32,
+        /// The height of the box the text is laid out in.
+        height: f32,
+        /// The name of the font to use. Defaults to `sans-serif`.
         font_family: String,
+        /// The size of the font, in pixels. Defaults to 16.
         pixel_size: usize,
+        /// Whether lines longer than the width of the box are wrapped.
         wrap: bool,
+        /// The color of the text. Defaults to opaque black.
         color: Color,
         bold: bool,
         italic: bool,
         underline: bool,
         strikeout: bool,
+        /// Whether kerning is used when laying out the text. Defaults to `true`.
         kerning: bool,
         halign: HorizontalAlignment,
         valign: VerticalAlignment,
     },
 }
 
+impl ObjectShape {
+    /// The width and height of the shape, for the shapes that have a size.
+    fn size(&self) -> Option<(f32, f32)> {
+        match self {
+            ObjectShape::Rect { width, height }
+            | ObjectShape::Ellipse { width, height }
+            | ObjectShape::Text { width, height, .. } => Some((*width, *height)),
+            _ => None,
+        }
+    }
+
+    /// Returns whether both shapes are the same regardless of their size, and of the position
+    /// held by points.
+    fn same_kind(&self, other: &ObjectShape) -> bool {
+        let without_size = |shape: &ObjectShape| {
+            let mut shape = shape.clone();
+            match &mut shape {
+                ObjectShape::Rect { width, height }
+                | ObjectShape::Ellipse { width, height }
+                | ObjectShape::Text { width, height, .. } => {
+                    *width = 0.0;
+                    *height = 0.0;
+                }
+                ObjectShape::Point(x, y) => {
+                    *x = 0.0;
+                    *y = 0.0;
+                }
+                _ => {}
+            }
+            shape
+        };
+        without_size(self) == without_size(other)
+    }
+}
+
+/// An [`ObjectShape`] resolved into absolute coordinates, as returned by
+/// [`ObjectData::resolved_shape`].
+///
+/// All coordinates are in pixels and relative to the same origin as the object's position; For
+/// tile collision shapes, that is the top-left corner of the tile.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResolvedShape {
+    /// An axis-aligned rectangle.
+    Rect {
+        /// The X coordinate of the rectangle's left edge.
+        x: f32,
+        /// The Y coordinate of the rectangle's top edge.
+        y: f32,
+        /// The width of the rectangle.
+        width: f32,
+        /// The height of the rectangle.
+        height: f32,
+    },
+    /// An ellipse inscribed in a rectangle, which is rotated around its top-left corner.
+    Ellipse {
+        /// The X coordinate of the bounding rectangle's top-left corner.
+        x: f32,
+        /// The Y coordinate of the bounding rectangle's top-left corner.
+        y: f32,
+        /// The width of the ellipse.
+        width: f32,
+        /// The height of the ellipse.
+        height: f32,
+        /// The clockwise rotation of the ellipse around `(x, y)`, in degrees.
+        rotation: f32,
+    },
+    /// A closed polygon. Rotated rectangles are resolved into polygons as well.
+    Polygon {
+        /// The vertices of the polygon.
+        points: Vec<(f32, f32)>,
+    },
+    /// An open polyline.
+    Polyline {
+        /// The vertices of the polyline.
+        points: Vec<(f32, f32)>,
+    },
+    /// A single point.
+    Point(f32, f32),
+}
+
+impl ResolvedShape {
+    /// Returns the smallest axis-aligned rectangle `(x, y, width, height)` containing the shape.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::ResolvedShape;
+    ///
+    /// let polyline = ResolvedShape::Polyline {
+    ///     points: vec![(4.0, 2.0), (-2.0, 6.0), (1.0, 3.0)],
+    /// };
+    /// assert_eq!(polyline.bounding_rect(), (-2.0, 2.0, 6.0, 4.0));
+    /// ```
+    pub fn bounding_rect(&self) -> (f32, f32, f32, f32) {
+        let points = match self {
+            ResolvedShape::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => return (*x, *y, *width, *height),
+            ResolvedShape::Ellipse {
+                x,
+                y,
+                width,
+                height,
+                rotation,
+            } => {
+                let (sin, cos) = rotation.to_radians().sin_cos();
+                let (rx, ry) = (width / 2.0, height / 2.0);
+                let center_x = x + rx * cos - ry * sin;
+                let center_y = y + rx * sin + ry * cos;
+                let extent_x = (rx * rx * cos * cos + ry * ry * sin * sin).sqrt();
+                let extent_y = (rx * rx * sin * sin + ry * ry * cos * cos).sqrt();
+                return (
+                    center_x - extent_x,
+                    center_y - extent_y,
+                    extent_x * 2.0,
+                    extent_y * 2.0,
+                );
+            }
+            ResolvedShape::Polygon { points } | ResolvedShape::Polyline { points } => {
+                points.as_slice()
+            }
+            ResolvedShape::Point(x, y) => return (*x, *y, 0.0, 0.0),
+        };
+        if points.is_empty() {
+            return (0.0, 0.0, 0.0, 0.0);
+        }
+        let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+        let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for &(x, y) in points {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        (min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+
+    /// Returns whether the specified point is inside the shape, which is useful to check whether
+    /// something entered a trigger area.
+    ///
+    /// Points on the edges of rectangles and ellipses are inside them, while points on the edges
+    /// of polygons may or may not be. Polylines and points have no area, so they contain nothing.
+    /// Polygons whose edges cross each other follow the even-odd rule.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::ResolvedShape;
+    ///
+    /// // A 20x10 ellipse rotated to stand upright.
+    /// let ellipse = ResolvedShape::Ellipse {
+    ///     x: 10.0,
+    ///     y: 0.0,
+    ///     width: 20.0,
+    ///     height: 10.0,
+    ///     rotation: 90.0,
+    /// };
+    /// assert!(ellipse.contains_point(5.0, 18.0));
+    /// assert!(!ellipse.contains_point(18.0, 5.0));
+    /// ```
+    pub fn contains_point(&self, px: f32, py: f32) -> bool {
+        match self {
+            ResolvedShape::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => (*x..=x + width).contains(&px) && (*y..=y + height).contains(&py),
+            ResolvedShape::Ellipse {
+                x,
+                y,
+                width,
+                height,
+                rotation,
+            } => {
+                let (rx, ry) = (width / 2.0, height / 2.0);
+                if rx <= 0.0 || ry <= 0.0 {
+                    return false;
+                }
+                // Undo the rotation around the top-left corner to test against an upright ellipse.
+                let (sin, cos) = rotation.to_radians().sin_cos();
+                let (dx, dy) = (px - x, py - y);
+                let local_x = (dx * cos + dy * sin - rx) / rx;
+                let local_y = (dy * cos - dx * sin - ry) / ry;
+                local_x * local_x + local_y * local_y <= 1.0
+            }
+            ResolvedShape::Polygon { points } => {
+                // Count the edges crossed by a ray going right from the point.
+                let mut inside = false;
+                for (i, &(x1, y1)) in points.iter().enumerate() {
+                    let (x2, y2) = points[(i + 1) % points.len()];
+                    if (y1 > py) != (y2 > py) && px < x1 + (py - y1) / (y2 - y1) * (x2 - x1) {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+            ResolvedShape::Polyline { .. } | ResolvedShape::Point(..) => false,
+        }
+    }
+
+    /// Returns whether the shape encloses an area: Polylines are open, and so are points, while
+    /// all the other shapes are closed.
+    pub fn is_closed(&self) -> bool {
+        !matches!(
+            self,
+            ResolvedShape::Polyline { .. } | ResolvedShape::Point(..)
+        )
+    }
+
+    /// Returns the order the vertices of a polygon go around it in, or [`None`] if the shape isn't
+    /// a polygon or if it has no area.
+    ///
+    /// Rotated rectangles resolved into polygons go clockwise.
+    pub fn winding_order(&self) -> Option<WindingOrder> {
+        let area = match self {
+            ResolvedShape::Polygon { points } => signed_area(points),
+            _ => return None,
+        };
+        if area > 0.0 {
+            Some(WindingOrder::Clockwise)
+        } else if area < 0.0 {
+            Some(WindingOrder::CounterClockwise)
+        } else {
+            None
+        }
+    }
+
+    /// Splits a rectangle or a polygon into triangles using ear clipping, returning [`None`] for
+    /// other shapes. The vertices of each triangle are in the winding order of the polygon.
+    ///
+    /// Polygons whose edges cross each other are only partially triangulated.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::ResolvedShape;
+    ///
+    /// // An L-shaped polygon, which is concave.
+    /// let polygon = ResolvedShape::Polygon {
+    ///     points: vec![(0., 0.), (2., 0.), (2., 1.), (1., 1.), (1., 2.), (0., 2.)],
+    /// };
+    /// let triangles = polygon.triangulate().unwrap();
+    /// assert_eq!(triangles.len(), 4);
+    /// ```
+    #[cfg(feature = "triangulate")]
+    pub fn triangulate(&self) -> Option<Vec<[(f32, f32); 3]>> {
+        match self {
+            ResolvedShape::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let (right, bottom) = (x + width, y + height);
+                Some(vec![
+                    [(*x, *y), (right, *y), (right, bottom)],
+                    [(*x, *y), (right, bottom), (*x, bottom)],
+                ])
+            }
+            ResolvedShape::Polygon { points } => Some(triangulate_polygon(points)),
+            _ => None,
+        }
+    }
+}
+
+/// The order in which the vertices of a polygon go around it, as seen on screen, where the Y axis
+/// points down.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WindingOrder {
+    /// The vertices go around the polygon clockwise.
+    Clockwise,
+    /// The vertices go around the polygon counter-clockwise.
+    CounterClockwise,
+}
+
+/// Returns twice the area of a polygon, which is positive if its vertices go clockwise on screen.
+fn signed_area(points: &[(f32, f32)]) -> f32 {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|((x1, y1), (x2, y2))| x1 * y2 - x2 * y1)
+        .sum()
+}
+
+/// Returns whether `c` is to the right of the line going from `a` to `b`, as seen on screen,
+/// through a positive result. Zero means the three points are aligned.
+#[cfg(feature = "triangulate")]
+fn cross((ax, ay): (f32, f32), (bx, by): (f32, f32), (cx, cy): (f32, f32)) -> f32 {
+    (bx - ax) * (cy - ay) - (by - ay) * (cx - ax)
+}
+
+#[cfg(feature = "triangulate")]
+fn triangulate_polygon(points: &[(f32, f32)]) -> Vec<[(f32, f32); 3]> {
+    let sign = signed_area(points).signum();
+    let mut triangles = Vec::new();
+    if sign == 0.0 {
+        return triangles;
+    }
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let vertex = |i: usize| {
+            let corner = [(i + len - 1) % len, i, (i + 1) % len];
+            corner.map(|i| points[remaining[i]])
+        };
+        // An ear is a convex vertex whose triangle contains none of the other vertices.
+        let ear = (0..len).find(|&i| {
+            let [a, b, c] = vertex(i);
+            cross(a, b, c) * sign > 0.0
+                && remaining.iter().all(|&j| {
+                    let p = points[j];
+                    [a, b, c].contains(&p)
+                        || cross(a, b, p) * sign < 0.0
+                        || cross(b, c, p) * sign < 0.0
+                        || cross(c, a, p) * sign < 0.0
+                })
+        });
+        match ear {
+            Some(i) => {
+                triangles.push(vertex(i));
+                remaining.remove(i);
+            }
+            // Vertices aligned with their neighbors can be dropped without losing any area.
+            None => match (0..len).find(|&i| {
+                let [a, b, c] = vertex(i);
+                cross(a, b, c) == 0.0
+            }) {
+                Some(i) => {
+                    remaining.remove(i);
+                }
+                None => return triangles,
+            },
+        }
+    }
+    if remaining.len() == 3 {
+        let [a, b, c] = [0, 1, 2].map(|i| points[remaining[i]]);
+        if cross(a, b, c) != 0.0 {
+            triangles.push([a, b, c]);
+        }
+    }
+    triangles
+}
+
 /// The horizontal alignment of an [`ObjectShape::Text`].
 #[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub enum HorizontalAlignment {
     #[default]
@@ -161,8 +565,30 @@ pub enum HorizontalAlignment {
     Justify,
 }
 
+impl HorizontalAlignment {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "left" => Some(HorizontalAlignment::Left),
+            "center" => Some(HorizontalAlignment::Center),
+            "right" => Some(HorizontalAlignment::Right),
+            "justify" => Some(HorizontalAlignment::Justify),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            HorizontalAlignment::Left => "left",
+            HorizontalAlignment::Center => "center",
+            HorizontalAlignment::Right => "right",
+            HorizontalAlignment::Justify => "justify",
+        }
+    }
+}
+
 /// The vertical alignment of an [`ObjectShape::Text`].
 #[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub enum VerticalAlignment {
     #[default]
@@ -171,17 +597,38 @@ pub enum VerticalAlignment {
     Bottom,
 }
 
+impl VerticalAlignment {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "top" => Some(VerticalAlignment::Top),
+            "center" => Some(VerticalAlignment::Center),
+            "bottom" => Some(VerticalAlignment::Bottom),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            VerticalAlignment::Top => "top",
+            VerticalAlignment::Center => "center",
+            VerticalAlignment::Bottom => "bottom",
+        }
+    }
+}
+
 /// Raw data belonging to an object. Used internally and for tile collisions.
 ///
 /// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tmx-object).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ObjectData {
     id: u32,
     tile: Option<ObjectTileData>,
     /// The name of the object, which is arbitrary and set by the user.
     pub name: String,
-    /// The type of the object, which is arbitrary and set by the user.
-    pub user_type: String,
+    /// The class of the object, which is arbitrary and set by the user. Read from the `type`
+    /// attribute of the object, or `class` in files saved by Tiled 1.9.
+    pub user_type: Option<String>,
     /// The X coordinate of this object in pixels.
     pub x: f32,
     /// The Y coordinate of this object in pixels.
@@ -194,6 +641,20 @@ pub struct ObjectData {
     pub shape: ObjectShape,
     /// The object's custom properties as set by the user.
     pub properties: Properties,
+    /// The elements and attributes of the object's `<object>` element that aren't part of the
+    /// format, if the loader [preserves them](crate::Loader::set_preserve_extensions()).
+    pub extensions: XmlExtensions,
+    /// The template the object is an instance of, if any, so that only the values it overrides
+    /// are written.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    template: SourceInfo<TemplateInstance>,
+}
+
+/// The template an object is an instance of, along with the path of the template's file.
+#[derive(Debug, Clone)]
+struct TemplateInstance {
+    path: PathBuf,
+    template: Arc<Template>,
 }
 
 impl ObjectData {
@@ -210,6 +671,83 @@ impl ObjectData {
     pub fn tile_data(&self) -> Option<ObjectTileData> {
         self.tile.clone()
     }
+
+    /// Gives the object a new id.
+    pub(crate) fn set_id(&mut self, id: u32) {
+        self.id = id;
+    }
+
+    /// Moves the tile of the object according to `mapping`, unless it comes from a template's
+    /// tileset.
+    pub(crate) fn remap_tile(&mut self, mapping: &GidMapping) {
+        if let Some(tile) = &mut self.tile {
+            if let TilesetLocation::Map(index) = tile.tileset_location {
+                let (index, id) = mapping.get(index, tile.id);
+                tile.tileset_location = TilesetLocation::Map(index);
+                tile.id = id;
+            }
+        }
+    }
+
+    /// Returns the object's shape with the object's position and rotation applied to it.
+    ///
+    /// Returns [`None`] for text objects, which have no geometric shape.
+    pub fn resolved_shape(&self) -> Option<ResolvedShape> {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let transform =
+            |(px, py): (f32, f32)| (self.x + px * cos - py * sin, self.y + px * sin + py * cos);
+        Some(match &self.shape {
+            ObjectShape::Rect { width, height } if self.rotation == 0.0 => ResolvedShape::Rect {
+                x: self.x,
+                y: self.y,
+                width: *width,
+                height: *height,
+            },
+            ObjectShape::Rect { width, height } => ResolvedShape::Polygon {
+                points: [(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)]
+                    .iter()
+                    .copied()
+                    .map(transform)
+                    .collect(),
+            },
+            ObjectShape::Ellipse { width, height } => ResolvedShape::Ellipse {
+                x: self.x,
+                y: self.y,
+                width: *width,
+                height: *height,
+                rotation: self.rotation,
+            },
+            ObjectShape::Polygon { points } => ResolvedShape::Polygon {
+                points: points.iter().copied().map(transform).collect(),
+            },
+            ObjectShape::Polyline { points } => ResolvedShape::Polyline {
+                points: points.iter().copied().map(transform).collect(),
+            },
+            ObjectShape::Point(_, _) => ResolvedShape::Point(self.x, self.y),
+            ObjectShape::Text { .. } => return None,
+        })
+    }
+
+    /// Creates a new visible, unrotated object with the specified id, position and shape, and no
+    /// name, type, tile or properties.
+    ///
+    /// Tiled expects object ids to be unique within a map; An id of 0 means the object has none.
+    pub fn new_shape(id: u32, x: f32, y: f32, shape: ObjectShape) -> Self {
+        Self {
+            id,
+            tile: None,
+            name: String::new(),
+            user_type: None,
+            x,
+            y,
+            rotation: 0.0,
+            visible: true,
+            shape,
+            properties: Properties::new(),
+            extensions: XmlExtensions::default(),
+            template: SourceInfo::default(),
+        }
+    }
 }
 
 impl ObjectData {
@@ -222,10 +760,9 @@ impl ObjectData {
         for_tileset: Option<Arc<Tileset>>,
         // Base path is a directory to which all other files are relative to
         base_path: &Path,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<ObjectData> {
-        let (id, tile, mut n, mut t, c, w, h, mut v, mut r, template, x, y) = get_attrs!(
+        let (id, tile, mut n, t, c, w, h, mut v, mut r, template, x, y) = get_attrs!(
             for v in attrs {
                 Some("id") => id ?= v.parse(),
                 Some("gid") => tile ?= v.parse::<u32>(),
@@ -242,6 +779,7 @@ impl ObjectData {
             }
             (id, tile, name, user_type, user_class, width, height, visible, rotation, template, x, y)
         );
+        let mut t = t.or(c);
         let x = x.unwrap_or(0.);
         let y = y.unwrap_or(0.);
         let mut tile = tile.and_then(|bits| {
@@ -250,28 +788,24 @@ impl ObjectData {
         // If the template attribute is there, we need to go fetch the template file
         let template = template
             .map(|template_path: String| {
-                let template_path = base_path.join(Path::new(&template_path));
-
-                // Check the cache to see if this template exists
-                let template = if let Some(templ) = cache.get_template(&template_path) {
-                    templ
-                } else {
-                    let template = Template::parse_template(&template_path, reader, cache)?;
-                    // Insert it into the cache
-                    cache.insert_template(&template_path, template.clone());
-                    template
-                };
+                let template_path = join_reference(base_path, &template_path);
+                let template = crate::parse::load_template(&template_path, ctx)?;
 
                 // The template sets the default values for the object
                 let obj = &template.object;
                 v.get_or_insert(obj.visible);
                 r.get_or_insert(obj.rotation);
                 n.get_or_insert_with(|| obj.name.clone());
-                t.get_or_insert_with(|| obj.user_type.clone());
+                if t.is_none() {
+                    t = obj.user_type.clone();
+                }
                 if let Some(templ_tile) = &obj.tile {
                     tile.get_or_insert_with(|| templ_tile.clone());
                 }
-                Ok(template)
+                Ok(TemplateInstance {
+                    path: template_path,
+                    template,
+                })
             })
             .transpose()?;
 
@@ -281,7 +815,6 @@ impl ObjectData {
         let rotation = r.unwrap_or(0f32);
         let id = id.unwrap_or(0u32);
         let name = n.unwrap_or_default();
-        let user_type: String = t.or(c).unwrap_or_default();
         let mut shape = None;
         let mut properties = HashMap::new();
 
@@ -306,18 +839,20 @@ impl ObjectData {
                 Ok(())
             },
             "text" => |attrs| {
-                shape = Some(ObjectData::new_text(attrs)?);
+                shape = Some(ObjectData::new_text(attrs, width, height, parser)?);
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, ctx.project, &ctx.interner, ctx.diagnostics)?;
                 Ok(())
             },
         });
+        let extensions = ctx.diagnostics.take_extensions();
 
         // Possibly copy properties from the template into the object
         // Any that already exist in the object's map don't get copied over
-        if let Some(templ) = template {
+        if let Some(instance) = &template {
+            let templ = &instance.template;
             shape.get_or_insert(templ.object.shape.clone());
 
             for (k, v) in &templ.object.properties {
@@ -333,13 +868,170 @@ impl ObjectData {
             id,
             tile,
             name,
-            user_type,
+            user_type: t,
             x,
             y,
             rotation,
             visible,
             shape,
             properties,
+            extensions,
+            template: template.map(SourceInfo::new).unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(feature = "json")]
+impl ObjectData {
+    /// If it is known that the object has no tile images in it (i.e. collision data)
+    /// then we can pass in [`None`] as the tilesets
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        tilesets: Option<&[MapTilesetGid]>,
+        for_tileset: Option<Arc<Tileset>>,
+        // Base path is a directory to which all other files are relative to
+        base_path: &Path,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<ObjectData> {
+        use crate::parse::json::{as_objects, JsonObjectExt};
+
+        let mut n = object.opt_str("name")?.map(str::to_owned);
+        let mut t = match object.opt_str("type")? {
+            Some(user_type) => Some(user_type.to_owned()),
+            None => object.opt_str("class")?.map(str::to_owned),
+        };
+        let mut v = object.opt_bool("visible")?;
+        let mut r = object.opt_f32("rotation")?;
+        let width = object.opt_f32("width")?.unwrap_or(0.);
+        let height = object.opt_f32("height")?.unwrap_or(0.);
+        let x = object.opt_f32("x")?.unwrap_or(0.);
+        let y = object.opt_f32("y")?.unwrap_or(0.);
+        let mut tile = object.opt_u32("gid")?.and_then(|bits| {
+            ObjectTileData::from_bits(bits, tilesets?, for_tileset.as_ref().cloned())
+        });
+        let mut properties = crate::properties::parse_json_properties(
+            object,
+            ctx.project,
+            &ctx.interner,
+            ctx.diagnostics,
+        )?;
+
+        let parse_points = |key: &str| -> Result<Option<Vec<(f32, f32)>>> {
+            object
+                .opt_array(key)?
+                .map(|points| {
+                    as_objects(points, key)?
+                        .map(|point| {
+                            Ok((
+                                point.req("x", |v| v.as_f64().map(|v| v as f32))?,
+                                point.req("y", |v| v.as_f64().map(|v| v as f32))?,
+                            ))
+                        })
+                        .collect()
+                })
+                .transpose()
+        };
+        let mut shape = if object.opt_bool("ellipse")? == Some(true) {
+            Some(ObjectShape::Ellipse { width, height })
+        } else if object.opt_bool("point")? == Some(true) {
+            Some(ObjectShape::Point(x, y))
+        } else if let Some(points) = parse_points("polygon")? {
+            Some(ObjectShape::Polygon { points })
+        } else if let Some(points) = parse_points("polyline")? {
+            Some(ObjectShape::Polyline { points })
+        } else if let Some(text) = object.opt_object("text")? {
+            Some(ObjectData::parse_json_text(text, width, height)?)
+        } else {
+            None
+        };
+
+        // If the template member is there, we need to go fetch the template file
+        let mut instance = None;
+        if let Some(template_path) = object.opt_str("template")? {
+            let template_path = join_reference(base_path, template_path);
+            let template = crate::parse::load_template(&template_path, ctx)?;
+
+            // The template sets the default values for the object
+            let obj = &template.object;
+            v.get_or_insert(obj.visible);
+            r.get_or_insert(obj.rotation);
+            n.get_or_insert_with(|| obj.name.clone());
+            if t.is_none() {
+                t = obj.user_type.clone();
+            }
+            if let Some(templ_tile) = &obj.tile {
+                tile.get_or_insert_with(|| templ_tile.clone());
+            }
+            shape.get_or_insert(obj.shape.clone());
+            // Any properties that already exist in the object don't get copied over
+            for (k, v) in &obj.properties {
+                if !properties.contains_key(k) {
+                    properties.insert(k.clone(), v.clone());
+                }
+            }
+            instance = Some(TemplateInstance {
+                path: template_path,
+                template,
+            });
+        }
+
+        Ok(ObjectData {
+            id: object.opt_u32("id")?.unwrap_or(0),
+            tile,
+            name: n.unwrap_or_default(),
+            user_type: t,
+            x,
+            y,
+            rotation: r.unwrap_or(0.),
+            visible: v.unwrap_or(true),
+            shape: shape.unwrap_or(ObjectShape::Rect { width, height }),
+            properties,
+            extensions: XmlExtensions::default(),
+            template: instance.map(SourceInfo::new).unwrap_or_default(),
+        })
+    }
+
+    fn parse_json_text(
+        text: &crate::parse::json::JsonObject,
+        width: f32,
+        height: f32,
+    ) -> Result<ObjectShape> {
+        use crate::parse::json::JsonObjectExt;
+
+        let flag = |key: &str| text.opt_bool(key).map(|v| v.unwrap_or(false));
+        Ok(ObjectShape::Text {
+            text: text.opt_str("text")?.unwrap_or_default().to_owned(),
+            width,
+            height,
+            font_family: text
+                .opt_str("fontfamily")?
+                .unwrap_or("sans-serif")
+                .to_owned(),
+            pixel_size: text
+                .opt("pixelsize", |v| v.as_u64().map(|v| v as usize))?
+                .unwrap_or(16),
+            wrap: flag("wrap")?,
+            color: text.opt_color("color")?.unwrap_or(Color {
+                red: 0,
+                green: 0,
+                blue: 0,
+                alpha: 255,
+            }),
+            bold: flag("bold")?,
+            italic: flag("italic")?,
+            underline: flag("underline")?,
+            strikeout: flag("strikeout")?,
+            kerning: text.opt_bool("kerning")?.unwrap_or(true),
+            halign: text
+                .opt("halign", |v| {
+                    v.as_str().and_then(HorizontalAlignment::from_name)
+                })?
+                .unwrap_or_default(),
+            valign: text
+                .opt("valign", |v| {
+                    v.as_str().and_then(VerticalAlignment::from_name)
+                })?
+                .unwrap_or_default(),
         })
     }
 }
@@ -365,7 +1057,12 @@ impl ObjectData {
         Ok(ObjectShape::Polygon { points })
     }
 
-    fn new_text(attrs: Vec<OwnedAttribute>) -> Result<ObjectShape> {
+    fn new_text(
+        attrs: Vec<OwnedAttribute>,
+        width: f32,
+        height: f32,
+        parser: &mut impl Iterator<Item = XmlEventResult>,
+    ) -> Result<ObjectShape> {
         let (
             font_family,
             pixel_size,
@@ -389,19 +1086,14 @@ impl ObjectData {
                 Some("underline") => underline ?= v.parse(),
                 Some("strikeout") => strikeout ?= v.parse(),
                 Some("kerning") => kerning ?= v.parse(),
-                Some("halign") => halign = match v.as_str() {
-                    "left" => HorizontalAlignment::Left,
-                    "center" => HorizontalAlignment::Center,
-                    "right" => HorizontalAlignment::Right,
-                    "justify" => HorizontalAlignment::Justify,
-                    _ => return Err(Error::MalformedAttributes("`halign` property did not contain a valid value of 'left', 'center', 'right' or 'justify'".to_string()))
+                Some("halign") => halign = match HorizontalAlignment::from_name(&v) {
+                    Some(halign) => halign,
+                    None => return Err(Error::MalformedAttributes("`halign` property did not contain a valid value of 'left', 'center', 'right' or 'justify'".to_string()))
                 },
-                Some("valign") => valign = match v.as_str() {
-                    "top" => VerticalAlignment::Top,
-                    "center" => VerticalAlignment::Center,
-                    "bottom" => VerticalAlignment::Bottom,
-                    _ => return Err(Error::MalformedAttributes(
-                        "`halign` property did not contain a valid value of 'top', 'center' or 'bottom'"
+                Some("valign") => valign = match VerticalAlignment::from_name(&v) {
+                    Some(valign) => valign,
+                    None => return Err(Error::MalformedAttributes(
+                        "`valign` property did not contain a valid value of 'top', 'center' or 'bottom'"
                             .to_string(),
                     )),
                 },
@@ -433,11 +1125,28 @@ impl ObjectData {
         let italic = italic == Some(1);
         let underline = underline == Some(1);
         let strikeout = strikeout == Some(1);
-        let kerning = kerning == Some(1);
+        let kerning = kerning != Some(0);
         let halign = halign.unwrap_or_default();
         let valign = valign.unwrap_or_default();
 
+        let mut text = String::new();
+        for next in parser {
+            match next.map_err(Error::XmlDecodingError)? {
+                XmlEvent::Characters(s) | XmlEvent::Whitespace(s) | XmlEvent::CData(s) => {
+                    text.push_str(&s)
+                }
+                XmlEvent::EndElement { .. } => break,
+                _ => {}
+            }
+        }
+        // XML parsers are meant to normalize line endings, which keeps the text the same when the
+        // file was checked out with Windows line endings.
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+
         Ok(ObjectShape::Text {
+            text,
+            width,
+            height,
             font_family,
             pixel_size,
             wrap,
@@ -476,6 +1185,164 @@ impl ObjectData {
     }
 }
 
+impl ObjectData {
+    /// Writes this object as an `<object>` element. `first_gids` are the first GIDs of the tilesets
+    /// in the map this object is contained in, used to write the GID of tile objects, and the path
+    /// of the template the object is an instance of is written relative to `path_relative_to`.
+    ///
+    /// Instances of templates only have the values they override written, like Tiled does.
+    pub(crate) fn write_xml(
+        &self,
+        writer: &mut EventWriter<impl Write>,
+        first_gids: &[Gid],
+        path_relative_to: &Path,
+    ) -> Result<()> {
+        let instance = self.template.get();
+        let base = instance.map(|instance| &instance.template.object);
+        // Whether a value should be written, given whether it differs from the default and
+        // whether it is the same as the template's.
+        let overrides =
+            |differs_from_default: bool, same_as_template: fn(&Self, &Self) -> bool| match base {
+                Some(base) => !same_as_template(self, base),
+                None => differs_from_default,
+            };
+
+        let mut attrs = Vec::new();
+        if self.id != 0 {
+            attrs.push(("id", self.id.to_string()));
+        }
+        if let Some(instance) = instance {
+            attrs.push(("template", relative_path(path_relative_to, &instance.path)));
+        }
+        if overrides(!self.name.is_empty(), |a, b| a.name == b.name) {
+            attrs.push(("name", self.name.clone()));
+        }
+        if let Some(user_type) = &self.user_type {
+            if overrides(true, |a, b| a.user_type == b.user_type) {
+                attrs.push(("type", user_type.clone()));
+            }
+        }
+        if let Some(tile) = &self.tile {
+            if overrides(true, |a, b| a.tile == b.tile) {
+                attrs.push(("gid", tile.to_bits(first_gids).to_string()));
+            }
+        }
+        attrs.push(("x", self.x.to_string()));
+        attrs.push(("y", self.y.to_string()));
+        if let Some((width, height)) = self.shape.size() {
+            if overrides(true, |a, b| a.shape.size() == b.shape.size()) {
+                attrs.push(("width", width.to_string()));
+                attrs.push(("height", height.to_string()));
+            }
+        }
+        if overrides(self.rotation != 0.0, |a, b| a.rotation == b.rotation) {
+            attrs.push(("rotation", self.rotation.to_string()));
+        }
+        if overrides(!self.visible, |a, b| a.visible == b.visible) {
+            attrs.push(("visible", if self.visible { "1" } else { "0" }.to_owned()));
+        }
+        self.extensions
+            .write_start_element(writer, "object", &attrs)?;
+        match base {
+            Some(base) => {
+                let overridden = self
+                    .properties
+                    .iter()
+                    .filter(|(name, value)| base.properties.get(*name) != Some(*value))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect();
+                write_properties(writer, &overridden)?;
+            }
+            None => write_properties(writer, &self.properties)?,
+        }
+        if base.is_some_and(|base| self.shape.same_kind(&base.shape)) {
+            self.extensions.write_elements(writer)?;
+            return write_end_element(writer);
+        }
+
+        let write_points = |points: &[(f32, f32)]| {
+            points
+                .iter()
+                .map(|(x, y)| format!("{},{}", x, y))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        match &self.shape {
+            ObjectShape::Rect { .. } => {}
+            ObjectShape::Ellipse { .. } => write_empty_element(writer, "ellipse", &[])?,
+            ObjectShape::Point(_, _) => write_empty_element(writer, "point", &[])?,
+            ObjectShape::Polyline { points } => {
+                write_empty_element(writer, "polyline", &[("points", write_points(points))])?
+            }
+            ObjectShape::Polygon { points } => {
+                write_empty_element(writer, "polygon", &[("points", write_points(points))])?
+            }
+            ObjectShape::Text {
+                text,
+                width: _,
+                height: _,
+                font_family,
+                pixel_size,
+                wrap,
+                color,
+                bold,
+                italic,
+                underline,
+                strikeout,
+                kerning,
+                halign,
+                valign,
+            } => {
+                let mut attrs = Vec::new();
+                if font_family != "sans-serif" {
+                    attrs.push(("fontfamily", font_family.clone()));
+                }
+                if *pixel_size != 16 {
+                    attrs.push(("pixelsize", pixel_size.to_string()));
+                }
+                if *wrap {
+                    attrs.push(("wrap", "1".to_owned()));
+                }
+                if *color
+                    != (Color {
+                        red: 0,
+                        green: 0,
+                        blue: 0,
+                        alpha: 255,
+                    })
+                {
+                    attrs.push(("color", color.to_short_string()));
+                }
+                let flags = [
+                    ("bold", *bold),
+                    ("italic", *italic),
+                    ("underline", *underline),
+                    ("strikeout", *strikeout),
+                ];
+                for (name, flag) in flags {
+                    if flag {
+                        attrs.push((name, "1".to_owned()));
+                    }
+                }
+                if !kerning {
+                    attrs.push(("kerning", "0".to_owned()));
+                }
+                if *halign != HorizontalAlignment::default() {
+                    attrs.push(("halign", halign.as_str().to_owned()));
+                }
+                if *valign != VerticalAlignment::default() {
+                    attrs.push(("valign", valign.as_str().to_owned()));
+                }
+                write_start_element(writer, "text", &attrs)?;
+                write_characters(writer, text)?;
+                write_end_element(writer)?
+            }
+        }
+        self.extensions.write_elements(writer)?;
+        write_end_element(writer)
+    }
+}
+
 map_wrapper!(
     #[doc = "Wrapper over an [`ObjectData`] that contains both a reference to the data as well as
     to the map it is contained in."]
@@ -484,10 +1351,132 @@ map_wrapper!(
 
 impl<'map> Object<'map> {
     /// Returns the tile that the object is using as image, if any.
+    ///
+    /// The GID of the object is already resolved: The returned [`ObjectTile`] gives access to the
+    /// [`Tile`] and its [`Tileset`], and its flip flags are decoded.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_tile_objects.tmx")?;
+    /// let object = map.get_object_by_id(2).unwrap();
+    ///
+    /// let tile = object.get_tile().unwrap();
+    /// assert_eq!(tile.id(), 4);
+    /// assert_eq!(tile.get_tileset().name, "tilesheet");
+    /// assert!(tile.flip_h && !tile.flip_v);
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn get_tile(&self) -> Option<ObjectTile<'map>> {
         self.data
             .tile
             .as_ref()
             .map(|tile| ObjectTile::new(self.map, tile))
     }
+
+    /// Returns the transform placing the object within its layer, ignoring the layer's offsets.
+    ///
+    /// Tile objects are placed according to the [object alignment](Tileset::object_alignment) of
+    /// their tileset, resolved for the orientation of the map.
+    pub fn transform(&self) -> ObjectTransform {
+        let (origin_x, origin_y) = match (self.get_tile(), &self.shape) {
+            (Some(tile), ObjectShape::Rect { width, height }) => {
+                let alignment = tile
+                    .get_tileset()
+                    .object_alignment
+                    .resolve(self.map.orientation);
+                let (anchor_x, anchor_y) = alignment.anchor();
+                (anchor_x * width, anchor_y * height)
+            }
+            _ => (0.0, 0.0),
+        };
+        ObjectTransform {
+            x: self.x,
+            y: self.y,
+            rotation: self.rotation,
+            origin_x,
+            origin_y,
+        }
+    }
+
+    /// Returns the transform placing the object in the map, which also applies the offsets of the
+    /// layer containing it and of the group layers containing that one.
+    ///
+    /// Parallax scrolling isn't applied, since it depends on the position of the camera.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_object_index.tmx")?;
+    ///
+    /// for layer in map.flattened_layers() {
+    ///     for object in layer.layer.as_object_layer().into_iter().flat_map(|l| l.objects()) {
+    ///         if let Some(corners) = object.world_corners(&layer) {
+    ///             println!("{} has corners {:?}", object.name, corners);
+    ///         }
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn world_transform(&self, layer: &FlattenedLayer) -> ObjectTransform {
+        let transform = self.transform();
+        ObjectTransform {
+            x: transform.x + layer.offset_x,
+            y: transform.y + layer.offset_y,
+            ..transform
+        }
+    }
+
+    /// Returns the corners of the object's box in the map, for objects that have a width and a
+    /// height: Rectangles, ellipses, text and tile objects. See [`Object::world_transform()`].
+    pub fn world_corners(&self, layer: &FlattenedLayer) -> Option<[(f32, f32); 4]> {
+        match self.shape {
+            ObjectShape::Rect { width, height }
+            | ObjectShape::Ellipse { width, height }
+            | ObjectShape::Text { width, height, .. } => {
+                Some(self.world_transform(layer).corners(width, height))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The position, rotation and origin of an object, as returned by [`Object::transform()`] and
+/// [`Object::world_transform()`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectTransform {
+    /// The X coordinate of the object's origin, in pixels.
+    pub x: f32,
+    /// The Y coordinate of the object's origin, in pixels.
+    pub y: f32,
+    /// The clockwise rotation of the object around its origin, in degrees.
+    pub rotation: f32,
+    /// The horizontal position of the origin within the object's box, from its left edge. Only
+    /// tile objects have an origin other than their top-left corner.
+    pub origin_x: f32,
+    /// The vertical position of the origin within the object's box, from its top edge.
+    pub origin_y: f32,
+}
+
+impl ObjectTransform {
+    /// Transforms a point given relative to the top-left corner of the unrotated object's box.
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (x, y) = (x - self.origin_x, y - self.origin_y);
+        (self.x + x * cos - y * sin, self.y + x * sin + y * cos)
+    }
+
+    /// Returns the corners of a box of the given size once transformed, starting from its
+    /// top-left corner and going clockwise.
+    pub fn corners(&self, width: f32, height: f32) -> [(f32, f32); 4] {
+        [
+            self.apply(0.0, 0.0),
+            self.apply(width, 0.0),
+            self.apply(width, height),
+            self.apply(0.0, height),
+        ]
+    }
 }
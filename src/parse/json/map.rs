@@ -0,0 +1,15 @@
+use std::path::Path;
+
+use crate::{parse::LoadContext, Map, ResourceCache, ResourceReader, Result};
+
+use super::read_json_object;
+
+pub fn parse_map(
+    path: &Path,
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+) -> Result<Map> {
+    let diagnostics = ctx.diagnostics;
+    let _document = diagnostics.enter_document(path);
+    let object = read_json_object(path, ctx.reader)?;
+    Map::parse_json(&object, path, ctx)
+}
@@ -0,0 +1,140 @@
+use std::{convert::TryFrom, path::Path};
+
+use serde_json::Value;
+
+use crate::{Color, Error, ResourceReader, Result, Version};
+
+mod map;
+pub use map::*;
+mod project;
+pub use project::*;
+mod template;
+pub(crate) use template::*;
+mod tileset;
+pub use tileset::*;
+#[cfg(feature = "world")]
+mod world;
+#[cfg(feature = "world")]
+pub use world::*;
+
+pub(crate) type JsonObject = serde_json::Map<String, Value>;
+
+/// Whether the file at the path given should be parsed as JSON rather than XML, based on its
+/// extension.
+pub(crate) fn is_json_path(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("json" | "tmj" | "tsj" | "tj")
+    )
+}
+
+/// Reads the file at the path given and parses it as a JSON object.
+pub(crate) fn read_json_object(
+    path: &Path,
+    reader: &mut impl ResourceReader,
+) -> Result<JsonObject> {
+    let file = reader
+        .read_from(path)
+        .map_err(|err| Error::ResourceLoadingError {
+            path: path.to_owned(),
+            err: Box::new(err),
+        })?;
+    match serde_json::from_reader(file).map_err(Error::JsonDecodingError)? {
+        Value::Object(object) => Ok(object),
+        _ => Err(Error::PrematureEnd(
+            "JSON document does not contain an object".to_string(),
+        )),
+    }
+}
+
+/// Typed access to the members of a JSON object, reporting errors the same way XML attributes do.
+pub(crate) trait JsonObjectExt {
+    /// Obtains an optional member, converting it with `f`. Null members are treated as missing.
+    fn opt<'a, T>(&'a self, key: &str, f: impl FnOnce(&'a Value) -> Option<T>)
+        -> Result<Option<T>>;
+
+    /// Obtains a required member, converting it with `f`.
+    fn req<'a, T>(&'a self, key: &str, f: impl FnOnce(&'a Value) -> Option<T>) -> Result<T> {
+        self.opt(key, f)?
+            .ok_or_else(|| Error::MalformedAttributes(format!("Missing attribute: {}", key)))
+    }
+
+    fn opt_str(&self, key: &str) -> Result<Option<&str>> {
+        self.opt(key, Value::as_str)
+    }
+
+    fn opt_u32(&self, key: &str) -> Result<Option<u32>> {
+        self.opt(key, as_u32)
+    }
+
+    fn opt_i32(&self, key: &str) -> Result<Option<i32>> {
+        self.opt(key, as_i32)
+    }
+
+    fn opt_f32(&self, key: &str) -> Result<Option<f32>> {
+        self.opt(key, |v| v.as_f64().map(|v| v as f32))
+    }
+
+    fn opt_bool(&self, key: &str) -> Result<Option<bool>> {
+        self.opt(key, Value::as_bool)
+    }
+
+    fn opt_color(&self, key: &str) -> Result<Option<Color>> {
+        self.opt(key, |v| v.as_str().and_then(|s| s.parse().ok()))
+    }
+
+    fn opt_array(&self, key: &str) -> Result<Option<&Vec<Value>>> {
+        self.opt(key, Value::as_array)
+    }
+
+    fn opt_object(&self, key: &str) -> Result<Option<&JsonObject>> {
+        self.opt(key, Value::as_object)
+    }
+}
+
+impl JsonObjectExt for JsonObject {
+    fn opt<'a, T>(
+        &'a self,
+        key: &str,
+        f: impl FnOnce(&'a Value) -> Option<T>,
+    ) -> Result<Option<T>> {
+        match self.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => f(value).map(Some).ok_or_else(|| {
+                Error::MalformedAttributes(format!("Error parsing attribute '{}'", key))
+            }),
+        }
+    }
+}
+
+pub(crate) fn as_u32(value: &Value) -> Option<u32> {
+    value.as_u64().and_then(|v| u32::try_from(v).ok())
+}
+
+pub(crate) fn as_i32(value: &Value) -> Option<i32> {
+    value.as_i64().and_then(|v| i32::try_from(v).ok())
+}
+
+/// Interprets a version, which older versions of Tiled stored as a number.
+pub(crate) fn as_version(value: &Value) -> Option<Version> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Interprets a JSON array as a list of objects, failing if any element isn't one.
+pub(crate) fn as_objects<'a>(
+    values: &'a [Value],
+    what: &str,
+) -> Result<impl Iterator<Item = &'a JsonObject>> {
+    if values.iter().all(Value::is_object) {
+        Ok(values.iter().filter_map(Value::as_object))
+    } else {
+        Err(Error::MalformedAttributes(format!(
+            "Expected every element of '{}' to be an object",
+            what
+        )))
+    }
+}
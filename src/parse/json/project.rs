@@ -0,0 +1,10 @@
+use std::path::Path;
+
+use crate::{Project, ResourceReader, Result};
+
+use super::read_json_object;
+
+pub fn parse_project(path: &Path, reader: &mut impl ResourceReader) -> Result<Project> {
+    let object = read_json_object(path, reader)?;
+    Project::parse_json(&object, path)
+}
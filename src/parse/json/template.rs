@@ -0,0 +1,54 @@
+use std::{path::Path, sync::Arc};
+
+use crate::{
+    parse::LoadContext, EmbeddedParseResultType, Error, MapTilesetGid, ObjectData, ResourceCache,
+    ResourceReader, Result, Template, Tileset,
+};
+
+use super::{read_json_object, JsonObjectExt};
+
+pub(crate) fn parse_template(
+    path: &Path,
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+) -> Result<Arc<Template>> {
+    let diagnostics = ctx.diagnostics;
+    let _document = diagnostics.enter_document(path);
+    let object = read_json_object(path, ctx.reader)?;
+
+    let mut tileset = None;
+    let mut tileset_source = None;
+    let mut tileset_gid = Vec::new();
+    if let Some(json_tileset) = object.opt_object("tileset")? {
+        let res = Tileset::parse_json_in_map(json_tileset, path, ctx)?;
+        let ts = match res.result_type {
+            EmbeddedParseResultType::ExternalReference { tileset_path } => {
+                let tileset = crate::parse::load_external_tileset(&tileset_path, ctx)?;
+                tileset_source = Some(tileset_path);
+                tileset
+            }
+            EmbeddedParseResultType::Embedded { tileset } => Arc::new(tileset),
+        };
+        tileset_gid.push(MapTilesetGid {
+            tileset: ts.clone(),
+            first_gid: res.first_gid,
+        });
+        tileset = Some(ts);
+    }
+
+    let object = object
+        .opt_object("object")?
+        .ok_or(Error::TemplateHasNoObject)?;
+    let object = ObjectData::parse_json(
+        object,
+        Some(&tileset_gid),
+        tileset.clone(),
+        path.parent().ok_or(Error::PathIsNotFile)?,
+        ctx,
+    )?;
+
+    Ok(Arc::new(Template {
+        tileset,
+        tileset_source,
+        object,
+    }))
+}
@@ -0,0 +1,15 @@
+use std::path::Path;
+
+use crate::{parse::LoadContext, ResourceCache, ResourceReader, Result, Tileset};
+
+use super::read_json_object;
+
+pub fn parse_tileset(
+    path: &Path,
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+) -> Result<Tileset> {
+    let diagnostics = ctx.diagnostics;
+    let _document = diagnostics.enter_document(path);
+    let object = read_json_object(path, ctx.reader)?;
+    Tileset::parse_json_external(&object, path, ctx).map(|tileset| tileset.with_source(path))
+}
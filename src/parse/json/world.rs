@@ -0,0 +1,31 @@
+use std::path::Path;
+
+use crate::{Error, ResourceReader, Result, World};
+
+use super::read_json_object;
+
+pub fn parse_world(path: &Path, reader: &mut impl ResourceReader) -> Result<World> {
+    let object = read_json_object(path, reader)?;
+    let mut world = World::parse_json(&object, path)?;
+
+    if !world.patterns.is_empty() {
+        let directory = path.parent().ok_or(Error::PathIsNotFile)?;
+        let mut files =
+            reader
+                .list_files(directory)
+                .map_err(|err| Error::ResourceLoadingError {
+                    path: directory.to_owned(),
+                    err: Box::new(err),
+                })?;
+        // Keep the resulting map order stable regardless of how the reader lists files.
+        files.sort();
+        let patterns = &world.patterns;
+        let matched: Vec<_> = files
+            .iter()
+            .filter_map(|file| patterns.iter().find_map(|p| p.match_path(file)))
+            .collect();
+        world.maps.extend(matched);
+    }
+
+    Ok(world)
+}
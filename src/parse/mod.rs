@@ -1 +1,178 @@
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "parallel")]
+mod parallel;
 pub mod xml;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    decoder::DataDecoders, image::ImageHook, layers::TileDataOptions, limits::TileBudget,
+    properties::Interner, warning::Diagnostics, Error, Gid, LoadLimit, LoadLimits, Map,
+    MapTilesetGid, Project, ResourceCache, ResourceReader, Result, Template, Tileset, VirtualPath,
+};
+
+/// The state shared by all the functions parsing a file and the files it references.
+pub(crate) struct LoadContext<'a, R, C> {
+    pub reader: &'a mut R,
+    pub cache: &'a mut C,
+    /// The project custom property types are resolved with, if any.
+    pub project: Option<&'a Project>,
+    /// Whether the data of tile layers should be kept encoded until it is first needed.
+    pub defer_tile_data: bool,
+    /// Whether finite tile layers should keep their tiles as raw GIDs.
+    pub compact_tile_data: bool,
+    /// Whether the object properties of maps should be checked against their objects.
+    pub resolve_object_references: bool,
+    /// The decoders used to decompress the data of tile layers.
+    pub decoders: &'a DataDecoders,
+    /// The limits on the resources used to load the file.
+    pub limits: LoadLimits,
+    /// Counts the tiles of the map being loaded.
+    pub tiles: TileBudget,
+    /// How many group layers the layer being parsed is nested in.
+    pub group_depth: usize,
+    /// Shares the storage of the property names of everything loaded.
+    pub interner: Interner,
+    /// Locates errors and collects the warnings found while loading.
+    pub diagnostics: &'a Diagnostics,
+    /// Called on the images of the tilesets and maps loaded.
+    pub image_hook: Option<&'a ImageHook>,
+}
+
+impl<'a, R, C> LoadContext<'a, R, C> {
+    pub(crate) fn tile_data_options(&self) -> TileDataOptions<'_> {
+        TileDataOptions {
+            defer: self.defer_tile_data,
+            compact: self.compact_tile_data,
+            decoders: self.decoders,
+            tiles: &self.tiles,
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    /// Enters a group layer, checking how deep group layers are nested against the limit of the
+    /// loader. Must be followed by decrementing [`LoadContext::group_depth`] once the group ends.
+    pub(crate) fn enter_group(&mut self) -> Result<()> {
+        match self.limits.max_group_depth {
+            Some(max) if self.group_depth >= max => Err(Error::LimitExceeded {
+                limit: LoadLimit::GroupDepth,
+                max: max as u64,
+            }),
+            _ => {
+                self.group_depth += 1;
+                Ok(())
+            }
+        }
+    }
+
+    /// Parses the contents of a group layer with `parse`. See [`LoadContext::enter_group()`].
+    pub(crate) fn in_group<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.enter_group()?;
+        let result = parse(self);
+        self.group_depth -= 1;
+        result
+    }
+}
+
+/// Parses a map, choosing the format based on the file extension.
+pub(crate) fn parse_map(
+    path: &Path,
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+) -> Result<Map> {
+    #[cfg(feature = "json")]
+    if json::is_json_path(path) {
+        return json::parse_map(path, ctx);
+    }
+    xml::parse_map(path, ctx)
+}
+
+/// Parses an external tileset, choosing the format based on the file extension.
+pub(crate) fn parse_tileset(
+    path: &Path,
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+) -> Result<Tileset> {
+    #[cfg(feature = "json")]
+    if json::is_json_path(path) {
+        return json::parse_tileset(path, ctx);
+    }
+    xml::parse_tileset(path, ctx)
+}
+
+/// Returns the external tileset located at the path given, parsing it and inserting it into the
+/// cache if it isn't already there. Tilesets are cached by their [normalized](VirtualPath) path.
+pub(crate) fn load_external_tileset(
+    path: &Path,
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+) -> Result<Arc<Tileset>> {
+    let key = VirtualPath::new(path);
+    if let Some(ts) = ctx.cache.get_tileset(&key) {
+        return Ok(ts);
+    }
+    let tileset = Arc::new(parse_tileset(path, ctx)?);
+    ctx.cache.insert_tileset(key, tileset.clone());
+    Ok(tileset)
+}
+
+/// A tileset of a map, whose loading may be postponed if it is external so that all the external
+/// tilesets of the map can be loaded together.
+pub(crate) enum MapTilesetEntry {
+    Loaded(MapTilesetGid),
+    External { first_gid: Gid, path: PathBuf },
+}
+
+/// Loads the external tilesets among the given entries, in parallel if the `parallel` feature is
+/// enabled, and appends all of them to `tilesets` in order.
+pub(crate) fn resolve_map_tilesets(
+    entries: Vec<MapTilesetEntry>,
+    tilesets: &mut Vec<MapTilesetGid>,
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+) -> Result<()> {
+    #[cfg(feature = "parallel")]
+    {
+        let paths: Vec<&Path> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                MapTilesetEntry::External { path, .. } => Some(path.as_path()),
+                MapTilesetEntry::Loaded(_) => None,
+            })
+            .collect();
+        parallel::preload_external_tilesets(&paths, ctx);
+    }
+
+    for entry in entries {
+        tilesets.push(match entry {
+            MapTilesetEntry::Loaded(tileset) => tileset,
+            MapTilesetEntry::External { first_gid, path } => MapTilesetGid {
+                first_gid,
+                tileset: load_external_tileset(&path, ctx)?,
+            },
+        });
+    }
+    Ok(())
+}
+
+/// Returns the template located at the path given, parsing it and inserting it into the cache if
+/// it isn't already there. Templates are cached by their [normalized](VirtualPath) path.
+pub(crate) fn load_template(
+    path: &Path,
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+) -> Result<Arc<Template>> {
+    let key = VirtualPath::new(path);
+    if let Some(template) = ctx.cache.get_template(&key) {
+        return Ok(template);
+    }
+    #[cfg(feature = "json")]
+    let template = if json::is_json_path(path) {
+        json::parse_template(path, ctx)?
+    } else {
+        Template::parse_template(path, ctx)?
+    };
+    #[cfg(not(feature = "json"))]
+    let template = Template::parse_template(path, ctx)?;
+    ctx.cache.insert_template(key, template.clone());
+    Ok(template)
+}
@@ -0,0 +1,142 @@
+use std::{
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    limits::TileBudget, warning::Diagnostics, DefaultResourceCache, ResourceCache, ResourceReader,
+    Tileset, VirtualPath, Warning,
+};
+
+use super::LoadContext;
+
+/// Serves the contents of a single file that has already been read.
+struct PreloadedReader<'a> {
+    path: &'a Path,
+    data: Option<Vec<u8>>,
+}
+
+impl ResourceReader for PreloadedReader<'_> {
+    type Resource = Cursor<Vec<u8>>;
+    type Error = std::io::Error;
+
+    fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+        match self.data.take() {
+            Some(data) if VirtualPath::new(path) == VirtualPath::new(self.path) => {
+                Ok(Cursor::new(data))
+            }
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "file was not preloaded",
+            )),
+        }
+    }
+}
+
+/// Parses the external tilesets at the given paths that aren't cached yet using several threads,
+/// and inserts the ones parsed successfully into the cache.
+///
+/// Files are still read sequentially, since the reader can only be used by one thread at a time.
+/// Tilesets that cannot be parsed on their own, either because they are broken or because they
+/// reference other files such as templates, are left for the caller to load as usual, which also
+/// takes care of reporting errors.
+pub(crate) fn preload_external_tilesets(
+    paths: &[&Path],
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+) {
+    let mut uncached: Vec<&Path> = Vec::new();
+    for &path in paths {
+        if ctx.cache.get_tileset(VirtualPath::new(path)).is_none() && !uncached.contains(&path) {
+            uncached.push(path);
+        }
+    }
+    // Not worth spawning threads for. Checked before reading anything, since the caller reads the
+    // files again if they aren't preloaded.
+    if uncached.len() < 2 {
+        return;
+    }
+
+    let mut files: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+    for path in uncached {
+        let mut data = Vec::new();
+        let read = ctx
+            .reader
+            .read_from(path)
+            .ok()
+            .and_then(|mut resource| resource.read_to_end(&mut data).ok());
+        if read.is_some() {
+            files.push((path.to_owned(), data));
+        }
+    }
+    if files.len() < 2 {
+        return;
+    }
+
+    let thread_count = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(files.len());
+    let files_per_thread = files.len().div_ceil(thread_count);
+    let project = ctx.project;
+    let defer_tile_data = ctx.defer_tile_data;
+    let compact_tile_data = ctx.compact_tile_data;
+    let resolve_object_references = ctx.resolve_object_references;
+    let decoders = ctx.decoders;
+    let limits = ctx.limits;
+    let policy = ctx.diagnostics.policy();
+    let strict = ctx.diagnostics.strict();
+    let preserve_extensions = ctx.diagnostics.preserve_extensions();
+    let image_hook = ctx.image_hook;
+
+    let tilesets: Vec<(PathBuf, Tileset, Vec<Warning>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = files
+            .chunks_mut(files_per_thread)
+            .map(|files| {
+                scope.spawn(move || {
+                    let mut tilesets = Vec::new();
+                    for (path, data) in files {
+                        let mut reader = PreloadedReader {
+                            path,
+                            data: Some(std::mem::take(data)),
+                        };
+                        let mut cache = DefaultResourceCache::new();
+                        let diagnostics = Diagnostics::new(policy, strict, preserve_extensions);
+                        let mut ctx = LoadContext {
+                            reader: &mut reader,
+                            cache: &mut cache,
+                            project,
+                            defer_tile_data,
+                            compact_tile_data,
+                            resolve_object_references,
+                            decoders,
+                            limits,
+                            tiles: TileBudget::new(limits.max_tiles),
+                            group_depth: 0,
+                            interner: Default::default(),
+                            diagnostics: &diagnostics,
+                            image_hook,
+                        };
+                        if let Ok(tileset) = super::parse_tileset(path, &mut ctx) {
+                            tilesets.push((path.clone(), tileset, diagnostics.take_warnings()));
+                        }
+                    }
+                    tilesets
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+
+    for (path, tileset, warnings) in tilesets {
+        ctx.cache
+            .insert_tileset(VirtualPath::new(path), Arc::new(tileset));
+        ctx.diagnostics.extend(warnings);
+    }
+}
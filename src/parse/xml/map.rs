@@ -1,44 +1,29 @@
-use std::path::Path;
+use std::{io::Read, path::Path};
 
-use xml::{reader::XmlEvent, EventReader};
-
-use crate::{Error, Map, ResourceCache, ResourceReader, Result};
+use crate::{parse::LoadContext, Error, Map, ResourceCache, ResourceReader, Result};
 
 pub fn parse_map(
     path: &Path,
-    reader: &mut impl ResourceReader,
-    cache: &mut impl ResourceCache,
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+) -> Result<Map> {
+    let reader = ctx
+        .reader
+        .read_from(path)
+        .map_err(|err| Error::ResourceLoadingError {
+            path: path.to_owned(),
+            err: Box::new(err),
+        })?;
+    parse_map_from(reader, path, ctx)
+}
+
+/// Parses a map from the given reader as if it had been read from `path`.
+pub fn parse_map_from(
+    reader: impl Read,
+    path: &Path,
+    ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
 ) -> Result<Map> {
-    let mut parser =
-        EventReader::new(
-            reader
-                .read_from(path)
-                .map_err(|err| Error::ResourceLoadingError {
-                    path: path.to_owned(),
-                    err: Box::new(err),
-                })?,
-        );
-    loop {
-        match parser.next().map_err(Error::XmlDecodingError)? {
-            XmlEvent::StartElement {
-                name, attributes, ..
-            } => {
-                if name.local_name == "map" {
-                    return Map::parse_xml(
-                        &mut parser.into_iter(),
-                        attributes,
-                        path,
-                        reader,
-                        cache,
-                    );
-                }
-            }
-            XmlEvent::EndDocument => {
-                return Err(Error::PrematureEnd(
-                    "Document ended before map was parsed".to_string(),
-                ))
-            }
-            _ => {}
-        }
-    }
+    let diagnostics = ctx.diagnostics;
+    super::parse_document(reader, path, "map", diagnostics, |events, attributes| {
+        Map::parse_xml(events, attributes, path, ctx)
+    })
 }
@@ -2,3 +2,471 @@ mod map;
 pub use map::*;
 mod tileset;
 pub use tileset::*;
+
+use std::{io::Read, path::Path};
+
+use xml::{attribute::OwnedAttribute, common::Position, reader::XmlEvent, EventReader};
+
+use crate::{
+    extensions::ExtensionElement, util::XmlEventResult, warning::Diagnostics, Error, ErrorPolicy,
+    Result, WarningKind, XmlExtensions,
+};
+
+/// Iterates over the events of an XML document while keeping track of the elements currently
+/// open, so that errors and warnings can be attributed to the element they happened in.
+///
+/// In [strict mode](crate::Loader::set_strict()), the events stop at the first element or
+/// attribute that isn't part of the format, and the error is kept to be retrieved with
+/// [`TrackedEvents::take_error()`].
+///
+/// When [extensions are preserved](crate::Loader::set_preserve_extensions()), the unknown
+/// elements and attributes of each element are collected, and handed to
+/// [`Diagnostics::close_extensions()`] once the element is closed.
+pub(crate) struct TrackedEvents<'d, R: Read> {
+    reader: EventReader<R>,
+    diagnostics: &'d Diagnostics,
+    finished: bool,
+    error: Option<Error>,
+    /// The extensions of the known elements currently open, innermost last.
+    extensions: Vec<XmlExtensions>,
+    /// The unknown element being collected, followed by its descendants currently open.
+    unknown: Vec<ExtensionElement>,
+}
+
+impl<'d, R: Read> TrackedEvents<'d, R> {
+    pub(crate) fn new(reader: R, diagnostics: &'d Diagnostics) -> Self {
+        Self {
+            reader: EventReader::new(reader),
+            diagnostics,
+            finished: false,
+            error: None,
+            extensions: Vec::new(),
+            unknown: Vec::new(),
+        }
+    }
+
+    /// Collects the unknown elements and attributes found in an event.
+    fn preserve(&mut self, event: &XmlEvent, parent: Option<&str>) {
+        match event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let unknown = !self.unknown.is_empty()
+                    || parent.is_some_and(|parent| is_unknown_element(&name.local_name, parent));
+                if unknown {
+                    self.unknown.push(ExtensionElement::new(name, attributes));
+                } else {
+                    let known = known_attributes(&name.local_name).unwrap_or_default();
+                    self.extensions
+                        .push(XmlExtensions::from_attributes(attributes, known));
+                }
+            }
+            XmlEvent::EndElement { .. } => match self.unknown.pop() {
+                Some(element) => match self.unknown.last_mut() {
+                    Some(parent) => parent.push_child(element),
+                    None => {
+                        if let Some(extensions) = self.extensions.last_mut() {
+                            extensions.push_element(element);
+                        }
+                    }
+                },
+                None => {
+                    if let Some(extensions) = self.extensions.pop() {
+                        self.diagnostics.close_extensions(extensions);
+                    }
+                }
+            },
+            XmlEvent::Characters(text) => {
+                if let Some(element) = self.unknown.last_mut() {
+                    element.push_text(text.clone());
+                }
+            }
+            XmlEvent::CData(text) => {
+                if let Some(element) = self.unknown.last_mut() {
+                    element.push_cdata(text.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the error that stopped the events early, if any. Takes precedence over the errors
+    /// the parser ran into after the events stopped.
+    pub(crate) fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+}
+
+impl<R: Read> Iterator for TrackedEvents<'_, R> {
+    type Item = XmlEventResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let event = self.reader.next();
+        let preserve = self.diagnostics.preserve_extensions();
+        match &event {
+            Ok(XmlEvent::StartElement {
+                name, attributes, ..
+            }) => {
+                let parent = if self.diagnostics.strict() || preserve {
+                    self.diagnostics.element_name()
+                } else {
+                    None
+                };
+                if preserve {
+                    self.preserve(event.as_ref().unwrap(), parent.as_deref());
+                }
+                let position = self.reader.position();
+                self.diagnostics.open_element(
+                    name.local_name.clone(),
+                    position.row + 1,
+                    position.column + 1,
+                );
+                let checked = check_element(
+                    &name.local_name,
+                    parent.as_deref(),
+                    attributes,
+                    self.diagnostics,
+                );
+                if let Err(err) = checked {
+                    self.error = Some(err);
+                    self.finished = true;
+                    return None;
+                }
+            }
+            Ok(XmlEvent::EndElement { .. }) => self.diagnostics.close_element(),
+            Ok(XmlEvent::EndDocument) | Err(_) => self.finished = true,
+            _ => {}
+        }
+        if let (
+            true,
+            Ok(
+                event
+                @ (XmlEvent::EndElement { .. } | XmlEvent::Characters(_) | XmlEvent::CData(_)),
+            ),
+        ) = (preserve, &event)
+        {
+            self.preserve(event, None);
+        }
+        Some(event)
+    }
+}
+
+/// Elements that newer versions of Tiled no longer write.
+const DEPRECATED_ELEMENTS: &[&str] = &["terraintypes", "wangcornercolor", "wangedgecolor"];
+
+/// Returns the attributes the TMX format defines for an element, or `None` if the attributes of
+/// the element aren't checked.
+fn known_attributes(element: &str) -> Option<&'static [&'static str]> {
+    const LAYER: &[&str] = &[
+        "id",
+        "name",
+        "class",
+        "x",
+        "y",
+        "width",
+        "height",
+        "opacity",
+        "visible",
+        "tintcolor",
+        "offsetx",
+        "offsety",
+        "parallaxx",
+        "parallaxy",
+        "locked",
+    ];
+    Some(match element {
+        "map" => &[
+            "version",
+            "tiledversion",
+            "class",
+            "orientation",
+            "renderorder",
+            "compressionlevel",
+            "width",
+            "height",
+            "tilewidth",
+            "tileheight",
+            "hexsidelength",
+            "staggeraxis",
+            "staggerindex",
+            "parallaxoriginx",
+            "parallaxoriginy",
+            "backgroundcolor",
+            "nextlayerid",
+            "nextobjectid",
+            "infinite",
+        ],
+        "tileset" => &[
+            "firstgid",
+            "source",
+            "name",
+            "class",
+            "tilewidth",
+            "tileheight",
+            "spacing",
+            "margin",
+            "tilecount",
+            "columns",
+            "objectalignment",
+            "tilerendersize",
+            "fillmode",
+            "version",
+            "tiledversion",
+            "backgroundcolor",
+        ],
+        "tileoffset" => &["x", "y"],
+        "grid" => &["orientation", "width", "height"],
+        "image" => &["format", "id", "source", "trans", "width", "height"],
+        "terrain" => &["name", "tile"],
+        "transformations" => &["hflip", "vflip", "rotate", "preferuntransformed"],
+        // Tiles of tilesets, and of tile layers using the XML encoding.
+        "tile" => &[
+            "id",
+            "type",
+            "class",
+            "terrain",
+            "probability",
+            "x",
+            "y",
+            "width",
+            "height",
+            "gid",
+        ],
+        "wangset" => &["name", "class", "type", "tile"],
+        "wangcolor" => &["name", "class", "color", "tile", "probability"],
+        "wangtile" => &["tileid", "wangid", "hflip", "vflip", "dflip"],
+        "frame" => &["tileid", "duration"],
+        "layer" => LAYER,
+        "data" => &["encoding", "compression"],
+        "chunk" => &["x", "y", "width", "height"],
+        "objectgroup" => &[
+            "id",
+            "name",
+            "class",
+            "color",
+            "x",
+            "y",
+            "width",
+            "height",
+            "opacity",
+            "visible",
+            "tintcolor",
+            "offsetx",
+            "offsety",
+            "parallaxx",
+            "parallaxy",
+            "draworder",
+            "locked",
+        ],
+        "object" => &[
+            "id", "name", "type", "class", "x", "y", "width", "height", "rotation", "gid",
+            "visible", "template",
+        ],
+        "polygon" | "polyline" => &["points"],
+        "text" => &[
+            "fontfamily",
+            "pixelsize",
+            "wrap",
+            "color",
+            "bold",
+            "italic",
+            "underline",
+            "strikeout",
+            "kerning",
+            "halign",
+            "valign",
+        ],
+        "imagelayer" => &[
+            "id",
+            "name",
+            "class",
+            "offsetx",
+            "offsety",
+            "x",
+            "y",
+            "opacity",
+            "visible",
+            "tintcolor",
+            "parallaxx",
+            "parallaxy",
+            "repeatx",
+            "repeaty",
+            "locked",
+        ],
+        "group" => LAYER,
+        "property" => &["name", "type", "propertytype", "value"],
+        "properties" | "animation" | "wangsets" | "ellipse" | "point" | "template" => &[],
+        _ => return None,
+    })
+}
+
+/// Returns the elements the TMX format allows in an element, or `None` if the children of the
+/// element aren't checked. Elements of tilesets, maps and templates are mixed, since they share
+/// names.
+fn known_children(parent: &str) -> Option<&'static [&'static str]> {
+    const LAYERS: &[&str] = &["properties", "layer", "objectgroup", "imagelayer", "group"];
+    Some(match parent {
+        "map" => &[
+            "properties",
+            "editorsettings",
+            "tileset",
+            "layer",
+            "objectgroup",
+            "imagelayer",
+            "group",
+        ],
+        "editorsettings" => &["chunksize", "export"],
+        "tileset" => &[
+            "image",
+            "tileoffset",
+            "grid",
+            "properties",
+            "terraintypes",
+            "tile",
+            "wangsets",
+            "transformations",
+        ],
+        "terraintypes" => &["terrain"],
+        "terrain" | "wangcolor" | "property" => &["properties"],
+        "imagelayer" => &["properties", "image"],
+        "tile" => &["properties", "image", "objectgroup", "animation"],
+        "animation" => &["frame"],
+        "wangsets" => &["wangset"],
+        "wangset" => &[
+            "properties",
+            "wangcolor",
+            "wangtile",
+            "wangcornercolor",
+            "wangedgecolor",
+        ],
+        "image" => &["data"],
+        "layer" => &["properties", "data"],
+        "data" => &["tile", "chunk"],
+        "chunk" => &["tile"],
+        "objectgroup" => &["properties", "object"],
+        "object" => &[
+            "properties",
+            "ellipse",
+            "point",
+            "polygon",
+            "polyline",
+            "text",
+        ],
+        "group" => LAYERS,
+        "properties" => &["property"],
+        "template" => &["tileset", "object"],
+        "tileoffset" | "grid" | "transformations" | "frame" | "wangtile" | "chunksize"
+        | "export" | "ellipse" | "point" | "polygon" | "polyline" | "text" => &[],
+        _ => return None,
+    })
+}
+
+/// Returns whether an element isn't one of those the format allows in its parent. Elements in
+/// parents whose children aren't checked are never unknown.
+fn is_unknown_element(element: &str, parent: &str) -> bool {
+    known_children(parent).is_some_and(|known| !known.contains(&element))
+}
+
+/// Reports deprecated elements and unknown attributes as warnings. In strict mode, unknown
+/// elements are reported too, and both are errors unless the loader skips broken items.
+fn check_element(
+    element: &str,
+    parent: Option<&str>,
+    attributes: &[OwnedAttribute],
+    diagnostics: &Diagnostics,
+) -> Result<()> {
+    if DEPRECATED_ELEMENTS.contains(&element) {
+        diagnostics.warn(WarningKind::DeprecatedElement {
+            element: element.to_owned(),
+        });
+        return Ok(());
+    }
+    if let Some(parent) = parent {
+        if is_unknown_element(element, parent) {
+            report_unknown(
+                diagnostics,
+                WarningKind::UnknownElement {
+                    element: element.to_owned(),
+                    parent: parent.to_owned(),
+                },
+            )?;
+            return Ok(());
+        }
+    }
+    let known = match known_attributes(element) {
+        Some(known) => known,
+        None => return Ok(()),
+    };
+    for attribute in attributes {
+        // Attributes from other namespaces are meant to be ignored.
+        if attribute.name.prefix.is_none() && !known.contains(&attribute.name.local_name.as_str()) {
+            report_unknown(
+                diagnostics,
+                WarningKind::UnknownAttribute {
+                    element: element.to_owned(),
+                    attribute: attribute.name.local_name.clone(),
+                },
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Reports an unknown element or attribute as a warning, or as an error in strict mode under
+/// [`ErrorPolicy::Fail`].
+fn report_unknown(diagnostics: &Diagnostics, kind: WarningKind) -> Result<()> {
+    if !diagnostics.strict() || diagnostics.policy() == ErrorPolicy::SkipAndWarn {
+        diagnostics.warn(kind);
+        return Ok(());
+    }
+    let err = match kind {
+        WarningKind::UnknownElement { element, parent } => {
+            Error::UnknownElement { element, parent }
+        }
+        WarningKind::UnknownAttribute { element, attribute } => {
+            Error::UnknownAttribute { element, attribute }
+        }
+        kind => unreachable!("{:?} is not about an unknown element or attribute", kind),
+    };
+    Err(diagnostics.locate(err))
+}
+
+/// Parses the XML document read from `path` by looking for its `root` element and passing it to
+/// `parse`. Errors are located using [`TrackedEvents`].
+pub(crate) fn parse_document<R: Read, T>(
+    reader: R,
+    path: &Path,
+    root: &str,
+    diagnostics: &Diagnostics,
+    parse: impl FnOnce(&mut TrackedEvents<R>, Vec<OwnedAttribute>) -> Result<T>,
+) -> Result<T> {
+    let _document = diagnostics.enter_document(path);
+    let mut events = TrackedEvents::new(reader, diagnostics);
+    loop {
+        match events.next() {
+            Some(Ok(XmlEvent::StartElement {
+                name, attributes, ..
+            })) if name.local_name == root => {
+                let result = parse(&mut events, attributes);
+                return match events.take_error() {
+                    Some(err) => Err(err),
+                    None => result.map_err(|err| diagnostics.locate(err)),
+                };
+            }
+            Some(Ok(_)) => {}
+            Some(Err(err)) => return Err(Error::XmlDecodingError(err)),
+            None => {
+                if let Some(err) = events.take_error() {
+                    return Err(err);
+                }
+                return Err(Error::PrematureEnd(format!(
+                    "Document ended before the {} element was parsed",
+                    root
+                )));
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Structures related to Tiled projects.
+
+#[cfg(feature = "json")]
+use std::path::Path;
+use std::path::PathBuf;
+
+#[cfg(feature = "json")]
+use serde_json::Value;
+
+#[cfg(feature = "json")]
+use crate::{
+    parse::json::{as_objects, as_u32, JsonObject, JsonObjectExt},
+    properties::{parse_json_properties, parse_json_property_list, Interner},
+    Error, Result,
+};
+use crate::{Color, Properties};
+
+/// A Tiled project, as stored in `.tiled-project` files. Loading projects requires the `json`
+/// feature.
+///
+/// Also see the [Tiled docs](https://doc.mapeditor.org/en/stable/manual/projects/).
+#[derive(Debug, PartialEq, Clone)]
+pub struct Project {
+    /// The path of the project file, as given to `Loader::load_project`.
+    pub source: PathBuf,
+    /// The folders that are part of the project.
+    pub folders: Vec<PathBuf>,
+    /// The directory containing the project's scripted extensions, if set.
+    pub extensions_path: Option<PathBuf>,
+    /// The custom property types defined by the project.
+    pub property_types: Vec<PropertyType>,
+    /// The custom properties of the project.
+    pub properties: Properties,
+}
+
+impl Project {
+    /// Obtains the custom property type with the name given, if the project defines it.
+    pub fn get_property_type(&self, name: &str) -> Option<&PropertyType> {
+        self.property_types.iter().find(|t| t.name() == name)
+    }
+}
+
+/// A custom property type defined by a [`Project`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum PropertyType {
+    /// An enumeration of string values.
+    Enum(EnumPropertyType),
+    /// A class, consisting of a set of members.
+    Class(ClassPropertyType),
+}
+
+impl PropertyType {
+    /// The ID of this type, unique within its project.
+    pub fn id(&self) -> u32 {
+        match self {
+            PropertyType::Enum(t) => t.id,
+            PropertyType::Class(t) => t.id,
+        }
+    }
+
+    /// The name of this type, which properties use to refer to it.
+    pub fn name(&self) -> &str {
+        match self {
+            PropertyType::Enum(t) => &t.name,
+            PropertyType::Class(t) => &t.name,
+        }
+    }
+}
+
+/// How the values of an [`EnumPropertyType`] are stored in files.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EnumStorageType {
+    /// Values are stored by name, in `string` properties.
+    String,
+    /// Values are stored by index, in `int` properties.
+    Int,
+}
+
+/// A custom enum property type.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EnumPropertyType {
+    /// The ID of this type, unique within its project.
+    pub id: u32,
+    /// The name of this type.
+    pub name: String,
+    /// How values of this type are stored.
+    pub storage_type: EnumStorageType,
+    /// The possible values of this type, in order.
+    pub values: Vec<String>,
+    /// Whether properties of this type can hold several values at once, in which case values are
+    /// stored as a comma-separated list of names or as a bitmask.
+    pub values_as_flags: bool,
+}
+
+/// A custom class property type.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClassPropertyType {
+    /// The ID of this type, unique within its project.
+    pub id: u32,
+    /// The name of this type.
+    pub name: String,
+    /// The color used to display objects of this class in the editor.
+    pub color: Option<Color>,
+    /// What this class can be used for, e.g. `property`, `map`, `layer` or `object`.
+    pub use_as: Vec<String>,
+    /// The members of this class along with their default values.
+    pub members: Properties,
+}
+
+#[cfg(feature = "json")]
+impl Project {
+    pub(crate) fn parse_json(object: &JsonObject, path: &Path) -> Result<Project> {
+        let root = path.parent().ok_or(Error::PathIsNotFile)?;
+
+        let folders = match object.opt_array("folders")? {
+            Some(folders) => folders
+                .iter()
+                .map(|folder| {
+                    folder
+                        .as_str()
+                        .map(|folder| root.join(folder))
+                        .ok_or_else(|| {
+                            Error::MalformedAttributes(
+                                "Error parsing attribute 'folders'".to_owned(),
+                            )
+                        })
+                })
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+        let interner = Interner::default();
+        let property_types = match object.opt_array("propertyTypes")? {
+            Some(types) => as_objects(types, "propertyTypes")?
+                .map(|object| PropertyType::parse_json(object, &interner))
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(Project {
+            source: path.to_owned(),
+            folders,
+            extensions_path: object
+                .opt_str("extensionsPath")?
+                .filter(|p| !p.is_empty())
+                .map(|p| root.join(p)),
+            property_types,
+            properties: parse_json_properties(object, None, &interner, &Default::default())?,
+        })
+    }
+}
+
+#[cfg(feature = "json")]
+impl PropertyType {
+    fn parse_json(object: &JsonObject, interner: &Interner) -> Result<PropertyType> {
+        let id = object.req("id", as_u32)?;
+        let name = object.req("name", Value::as_str)?.to_owned();
+        match object.req("type", Value::as_str)? {
+            "enum" => Ok(PropertyType::Enum(EnumPropertyType {
+                id,
+                name,
+                storage_type: match object.opt_str("storageType")? {
+                    Some("int") => EnumStorageType::Int,
+                    _ => EnumStorageType::String,
+                },
+                values: object.req("values", |v| {
+                    v.as_array()?
+                        .iter()
+                        .map(|v| v.as_str().map(str::to_owned))
+                        .collect()
+                })?,
+                values_as_flags: object.opt_bool("valuesAsFlags")?.unwrap_or(false),
+            })),
+            "class" => Ok(PropertyType::Class(ClassPropertyType {
+                id,
+                name,
+                color: object.opt_color("color")?,
+                use_as: object
+                    .opt("useAs", |v| {
+                        v.as_array()?
+                            .iter()
+                            .map(|v| v.as_str().map(str::to_owned))
+                            .collect()
+                    })?
+                    .unwrap_or_default(),
+                members: parse_json_property_list(
+                    object,
+                    "members",
+                    None,
+                    interner,
+                    &Default::default(),
+                )?,
+            })),
+            other => Err(Error::UnknownPropertyType {
+                type_name: other.to_owned(),
+            }),
+        }
+    }
+}
@@ -1,14 +1,28 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt,
+    io::Write,
+    str::FromStr,
+    sync::Arc,
+};
 
-use xml::{attribute::OwnedAttribute, reader::XmlEvent};
+use xml::{attribute::OwnedAttribute, reader::XmlEvent, EventWriter};
 
 use crate::{
     error::{Error, Result},
-    util::{get_attrs, parse_tag, XmlEventResult},
+    util::{
+        get_attrs, parse_tag, write_characters, write_empty_element, write_end_element,
+        write_start_element, XmlEventResult,
+    },
+    warning::Diagnostics,
+    Project, PropertyType,
 };
 
 /// Represents a RGBA color with 8-bit depth on each channel.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub struct Color {
     pub alpha: u8,
@@ -17,6 +31,83 @@ pub struct Color {
     pub blue: u8,
 }
 
+impl Color {
+    /// Multiplies each channel of this color with the same channel of `other`, treating channels
+    /// as values between 0.0 and 1.0. This is how Tiled combines tint colors.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::Color;
+    ///
+    /// let white = Color { alpha: 255, red: 255, green: 255, blue: 255 };
+    /// let tint = Color { alpha: 128, red: 255, green: 0, blue: 51 };
+    /// assert_eq!(white.multiply(tint), tint);
+    /// assert_eq!(
+    ///     tint.multiply(tint),
+    ///     Color { alpha: 64, red: 255, green: 0, blue: 10 }
+    /// );
+    /// ```
+    pub fn multiply(self, other: Color) -> Color {
+        let channel = |a: u8, b: u8| ((a as u32 * b as u32 + 127) / 255) as u8;
+        Color {
+            alpha: channel(self.alpha, other.alpha),
+            red: channel(self.red, other.red),
+            green: channel(self.green, other.green),
+            blue: channel(self.blue, other.blue),
+        }
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    /// Converts the color into its red, green, blue and alpha channels, in that order, as values
+    /// between 0.0 and 1.0.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::Color;
+    ///
+    /// let color = Color { alpha: 255, red: 255, green: 0, blue: 51 };
+    /// assert_eq!(<[f32; 4]>::from(color), [1.0, 0.0, 0.2, 1.0]);
+    /// ```
+    fn from(color: Color) -> Self {
+        [
+            color.red as f32 / 255.0,
+            color.green as f32 / 255.0,
+            color.blue as f32 / 255.0,
+            color.alpha as f32 / 255.0,
+        ]
+    }
+}
+
+impl From<Color> for u32 {
+    /// Packs the color into a `0xAARRGGBB` integer, the same order Tiled stores it in.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::Color;
+    ///
+    /// let color = Color { alpha: 0x80, red: 0x20, green: 0x40, blue: 0x60 };
+    /// assert_eq!(u32::from(color), 0x80204060);
+    /// assert_eq!(Color::from(0x80204060), color);
+    /// ```
+    fn from(color: Color) -> Self {
+        u32::from_be_bytes([color.alpha, color.red, color.green, color.blue])
+    }
+}
+
+impl From<u32> for Color {
+    /// Unpacks a color from a `0xAARRGGBB` integer.
+    fn from(argb: u32) -> Self {
+        let [alpha, red, green, blue] = argb.to_be_bytes();
+        Color {
+            alpha,
+            red,
+            green,
+            blue,
+        }
+    }
+}
+
 impl FromStr for Color {
     type Err = ();
 
@@ -61,10 +152,34 @@ impl FromStr for Color {
     }
 }
 
+impl fmt::Display for Color {
+    /// Formats the color the way Tiled stores it in files, i.e. `#AARRGGBB`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{:02x}{:02x}{:02x}{:02x}",
+            self.alpha, self.red, self.green, self.blue
+        )
+    }
+}
+
+impl Color {
+    /// Formats the color the way Tiled stores the colors of maps and layers, which leaves out
+    /// the alpha channel of opaque colors, i.e. `#RRGGBB` or `#AARRGGBB`.
+    pub(crate) fn to_short_string(self) -> String {
+        if self.alpha == 255 {
+            format!("#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+        } else {
+            self.to_string()
+        }
+    }
+}
+
 /// Represents a custom property's value.
 ///
 /// Also read the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tmx-properties).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PropertyValue {
     /// A boolean value. Corresponds to the `bool` property type.
     BoolValue(bool),
@@ -82,6 +197,29 @@ pub enum PropertyValue {
     /// An object ID value. Corresponds to the `object` property type.
     /// Holds the id of a referenced object, or 0 if unset.
     ObjectValue(u32),
+    /// A class value. Corresponds to the `class` property type.
+    ClassValue {
+        /// The name of the custom class this value is an instance of.
+        property_type: String,
+        /// The members of this value which are set. Members left at their default value aren't
+        /// included.
+        members: Properties,
+    },
+    /// A value of a custom enum type defined by the loaded [`Project`]. Corresponds to `string` and
+    /// `int` properties whose `propertytype` refers to an enum.
+    ///
+    /// Without a loaded project, such properties are read as [`StringValue`](Self::StringValue)s
+    /// and [`IntValue`](Self::IntValue)s instead.
+    EnumValue {
+        /// The name of the enum type this value belongs to.
+        type_name: String,
+        /// The name of the value. For enums whose values are flags, the names of all the set values
+        /// separated by commas.
+        value: String,
+        /// For enums whose values are flags, the names of all the set values, in the order the enum
+        /// declares them. Empty otherwise.
+        flags: Vec<String>,
+    },
 }
 
 impl PropertyValue {
@@ -126,41 +264,663 @@ impl PropertyValue {
     }
 }
 
+impl PropertyValue {
+    /// Returns the name of the type of this value as used in the `type` attribute of properties.
+    fn type_name(&self) -> &'static str {
+        match self {
+            PropertyValue::BoolValue(_) => "bool",
+            PropertyValue::FloatValue(_) => "float",
+            PropertyValue::IntValue(_) => "int",
+            PropertyValue::ColorValue(_) => "color",
+            PropertyValue::StringValue(_) => "string",
+            PropertyValue::FileValue(_) => "file",
+            PropertyValue::ObjectValue(_) => "object",
+            PropertyValue::ClassValue { .. } => "class",
+            PropertyValue::EnumValue { .. } => "string",
+        }
+    }
+}
+
+/// A type that can be converted from a [`PropertyValue`] of a specific type.
+///
+/// This is used by [`FromProperties`] implementations to read the properties they need, and is
+/// implemented for the types of values properties can hold. Types deriving [`FromProperties`] also
+/// implement it, so that they can be read from class properties.
+pub trait FromPropertyValue: Sized {
+    /// The name of the property type the value is expected to have, as used in the `type`
+    /// attribute of properties. Used in error messages.
+    const TYPE_NAME: &'static str;
+
+    /// Converts a property value, or returns [`None`] if it isn't of the right type.
+    fn from_property_value(value: &PropertyValue) -> Option<Self>;
+}
+
+impl FromPropertyValue for bool {
+    const TYPE_NAME: &'static str = "bool";
+
+    fn from_property_value(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::BoolValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromPropertyValue for i32 {
+    const TYPE_NAME: &'static str = "int";
+
+    fn from_property_value(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::IntValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromPropertyValue for f32 {
+    const TYPE_NAME: &'static str = "float";
+
+    /// Integers are converted as well.
+    fn from_property_value(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::FloatValue(value) => Some(*value),
+            PropertyValue::IntValue(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+}
+
+impl FromPropertyValue for Color {
+    const TYPE_NAME: &'static str = "color";
+
+    fn from_property_value(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::ColorValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromPropertyValue for String {
+    const TYPE_NAME: &'static str = "string";
+
+    /// Files and values of enums are converted as well.
+    fn from_property_value(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::StringValue(value)
+            | PropertyValue::FileValue(value)
+            | PropertyValue::EnumValue { value, .. } => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromPropertyValue for PropertyValue {
+    const TYPE_NAME: &'static str = "any";
+
+    fn from_property_value(value: &PropertyValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+/// A type that can be built from a set of [`Properties`], such as the members of a custom class.
+///
+/// With the `derive` feature enabled, this trait can be derived for structs with named fields.
+/// Each field is read from the property of the same name using [`FromPropertyValue`], and the
+/// derive accepts these attributes:
+/// - `#[tiled(rename = "name")]` reads the field from the property with the given name instead.
+/// - `#[tiled(default)]` uses the field type's [`Default`] value if the property is missing.
+///
+/// Fields of type [`Option`] are set to [`None`] if their property is missing. Any other missing
+/// property results in an [`Error::MissingProperty`], and a property of the wrong type in an
+/// [`Error::PropertyTypeMismatch`].
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{FromProperties, Loader};
+///
+/// struct LayerInfo {
+///     prop1: String,
+///     speed: f32,
+/// }
+///
+/// impl FromProperties for LayerInfo {
+///     // This is equivalent to deriving the trait with `#[tiled(default)]` on `speed`.
+///     fn from_properties(properties: &tiled::Properties) -> tiled::Result<Self> {
+///         Ok(Self {
+///             prop1: tiled::required_property(properties, "prop1")?,
+///             speed: tiled::optional_property(properties, "speed")?.unwrap_or_default(),
+///         })
+///     }
+/// }
+///
+/// let map = Loader::new().load_tmx_map("assets/tiled_csv.tmx")?;
+/// let info = LayerInfo::from_properties(&map.get_layer(0).unwrap().properties)?;
+/// assert_eq!(info.prop1, "12");
+/// assert_eq!(info.speed, 0.0);
+/// # Ok(())
+/// # }
+/// ```
+pub trait FromProperties: Sized {
+    /// Builds a value from the properties given.
+    fn from_properties(properties: &Properties) -> Result<Self>;
+}
+
+/// Reads the property with the given name, returning an error if it is missing or of the wrong
+/// type.
+pub fn required_property<T: FromPropertyValue>(properties: &Properties, name: &str) -> Result<T> {
+    optional_property(properties, name)?.ok_or_else(|| Error::MissingProperty {
+        name: name.to_owned(),
+    })
+}
+
+/// Reads the property with the given name if there is one, returning an error if it is of the wrong
+/// type.
+pub fn optional_property<T: FromPropertyValue>(
+    properties: &Properties,
+    name: &str,
+) -> Result<Option<T>> {
+    properties
+        .get(name)
+        .map(|value| {
+            T::from_property_value(value).ok_or_else(|| Error::PropertyTypeMismatch {
+                name: name.to_owned(),
+                expected: T::TYPE_NAME,
+                found: value.type_name(),
+            })
+        })
+        .transpose()
+}
+
+/// Typed accessors for the values of [`Properties`], so that they don't need to be matched by
+/// hand.
+///
+/// The `get_*` methods return [`None`] if the property is missing or of another type, while the
+/// `try_get_*` ones return an [`Error::MissingProperty`] or an [`Error::PropertyTypeMismatch`]
+/// describing the problem. Values are converted as done by [`FromPropertyValue`]; For instance,
+/// [`get_float()`](Self::get_float()) also accepts integers.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{Loader, PropertiesExt};
+///
+/// let map = Loader::new().load_tmx_map("assets/tiled_csv.tmx")?;
+/// let properties = &map.get_layer(0).unwrap().properties;
+/// assert_eq!(properties.get_string("prop1"), Some("12"));
+/// assert_eq!(properties.get_int("prop1"), None);
+/// assert!(properties.try_get_bool("solid").is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub trait PropertiesExt {
+    /// Returns the value of a `bool` property.
+    fn get_bool(&self, name: &str) -> Option<bool>;
+    /// Returns the value of an `int` property.
+    fn get_int(&self, name: &str) -> Option<i32>;
+    /// Returns the value of a `float` or `int` property.
+    fn get_float(&self, name: &str) -> Option<f32>;
+    /// Returns the value of a `color` property.
+    fn get_color(&self, name: &str) -> Option<Color>;
+    /// Returns the value of a `string` or `file` property, or the name of an enum value.
+    fn get_string(&self, name: &str) -> Option<&str>;
+
+    /// Returns the value of a `bool` property, or an error if it is missing or of another type.
+    fn try_get_bool(&self, name: &str) -> Result<bool>;
+    /// Returns the value of an `int` property, or an error if it is missing or of another type.
+    fn try_get_int(&self, name: &str) -> Result<i32>;
+    /// Returns the value of a `float` or `int` property, or an error if it is missing or of
+    /// another type.
+    fn try_get_float(&self, name: &str) -> Result<f32>;
+    /// Returns the value of a `color` property, or an error if it is missing or of another type.
+    fn try_get_color(&self, name: &str) -> Result<Color>;
+    /// Returns the value of a `string` or `file` property, or the name of an enum value, or an
+    /// error if it is missing or of another type.
+    fn try_get_string(&self, name: &str) -> Result<&str>;
+}
+
+impl PropertiesExt for Properties {
+    fn get_bool(&self, name: &str) -> Option<bool> {
+        self.get(name).and_then(bool::from_property_value)
+    }
+
+    fn get_int(&self, name: &str) -> Option<i32> {
+        self.get(name).and_then(i32::from_property_value)
+    }
+
+    fn get_float(&self, name: &str) -> Option<f32> {
+        self.get(name).and_then(f32::from_property_value)
+    }
+
+    fn get_color(&self, name: &str) -> Option<Color> {
+        self.get(name).and_then(Color::from_property_value)
+    }
+
+    fn get_string(&self, name: &str) -> Option<&str> {
+        self.try_get_string(name).ok()
+    }
+
+    fn try_get_bool(&self, name: &str) -> Result<bool> {
+        required_property(self, name)
+    }
+
+    fn try_get_int(&self, name: &str) -> Result<i32> {
+        required_property(self, name)
+    }
+
+    fn try_get_float(&self, name: &str) -> Result<f32> {
+        required_property(self, name)
+    }
+
+    fn try_get_color(&self, name: &str) -> Result<Color> {
+        required_property(self, name)
+    }
+
+    fn try_get_string(&self, name: &str) -> Result<&str> {
+        match self.get(name) {
+            Some(
+                PropertyValue::StringValue(value)
+                | PropertyValue::FileValue(value)
+                | PropertyValue::EnumValue { value, .. },
+            ) => Ok(value),
+            Some(value) => Err(Error::PropertyTypeMismatch {
+                name: name.to_owned(),
+                expected: String::TYPE_NAME,
+                found: value.type_name(),
+            }),
+            None => Err(Error::MissingProperty {
+                name: name.to_owned(),
+            }),
+        }
+    }
+}
+
 /// A custom property container.
-pub type Properties = HashMap<String, PropertyValue>;
+///
+/// Property names are reference counted: The same names tend to be used by many objects, so
+/// properties loaded together share the storage of their names.
+///
+/// Only property names are shared this way. Layer names and classes stay `String`s, since they
+/// are public fields meant to be edited in place, and are far less repeated: A map has one name
+/// per layer, while each of its objects carries the names of all of its properties.
+pub type Properties = HashMap<Arc<str>, PropertyValue>;
+
+/// Adds the name and referenced object id of each set object property in `properties` to
+/// `references`, including those nested in class properties.
+pub(crate) fn collect_object_references(
+    properties: &Properties,
+    references: &mut Vec<(String, u32)>,
+) {
+    for (name, value) in properties {
+        match value {
+            PropertyValue::ObjectValue(id) if *id != 0 => references.push((name.to_string(), *id)),
+            PropertyValue::ClassValue { members, .. } => {
+                collect_object_references(members, references)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replaces the object ids referenced by the object properties in `properties`, including those
+/// nested in class properties, with their value in `ids`. References to other ids are left as is.
+pub(crate) fn remap_object_references(properties: &mut Properties, ids: &HashMap<u32, u32>) {
+    for value in properties.values_mut() {
+        match value {
+            PropertyValue::ObjectValue(id) => {
+                if let Some(new_id) = ids.get(id) {
+                    *id = *new_id;
+                }
+            }
+            PropertyValue::ClassValue { members, .. } => remap_object_references(members, ids),
+            _ => {}
+        }
+    }
+}
+
+/// Shares the storage of identical strings, such as property names, between everything parsed
+/// while loading a file and the files it references.
+#[derive(Debug, Default)]
+pub(crate) struct Interner {
+    strings: RefCell<HashSet<Arc<str>>>,
+}
+
+impl Interner {
+    /// Returns a shared copy of the given string.
+    pub(crate) fn intern(&self, s: &str) -> Arc<str> {
+        let mut strings = self.strings.borrow_mut();
+        match strings.get(s) {
+            Some(interned) => interned.clone(),
+            None => {
+                let interned: Arc<str> = Arc::from(s);
+                strings.insert(interned.clone());
+                interned
+            }
+        }
+    }
+}
 
 pub(crate) fn parse_properties(
     parser: &mut impl Iterator<Item = XmlEventResult>,
+    project: Option<&Project>,
+    interner: &Interner,
+    diagnostics: &Diagnostics,
 ) -> Result<Properties> {
     let mut p = HashMap::new();
     parse_tag!(parser, "properties", {
-        "property" => |attrs:Vec<OwnedAttribute>| {
-            let (t, v_attr, k) = get_attrs!(
-                for attr in attrs {
-                    Some("type") => obj_type = attr,
-                    Some("value") => value = attr,
-                    "name" => name = attr
+        "property" => |attrs| {
+            let depth = diagnostics.depth();
+            match parse_property(parser, attrs, project, interner, diagnostics) {
+                Ok((name, value)) => {
+                    p.insert(name, value);
+                    Ok(())
                 }
-                (obj_type, value, name)
-            );
-            let t = t.unwrap_or_else(|| "string".to_owned());
-
-            let v: String = match v_attr {
-                Some(val) => val,
-                None => {
-                    // if the "value" attribute was missing, might be a multiline string
-                    match parser.next() {
-                        Some(Ok(XmlEvent::Characters(s))) => Ok(s),
-                        Some(Err(err)) => Err(Error::XmlDecodingError(err)),
-                        None => unreachable!(), // EndDocument or error must come first
-                        _ => Err(Error::MalformedAttributes(format!("property '{}' is missing a value", k))),
-                    }?
-                }
-            };
-
-            p.insert(k, PropertyValue::new(t, v)?);
-            Ok(())
+                Err(err) => diagnostics.skip_element(parser, depth, err),
+            }
         },
     });
     Ok(p)
 }
+
+fn parse_property(
+    parser: &mut impl Iterator<Item = XmlEventResult>,
+    attrs: Vec<OwnedAttribute>,
+    project: Option<&Project>,
+    interner: &Interner,
+    diagnostics: &Diagnostics,
+) -> Result<(Arc<str>, PropertyValue)> {
+    let (t, v_attr, property_type, k) = get_attrs!(
+        for attr in attrs {
+            Some("type") => obj_type = attr,
+            Some("value") => value = attr,
+            Some("propertytype") => property_type = attr,
+            "name" => name = attr
+        }
+        (obj_type, value, property_type, name)
+    );
+    let t = t.unwrap_or_else(|| "string".to_owned());
+
+    if t == "class" {
+        let mut members = HashMap::new();
+        parse_tag!(parser, "property", {
+            "properties" => |_| {
+                members = parse_properties(parser, project, interner, diagnostics)?;
+                Ok(())
+            },
+        });
+        return Ok((
+            interner.intern(&k),
+            PropertyValue::ClassValue {
+                property_type: property_type.unwrap_or_default(),
+                members,
+            },
+        ));
+    }
+
+    let v: String = match v_attr {
+        Some(val) => val,
+        None => {
+            // if the "value" attribute was missing, might be a multiline string
+            match parser.next() {
+                Some(Ok(XmlEvent::Characters(s))) => Ok(s),
+                Some(Err(err)) => Err(Error::XmlDecodingError(err)),
+                None => unreachable!(), // EndDocument or error must come first
+                _ => Err(Error::MalformedAttributes(format!(
+                    "property '{}' is missing a value",
+                    k
+                ))),
+            }?
+        }
+    };
+
+    let value = PropertyValue::new(t, v)?;
+    Ok((
+        interner.intern(&k),
+        resolve_enum(value, property_type.as_deref(), project)?,
+    ))
+}
+
+/// Parses a JSON `properties` array, if any.
+#[cfg(feature = "json")]
+pub(crate) fn parse_json_properties(
+    object: &crate::parse::json::JsonObject,
+    project: Option<&Project>,
+    interner: &Interner,
+    diagnostics: &Diagnostics,
+) -> Result<Properties> {
+    parse_json_property_list(object, "properties", project, interner, diagnostics)
+}
+
+/// Parses an array of JSON properties stored in the member given, if any.
+#[cfg(feature = "json")]
+pub(crate) fn parse_json_property_list(
+    object: &crate::parse::json::JsonObject,
+    key: &str,
+    project: Option<&Project>,
+    interner: &Interner,
+    diagnostics: &Diagnostics,
+) -> Result<Properties> {
+    use crate::parse::json::{as_objects, JsonObjectExt};
+
+    let mut p = HashMap::new();
+    if let Some(properties) = object.opt_array(key)? {
+        for property in as_objects(properties, key)? {
+            match parse_json_property(property, project, interner) {
+                Ok((name, value)) => {
+                    p.insert(name, value);
+                }
+                Err(err) => diagnostics.skip(err)?,
+            }
+        }
+    }
+    Ok(p)
+}
+
+#[cfg(feature = "json")]
+fn parse_json_property(
+    property: &crate::parse::json::JsonObject,
+    project: Option<&Project>,
+    interner: &Interner,
+) -> Result<(Arc<str>, PropertyValue)> {
+    use crate::parse::json::JsonObjectExt;
+    use serde_json::Value;
+
+    let name = property.req("name", Value::as_str)?;
+    let t = property.opt_str("type")?.unwrap_or("string");
+    if t == "class" {
+        // Maps use "propertytype", while projects use "propertyType".
+        let property_type = match property.opt_str("propertytype")? {
+            Some(property_type) => Some(property_type),
+            None => property.opt_str("propertyType")?,
+        };
+        let members = match property.opt_object("value")? {
+            Some(members) => parse_json_class_members(members, interner),
+            None => HashMap::new(),
+        };
+        return Ok((
+            interner.intern(name),
+            PropertyValue::ClassValue {
+                property_type: property_type.unwrap_or_default().to_owned(),
+                members,
+            },
+        ));
+    }
+    // Reuse the same conversion the XML format uses.
+    let v = match property.get("value") {
+        Some(Value::String(s)) => s.clone(),
+        Some(v @ (Value::Bool(_) | Value::Number(_))) => v.to_string(),
+        _ => {
+            return Err(Error::MalformedAttributes(format!(
+                "property '{}' is missing a value",
+                name
+            )))
+        }
+    };
+    let value = PropertyValue::new(t.to_owned(), v)?;
+    let property_type = property.opt_str("propertytype")?;
+    Ok((
+        interner.intern(name),
+        resolve_enum(value, property_type, project)?,
+    ))
+}
+
+/// Converts a property whose `propertytype` refers to one of the project's enums into a
+/// [`PropertyValue::EnumValue`]. Any other property is returned as is.
+fn resolve_enum(
+    value: PropertyValue,
+    property_type: Option<&str>,
+    project: Option<&Project>,
+) -> Result<PropertyValue> {
+    let enum_type = match property_type
+        .zip(project)
+        .and_then(|(name, project)| project.get_property_type(name))
+    {
+        Some(PropertyType::Enum(enum_type)) => enum_type,
+        _ => return Ok(value),
+    };
+
+    let names: Vec<String> = match &value {
+        PropertyValue::StringValue(s) if enum_type.values_as_flags => s
+            .split(',')
+            .filter(|name| !name.is_empty())
+            .map(str::to_owned)
+            .collect(),
+        PropertyValue::StringValue(s) => vec![s.clone()],
+        PropertyValue::IntValue(bits) if enum_type.values_as_flags => enum_type
+            .values
+            .iter()
+            .take(32)
+            .enumerate()
+            .filter(|(bit, _)| bits & (1 << bit) != 0)
+            .map(|(_, name)| name.clone())
+            .collect(),
+        PropertyValue::IntValue(index) => {
+            let name = usize::try_from(*index)
+                .ok()
+                .and_then(|index| enum_type.values.get(index))
+                .ok_or_else(|| Error::InvalidPropertyValue {
+                    description: format!(
+                        "{} is not a valid value of enum '{}'",
+                        index, enum_type.name
+                    ),
+                })?;
+            vec![name.clone()]
+        }
+        _ => return Ok(value),
+    };
+
+    Ok(PropertyValue::EnumValue {
+        type_name: enum_type.name.clone(),
+        value: names.join(","),
+        flags: if enum_type.values_as_flags {
+            names
+        } else {
+            Vec::new()
+        },
+    })
+}
+
+/// Converts the members of a JSON class value. Unlike regular properties, they are stored without
+/// their type, so it is inferred from the JSON value: Strings (which may hold colors or files) are
+/// read as [`PropertyValue::StringValue`], and nested classes have an empty
+/// [`property_type`](PropertyValue::ClassValue::property_type).
+#[cfg(feature = "json")]
+fn parse_json_class_members(
+    members: &crate::parse::json::JsonObject,
+    interner: &Interner,
+) -> Properties {
+    use serde_json::Value;
+
+    members
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = match value {
+                Value::Bool(b) => PropertyValue::BoolValue(*b),
+                Value::Number(n) => match n.as_i64().and_then(|n| i32::try_from(n).ok()) {
+                    Some(n) => PropertyValue::IntValue(n),
+                    None => PropertyValue::FloatValue(n.as_f64()? as f32),
+                },
+                Value::String(s) => PropertyValue::StringValue(s.clone()),
+                Value::Object(members) => PropertyValue::ClassValue {
+                    property_type: String::new(),
+                    members: parse_json_class_members(members, interner),
+                },
+                Value::Null | Value::Array(_) => return None,
+            };
+            Some((interner.intern(name), value))
+        })
+        .collect()
+}
+
+/// Writes a `<properties>` element, if there are any properties to write. Properties are sorted by
+/// name so that the output is deterministic.
+pub(crate) fn write_properties(
+    writer: &mut EventWriter<impl Write>,
+    properties: &Properties,
+) -> Result<()> {
+    if properties.is_empty() {
+        return Ok(());
+    }
+
+    let mut names: Vec<&Arc<str>> = properties.keys().collect();
+    names.sort();
+
+    write_start_element(writer, "properties", &[])?;
+    for name in names {
+        let value = &properties[name];
+        let mut attrs = vec![("name", name.to_string())];
+        if !matches!(
+            value,
+            PropertyValue::StringValue(_) | PropertyValue::EnumValue { .. }
+        ) {
+            attrs.push(("type", value.type_name().to_owned()));
+        }
+        match value {
+            PropertyValue::ClassValue {
+                property_type,
+                members,
+            } => {
+                attrs.push(("propertytype", property_type.clone()));
+                write_start_element(writer, "property", &attrs)?;
+                write_properties(writer, members)?;
+                write_end_element(writer)?;
+            }
+            PropertyValue::EnumValue {
+                type_name, value, ..
+            } => {
+                // Enum values are written by name, which Tiled accepts regardless of how the enum
+                // stores its values.
+                attrs.push(("propertytype", type_name.clone()));
+                attrs.push(("value", value.clone()));
+                write_empty_element(writer, "property", &attrs)?;
+            }
+            // Multiline strings are written as text content, like Tiled does.
+            PropertyValue::StringValue(s) if s.contains('\n') => {
+                write_start_element(writer, "property", &attrs)?;
+                write_characters(writer, s)?;
+                write_end_element(writer)?;
+            }
+            _ => {
+                attrs.push((
+                    "value",
+                    match value {
+                        PropertyValue::BoolValue(v) => v.to_string(),
+                        PropertyValue::FloatValue(v) => v.to_string(),
+                        PropertyValue::IntValue(v) => v.to_string(),
+                        PropertyValue::ColorValue(v) => v.to_string(),
+                        PropertyValue::StringValue(v) => v.clone(),
+                        PropertyValue::FileValue(v) => v.clone(),
+                        PropertyValue::ObjectValue(v) => v.to_string(),
+                        // Handled above.
+                        PropertyValue::ClassValue { .. } | PropertyValue::EnumValue { .. } => {
+                            unreachable!()
+                        }
+                    },
+                ));
+                write_empty_element(writer, "property", &attrs)?;
+            }
+        }
+    }
+    write_end_element(writer)
+}
@@ -0,0 +1,116 @@
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use crate::{util::normalize_path, ResourceReader};
+
+/// Retrieves the contents of a URL for a [`UrlResourceReader`].
+///
+/// This is implemented for any `FnMut(&str) -> std::io::Result<Vec<u8>>` closure, which makes it
+/// easy to use the HTTP client of your choice, along with its timeouts, redirects and TLS.
+pub trait Fetch {
+    /// Returns the contents of the resource at `url`.
+    fn fetch(&mut self, url: &str) -> std::io::Result<Vec<u8>>;
+}
+
+impl<F: FnMut(&str) -> std::io::Result<Vec<u8>>> Fetch for F {
+    fn fetch(&mut self, url: &str) -> std::io::Result<Vec<u8>> {
+        self(url)
+    }
+}
+
+/// A [`ResourceReader`] that downloads files from a web server.
+///
+/// Paths are turned into URLs by appending them to a base URL, after resolving their `.` and `..`
+/// components, so that the files referenced by a map are downloaded from next to it. Paths that
+/// already are URLs, such as `https://example.com/map.tmx`, are used as they are.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{DefaultResourceCache, Loader, UrlResourceReader};
+///
+/// // Serves the files from the `assets` directory, as if they were hosted there.
+/// let fetch = |url: &str| {
+///     let path = url.strip_prefix("https://example.com/maps/").unwrap();
+///     std::fs::read(std::path::Path::new("assets").join(path))
+/// };
+/// let reader = UrlResourceReader::new("https://example.com/maps", fetch);
+/// let mut loader = Loader::with_cache_and_reader(DefaultResourceCache::new(), reader);
+/// let map = loader.load_tmx_map("tiled_base64_external.tmx")?;
+/// assert_eq!(map.tilesets()[0].name, "tilesheet");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UrlResourceReader<F> {
+    base_url: String,
+    fetch: F,
+}
+
+impl<F: Fetch> UrlResourceReader<F> {
+    /// Creates a reader that resolves paths relative to `base_url` and downloads them using
+    /// `fetch`.
+    pub fn new(base_url: impl Into<String>, fetch: F) -> Self {
+        let mut base_url = base_url.into();
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        Self { base_url, fetch }
+    }
+
+    /// Returns the URL the files are resolved relative to, which always ends with a slash.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Returns the URL a path is downloaded from, or [`None`] if it points outside of the base
+    /// URL.
+    pub fn url_for(&self, path: &Path) -> Option<String> {
+        let path_str = path.to_str()?;
+        if path_str.contains("://") {
+            return Some(path_str.to_owned());
+        }
+        let mut url = self.base_url.clone();
+        for (index, segment) in normalize_path(path)?.iter().enumerate() {
+            if index > 0 {
+                url.push('/');
+            }
+            percent_encode(segment.to_str()?, &mut url);
+        }
+        Some(url)
+    }
+}
+
+impl<F: Fetch> ResourceReader for UrlResourceReader<F> {
+    type Resource = Cursor<Vec<u8>>;
+    type Error = std::io::Error;
+
+    fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+        let url = self.url_for(path).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is outside of {}", path.display(), self.base_url),
+            )
+        })?;
+        self.fetch.fetch(&url).map(Cursor::new)
+    }
+
+    fn list_files(&mut self, _directory: &Path) -> std::result::Result<Vec<PathBuf>, Self::Error> {
+        // Web servers have no standard way of listing files.
+        Ok(Vec::new())
+    }
+}
+
+/// Appends `segment` to `url`, escaping the characters that aren't allowed in URL paths.
+fn percent_encode(segment: &str, url: &mut String) {
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Draws maps into images in software, such as to make thumbnails or to check the result of
+//! generating a map in tests.
+//!
+//! The visible tile layers, image layers and tile objects of the map are drawn in display order,
+//! with the opacity and tint color of their layers applied. Objects other than tile objects are
+//! considered editor aids and aren't drawn, and parallax factors are ignored, as if the camera was
+//! at the top-left corner of the map. Tile images are drawn at their own size, whatever the
+//! [tile render size](crate::Tileset::tile_render_size) of their tileset.
+//!
+//! All orientations are supported. The image covers the tiles of the map from `(0, 0)` to its
+//! [`width`](Map::width) and [`height`](Map::height): For infinite maps, that is the area Tiled
+//! shows by default rather than the area covered by their chunks.
+//!
+//! Only available with the `render` feature.
+//!
+//! ## Example
+//! ```
+//! # fn main() -> tiled::Result<()> {
+//! use tiled::render::{rasterize, RenderOptions};
+//!
+//! let map = tiled::Loader::new().load_tmx_map("assets/tiled_base64_external.tmx")?;
+//! let image = rasterize(&map, &RenderOptions::default())?;
+//! assert_eq!(image.dimensions(), (map.width * map.tile_width, map.height * map.tile_height));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use image_crate::{imageops, Rgba, RgbaImage};
+
+use crate::{
+    Color, FlattenedLayer, Image, LayerType, Map, ObjectShape, ObjectTransform, Orientation,
+    ResourceReader, Result, TileId, TileTransform, Tileset,
+};
+
+/// Options controlling how [`rasterize()`] draws a map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Whether to fill the image with the [background color](Map::background_color) of the map
+    /// before drawing it, if it has one. Enabled by default.
+    pub draw_background: bool,
+    /// Whether to also draw the layers and objects that are hidden, including the layers inside
+    /// hidden group layers. Disabled by default.
+    pub draw_hidden_layers: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            draw_background: true,
+            draw_hidden_layers: false,
+        }
+    }
+}
+
+/// Draws a map into a new image, reading the images it uses from the filesystem. See the
+/// [module documentation](self) for details.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub fn rasterize(map: &Map, options: &RenderOptions) -> Result<RgbaImage> {
+    rasterize_with_reader(map, &mut crate::FilesystemResourceReader, options)
+}
+
+/// Draws a map into a new image, reading the images it uses with `reader`. See the
+/// [module documentation](self) for details.
+pub fn rasterize_with_reader(
+    map: &Map,
+    reader: &mut impl ResourceReader,
+    options: &RenderOptions,
+) -> Result<RgbaImage> {
+    let (width, height) = map_size(map);
+    let mut renderer = Renderer {
+        map,
+        reader,
+        canvas: RgbaImage::new(width, height),
+        sheets: HashMap::new(),
+        tiles: HashMap::new(),
+    };
+    if let (true, Some(color)) = (options.draw_background, map.background_color) {
+        let color = Rgba([color.red, color.green, color.blue, color.alpha]);
+        renderer
+            .canvas
+            .pixels_mut()
+            .for_each(|pixel| *pixel = color);
+    }
+    for layer in map.flattened_layers() {
+        if layer.visible || options.draw_hidden_layers {
+            renderer.draw_layer(&layer, options)?;
+        }
+    }
+    Ok(renderer.canvas)
+}
+
+/// Returns the size in pixels of the area covered by the tiles of a map.
+fn map_size(map: &Map) -> (u32, u32) {
+    let (tile_width, tile_height) = (map.tile_width, map.tile_height);
+    match map.orientation {
+        Orientation::Orthogonal => (map.width * tile_width, map.height * tile_height),
+        Orientation::Isometric => {
+            let size = map.width + map.height;
+            (size * tile_width / 2, size * tile_height / 2)
+        }
+        Orientation::Hexagonal | Orientation::Staggered => {
+            let (mut width, mut height) = (0.0f32, 0.0f32);
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    let (px, py) = map.project((x as f32, y as f32));
+                    width = width.max(px + tile_width as f32);
+                    height = height.max(py + tile_height as f32);
+                }
+            }
+            (width.ceil() as u32, height.ceil() as u32)
+        }
+    }
+}
+
+struct Renderer<'a, R: ResourceReader> {
+    map: &'a Map,
+    reader: &'a mut R,
+    canvas: RgbaImage,
+    /// The decoded images of the tilesets based on a single image, by address.
+    sheets: HashMap<*const Tileset, RgbaImage>,
+    /// The decoded images of the tiles drawn so far, by address of their tileset.
+    tiles: HashMap<(*const Tileset, TileId), Option<RgbaImage>>,
+}
+
+impl<R: ResourceReader> Renderer<'_, R> {
+    fn draw_layer(&mut self, layer: &FlattenedLayer, options: &RenderOptions) -> Result<()> {
+        let paint = Paint {
+            opacity: layer.opacity,
+            tint: layer.tint_color,
+        };
+        match layer.layer.layer_type() {
+            LayerType::Tiles(tiles) => {
+                let (tile_width, tile_height) = (self.map.tile_width, self.map.tile_height);
+                for ((x, y), tile) in tiles.tiles_in_rect(0, 0, self.map.width, self.map.height) {
+                    let tileset = tile.get_tileset();
+                    let pixels = match self.tile_pixels(tileset, tile.id())? {
+                        Some(pixels) => transformed(pixels, tile.transform()),
+                        None => continue,
+                    };
+                    // Tile images are aligned to the bottom-left corner of their cell.
+                    let (left, bottom) = match self.map.orientation {
+                        Orientation::Isometric => {
+                            let (x, y) = self.map.iso_to_screen(x as f32, y as f32);
+                            (x - tile_width as f32 / 2.0, y + tile_height as f32)
+                        }
+                        _ => {
+                            let (x, y) = self.map.project((x as f32, y as f32));
+                            (x, y + tile_height as f32)
+                        }
+                    };
+                    let x = left + tileset.offset_x as f32 + layer.offset_x;
+                    let y =
+                        bottom - pixels.height() as f32 + tileset.offset_y as f32 + layer.offset_y;
+                    paint.draw(
+                        &mut self.canvas,
+                        &pixels,
+                        x.round() as i64,
+                        y.round() as i64,
+                    );
+                }
+            }
+            LayerType::Objects(objects) => {
+                for object in objects.objects() {
+                    let (tile, (width, height)) = match (object.get_tile(), &object.shape) {
+                        (Some(tile), ObjectShape::Rect { width, height }) => {
+                            (tile, (*width, *height))
+                        }
+                        _ => continue,
+                    };
+                    if !object.visible && !options.draw_hidden_layers {
+                        continue;
+                    }
+                    let tileset = tile.get_tileset();
+                    let pixels = match self.tile_pixels(tileset, tile.id())? {
+                        Some(pixels) => transformed(pixels, tile.transform()),
+                        None => continue,
+                    };
+                    let mut transform = object.world_transform(layer);
+                    if self.map.orientation == Orientation::Isometric {
+                        // Object positions are given along the axes of the map in this case.
+                        let unit = self.map.tile_height as f32;
+                        let (x, y) = self.map.iso_to_screen(
+                            (transform.x - layer.offset_x) / unit,
+                            (transform.y - layer.offset_y) / unit,
+                        );
+                        transform.x = x + layer.offset_x;
+                        transform.y = y + layer.offset_y;
+                    }
+                    transform.x += tileset.offset_x as f32;
+                    transform.y += tileset.offset_y as f32;
+                    paint.draw_transformed(&mut self.canvas, &pixels, &transform, width, height);
+                }
+            }
+            LayerType::Image(image_layer) => {
+                let image = match &image_layer.image {
+                    Some(image) => image,
+                    None => return Ok(()),
+                };
+                let pixels = image.load_pixels(self.reader)?;
+                let (width, height) = (pixels.width() as i64, pixels.height() as i64);
+                if width == 0 || height == 0 {
+                    return Ok(());
+                }
+                let (x, y) = (layer.offset_x.round() as i64, layer.offset_y.round() as i64);
+                let xs = repeated(x, width, self.canvas.width(), image_layer.repeat_x);
+                let ys = repeated(y, height, self.canvas.height(), image_layer.repeat_y);
+                for &y in &ys {
+                    for &x in &xs {
+                        paint.draw(&mut self.canvas, &pixels, x, y);
+                    }
+                }
+            }
+            LayerType::Group(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the image of a tile, decoding it if it hasn't been yet.
+    fn tile_pixels(&mut self, tileset: &Tileset, id: TileId) -> Result<Option<&RgbaImage>> {
+        let key = (tileset as *const Tileset, id);
+        if !self.tiles.contains_key(&key) {
+            let tile_image = tileset.get_tile(id).and_then(|tile| tile.image.clone());
+            let pixels = match (tile_image, &tileset.image, tileset.tile_image_rect(id)) {
+                (Some(image), _, _) => Some(image.load_pixels(self.reader)?),
+                (None, Some(image), Some((x, y, width, height))) => {
+                    let sheet = self.sheet(tileset, image)?;
+                    Some(imageops::crop_imm(sheet, x, y, width, height).to_image())
+                }
+                _ => None,
+            };
+            self.tiles.insert(key, pixels);
+        }
+        Ok(self.tiles[&key].as_ref())
+    }
+
+    /// Returns the image of a tileset based on a single image, decoding it if it hasn't been yet.
+    fn sheet(&mut self, tileset: &Tileset, image: &Image) -> Result<&RgbaImage> {
+        let key = tileset as *const Tileset;
+        if !self.sheets.contains_key(&key) {
+            let pixels = image.load_pixels(self.reader)?;
+            self.sheets.insert(key, pixels);
+        }
+        Ok(&self.sheets[&key])
+    }
+}
+
+/// Returns the positions along an axis of the canvas, which is `extent` pixels long, at which an
+/// image `size` pixels long placed at `start` is drawn.
+fn repeated(start: i64, size: i64, extent: u32, repeat: bool) -> Vec<i64> {
+    if repeat {
+        // Start from the copy overlapping the edge of the canvas.
+        let first = start.rem_euclid(size) - size;
+        (first..extent as i64).step_by(size as usize).collect()
+    } else {
+        vec![start]
+    }
+}
+
+/// Mirrors and rotates a tile image.
+fn transformed(pixels: &RgbaImage, transform: TileTransform) -> RgbaImage {
+    let pixels = if transform.flip_h {
+        imageops::flip_horizontal(pixels)
+    } else {
+        pixels.clone()
+    };
+    match transform.rotation {
+        90 => imageops::rotate90(&pixels),
+        180 => imageops::rotate180(&pixels),
+        270 => imageops::rotate270(&pixels),
+        _ => pixels,
+    }
+}
+
+/// The opacity and tint color a layer is drawn with.
+struct Paint {
+    opacity: f32,
+    tint: Option<Color>,
+}
+
+impl Paint {
+    /// Draws an image over the canvas with its top-left corner at the position given.
+    fn draw(&self, canvas: &mut RgbaImage, pixels: &RgbaImage, x: i64, y: i64) {
+        for (px, py, pixel) in pixels.enumerate_pixels() {
+            let (cx, cy) = (x + px as i64, y + py as i64);
+            if (0..canvas.width() as i64).contains(&cx) && (0..canvas.height() as i64).contains(&cy)
+            {
+                self.blend(canvas.get_pixel_mut(cx as u32, cy as u32), *pixel);
+            }
+        }
+    }
+
+    /// Draws an image over the canvas, stretched to fill a box of the size given placed by
+    /// `transform`.
+    fn draw_transformed(
+        &self,
+        canvas: &mut RgbaImage,
+        pixels: &RgbaImage,
+        transform: &ObjectTransform,
+        width: f32,
+        height: f32,
+    ) {
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        let corners = transform.corners(width, height);
+        let (mut x0, mut y0, mut x1, mut y1) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for &(x, y) in corners.iter() {
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+        }
+        let x0 = (x0.floor().max(0.0) as u32).min(canvas.width());
+        let y0 = (y0.floor().max(0.0) as u32).min(canvas.height());
+        let x1 = (x1.ceil().max(0.0) as u32).min(canvas.width());
+        let y1 = (y1.ceil().max(0.0) as u32).min(canvas.height());
+
+        let (sin, cos) = transform.rotation.to_radians().sin_cos();
+        let scale_x = pixels.width() as f32 / width;
+        let scale_y = pixels.height() as f32 / height;
+        for cy in y0..y1 {
+            for cx in x0..x1 {
+                // Sample the image at the center of the pixel, undoing the rotation.
+                let (dx, dy) = (cx as f32 + 0.5 - transform.x, cy as f32 + 0.5 - transform.y);
+                let local_x = dx * cos + dy * sin + transform.origin_x;
+                let local_y = -dx * sin + dy * cos + transform.origin_y;
+                if (0.0..width).contains(&local_x) && (0.0..height).contains(&local_y) {
+                    let px = ((local_x * scale_x) as u32).min(pixels.width() - 1);
+                    let py = ((local_y * scale_y) as u32).min(pixels.height() - 1);
+                    self.blend(canvas.get_pixel_mut(cx, cy), *pixels.get_pixel(px, py));
+                }
+            }
+        }
+    }
+
+    /// Blends a pixel over another, applying the opacity and tint color.
+    fn blend(&self, dst: &mut Rgba<u8>, src: Rgba<u8>) {
+        let channel = |value: u8| value as f32 / 255.0;
+        let tint = self.tint.map_or([1.0; 4], |tint| {
+            [
+                channel(tint.red),
+                channel(tint.green),
+                channel(tint.blue),
+                channel(tint.alpha),
+            ]
+        });
+        let src_alpha = channel(src[3]) * tint[3] * self.opacity;
+        if src_alpha <= 0.0 {
+            return;
+        }
+        let dst_alpha = channel(dst[3]);
+        let alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+        for i in 0..3 {
+            let src_value = channel(src[i]) * tint[i];
+            let value =
+                (src_value * src_alpha + channel(dst[i]) * dst_alpha * (1.0 - src_alpha)) / alpha;
+            dst[i] = (value * 255.0).round() as u8;
+        }
+        dst[3] = (alpha * 255.0).round() as u8;
+    }
+}
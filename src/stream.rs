@@ -0,0 +1,81 @@
+use std::{error::Error as StdError, future::poll_fn, path::Path, pin::pin};
+
+use futures_core::Stream;
+
+use crate::{Error, Loader, Map, ResourceCache, ResourceReader, Result, Tileset};
+
+impl<Cache: ResourceCache, Reader: ResourceReader> Loader<Cache, Reader> {
+    /// Parses a Tiled map from an asynchronous stream of bytes, treating it as if it had been read
+    /// from `virtual_path`. Requires the `async` feature.
+    ///
+    /// The XML parser is synchronous, so the whole stream is read into memory before the map is
+    /// parsed with [`Loader::load_tmx_map_from_reader`]. External tilesets, templates and images are
+    /// still loaded through the [`ResourceReader`] of the loader. An error returned by the stream
+    /// is reported as an [`Error::ResourceLoadingError`] for `virtual_path`.
+    ///
+    /// Any stream of byte chunks can be used, such as the body of an HTTP response. Types
+    /// implementing `AsyncRead` can be turned into one with `tokio_util::io::ReaderStream`, or
+    /// read into a buffer with `futures::AsyncReadExt::read_to_end` and given to
+    /// [`Loader::load_tmx_map_from_reader`] instead.
+    ///
+    /// ## Example
+    /// ```ignore
+    /// let response = reqwest::get("https://example.com/maps/level.tmx").await?;
+    /// let map = tiled::Loader::new()
+    ///     .load_tmx_map_from_stream(response.bytes_stream(), "maps/level.tmx")
+    ///     .await?;
+    /// ```
+    pub async fn load_tmx_map_from_stream<B, E>(
+        &mut self,
+        stream: impl Stream<Item = std::result::Result<B, E>>,
+        virtual_path: impl AsRef<Path>,
+    ) -> Result<Map>
+    where
+        B: AsRef<[u8]>,
+        E: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        let data = read_stream(stream, virtual_path.as_ref()).await?;
+        self.load_tmx_map_from_reader(&data[..], virtual_path)
+    }
+
+    /// Parses a Tiled tileset from an asynchronous stream of bytes, treating it as if it had been
+    /// read from `virtual_path`. Requires the `async` feature. See
+    /// [`Loader::load_tmx_map_from_stream`] for details.
+    ///
+    /// ## Note
+    /// This function will **not** cache the tileset inside the internal [`ResourceCache`], since
+    /// in this context it is not an intermediate object.
+    pub async fn load_tsx_tileset_from_stream<B, E>(
+        &mut self,
+        stream: impl Stream<Item = std::result::Result<B, E>>,
+        virtual_path: impl AsRef<Path>,
+    ) -> Result<Tileset>
+    where
+        B: AsRef<[u8]>,
+        E: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        let data = read_stream(stream, virtual_path.as_ref()).await?;
+        self.load_tsx_tileset_from_reader(&data[..], virtual_path)
+    }
+}
+
+/// Reads all the chunks of a stream into a single buffer.
+async fn read_stream<B, E>(
+    stream: impl Stream<Item = std::result::Result<B, E>>,
+    path: &Path,
+) -> Result<Vec<u8>>
+where
+    B: AsRef<[u8]>,
+    E: Into<Box<dyn StdError + Send + Sync>>,
+{
+    let mut stream = pin!(stream);
+    let mut data = Vec::new();
+    while let Some(chunk) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+        let chunk = chunk.map_err(|err| Error::ResourceLoadingError {
+            path: path.to_owned(),
+            err: err.into(),
+        })?;
+        data.extend_from_slice(chunk.as_ref());
+    }
+    Ok(data)
+}
@@ -1,12 +1,12 @@
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use xml::EventReader;
-use xml::{attribute::OwnedAttribute, reader::XmlEvent};
+use xml::attribute::OwnedAttribute;
 
 use crate::{
-    util::*, EmbeddedParseResultType, Error, MapTilesetGid, ObjectData, ResourceCache,
-    ResourceReader, Result, Tileset,
+    parse::LoadContext, util::*, EmbeddedParseResultType, Error, MapTilesetGid, ObjectData,
+    ResourceCache, ResourceReader, Result, Tileset,
 };
 
 /// A template, consisting of an object and a tileset
@@ -14,79 +14,97 @@ use crate::{
 /// Templates define a tileset and object data to use for an object that can be shared between multiple objects and
 /// maps.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Template {
     /// The tileset this template contains a reference to
     pub tileset: Option<Arc<Tileset>>,
+    /// The path of the external tileset file the template refers to, if it has a tileset and it
+    /// isn't embedded. Used when writing the template.
+    pub tileset_source: Option<PathBuf>,
     /// The object data for this template
     pub object: ObjectData,
 }
 
 impl Template {
+    /// Writes this template to a new TX file at the given path, replacing it if it exists.
+    ///
+    /// The path of the template's tileset, if external, is written relative to the directory the
+    /// TX file is written to.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{ObjectData, ObjectShape, Template};
+    ///
+    /// let mut object = ObjectData::new_shape(0, 0.0, 0.0, ObjectShape::Point(0.0, 0.0));
+    /// object.name = "spawn".to_owned();
+    /// let template = Template {
+    ///     tileset: None,
+    ///     tileset_source: None,
+    ///     object,
+    /// };
+    /// template.write_to_tx("assets/templates/spawn.tx")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_to_tx(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path).map_err(|err| Error::ResourceWritingError {
+            path: path.to_owned(),
+            err,
+        })?;
+        self.write_tx(std::io::BufWriter::new(file), path)
+    }
+
+    /// Writes this template in the TX format to an arbitrary writer.
+    ///
+    /// `path` is the path the resulting file is meant to be stored at, and is only used to make the
+    /// path of the template's tileset relative to it.
+    pub fn write_tx(&self, writer: impl Write, path: impl AsRef<Path>) -> Result<()> {
+        crate::write::xml::write_template(self, writer, path.as_ref())
+    }
+
     pub(crate) fn parse_template(
         path: &Path,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<Arc<Template>> {
         // Open the template file
-        let file = reader
+        let file = ctx
+            .reader
             .read_from(path)
             .map_err(|err| Error::ResourceLoadingError {
                 path: path.to_owned(),
                 err: Box::new(err),
             })?;
 
-        let mut template_parser = EventReader::new(file);
-        loop {
-            match template_parser.next().map_err(Error::XmlDecodingError)? {
-                XmlEvent::StartElement {
-                    name,
-                    attributes: _,
-                    ..
-                } if name.local_name == "template" => {
-                    let template = Self::parse_external_template(
-                        &mut template_parser.into_iter(),
-                        path,
-                        reader,
-                        cache,
-                    )?;
-                    return Ok(template);
-                }
-                XmlEvent::EndDocument => {
-                    return Err(Error::PrematureEnd(
-                        "Template Document ended before template element was parsed".to_string(),
-                    ))
-                }
-                _ => {}
-            }
-        }
+        let diagnostics = ctx.diagnostics;
+
+        crate::parse::xml::parse_document(file, path, "template", diagnostics, |events, _| {
+            Self::parse_external_template(events, path, ctx)
+        })
     }
 
     fn parse_external_template(
         parser: &mut impl Iterator<Item = XmlEventResult>,
         template_path: &Path,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<Arc<Template>> {
         let mut object = Option::None;
         let mut tileset = None;
+        let mut tileset_source = None;
         let mut tileset_gid: Vec<MapTilesetGid> = vec![];
 
         parse_tag!(parser, "template", {
             "object" => |attrs| {
-                object = Some(ObjectData::new(parser, attrs, Some(&tileset_gid), tileset.clone(), template_path.parent().ok_or(Error::PathIsNotFile)?, reader, cache)?);
+                object = Some(ObjectData::new(parser, attrs, Some(&tileset_gid), tileset.clone(), template_path.parent().ok_or(Error::PathIsNotFile)?, ctx)?);
                 Ok(())
             },
             "tileset" => |attrs: Vec<OwnedAttribute>| {
-                let res = Tileset::parse_xml_in_map(parser, &attrs, template_path, reader, cache)?;
+                let res = Tileset::parse_xml_in_map(parser, &attrs, template_path, ctx)?;
                 match res.result_type {
                     EmbeddedParseResultType::ExternalReference { tileset_path } => {
-                        tileset = Some(if let Some(ts) = cache.get_tileset(&tileset_path) {
-                            ts
-                        } else {
-                            let tileset = Arc::new(crate::parse::xml::parse_tileset(&tileset_path, reader, cache)?);
-                            cache.insert_tileset(tileset_path.clone(), tileset.clone());
-                            tileset
-                        });
+                        tileset = Some(crate::parse::load_external_tileset(&tileset_path, ctx)?);
+                        tileset_source = Some(tileset_path);
                     }
                     EmbeddedParseResultType::Embedded { tileset: embedded_tileset } => {
                         tileset = Some(Arc::new(embedded_tileset));
@@ -102,6 +120,10 @@ impl Template {
 
         let object = object.ok_or(Error::TemplateHasNoObject)?;
 
-        Ok(Arc::new(Template { tileset, object }))
+        Ok(Arc::new(Template {
+            tileset,
+            tileset_source,
+            object,
+        }))
     }
 }
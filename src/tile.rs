@@ -1,22 +1,25 @@
-use std::{collections::HashMap, path::Path};
+use std::{collections::HashMap, io::Write, path::Path};
 
-use xml::attribute::OwnedAttribute;
+use xml::{attribute::OwnedAttribute, EventWriter};
 
 use crate::{
-    animation::{parse_animation, Frame},
+    animation::{parse_animation, write_animation, Frame},
     error::Error,
     image::Image,
     layers::ObjectLayerData,
-    properties::{parse_properties, Properties},
-    util::{get_attrs, parse_tag, XmlEventResult},
-    ResourceCache, ResourceReader, Result, Tileset,
+    objects::{ObjectData, ResolvedShape},
+    parse::LoadContext,
+    properties::{parse_properties, write_properties, Properties},
+    util::{get_attrs, parse_tag, write_end_element, XmlEventResult},
+    ResourceCache, ResourceReader, Result, Tileset, XmlExtensions,
 };
 
 /// A tile ID, local to a tileset.
 pub type TileId = u32;
 
 /// Raw data belonging to a tile.
-#[derive(Debug, PartialEq, Clone, Default)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TileData {
     /// The image of the tile. Only set when the tile is part of an "image collection" tileset.
     pub image: Option<Image>,
@@ -26,10 +29,29 @@ pub struct TileData {
     pub collision: Option<ObjectLayerData>,
     /// The animation frames of this tile.
     pub animation: Option<Vec<Frame>>,
-    /// The type of this tile.
+    /// The class of this tile, which is arbitrarily set by the user. Read from the `type` attribute
+    /// of the tile, or `class` in files saved by Tiled 1.9 and later.
     pub user_type: Option<String>,
     /// The probability of this tile.
     pub probability: f32,
+    /// The elements and attributes of the tile's `<tile>` element that aren't part of the
+    /// format, if the loader [preserves them](crate::Loader::set_preserve_extensions()).
+    pub extensions: XmlExtensions,
+}
+
+impl Default for TileData {
+    fn default() -> Self {
+        Self {
+            image: None,
+            properties: Properties::default(),
+            collision: None,
+            animation: None,
+            user_type: None,
+            // Same as when the attribute is missing.
+            probability: 1.0,
+            extensions: XmlExtensions::default(),
+        }
+    }
 }
 
 /// Points to a tile belonging to a tileset.
@@ -60,12 +82,22 @@ impl<'tileset> std::ops::Deref for Tile<'tileset> {
 }
 
 impl TileData {
+    /// Returns an iterator over the shapes of the tile's collision objects, in tile-local
+    /// coordinates (relative to the tile's top-left corner).
+    ///
+    /// Text objects, which have no geometric shape, are skipped.
+    pub fn collision_shapes(&self) -> impl Iterator<Item = ResolvedShape> + '_ {
+        self.collision
+            .iter()
+            .flat_map(|collision| collision.object_data())
+            .filter_map(ObjectData::resolved_shape)
+    }
+
     pub(crate) fn new(
         parser: &mut impl Iterator<Item = XmlEventResult>,
         attrs: Vec<OwnedAttribute>,
         path_relative_to: &Path,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<(TileId, TileData)> {
         let ((user_type, user_class, probability), id) = get_attrs!(
             for v in attrs {
@@ -87,13 +119,13 @@ impl TileData {
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, ctx.project, &ctx.interner, ctx.diagnostics)?;
                 Ok(())
             },
             "objectgroup" => |attrs| {
                 // Tile objects are not allowed within tile object groups, so we can pass None as the
                 // tilesets vector
-                objectgroup = Some(ObjectLayerData::new(parser, attrs, None, None, path_relative_to, reader, cache)?.0);
+                objectgroup = Some(ObjectLayerData::new(parser, attrs, None, None, path_relative_to, ctx)?.0);
                 Ok(())
             },
             "animation" => |_| {
@@ -101,6 +133,7 @@ impl TileData {
                 Ok(())
             },
         });
+        let extensions = ctx.diagnostics.take_extensions();
         Ok((
             id,
             TileData {
@@ -110,7 +143,92 @@ impl TileData {
                 animation,
                 user_type,
                 probability: probability.unwrap_or(1.0),
+                extensions,
             },
         ))
     }
 }
+
+#[cfg(feature = "json")]
+impl TileData {
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        path_relative_to: &Path,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<(TileId, TileData)> {
+        use crate::parse::json::{as_u32, JsonObjectExt};
+
+        let id = object.req("id", as_u32)?;
+        let user_type = match object.opt_str("type")? {
+            Some(user_type) => Some(user_type),
+            None => object.opt_str("class")?,
+        };
+        let collision = object
+            .opt_object("objectgroup")?
+            .map(|objectgroup| {
+                // Tile objects are not allowed within tile object groups, so we can pass None as the
+                // tilesets vector
+                ObjectLayerData::parse_json(objectgroup, None, None, path_relative_to, ctx)
+            })
+            .transpose()?;
+        let animation = object
+            .opt_array("animation")?
+            .map(|frames| crate::animation::parse_json_animation(frames))
+            .transpose()?;
+
+        Ok((
+            id,
+            TileData {
+                image: Image::parse_json(object, path_relative_to)?,
+                properties: crate::properties::parse_json_properties(
+                    object,
+                    ctx.project,
+                    &ctx.interner,
+                    ctx.diagnostics,
+                )?,
+                collision,
+                animation,
+                user_type: user_type.map(str::to_owned),
+                probability: object.opt_f32("probability")?.unwrap_or(1.0),
+                extensions: XmlExtensions::default(),
+            },
+        ))
+    }
+}
+
+impl TileData {
+    /// Writes this tile as a `<tile>` element, unless it doesn't hold any data worth writing.
+    pub(crate) fn write_xml(
+        &self,
+        writer: &mut EventWriter<impl Write>,
+        id: TileId,
+        path_relative_to: &Path,
+    ) -> Result<()> {
+        if *self == TileData::default() {
+            return Ok(());
+        }
+
+        let mut attrs = vec![("id", id.to_string())];
+        if let Some(user_type) = &self.user_type {
+            attrs.push(("type", user_type.clone()));
+        }
+        if self.probability != 1.0 {
+            attrs.push(("probability", self.probability.to_string()));
+        }
+        self.extensions
+            .write_start_element(writer, "tile", &attrs)?;
+        write_properties(writer, &self.properties)?;
+        if let Some(image) = &self.image {
+            image.write_xml(writer, path_relative_to)?;
+        }
+        if let Some(collision) = &self.collision {
+            // Tile collision objects cannot reference tiles, so no GIDs are needed.
+            collision.write_xml(writer, Vec::new(), &[], path_relative_to, |_| Ok(()))?;
+        }
+        if let Some(animation) = &self.animation {
+            write_animation(writer, animation)?;
+        }
+        self.extensions.write_elements(writer)?;
+        write_end_element(writer)
+    }
+}
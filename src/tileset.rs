@@ -1,13 +1,19 @@
 use std::collections::HashMap;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use xml::attribute::OwnedAttribute;
+use xml::EventWriter;
 
 use crate::error::{Error, Result};
-use crate::image::Image;
-use crate::properties::{parse_properties, Properties};
+use crate::image::{Image, ImageHook};
+use crate::parse::LoadContext;
+use crate::properties::{parse_properties, write_properties, Properties, PropertyValue};
 use crate::tile::TileData;
-use crate::{util::*, Gid, ResourceCache, ResourceReader, Tile, TileId};
+use crate::write::SourceInfo;
+use crate::{
+    util::*, Gid, Orientation, ResourceCache, ResourceReader, Tile, TileId, Version, XmlExtensions,
+};
 
 mod wangset;
 pub use wangset::*;
@@ -16,6 +22,7 @@ pub use wangset::*;
 ///
 /// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tileset).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tileset {
     /// The name of the tileset, set by the user.
     pub name: String,
@@ -42,10 +49,30 @@ pub struct Tileset {
     /// calculated using [image](Self::image) width, [tile width](Self::tile_width),
     /// [spacing](Self::spacing) and [margin](Self::margin).
     pub columns: u32,
-    /// The x-offset to be used when drawing tiles of this tileset.
+    /// The horizontal offset in pixels to be used when drawing tiles of this tileset, read from its
+    /// `<tileoffset>` element. Positive values move tiles to the right.
+    ///
+    /// Tiles whose size differs from the map's are aligned to the bottom-left corner of their cell
+    /// before applying the offset.
     pub offset_x: i32,
-    /// The y-offset to be used when drawing tiles of this tileset.
+    /// The vertical offset in pixels to be used when drawing tiles of this tileset, read from its
+    /// `<tileoffset>` element. Positive values move tiles down.
     pub offset_y: i32,
+    /// The grid used to place tiles of this tileset when they are drawn, if set by its `<grid>`
+    /// element.
+    ///
+    /// This is needed to correctly render image collection tilesets meant for isometric maps.
+    pub grid: Option<TilesetGrid>,
+    /// The ways tiles of this tileset may be transformed when painting them, such as by Wang
+    /// autotiling.
+    pub transformations: TileTransformations,
+    /// The size tiles of this tileset are drawn at.
+    pub tile_render_size: TileRenderSize,
+    /// How tiles of this tileset are fit within the size they're drawn at, when it differs from
+    /// theirs.
+    pub fill_mode: FillMode,
+    /// The point of tile objects using this tileset that their position refers to.
+    pub object_alignment: ObjectAlignment,
 
     /// A tileset can either:
     /// * have a single spritesheet `image` in `tileset` ("regular" tileset);
@@ -67,6 +94,200 @@ pub struct Tileset {
 
     /// The custom tileset type, arbitrarily set by the user.
     pub user_type: Option<String>,
+
+    /// The version of the TMX format the tileset was saved in, read from its `version` attribute,
+    /// or the version of its map if it is embedded in one. `None` for tilesets built in code.
+    pub version: Option<Version>,
+
+    /// The version of Tiled the tileset was saved with, read from its `tiledversion` attribute, or
+    /// that of its map if it is embedded in one. `None` if it isn't a version number.
+    pub tiled_version: Option<Version>,
+
+    /// The elements and attributes of the tileset's `<tileset>` element that aren't part of the
+    /// format, if the loader [preserves them](crate::Loader::set_preserve_extensions()).
+    pub extensions: XmlExtensions,
+
+    /// The directory of the file the tileset was loaded from, which the relative paths it
+    /// contains are relative to.
+    root_path: PathBuf,
+    /// The path of the file the tileset was loaded from, if it is an external tileset, which maps
+    /// refer to when they are written.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: SourceInfo<PathBuf>,
+}
+
+/// A builder for creating [`Tileset`]s in code rather than loading them from files.
+///
+/// ## Example
+/// ```
+/// use std::path::PathBuf;
+///
+/// use tiled::{Image, TilesetBuilder};
+///
+/// let tileset = TilesetBuilder::new("terrain", 32, 32)
+///     .spacing(2)
+///     .image(Image {
+///         source: PathBuf::from("terrain.png"),
+///         width: 134,
+///         height: 66,
+///         transparent_colour: None,
+///         handle: None,
+///     })
+///     .build();
+///
+/// assert_eq!(tileset.columns, 4);
+/// assert_eq!(tileset.tilecount, 8);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TilesetBuilder {
+    tileset: Tileset,
+    columns: Option<u32>,
+}
+
+impl TilesetBuilder {
+    /// Creates a builder for an image collection tileset with the name and (maximum) tile size in
+    /// pixels given, and no tiles.
+    pub fn new(name: impl Into<String>, tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            tileset: Tileset {
+                name: name.into(),
+                tile_width,
+                tile_height,
+                spacing: 0,
+                margin: 0,
+                tilecount: 0,
+                columns: 0,
+                offset_x: 0,
+                offset_y: 0,
+                grid: None,
+                transformations: TileTransformations::default(),
+                tile_render_size: TileRenderSize::default(),
+                fill_mode: FillMode::default(),
+                object_alignment: ObjectAlignment::default(),
+                image: None,
+                tiles: HashMap::new(),
+                wang_sets: Vec::new(),
+                properties: Properties::new(),
+                user_type: None,
+                version: None,
+                tiled_version: None,
+                extensions: XmlExtensions::default(),
+                root_path: PathBuf::new(),
+                source: SourceInfo::default(),
+            },
+            columns: None,
+        }
+    }
+
+    /// Sets the spacing in pixels between the tiles of the tileset image.
+    pub fn spacing(mut self, spacing: u32) -> Self {
+        self.tileset.spacing = spacing;
+        self
+    }
+
+    /// Sets the margin in pixels around the tiles of the tileset image.
+    pub fn margin(mut self, margin: u32) -> Self {
+        self.tileset.margin = margin;
+        self
+    }
+
+    /// Sets the offset in pixels to be used when drawing the tiles of the tileset.
+    pub fn offset(mut self, x: i32, y: i32) -> Self {
+        self.tileset.offset_x = x;
+        self.tileset.offset_y = y;
+        self
+    }
+
+    /// Sets the grid used to place the tiles of the tileset.
+    pub fn grid(mut self, grid: TilesetGrid) -> Self {
+        self.tileset.grid = Some(grid);
+        self
+    }
+
+    /// Sets the ways the tiles of the tileset may be transformed when painting them.
+    pub fn transformations(mut self, transformations: TileTransformations) -> Self {
+        self.tileset.transformations = transformations;
+        self
+    }
+
+    /// Sets the size the tiles of the tileset are drawn at.
+    pub fn tile_render_size(mut self, tile_render_size: TileRenderSize) -> Self {
+        self.tileset.tile_render_size = tile_render_size;
+        self
+    }
+
+    /// Sets how the tiles of the tileset are fit within the size they're drawn at.
+    pub fn fill_mode(mut self, fill_mode: FillMode) -> Self {
+        self.tileset.fill_mode = fill_mode;
+        self
+    }
+
+    /// Sets the point of tile objects using the tileset that their position refers to.
+    pub fn object_alignment(mut self, object_alignment: ObjectAlignment) -> Self {
+        self.tileset.object_alignment = object_alignment;
+        self
+    }
+
+    /// Sets the spritesheet image of the tileset, making it a regular tileset. Its columns and tile
+    /// count are then calculated from the image size, [tile size](Self::new),
+    /// [spacing](Self::spacing) and [margin](Self::margin).
+    pub fn image(mut self, image: Image) -> Self {
+        self.tileset.image = Some(image);
+        self
+    }
+
+    /// Sets the number of tile columns of an image collection tileset. Ignored for regular
+    /// tilesets.
+    pub fn columns(mut self, columns: u32) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Sets the data of the tile with the local id given, replacing the previous one if any.
+    pub fn tile(mut self, id: TileId, tile: TileData) -> Self {
+        self.tileset.tiles.insert(id, tile);
+        self
+    }
+
+    /// Appends a wang set to the tileset.
+    pub fn wang_set(mut self, wang_set: WangSet) -> Self {
+        self.tileset.wang_sets.push(wang_set);
+        self
+    }
+
+    /// Sets a custom property of the tileset, replacing the previous value if any.
+    pub fn property(mut self, name: impl Into<std::sync::Arc<str>>, value: PropertyValue) -> Self {
+        self.tileset.properties.insert(name.into(), value);
+        self
+    }
+
+    /// Sets the type of the tileset.
+    pub fn user_type(mut self, user_type: impl Into<String>) -> Self {
+        self.tileset.user_type = Some(user_type.into());
+        self
+    }
+
+    /// Builds the tileset.
+    ///
+    /// The tile count of an image collection tileset is the amount of tiles added to it.
+    pub fn build(mut self) -> Tileset {
+        let tileset = &mut self.tileset;
+        match &tileset.image {
+            Some(image) => {
+                tileset.columns = ((image.width as u32).saturating_sub(tileset.margin)
+                    + tileset.spacing)
+                    / (tileset.tile_width + tileset.spacing);
+                let rows = ((image.height as u32).saturating_sub(tileset.margin) + tileset.spacing)
+                    / (tileset.tile_height + tileset.spacing);
+                tileset.tilecount = tileset.columns * rows;
+            }
+            None => {
+                tileset.columns = self.columns.unwrap_or(0);
+                tileset.tilecount = tileset.tiles.len() as u32;
+            }
+        }
+        self.tileset
+    }
 }
 
 pub(crate) enum EmbeddedParseResultType {
@@ -89,6 +310,9 @@ struct TilesetProperties {
     user_type: Option<String>,
     tile_width: u32,
     tile_height: u32,
+    tile_render_size: TileRenderSize,
+    fill_mode: FillMode,
+    object_alignment: ObjectAlignment,
     /// The root all non-absolute paths contained within the tileset are relative to.
     root_path: PathBuf,
 }
@@ -100,6 +324,77 @@ impl Tileset {
         self.tiles.get(&id).map(|data| Tile::new(self, data))
     }
 
+    /// Returns the rectangle `(x, y, width, height)` in pixels that the tile with the specified ID
+    /// occupies within the tileset's [image](Self::image), accounting for the tileset's margin,
+    /// spacing and columns.
+    ///
+    /// Returns [`None`] for image collection tilesets, which have no tileset image, and for IDs
+    /// past the tile count.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let tileset = tiled::Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    ///
+    /// // The tileset has 14 columns of 32x32 tiles.
+    /// assert_eq!(tileset.tile_image_rect(15), Some((32, 32, 32, 32)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tile_image_rect(&self, id: TileId) -> Option<(u32, u32, u32, u32)> {
+        if self.image.is_none() || self.columns == 0 || id >= self.tilecount {
+            return None;
+        }
+        let column = id % self.columns;
+        let row = id / self.columns;
+        Some((
+            self.margin + column * (self.tile_width + self.spacing),
+            self.margin + row * (self.tile_height + self.spacing),
+            self.tile_width,
+            self.tile_height,
+        ))
+    }
+
+    /// Reads and decodes the image of the tile with the specified ID using `reader`, with the
+    /// [transparent color](Image::transparent_colour) of the image applied. See
+    /// [`Image::load_pixels()`].
+    ///
+    /// The whole image of the tileset is decoded to extract a single tile from it. To extract
+    /// many tiles, decode it once and crop the [rectangles](Self::tile_image_rect) of the tiles
+    /// instead.
+    ///
+    /// Returns `None` if the tile doesn't exist or has no image.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{FilesystemResourceReader, Loader};
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    /// let pixels = tileset.load_tile_pixels(15, &mut FilesystemResourceReader)?;
+    /// assert_eq!(pixels.unwrap().dimensions(), (32, 32));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn load_tile_pixels(
+        &self,
+        id: TileId,
+        reader: &mut impl ResourceReader,
+    ) -> Result<Option<image_crate::RgbaImage>> {
+        if let Some(image) = self.tiles.get(&id).and_then(|tile| tile.image.as_ref()) {
+            return image.load_pixels(reader).map(Some);
+        }
+        match (&self.image, self.tile_image_rect(id)) {
+            (Some(image), Some((x, y, width, height))) => {
+                let pixels = image.load_pixels(reader)?;
+                let view = image_crate::imageops::crop_imm(&pixels, x, y, width, height);
+                Ok(Some(view.to_image()))
+            }
+            _ => Ok(None),
+        }
+    }
+
     /// Iterates through the tiles from this tileset.
     #[inline]
     pub fn tiles(&self) -> impl ExactSizeIterator<Item = (TileId, Tile)> {
@@ -107,6 +402,164 @@ impl Tileset {
             .iter()
             .map(move |(id, data)| (*id, Tile::new(self, data)))
     }
+
+    /// The directory of the file the tileset was loaded from, which the relative paths it
+    /// contains are relative to. This is the directory of the map or template for embedded
+    /// tilesets, and empty for tilesets created with a [`TilesetBuilder`].
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    /// Returns the path a file property of the tileset, one of its tiles or one of its Wang sets
+    /// refers to, resolved against the tileset's [root path](Self::root_path).
+    ///
+    /// The path is normalized lexically, without accessing the filesystem. Returns [`None`] if the
+    /// value isn't a [`FileValue`](PropertyValue::FileValue) or is empty.
+    pub fn resolve_file_property(&self, value: &PropertyValue) -> Option<PathBuf> {
+        resolve_file_property(&self.root_path, value)
+    }
+}
+
+impl Tileset {
+    /// Writes this tileset to a new TSX file at the given path, replacing it if it exists.
+    ///
+    /// All paths contained within the tileset (such as image sources) are written relative to the
+    /// directory the TSX file is written to.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let mut tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    /// tileset.name = "renamed".to_owned();
+    /// tileset.write_to_tsx("assets/tilesheet_renamed.tsx")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_to_tsx(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path).map_err(|err| Error::ResourceWritingError {
+            path: path.to_owned(),
+            err,
+        })?;
+        self.write_tsx(std::io::BufWriter::new(file), path)
+    }
+
+    /// Writes this tileset in the TSX format to an arbitrary writer.
+    ///
+    /// `path` is the path the resulting file is meant to be stored at, and is only used to make the
+    /// paths contained within the tileset relative to it.
+    pub fn write_tsx(&self, writer: impl Write, path: impl AsRef<Path>) -> Result<()> {
+        crate::write::xml::write_tileset(self, writer, path.as_ref())
+    }
+
+    /// Writes a `<tileset>` element. If `first_gid` is set, the tileset is written as embedded into
+    /// a map or template.
+    pub(crate) fn write_xml(
+        &self,
+        writer: &mut EventWriter<impl Write>,
+        path_relative_to: &Path,
+        first_gid: Option<Gid>,
+    ) -> Result<()> {
+        let mut attrs = Vec::new();
+        if let Some(first_gid) = first_gid {
+            attrs.push(("firstgid", first_gid.0.to_string()));
+        }
+        // Tilesets embedded in maps are saved with the versions of their map.
+        if first_gid.is_none() {
+            if let Some(version) = self.version {
+                attrs.push(("version", version.to_string()));
+            }
+            if let Some(tiled_version) = self.tiled_version {
+                attrs.push(("tiledversion", tiled_version.to_string()));
+            }
+        }
+        attrs.push(("name", self.name.clone()));
+        if let Some(user_type) = &self.user_type {
+            attrs.push(("class", user_type.clone()));
+        }
+        attrs.push(("tilewidth", self.tile_width.to_string()));
+        attrs.push(("tileheight", self.tile_height.to_string()));
+        if self.spacing != 0 {
+            attrs.push(("spacing", self.spacing.to_string()));
+        }
+        if self.margin != 0 {
+            attrs.push(("margin", self.margin.to_string()));
+        }
+        attrs.push(("tilecount", self.tilecount.to_string()));
+        attrs.push(("columns", self.columns.to_string()));
+        if self.object_alignment != ObjectAlignment::default() {
+            attrs.push(("objectalignment", self.object_alignment.as_str().to_owned()));
+        }
+        if self.tile_render_size != TileRenderSize::default() {
+            attrs.push(("tilerendersize", self.tile_render_size.as_str().to_owned()));
+        }
+        if self.fill_mode != FillMode::default() {
+            attrs.push(("fillmode", self.fill_mode.as_str().to_owned()));
+        }
+        self.extensions
+            .write_start_element(writer, "tileset", &attrs)?;
+
+        if self.offset_x != 0 || self.offset_y != 0 {
+            write_empty_element(
+                writer,
+                "tileoffset",
+                &[
+                    ("x", self.offset_x.to_string()),
+                    ("y", self.offset_y.to_string()),
+                ],
+            )?;
+        }
+        if let Some(grid) = &self.grid {
+            write_empty_element(
+                writer,
+                "grid",
+                &[
+                    ("orientation", grid.orientation.as_str().to_owned()),
+                    ("width", grid.width.to_string()),
+                    ("height", grid.height.to_string()),
+                ],
+            )?;
+        }
+        if self.transformations != TileTransformations::default() {
+            let flag = |b: bool| if b { "1" } else { "0" }.to_owned();
+            let transformations = &self.transformations;
+            write_empty_element(
+                writer,
+                "transformations",
+                &[
+                    ("hflip", flag(transformations.flip_h)),
+                    ("vflip", flag(transformations.flip_v)),
+                    ("rotate", flag(transformations.rotate)),
+                    (
+                        "preferuntransformed",
+                        flag(transformations.prefer_untransformed),
+                    ),
+                ],
+            )?;
+        }
+        write_properties(writer, &self.properties)?;
+        if let Some(image) = &self.image {
+            image.write_xml(writer, path_relative_to)?;
+        }
+
+        let mut ids: Vec<&TileId> = self.tiles.keys().collect();
+        ids.sort();
+        for id in ids {
+            self.tiles[id].write_xml(writer, *id, path_relative_to)?;
+        }
+
+        if !self.wang_sets.is_empty() {
+            write_start_element(writer, "wangsets", &[])?;
+            for wang_set in &self.wang_sets {
+                wang_set.write_xml(writer)?;
+            }
+            write_end_element(writer)?;
+        }
+        self.extensions.write_elements(writer)?;
+        write_end_element(writer)
+    }
 }
 
 impl Tileset {
@@ -114,10 +567,9 @@ impl Tileset {
         parser: &mut impl Iterator<Item = XmlEventResult>,
         attrs: &[OwnedAttribute],
         path: &Path, // Template or Map file
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<EmbeddedParseResult> {
-        Tileset::parse_xml_embedded(parser, attrs, path, reader, cache).or_else(|err| {
+        Tileset::parse_xml_embedded(parser, attrs, path, ctx).or_else(|err| {
             if matches!(err, Error::MalformedAttributes(_)) {
                 Tileset::parse_xml_reference(attrs, path)
             } else {
@@ -130,11 +582,11 @@ impl Tileset {
         parser: &mut impl Iterator<Item = XmlEventResult>,
         attrs: &[OwnedAttribute],
         path: &Path, // Template or Map file
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<EmbeddedParseResult> {
         let (
             (spacing, margin, columns, name, user_type, user_class),
+            (tile_render_size, fill_mode, object_alignment),
             (tilecount, first_gid, tile_width, tile_height),
         ) = get_attrs!(
            for v in attrs {
@@ -144,13 +596,16 @@ impl Tileset {
             Some("name") => name = v,
             Some("type") => user_type ?= v.parse(),
             Some("class") => user_class ?= v.parse(),
+            Some("tilerendersize") => tile_render_size = v,
+            Some("fillmode") => fill_mode = v,
+            Some("objectalignment") => object_alignment = v,
 
             "tilecount" => tilecount ?= v.parse::<u32>(),
             "firstgid" => first_gid ?= v.parse::<u32>().map(Gid),
             "tilewidth" => tile_width ?= v.parse::<u32>(),
             "tileheight" => tile_height ?= v.parse::<u32>(),
            }
-           ((spacing, margin, columns, name, user_type, user_class), (tilecount, first_gid, tile_width, tile_height))
+           ((spacing, margin, columns, name, user_type, user_class), (tile_render_size, fill_mode, object_alignment), (tilecount, first_gid, tile_width, tile_height))
         );
 
         let root_path = path.parent().ok_or(Error::PathIsNotFile)?.to_owned();
@@ -167,9 +622,11 @@ impl Tileset {
                 tilecount,
                 tile_height,
                 tile_width,
+                tile_render_size: TileRenderSize::parse(tile_render_size.as_deref())?,
+                fill_mode: FillMode::parse(fill_mode.as_deref())?,
+                object_alignment: ObjectAlignment::parse(object_alignment.as_deref())?,
             },
-            reader,
-            cache,
+            ctx,
         )
         .map(|tileset| EmbeddedParseResult {
             first_gid,
@@ -189,7 +646,7 @@ impl Tileset {
             (first_gid, source)
         );
 
-        let tileset_path = map_path.parent().ok_or(Error::PathIsNotFile)?.join(source);
+        let tileset_path = join_reference(map_path.parent().ok_or(Error::PathIsNotFile)?, &source);
 
         Ok(EmbeddedParseResult {
             first_gid,
@@ -201,11 +658,11 @@ impl Tileset {
         parser: &mut impl Iterator<Item = XmlEventResult>,
         attrs: &[OwnedAttribute],
         path: &Path,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<Tileset> {
         let (
             (spacing, margin, columns, name, user_type, user_class),
+            (tile_render_size, fill_mode, object_alignment, version, tiled_version),
             (tilecount, tile_width, tile_height),
         ) = get_attrs!(
             for v in attrs {
@@ -215,12 +672,17 @@ impl Tileset {
                 Some("name") => name = v,
                 Some("type") => user_type ?= v.parse(),
                 Some("class") => user_class ?= v.parse(),
+                Some("tilerendersize") => tile_render_size = v,
+                Some("fillmode") => fill_mode = v,
+                Some("objectalignment") => object_alignment = v,
+                Some("version") => version ?= v.parse::<Version>(),
+                Some("tiledversion") => tiled_version = v,
 
                 "tilecount" => tilecount ?= v.parse::<u32>(),
                 "tilewidth" => tile_width ?= v.parse::<u32>(),
                 "tileheight" => tile_height ?= v.parse::<u32>(),
             }
-            ((spacing, margin, columns, name, user_type, user_class), (tilecount, tile_width, tile_height))
+            ((spacing, margin, columns, name, user_type, user_class), (tile_render_size, fill_mode, object_alignment, version, tiled_version), (tilecount, tile_width, tile_height))
         );
 
         let root_path = path.parent().ok_or(Error::PathIsNotFile)?.to_owned();
@@ -237,23 +699,54 @@ impl Tileset {
                 tilecount,
                 tile_height,
                 tile_width,
+                tile_render_size: TileRenderSize::parse(tile_render_size.as_deref())?,
+                fill_mode: FillMode::parse(fill_mode.as_deref())?,
+                object_alignment: ObjectAlignment::parse(object_alignment.as_deref())?,
             },
-            reader,
-            cache,
+            ctx,
         )
+        .map(|tileset| Tileset {
+            version,
+            tiled_version: tiled_version.and_then(|v| v.parse().ok()),
+            ..tileset.with_source(path)
+        })
+    }
+
+    /// Records the versions of the map an embedded tileset was loaded from, which it was saved
+    /// with.
+    pub(crate) fn embedded_in(self, version: Version, tiled_version: Option<Version>) -> Self {
+        Tileset {
+            version: Some(version),
+            tiled_version,
+            ..self
+        }
+    }
+
+    /// Records the path of the external tileset file `self` was loaded from.
+    pub(crate) fn with_source(mut self, path: &Path) -> Self {
+        self.source = SourceInfo::new(path.to_owned());
+        self
+    }
+
+    /// The path of the file the tileset was loaded from, if it is an external tileset.
+    pub(crate) fn source_path(&self) -> Option<&Path> {
+        self.source.get().map(PathBuf::as_path)
     }
 
     fn finish_parsing_xml(
         parser: &mut impl Iterator<Item = XmlEventResult>,
         prop: TilesetProperties,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
     ) -> Result<Tileset> {
         let mut image = Option::None;
         let mut tiles = HashMap::with_capacity(prop.tilecount as usize);
         let mut properties = HashMap::new();
         let mut wang_sets = Vec::new();
+        let mut terrains = Vec::new();
+        let mut tile_terrains = HashMap::new();
         let mut offset = (0i32, 0i32);
+        let mut grid = None;
+        let mut transformations = TileTransformations::default();
 
         parse_tag!(parser, "tileset", {
             "image" => |attrs| {
@@ -264,21 +757,46 @@ impl Tileset {
                 offset = parse_tileoffset(attrs)?;
                 Ok(())
             },
-            "properties" => |_| {
-                properties = parse_properties(parser)?;
+            "grid" => |attrs| {
+                grid = Some(TilesetGrid::new(attrs)?);
+                Ok(())
+            },
+            "transformations" => |attrs| {
+                transformations = TileTransformations::new(attrs)?;
                 Ok(())
             },
-            "tile" => |attrs| {
-                let (id, tile) = TileData::new(parser, attrs, &prop.root_path, reader, cache)?;
-                tiles.insert(id, tile);
+            "properties" => |_| {
+                properties = parse_properties(parser, ctx.project, &ctx.interner, ctx.diagnostics)?;
                 Ok(())
             },
+            "tile" => |attrs: Vec<OwnedAttribute>| {
+                let depth = ctx.diagnostics.depth();
+                let tile = wangset::terrain::parse_tile_terrain(&attrs).and_then(|terrain| {
+                    Ok((terrain, TileData::new(parser, attrs, &prop.root_path, ctx)?))
+                });
+                match tile {
+                    Ok((terrain, (id, tile))) => {
+                        if let Some(terrain) = terrain {
+                            tile_terrains.insert(id, terrain);
+                        }
+                        tiles.insert(id, tile);
+                        Ok(())
+                    }
+                    Err(err) => ctx.diagnostics.skip_element(parser, depth, err),
+                }
+            },
             "wangset" => |attrs| {
-                let set = WangSet::new(parser, attrs)?;
+                let set = WangSet::new(parser, attrs, ctx.project)?;
                 wang_sets.push(set);
                 Ok(())
             },
+            "terraintypes" => |_| {
+                terrains = wangset::terrain::parse_terrain_types(parser, ctx.project)?;
+                Ok(())
+            },
         });
+        wang_sets.extend(wangset::terrain::terrain_wang_set(terrains, tile_terrains));
+        let extensions = ctx.diagnostics.take_extensions();
 
         // A tileset is considered an image collection tileset if there is no image attribute (because its tiles do).
         let is_image_collection_tileset = image.is_none();
@@ -296,9 +814,12 @@ impl Tileset {
             .map(Ok)
             .unwrap_or_else(|| Self::calculate_columns(&image, prop.tile_width, margin, spacing))?;
 
-        Ok(Tileset {
+        let mut tileset = Tileset {
             name: prop.name,
             user_type: prop.user_type,
+            version: None,
+            tiled_version: None,
+            extensions,
             tile_width: prop.tile_width,
             tile_height: prop.tile_height,
             spacing,
@@ -306,12 +827,34 @@ impl Tileset {
             columns,
             offset_x: offset.0,
             offset_y: offset.1,
+            grid,
+            transformations,
+            tile_render_size: prop.tile_render_size,
+            fill_mode: prop.fill_mode,
+            object_alignment: prop.object_alignment,
             tilecount: prop.tilecount,
             image,
             tiles,
             wang_sets,
             properties,
-        })
+            root_path: prop.root_path,
+            source: SourceInfo::default(),
+        };
+        if let Some(hook) = ctx.image_hook {
+            tileset.attach_image_handles(hook);
+        }
+        Ok(tileset)
+    }
+
+    /// Attaches the handles returned by `hook` to the image of the tileset and those of its tiles.
+    fn attach_image_handles(&mut self, hook: &ImageHook) {
+        let tile_images = self
+            .tiles
+            .values_mut()
+            .filter_map(|tile| tile.image.as_mut());
+        for image in self.image.iter_mut().chain(tile_images) {
+            image.attach_handle(hook);
+        }
     }
 
     fn calculate_columns(
@@ -331,6 +874,452 @@ impl Tileset {
     }
 }
 
+#[cfg(feature = "json")]
+impl Tileset {
+    pub(crate) fn parse_json_in_map(
+        object: &crate::parse::json::JsonObject,
+        path: &Path, // Template or Map file
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<EmbeddedParseResult> {
+        use crate::parse::json::{as_u32, JsonObjectExt};
+
+        let first_gid = Gid(object.req("firstgid", as_u32)?);
+        let result_type = match object.opt_str("source")? {
+            Some(source) => EmbeddedParseResultType::ExternalReference {
+                tileset_path: join_reference(path.parent().ok_or(Error::PathIsNotFile)?, source),
+            },
+            None => EmbeddedParseResultType::Embedded {
+                tileset: Self::parse_json_external(object, path, ctx)?,
+            },
+        };
+
+        Ok(EmbeddedParseResult {
+            first_gid,
+            result_type,
+        })
+    }
+
+    pub(crate) fn parse_json_external(
+        object: &crate::parse::json::JsonObject,
+        path: &Path,
+        ctx: &mut LoadContext<impl ResourceReader, impl ResourceCache>,
+    ) -> Result<Tileset> {
+        use crate::parse::json::{as_objects, as_u32, as_version, JsonObjectExt};
+
+        let prop = TilesetProperties {
+            spacing: object.opt_u32("spacing")?,
+            margin: object.opt_u32("margin")?,
+            tilecount: object.req("tilecount", as_u32)?,
+            columns: object.opt_u32("columns")?,
+            name: object.opt_str("name")?.unwrap_or_default().to_owned(),
+            user_type: object.opt_str("class")?.map(str::to_owned),
+            tile_width: object.req("tilewidth", as_u32)?,
+            tile_height: object.req("tileheight", as_u32)?,
+            tile_render_size: TileRenderSize::parse(object.opt_str("tilerendersize")?)?,
+            fill_mode: FillMode::parse(object.opt_str("fillmode")?)?,
+            object_alignment: ObjectAlignment::parse(object.opt_str("objectalignment")?)?,
+            root_path: path.parent().ok_or(Error::PathIsNotFile)?.to_owned(),
+        };
+
+        let image = Image::parse_json(object, &prop.root_path)?;
+        let offset = match object.opt_object("tileoffset")? {
+            Some(offset) => (
+                offset.req("x", crate::parse::json::as_i32)?,
+                offset.req("y", crate::parse::json::as_i32)?,
+            ),
+            None => (0, 0),
+        };
+        let grid = object
+            .opt_object("grid")?
+            .map(TilesetGrid::parse_json)
+            .transpose()?;
+        let transformations = object
+            .opt_object("transformations")?
+            .map(TileTransformations::parse_json)
+            .transpose()?
+            .unwrap_or_default();
+
+        let mut tiles = HashMap::with_capacity(prop.tilecount as usize);
+        let mut tile_terrains = HashMap::new();
+        if let Some(json_tiles) = object.opt_array("tiles")? {
+            for tile in as_objects(json_tiles, "tiles")? {
+                let tile = wangset::terrain::parse_json_tile_terrain(tile).and_then(|terrain| {
+                    Ok((terrain, TileData::parse_json(tile, &prop.root_path, ctx)?))
+                });
+                match tile {
+                    Ok((terrain, (id, tile))) => {
+                        if let Some(terrain) = terrain {
+                            tile_terrains.insert(id, terrain);
+                        }
+                        tiles.insert(id, tile);
+                    }
+                    Err(err) => ctx.diagnostics.skip(err)?,
+                }
+            }
+        }
+
+        let mut wang_sets: Vec<WangSet> = match object.opt_array("wangsets")? {
+            Some(wang_sets) => as_objects(wang_sets, "wangsets")?
+                .map(|wang_set| WangSet::parse_json(wang_set, ctx.project))
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+        let terrains = wangset::terrain::parse_json_terrains(object, ctx.project)?;
+        wang_sets.extend(wangset::terrain::terrain_wang_set(terrains, tile_terrains));
+
+        // A tileset is considered an image collection tileset if there is no image attribute (because its tiles do).
+        if image.is_some() {
+            for tile_id in 0..prop.tilecount {
+                tiles.entry(tile_id).or_default();
+            }
+        }
+
+        let margin = prop.margin.unwrap_or(0);
+        let spacing = prop.spacing.unwrap_or(0);
+        let columns = prop
+            .columns
+            .map(Ok)
+            .unwrap_or_else(|| Self::calculate_columns(&image, prop.tile_width, margin, spacing))?;
+
+        let mut tileset = Tileset {
+            name: prop.name,
+            user_type: prop.user_type,
+            version: object.opt("version", as_version)?,
+            tiled_version: object.opt_str("tiledversion")?.and_then(|v| v.parse().ok()),
+            extensions: XmlExtensions::default(),
+            tile_width: prop.tile_width,
+            tile_height: prop.tile_height,
+            spacing,
+            margin,
+            columns,
+            offset_x: offset.0,
+            offset_y: offset.1,
+            grid,
+            transformations,
+            tile_render_size: prop.tile_render_size,
+            fill_mode: prop.fill_mode,
+            object_alignment: prop.object_alignment,
+            tilecount: prop.tilecount,
+            image,
+            tiles,
+            wang_sets,
+            properties: crate::properties::parse_json_properties(
+                object,
+                ctx.project,
+                &ctx.interner,
+                ctx.diagnostics,
+            )?,
+            root_path: prop.root_path,
+            source: SourceInfo::default(),
+        };
+        if let Some(hook) = ctx.image_hook {
+            tileset.attach_image_handles(hook);
+        }
+        Ok(tileset)
+    }
+}
+
+/// The grid used to place the tiles of a [`Tileset`].
+///
+/// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#grid).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilesetGrid {
+    /// The orientation of the grid.
+    pub orientation: GridOrientation,
+    /// The width in pixels of a cell of the grid.
+    pub width: u32,
+    /// The height in pixels of a cell of the grid.
+    pub height: u32,
+}
+
+impl TilesetGrid {
+    fn new(attrs: Vec<OwnedAttribute>) -> Result<Self> {
+        let (orientation, (width, height)) = get_attrs!(
+            for v in attrs {
+                Some("orientation") => orientation = v,
+                "width" => width ?= v.parse::<u32>(),
+                "height" => height ?= v.parse::<u32>(),
+            }
+            (orientation, (width, height))
+        );
+        Ok(TilesetGrid {
+            orientation: GridOrientation::parse(orientation.as_deref())?,
+            width,
+            height,
+        })
+    }
+
+    #[cfg(feature = "json")]
+    fn parse_json(object: &crate::parse::json::JsonObject) -> Result<Self> {
+        use crate::parse::json::{as_u32, JsonObjectExt};
+
+        Ok(TilesetGrid {
+            orientation: GridOrientation::parse(object.opt_str("orientation")?)?,
+            width: object.req("width", as_u32)?,
+            height: object.req("height", as_u32)?,
+        })
+    }
+}
+
+/// The orientation of a [`TilesetGrid`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+pub enum GridOrientation {
+    #[default]
+    Orthogonal,
+    Isometric,
+}
+
+impl GridOrientation {
+    /// Parses the orientation of a grid, which is orthogonal if unspecified.
+    fn parse(name: Option<&str>) -> Result<Self> {
+        match name {
+            None | Some("orthogonal") => Ok(GridOrientation::Orthogonal),
+            Some("isometric") => Ok(GridOrientation::Isometric),
+            Some(_) => Err(Error::MalformedAttributes(
+                "grid orientation must be 'orthogonal' or 'isometric'".to_owned(),
+            )),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            GridOrientation::Orthogonal => "orthogonal",
+            GridOrientation::Isometric => "isometric",
+        }
+    }
+}
+
+/// The size the tiles of a [`Tileset`] are drawn at.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TileRenderSize {
+    /// Tiles are drawn at their own size. This is the default.
+    #[default]
+    Tile,
+    /// Tiles are drawn at the tile size of the map they're in, following the tileset's
+    /// [fill mode](Tileset::fill_mode).
+    Grid,
+}
+
+impl TileRenderSize {
+    /// Parses a tile render size, which is [`TileRenderSize::Tile`] if unspecified.
+    fn parse(name: Option<&str>) -> Result<Self> {
+        match name {
+            None | Some("tile") => Ok(TileRenderSize::Tile),
+            Some("grid") => Ok(TileRenderSize::Grid),
+            Some(_) => Err(Error::MalformedAttributes(
+                "tilerendersize must be 'tile' or 'grid'".to_owned(),
+            )),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            TileRenderSize::Tile => "tile",
+            TileRenderSize::Grid => "grid",
+        }
+    }
+}
+
+/// How the tiles of a [`Tileset`] are fit within the size they're drawn at, when it differs from
+/// theirs.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FillMode {
+    /// Tiles are stretched to fill the size they're drawn at. This is the default.
+    #[default]
+    Stretch,
+    /// Tiles are scaled as much as possible while keeping their aspect ratio, and centered within
+    /// the size they're drawn at.
+    PreserveAspectFit,
+}
+
+impl FillMode {
+    /// Parses a fill mode, which is [`FillMode::Stretch`] if unspecified.
+    fn parse(name: Option<&str>) -> Result<Self> {
+        match name {
+            None | Some("stretch") => Ok(FillMode::Stretch),
+            Some("preserve-aspect-fit") => Ok(FillMode::PreserveAspectFit),
+            Some(_) => Err(Error::MalformedAttributes(
+                "fillmode must be 'stretch' or 'preserve-aspect-fit'".to_owned(),
+            )),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            FillMode::Stretch => "stretch",
+            FillMode::PreserveAspectFit => "preserve-aspect-fit",
+        }
+    }
+}
+
+/// The point of a tile object that its position refers to, set by its [`Tileset`].
+///
+/// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tileset).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+pub enum ObjectAlignment {
+    /// The alignment depends on the orientation of the map. See [`ObjectAlignment::resolve()`].
+    #[default]
+    Unspecified,
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl ObjectAlignment {
+    /// Returns the alignment Tiled uses for tile objects in a map with the given orientation.
+    /// [`ObjectAlignment::Unspecified`] stands for [`ObjectAlignment::Bottom`] in isometric maps
+    /// and for [`ObjectAlignment::BottomLeft`] otherwise; other alignments are returned as is.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{ObjectAlignment, Orientation};
+    ///
+    /// assert_eq!(
+    ///     ObjectAlignment::Unspecified.resolve(Orientation::Isometric),
+    ///     ObjectAlignment::Bottom
+    /// );
+    /// assert_eq!(
+    ///     ObjectAlignment::Unspecified.resolve(Orientation::Orthogonal),
+    ///     ObjectAlignment::BottomLeft
+    /// );
+    /// assert_eq!(
+    ///     ObjectAlignment::Center.resolve(Orientation::Isometric),
+    ///     ObjectAlignment::Center
+    /// );
+    /// ```
+    pub fn resolve(self, orientation: Orientation) -> ObjectAlignment {
+        match (self, orientation) {
+            (ObjectAlignment::Unspecified, Orientation::Isometric) => ObjectAlignment::Bottom,
+            (ObjectAlignment::Unspecified, _) => ObjectAlignment::BottomLeft,
+            (alignment, _) => alignment,
+        }
+    }
+
+    /// Returns the point of a tile object that the alignment refers to, as fractions of the
+    /// object's width and height from its top-left corner. [`ObjectAlignment::Unspecified`] is
+    /// treated as [`ObjectAlignment::BottomLeft`], so it should be [resolved](Self::resolve())
+    /// first.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::ObjectAlignment;
+    ///
+    /// assert_eq!(ObjectAlignment::Bottom.anchor(), (0.5, 1.0));
+    /// ```
+    pub fn anchor(self) -> (f32, f32) {
+        match self {
+            ObjectAlignment::TopLeft => (0.0, 0.0),
+            ObjectAlignment::Top => (0.5, 0.0),
+            ObjectAlignment::TopRight => (1.0, 0.0),
+            ObjectAlignment::Left => (0.0, 0.5),
+            ObjectAlignment::Center => (0.5, 0.5),
+            ObjectAlignment::Right => (1.0, 0.5),
+            ObjectAlignment::Unspecified | ObjectAlignment::BottomLeft => (0.0, 1.0),
+            ObjectAlignment::Bottom => (0.5, 1.0),
+            ObjectAlignment::BottomRight => (1.0, 1.0),
+        }
+    }
+
+    /// Parses an object alignment, which is [`ObjectAlignment::Unspecified`] if not present.
+    fn parse(name: Option<&str>) -> Result<Self> {
+        Ok(match name {
+            None | Some("unspecified") => ObjectAlignment::Unspecified,
+            Some("topleft") => ObjectAlignment::TopLeft,
+            Some("top") => ObjectAlignment::Top,
+            Some("topright") => ObjectAlignment::TopRight,
+            Some("left") => ObjectAlignment::Left,
+            Some("center") => ObjectAlignment::Center,
+            Some("right") => ObjectAlignment::Right,
+            Some("bottomleft") => ObjectAlignment::BottomLeft,
+            Some("bottom") => ObjectAlignment::Bottom,
+            Some("bottomright") => ObjectAlignment::BottomRight,
+            Some(name) => {
+                return Err(Error::MalformedAttributes(format!(
+                    "invalid objectalignment '{}'",
+                    name
+                )))
+            }
+        })
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ObjectAlignment::Unspecified => "unspecified",
+            ObjectAlignment::TopLeft => "topleft",
+            ObjectAlignment::Top => "top",
+            ObjectAlignment::TopRight => "topright",
+            ObjectAlignment::Left => "left",
+            ObjectAlignment::Center => "center",
+            ObjectAlignment::Right => "right",
+            ObjectAlignment::BottomLeft => "bottomleft",
+            ObjectAlignment::Bottom => "bottom",
+            ObjectAlignment::BottomRight => "bottomright",
+        }
+    }
+}
+
+/// The ways the tiles of a [`Tileset`] may be transformed when painting them, such as by Wang
+/// autotiling. No transformations are allowed by default.
+///
+/// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#transformations).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileTransformations {
+    /// Whether tiles may be flipped horizontally.
+    pub flip_h: bool,
+    /// Whether tiles may be flipped vertically.
+    pub flip_v: bool,
+    /// Whether tiles may be rotated in 90 degree increments.
+    pub rotate: bool,
+    /// Whether untransformed tiles are preferred over transformed ones when several tiles fit.
+    pub prefer_untransformed: bool,
+}
+
+impl TileTransformations {
+    fn new(attrs: Vec<OwnedAttribute>) -> Result<Self> {
+        let (flip_h, flip_v, rotate, prefer_untransformed) = get_attrs!(
+            for v in attrs {
+                Some("hflip") => flip_h ?= v.parse().map(|x: i32| x == 1),
+                Some("vflip") => flip_v ?= v.parse().map(|x: i32| x == 1),
+                Some("rotate") => rotate ?= v.parse().map(|x: i32| x == 1),
+                Some("preferuntransformed") => prefer_untransformed ?= v.parse().map(|x: i32| x == 1),
+            }
+            (flip_h, flip_v, rotate, prefer_untransformed)
+        );
+        Ok(TileTransformations {
+            flip_h: flip_h.unwrap_or(false),
+            flip_v: flip_v.unwrap_or(false),
+            rotate: rotate.unwrap_or(false),
+            prefer_untransformed: prefer_untransformed.unwrap_or(false),
+        })
+    }
+
+    #[cfg(feature = "json")]
+    fn parse_json(object: &crate::parse::json::JsonObject) -> Result<Self> {
+        use crate::parse::json::JsonObjectExt;
+
+        Ok(TileTransformations {
+            flip_h: object.opt_bool("hflip")?.unwrap_or(false),
+            flip_v: object.opt_bool("vflip")?.unwrap_or(false),
+            rotate: object.opt_bool("rotate")?.unwrap_or(false),
+            prefer_untransformed: object.opt_bool("preferuntransformed")?.unwrap_or(false),
+        })
+    }
+}
+
 /// Parse the optional <tileoffset x=... y=.../> tag.
 fn parse_tileoffset(attrs: Vec<OwnedAttribute>) -> Result<(i32, i32)> {
     Ok(get_attrs!(
@@ -1,21 +1,26 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io::Write};
 
-use xml::attribute::OwnedAttribute;
+use xml::{attribute::OwnedAttribute, EventWriter};
 
 use crate::{
     error::Error,
-    properties::{parse_properties, Properties},
-    util::{get_attrs, parse_tag, XmlEventResult},
-    Result, TileId,
+    properties::{parse_properties, write_properties, Properties},
+    util::{
+        get_attrs, parse_tag, write_empty_element, write_end_element, write_start_element,
+        XmlEventResult,
+    },
+    Project, Result, TileId,
 };
 
 mod wang_color;
 pub use wang_color::*;
 mod wang_tile;
 pub use wang_tile::*;
+pub(crate) mod terrain;
 
 /// Wang set's terrain brush connection type.
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub enum WangSetType {
     Corner,
@@ -31,6 +36,7 @@ impl Default for WangSetType {
 
 /// Raw data belonging to a WangSet.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WangSet {
     /// The name of the Wang set.
     pub name: String,
@@ -51,6 +57,7 @@ impl WangSet {
     pub fn new(
         parser: &mut impl Iterator<Item = XmlEventResult>,
         attrs: Vec<OwnedAttribute>,
+        project: Option<&Project>,
     ) -> Result<WangSet> {
         // Get common data
         let (name, wang_set_type, tile) = get_attrs!(
@@ -75,7 +82,7 @@ impl WangSet {
         let mut properties = HashMap::new();
         parse_tag!(parser, "wangset", {
             "wangcolor" => |attrs| {
-                let color = WangColor::new(parser, attrs)?;
+                let color = WangColor::new(parser, attrs, project)?;
                 wang_colors.push(color);
                 Ok(())
             },
@@ -85,7 +92,7 @@ impl WangSet {
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, project, &Default::default(), &Default::default())?;
                 Ok(())
             },
         });
@@ -100,3 +107,102 @@ impl WangSet {
         })
     }
 }
+
+impl WangSet {
+    /// Returns the color referenced by a [`WangId`] value, or [`None`] if the index is 0 (no
+    /// color) or out of range.
+    #[inline]
+    pub fn get_color(&self, index: u8) -> Option<&WangColor> {
+        self.wang_colors.get((index as usize).checked_sub(1)?)
+    }
+
+    /// Returns the Wang tile data of the tile with the given local ID, if it is part of this set.
+    #[inline]
+    pub fn get_wang_tile(&self, id: TileId) -> Option<&WangTile> {
+        self.wang_tiles.get(&id)
+    }
+
+    #[cfg(feature = "json")]
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        project: Option<&Project>,
+    ) -> Result<WangSet> {
+        use crate::parse::json::{as_objects, JsonObjectExt};
+        use serde_json::Value;
+
+        let name = object.req("name", Value::as_str)?.to_owned();
+        let wang_set_type = match object.opt_str("type")? {
+            Some("corner") => WangSetType::Corner,
+            Some("edge") => WangSetType::Edge,
+            _ => WangSetType::default(),
+        };
+        let tile = object.req("tile", Value::as_i64)?;
+        let tile = if tile >= 0 { Some(tile as u32) } else { None };
+
+        let wang_colors = match object.opt_array("colors")? {
+            Some(colors) => as_objects(colors, "colors")?
+                .map(|color| WangColor::parse_json(color, project))
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+        let wang_tiles = match object.opt_array("wangtiles")? {
+            Some(tiles) => as_objects(tiles, "wangtiles")?
+                .map(WangTile::parse_json)
+                .collect::<Result<_>>()?,
+            None => HashMap::new(),
+        };
+
+        Ok(WangSet {
+            name,
+            wang_set_type,
+            tile,
+            wang_colors,
+            wang_tiles,
+            properties: crate::properties::parse_json_properties(
+                object,
+                project,
+                &Default::default(),
+                &Default::default(),
+            )?,
+        })
+    }
+
+    pub(crate) fn write_xml(&self, writer: &mut EventWriter<impl Write>) -> Result<()> {
+        let wang_set_type = match self.wang_set_type {
+            WangSetType::Corner => "corner",
+            WangSetType::Edge => "edge",
+            WangSetType::Mixed => "mixed",
+        };
+        write_start_element(
+            writer,
+            "wangset",
+            &[
+                ("name", self.name.clone()),
+                ("type", wang_set_type.to_owned()),
+                ("tile", self.tile.map_or(-1, i64::from).to_string()),
+            ],
+        )?;
+        write_properties(writer, &self.properties)?;
+        for color in &self.wang_colors {
+            color.write_xml(writer)?;
+        }
+
+        let mut tile_ids: Vec<&TileId> = self.wang_tiles.keys().collect();
+        tile_ids.sort();
+        for id in tile_ids {
+            let wang_id = self.wang_tiles[id]
+                .wang_id
+                .0
+                .iter()
+                .map(u8::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+            write_empty_element(
+                writer,
+                "wangtile",
+                &[("tileid", id.to_string()), ("wangid", wang_id)],
+            )?;
+        }
+        write_end_element(writer)
+    }
+}
@@ -0,0 +1,215 @@
+//! Support for the terrain data used by tilesets saved before Tiled 1.5.
+//!
+//! Terrains were replaced by Wang sets in Tiled 1.5, which converts them into a single corner
+//! Wang set when loading an old file. We do the same, so that terrain information is always
+//! available through [`Tileset::wang_sets`](crate::Tileset::wang_sets).
+
+use std::collections::HashMap;
+
+use xml::attribute::OwnedAttribute;
+
+use crate::{
+    error::Error,
+    properties::{parse_properties, Color},
+    util::{get_attrs, parse_tag, XmlEventResult},
+    Project, Result, TileId,
+};
+
+use super::{WangColor, WangId, WangSet, WangSetType, WangTile};
+
+/// The name given to Wang sets converted from legacy terrains, same as Tiled uses.
+const TERRAIN_SET_NAME: &str = "Terrains";
+
+/// Colors assigned to converted terrains, since terrains had none.
+const TERRAIN_COLORS: [(u8, u8, u8); 8] = [
+    (255, 0, 0),
+    (0, 255, 0),
+    (0, 0, 255),
+    (255, 119, 0),
+    (0, 233, 255),
+    (255, 0, 216),
+    (255, 255, 0),
+    (160, 0, 255),
+];
+
+fn terrain_color(index: usize) -> Color {
+    let (red, green, blue) = TERRAIN_COLORS[index % TERRAIN_COLORS.len()];
+    Color {
+        alpha: 255,
+        red,
+        green,
+        blue,
+    }
+}
+
+fn new_terrain(index: usize, name: String, tile: i64, properties: crate::Properties) -> WangColor {
+    WangColor {
+        name,
+        color: terrain_color(index),
+        tile: if tile >= 0 { Some(tile as u32) } else { None },
+        probability: 1.0,
+        properties,
+    }
+}
+
+/// Parses the contents of a `<terraintypes>` element into Wang colors.
+pub(crate) fn parse_terrain_types(
+    parser: &mut impl Iterator<Item = XmlEventResult>,
+    project: Option<&Project>,
+) -> Result<Vec<WangColor>> {
+    let mut terrains = Vec::new();
+    parse_tag!(parser, "terraintypes", {
+        "terrain" => |attrs: Vec<OwnedAttribute>| {
+            let (name, tile) = get_attrs!(
+                for v in attrs {
+                    Some("name") => name ?= v.parse::<String>(),
+                    Some("tile") => tile ?= v.parse::<i64>(),
+                }
+                (name, tile)
+            );
+            let mut properties = HashMap::new();
+            parse_tag!(parser, "terrain", {
+                "properties" => |_| {
+                    properties = parse_properties(parser, project, &Default::default(), &Default::default())?;
+                    Ok(())
+                },
+            });
+            terrains.push(new_terrain(
+                terrains.len(),
+                name.unwrap_or_default(),
+                tile.unwrap_or(-1),
+                properties,
+            ));
+            Ok(())
+        },
+    });
+    Ok(terrains)
+}
+
+/// Returns the Wang ID described by a tile's `terrain` attribute, if it has one.
+pub(crate) fn parse_tile_terrain(attrs: &[OwnedAttribute]) -> Result<Option<WangId>> {
+    attrs
+        .iter()
+        .find(|attr| attr.name.local_name == "terrain")
+        .map(|attr| {
+            let mut corners = [None; 4];
+            let values: Vec<&str> = attr.value.split(',').collect();
+            if values.len() != corners.len() {
+                return Err(Error::InvalidWangIdEncoding {
+                    read_string: attr.value.clone(),
+                });
+            }
+            for (corner, value) in corners.iter_mut().zip(values) {
+                if !value.is_empty() {
+                    *corner =
+                        Some(
+                            value
+                                .parse::<i64>()
+                                .map_err(|_| Error::InvalidWangIdEncoding {
+                                    read_string: attr.value.clone(),
+                                })?,
+                        );
+                }
+            }
+            Ok(corners_to_wang_id(corners))
+        })
+        .transpose()
+}
+
+/// Converts terrain corners, in top-left, top-right, bottom-left, bottom-right order and with
+/// terrains indexed from 0, to a Wang ID.
+fn corners_to_wang_id(corners: [Option<i64>; 4]) -> WangId {
+    let color = |corner: Option<i64>| match corner {
+        Some(terrain) if (0..u8::MAX as i64).contains(&terrain) => terrain as u8 + 1,
+        _ => 0,
+    };
+    let [top_left, top_right, bottom_left, bottom_right] = corners;
+    WangId([
+        0,
+        color(top_right),
+        0,
+        color(bottom_right),
+        0,
+        color(bottom_left),
+        0,
+        color(top_left),
+    ])
+}
+
+/// Builds the Wang set replacing a tileset's legacy terrains, or returns [`None`] if the tileset
+/// had no terrains.
+pub(crate) fn terrain_wang_set(
+    terrains: Vec<WangColor>,
+    tiles: HashMap<TileId, WangId>,
+) -> Option<WangSet> {
+    if terrains.is_empty() && tiles.is_empty() {
+        return None;
+    }
+    Some(WangSet {
+        name: TERRAIN_SET_NAME.to_owned(),
+        wang_set_type: WangSetType::Corner,
+        tile: None,
+        wang_colors: terrains,
+        wang_tiles: tiles
+            .into_iter()
+            .map(|(id, wang_id)| (id, WangTile { wang_id }))
+            .collect(),
+        properties: HashMap::new(),
+    })
+}
+
+#[cfg(feature = "json")]
+pub(crate) fn parse_json_terrains(
+    object: &crate::parse::json::JsonObject,
+    project: Option<&Project>,
+) -> Result<Vec<WangColor>> {
+    use crate::parse::json::{as_objects, JsonObjectExt};
+
+    match object.opt_array("terrains")? {
+        Some(terrains) => as_objects(terrains, "terrains")?
+            .enumerate()
+            .map(|(index, terrain)| {
+                Ok(new_terrain(
+                    index,
+                    terrain.opt_str("name")?.unwrap_or_default().to_owned(),
+                    terrain.opt_i32("tile")?.map_or(-1, i64::from),
+                    crate::properties::parse_json_properties(
+                        terrain,
+                        project,
+                        &Default::default(),
+                        &Default::default(),
+                    )?,
+                ))
+            })
+            .collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(feature = "json")]
+pub(crate) fn parse_json_tile_terrain(
+    object: &crate::parse::json::JsonObject,
+) -> Result<Option<WangId>> {
+    use crate::parse::json::JsonObjectExt;
+
+    object
+        .opt_array("terrain")?
+        .map(|values| {
+            let mut corners = [None; 4];
+            if values.len() != corners.len() {
+                return Err(Error::MalformedAttributes(
+                    "tile terrain must have exactly 4 values".to_owned(),
+                ));
+            }
+            for (corner, value) in corners.iter_mut().zip(values) {
+                let terrain = value.as_i64().ok_or_else(|| {
+                    Error::MalformedAttributes("tile terrain values must be integers".to_owned())
+                })?;
+                if terrain >= 0 {
+                    *corner = Some(terrain);
+                }
+            }
+            Ok(corners_to_wang_id(corners))
+        })
+        .transpose()
+}
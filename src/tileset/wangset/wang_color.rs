@@ -1,16 +1,17 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io::Write};
 
-use xml::attribute::OwnedAttribute;
+use xml::{attribute::OwnedAttribute, EventWriter};
 
 use crate::{
     error::Error,
-    properties::{parse_properties, Color, Properties},
-    util::{get_attrs, parse_tag, XmlEventResult},
-    Result, TileId,
+    properties::{parse_properties, write_properties, Color, Properties},
+    util::{get_attrs, parse_tag, write_end_element, write_start_element, XmlEventResult},
+    Project, Result, TileId,
 };
 
 /// Stores the data of the Wang color.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WangColor {
     /// The name of this color.
     pub name: String,
@@ -29,6 +30,7 @@ impl WangColor {
     pub fn new(
         parser: &mut impl Iterator<Item = XmlEventResult>,
         attrs: Vec<OwnedAttribute>,
+        project: Option<&Project>,
     ) -> Result<WangColor> {
         // Get common data
         let (name, color, tile, probability) = get_attrs!(
@@ -47,7 +49,7 @@ impl WangColor {
         let mut properties = HashMap::new();
         parse_tag!(parser, "wangcolor", {
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, project, &Default::default(), &Default::default())?;
                 Ok(())
             },
         });
@@ -61,3 +63,43 @@ impl WangColor {
         })
     }
 }
+
+impl WangColor {
+    #[cfg(feature = "json")]
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+        project: Option<&Project>,
+    ) -> Result<WangColor> {
+        use crate::parse::json::JsonObjectExt;
+        use serde_json::Value;
+
+        let tile = object.req("tile", Value::as_i64)?;
+        Ok(WangColor {
+            name: object.req("name", Value::as_str)?.to_owned(),
+            color: object.req("color", |v| v.as_str().and_then(|s| s.parse().ok()))?,
+            tile: if tile >= 0 { Some(tile as u32) } else { None },
+            probability: object.req("probability", |v| v.as_f64().map(|v| v as f32))?,
+            properties: crate::properties::parse_json_properties(
+                object,
+                project,
+                &Default::default(),
+                &Default::default(),
+            )?,
+        })
+    }
+
+    pub(crate) fn write_xml(&self, writer: &mut EventWriter<impl Write>) -> Result<()> {
+        write_start_element(
+            writer,
+            "wangcolor",
+            &[
+                ("name", self.name.clone()),
+                ("color", self.color.to_string()),
+                ("tile", self.tile.map_or(-1, i64::from).to_string()),
+                ("probability", self.probability.to_string()),
+            ],
+        )?;
+        write_properties(writer, &self.properties)?;
+        write_end_element(writer)
+    }
+}
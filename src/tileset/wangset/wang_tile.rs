@@ -9,9 +9,32 @@ use crate::{
 };
 
 /// The Wang ID, stored as an array of 8 u8 values.
+///
+/// The values are ordered clockwise starting from the top edge: top, top-right, right,
+/// bottom-right, bottom, bottom-left, left and top-left. Each value is a 1-based index into
+/// [`WangSet::wang_colors`](crate::WangSet::wang_colors), with 0 meaning "no color".
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WangId(pub [u8; 8]);
 
+impl WangId {
+    /// Returns the color indices of the edges, in top, right, bottom, left order.
+    pub fn edges(&self) -> [u8; 4] {
+        [self.0[0], self.0[2], self.0[4], self.0[6]]
+    }
+
+    /// Returns the color indices of the corners, in top-right, bottom-right, bottom-left,
+    /// top-left order.
+    pub fn corners(&self) -> [u8; 4] {
+        [self.0[1], self.0[3], self.0[5], self.0[7]]
+    }
+
+    /// Returns true if any edge or corner of this ID uses the given color index.
+    pub fn has_color(&self, color: u8) -> bool {
+        color != 0 && self.0.contains(&color)
+    }
+}
+
 impl FromStr for WangId {
     type Err = Error;
 
@@ -37,6 +60,7 @@ impl FromStr for WangId {
 
 /// Stores the Wang ID.
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WangTile {
     #[allow(missing_docs)]
     pub wang_id: WangId,
@@ -60,3 +84,27 @@ impl WangTile {
         Ok((tile_id, WangTile { wang_id }))
     }
 }
+
+#[cfg(feature = "json")]
+impl WangTile {
+    pub(crate) fn parse_json(
+        object: &crate::parse::json::JsonObject,
+    ) -> Result<(TileId, WangTile)> {
+        use crate::parse::json::{as_u32, JsonObjectExt};
+        use std::convert::TryFrom;
+
+        let tile_id = object.req("tileid", as_u32)?;
+        let wang_id = object.req("wangid", |v| {
+            let values = v.as_array()?;
+            let mut ret = [0u8; 8];
+            if values.len() != ret.len() {
+                return None;
+            }
+            for (v, value) in ret.iter_mut().zip(values) {
+                *v = u8::try_from(value.as_u64()?).ok()?;
+            }
+            Some(WangId(ret))
+        })?;
+        Ok((tile_id, WangTile { wang_id }))
+    }
+}
@@ -241,10 +241,153 @@ pub(crate) use get_attrs;
 pub(crate) use map_wrapper;
 pub(crate) use parse_tag;
 
-use crate::{Gid, MapTilesetGid};
+use std::{
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use xml::{writer::XmlEvent as XmlWriteEvent, EventWriter};
+
+use crate::{Error, Gid, MapTilesetGid, PropertyValue, Result, VirtualPath};
 
 pub(crate) type XmlEventResult = xml::reader::Result<xml::reader::XmlEvent>;
 
+/// Writes a start tag with the given attributes. Attributes are written in the order given.
+pub(crate) fn write_start_element(
+    writer: &mut EventWriter<impl Write>,
+    name: &str,
+    attrs: &[(&str, String)],
+) -> Result<()> {
+    let mut element = XmlWriteEvent::start_element(name);
+    for (attr, value) in attrs {
+        element = element.attr(*attr, value);
+    }
+    writer.write(element).map_err(Error::XmlEncodingError)
+}
+
+/// Closes the last tag opened with [`write_start_element`].
+pub(crate) fn write_end_element(writer: &mut EventWriter<impl Write>) -> Result<()> {
+    writer
+        .write(XmlWriteEvent::end_element())
+        .map_err(Error::XmlEncodingError)
+}
+
+/// Writes an element without any children.
+pub(crate) fn write_empty_element(
+    writer: &mut EventWriter<impl Write>,
+    name: &str,
+    attrs: &[(&str, String)],
+) -> Result<()> {
+    write_start_element(writer, name, attrs)?;
+    write_end_element(writer)
+}
+
+/// Writes text content inside the element currently open.
+pub(crate) fn write_characters(writer: &mut EventWriter<impl Write>, text: &str) -> Result<()> {
+    writer
+        .write(XmlWriteEvent::characters(text))
+        .map_err(Error::XmlEncodingError)
+}
+
+/// Returns `path` expressed relative to the `base` directory, using forward slashes as Tiled
+/// does. Both paths are normalized lexically (the filesystem is never accessed).
+///
+/// If no relative path can be built (e.g. only one of them is absolute), `path` is returned as-is.
+pub(crate) fn relative_path(base: &Path, path: &Path) -> String {
+    fn normalize(path: &Path) -> Vec<Component<'_>> {
+        let mut out: Vec<Component> = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir if matches!(out.last(), Some(Component::Normal(_))) => {
+                    out.pop();
+                }
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    let as_is = || path.to_string_lossy().replace('\\', "/");
+    if base.is_absolute() != path.is_absolute() {
+        return as_is();
+    }
+
+    let base = normalize(base);
+    let target = normalize(path);
+    let common = base
+        .iter()
+        .zip(target.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if base[common..]
+        .iter()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return as_is();
+    }
+
+    let relative: PathBuf = (common..base.len())
+        .map(|_| Component::ParentDir)
+        .chain(target[common..].iter().copied())
+        .collect();
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns `path` resolved against the `root` directory, with its `.` and `..` components resolved
+/// lexically (the filesystem is never accessed). Leading `..` components that cannot be resolved
+/// are kept.
+pub(crate) fn resolve_path(root: &Path, path: &Path) -> PathBuf {
+    let joined = root.join(path);
+    let mut resolved: Vec<Component> = Vec::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(resolved.last(), Some(Component::Normal(_))) => {
+                resolved.pop();
+            }
+            Component::ParentDir
+                if matches!(
+                    resolved.last(),
+                    Some(Component::RootDir | Component::Prefix(_))
+                ) => {}
+            c => resolved.push(c),
+        }
+    }
+    resolved.into_iter().collect()
+}
+
+/// Joins a reference found in a file, such as the source of a tileset, to the directory of the
+/// file. Backslashes are treated as separators on every platform, since maps authored on Windows
+/// may use them.
+pub(crate) fn join_reference(directory: &Path, reference: &str) -> PathBuf {
+    directory.join(reference.replace('\\', "/"))
+}
+
+/// Returns the path a file property refers to, resolved against `root`. See [`resolve_path`].
+pub(crate) fn resolve_file_property(root: &Path, value: &PropertyValue) -> Option<PathBuf> {
+    match value {
+        PropertyValue::FileValue(path) if !path.is_empty() => {
+            Some(resolve_path(root, Path::new(path)))
+        }
+        _ => None,
+    }
+}
+
+/// Resolves the `.` and `..` components of a relative path lexically, for readers that don't
+/// have a filesystem to do it for them. Backslashes are treated as separators, and root and
+/// prefix components are ignored, so that absolute paths are treated as relative to the root of
+/// the reader. See [`VirtualPath`].
+///
+/// Returns [`None`] if the path points outside of the root.
+pub(crate) fn normalize_path(path: &Path) -> Option<PathBuf> {
+    VirtualPath::new(path).relative_to_root().map(PathBuf::from)
+}
+
 /// Returns both the tileset and its index
 pub(crate) fn get_tileset_for_gid(
     tilesets: &[MapTilesetGid],
@@ -0,0 +1,255 @@
+use std::{fmt, path::PathBuf};
+
+use crate::{Layer, LayerType, Map, ObjectShape, ResourceReader, TileId};
+
+/// An issue found in a loaded [`Map`] by [`Map::validate()`].
+///
+/// Unlike [`Warning`](crate::Warning)s, which are found while parsing, these concern the content
+/// of the map as a whole, and are usually left behind by external tools or by editing a map in
+/// code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationIssue {
+    /// A tile layer places a tile that its tileset doesn't contain, such as an ID past the tile
+    /// count of the tileset.
+    ///
+    /// GIDs that don't belong to any tileset can't be represented once the map is loaded; They
+    /// are reported as [`WarningKind::InvalidGid`](crate::WarningKind::InvalidGid) instead.
+    InvalidTile {
+        /// The id of the tile layer.
+        layer: u32,
+        /// The x coordinate of the tile, in tiles.
+        x: i32,
+        /// The y coordinate of the tile, in tiles.
+        y: i32,
+        /// The name of the tileset the tile refers to.
+        tileset: String,
+        /// The local ID of the tile within that tileset.
+        id: TileId,
+    },
+    /// A tile object uses a tile that its tileset doesn't contain.
+    InvalidTileObject {
+        /// The id of the object.
+        object: u32,
+        /// The name of the tileset the tile refers to.
+        tileset: String,
+        /// The local ID of the tile within that tileset.
+        id: TileId,
+    },
+    /// Several layers of the map share the same id.
+    DuplicateLayerId {
+        /// The shared id.
+        id: u32,
+    },
+    /// Several objects of the map share the same id.
+    DuplicateObjectId {
+        /// The shared id.
+        id: u32,
+    },
+    /// An image used by a tileset, a tile or an image layer of the map can't be read.
+    MissingFile {
+        /// The path of the image, as found in the map.
+        path: PathBuf,
+    },
+    /// An object has a shape which doesn't cover anything: A rectangle or an ellipse with no
+    /// width or height, a polygon with less than three points or a polyline with less than two.
+    EmptyShape {
+        /// The id of the object.
+        object: u32,
+    },
+    /// An object property of the map, one of its layers or one of its objects refers to an object
+    /// which doesn't exist.
+    UnresolvedObjectReference {
+        /// The name of the property.
+        property: String,
+        /// The id of the missing object.
+        id: u32,
+    },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::InvalidTile {
+                layer,
+                x,
+                y,
+                tileset,
+                id,
+            } => write!(
+                f,
+                "tile ({x}, {y}) of layer {layer} is tile {id}, missing from tileset '{tileset}'"
+            ),
+            ValidationIssue::InvalidTileObject {
+                object,
+                tileset,
+                id,
+            } => write!(
+                f,
+                "object {object} uses tile {id}, missing from tileset '{tileset}'"
+            ),
+            ValidationIssue::DuplicateLayerId { id } => {
+                write!(f, "several layers have the id {id}")
+            }
+            ValidationIssue::DuplicateObjectId { id } => {
+                write!(f, "several objects have the id {id}")
+            }
+            ValidationIssue::MissingFile { path } => {
+                write!(f, "file {} can't be read", path.display())
+            }
+            ValidationIssue::EmptyShape { object } => {
+                write!(f, "the shape of object {object} is empty")
+            }
+            ValidationIssue::UnresolvedObjectReference { property, id } => write!(
+                f,
+                "property '{property}' refers to object {id}, which doesn't exist"
+            ),
+        }
+    }
+}
+
+impl Map {
+    /// Checks the map for issues that loading it doesn't catch, reading the files it refers to
+    /// from the filesystem. Returns them in the order they were found, or an empty list if the map
+    /// is valid.
+    ///
+    /// See [`ValidationIssue`] for the issues checked.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// assert_eq!(map.validate(), vec![]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        self.validate_with_reader(&mut crate::FilesystemResourceReader)
+    }
+
+    /// Checks the map for issues like [`Map::validate()`] does, reading the files it refers to
+    /// using `reader`.
+    pub fn validate_with_reader(&self, reader: &mut impl ResourceReader) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let mut layer_ids = Vec::new();
+        let mut object_ids = Vec::new();
+        let mut images = Vec::new();
+
+        for tileset in self.tilesets() {
+            images.extend(tileset.image.iter().map(|image| image.source.clone()));
+            images.extend(
+                tileset
+                    .tiles()
+                    .filter_map(|(_, tile)| tile.image.as_ref().map(|image| image.source.clone())),
+            );
+        }
+        for layer in self.layers() {
+            check_layer(
+                layer,
+                &mut issues,
+                &mut layer_ids,
+                &mut object_ids,
+                &mut images,
+            );
+        }
+
+        let mut checked = Vec::new();
+        for path in images {
+            if path.as_os_str().is_empty() || checked.contains(&path) {
+                continue;
+            }
+            if reader.read_from(&path).is_err() {
+                issues.push(ValidationIssue::MissingFile { path: path.clone() });
+            }
+            checked.push(path);
+        }
+        issues.extend(
+            self.unresolved_object_references()
+                .into_iter()
+                .map(|(property, id)| ValidationIssue::UnresolvedObjectReference { property, id }),
+        );
+        issues
+    }
+}
+
+fn check_layer(
+    layer: Layer,
+    issues: &mut Vec<ValidationIssue>,
+    layer_ids: &mut Vec<u32>,
+    object_ids: &mut Vec<u32>,
+    images: &mut Vec<PathBuf>,
+) {
+    check_id(layer.id(), layer_ids, issues, |id| {
+        ValidationIssue::DuplicateLayerId { id }
+    });
+    match layer.layer_type() {
+        LayerType::Tiles(tiles) => {
+            for ((x, y), tile) in tiles.all_tiles() {
+                if tile.get_tile().is_none() {
+                    issues.push(ValidationIssue::InvalidTile {
+                        layer: layer.id(),
+                        x,
+                        y,
+                        tileset: tile.get_tileset().name.clone(),
+                        id: tile.id(),
+                    });
+                }
+            }
+        }
+        LayerType::Objects(objects) => {
+            for object in objects.objects() {
+                check_id(object.id(), object_ids, issues, |id| {
+                    ValidationIssue::DuplicateObjectId { id }
+                });
+                if let Some(tile) = object.get_tile() {
+                    if tile.get_tile().is_none() {
+                        issues.push(ValidationIssue::InvalidTileObject {
+                            object: object.id(),
+                            tileset: tile.get_tileset().name.clone(),
+                            id: tile.id(),
+                        });
+                    }
+                }
+                let empty = match &object.shape {
+                    ObjectShape::Rect { width, height }
+                    | ObjectShape::Ellipse { width, height } => *width == 0.0 || *height == 0.0,
+                    ObjectShape::Polygon { points } => points.len() < 3,
+                    ObjectShape::Polyline { points } => points.len() < 2,
+                    _ => false,
+                };
+                // Tile objects without a size take the size of their tile.
+                if empty && object.get_tile().is_none() {
+                    issues.push(ValidationIssue::EmptyShape {
+                        object: object.id(),
+                    });
+                }
+            }
+        }
+        LayerType::Image(image) => {
+            images.extend(image.image.iter().map(|image| image.source.clone()));
+        }
+        LayerType::Group(group) => {
+            for layer in group.layers() {
+                check_layer(layer, issues, layer_ids, object_ids, images);
+            }
+        }
+    }
+}
+
+/// Records an id, reporting it with `issue` the second time it is seen. Ids of 0 mean the item has
+/// none, and are ignored.
+fn check_id(
+    id: u32,
+    seen: &mut Vec<u32>,
+    issues: &mut Vec<ValidationIssue>,
+    issue: impl FnOnce(u32) -> ValidationIssue,
+) {
+    if id == 0 {
+        return;
+    }
+    if seen.iter().filter(|seen| **seen == id).count() == 1 {
+        issues.push(issue(id));
+    }
+    seen.push(id);
+}
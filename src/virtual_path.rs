@@ -0,0 +1,198 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// A path to a resource in a normalized form, which is the same on every platform.
+///
+/// Maps authored on Windows may refer to their tilesets, templates and images with backslashes,
+/// and references often go through `..` segments. Normalizing the path separates its segments
+/// with forward slashes and resolves the `.` and `..` segments lexically, so that every way of
+/// referring to a file gives the same path. The loader caches resources using normalized paths,
+/// so that a tileset referred to in different ways is only loaded once, and the in-memory and
+/// archive readers look up their files with them.
+///
+/// `..` segments are resolved without accessing the filesystem, so a path going through a
+/// symbolic link may not refer to the same file once normalized. For this reason,
+/// [`ResourceReader`](crate::ResourceReader)s are given the paths as they were joined, and
+/// [`FilesystemResourceReader`](crate::FilesystemResourceReader) lets the operating system
+/// resolve them. Leading `..` segments are kept in relative paths, and dropped at the root of
+/// absolute paths. Windows UNC paths keep their server and share, and verbatim paths their
+/// `\\?\` prefix. URLs, which the `UrlResourceReader` of the `http` feature accepts as paths,
+/// keep their scheme.
+///
+/// ## Example
+/// ```
+/// use tiled::VirtualPath;
+///
+/// let path = VirtualPath::new("maps\\..\\tilesets\\.\\tilesheet.tsx");
+/// assert_eq!(path.as_str(), "tilesets/tilesheet.tsx");
+/// assert_eq!(path, VirtualPath::new("tilesets/tilesheet.tsx"));
+/// assert_eq!(
+///     VirtualPath::new("maps/level.tmx").parent().join("../tilesheet.tsx").as_str(),
+///     "tilesheet.tsx"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct VirtualPath(String);
+
+impl VirtualPath {
+    /// Normalizes the path given. Characters that aren't valid Unicode are replaced with
+    /// [`char::REPLACEMENT_CHARACTER`].
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self::normalize(&path.as_ref().to_string_lossy())
+    }
+
+    fn normalize(path: &str) -> Self {
+        let path = path.replace('\\', "/");
+        let (root, rest) = split_root(&path);
+        let mut segments: Vec<&str> = Vec::new();
+        for segment in rest.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." if matches!(segments.last(), Some(&last) if last != "..") => {
+                    segments.pop();
+                }
+                // There is nothing above the root.
+                ".." if root.ends_with('/') => {}
+                segment => segments.push(segment),
+            }
+        }
+        Self(root.to_owned() + &segments.join("/"))
+    }
+
+    /// Returns the path as a string, with its segments separated by forward slashes.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the path as a [`Path`], to be passed to a
+    /// [`ResourceReader`](crate::ResourceReader).
+    pub fn as_path(&self) -> &Path {
+        Path::new(&self.0)
+    }
+
+    /// Returns whether the path starts at a root, a drive or the address of a server, rather than
+    /// being relative.
+    pub fn is_absolute(&self) -> bool {
+        let root = split_root(&self.0).0;
+        root.starts_with('/') || root.ends_with('/') || root.contains("://")
+    }
+
+    /// Returns the path without its last segment, which is the directory containing the file it
+    /// refers to. The parent of a path with a single segment is the empty path, or the root.
+    pub fn parent(&self) -> Self {
+        let (root, rest) = split_root(&self.0);
+        match rest.rfind('/') {
+            Some(index) => Self(self.0[..root.len() + index].to_owned()),
+            None if rest == ".." => Self(self.0.clone() + "/.."),
+            None => Self(root.to_owned()),
+        }
+    }
+
+    /// Resolves a reference, such as the source of a tileset, against this path, which is
+    /// assumed to be a directory. Absolute references are kept as they are.
+    pub fn join(&self, reference: impl AsRef<Path>) -> Self {
+        let reference = Self::new(reference);
+        if reference.is_absolute() || self.0.is_empty() {
+            reference
+        } else if reference.0.is_empty() {
+            self.clone()
+        } else {
+            Self::normalize(&format!("{}/{}", self.0, reference.0))
+        }
+    }
+
+    /// Returns the path without its root, or [`None`] if it points above the directory it is
+    /// relative to. Used by readers that treat all paths as relative to their own root.
+    pub(crate) fn relative_to_root(&self) -> Option<&str> {
+        let rest = split_root(&self.0).1;
+        if rest == ".." || rest.starts_with("../") {
+            None
+        } else {
+            Some(rest)
+        }
+    }
+}
+
+/// Splits a path whose separators are forward slashes into its root, if any, and the rest. The
+/// root is the scheme and server of a URL, the server and share of a UNC path, a Windows
+/// verbatim (`\\?\`) or device (`\\.\`) prefix along with the drive or share following it, a
+/// drive letter, a slash, or a drive letter followed by a slash.
+fn split_root(path: &str) -> (&str, &str) {
+    if let Some(index) = path.find("://") {
+        let scheme = &path[..index];
+        if scheme.len() > 1
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+')
+        {
+            let server_end = path[index + 3..]
+                .find('/')
+                .map_or(path.len(), |end| index + 3 + end + 1);
+            return path.split_at(server_end.min(path.len()));
+        }
+    }
+    if let Some(rest) = path.strip_prefix("//") {
+        let root_len = match rest.strip_prefix("?/").or_else(|| rest.strip_prefix("./")) {
+            // A share behind a verbatim prefix, such as `\\?\UNC\server\share\`.
+            Some(rest) if rest.starts_with("UNC/") => 4 + segments_len(rest, 3),
+            // A drive or a volume behind a verbatim or device prefix, such as `\\?\C:\`.
+            Some(rest) => 4 + segments_len(rest, 1),
+            // A server and a share, such as `\\server\share\`.
+            None => 2 + segments_len(rest, 2),
+        };
+        return path.split_at(root_len);
+    }
+    let bytes = path.as_bytes();
+    let root_len = match bytes {
+        [drive, b':', b'/', ..] if drive.is_ascii_alphabetic() => 3,
+        [drive, b':', ..] if drive.is_ascii_alphabetic() => 2,
+        [b'/', ..] => 1,
+        _ => 0,
+    };
+    path.split_at(root_len)
+}
+
+/// Returns the length of the first `count` segments of `path`, including the slashes following
+/// them.
+fn segments_len(path: &str, count: usize) -> usize {
+    let mut len = 0;
+    for _ in 0..count {
+        match path[len..].find('/') {
+            Some(end) => len += end + 1,
+            None => return path.len(),
+        }
+    }
+    len
+}
+
+impl AsRef<Path> for VirtualPath {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl From<&Path> for VirtualPath {
+    fn from(path: &Path) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<&str> for VirtualPath {
+    fn from(path: &str) -> Self {
+        Self::normalize(path)
+    }
+}
+
+impl From<VirtualPath> for PathBuf {
+    fn from(path: VirtualPath) -> Self {
+        PathBuf::from(path.0)
+    }
+}
+
+impl fmt::Display for VirtualPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
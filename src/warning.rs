@@ -0,0 +1,405 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{util::XmlEventResult, Error, Result, XmlExtensions};
+
+/// What a [`Loader`](crate::Loader) does when it finds an issue that only affects part of a file.
+///
+/// See [`Loader::set_error_policy()`](crate::Loader::set_error_policy()).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// Stop loading and return the error. This is the default.
+    #[default]
+    Fail,
+    /// Skip the broken item, such as a property or a tile of a tileset, and report it as a
+    /// [`Warning`] instead.
+    ///
+    /// Errors that leave the rest of the file unreadable, such as malformed XML or JSON, are still
+    /// returned.
+    SkipAndWarn,
+}
+
+/// A non-fatal issue found while loading a file.
+///
+/// Warnings are collected by the [`Loader`](crate::Loader) that found them, and can be retrieved
+/// with [`Loader::take_warnings()`](crate::Loader::take_warnings()) until the loader starts loading
+/// another file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// The path of the file the issue was found in.
+    pub path: PathBuf,
+    /// Where the issue was found within the file. Only known for XML files.
+    pub location: Option<ElementLocation>,
+    /// The issue found.
+    pub kind: WarningKind,
+}
+
+/// The element of an XML file a [`Warning`] was found in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementLocation {
+    /// The name of the innermost element being parsed when the issue was found.
+    pub element: String,
+    /// The line of the element's start tag, starting from 1.
+    pub line: u64,
+    /// The column of the element's start tag, starting from 1.
+    pub column: u64,
+}
+
+/// The kinds of issues reported by [`Warning`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WarningKind {
+    /// An item that could not be parsed was skipped, following [`ErrorPolicy::SkipAndWarn`].
+    Skipped {
+        /// A description of the error that caused the item to be skipped.
+        reason: String,
+    },
+    /// An element that newer versions of Tiled no longer write was found. Such elements are
+    /// either converted, such as `<terraintypes>`, or ignored, such as `<wangcornercolor>`.
+    DeprecatedElement {
+        /// The name of the element.
+        element: String,
+    },
+    /// An element that isn't part of the format was found, and ignored. Only reported in
+    /// [strict mode](crate::Loader::set_strict()).
+    UnknownElement {
+        /// The name of the element.
+        element: String,
+        /// The name of the element containing it.
+        parent: String,
+    },
+    /// An attribute that isn't part of the format was found, and ignored.
+    UnknownAttribute {
+        /// The name of the element the attribute belongs to.
+        element: String,
+        /// The name of the attribute.
+        attribute: String,
+    },
+    /// A tile layer refers to a GID which doesn't belong to any tile of the map's tilesets. The
+    /// tiles using it are left empty, or have no [`Tile`](crate::Tile) data.
+    ///
+    /// Each invalid GID is only reported once per layer, or chunk of an infinite layer. The data of
+    /// layers whose decoding is [deferred](crate::Loader::set_defer_tile_data()) is not checked.
+    InvalidGid {
+        /// The invalid GID, without its flipping flags.
+        gid: u32,
+    },
+    /// An object property refers to an object that doesn't exist in the map. Only reported when
+    /// [resolving object references](crate::Loader::set_resolve_object_references()).
+    UnresolvedObjectReference {
+        /// The name of the property.
+        property: String,
+        /// The id of the object the property refers to.
+        id: u32,
+    },
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WarningKind::Skipped { reason } => write!(fmt, "Skipped an item: {}", reason),
+            WarningKind::DeprecatedElement { element } => {
+                write!(fmt, "The <{}> element is deprecated", element)
+            }
+            WarningKind::UnknownElement { element, parent } => {
+                write!(fmt, "Unknown element <{}> in <{}> element", element, parent)
+            }
+            WarningKind::UnknownAttribute { element, attribute } => write!(
+                fmt,
+                "Unknown attribute '{}' in <{}> element",
+                attribute, element
+            ),
+            WarningKind::InvalidGid { gid } => {
+                write!(fmt, "GID {} doesn't refer to a tile of any tileset", gid)
+            }
+            WarningKind::UnresolvedObjectReference { property, id } => write!(
+                fmt,
+                "Property '{}' refers to object {}, which doesn't exist",
+                property, id
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(
+                fmt,
+                "{} (in <{}> at {}:{}:{})",
+                self.kind,
+                location.element,
+                self.path.to_string_lossy(),
+                location.line,
+                location.column
+            ),
+            None => write!(fmt, "{} (in {})", self.kind, self.path.to_string_lossy()),
+        }
+    }
+}
+
+/// A file being parsed, along with the XML elements currently open in it.
+#[derive(Debug, Clone)]
+struct Document {
+    path: PathBuf,
+    open_elements: Vec<(String, u64, u64)>,
+}
+
+/// Keeps track of where parsing currently is, so that errors and warnings can be located, and
+/// collects warnings following an [`ErrorPolicy`].
+///
+/// The state is kept behind mutexes so that loaders stay `Sync`; They are never contended, since
+/// loading a file requires a mutable reference to the loader.
+#[derive(Debug, Default)]
+pub(crate) struct Diagnostics {
+    policy: ErrorPolicy,
+    /// Whether elements and attributes that aren't part of the format are errors.
+    strict: bool,
+    /// Whether elements and attributes that aren't part of the format are kept.
+    preserve_extensions: bool,
+    /// The extensions of the last XML element closed, if they are kept.
+    closed_extensions: Mutex<Option<XmlExtensions>>,
+    warnings: Mutex<Vec<Warning>>,
+    /// The files being parsed, the innermost one last.
+    documents: Mutex<Vec<Document>>,
+}
+
+impl Clone for Diagnostics {
+    fn clone(&self) -> Self {
+        Self {
+            policy: self.policy,
+            strict: self.strict,
+            preserve_extensions: self.preserve_extensions,
+            closed_extensions: Mutex::new(lock(&self.closed_extensions).clone()),
+            warnings: Mutex::new(lock(&self.warnings).clone()),
+            documents: Mutex::new(lock(&self.documents).clone()),
+        }
+    }
+}
+
+impl Diagnostics {
+    #[cfg(feature = "parallel")]
+    pub(crate) fn new(policy: ErrorPolicy, strict: bool, preserve_extensions: bool) -> Self {
+        Self {
+            policy,
+            strict,
+            preserve_extensions,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn policy(&self) -> ErrorPolicy {
+        self.policy
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: ErrorPolicy) {
+        self.policy = policy;
+    }
+
+    pub(crate) fn strict(&self) -> bool {
+        self.strict
+    }
+
+    pub(crate) fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub(crate) fn preserve_extensions(&self) -> bool {
+        self.preserve_extensions
+    }
+
+    pub(crate) fn set_preserve_extensions(&mut self, preserve: bool) {
+        self.preserve_extensions = preserve;
+    }
+
+    /// Records the extensions of the XML element that was just closed.
+    pub(crate) fn close_extensions(&self, extensions: XmlExtensions) {
+        *lock(&self.closed_extensions) = Some(extensions);
+    }
+
+    /// Returns the extensions of the last XML element closed, which is the element just parsed
+    /// when called right after parsing it. Empty if extensions aren't kept.
+    pub(crate) fn take_extensions(&self) -> XmlExtensions {
+        lock(&self.closed_extensions).take().unwrap_or_default()
+    }
+
+    /// Returns and clears the warnings collected so far.
+    pub(crate) fn take_warnings(&self) -> Vec<Warning> {
+        std::mem::take(&mut *lock(&self.warnings))
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(crate) fn extend(&self, warnings: Vec<Warning>) {
+        lock(&self.warnings).extend(warnings);
+    }
+
+    /// Marks the start of the parsing of the file at `path`, until the returned guard is dropped.
+    /// The warnings of the previous load are cleared when the file isn't referenced by another one
+    /// being parsed, so that they don't pile up in long-lived loaders.
+    pub(crate) fn enter_document(&self, path: &Path) -> DocumentGuard<'_> {
+        if lock(&self.documents).is_empty() {
+            lock(&self.warnings).clear();
+        }
+        lock(&self.documents).push(Document {
+            path: path.to_owned(),
+            open_elements: Vec::new(),
+        });
+        DocumentGuard { diagnostics: self }
+    }
+
+    pub(crate) fn open_element(&self, name: String, line: u64, column: u64) {
+        if let Some(document) = lock(&self.documents).last_mut() {
+            document.open_elements.push((name, line, column));
+        }
+    }
+
+    pub(crate) fn close_element(&self) {
+        if let Some(document) = lock(&self.documents).last_mut() {
+            document.open_elements.pop();
+        }
+    }
+
+    /// Returns the number of XML elements currently open in the file being parsed.
+    pub(crate) fn depth(&self) -> usize {
+        lock(&self.documents)
+            .last()
+            .map_or(0, |document| document.open_elements.len())
+    }
+
+    /// Returns the name of the innermost XML element currently open.
+    pub(crate) fn element_name(&self) -> Option<String> {
+        lock(&self.documents)
+            .last()
+            .and_then(|document| document.open_elements.last())
+            .map(|(element, ..)| element.clone())
+    }
+
+    fn current_location(&self) -> Option<(PathBuf, Option<ElementLocation>)> {
+        let documents = lock(&self.documents);
+        let document = documents.last()?;
+        let location = document
+            .open_elements
+            .last()
+            .map(|(element, line, column)| ElementLocation {
+                element: element.clone(),
+                line: *line,
+                column: *column,
+            });
+        Some((document.path.clone(), location))
+    }
+
+    /// Adds the innermost element currently open to `err`, unless the error already says where it
+    /// happened.
+    pub(crate) fn locate(&self, err: Error) -> Error {
+        if let Error::XmlDecodingError(_)
+        | Error::ResourceLoadingError { .. }
+        | Error::ElementError { .. } = err
+        {
+            return err;
+        }
+        match self.current_location() {
+            Some((path, Some(location))) => Error::ElementError {
+                path,
+                element: location.element,
+                line: location.line,
+                column: location.column,
+                err: Box::new(err),
+            },
+            _ => err,
+        }
+    }
+
+    /// Records a warning located at the innermost element currently open.
+    pub(crate) fn warn(&self, kind: WarningKind) {
+        let (path, location) = self.current_location().unwrap_or_default();
+        lock(&self.warnings).push(Warning {
+            path,
+            location,
+            kind,
+        });
+    }
+
+    /// Handles an error that only affects the item being parsed: Under
+    /// [`ErrorPolicy::SkipAndWarn`], the error is recorded as a warning so that the caller can
+    /// skip the item. Otherwise, or if the error is fatal, it is returned.
+    pub(crate) fn skip(&self, err: Error) -> Result<()> {
+        if self.policy == ErrorPolicy::Fail || is_fatal(&err) {
+            return Err(err);
+        }
+        match err {
+            Error::ElementError {
+                path,
+                element,
+                line,
+                column,
+                err,
+            } => lock(&self.warnings).push(Warning {
+                path,
+                location: Some(ElementLocation {
+                    element,
+                    line,
+                    column,
+                }),
+                kind: WarningKind::Skipped {
+                    reason: err.to_string(),
+                },
+            }),
+            err => self.warn(WarningKind::Skipped {
+                reason: err.to_string(),
+            }),
+        }
+        Ok(())
+    }
+
+    /// Like [`Diagnostics::skip()`], but also skips the rest of the XML element that was open at
+    /// `depth` if the error was recorded as a warning.
+    pub(crate) fn skip_element(
+        &self,
+        parser: &mut impl Iterator<Item = XmlEventResult>,
+        depth: usize,
+        err: Error,
+    ) -> Result<()> {
+        self.skip(err)?;
+        while depth > 0 && self.depth() >= depth {
+            match parser.next() {
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(Error::XmlDecodingError(err)),
+                None => {
+                    return Err(Error::PrematureEnd(
+                        "Document ended before we expected.".to_string(),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // The state is never left inconsistent, so a panic while parsing doesn't matter.
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns whether an error leaves the rest of the file it occured in unreadable.
+fn is_fatal(err: &Error) -> bool {
+    match err.inner() {
+        Error::XmlDecodingError(_) | Error::PrematureEnd(_) => true,
+        #[cfg(feature = "json")]
+        Error::JsonDecodingError(_) => true,
+        _ => false,
+    }
+}
+
+/// Marks the end of the parsing of a file when dropped. See [`Diagnostics::enter_document()`].
+pub(crate) struct DocumentGuard<'a> {
+    diagnostics: &'a Diagnostics,
+}
+
+impl Drop for DocumentGuard<'_> {
+    fn drop(&mut self) {
+        lock(&self.diagnostics.documents).pop();
+    }
+}
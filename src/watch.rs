@@ -0,0 +1,276 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::{Error, Loader, Map, ResourceCache, ResourceReader, Result};
+
+/// How long to wait for further changes after a file changes, since saving a file often takes
+/// several writes.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// A map loaded again by a [`MapWatcher`].
+#[derive(Debug)]
+pub struct MapUpdate {
+    /// The path of the map, as given to [`Loader::watch()`].
+    pub path: PathBuf,
+    /// The map, or the error that occured while loading it.
+    pub map: Result<Map>,
+}
+
+/// Watches maps and the files they use, loading the maps again whenever one of these changes.
+/// Created with [`Loader::watch()`].
+///
+/// The maps are delivered through the [`receiver()`](Self::receiver()) of the watcher. Watching
+/// stops once the watcher is dropped.
+#[derive(Debug)]
+pub struct MapWatcher {
+    receiver: Receiver<MapUpdate>,
+    events: Sender<WatchEvent>,
+}
+
+impl MapWatcher {
+    /// Returns the channel through which the maps are delivered.
+    pub fn receiver(&self) -> &Receiver<MapUpdate> {
+        &self.receiver
+    }
+}
+
+impl Drop for MapWatcher {
+    fn drop(&mut self) {
+        // The thread may have stopped already if the watcher failed.
+        let _ = self.events.send(WatchEvent::Stop);
+    }
+}
+
+enum WatchEvent {
+    Changed(notify::Result<notify::Event>),
+    Stop,
+}
+
+impl<Cache, Reader> Loader<Cache, Reader>
+where
+    Cache: ResourceCache + Send + 'static,
+    Reader: ResourceReader + Send + 'static,
+{
+    /// Watches the maps at the paths given for changes, consuming the loader. Requires the
+    /// `watch` feature.
+    ///
+    /// The maps are loaded right away, then again each time their file or one of the tilesets,
+    /// templates or images they use changes on the filesystem. Changed tilesets and templates are
+    /// invalidated in the cache of the loader beforehand, so that only the files which changed are
+    /// parsed again. Each map loaded, or the error that prevented it, is sent to the
+    /// [receiver](MapWatcher::receiver()) of the returned watcher as a [`MapUpdate`]. Maps in the
+    /// JSON format are recognized by their extension if the `json` feature is enabled.
+    ///
+    /// The loading happens on a background thread, so a game can pick up the changes made to its
+    /// maps in Tiled without waiting for them.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # fn main() -> tiled::Result<()> {
+    /// let watcher = tiled::Loader::new().watch(["assets/tiled_base64_external.tmx"])?;
+    /// for update in watcher.receiver() {
+    ///     match update.map {
+    ///         Ok(map) => println!("{} has {} layers", update.path.display(), map.layers().len()),
+    ///         Err(err) => println!("{} can't be loaded: {err}", update.path.display()),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(self, paths: impl IntoIterator<Item = impl AsRef<Path>>) -> Result<MapWatcher> {
+        let (events, event_receiver) = mpsc::channel();
+        let (updates, receiver) = mpsc::channel();
+        let sender = events.clone();
+        let watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(WatchEvent::Changed(event));
+        })
+        .map_err(|err| watch_error(PathBuf::new(), err))?;
+
+        let mut state = WatchState {
+            loader: self,
+            watcher,
+            watched_dirs: HashSet::new(),
+            maps: paths
+                .into_iter()
+                .map(|path| WatchedMap {
+                    path: path.as_ref().to_owned(),
+                    dependencies: HashMap::new(),
+                })
+                .collect(),
+        };
+        for index in 0..state.maps.len() {
+            let path = state.maps[index].path.clone();
+            state.watch_dir(&path)?;
+        }
+
+        thread::spawn(move || {
+            let all: Vec<_> = (0..state.maps.len()).collect();
+            if !state.load(&all, &updates) {
+                return;
+            }
+            while let Ok(WatchEvent::Changed(event)) = event_receiver.recv() {
+                let mut changed = HashSet::new();
+                let mut event = event;
+                loop {
+                    match event {
+                        Ok(event) if is_change(&event.kind) => {
+                            changed.extend(event.paths.iter().map(|path| normalize(path)))
+                        }
+                        Ok(_) => {}
+                        Err(err) => {
+                            let path = err.paths.first().cloned().unwrap_or_default();
+                            let error = watch_error(path.clone(), err);
+                            if updates
+                                .send(MapUpdate {
+                                    path,
+                                    map: Err(error),
+                                })
+                                .is_err()
+                            {
+                                return;
+                            }
+                        }
+                    }
+                    event = match event_receiver.recv_timeout(DEBOUNCE) {
+                        Ok(WatchEvent::Changed(event)) => event,
+                        Ok(WatchEvent::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+                        Err(RecvTimeoutError::Timeout) => break,
+                    };
+                }
+                let affected = state.invalidate(&changed);
+                if !state.load(&affected, &updates) {
+                    return;
+                }
+            }
+        });
+
+        Ok(MapWatcher { receiver, events })
+    }
+}
+
+/// What the background thread of a [`MapWatcher`] works with.
+struct WatchState<Cache: ResourceCache, Reader: ResourceReader> {
+    loader: Loader<Cache, Reader>,
+    watcher: notify::RecommendedWatcher,
+    watched_dirs: HashSet<PathBuf>,
+    maps: Vec<WatchedMap>,
+}
+
+struct WatchedMap {
+    /// The path of the map, as given by the user.
+    path: PathBuf,
+    /// The files used by the map as of the last time it was loaded, along with the resource to
+    /// invalidate in the cache when each of them changes, if any. Paths are
+    /// [normalized](normalize).
+    dependencies: HashMap<PathBuf, Option<PathBuf>>,
+}
+
+impl<Cache: ResourceCache, Reader: ResourceReader> WatchState<Cache, Reader> {
+    /// Loads the maps with the indices given and sends them. Returns `false` if the watcher was
+    /// dropped.
+    fn load(&mut self, indices: &[usize], updates: &Sender<MapUpdate>) -> bool {
+        for &index in indices {
+            let path = self.maps[index].path.clone();
+            let (mut map, dependencies) = self.loader.load_map_with_dependencies(&path);
+            let dependencies = dependencies
+                .into_iter()
+                .map(|(file, owner)| (normalize(&file), owner));
+            // Keep the files used previously if the map can't be loaded, so that fixing them
+            // reloads it.
+            if map.is_ok() {
+                self.maps[index].dependencies = dependencies.collect();
+            } else {
+                self.maps[index].dependencies.extend(dependencies);
+            }
+            let files: Vec<_> = self.maps[index].dependencies.keys().cloned().collect();
+            for file in files {
+                if let Err(err) = self.watch_dir(&file) {
+                    map = Err(err);
+                }
+            }
+            if updates.send(MapUpdate { path, map }).is_err() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Invalidates the resources read from the changed files given, and returns the indices of
+    /// the maps affected.
+    fn invalidate(&mut self, changed: &HashSet<PathBuf>) -> Vec<usize> {
+        let mut affected = Vec::new();
+        for (index, map) in self.maps.iter().enumerate() {
+            let mut is_affected = changed.contains(&normalize(&map.path));
+            for path in changed {
+                if let Some(resource) = map.dependencies.get(path) {
+                    is_affected = true;
+                    if let Some(resource) = resource {
+                        self.loader.cache_mut().invalidate(resource);
+                    }
+                }
+            }
+            if is_affected {
+                // Templates hold on to the tilesets they use, so they are loaded again as well in
+                // case one of these changed.
+                for resource in map.dependencies.values().flatten() {
+                    if self.loader.cache().get_template(resource).is_some() {
+                        self.loader.cache_mut().invalidate(resource);
+                    }
+                }
+                affected.push(index);
+            }
+        }
+        affected
+    }
+
+    /// Watches the directory containing the file given, unless it is watched already. Watching
+    /// the directory rather than the file keeps working when editors replace the file on save.
+    fn watch_dir(&mut self, file: &Path) -> Result<()> {
+        let dir = normalize(file)
+            .parent()
+            .map(Path::to_owned)
+            .unwrap_or_default();
+        if self.watched_dirs.contains(&dir) {
+            return Ok(());
+        }
+        self.watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(|err| watch_error(dir.clone(), err))?;
+        self.watched_dirs.insert(dir);
+        Ok(())
+    }
+}
+
+fn is_change(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Any | EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    )
+}
+
+fn watch_error(path: PathBuf, err: notify::Error) -> Error {
+    Error::ResourceLoadingError {
+        path,
+        err: Box::new(err),
+    }
+}
+
+/// Returns the path given with its directory made absolute and canonical, like the paths of the
+/// events sent for the files in watched directories, even if the file itself doesn't exist.
+fn normalize(path: &Path) -> PathBuf {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match (dir.canonicalize(), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => path.to_owned(),
+    }
+}
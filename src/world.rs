@@ -0,0 +1,140 @@
+//! Structures related to Tiled worlds.
+
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::{
+    parse::json::{as_i32, as_objects, JsonObject, JsonObjectExt},
+    Error, Result,
+};
+
+/// A Tiled world, which arranges several maps next to each other.
+///
+/// Also see the [Tiled docs](https://doc.mapeditor.org/en/stable/manual/worlds/).
+#[derive(Debug, Clone)]
+pub struct World {
+    /// The path of the world file, as given to [`Loader::load_world`](crate::Loader::load_world).
+    pub source: PathBuf,
+    /// The maps present in this world. Maps matched by the world's [patterns](Self::patterns)
+    /// appear after the ones listed explicitly, sorted by path.
+    pub maps: Vec<WorldMap>,
+    /// The patterns used to position maps without listing them in the world file.
+    pub patterns: Vec<WorldPattern>,
+    /// Whether Tiled should only show the maps adjacent to the one being edited.
+    pub only_show_adjacent_maps: bool,
+}
+
+/// A map placed within a [`World`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct WorldMap {
+    /// The path to the map file.
+    pub source: PathBuf,
+    /// The X position of the map within the world, in pixels.
+    pub x: i32,
+    /// The Y position of the map within the world, in pixels.
+    pub y: i32,
+    /// The width of the map in pixels, if known.
+    pub width: Option<u32>,
+    /// The height of the map in pixels, if known.
+    pub height: Option<u32>,
+}
+
+/// A pattern positioning every map whose file name matches a regular expression.
+///
+/// The first two capture groups of the expression are read as the X and Y coordinates of the
+/// map, which are then multiplied and offset to obtain its position in pixels.
+#[derive(Debug, Clone)]
+pub struct WorldPattern {
+    /// The regular expression file names are matched against.
+    pub regexp: Regex,
+    /// The value the captured X coordinate is multiplied by.
+    pub multiplier_x: i32,
+    /// The value the captured Y coordinate is multiplied by.
+    pub multiplier_y: i32,
+    /// The value added to the X position of matched maps.
+    pub offset_x: i32,
+    /// The value added to the Y position of matched maps.
+    pub offset_y: i32,
+    /// The width in pixels of matched maps. Defaults to [`Self::multiplier_x`].
+    pub map_width: u32,
+    /// The height in pixels of matched maps. Defaults to [`Self::multiplier_y`].
+    pub map_height: u32,
+}
+
+impl WorldPattern {
+    /// Matches the file name of the path given against this pattern, returning the resulting map
+    /// placement if it matches.
+    pub fn match_path(&self, path: &Path) -> Option<WorldMap> {
+        let file_name = path.file_name()?.to_str()?;
+        let captures = self.regexp.captures(file_name)?;
+        let x: i32 = captures.get(1)?.as_str().parse().ok()?;
+        let y: i32 = captures.get(2)?.as_str().parse().ok()?;
+
+        Some(WorldMap {
+            source: path.to_owned(),
+            x: x * self.multiplier_x + self.offset_x,
+            y: y * self.multiplier_y + self.offset_y,
+            width: Some(self.map_width),
+            height: Some(self.map_height),
+        })
+    }
+}
+
+impl World {
+    pub(crate) fn parse_json(object: &JsonObject, path: &Path) -> Result<World> {
+        let root = path.parent().ok_or(Error::PathIsNotFile)?;
+
+        let maps = match object.opt_array("maps")? {
+            Some(maps) => as_objects(maps, "maps")?
+                .map(|map| WorldMap::parse_json(map, root))
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+        let patterns = match object.opt_array("patterns")? {
+            Some(patterns) => as_objects(patterns, "patterns")?
+                .map(WorldPattern::parse_json)
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(World {
+            source: path.to_owned(),
+            maps,
+            patterns,
+            only_show_adjacent_maps: object.opt_bool("onlyShowAdjacentMaps")?.unwrap_or(false),
+        })
+    }
+}
+
+impl WorldMap {
+    fn parse_json(object: &JsonObject, root: &Path) -> Result<WorldMap> {
+        Ok(WorldMap {
+            source: root.join(object.req("fileName", serde_json::Value::as_str)?),
+            x: object.opt_i32("x")?.unwrap_or(0),
+            y: object.opt_i32("y")?.unwrap_or(0),
+            width: object.opt_u32("width")?,
+            height: object.opt_u32("height")?,
+        })
+    }
+}
+
+impl WorldPattern {
+    fn parse_json(object: &JsonObject) -> Result<WorldPattern> {
+        let multiplier_x = object.req("multiplierX", as_i32)?;
+        let multiplier_y = object.req("multiplierY", as_i32)?;
+        Ok(WorldPattern {
+            regexp: object.req("regexp", |v| Regex::new(v.as_str()?).ok())?,
+            multiplier_x,
+            multiplier_y,
+            offset_x: object.opt_i32("offsetX")?.unwrap_or(0),
+            offset_y: object.opt_i32("offsetY")?.unwrap_or(0),
+            map_width: object
+                .opt_u32("mapWidth")?
+                .unwrap_or(multiplier_x.unsigned_abs()),
+            map_height: object
+                .opt_u32("mapHeight")?
+                .unwrap_or(multiplier_y.unsigned_abs()),
+        })
+    }
+}
@@ -0,0 +1,95 @@
+pub mod xml;
+
+/// Options for writing maps, see [`Map::write_tmx()`](crate::Map::write_tmx()).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriteOptions {
+    /// Whether to write the map the way the file it was loaded from was written, where possible,
+    /// so that a map that was only slightly modified produces a small diff against its source.
+    /// `false` by default.
+    ///
+    /// When set, the data of each tile layer is written with the encoding and compression of its
+    /// source, and compressed data whose tiles haven't changed is written back exactly as it was
+    /// read. The chunk size of infinite layers, the position of the map's properties among its
+    /// tilesets and layers, its `tiledversion`, `renderorder`, `compressionlevel`, `nextlayerid`
+    /// and `nextobjectid` attributes, and its line endings are kept as well.
+    ///
+    /// Otherwise, tile data is written with [`tile_data_encoding`](Self::tile_data_encoding), with
+    /// the chunks of infinite layers being 16x16 tiles, like Tiled does by default.
+    pub preserve_formatting: bool,
+    /// How the data of tile layers is encoded, CSV by default. When formatting is preserved, this
+    /// only applies to the layers whose source encoding can't be written back, such as the ones
+    /// created from code.
+    pub tile_data_encoding: TileDataEncoding,
+}
+
+/// How the data of tile layers is encoded when writing maps, see [`WriteOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum TileDataEncoding {
+    /// GIDs written as comma-separated values, one row of tiles per line.
+    #[default]
+    Csv,
+    /// GIDs written as base64-encoded little-endian 32-bit integers.
+    Base64,
+    /// Like [`Base64`](Self::Base64), with the integers compressed with zlib before being encoded.
+    Base64Zlib,
+    /// Like [`Base64`](Self::Base64), with the integers compressed with gzip before being encoded.
+    Base64Gzip,
+    /// Like [`Base64`](Self::Base64), with the integers compressed with Zstandard before being
+    /// encoded.
+    #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+    Base64Zstd,
+}
+
+impl TileDataEncoding {
+    /// The value of the `encoding` attribute of the `<data>` element.
+    pub(crate) fn encoding(self) -> &'static str {
+        match self {
+            TileDataEncoding::Csv => "csv",
+            _ => "base64",
+        }
+    }
+
+    /// The value of the `compression` attribute of the `<data>` element, if any.
+    pub(crate) fn compression(self) -> Option<&'static str> {
+        match self {
+            TileDataEncoding::Csv | TileDataEncoding::Base64 => None,
+            TileDataEncoding::Base64Zlib => Some("zlib"),
+            TileDataEncoding::Base64Gzip => Some("gzip"),
+            #[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+            TileDataEncoding::Base64Zstd => Some("zstd"),
+        }
+    }
+}
+
+/// Information about the file some data was loaded from, which is only used to write it back the
+/// same way. It is ignored when comparing, so that the same data loaded from different files, or
+/// created from code, still compares equal.
+#[derive(Debug, Clone)]
+pub(crate) struct SourceInfo<T>(Option<Box<T>>);
+
+impl<T> SourceInfo<T> {
+    pub(crate) fn new(info: T) -> Self {
+        Self(Some(Box::new(info)))
+    }
+
+    pub(crate) fn get(&self) -> Option<&T> {
+        self.0.as_deref()
+    }
+
+    pub(crate) fn get_mut(&mut self) -> Option<&mut T> {
+        self.0.as_deref_mut()
+    }
+}
+
+impl<T> Default for SourceInfo<T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<T> PartialEq for SourceInfo<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,18 @@
+use std::{io::Write, path::Path};
+
+use crate::{Error, Map, Result, WriteOptions};
+
+pub fn write_map(map: &Map, writer: impl Write, path: &Path, options: WriteOptions) -> Result<()> {
+    if map.writes_crlf(options) {
+        write_map_to(map, super::CrlfWriter::new(writer), path, options)
+    } else {
+        write_map_to(map, writer, path, options)
+    }
+}
+
+fn write_map_to(map: &Map, writer: impl Write, path: &Path, options: WriteOptions) -> Result<()> {
+    let mut writer = super::start_document(writer)?;
+    let root_path = path.parent().ok_or(Error::PathIsNotFile)?;
+    map.write_xml(&mut writer, root_path, options)?;
+    super::end_document(writer)
+}
@@ -0,0 +1,70 @@
+use std::io::{self, Write};
+
+use xml::{writer::XmlEvent, EmitterConfig, EventWriter};
+
+use crate::{Error, Result};
+
+mod map;
+mod template;
+mod tileset;
+pub use map::*;
+pub use template::*;
+pub use tileset::*;
+
+/// Creates an XML writer that writes the XML declaration and indents its output the way Tiled does.
+fn start_document<W: Write>(writer: W) -> Result<EventWriter<W>> {
+    let mut writer = EmitterConfig::new()
+        .perform_indent(true)
+        .indent_string(" ")
+        .pad_self_closing(false)
+        .create_writer(writer);
+    writer
+        .write(XmlEvent::StartDocument {
+            version: xml::common::XmlVersion::Version10,
+            encoding: Some("UTF-8"),
+            standalone: None,
+        })
+        .map_err(Error::XmlEncodingError)?;
+    Ok(writer)
+}
+
+/// Ends the document with a line break, like Tiled does.
+fn end_document<W: Write>(writer: EventWriter<W>) -> Result<()> {
+    writer
+        .into_inner()
+        .write_all(b"\n")
+        .map_err(|err| Error::XmlEncodingError(xml::writer::Error::Io(err)))
+}
+
+/// Turns the line feeds written into `\r\n`, except for those already preceded by a carriage
+/// return, like Git does when checking files out with Windows line endings.
+struct CrlfWriter<W> {
+    inner: W,
+    last: u8,
+}
+
+impl<W> CrlfWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, last: 0 }
+    }
+}
+
+impl<W: Write> Write for CrlfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut start = 0;
+        for (index, &byte) in buf.iter().enumerate() {
+            if byte == b'\n' && self.last != b'\r' {
+                self.inner.write_all(&buf[start..index])?;
+                self.inner.write_all(b"\r")?;
+                start = index;
+            }
+            self.last = byte;
+        }
+        self.inner.write_all(&buf[start..])?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,30 @@
+use std::{io::Write, path::Path};
+
+use crate::{
+    util::{relative_path, write_empty_element, write_end_element, write_start_element},
+    Error, Gid, Result, Template,
+};
+
+pub fn write_template(template: &Template, writer: impl Write, path: &Path) -> Result<()> {
+    let mut writer = super::start_document(writer)?;
+    let root_path = path.parent().ok_or(Error::PathIsNotFile)?;
+
+    write_start_element(&mut writer, "template", &[])?;
+    if let Some(tileset) = &template.tileset {
+        // Tile objects of templates always refer to the template's tileset with a first GID of 1.
+        match &template.tileset_source {
+            Some(source) => write_empty_element(
+                &mut writer,
+                "tileset",
+                &[
+                    ("firstgid", "1".to_owned()),
+                    ("source", relative_path(root_path, source)),
+                ],
+            )?,
+            None => tileset.write_xml(&mut writer, root_path, Some(Gid(1)))?,
+        }
+    }
+    template.object.write_xml(&mut writer, &[], root_path)?;
+    write_end_element(&mut writer)?;
+    super::end_document(writer)
+}
@@ -0,0 +1,10 @@
+use std::{io::Write, path::Path};
+
+use crate::{Error, Result, Tileset};
+
+pub fn write_tileset(tileset: &Tileset, writer: impl Write, path: &Path) -> Result<()> {
+    let mut writer = super::start_document(writer)?;
+    let root_path = path.parent().ok_or(Error::PathIsNotFile)?;
+    tileset.write_xml(&mut writer, root_path, None)?;
+    super::end_document(writer)
+}
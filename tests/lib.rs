@@ -1,9 +1,47 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 use tiled::{
-    Color, FiniteTileLayer, GroupLayer, Layer, LayerType, Loader, Map, ObjectLayer, ObjectShape,
-    PropertyValue, ResourceCache, TileLayer, TilesetLocation, WangId,
+    coords::{hex::HexLayout, staggered::StaggeredLayout},
+    AnimationState, ChunkData, Color, CompatibilityIssue, DataDecoder, ElementLocation,
+    EmbeddedResourceReader, Error, ErrorPolicy, FilesystemResourceReader, FillMode,
+    FiniteTileLayer, FiniteTileLayerData, FlattenedLayer, Frame, GidMapping, GridOrientation,
+    GroupLayer, HorizontalAlignment, Image, InfiniteTileLayerData, Layer, LayerData, LayerKind,
+    LayerTileData, LayerType, LoadLimit, LoadLimits, Loader, LruCache, LruCapacity, Map,
+    MapBuilder, MapEvent, MemoryResourceReader, Object, ObjectAlignment, ObjectData, ObjectIndex,
+    ObjectLayer, ObjectShape, ObjectTransform, Orientation, PrefixMappedReader, PropertiesExt,
+    PropertyChange, PropertyOwner, PropertyValue, ResizeAnchor, ResolvedShape, ResourceCache,
+    ResourceReader, SharedCache, StaggerAxis, StaggerIndex, TileChange, TileData, TileDataEncoding,
+    TileLayer, TileRenderSize, TileTransform, TileTransformations, TilesetBuilder, TilesetGrid,
+    TilesetLocation, ValidationIssue, Version, VerticalAlignment, VirtualPath, WangId, WangSetType,
+    Warning, WarningKind, WindingOrder, WriteOptions,
 };
 
+/// Serves a single in-memory file, falling back to the filesystem for everything else.
+struct InMemoryResourceReader {
+    path: PathBuf,
+    data: Vec<u8>,
+}
+
+impl ResourceReader for InMemoryResourceReader {
+    type Resource = Box<dyn Read>;
+    type Error = std::io::Error;
+
+    fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+        if path == self.path {
+            Ok(Box::new(Cursor::new(self.data.clone())))
+        } else {
+            Ok(Box::new(FilesystemResourceReader.read_from(path)?))
+        }
+    }
+}
+
 fn as_finite<'map>(data: TileLayer<'map>) -> FiniteTileLayer<'map> {
     match data {
         TileLayer::Finite(data) => data,
@@ -34,14 +72,17 @@ fn test_gzip_and_zlib_encoded_and_raw_are_the_same() {
     let z = loader.load_tmx_map("assets/tiled_base64_zlib.tmx").unwrap();
     let g = loader.load_tmx_map("assets/tiled_base64_gzip.tmx").unwrap();
     let r = loader.load_tmx_map("assets/tiled_base64.tmx").unwrap();
-    let zstd = loader
-        .load_tmx_map("assets/tiled_base64_zstandard.tmx")
-        .unwrap();
     let c = Loader::new().load_tmx_map("assets/tiled_csv.tmx").unwrap();
     compare_everything_but_tileset_sources(&z, &g);
     compare_everything_but_tileset_sources(&z, &r);
     compare_everything_but_tileset_sources(&z, &c);
-    compare_everything_but_tileset_sources(&z, &zstd);
+    #[cfg(feature = "zstd")]
+    {
+        let zstd = loader
+            .load_tmx_map("assets/tiled_base64_zstandard.tmx")
+            .unwrap();
+        compare_everything_but_tileset_sources(&z, &zstd);
+    }
 
     let layer = as_finite(c.get_layer(0).unwrap().as_tile_layer().unwrap());
     {
@@ -134,364 +175,4936 @@ fn test_infinite_map() {
 }
 
 #[test]
-fn test_image_layers() {
+fn test_infinite_map_chunks() {
     let r = Loader::new()
-        .load_tmx_map("assets/tiled_image_layers.tmx")
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
         .unwrap();
-    assert_eq!(r.layers().len(), 2);
-    let mut image_layers = r.layers().map(|layer| {
-        if let LayerType::Image(img) = layer.layer_type() {
-            (img, layer)
-        } else {
-            panic!("Found layer that isn't an image layer")
+
+    if let TileLayer::Infinite(inf) = &r.get_layer(1).unwrap().as_tile_layer().unwrap() {
+        let mut tile_count = 0;
+        for (chunk_pos, chunk) in inf.chunks() {
+            assert_eq!(chunk.position(), chunk_pos);
+            assert_eq!(
+                chunk.origin(),
+                (
+                    chunk_pos.0 * ChunkData::WIDTH as i32,
+                    chunk_pos.1 * ChunkData::HEIGHT as i32
+                )
+            );
+            assert_eq!(chunk.size(), (ChunkData::WIDTH, ChunkData::HEIGHT));
+            for ((x, y), tile) in chunk.tiles() {
+                assert_eq!(ChunkData::tile_to_chunk_pos(x, y), chunk_pos);
+                assert_eq!(inf.get_tile(x, y).unwrap().id(), tile.id());
+                tile_count += 1;
+            }
         }
-    });
-    {
-        let first = image_layers.next().unwrap();
-        assert_eq!(first.1.name, "Image Layer 1");
-        assert!(
-            first.0.image.is_none(),
-            "{}'s image should be None",
-            first.1.name
-        );
-    }
-    {
-        let second = image_layers.next().unwrap();
-        assert_eq!(second.1.name, "Image Layer 2");
-        let image = second
-            .0
-            .image
-            .as_ref()
-            .unwrap_or_else(|| panic!("{}'s image shouldn't be None", second.1.name));
-        assert_eq!(image.source, PathBuf::from("assets/tilesheet.png"));
-        assert_eq!(image.width, 448);
-        assert_eq!(image.height, 192);
+        assert!(tile_count > 0);
+
+        let chunk = inf.get_chunk(0, 0).unwrap();
+        assert!(!chunk.is_empty());
+        assert!(chunk
+            .tiles()
+            .any(|(pos, tile)| pos == (15, 15) && tile.id() == 22));
+    } else {
+        panic!("It is wrongly recognised as a finite map");
     }
 }
 
 #[test]
-fn test_tile_property() {
+fn test_infinite_layer_bounds() {
     let r = Loader::new()
-        .load_tmx_map("assets/tiled_base64.tmx")
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
         .unwrap();
-    let prop_value: String = if let Some(&PropertyValue::StringValue(ref v)) = r.tilesets()[0]
-        .get_tile(1)
-        .unwrap()
-        .properties
-        .get("a tile property")
-    {
-        v.clone()
+
+    let layer = r.get_layer(0).unwrap().as_tile_layer().unwrap();
+    if let TileLayer::Infinite(inf) = &layer {
+        assert_eq!(inf.bounds(), Some(((-16, 0), (31, 47))));
     } else {
-        String::new()
-    };
-    assert_eq!("123", prop_value);
+        panic!("It is wrongly recognised as a finite map");
+    }
+
+    let mut data = InfiniteTileLayerData::default();
+    assert_eq!(data.bounds(), None);
+    data.set_tile(-40, 3, Some(LayerTileData::new(0, 1)));
+    data.set_tile(2, -70, Some(LayerTileData::new(0, 1)));
+    assert_eq!(data.bounds(), Some(((-40, -70), (2, 3))));
+    assert_eq!(data.get_tile_data(-40, 3), Some(&LayerTileData::new(0, 1)));
+    // Cleared tiles no longer count towards the bounds.
+    data.set_tile(2, -70, None);
+    assert_eq!(data.bounds(), Some(((-40, 3), (-40, 3))));
 }
 
+#[cfg(feature = "zstd")]
 #[test]
-fn test_layer_property() {
-    let r = Loader::new()
-        .load_tmx_map("assets/tiled_base64.tmx")
+fn test_zstd_infinite_map() {
+    let z = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
         .unwrap();
-    let prop_value: String = if let Some(&PropertyValue::StringValue(ref v)) =
-        r.get_layer(0).unwrap().properties.get("prop3")
-    {
-        v.clone()
-    } else {
-        String::new()
-    };
-    assert_eq!("Line 1\r\nLine 2\r\nLine 3,\r\n  etc\r\n   ", prop_value);
+    let mut loader = Loader::new();
+    let zstd = loader
+        .load_tmx_map("assets/tiled_base64_zstandard_infinite.tmx")
+        .unwrap();
+    loader.set_defer_tile_data(true);
+    let deferred = loader
+        .load_tmx_map("assets/tiled_base64_zstandard_infinite.tmx")
+        .unwrap();
+    compare_everything_but_tileset_sources(&z, &zstd);
+
+    for map in [&zstd, &deferred] {
+        for (expected, layer) in z.layers().zip(map.layers()) {
+            match (expected.as_tile_layer(), layer.as_tile_layer()) {
+                (Some(TileLayer::Infinite(expected)), Some(TileLayer::Infinite(layer))) => {
+                    layer.ensure_decoded().unwrap();
+                    assert_eq!(*expected, *layer);
+                }
+                (None, None) => {}
+                _ => panic!("Layer types differ"),
+            }
+        }
+    }
 }
 
+#[cfg(not(feature = "zstd"))]
 #[test]
-fn test_object_group_property() {
-    let r = Loader::new()
-        .load_tmx_map("assets/tiled_object_groups.tmx")
-        .unwrap();
-    let group_layer = r.get_layer(1).unwrap();
-    let group_layer = group_layer.as_group_layer().unwrap();
-    let sub_layer = group_layer.get_layer(0).unwrap();
-    let prop_value: bool = if let Some(&PropertyValue::BoolValue(ref v)) =
-        sub_layer.properties.get("an object group property")
-    {
-        *v
-    } else {
-        false
-    };
-    assert!(prop_value);
+fn test_zstd_without_feature() {
+    let err = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zstandard.tmx")
+        .unwrap_err();
+    assert!(matches!(
+        err.inner(),
+        Error::InvalidEncodingFormat { compression: Some(compression), .. } if compression == "zstd"
+    ));
+    assert!(err
+        .to_string()
+        .contains("Tile layer data is compressed using zstd, which requires the `zstd` feature"));
 }
-#[test]
-fn test_tileset_property() {
-    let r = Loader::new()
-        .load_tmx_map("assets/tiled_base64.tmx")
-        .unwrap();
-    let prop_value: String = if let Some(&PropertyValue::StringValue(ref v)) =
-        r.tilesets()[0].properties.get("tileset property")
-    {
-        v.clone()
-    } else {
-        String::new()
-    };
-    assert_eq!("tsp", prop_value);
+
+/// Passes data through untouched, counting how many times it was used.
+struct PassthroughDecoder(Arc<AtomicUsize>);
+
+impl DataDecoder for PassthroughDecoder {
+    fn decompress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(data.to_vec())
+    }
 }
 
 #[test]
-fn test_flipped() {
-    let r = Loader::new()
-        .load_tmx_map("assets/tiled_flipped.tmx")
-        .unwrap();
-    let layer = r.get_layer(0).unwrap().as_tile_layer().unwrap();
+fn test_custom_data_decoder() {
+    let path = "assets/tiled_base64.tmx";
+    let expected = Loader::new().load_tmx_map(path).unwrap();
+    let data = std::fs::read_to_string(path).unwrap().replace(
+        r#"<data encoding="base64">"#,
+        r#"<data encoding="base64" compression="passthrough">"#,
+    );
+    let reader = InMemoryResourceReader {
+        path: PathBuf::from(path),
+        data: data.into_bytes(),
+    };
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
 
-    let t1 = layer.get_tile(0, 0).unwrap();
-    let t2 = layer.get_tile(1, 0).unwrap();
-    let t3 = layer.get_tile(0, 1).unwrap();
-    let t4 = layer.get_tile(1, 1).unwrap();
-    assert_eq!(t1.id(), t2.id());
-    assert_eq!(t2.id(), t3.id());
-    assert_eq!(t3.id(), t4.id());
-    assert!(t1.flip_d);
-    assert!(t1.flip_h);
-    assert!(t1.flip_v);
-    assert!(!t2.flip_d);
-    assert!(!t2.flip_h);
-    assert!(t2.flip_v);
-    assert!(!t3.flip_d);
-    assert!(t3.flip_h);
-    assert!(!t3.flip_v);
-    assert!(t4.flip_d);
-    assert!(!t4.flip_h);
-    assert!(!t4.flip_v);
+    // Unknown compressions can't be decoded.
+    assert!(matches!(
+        loader.load_tmx_map(path).unwrap_err().inner(),
+        Error::InvalidEncodingFormat { .. }
+    ));
+
+    let uses = Arc::new(AtomicUsize::new(0));
+    loader.register_decoder("passthrough", PassthroughDecoder(uses.clone()));
+    let map = loader.load_tmx_map(path).unwrap();
+    assert_eq!(uses.load(Ordering::SeqCst), 1);
+    let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+    let expected_layer = as_finite(expected.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert_eq!(*layer, *expected_layer);
+
+    // Deferred layers are decoded with the decoder registered when they were loaded.
+    loader.set_defer_tile_data(true);
+    let map = loader.load_tmx_map(path).unwrap();
+    assert_eq!(uses.load(Ordering::SeqCst), 1);
+    map.get_layer(0)
+        .unwrap()
+        .as_tile_layer()
+        .unwrap()
+        .ensure_decoded()
+        .unwrap();
+    assert_eq!(uses.load(Ordering::SeqCst), 2);
 }
 
 #[test]
-fn test_ldk_export() {
-    let r = Loader::new()
-        .load_tmx_map("assets/ldk_tiled_export.tmx")
-        .unwrap();
-    let layer = as_finite(r.get_layer(0).unwrap().as_tile_layer().unwrap());
-    {
-        assert_eq!(layer.width(), 8);
-        assert_eq!(layer.height(), 8);
-    }
-    assert!(layer.get_tile(0, 0).is_none());
-    assert_eq!(layer.get_tile(0, 1).unwrap().id(), 0);
+fn test_replacing_builtin_decoder() {
+    let uses = Arc::new(AtomicUsize::new(0));
+    let mut loader = Loader::new();
+    loader.register_decoder("zlib", PassthroughDecoder(uses.clone()));
+    // Passing zlib data through untouched doesn't give valid tile data, but the decoder is used.
+    let _ = loader.load_tmx_map("assets/tiled_base64_zlib.tmx");
+    assert!(uses.load(Ordering::SeqCst) > 0);
+    // Other compressions are unaffected.
+    loader.load_tmx_map("assets/tiled_base64_gzip.tmx").unwrap();
 }
 
 #[test]
-fn test_parallax_layers() {
-    let r = Loader::new()
-        .load_tmx_map("assets/tiled_parallax.tmx")
+fn test_multiple_external_tilesets() {
+    let mut loader = Loader::new();
+    let map = loader
+        .load_tmx_map("assets/tiled_multiple_external_tilesets.tmx")
         .unwrap();
-    for (i, layer) in r.layers().enumerate() {
-        match i {
-            0 => {
-                assert_eq!(layer.name, "Background");
-                assert_eq!(layer.parallax_x, 0.5);
-                assert_eq!(layer.parallax_y, 0.75);
-            }
-            1 => {
-                assert_eq!(layer.name, "Middle");
-                assert_eq!(layer.parallax_x, 1.0);
-                assert_eq!(layer.parallax_y, 1.0);
-            }
-            2 => {
-                assert_eq!(layer.name, "Foreground");
-                assert_eq!(layer.parallax_x, 2.0);
-                assert_eq!(layer.parallax_y, 2.0);
-            }
-            _ => panic!("unexpected layer"),
-        }
+
+    let external = [
+        (0, "assets/tilesheet.tsx"),
+        (1, "assets/tilesheet_wangsets.tsx"),
+        (3, "assets/tiled_legacy_terrain.tsx"),
+        (4, "assets/tiled_tile_collision.tsx"),
+    ];
+    assert_eq!(map.tilesets().len(), 5);
+    assert_eq!(map.tilesets()[2].name, "embedded");
+    for (index, path) in external {
+        let tileset = Loader::new().load_tsx_tileset(path).unwrap();
+        assert_eq!(*map.tilesets()[index], tileset);
+        assert!(Arc::ptr_eq(
+            &map.tilesets()[index],
+            &loader.cache().get_tileset(path).unwrap()
+        ));
     }
-}
 
-#[test]
-fn test_object_property() {
-    let r = Loader::new()
-        .load_tmx_map("assets/tiled_object_property.tmx")
+    let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+    for x in 0..4 {
+        let tile = layer.get_tile(x, 0).unwrap();
+        assert_eq!(tile.tileset_index(), x as usize);
+        assert_eq!(tile.id(), x as u32);
+    }
+    let tile = layer.get_tile(0, 1).unwrap();
+    assert_eq!(tile.tileset_index(), 4);
+    assert_eq!(tile.get_tile().unwrap().collision_shapes().count(), 6);
+
+    // Loading the map again reuses the cached tilesets.
+    let again = loader
+        .load_tmx_map("assets/tiled_multiple_external_tilesets.tmx")
         .unwrap();
-    let layer = r.get_layer(1).unwrap();
-    let prop_value = if let Some(PropertyValue::ObjectValue(v)) = layer
-        .as_object_layer()
-        .unwrap()
-        .get_object(0)
-        .unwrap()
-        .properties
-        .get("object property")
-    {
-        *v
-    } else {
-        0
-    };
-    assert_eq!(3, prop_value);
+    assert!(Arc::ptr_eq(&map.tilesets()[0], &again.tilesets()[0]));
 }
 
 #[test]
-fn test_tint_color() {
-    let r = Loader::new()
-        .load_tmx_map("assets/tiled_image_layers.tmx")
+fn test_load_from_reader() {
+    let expected = Loader::new()
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+
+    let data = std::fs::read("assets/tiled_base64_external.tmx").unwrap();
+    let mut loader = Loader::new();
+    let map = loader
+        .load_tmx_map_from_reader(Cursor::new(data.clone()), "assets/does_not_exist.tmx")
+        .unwrap();
+    compare_everything_but_tileset_sources(&expected, &map);
+    assert_eq!(map.tilesets(), expected.tilesets());
+    // External files are still resolved relative to the virtual path.
+    assert!(loader.cache().get_tileset("assets/tilesheet.tsx").is_some());
+    assert!(matches!(
+        Loader::new().load_tmx_map_from_reader(&data[..], "somewhere/else.tmx"),
+        Err(Error::ResourceLoadingError { .. })
+    ));
+
+    let data = std::fs::read("assets/tilesheet.tsx").unwrap();
+    let tileset = Loader::new()
+        .load_tsx_tileset_from_reader(&data[..], "assets/virtual.tsx")
         .unwrap();
     assert_eq!(
-        r.get_layer(0).unwrap().tint_color,
-        Some(Color {
-            alpha: 0x12,
-            red: 0x34,
-            green: 0x56,
-            blue: 0x78
-        })
-    );
-    assert_eq!(
-        r.get_layer(1).unwrap().tint_color,
-        Some(Color {
-            alpha: 0xFF,
-            red: 0x12,
-            green: 0x34,
-            blue: 0x56
-        })
+        tileset.image.unwrap().source,
+        PathBuf::from("assets/tilesheet.png")
     );
 }
 
 #[test]
-fn test_group_layers() {
-    let r = Loader::new()
+fn test_property_names_are_shared() {
+    let map = Loader::new()
         .load_tmx_map("assets/tiled_group_layers.tmx")
         .unwrap();
+    let key_of = |layer: Layer| layer.properties.get_key_value("key").unwrap().0.clone();
+    let first = key_of(map.get_layer(0).unwrap());
+    let group = key_of(map.get_layer(1).unwrap());
+    assert!(Arc::ptr_eq(&first, &group));
+}
 
-    // Depth = 0
-    let layer_tile_1 = r.get_layer(0).unwrap();
-    let layer_group_1 = r.get_layer(1).unwrap();
-    let layer_group_2 = r.get_layer(2).unwrap();
+#[test]
+fn test_lru_cache() {
+    let tileset = |path: &str| Arc::new(Loader::new().load_tsx_tileset(path).unwrap());
+    let a = tileset("assets/tilesheet.tsx");
+    let b = tileset("assets/tiled_tile_collision.tsx");
+    let c = tileset("assets/tiled_legacy_terrain.tsx");
+
+    let mut cache = LruCache::new(2);
+    cache.insert_tileset("a", a.clone());
+    cache.insert_tileset("b", b.clone());
+    assert!(cache.get_tileset("a").is_some());
+    cache.insert_tileset("c", c.clone());
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get_tileset("b").is_none());
+    assert!(cache.get_tileset("a").is_some());
+    assert!(cache.get_tileset("c").is_some());
+
+    // A resource bigger than the whole cache is still kept until something else is inserted.
+    let mut cache = LruCache::with_capacity(LruCapacity::Bytes(1));
+    cache.insert_tileset("a", a);
+    assert!(cache.get_tileset("a").is_some());
+    cache.insert_tileset("b", b);
+    assert!(cache.get_tileset("a").is_none());
+    assert!(cache.get_tileset("b").is_some());
+    assert!(cache.estimated_bytes() > 0);
+
+    // Sizes include the data owned by the resources, such as their properties.
+    let size_of = |tileset: Arc<tiled::Tileset>| {
+        let mut cache = LruCache::with_capacity(LruCapacity::Bytes(usize::MAX));
+        cache.insert_tileset("tileset", tileset);
+        cache.estimated_bytes()
+    };
+    let mut annotated = (*c).clone();
+    annotated.properties.insert(
+        "notes".into(),
+        PropertyValue::StringValue("x".repeat(10_000)),
+    );
+    assert!(size_of(Arc::new(annotated)) >= size_of(c) + 10_000);
+
+    // Templates count the tileset they keep alive.
+    let mut loader = Loader::with_cache_and_reader(
+        LruCache::with_capacity(LruCapacity::Bytes(usize::MAX)),
+        FilesystemResourceReader,
+    );
+    loader
+        .load_tmx_map("assets/tiled_object_template.tmx")
+        .unwrap();
+    let tileset = loader
+        .cache()
+        .get_tileset("assets/tilesheet_template.tsx")
+        .unwrap();
+    let with_template = loader.cache().estimated_bytes();
+    loader
+        .cache_mut()
+        .invalidate("assets/tiled_object_template.tx");
+    let template_size = with_template - loader.cache().estimated_bytes();
+    assert!(template_size > size_of(tileset));
+
+    let mut loader = Loader::with_cache_and_reader(LruCache::new(1), FilesystemResourceReader);
+    let map = loader
+        .load_tmx_map("assets/tiled_multiple_external_tilesets.tmx")
+        .unwrap();
+    assert_eq!(map.tilesets().len(), 5);
+    assert_eq!(loader.cache().len(), 1);
+}
+
+#[test]
+fn test_loader_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let mut loader = Loader::with_cache_and_reader(SharedCache::new(), FilesystemResourceReader);
+    loader.set_error_policy(ErrorPolicy::SkipAndWarn);
+    assert_send_sync(&loader);
+    assert_send_sync(&Loader::new());
+
+    // Loaders can be shared between threads to read their settings.
+    std::thread::scope(|scope| {
+        scope.spawn(|| assert_eq!(loader.error_policy(), ErrorPolicy::SkipAndWarn));
+    });
+    loader
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    assert!(loader.take_warnings().is_empty());
+}
+
+#[test]
+fn test_shared_cache() {
+    let cache = SharedCache::new();
+    let path = "assets/tiled_multiple_external_tilesets.tmx";
+    let first = Loader::with_cache_and_reader(cache.clone(), FilesystemResourceReader)
+        .load_tmx_map(path)
+        .unwrap();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let cache = cache.clone();
+            std::thread::spawn(move || {
+                Loader::with_cache_and_reader(cache, FilesystemResourceReader)
+                    .load_tmx_map(path)
+                    .unwrap()
+            })
+        })
+        .collect();
+    let maps: Vec<Map> = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect();
+
+    let cached = cache.get_tileset("assets/tilesheet.tsx").unwrap();
+    assert!(Arc::ptr_eq(&first.tilesets()[0], &cached));
+    for map in &maps {
+        // The third tileset is embedded, so it isn't cached.
+        for index in [0, 1, 3, 4] {
+            assert!(Arc::ptr_eq(
+                &map.tilesets()[index],
+                &first.tilesets()[index]
+            ));
+        }
+    }
+}
+
+#[test]
+fn test_reload_map() {
+    let tileset_path = "assets/tilesheet.tsx";
+    let map_path = "assets/tiled_base64_external.tmx";
+    let reader = InMemoryResourceReader {
+        path: PathBuf::from(tileset_path),
+        data: std::fs::read(tileset_path).unwrap(),
+    };
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    let map = loader.load_tmx_map(map_path).unwrap();
+    assert_eq!(map.tilesets()[0].name, "tilesheet");
+
+    let edited = String::from_utf8(loader.reader().data.clone())
+        .unwrap()
+        .replace(r#"name="tilesheet""#, r#"name="edited""#);
+    loader.reader_mut().data = edited.into_bytes();
+    let cached = loader.load_tmx_map(map_path).unwrap();
+    assert_eq!(cached.tilesets()[0].name, "tilesheet");
+
+    let reloaded = loader.reload_map(map_path).unwrap();
+    assert_eq!(reloaded.tilesets()[0].name, "edited");
+    let map = loader.load_tmx_map(map_path).unwrap();
+    assert!(Arc::ptr_eq(&map.tilesets()[0], &reloaded.tilesets()[0]));
+
+    loader.cache_mut().invalidate(tileset_path);
+    assert!(loader.cache().get_tileset(tileset_path).is_none());
+    loader.load_tmx_map(map_path).unwrap();
+    assert!(loader.cache().get_tileset(tileset_path).is_some());
+    loader.cache_mut().clear();
+    assert!(loader.cache().tilesets.is_empty());
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn test_zip_resource_reader() {
+    let archive = std::fs::File::open("assets/tiled_archive.zip").unwrap();
+    let reader = tiled::ZipResourceReader::new(archive).unwrap();
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    let map = loader.load_tmx_map("maps/external.tmx").unwrap();
+    let expected = Loader::new()
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    compare_everything_but_tileset_sources(&map, &expected);
+
+    let image = map.tilesets()[0].image.as_ref().unwrap();
+    assert_eq!(image.source, Path::new("maps/../tilesets/tilesheet.png"));
+    let mut png = Vec::new();
+    loader
+        .reader_mut()
+        .read_from(&image.source)
+        .unwrap()
+        .read_to_end(&mut png)
+        .unwrap();
+    assert_eq!(png, std::fs::read("assets/tilesheet.png").unwrap());
 
     assert_eq!(
-        Some(&PropertyValue::StringValue("value1".to_string())),
-        layer_tile_1.properties.get("key")
-    );
-    assert_eq!(
-        Some(&PropertyValue::ColorValue(Color {
-            alpha: 0x12,
-            red: 0x34,
-            green: 0x56,
-            blue: 0x78
-        })),
-        layer_group_1.properties.get("key")
+        loader
+            .reader_mut()
+            .list_files(Path::new("tilesets"))
+            .unwrap(),
+        vec![
+            PathBuf::from("tilesets/tilesheet.png"),
+            PathBuf::from("tilesets/tilesheet.tsx"),
+        ]
     );
+    let missing = loader.reader_mut().read_from(Path::new("maps/missing.tmx"));
+    assert_eq!(missing.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    let outside = loader.reader_mut().read_from(Path::new("../external.tmx"));
+    assert_eq!(outside.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_embedded_resource_reader() {
+    let reader: EmbeddedResourceReader = vec![
+        (
+            "maps/map.tmx",
+            include_bytes!("../assets/tiled_object_template.tmx") as &'static [u8],
+        ),
+        (
+            "maps/tiled_object_template.tx",
+            include_bytes!("../assets/tiled_object_template.tx"),
+        ),
+        (
+            "maps/tilesheet.tsx",
+            include_bytes!("../assets/tilesheet.tsx"),
+        ),
+        (
+            "maps/tilesheet_template.tsx",
+            include_bytes!("../assets/tilesheet_template.tsx"),
+        ),
+    ]
+    .into_iter()
+    .collect();
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    let map = loader.load_tmx_map("maps/map.tmx").unwrap();
+    let expected = Loader::new()
+        .load_tmx_map("assets/tiled_object_template.tmx")
+        .unwrap();
+    compare_everything_but_tileset_sources(&map, &expected);
+
+    let reader = loader.reader_mut();
+    assert!(reader
+        .read_from(Path::new("maps/../maps/./map.tmx"))
+        .is_ok());
+    let missing = reader.read_from(Path::new("assets/tiled_base64.tmx"));
+    assert_eq!(missing.unwrap_err().kind(), std::io::ErrorKind::NotFound);
     assert_eq!(
-        Some(&PropertyValue::StringValue("value5".to_string())),
-        layer_group_2.properties.get("key")
+        reader.list_files(Path::new("maps")).unwrap(),
+        vec![
+            PathBuf::from("maps/map.tmx"),
+            PathBuf::from("maps/tiled_object_template.tx"),
+            PathBuf::from("maps/tilesheet.tsx"),
+            PathBuf::from("maps/tilesheet_template.tsx"),
+        ]
     );
+}
 
-    // Depth = 1
-    let layer_group_1 = layer_group_1.as_group_layer().unwrap();
-    let layer_tile_2 = layer_group_1.get_layer(0).unwrap();
-    let layer_group_2 = layer_group_2.as_group_layer().unwrap();
-    let layer_group_3 = layer_group_2.get_layer(0).unwrap();
+#[cfg(feature = "http")]
+#[test]
+fn test_url_resource_reader() {
+    let requested = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let fetch = {
+        let requested = requested.clone();
+        move |url: &str| {
+            requested.lock().unwrap().push(url.to_owned());
+            let path = url.strip_prefix("https://example.com/assets/").unwrap();
+            std::fs::read(Path::new("assets").join(path))
+        }
+    };
+    let reader = tiled::UrlResourceReader::new("https://example.com/assets", fetch);
+    assert_eq!(reader.base_url(), "https://example.com/assets/");
     assert_eq!(
-        Some(&PropertyValue::StringValue("value2".to_string())),
-        layer_tile_2.properties.get("key")
+        reader.url_for(Path::new("maps/../my map.tmx")).unwrap(),
+        "https://example.com/assets/my%20map.tmx"
     );
     assert_eq!(
-        Some(&PropertyValue::StringValue("value6".to_string())),
-        layer_group_3.properties.get("key")
+        reader
+            .url_for(Path::new("http://other.com/map.tmx"))
+            .unwrap(),
+        "http://other.com/map.tmx"
     );
+    assert!(reader.url_for(Path::new("../map.tmx")).is_none());
 
-    // Depth = 2
-    let layer_group_3 = layer_group_3.as_group_layer().unwrap();
-    let layer_tile_3 = layer_group_3.get_layer(0).unwrap();
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    let map = loader.load_tmx_map("tiled_base64_external.tmx").unwrap();
+    assert_eq!(map.tilesets()[0].name, "tilesheet");
     assert_eq!(
-        Some(&PropertyValue::StringValue("value3".to_string())),
-        layer_tile_3.properties.get("key")
+        *requested.lock().unwrap(),
+        vec![
+            "https://example.com/assets/tiled_base64_external.tmx",
+            "https://example.com/assets/tilesheet.tsx",
+        ]
     );
 }
 
+#[cfg(feature = "async")]
 #[test]
-fn test_object_template_property() {
-    let r = Loader::new()
-        .load_tmx_map("assets/tiled_object_template.tmx")
-        .unwrap();
+fn test_load_from_stream() {
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+    };
 
-    let object_layer = r.get_layer(1).unwrap().as_object_layer().unwrap();
-    let object = object_layer.get_object(0).unwrap(); // The templated object
-    let object_nt = object_layer.get_object(1).unwrap(); // The non-templated object
+    /// Yields its chunks one at a time, returning `Pending` before each of them.
+    struct ChunkStream {
+        chunks: Vec<Result<Vec<u8>, std::io::Error>>,
+        ready: bool,
+    }
 
-    // Test core properties
-    assert_eq!(
-        object.shape,
-        ObjectShape::Rect {
-            width: 32.0,
-            height: 32.0
+    impl futures_core::Stream for ChunkStream {
+        type Item = Result<Vec<u8>, std::io::Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.ready = false;
+            if self.chunks.is_empty() {
+                Poll::Ready(None)
+            } else {
+                Poll::Ready(Some(self.chunks.remove(0)))
+            }
         }
+    }
+
+    fn chunks(path: &str) -> ChunkStream {
+        let data = std::fs::read(path).unwrap();
+        ChunkStream {
+            chunks: data.chunks(100).map(|chunk| Ok(chunk.to_vec())).collect(),
+            ready: false,
+        }
+    }
+
+    let mut loader = Loader::new();
+    let map = pollster::block_on(loader.load_tmx_map_from_stream(
+        chunks("assets/tiled_base64_external.tmx"),
+        "assets/virtual_map.tmx",
+    ))
+    .unwrap();
+    assert_eq!(
+        map,
+        Loader::new()
+            .load_tmx_map("assets/tiled_base64_external.tmx")
+            .unwrap()
     );
-    assert_eq!(object.x, 32.0);
-    assert_eq!(object.y, 32.0);
 
-    // Test properties are copied over
+    let tileset = pollster::block_on(
+        loader.load_tsx_tileset_from_stream(chunks("assets/tilesheet.tsx"), "assets/virtual.tsx"),
+    )
+    .unwrap();
+    assert_eq!(tileset.name, "tilesheet");
+
+    let mut stream = chunks("assets/tiled_base64_external.tmx");
+    stream.chunks.insert(
+        1,
+        Err(std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "reset",
+        )),
+    );
+    let err = pollster::block_on(loader.load_tmx_map_from_stream(stream, "assets/virtual_map.tmx"))
+        .unwrap_err();
+    match err {
+        tiled::Error::ResourceLoadingError { path, err } => {
+            assert_eq!(path, Path::new("assets/virtual_map.tmx"));
+            assert_eq!(err.to_string(), "reset");
+        }
+        err => panic!("Unexpected error: {:?}", err),
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mmap_resource_reader() {
+    let reader = unsafe { tiled::MmapResourceReader::new() };
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    for path in [
+        "assets/tiled_base64_external.tmx",
+        "assets/tiled_base64_zlib_infinite.tmx",
+        "assets/tiled_object_template.tmx",
+    ] {
+        let map = loader.load_tmx_map(path).unwrap();
+        assert_eq!(map, Loader::new().load_tmx_map(path).unwrap());
+    }
+
+    let file = loader
+        .reader_mut()
+        .read_from(Path::new("assets/tilesheet.tsx"))
+        .unwrap();
     assert_eq!(
-        Some(&PropertyValue::IntValue(1)),
-        object.properties.get("property")
+        file.as_bytes(),
+        std::fs::read("assets/tilesheet.tsx").unwrap()
+    );
+    assert!(loader.load_tmx_map("assets/missing.tmx").is_err());
+    assert_eq!(
+        loader
+            .reader_mut()
+            .list_files(Path::new("assets/world"))
+            .unwrap()
+            .len(),
+        FilesystemResourceReader
+            .list_files(Path::new("assets/world"))
+            .unwrap()
+            .len()
     );
+}
 
-    // Test tileset handling
+#[test]
+fn test_reader_combinators() {
+    let mod_files = EmbeddedResourceReader::new().with_file(
+        "game/tilesheet.tsx",
+        include_bytes!("../assets/tilesheet_template.tsx"),
+    );
+    let base_files = PrefixMappedReader::new(FilesystemResourceReader)
+        .with_mapping("game", "assets")
+        .with_mapping("game/maps", "assets/does_not_exist");
     assert_eq!(
-        object.get_tile().unwrap().get_tileset().name,
-        "tilesheet_template"
+        base_files.map_path(Path::new("game/maps/map.tmx")),
+        Path::new("assets/does_not_exist/map.tmx")
     );
     assert_eq!(
-        object_nt.get_tile().unwrap().get_tileset().name,
-        "tilesheet"
+        base_files.map_path(Path::new("other/map.tmx")),
+        Path::new("other/map.tmx")
+    );
+
+    let mut loader = Loader::with_cache_and_reader(
+        tiled::DefaultResourceCache::new(),
+        mod_files.or_else(base_files),
+    );
+    let map = loader
+        .load_tmx_map("game/tiled_base64_external.tmx")
+        .unwrap();
+    assert_eq!(map.tilesets()[0].name, "tilesheet_template");
+    assert!(loader
+        .reader_mut()
+        .read_from(Path::new("game/maps/map.tmx"))
+        .is_err());
+
+    let files = loader.reader_mut().list_files(Path::new("game")).unwrap();
+    assert_eq!(files[0], Path::new("game/tilesheet.tsx"));
+    assert!(files.contains(&PathBuf::from("game/tiled_base64_external.tmx")));
+    assert_eq!(
+        files
+            .iter()
+            .filter(|file| file.ends_with("tilesheet.tsx"))
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn test_error_location() {
+    let path = "assets/tiled_csv.tmx";
+    let load = |from: &str, to: &str| {
+        let data = std::fs::read_to_string(path).unwrap().replace(from, to);
+        let reader = InMemoryResourceReader {
+            path: PathBuf::from(path),
+            data: data.into_bytes(),
+        };
+        Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader)
+            .load_tmx_map(path)
+            .unwrap_err()
+    };
+
+    let err = load(
+        r#"<property name="prop1" value="12"/>"#,
+        r#"<property name="prop1" type="int" value="twelve"/>"#,
+    );
+    match &err {
+        Error::ElementError {
+            path: error_path,
+            element,
+            line,
+            column,
+            err,
+        } => {
+            assert_eq!(error_path, Path::new(path));
+            assert_eq!(element, "property");
+            assert_eq!((*line, *column), (16, 4));
+            assert!(matches!(**err, Error::InvalidPropertyValue { .. }));
+        }
+        _ => panic!("Unexpected error: {:?}", err),
+    }
+    assert!(matches!(err.inner(), Error::InvalidPropertyValue { .. }));
+    assert!(err
+        .to_string()
+        .ends_with("(in <property> at assets/tiled_csv.tmx:16:4)"));
+
+    let err = load(
+        r#"width="100" height="100">"#,
+        r#"width="wide" height="100">"#,
     );
     assert!(matches!(
-        object.get_tile().unwrap().tileset_location(),
-        TilesetLocation::Template(..)
+        err,
+        Error::ElementError { ref element, line: 14, .. } if element == "layer"
     ));
+    assert!(matches!(err.inner(), Error::MalformedAttributes(_)));
+}
+
+#[test]
+fn test_skip_and_warn() {
+    let path = "assets/tiled_csv.tmx";
+    let data = std::fs::read_to_string(path)
+        .unwrap()
+        .replace(
+            r#"<property name="prop1" value="12"/>"#,
+            r#"<property name="prop1" type="vector" value="12"/>"#,
+        )
+        .replace(r#"<tile id="1">"#, r#"<tile id="1" probability="high">"#);
+    let mut loader = Loader::with_cache_and_reader(
+        tiled::DefaultResourceCache::new(),
+        InMemoryResourceReader {
+            path: PathBuf::from(path),
+            data: data.into_bytes(),
+        },
+    );
+    assert_eq!(loader.error_policy(), ErrorPolicy::Fail);
+    assert!(loader.load_tmx_map(path).is_err());
+    assert!(loader.take_warnings().is_empty());
+
+    loader.set_error_policy(ErrorPolicy::SkipAndWarn);
+    let map = loader.load_tmx_map(path).unwrap();
+    let layer = map.get_layer(0).unwrap();
+    assert!(!layer.properties.contains_key("prop1"));
+    assert!(layer.properties.contains_key("prop2"));
+    assert!(layer.properties.contains_key("prop3"));
+    let tile = map.tilesets()[0].get_tile(1).unwrap();
+    assert!(tile.properties.is_empty());
+    assert_eq!(tile.probability, 1.0);
+
+    let warnings = loader.take_warnings();
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0].path, Path::new(path));
     assert_eq!(
-        object_nt.get_tile().unwrap().tileset_location(),
-        &TilesetLocation::Map(0)
+        warnings[0].location,
+        Some(ElementLocation {
+            element: "tile".to_owned(),
+            line: 8,
+            column: 3,
+        })
     );
-    assert_eq!(object.get_tile().unwrap().id(), 44);
-    assert_eq!(object_nt.get_tile().unwrap().id(), 44);
+    assert!(matches!(
+        &warnings[1],
+        Warning {
+            location: Some(ElementLocation { element, line: 16, .. }),
+            kind: WarningKind::Skipped { reason },
+            ..
+        } if element == "property" && reason.contains("vector")
+    ));
+    assert!(loader.take_warnings().is_empty());
 }
 
 #[test]
-fn test_templates() {
+fn test_warnings() {
+    let path = "assets/tiled_csv.tmx";
+    let data = std::fs::read_to_string(path)
+        .unwrap()
+        .replace(
+            r#"<layer id="1" name="Tile Layer 1""#,
+            r#"<layer id="1" name="Tile Layer 1" mood="happy""#,
+        )
+        .replacen("35,35,35,35,35,", "35,1000,35,1000,35,", 1);
+    let mut loader = Loader::with_cache_and_reader(
+        tiled::DefaultResourceCache::new(),
+        InMemoryResourceReader {
+            path: PathBuf::from(path),
+            data: data.into_bytes(),
+        },
+    );
+    let map = loader.load_tmx_map(path).unwrap();
+    let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert!(layer.get_tile(1, 0).unwrap().get_tile().is_none());
+
+    let warnings = loader.take_warnings();
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(
+        warnings[0].kind,
+        WarningKind::UnknownAttribute {
+            element: "layer".to_owned(),
+            attribute: "mood".to_owned(),
+        }
+    );
+    assert_eq!(warnings[0].location.as_ref().unwrap().line, 14);
+    assert_eq!(warnings[1].kind, WarningKind::InvalidGid { gid: 1000 });
+    assert_eq!(warnings[1].location.as_ref().unwrap().element, "data");
+    assert_eq!(
+        warnings[1].to_string(),
+        "GID 1000 doesn't refer to a tile of any tileset (in <data> at assets/tiled_csv.tmx:24:3)"
+    );
+
     let mut loader = Loader::new();
-    let map = loader.load_tmx_map("assets/templates/example.tmx").unwrap();
+    loader
+        .load_tsx_tileset("assets/tiled_legacy_terrain.tsx")
+        .unwrap();
+    assert!(matches!(
+        loader.take_warnings().as_slice(),
+        [Warning {
+            kind: WarningKind::DeprecatedElement { element },
+            ..
+        }] if element == "terraintypes"
+    ));
+}
 
-    assert_eq!(loader.cache().templates.len(), 3);
+#[cfg(feature = "derive")]
+#[test]
+fn test_derive_from_properties() {
+    use tiled::FromProperties;
+
+    #[derive(FromProperties, Debug, PartialEq)]
+    struct Key {
+        name: String,
+    }
+
+    #[derive(FromProperties, Debug, PartialEq)]
+    struct Door {
+        #[tiled(default)]
+        locked: bool,
+        key: Option<Key>,
+    }
+
+    #[derive(FromProperties, Debug, PartialEq)]
+    struct Info {
+        #[tiled(rename = "front door")]
+        front_door: Door,
+        #[tiled(rename = "empty door")]
+        empty_door: Door,
+        plain: String,
+        missing: Option<f32>,
+    }
+
+    #[derive(FromProperties, Debug)]
+    struct Missing {
+        #[allow(dead_code)]
+        missing: i32,
+    }
+
+    #[derive(FromProperties, Debug)]
+    struct Mismatched {
+        #[allow(dead_code)]
+        plain: i32,
+    }
+
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_class_property.tmx")
+        .unwrap();
     assert_eq!(
-        if let LayerType::Tiles(x) = map.get_layer(0).unwrap().layer_type() {
-            x
-        } else {
-            panic!()
+        Info::from_properties(&map.properties).unwrap(),
+        Info {
+            front_door: Door {
+                locked: true,
+                key: Some(Key {
+                    name: "golden key".to_owned()
+                }),
+            },
+            empty_door: Door {
+                locked: false,
+                key: None,
+            },
+            plain: "after the classes".to_owned(),
+            missing: None,
         }
-        .get_tile(0, 0)
-        .unwrap()
-        .get_tileset()
-        .image
-        .as_ref()
-        .unwrap()
-        .source
-        .canonicalize()
-        .unwrap(),
-        PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/tilesheet.png"))
-            .canonicalize()
-            .unwrap()
     );
+    assert!(matches!(
+        Missing::from_properties(&map.properties),
+        Err(Error::MissingProperty { name }) if name == "missing"
+    ));
+    assert!(matches!(
+        Mismatched::from_properties(&map.properties),
+        Err(Error::PropertyTypeMismatch {
+            name,
+            expected: "int",
+            found: "string",
+        }) if name == "plain"
+    ));
 }
 
 #[test]
-fn test_reading_wang_sets() {
+fn test_typed_property_getters() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_class_property.tmx")
+        .unwrap();
+    let door = match &map.properties["front door"] {
+        PropertyValue::ClassValue { members, .. } => members,
+        _ => panic!("Not a class"),
+    };
+    assert_eq!(door.get_bool("locked"), Some(true));
+    assert_eq!(door.get_int("locked"), None);
+    assert_eq!(door.get_bool("unlocked"), None);
+    assert!(door.try_get_bool("locked").unwrap());
+    assert!(matches!(
+        door.try_get_string("locked"),
+        Err(Error::PropertyTypeMismatch {
+            expected: "string",
+            found: "bool",
+            ..
+        })
+    ));
+    assert!(matches!(
+        door.try_get_color("paint"),
+        Err(Error::MissingProperty { name }) if name == "paint"
+    ));
+    assert_eq!(
+        map.properties.try_get_string("plain").unwrap(),
+        "after the classes"
+    );
+
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    let tile = map.tilesets()[0].get_tile(1).unwrap();
+    assert_eq!(tile.properties.get_string("a tile property"), Some("123"));
+    assert_eq!(tile.properties.get_float("a tile property"), None);
+}
+
+#[test]
+fn test_resolve_object_property() {
     let mut loader = Loader::new();
     let map = loader
-        .load_tmx_map("assets/tiled_csv_wangsets.tmx")
+        .load_tmx_map("assets/tiled_object_property_dangling.tmx")
         .unwrap();
+    assert!(loader.take_warnings().is_empty());
 
-    // We will pick some random data from the wangsets for tessting
-    let tileset = map.tilesets().get(0).unwrap();
-    assert_eq!(tileset.wang_sets.len(), 3);
-    let wangset_2 = tileset.wang_sets.get(1).unwrap();
-    let tile_10 = wangset_2.wang_tiles.get(&10).unwrap();
-    assert_eq!(tile_10.wang_id, WangId([2u8, 2, 0, 2, 0, 2, 2, 2]));
-    let wangset_3 = tileset.wang_sets.get(2).unwrap();
-    let color_2 = wangset_3.wang_colors.get(1).unwrap();
-    let readed_damage = color_2.properties.get("Damage").unwrap();
-    let damage_value = &PropertyValue::FloatValue(32.1);
-    assert_eq!(readed_damage, damage_value);
+    let spawn = map
+        .resolve_object_property(&map.properties["spawn"])
+        .unwrap();
+    assert_eq!(spawn.id(), 3);
+    assert_eq!(spawn.x, 32.0);
+    assert!(map.get_object_by_id(2).is_some());
+    assert!(map.get_object_by_id(7).is_none());
+    assert!(map
+        .resolve_object_property(&PropertyValue::ObjectValue(0))
+        .is_none());
+    assert!(map
+        .resolve_object_property(&PropertyValue::IntValue(3))
+        .is_none());
+
+    loader.set_resolve_object_references(true);
+    loader
+        .load_tmx_map("assets/tiled_object_property_dangling.tmx")
+        .unwrap();
+    let warnings = loader.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].kind,
+        WarningKind::UnresolvedObjectReference {
+            property: "target".to_owned(),
+            id: 7
+        }
+    );
+    assert_eq!(
+        warnings[0].path,
+        Path::new("assets/tiled_object_property_dangling.tmx")
+    );
+}
+
+#[test]
+fn test_resolve_file_property() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_file_property.tmx")
+        .unwrap();
+    assert_eq!(map.root_path(), Path::new("assets"));
+    assert_eq!(
+        map.resolve_file_property(&map.properties["script"]),
+        Some(PathBuf::from("assets/scripts/level.lua"))
+    );
+
+    let layer = map.get_layer(0).unwrap().as_object_layer().unwrap();
+    let object = layer.get_object(0).unwrap();
+    assert_eq!(
+        map.resolve_file_property(&object.properties["music"]),
+        Some(PathBuf::from("music/theme.ogg"))
+    );
+    assert_eq!(map.resolve_file_property(&object.properties["unset"]), None);
+    assert_eq!(
+        map.resolve_file_property(&PropertyValue::StringValue("level.lua".to_owned())),
+        None
+    );
+
+    let tileset = &map.tilesets()[0];
+    assert_eq!(tileset.root_path(), Path::new("assets/templates"));
+    assert_eq!(
+        tileset.resolve_file_property(&PropertyValue::FileValue("../../data.json".to_owned())),
+        Some(PathBuf::from("data.json"))
+    );
+}
+
+#[test]
+fn test_tileset_tile_offset() {
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tiled_tileoffset.tsx")
+        .unwrap();
+    assert_eq!((tileset.offset_x, tileset.offset_y), (-4, 16));
+
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet.tsx")
+        .unwrap();
+    assert_eq!((tileset.offset_x, tileset.offset_y), (0, 0));
+}
+
+#[test]
+fn test_tileset_options() {
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tiled_tileset_options.tsx")
+        .unwrap();
+    assert_eq!(
+        tileset.grid,
+        Some(TilesetGrid {
+            orientation: GridOrientation::Isometric,
+            width: 64,
+            height: 32
+        })
+    );
+    assert_eq!(
+        tileset.transformations,
+        TileTransformations {
+            flip_h: true,
+            flip_v: false,
+            rotate: true,
+            prefer_untransformed: true
+        }
+    );
+    assert_eq!(tileset.tile_render_size, TileRenderSize::Grid);
+    assert_eq!(tileset.fill_mode, FillMode::PreserveAspectFit);
+    assert_eq!(tileset.object_alignment, ObjectAlignment::Bottom);
+    let image = tileset.get_tile(0).unwrap().image.clone().unwrap();
+    assert_eq!(
+        image.transparent_colour,
+        Some(Color {
+            alpha: 0xff,
+            red: 0xff,
+            green: 0x00,
+            blue: 0xff
+        })
+    );
+
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet.tsx")
+        .unwrap();
+    assert_eq!(tileset.grid, None);
+    assert_eq!(tileset.transformations, TileTransformations::default());
+    assert_eq!(tileset.tile_render_size, TileRenderSize::Tile);
+    assert_eq!(tileset.fill_mode, FillMode::Stretch);
+    assert_eq!(tileset.object_alignment, ObjectAlignment::Unspecified);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_apply_transparent_colour() {
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tiled_tileset_options.tsx")
+        .unwrap();
+    let image = tileset.get_tile(0).unwrap().image.clone().unwrap();
+
+    let mut pixels = image_crate::RgbaImage::from_raw(
+        3,
+        1,
+        vec![
+            0xff, 0x00, 0xff, 0xff, 0xff, 0x00, 0xfe, 0xff, 0xff, 0x00, 0xff, 0x80,
+        ],
+    )
+    .unwrap();
+    image.apply_transparent_colour(&mut pixels);
+    assert_eq!(
+        pixels.into_raw(),
+        vec![0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0xfe, 0xff, 0xff, 0x00, 0xff, 0x00]
+    );
+
+    let mut pixels = image_crate::RgbaImage::from_raw(1, 1, vec![0xff, 0x00, 0xff, 0xff]).unwrap();
+    let opaque = Image {
+        transparent_colour: None,
+        ..image
+    };
+    opaque.apply_transparent_colour(&mut pixels);
+    assert_eq!(pixels.into_raw(), vec![0xff, 0x00, 0xff, 0xff]);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_load_pixels() {
+    let mut reader = FilesystemResourceReader;
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet.tsx")
+        .unwrap();
+    let sheet = tileset
+        .image
+        .as_ref()
+        .unwrap()
+        .load_pixels(&mut reader)
+        .unwrap();
+    assert_eq!(sheet.dimensions(), (448, 192));
+
+    let tile = tileset.load_tile_pixels(15, &mut reader).unwrap().unwrap();
+    let expected = image_crate::imageops::crop_imm(&sheet, 32, 32, 32, 32).to_image();
+    assert_eq!(tile, expected);
+    assert_eq!(tileset.load_tile_pixels(84, &mut reader).unwrap(), None);
+
+    // Image collection tilesets decode the image of the tile.
+    let mut image = tileset.image.clone().unwrap();
+    image.transparent_colour = Some(Color {
+        alpha: 0xff,
+        red: sheet.get_pixel(0, 0).0[0],
+        green: sheet.get_pixel(0, 0).0[1],
+        blue: sheet.get_pixel(0, 0).0[2],
+    });
+    let collection = TilesetBuilder::new("collection", 448, 192)
+        .tile(
+            3,
+            TileData {
+                image: Some(image),
+                ..Default::default()
+            },
+        )
+        .build();
+    let pixels = collection
+        .load_tile_pixels(3, &mut reader)
+        .unwrap()
+        .unwrap();
+    assert_eq!(pixels.dimensions(), (448, 192));
+    assert_eq!(pixels.get_pixel(0, 0).0[3], 0);
+    assert_eq!(collection.load_tile_pixels(0, &mut reader).unwrap(), None);
+
+    let missing = Image {
+        source: PathBuf::from("assets/missing.png"),
+        ..tileset.image.clone().unwrap()
+    };
+    assert!(matches!(
+        missing.load_pixels(&mut reader),
+        Err(Error::ResourceLoadingError { .. })
+    ));
+    let not_an_image = Image {
+        source: PathBuf::from("assets/tilesheet.tsx"),
+        ..missing
+    };
+    assert!(matches!(
+        not_an_image.load_pixels(&mut reader),
+        Err(Error::ImageDecodingError { .. })
+    ));
+}
+
+#[cfg(feature = "atlas")]
+#[test]
+fn test_pack_atlas() {
+    let mut reader = FilesystemResourceReader;
+    let sheet = Loader::new()
+        .load_tsx_tileset("assets/tilesheet.tsx")
+        .unwrap();
+    let atlas = sheet.pack_atlas(&mut reader, 4).unwrap();
+    assert_eq!(atlas.image.dimensions(), (448, 192));
+    assert_eq!(atlas.rects.len(), 84);
+    assert_eq!(atlas.get_rect(15), sheet.tile_image_rect(15));
+
+    let image = sheet.image.clone().unwrap();
+    let tile = |image: &Image| TileData {
+        image: Some(image.clone()),
+        ..Default::default()
+    };
+    let collection = TilesetBuilder::new("collection", 448, 192)
+        .tile(0, tile(&image))
+        .tile(1, TileData::default())
+        .tile(2, tile(&image))
+        .tile(5, tile(&image))
+        .build();
+    let atlas = collection.pack_atlas(&mut reader, 4).unwrap();
+    // Two images fit in each row of an atlas 1024 pixels wide.
+    assert_eq!(atlas.image.dimensions(), (1024, 192 * 2 + 4));
+    assert_eq!(atlas.get_rect(0), Some((0, 0, 448, 192)));
+    assert_eq!(atlas.get_rect(1), None);
+    assert_eq!(atlas.get_rect(2), Some((452, 0, 448, 192)));
+    assert_eq!(atlas.get_rect(5), Some((0, 196, 448, 192)));
+    assert_eq!(
+        atlas.get_uv_rect(5),
+        Some((0.0, 196.0 / 388.0, 448.0 / 1024.0, 1.0))
+    );
+    let pixels = image.load_pixels(&mut reader).unwrap();
+    let packed = image_crate::imageops::crop_imm(&atlas.image, 452, 0, 448, 192).to_image();
+    assert_eq!(packed, pixels);
+
+    let empty = TilesetBuilder::new("empty", 32, 32).build();
+    let atlas = empty.pack_atlas(&mut reader, 4).unwrap();
+    assert_eq!(atlas.image.dimensions(), (0, 0));
+    assert!(atlas.rects.is_empty());
+}
+
+#[cfg(feature = "render")]
+#[test]
+fn test_rasterize() {
+    use tiled::render::{rasterize, RenderOptions};
+
+    // Pixels that are fully transparent are left untouched when drawn on an empty image.
+    fn visible(mut pixels: image_crate::RgbaImage) -> image_crate::RgbaImage {
+        for pixel in pixels.pixels_mut().filter(|pixel| pixel[3] == 0) {
+            *pixel = image_crate::Rgba([0; 4]);
+        }
+        pixels
+    }
+    let crop = |image: &image_crate::RgbaImage, x, y| {
+        image_crate::imageops::crop_imm(image, x, y, 32, 32).to_image()
+    };
+
+    let mut reader = FilesystemResourceReader;
+    let tileset = Arc::new(
+        Loader::new()
+            .load_tsx_tileset("assets/tilesheet.tsx")
+            .unwrap(),
+    );
+    let tile = tileset.load_tile_pixels(15, &mut reader).unwrap().unwrap();
+    let tile_layer = |name, x, y, tile: LayerTileData| {
+        let mut layer = LayerData::new_finite_tile_layer(name, 3, 2);
+        let tiles = layer.as_finite_tile_layer_mut().unwrap();
+        tiles.set_tile(x, y, Some(tile));
+        layer
+    };
+    let mut mirrored = LayerTileData::new(0, 15);
+    mirrored.flip_h = true;
+    let mut hidden = tile_layer("hidden", 1, 0, LayerTileData::new(0, 15));
+    hidden.visible = false;
+    let mut faded = tile_layer("faded", 1, 1, LayerTileData::new(0, 15));
+    faded.opacity = 0.0;
+    let background = Color {
+        alpha: 0xff,
+        red: 0x10,
+        green: 0x20,
+        blue: 0x30,
+    };
+    let map = MapBuilder::new(Orientation::Orthogonal, 3, 2, 32, 32)
+        .tileset(tileset.clone())
+        .background_color(background)
+        .layer(tile_layer("plain", 0, 0, LayerTileData::new(0, 15)))
+        .layer(tile_layer("mirrored", 2, 1, mirrored))
+        .layer(hidden)
+        .layer(faded)
+        .build()
+        .unwrap();
+
+    let options = RenderOptions {
+        draw_background: false,
+        ..Default::default()
+    };
+    let image = rasterize(&map, &options).unwrap();
+    assert_eq!(image.dimensions(), (96, 64));
+    assert_eq!(crop(&image, 0, 0), visible(tile.clone()));
+    let flipped = image_crate::imageops::flip_horizontal(&tile);
+    assert_eq!(crop(&image, 64, 32), visible(flipped));
+    assert!(crop(&image, 32, 0).pixels().all(|pixel| pixel[3] == 0));
+    assert!(crop(&image, 32, 32).pixels().all(|pixel| pixel[3] == 0));
+
+    let options = RenderOptions {
+        draw_background: false,
+        draw_hidden_layers: true,
+    };
+    let image = rasterize(&map, &options).unwrap();
+    assert_eq!(crop(&image, 32, 0), visible(tile.clone()));
+
+    let image = rasterize(&map, &RenderOptions::default()).unwrap();
+    assert_eq!(image.get_pixel(40, 40).0, [0x10, 0x20, 0x30, 0xff]);
+
+    // Isometric tiles are centered horizontally on their cell.
+    let map = MapBuilder::new(Orientation::Isometric, 2, 2, 64, 32)
+        .tileset(tileset.clone())
+        .layer({
+            let mut layer = LayerData::new_finite_tile_layer("tiles", 2, 2);
+            let tiles = layer.as_finite_tile_layer_mut().unwrap();
+            tiles.set_tile(0, 0, Some(LayerTileData::new(0, 15)));
+            layer
+        })
+        .build()
+        .unwrap();
+    let image = rasterize(&map, &options).unwrap();
+    assert_eq!(image.dimensions(), (128, 64));
+    assert_eq!(crop(&image, 32, 0), visible(tile));
+
+    // Image layers are drawn at their offset.
+    let sheet = tileset.image.clone().unwrap();
+    let mut layer = LayerData::new_image_layer("image", Some(sheet.clone()));
+    layer.offset_x = -64.0;
+    layer.offset_y = -32.0;
+    let map = MapBuilder::new(Orientation::Orthogonal, 3, 2, 32, 32)
+        .layer(layer)
+        .build()
+        .unwrap();
+    let image = rasterize(&map, &options).unwrap();
+    let pixels = sheet.load_pixels(&mut reader).unwrap();
+    let expected = image_crate::imageops::crop_imm(&pixels, 64, 32, 96, 64).to_image();
+    assert_eq!(image, visible(expected));
+
+    // Tile objects are placed according to their alignment.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_tile_objects.tmx")
+        .unwrap();
+    let image = rasterize(&map, &options).unwrap();
+    let tile = map.tilesets()[0]
+        .load_tile_pixels(4, &mut reader)
+        .unwrap()
+        .unwrap();
+    assert_eq!(crop(&image, 0, 0), visible(tile));
+    assert_eq!(image.get_pixel(100, 10)[3], 0);
+}
+
+#[test]
+fn test_flattened_layers() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_nested_groups.tmx")
+        .unwrap();
+    let layers: Vec<_> = map.flattened_layers().collect();
+    let names: Vec<_> = layers
+        .iter()
+        .map(|layer| layer.layer.name.as_str())
+        .collect();
+    assert_eq!(names, ["Trees", "Sky", "Ground", "Trees"]);
+
+    let trees = &layers[0];
+    assert_eq!((trees.offset_x, trees.offset_y), (13.0, -1.0));
+    assert_eq!(trees.opacity, 0.25);
+    assert_eq!(
+        trees.tint_color,
+        Some(Color {
+            alpha: 0x80,
+            red: 0xff,
+            green: 0x80,
+            blue: 0x00
+        })
+    );
+    assert_eq!((trees.parallax_x, trees.parallax_y), (0.5, 0.5));
+    assert!(!trees.visible);
+    // The layer's own values are left untouched.
+    assert_eq!(trees.layer.offset_x, 1.0);
+    assert!(trees.layer.visible);
+
+    let sky = &layers[1];
+    assert_eq!((sky.offset_x, sky.offset_y), (10.0, -4.0));
+    assert_eq!(sky.opacity, 0.5);
+    assert_eq!(
+        sky.tint_color,
+        Some(Color {
+            alpha: 0xff,
+            red: 0xff,
+            green: 0x80,
+            blue: 0x00
+        })
+    );
+    assert_eq!((sky.parallax_x, sky.parallax_y), (0.5, 1.0));
+    assert!(sky.visible);
+
+    for layer in &layers[2..] {
+        assert_eq!((layer.offset_x, layer.offset_y), (0.0, 0.0));
+        assert_eq!(layer.opacity, 1.0);
+        assert_eq!(layer.tint_color, None);
+        assert_eq!((layer.parallax_x, layer.parallax_y), (1.0, 1.0));
+        assert!(layer.visible);
+    }
+}
+
+#[test]
+fn test_get_layer_by_path() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_nested_groups.tmx")
+        .unwrap();
+
+    let trees = map.get_layer_by_path("Background/Decor/Trees").unwrap();
+    assert_eq!(trees.id(), 3);
+    assert_eq!(map.get_layer_by_path("Foreground/Trees").unwrap().id(), 7);
+    assert_eq!(map.get_layer_by_path("Background/Decor").unwrap().id(), 2);
+    assert_eq!(map.get_layer_by_path("Ground").unwrap().id(), 5);
+
+    assert!(map.get_layer_by_path("Trees").is_none());
+    assert!(map.get_layer_by_path("Background/Trees").is_none());
+    assert!(map.get_layer_by_path("Ground/Trees").is_none());
+    assert!(map.get_layer_by_path("").is_none());
+}
+
+#[test]
+fn test_get_layer_by_id() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_nested_groups.tmx")
+        .unwrap();
+
+    for (id, name) in [(1, "Background"), (3, "Trees"), (5, "Ground"), (7, "Trees")] {
+        let layer = map.get_layer_by_id(id).unwrap();
+        assert_eq!((layer.id(), layer.name.as_str()), (id, name));
+    }
+    assert_eq!(
+        map.get_layer_by_id(3).unwrap(),
+        map.get_layer_by_path("Background/Decor/Trees").unwrap()
+    );
+    assert!(map.get_layer_by_id(0).is_none());
+    assert!(map.get_layer_by_id(8).is_none());
+}
+
+#[test]
+fn test_get_object_and_layer_by_id() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_nested_groups.tmx")
+        .unwrap();
+
+    let (layer, object) = map.get_object_and_layer_by_id(1).unwrap();
+    assert_eq!(layer.id(), 7);
+    assert_eq!(object.name, "stump");
+    assert_eq!(map.get_object_by_id(1).unwrap(), object);
+    assert!(map.get_object_and_layer_by_id(2).is_none());
+}
+
+#[test]
+fn test_object_index() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_object_index.tmx")
+        .unwrap();
+    let ids = |objects: &mut dyn Iterator<Item = Object>| -> Vec<u32> {
+        objects.map(|object| object.id()).collect()
+    };
+
+    for index in [
+        ObjectIndex::new(&map),
+        ObjectIndex::with_cell_size(&map, 7.0),
+    ] {
+        // Text objects aren't indexed.
+        assert_eq!(index.len(), 6);
+        assert_eq!(
+            ids(&mut index.query_rect(-1000.0, -1000.0, 2000.0, 2000.0)),
+            [1, 2, 3, 4, 5, 7]
+        );
+        assert_eq!(ids(&mut index.query_point(16.0, 16.0)), [1]);
+        assert_eq!(ids(&mut index.query_point(32.0, 32.0)), [1]);
+        assert_eq!(ids(&mut index.query_point(200.0, 40.0)), [3]);
+        assert_eq!(ids(&mut index.query_point(505.0, 505.0)), [7]);
+        assert!(index.query_point(400.0, 400.0).next().is_none());
+
+        // The ellipse is rotated around its top-left corner, covering (150, 100) to (160, 120).
+        assert_eq!(ids(&mut index.query_rect(145.0, 95.0, 10.0, 10.0)), [2]);
+        assert!(index.query_point(165.0, 105.0).next().is_none());
+
+        // The polygon covers (300, 280) to (350, 330).
+        assert_eq!(ids(&mut index.query_point(345.0, 285.0)), [4]);
+
+        // Tile objects are aligned to their bottom-left corner in orthogonal maps.
+        assert_eq!(ids(&mut index.query_point(80.0, 100.0)), [5]);
+        assert!(index.query_point(80.0, 140.0).next().is_none());
+    }
+}
+
+#[test]
+fn test_tiles_in_rect() {
+    fn expected(layer: &TileLayer, x: i32, y: i32, w: i32, h: i32) -> Vec<((i32, i32), u32)> {
+        (y..y + h)
+            .flat_map(|y| (x..x + w).map(move |x| (x, y)))
+            .filter_map(|(x, y)| layer.get_tile(x, y).map(|tile| ((x, y), tile.id())))
+            .collect()
+    }
+    fn in_rect(layer: &TileLayer, x: i32, y: i32, w: u32, h: u32) -> Vec<((i32, i32), u32)> {
+        let mut tiles: Vec<_> = layer
+            .tiles_in_rect(x, y, w, h)
+            .map(|(pos, tile)| (pos, tile.id()))
+            .collect();
+        tiles.sort_by_key(|&((x, y), _)| (y, x));
+        tiles
+    }
+
+    let finite = Loader::new().load_tmx_map("assets/tiled_csv.tmx").unwrap();
+    let infinite = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    for map in [&finite, &infinite] {
+        let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+        for (x, y, w, h) in [
+            (0, 0, 5, 5),
+            (-3, -2, 8, 6),
+            (-20, 10, 40, 30),
+            (90, 90, 4, 4),
+        ] {
+            assert_eq!(
+                in_rect(&layer, x, y, w as u32, h as u32),
+                expected(&layer, x, y, w, h)
+            );
+        }
+        assert!(layer.tiles_in_rect(0, 0, 0, 10).next().is_none());
+        // A rectangle covering the whole layer yields every tile once.
+        let all = in_rect(&layer, i32::MIN, i32::MIN, u32::MAX, u32::MAX);
+        assert_eq!(all, expected(&layer, -100, -100, 200, 200));
+    }
+
+    // Finite layers are gone through row by row.
+    let layer = finite.get_layer(0).unwrap().as_tile_layer().unwrap();
+    let positions: Vec<_> = layer
+        .tiles_in_rect(1, 1, 2, 2)
+        .map(|(pos, _)| pos)
+        .collect();
+    assert_eq!(positions, [(1, 1), (2, 1), (1, 2), (2, 2)]);
+}
+
+#[test]
+fn test_objects_with_class() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_classes.tmx")
+        .unwrap();
+    let ids =
+        |objects: &mut dyn Iterator<Item = Object>| objects.map(|o| o.id()).collect::<Vec<_>>();
+    assert_eq!(ids(&mut map.objects_with_class("spawn")), [1]);
+    // Classes may come from the `type` attribute of older files, or from templates.
+    assert_eq!(ids(&mut map.objects_with_class("chest")), [2]);
+    assert_eq!(ids(&mut map.objects_with_class("door")), [4]);
+    assert!(map.objects_with_class("ground").next().is_none());
+
+    let layer = map.get_layer(1).unwrap().as_object_layer().unwrap();
+    assert_eq!(ids(&mut layer.objects_with_class("spawn")), [1]);
+    assert!(layer.objects_with_name("spawn").next().is_none());
+
+    // Object layers nested in group layers are searched as well.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_nested_groups.tmx")
+        .unwrap();
+    assert_eq!(ids(&mut map.objects_with_class("prop")), [1]);
+    assert_eq!(ids(&mut map.objects_with_name("stump")), [1]);
+}
+
+#[test]
+fn test_text_objects() {
+    let map = Loader::new().load_tmx_map("assets/tiled_text.tmx").unwrap();
+    let layer = map.get_layer(0).unwrap().as_object_layer().unwrap();
+
+    match &layer.get_object(0).unwrap().shape {
+        ObjectShape::Text {
+            text,
+            width,
+            height,
+            font_family,
+            pixel_size,
+            wrap,
+            kerning,
+            halign,
+            ..
+        } => {
+            assert_eq!(text, "Hello World");
+            assert_eq!((*width, *height), (96.0, 24.0));
+            assert_eq!(font_family, "sans-serif");
+            assert_eq!(*pixel_size, 16);
+            assert!(!wrap);
+            // Kerning is enabled unless disabled explicitly.
+            assert!(kerning);
+            assert_eq!(*halign, HorizontalAlignment::Left);
+        }
+        shape => panic!("expected a text object, got {:?}", shape),
+    }
+
+    let dialogue = layer.get_object(1).unwrap();
+    assert_eq!(
+        dialogue.shape,
+        ObjectShape::Text {
+            text: "Welcome, traveller!\n  Mind the  gap.".to_owned(),
+            width: 200.0,
+            height: 80.0,
+            font_family: "Serif".to_owned(),
+            pixel_size: 12,
+            wrap: true,
+            color: Color {
+                red: 255,
+                green: 0,
+                blue: 0,
+                alpha: 128,
+            },
+            bold: true,
+            italic: true,
+            underline: true,
+            strikeout: true,
+            kerning: false,
+            halign: HorizontalAlignment::Justify,
+            valign: VerticalAlignment::Bottom,
+        }
+    );
+}
+
+#[test]
+fn test_text_objects_line_endings() {
+    let tmx = std::fs::read_to_string("assets/tiled_text.tmx")
+        .unwrap()
+        .replace("\r\n", "\n");
+    for tmx in [tmx.clone(), tmx.replace('\n', "\r\n")] {
+        let map = Loader::new()
+            .load_tmx_map_from_reader(Cursor::new(tmx), "assets/tiled_text.tmx")
+            .unwrap();
+        let dialogue = map.get_object_by_id(2).unwrap();
+        match &dialogue.shape {
+            ObjectShape::Text { text, .. } => {
+                assert_eq!(text, "Welcome, traveller!\n  Mind the  gap.")
+            }
+            shape => panic!("expected a text object, got {:?}", shape),
+        }
+    }
+}
+
+#[test]
+fn test_point_objects() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_object_index.tmx")
+        .unwrap();
+    let point = map.get_object_by_id(3).unwrap();
+    assert_eq!(point.shape, ObjectShape::Point(200.0, 40.0));
+    assert_eq!(
+        point.resolved_shape(),
+        Some(ResolvedShape::Point(200.0, 40.0))
+    );
+
+    let mut layer = LayerData::new_object_layer("markers");
+    let markers = layer.as_object_layer_mut().unwrap();
+    markers.add_object(ObjectData::new_shape(
+        1,
+        0.0,
+        0.0,
+        ObjectShape::Point(0.0, 0.0),
+    ));
+    markers.add_object(ObjectData::new_shape(
+        2,
+        0.0,
+        0.0,
+        ObjectShape::Rect {
+            width: 0.0,
+            height: 0.0,
+        },
+    ));
+    // Points and empty rectangles at the same position are told apart.
+    let objects = markers.object_data();
+    assert_ne!(objects[0].shape, objects[1].shape);
+}
+
+#[test]
+fn test_ellipse_geometry() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_object_index.tmx")
+        .unwrap();
+    // A 20x10 ellipse at (160, 100), rotated by 90 degrees around that point.
+    let ellipse = map.get_object_by_id(2).unwrap().resolved_shape().unwrap();
+
+    let (x, y, width, height) = ellipse.bounding_rect();
+    for (value, expected) in [(x, 150.0), (y, 100.0), (width, 10.0), (height, 20.0)] {
+        assert!((value - expected).abs() < 1e-4, "{} != {}", value, expected);
+    }
+    assert!(ellipse.contains_point(155.0, 110.0));
+    assert!(ellipse.contains_point(159.0, 110.0));
+    assert!(ellipse.contains_point(155.0, 119.5));
+    assert!(!ellipse.contains_point(161.0, 110.0));
+    // The corners of the bounding rectangle are outside of the ellipse.
+    assert!(!ellipse.contains_point(150.5, 101.0));
+    assert!(!ellipse.contains_point(159.5, 119.0));
+
+    let rect = map.get_object_by_id(1).unwrap().resolved_shape().unwrap();
+    assert!(rect.contains_point(0.0, 32.0));
+    assert!(!rect.contains_point(-0.5, 16.0));
+
+    let polygon = map.get_object_by_id(4).unwrap().resolved_shape().unwrap();
+    assert!(polygon.contains_point(315.0, 300.0));
+    assert!(!polygon.contains_point(290.0, 300.0));
+    let point = map.get_object_by_id(3).unwrap().resolved_shape().unwrap();
+    assert!(!point.contains_point(200.0, 40.0));
+}
+
+#[test]
+fn test_polygon_utilities() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_object_index.tmx")
+        .unwrap();
+    let polygon = map.get_object_by_id(4).unwrap().resolved_shape().unwrap();
+    assert!(polygon.is_closed());
+    assert_eq!(polygon.winding_order(), Some(WindingOrder::Clockwise));
+    assert_eq!(polygon.bounding_rect(), (300.0, 280.0, 50.0, 50.0));
+
+    let reversed = ResolvedShape::Polygon {
+        points: vec![(0.0, 0.0), (0.0, 10.0), (10.0, 0.0)],
+    };
+    assert_eq!(
+        reversed.winding_order(),
+        Some(WindingOrder::CounterClockwise)
+    );
+    let flat = ResolvedShape::Polygon {
+        points: vec![(0.0, 0.0), (5.0, 5.0), (10.0, 10.0)],
+    };
+    assert_eq!(flat.winding_order(), None);
+
+    let polyline = ResolvedShape::Polyline {
+        points: vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)],
+    };
+    assert!(!polyline.is_closed());
+    assert_eq!(polyline.winding_order(), None);
+    assert!(!polyline.contains_point(9.0, 1.0));
+}
+
+#[cfg(feature = "triangulate")]
+#[test]
+fn test_triangulate() {
+    fn area(triangles: &[[(f32, f32); 3]]) -> f32 {
+        triangles
+            .iter()
+            .map(|[(ax, ay), (bx, by), (cx, cy)]| {
+                ((bx - ax) * (cy - ay) - (by - ay) * (cx - ax)) / 2.0
+            })
+            .sum()
+    }
+
+    // A U-shaped polygon going counter-clockwise, with an aligned vertex on its bottom edge.
+    let polygon = ResolvedShape::Polygon {
+        points: vec![
+            (0.0, 0.0),
+            (0.0, 3.0),
+            (1.5, 3.0),
+            (3.0, 3.0),
+            (3.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 2.0),
+            (1.0, 2.0),
+            (1.0, 0.0),
+        ],
+    };
+    let triangles = polygon.triangulate().unwrap();
+    assert_eq!(triangles.len(), 7);
+    // The triangles keep the winding order of the polygon and cover the same area.
+    assert_eq!(area(&triangles), -7.0);
+    for [a, b, c] in &triangles {
+        let centroid = ((a.0 + b.0 + c.0) / 3.0, (a.1 + b.1 + c.1) / 3.0);
+        assert!(polygon.contains_point(centroid.0, centroid.1));
+    }
+
+    let rect = ResolvedShape::Rect {
+        x: 1.0,
+        y: 1.0,
+        width: 4.0,
+        height: 2.0,
+    };
+    assert_eq!(area(&rect.triangulate().unwrap()), 8.0);
+    assert!(ResolvedShape::Point(0.0, 0.0).triangulate().is_none());
+}
+
+#[test]
+fn test_object_world_transform() {
+    fn assert_near(actual: [(f32, f32); 4], expected: [(f32, f32); 4]) {
+        for ((ax, ay), (ex, ey)) in actual.iter().zip(expected) {
+            assert!(
+                (ax - ex).abs() < 1e-4 && (ay - ey).abs() < 1e-4,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_object_index.tmx")
+        .unwrap();
+    let layer = map.flattened_layers().next().unwrap();
+    // Tile objects are aligned to the bottom-left corner in orthogonal maps.
+    let tile = map.get_object_by_id(5).unwrap();
+    let transform = tile.world_transform(&layer);
+    assert_eq!((transform.origin_x, transform.origin_y), (0.0, 32.0));
+    assert_near(
+        tile.world_corners(&layer).unwrap(),
+        [(64.0, 96.0), (96.0, 96.0), (96.0, 128.0), (64.0, 128.0)],
+    );
+    assert!(map
+        .get_object_by_id(3)
+        .unwrap()
+        .world_corners(&layer)
+        .is_none());
+
+    let rotated = ObjectTransform {
+        rotation: 90.0,
+        ..transform
+    };
+    assert_near(
+        rotated.corners(32.0, 32.0),
+        [(96.0, 128.0), (96.0, 160.0), (64.0, 160.0), (64.0, 128.0)],
+    );
+
+    // The offsets of the layers containing the object are applied.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_nested_groups.tmx")
+        .unwrap();
+    let layer = FlattenedLayer {
+        offset_x: 4.0,
+        offset_y: 6.0,
+        ..map.flattened_layers().last().unwrap()
+    };
+    let stump = map.get_object_by_id(1).unwrap();
+    let transform = stump.world_transform(&layer);
+    assert_eq!((transform.x, transform.y), (12.0, 14.0));
+    assert_eq!(stump.transform().x, 8.0);
+}
+
+#[test]
+fn test_tile_object_gids() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_tile_objects.tmx")
+        .unwrap();
+
+    let plain = map.get_object_by_id(1).unwrap().get_tile().unwrap();
+    assert_eq!(plain.id(), 4);
+    assert!(!plain.flip_h && !plain.flip_v && !plain.flip_d);
+    assert_eq!(plain.transform().rotation, 0);
+
+    let mirrored = map.get_object_by_id(2).unwrap().get_tile().unwrap();
+    assert_eq!(mirrored.id(), 4);
+    assert_eq!(mirrored.get_tileset().name, "tilesheet");
+    assert!(mirrored.flip_h && !mirrored.flip_v && !mirrored.flip_d);
+    assert_eq!(
+        mirrored.transform(),
+        TileTransform {
+            rotation: 0,
+            flip_h: true
+        }
+    );
+
+    // The GID is resolved against the tileset it belongs to.
+    let upside_down = map.get_object_by_id(3).unwrap().get_tile().unwrap();
+    assert_eq!(upside_down.id(), 0);
+    assert_eq!(upside_down.get_tileset().name, "isometric_props");
+    assert!(upside_down.get_tile().is_some());
+    assert!(!upside_down.flip_h && upside_down.flip_v);
+    assert_eq!(upside_down.transform().rotation, 180);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    for path in [
+        "assets/tiled_csv.tmx",
+        "assets/tiled_base64_zlib_infinite.tmx",
+        "assets/tiled_group_layers.tmx",
+        "assets/tiled_object_template.tmx",
+        "assets/tiled_text.tmx",
+        "assets/tiled_tile_objects.tmx",
+        "assets/tiled_class_property.tmx",
+    ] {
+        let map = Loader::new().load_tmx_map(path).unwrap();
+        let json = serde_json::to_string(&map).unwrap();
+        let deserialized: Map = serde_json::from_str(&json).unwrap();
+        assert_eq!(map, deserialized, "{} changed", path);
+    }
+
+    // Deferred tile data is decoded to be serialized.
+    let mut loader = Loader::new();
+    loader.set_defer_tile_data(true);
+    let map = loader
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    let deserialized: Map = serde_json::from_str(&serde_json::to_string(&map).unwrap()).unwrap();
+    assert_eq!(map, deserialized);
+
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet_wangsets.tsx")
+        .unwrap();
+    let json = serde_json::to_string(&tileset).unwrap();
+    assert_eq!(tileset, serde_json::from_str(&json).unwrap());
+
+    let wrong_size = r#"{"width": 2, "height": 2, "tiles": [null]}"#;
+    assert!(serde_json::from_str::<tiled::FiniteTileLayerData>(wrong_size).is_err());
+}
+
+#[test]
+fn test_image_loaded_hook() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut loader = Loader::new();
+    let counter = calls.clone();
+    loader.on_image_loaded(move |image| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        image.source.clone()
+    });
+
+    let map = loader
+        .load_tmx_map("assets/tiled_image_layers.tmx")
+        .unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    let layer_image = match map.get_layer(1).unwrap().layer_type() {
+        LayerType::Image(layer) => layer.image.clone().unwrap(),
+        _ => panic!("expected an image layer"),
+    };
+    let handle = layer_image.handle.as_ref().unwrap();
+    assert_eq!(handle.downcast_ref::<PathBuf>(), Some(&layer_image.source));
+    assert_eq!(handle.downcast_ref::<String>(), None);
+
+    // External tilesets are cached, along with their handles.
+    let first = loader
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    let second = loader
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    let image = |map: &Map| map.tilesets()[0].image.clone().unwrap();
+    assert!(image(&first).handle.is_some());
+    assert_eq!(image(&first).handle, image(&second).handle);
+
+    #[cfg(feature = "json")]
+    {
+        let map = loader
+            .load_json_map("assets/tiled_image_layers.tmj")
+            .unwrap();
+        assert!(map.tilesets()[0].image.as_ref().unwrap().handle.is_some());
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+    }
+
+    // Loaders without a hook leave the handles empty.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_image_layers.tmx")
+        .unwrap();
+    assert_eq!(map.tilesets()[0].image.as_ref().unwrap().handle, None);
+
+    // Handles don't take part in the equality of images.
+    let image_layer = |map: &Map| match map.get_layer(1).unwrap().layer_type() {
+        LayerType::Image(layer) => layer.image.clone().unwrap(),
+        _ => panic!("expected an image layer"),
+    };
+    assert_eq!(image_layer(&map), layer_image);
+    let reload = loader
+        .load_tmx_map("assets/tiled_image_layers.tmx")
+        .unwrap();
+    let reloaded_image = image_layer(&reload);
+    assert_ne!(reloaded_image.handle, layer_image.handle);
+    assert_eq!(reloaded_image, layer_image);
+}
+
+#[test]
+fn test_tile_layer_csv() {
+    // The CSV data of the tile layers in a file, in order.
+    fn csv_data(path: &str) -> Vec<String> {
+        let file = std::fs::read_to_string(path).unwrap().replace("\r\n", "\n");
+        file.split("<data encoding=\"csv\">")
+            .skip(1)
+            .map(|data| {
+                data.split("</data>")
+                    .next()
+                    .unwrap()
+                    .trim_start()
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    for path in ["assets/tiled_csv.tmx", "assets/tiled_flipped.tmx"] {
+        let map = Loader::new().load_tmx_map(path).unwrap();
+        let csv: Vec<String> = map
+            .tile_layers_to_csv()
+            .into_iter()
+            .map(|(_, csv)| csv)
+            .collect();
+        assert_eq!(csv, csv_data(path), "{}", path);
+    }
+
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    let bounds = match &layer {
+        TileLayer::Infinite(infinite) => infinite.bounds().unwrap(),
+        _ => panic!("expected an infinite layer"),
+    };
+    let csv = layer.to_csv();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows.len() as i32, bounds.1 .1 - bounds.0 .1 + 1);
+    let first_row: Vec<u32> = rows[0]
+        .trim_end_matches(',')
+        .split(',')
+        .map(|gid| gid.parse().unwrap())
+        .collect();
+    assert_eq!(first_row.len() as i32, bounds.1 .0 - bounds.0 .0 + 1);
+    let (x, y) = bounds.0;
+    let expected: Vec<u32> = (0..first_row.len() as i32)
+        .map(|column| layer.get_tile(x + column, y).map_or(0, |tile| tile.gid()))
+        .collect();
+    assert_eq!(first_row, expected);
+
+    // Maps built from scratch give consecutive GIDs to their tilesets.
+    let image = Image {
+        source: PathBuf::from("terrain.png"),
+        width: 64,
+        height: 16,
+        transparent_colour: None,
+        handle: None,
+    };
+    let sheet = TilesetBuilder::new("sheet", 16, 16).image(image).build();
+    let collection = TilesetBuilder::new("collection", 16, 16)
+        .tile(7, TileData::default())
+        .build();
+    let mut layer = LayerData::new_finite_tile_layer("tiles", 2, 1);
+    let tiles = layer.as_finite_tile_layer_mut().unwrap();
+    tiles.set_tile(0, 0, Some(LayerTileData::new(1, 7)));
+    let mut flipped = LayerTileData::new(0, 2);
+    flipped.flip_v = true;
+    tiles.set_tile(1, 0, Some(flipped));
+    let map = MapBuilder::new(Orientation::Orthogonal, 2, 1, 16, 16)
+        .tileset(sheet)
+        .tileset(collection)
+        .layer(layer)
+        .build()
+        .unwrap();
+    assert_eq!(map.get_first_gid(0), Some(1));
+    assert_eq!(map.get_first_gid(1), Some(5));
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert_eq!(layer.to_csv(), "12,1073741827\n");
+}
+
+#[test]
+fn test_validate() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    assert_eq!(map.validate(), vec![]);
+
+    let mut ground = LayerData::new_finite_tile_layer("ground", 4, 4);
+    let tiles = ground.as_finite_tile_layer_mut().unwrap();
+    tiles.set_tile(1, 2, Some(LayerTileData::new(0, 7)));
+    let mut objects = LayerData::new_object_layer("objects");
+    let layer = objects.as_object_layer_mut().unwrap();
+    let rect = ObjectShape::Rect {
+        width: 8.0,
+        height: 8.0,
+    };
+    layer.add_object(ObjectData::new_shape(1, 0.0, 0.0, rect.clone()));
+    layer.add_object(ObjectData::new_shape(1, 0.0, 0.0, rect));
+    layer.add_object(ObjectData::new_shape(
+        2,
+        0.0,
+        0.0,
+        ObjectShape::Polyline {
+            points: vec![(0.0, 0.0)],
+        },
+    ));
+    let image = Image {
+        source: "assets/missing.png".into(),
+        width: 8,
+        height: 8,
+        transparent_colour: None,
+        handle: None,
+    };
+    let map = MapBuilder::new(Orientation::Orthogonal, 4, 4, 32, 32)
+        .tileset(TilesetBuilder::new("empty", 32, 32).build())
+        .property("target", PropertyValue::ObjectValue(5))
+        .layer(ground)
+        .layer(objects)
+        .layer(LayerData::new_image_layer("background", Some(image)))
+        .build()
+        .unwrap();
+
+    let issues = map.validate();
+    assert_eq!(
+        issues,
+        vec![
+            ValidationIssue::InvalidTile {
+                layer: 1,
+                x: 1,
+                y: 2,
+                tileset: "empty".to_owned(),
+                id: 7
+            },
+            ValidationIssue::DuplicateObjectId { id: 1 },
+            ValidationIssue::EmptyShape { object: 2 },
+            ValidationIssue::MissingFile {
+                path: "assets/missing.png".into()
+            },
+            ValidationIssue::UnresolvedObjectReference {
+                property: "target".to_owned(),
+                id: 5
+            },
+        ]
+    );
+    assert_eq!(
+        issues[0].to_string(),
+        "tile (1, 2) of layer 1 is tile 7, missing from tileset 'empty'"
+    );
+}
+
+#[test]
+fn test_map_diff() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    assert!(tiled::diff(&map, &map).is_empty());
+
+    let point = |id, x| ObjectData::new_shape(id, x, 0.0, ObjectShape::Point(x, 0.0));
+    let mut ground = LayerData::new_finite_tile_layer("ground", 4, 4);
+    let tiles = ground.as_finite_tile_layer_mut().unwrap();
+    tiles.set_tile(0, 0, Some(LayerTileData::new(0, 1)));
+    tiles.set_tile(3, 1, Some(LayerTileData::new(0, 2)));
+    let mut objects = LayerData::new_object_layer("objects");
+    let layer = objects.as_object_layer_mut().unwrap();
+    layer.add_object(point(1, 0.0));
+    layer.add_object(point(2, 0.0));
+    layer.add_object(point(3, 0.0));
+    let builder = MapBuilder::new(Orientation::Orthogonal, 4, 4, 32, 32)
+        .tileset(TilesetBuilder::new("terrain", 32, 32).build())
+        .layer(ground)
+        .layer(objects);
+    let old = builder
+        .clone()
+        .property("level", PropertyValue::IntValue(1))
+        .layer(LayerData::new_object_layer("removed"))
+        .build()
+        .unwrap();
+    let mut new = builder
+        .property("level", PropertyValue::IntValue(2))
+        .build()
+        .unwrap();
+
+    let tiles = new.get_layer_mut(0).unwrap();
+    let tiles = tiles.as_finite_tile_layer_mut().unwrap();
+    tiles.set_tile(0, 0, None);
+    tiles.set_tile(1, 0, Some(LayerTileData::new(0, 3)));
+    let objects = new.get_layer_mut(1).unwrap().as_object_layer_mut().unwrap();
+    objects.remove_object(0);
+    objects.object_data_mut()[0].x = 16.0;
+    objects.object_data_mut()[1]
+        .properties
+        .insert("hp".into(), PropertyValue::IntValue(3));
+    objects.add_object(point(4, 0.0));
+
+    let diff = tiled::diff(&old, &new);
+    assert!(diff.added_layers.is_empty());
+    assert_eq!(diff.removed_layers, vec![3]);
+    assert_eq!(
+        diff.tiles,
+        vec![
+            TileChange {
+                layer: 1,
+                x: 0,
+                y: 0,
+                old: Some(LayerTileData::new(0, 1)),
+                new: None
+            },
+            TileChange {
+                layer: 1,
+                x: 1,
+                y: 0,
+                old: None,
+                new: Some(LayerTileData::new(0, 3))
+            },
+        ]
+    );
+    assert_eq!(diff.added_objects, vec![4]);
+    assert_eq!(diff.removed_objects, vec![1]);
+    assert_eq!(diff.changed_objects, vec![2]);
+    assert_eq!(
+        diff.properties,
+        vec![
+            PropertyChange {
+                owner: PropertyOwner::Map,
+                name: "level".to_owned(),
+                old: Some(PropertyValue::IntValue(1)),
+                new: Some(PropertyValue::IntValue(2)),
+            },
+            PropertyChange {
+                owner: PropertyOwner::Object(3),
+                name: "hp".to_owned(),
+                old: None,
+                new: Some(PropertyValue::IntValue(3)),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_remap_gids() {
+    let terrain = Arc::new(TilesetBuilder::new("terrain", 32, 32).build());
+    let props = Arc::new(TilesetBuilder::new("props", 32, 32).build());
+    let mut ground = LayerData::new_finite_tile_layer("ground", 2, 1);
+    let tiles = ground.as_finite_tile_layer_mut().unwrap();
+    tiles.set_tile(0, 0, Some(LayerTileData::new(0, 1)));
+    let mut tile = LayerTileData::new(1, 2);
+    tile.flip_h = true;
+    tiles.set_tile(1, 0, Some(tile));
+    let mut map = MapBuilder::new(Orientation::Orthogonal, 2, 1, 32, 32)
+        .tileset(terrain.clone())
+        .tileset(props)
+        .layer(ground)
+        .build()
+        .unwrap();
+    let original = map.clone();
+
+    // Dropping a tileset that is still in use fails without modifying the map.
+    let mapping = GidMapping::new(vec![terrain.clone()]);
+    assert!(matches!(
+        map.remap_gids(&mapping),
+        Err(Error::InvalidTileFound)
+    ));
+    assert_eq!(map, original);
+
+    // Merge the props into the terrain tileset, after its tiles.
+    let mapping = GidMapping::new(vec![terrain]).merge_tileset(1, 0, 4);
+    map.remap_gids(&mapping).unwrap();
+    assert_eq!(map.tilesets().len(), 1);
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert_eq!(layer.get_tile(0, 0).unwrap().gid(), 2);
+    let tile = layer.get_tile(1, 0).unwrap();
+    assert_eq!((tile.tileset_index(), tile.id()), (0, 6));
+    assert!(tile.flip_h);
+}
+
+#[test]
+fn test_merge_maps() {
+    let point = |id, x, y| ObjectData::new_shape(id, x, y, ObjectShape::Point(x, y));
+    let mut ground = LayerData::new_finite_tile_layer("ground", 8, 8);
+    let tiles = ground.as_finite_tile_layer_mut().unwrap();
+    tiles.set_tile(1, 1, Some(LayerTileData::new(0, 3)));
+    tiles.set_tile(7, 7, Some(LayerTileData::new(0, 4)));
+    let mut objects = LayerData::new_object_layer("objects");
+    let layer = objects.as_object_layer_mut().unwrap();
+    layer.add_object(point(1, 8.0, 8.0));
+    let mut door = point(2, 16.0, 0.0);
+    door.properties
+        .insert("target".into(), PropertyValue::ObjectValue(1));
+    layer.add_object(door);
+    let room = MapBuilder::new(Orientation::Orthogonal, 8, 8, 32, 32)
+        .tileset(TilesetBuilder::new("terrain", 32, 32).build())
+        .layer(ground)
+        .layer(objects)
+        .build()
+        .unwrap();
+
+    let mut spawns = LayerData::new_object_layer("spawns");
+    spawns
+        .as_object_layer_mut()
+        .unwrap()
+        .add_object(point(1, 0.0, 0.0));
+    let mut world = MapBuilder::new(Orientation::Orthogonal, 10, 10, 32, 32)
+        .tileset(TilesetBuilder::new("props", 32, 32).build())
+        .layer(spawns)
+        .build()
+        .unwrap();
+    world.merge(&room, (4, 2));
+    world.merge(&room, (0, 0));
+
+    assert_eq!(world.tilesets().len(), 2);
+    assert_eq!(world.tilesets()[1].name, "terrain");
+    assert_eq!(world.get_first_gid(1), Some(1));
+    let ids: Vec<_> = world.layers().map(|layer| layer.id()).collect();
+    assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+
+    let ground = world.get_layer(1).unwrap().as_tile_layer().unwrap();
+    let tile = ground.get_tile(5, 3).unwrap();
+    assert_eq!((tile.tileset_index(), tile.id()), (1, 3));
+    assert_eq!(ground.get_tile(1, 1), None);
+    // The tile at (7, 7) of the room falls outside of the world.
+    assert_eq!(ground.tiles_in_rect(0, 0, 10, 10).count(), 1);
+
+    let moved = world.get_object_by_id(2).unwrap();
+    assert_eq!((moved.x, moved.y), (8.0 + 128.0, 8.0 + 64.0));
+    let door = world.get_object_by_id(3).unwrap();
+    assert_eq!(door.properties["target"], PropertyValue::ObjectValue(2));
+    let door = world.get_object_by_id(5).unwrap();
+    assert_eq!(door.properties["target"], PropertyValue::ObjectValue(4));
+}
+
+#[test]
+fn test_crop_map() {
+    let point = |id, x, y| ObjectData::new_shape(id, x, y, ObjectShape::Point(x, y));
+    let mut ground = LayerData::new_finite_tile_layer("ground", 8, 8);
+    let tiles = ground.as_finite_tile_layer_mut().unwrap();
+    tiles.set_tile(1, 1, Some(LayerTileData::new(0, 3)));
+    tiles.set_tile(6, 6, Some(LayerTileData::new(1, 4)));
+    let mut objects = LayerData::new_object_layer("objects");
+    let layer = objects.as_object_layer_mut().unwrap();
+    layer.add_object(point(1, 40.0, 40.0));
+    layer.add_object(point(2, 200.0, 200.0));
+    let map = MapBuilder::new(Orientation::Orthogonal, 8, 8, 32, 32)
+        .tileset(TilesetBuilder::new("terrain", 32, 32).build())
+        .tileset(TilesetBuilder::new("props", 32, 32).build())
+        .layer(ground)
+        .layer(objects)
+        .build()
+        .unwrap();
+
+    let cell = map.crop(4, 4, 4, 4);
+    assert_eq!((cell.width, cell.height), (4, 4));
+    assert_eq!(cell.tilesets().len(), 1);
+    assert_eq!(cell.tilesets()[0].name, "props");
+    let tiles = cell.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert_eq!(tiles.tiles_in_rect(0, 0, 4, 4).count(), 1);
+    let tile = tiles.get_tile(2, 2).unwrap();
+    assert_eq!((tile.tileset_index(), tile.id()), (0, 4));
+    assert!(cell.get_object_by_id(1).is_none());
+    let object = cell.get_object_by_id(2).unwrap();
+    assert_eq!((object.x, object.y), (72.0, 72.0));
+    assert_eq!(cell.get_layer(1).unwrap().id(), 2);
+
+    let cell = map.crop(-2, -2, 4, 4);
+    assert_eq!(cell.tilesets()[0].name, "terrain");
+    let tiles = cell.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert_eq!(tiles.get_tile(3, 3).unwrap().id(), 3);
+    assert_eq!(cell.get_object_by_id(1).unwrap().x, 104.0);
+}
+
+#[test]
+fn test_resize_map() {
+    let mut ground = LayerData::new_finite_tile_layer("ground", 4, 4);
+    let tiles = ground.as_finite_tile_layer_mut().unwrap();
+    tiles.set_tile(0, 0, Some(LayerTileData::new(0, 1)));
+    tiles.set_tile(1, 3, Some(LayerTileData::new(0, 2)));
+    let mut objects = LayerData::new_object_layer("objects");
+    objects
+        .as_object_layer_mut()
+        .unwrap()
+        .add_object(ObjectData::new_shape(
+            1,
+            8.0,
+            8.0,
+            ObjectShape::Point(8.0, 8.0),
+        ));
+    let mut map = MapBuilder::new(Orientation::Orthogonal, 4, 4, 32, 32)
+        .tileset(TilesetBuilder::new("terrain", 32, 32).build())
+        .layer(ground)
+        .layer(objects)
+        .build()
+        .unwrap();
+
+    map.resize(6, 2, ResizeAnchor::BottomRight);
+    assert_eq!((map.width, map.height), (6, 2));
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert_eq!((layer.width(), layer.height()), (Some(6), Some(2)));
+    assert_eq!(layer.tiles_in_rect(0, 0, 6, 2).count(), 1);
+    assert_eq!(layer.get_tile(3, 1).unwrap().id(), 2);
+    let object = map.get_object_by_id(1).unwrap();
+    assert_eq!((object.x, object.y), (72.0, -56.0));
+
+    map.resize(8, 4, ResizeAnchor::TopLeft);
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert_eq!(layer.get_tile(3, 1).unwrap().id(), 2);
+}
+
+#[test]
+fn test_move_layers() {
+    let mut map = Loader::new()
+        .load_tmx_map("assets/tiled_group_layers.tmx")
+        .unwrap();
+    let ids = |map: &Map| map.layers().map(|layer| layer.id()).collect::<Vec<_>>();
+    let child_ids = |map: &Map, group| {
+        let group = map
+            .get_layer_by_id(group)
+            .unwrap()
+            .as_group_layer()
+            .unwrap();
+        group.layers().map(|layer| layer.id()).collect::<Vec<_>>()
+    };
+    assert_eq!(ids(&map), vec![1, 3, 6]);
+
+    assert!(map.move_layer(6, 0));
+    assert_eq!(ids(&map), vec![6, 1, 3]);
+    assert!(!map.move_layer(6, 3));
+    assert!(!map.move_layer(42, 0));
+    assert_eq!(ids(&map), vec![6, 1, 3]);
+
+    // Move the nested tile layer out of its groups, then the first tile layer into a group.
+    assert!(map.move_layer_into(9, None, 3));
+    assert_eq!(ids(&map), vec![6, 1, 3, 9]);
+    assert_eq!(child_ids(&map, 8), Vec::<u32>::new());
+    assert!(map.move_layer_into(1, Some(3), 0));
+    assert_eq!(ids(&map), vec![6, 3, 9]);
+    assert_eq!(child_ids(&map, 3), vec![1, 5]);
+    assert_eq!(map.get_layer_by_path("group-1/tile-1").unwrap().id(), 1);
+
+    // A group can't be moved into itself or its children, nor into a layer that isn't a group.
+    assert!(!map.move_layer_into(6, Some(8), 0));
+    assert!(!map.move_layer_into(6, Some(6), 0));
+    assert!(!map.move_layer_into(6, Some(9), 0));
+    assert!(!map.move_layer_into(6, Some(3), 5));
+    assert_eq!(ids(&map), vec![6, 3, 9]);
+    assert!(map.move_layer_into(8, Some(3), 2));
+    assert_eq!(child_ids(&map, 3), vec![1, 5, 8]);
+    assert_eq!(child_ids(&map, 6), Vec::<u32>::new());
+}
+
+#[test]
+fn test_tile_usage() {
+    let mut ground = LayerData::new_finite_tile_layer("ground", 4, 4);
+    let tiles = ground.as_finite_tile_layer_mut().unwrap();
+    tiles.set_tile(0, 0, Some(LayerTileData::new(1, 3)));
+    tiles.set_tile(1, 0, Some(LayerTileData::new(1, 3)));
+    let mut flipped = LayerTileData::new(1, 3);
+    flipped.flip_v = true;
+    tiles.set_tile(2, 0, Some(flipped));
+    let mut decor = LayerData::new_group_layer("decor");
+    let mut nested = LayerData::new_finite_tile_layer("nested", 4, 4);
+    nested
+        .as_finite_tile_layer_mut()
+        .unwrap()
+        .set_tile(3, 3, Some(LayerTileData::new(1, 5)));
+    decor.as_group_layer_mut().unwrap().add_layer(nested);
+    let map = MapBuilder::new(Orientation::Orthogonal, 4, 4, 32, 32)
+        .tileset(TilesetBuilder::new("unused", 32, 32).build())
+        .tileset(TilesetBuilder::new("terrain", 32, 32).build())
+        .tileset(TilesetBuilder::new("props", 32, 32).build())
+        .layer(ground)
+        .layer(decor)
+        .build()
+        .unwrap();
+
+    let usage = map.tile_usage();
+    assert_eq!(usage.len(), 2);
+    assert_eq!(usage[&(1, 3)], 3);
+    assert_eq!(usage[&(1, 5)], 1);
+    assert_eq!(map.unused_tilesets(), vec![0, 2]);
+}
+
+#[test]
+fn test_autotile() {
+    use tiled::autotile::Autotiler;
+
+    let mut tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet_wangsets.tsx")
+        .unwrap();
+    let wang_set = tileset.wang_sets[0].clone();
+
+    // A 3x3 block in the middle of a 7x7 layer.
+    let filled: Vec<bool> = (0..49)
+        .map(|i| (2..5).contains(&(i % 7)) && (2..5).contains(&(i / 7)))
+        .collect();
+    let mut layer = FiniteTileLayerData::empty(7, 7);
+    layer.set_tile(0, 0, Some(LayerTileData::new(0, 3)));
+    Autotiler::new(&tileset, 0, &wang_set, 1).fill(&mut layer, &filled);
+
+    let id_at = |layer: &FiniteTileLayerData, x, y| layer.get_tile_data(x, y).map(|t| t.id());
+    assert_eq!(id_at(&layer, 0, 0), None);
+    for (x, y) in [(2, 2), (3, 3), (4, 4)] {
+        assert!(matches!(id_at(&layer, x, y), Some(16 | 17)));
+    }
+    // Cells right of the block only have their left side colored.
+    for y in 2..5 {
+        assert!(matches!(id_at(&layer, 5, y), Some(14 | 70)));
+    }
+    assert!(matches!(id_at(&layer, 3, 1), Some(1 | 57)));
+
+    // The same seed gives the same tiles.
+    let mut other = FiniteTileLayerData::empty(7, 7);
+    Autotiler::new(&tileset, 0, &wang_set, 1).fill(&mut other, &filled);
+    assert_eq!(layer, other);
+
+    // Without tiles colored on their right side, flipped tiles are used if allowed.
+    let mut wang_set = wang_set;
+    wang_set.wang_tiles.retain(|id, _| *id != 15 && *id != 71);
+    tileset.transformations.flip_h = true;
+    let autotiler = Autotiler::new(&tileset, 0, &wang_set, 1).seed(7);
+    let tile = autotiler
+        .select(WangId([0, 1, 1, 1, 0, 0, 0, 0]), 0, 0)
+        .unwrap();
+    assert!(matches!(tile.id(), 14 | 70));
+    assert!(tile.flip_h && !tile.flip_v && !tile.flip_d);
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn test_watch_map() {
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join(format!("tiled_watch_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for file in [
+        "tiled_base64_external.tmx",
+        "tilesheet.tsx",
+        "tilesheet.png",
+    ] {
+        std::fs::copy(Path::new("assets").join(file), dir.join(file)).unwrap();
+    }
+    let map_path = dir.join("tiled_base64_external.tmx");
+    let watcher = Loader::new().watch([&map_path]).unwrap();
+    let timeout = Duration::from_secs(10);
+
+    // The map is loaded right away.
+    let update = watcher.receiver().recv_timeout(timeout).unwrap();
+    assert_eq!(update.path, map_path);
+    assert_eq!(update.map.unwrap().tilesets()[0].name, "tilesheet");
+
+    // Then again when its tileset changes.
+    let tileset = std::fs::read_to_string(dir.join("tilesheet.tsx")).unwrap();
+    let tileset = tileset.replace("name=\"tilesheet\"", "name=\"renamed\"");
+    std::fs::write(dir.join("tilesheet.tsx"), tileset).unwrap();
+    // The file may be read while partially written, so a few failed attempts are expected.
+    let map = loop {
+        match watcher.receiver().recv_timeout(timeout).unwrap().map {
+            Ok(map) if map.tilesets()[0].name != "tilesheet" => break map,
+            _ => {}
+        }
+    };
+    assert_eq!(map.tilesets()[0].name, "renamed");
+
+    drop(watcher);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_scan_dependencies() {
+    let mut loader = Loader::new();
+    let files = loader
+        .scan_dependencies("assets/tiled_object_template.tmx")
+        .unwrap();
+    assert_eq!(
+        files,
+        [
+            "assets/tilesheet.tsx",
+            "assets/tilesheet.png",
+            "assets/tilesheet_template.tsx",
+            "assets/tiled_object_template.tx",
+        ]
+        .map(PathBuf::from)
+    );
+    // Cached resources are found as well.
+    assert_eq!(
+        loader
+            .scan_dependencies("assets/tiled_object_template.tmx")
+            .unwrap(),
+        files
+    );
+    assert!(!loader.defer_tile_data());
+
+    // Embedded tilesets and image layers only contribute their images.
+    let files = loader
+        .scan_dependencies("assets/tiled_image_layers.tmx")
+        .unwrap();
+    assert_eq!(files, [PathBuf::from("assets/tilesheet.png")]);
+
+    assert!(loader.scan_dependencies("assets/missing.tmx").is_err());
+}
+
+#[test]
+fn test_virtual_path() {
+    let cases = [
+        (
+            "maps\\..\\tilesets\\tilesheet.tsx",
+            "tilesets/tilesheet.tsx",
+        ),
+        ("./maps//level.tmx", "maps/level.tmx"),
+        ("../shared/../tilesets/a.tsx", "../tilesets/a.tsx"),
+        ("/../assets/a.tsx", "/assets/a.tsx"),
+        ("C:\\maps\\..\\a.tsx", "C:/a.tsx"),
+        (
+            "https://example.com/maps/../a.tmx",
+            "https://example.com/a.tmx",
+        ),
+        ("\\\\?\\C:\\maps\\..\\..\\a.tsx", "//?/C:/a.tsx"),
+        (
+            "\\\\server\\share\\maps\\..\\..\\a.tsx",
+            "//server/share/a.tsx",
+        ),
+        (
+            "\\\\?\\UNC\\server\\share\\..\\a.tsx",
+            "//?/UNC/server/share/a.tsx",
+        ),
+    ];
+    for (path, expected) in cases {
+        assert_eq!(VirtualPath::new(path).as_str(), expected, "{path}");
+    }
+    let level = VirtualPath::new("maps/level.tmx");
+    assert_eq!(level.parent().as_str(), "maps");
+    assert_eq!(level.parent().parent().as_str(), "");
+    assert_eq!(level.parent().join("..\\a.tsx").as_str(), "a.tsx");
+    assert_eq!(level.parent().join("/a.tsx").as_str(), "/a.tsx");
+    assert!(!level.is_absolute());
+    assert!(VirtualPath::new("/maps").is_absolute());
+    let share = VirtualPath::new("\\\\server\\share\\maps");
+    assert!(share.is_absolute());
+    assert_eq!(share.join("../../a.tsx").as_str(), "//server/share/a.tsx");
+
+    /// Finds files by their normalized path, recording the paths it is given.
+    struct RecordingReader(HashMap<&'static str, String>, Vec<PathBuf>);
+
+    impl ResourceReader for RecordingReader {
+        type Resource = Cursor<Vec<u8>>;
+        type Error = std::io::Error;
+
+        fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+            self.1.push(path.to_owned());
+            self.0
+                .get(VirtualPath::new(path).as_str())
+                .map(|data| Cursor::new(data.clone().into_bytes()))
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))
+        }
+    }
+
+    // A map authored on Windows, referring to its tileset with backslashes.
+    let map = std::fs::read_to_string("assets/tiled_base64_external.tmx")
+        .unwrap()
+        .replace("\"tilesheet.tsx\"", "\"..\\tilesets\\.\\tilesheet.tsx\"");
+    let reader = RecordingReader(
+        HashMap::from([
+            ("maps/level.tmx", map),
+            (
+                "tilesets/tilesheet.tsx",
+                std::fs::read_to_string("assets/tilesheet.tsx").unwrap(),
+            ),
+        ]),
+        Vec::new(),
+    );
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    let map = loader.load_tmx_map("./maps/level.tmx").unwrap();
+    // Readers are given the paths as they were joined, leaving `..` to the filesystem.
+    assert_eq!(
+        loader.reader().1,
+        [
+            Path::new("./maps/level.tmx"),
+            &Path::new("./maps").join("../tilesets/./tilesheet.tsx")
+        ]
+    );
+    let expected = Loader::new()
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    compare_everything_but_tileset_sources(&map, &expected);
+    assert_eq!(
+        VirtualPath::new(&map.tilesets()[0].image.as_ref().unwrap().source).as_str(),
+        "tilesets/tilesheet.png"
+    );
+
+    let cached = loader
+        .cache()
+        .get_tileset("tilesets/tilesheet.tsx")
+        .unwrap();
+    assert!(Arc::ptr_eq(&cached, &map.tilesets()[0]));
+    assert!(loader
+        .cache()
+        .get_tileset("maps\\..\\tilesets\\tilesheet.tsx")
+        .is_some());
+    loader.cache_mut().invalidate("./tilesets/tilesheet.tsx");
+    assert!(loader.cache().tilesets.is_empty());
+}
+
+#[test]
+fn test_memory_resource_reader() {
+    let reader: MemoryResourceReader = vec![
+        (
+            "maps/level.tmx",
+            std::fs::read("assets/tiled_base64_external.tmx").unwrap(),
+        ),
+        ("maps/tilesheet.tsx", b"not a tileset".to_vec()),
+    ]
+    .into_iter()
+    .collect();
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    assert!(loader.load_tmx_map("maps/level.tmx").is_err());
+
+    // Files can be replaced once downloaded again.
+    let tileset = std::fs::read("assets/tilesheet.tsx").unwrap();
+    loader.reader_mut().insert("maps\\tilesheet.tsx", tileset);
+    let map = loader.load_tmx_map("./maps/level.tmx").unwrap();
+    let expected = Loader::new()
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    compare_everything_but_tileset_sources(&map, &expected);
+
+    let reader = loader.reader_mut();
+    assert!(reader.contains("maps/../maps/level.tmx"));
+    assert_eq!(
+        reader.list_files(Path::new("maps")).unwrap(),
+        [
+            PathBuf::from("maps/level.tmx"),
+            PathBuf::from("maps/tilesheet.tsx"),
+        ]
+    );
+    assert!(reader.remove("maps/level.tmx").is_some());
+    assert!(!reader.contains("maps/level.tmx"));
+    let missing = reader.read_from(Path::new("maps/level.tmx"));
+    assert_eq!(missing.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_map_events() {
+    for path in [
+        "assets/tiled_base64.tmx",
+        "assets/tiled_base64_zlib_infinite.tmx",
+        "assets/tiled_group_layers.tmx",
+        "assets/tiled_object_groups.tmx",
+        "assets/tiled_image_layers.tmx",
+        "assets/tiled_xml.tmx",
+    ] {
+        let map = Loader::new().load_tmx_map(path).unwrap();
+        let mut loader = Loader::new();
+        let mut events = loader.map_events(path).unwrap();
+        match events.next().unwrap().unwrap() {
+            MapEvent::MapStarted(started) => {
+                assert_eq!(started.properties, map.properties);
+                assert_eq!(started.infinite(), map.infinite());
+                assert_eq!((started.width, started.height), (map.width, map.height));
+                assert_eq!(started.tilesets().len(), 0);
+                assert_eq!(started.layers().len(), 0);
+            }
+            event => panic!("{}: unexpected first event {:?}", path, event),
+        }
+
+        let mut tilesets = Vec::new();
+        // All the layers of the map, groups before their children.
+        fn collect<'map>(layers: impl Iterator<Item = Layer<'map>>, all: &mut Vec<Layer<'map>>) {
+            for layer in layers {
+                all.push(layer);
+                if let LayerType::Group(group) = layer.layer_type() {
+                    collect(group.layers(), all);
+                }
+            }
+        }
+        let mut all = Vec::new();
+        collect(map.layers(), &mut all);
+        let mut layers = all.into_iter();
+        let mut open: Vec<(Layer, usize)> = Vec::new();
+        let mut tiles = 0;
+        for event in events {
+            match event.unwrap() {
+                MapEvent::Tileset { first_gid, tileset } => tilesets.push((first_gid, tileset)),
+                MapEvent::LayerStarted(info) => {
+                    let layer = layers.next().unwrap();
+                    assert_eq!(info.id, layer.id());
+                    assert_eq!(info.name, layer.name);
+                    assert_eq!(info.properties, layer.properties);
+                    assert_eq!(info.opacity, layer.opacity);
+                    match (&info.kind, layer.layer_type()) {
+                        (LayerKind::Tiles { .. }, LayerType::Tiles(_))
+                        | (LayerKind::Objects { .. }, LayerType::Objects(_))
+                        | (LayerKind::Group, LayerType::Group(_)) => {}
+                        (LayerKind::Image(image), LayerType::Image(expected)) => {
+                            assert_eq!(image, &*expected)
+                        }
+                        (kind, _) => {
+                            panic!("{}: unexpected kind {:?} for {}", path, kind, layer.name)
+                        }
+                    }
+                    open.push((layer, 0));
+                }
+                MapEvent::TileRow(row) => {
+                    let layer = open.last().unwrap().0.as_tile_layer().unwrap();
+                    for (x, tile) in (row.x..).zip(&row.tiles) {
+                        let expected = match layer {
+                            TileLayer::Finite(layer) => layer.get_tile_data(x, row.y).copied(),
+                            TileLayer::Infinite(layer) => layer.get_tile_data(x, row.y).copied(),
+                        };
+                        assert_eq!(*tile, expected, "{}: tile at {}, {}", path, x, row.y);
+                    }
+                    tiles += row.tiles.iter().flatten().count();
+                }
+                MapEvent::ObjectParsed(object) => {
+                    let (layer, count) = open.last_mut().unwrap();
+                    let objects = layer.as_object_layer().unwrap();
+                    assert_eq!(object, objects.object_data()[*count]);
+                    *count += 1;
+                }
+                MapEvent::LayerEnded => {
+                    let (layer, count) = open.pop().unwrap();
+                    if let Some(objects) = layer.as_object_layer() {
+                        assert_eq!(count, objects.object_data().len());
+                    }
+                }
+                event => panic!("{}: unexpected event {:?}", path, event),
+            }
+        }
+        assert!(open.is_empty(), "{}", path);
+        assert!(layers.next().is_none(), "{}", path);
+        assert_eq!(tilesets.len(), map.tilesets().len());
+        for (index, (first_gid, tileset)) in tilesets.iter().enumerate() {
+            assert_eq!(**tileset, *map.tilesets()[index]);
+            assert_eq!(Some(*first_gid), map.get_first_gid(index));
+        }
+
+        let expected: usize = map
+            .flattened_layers()
+            .filter_map(|layer| layer.layer.as_tile_layer())
+            .map(|layer| match layer {
+                TileLayer::Finite(layer) => (0..layer.height() as i32)
+                    .flat_map(|y| (0..layer.width() as i32).map(move |x| (x, y)))
+                    .filter(|&(x, y)| layer.get_tile_data(x, y).is_some())
+                    .count(),
+                TileLayer::Infinite(layer) => layer
+                    .chunk_data()
+                    .map(|(_, chunk)| chunk.tile_data().count())
+                    .sum(),
+            })
+            .sum();
+        assert_eq!(tiles, expected, "{}", path);
+    }
+
+    let mut loader = Loader::new();
+    assert!(loader.map_events("assets/missing.tmx").is_err());
+    // Iteration stops at the first error.
+    let data = std::fs::read_to_string("assets/tiled_base64.tmx").unwrap();
+    let data = data.replace("encoding=\"base64\"", "encoding=\"hex\"");
+    let reader = MemoryResourceReader::new().with_file("map.tmx", data.into_bytes());
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    let events: Vec<_> = loader.map_events("map.tmx").unwrap().collect();
+    assert!(
+        matches!(events.last(), Some(Err(Error::ElementError { element, .. })) if element == "data")
+    );
+    assert_eq!(events.iter().filter(|event| event.is_err()).count(), 1);
+}
+
+#[test]
+fn test_tile_data_decoding() {
+    let load = |width: usize, encoding: &str, data: &str| {
+        let map = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="{width}" height="1" tilewidth="32" tileheight="32" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tileset>
+ <layer id="1" name="layer" width="{width}" height="1">
+  <data encoding="{encoding}">{data}</data>
+ </layer>
+</map>"#
+        );
+        let reader = MemoryResourceReader::new().with_file("map.tmx", map.into_bytes());
+        let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+        let map = loader.load_tmx_map("map.tmx")?;
+        let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+        Ok::<_, Error>(
+            (0..width as i32)
+                .map(|x| layer.get_tile_data(x, 0).map(|tile| tile.id()))
+                .collect::<Vec<_>>(),
+        )
+    };
+    let expected = [Some(0), Some(1), Some(2), None, Some(83), Some(4), Some(5)];
+
+    for (width, data) in [
+        (5, "AQAAAAIAAAADAAAAAAAAAFQAAAA="),
+        (6, "\n   AQAAAAIAAAADAAAAAAAAAFQAAAAFAAAA\n  "),
+        (7, "AQAAAAIAAAADAAAAAAAAAFQAAAAFAAAABgAAAA=="),
+    ] {
+        assert_eq!(load(width, "base64", data).unwrap(), expected[..width]);
+    }
+    assert_eq!(
+        load(7, "csv", "\n1,2, 3,0\n,84,5 ,\r\n6\n").unwrap(),
+        expected
+    );
+
+    for data in [
+        "AQAAAAIAAAADAAAA*AAAAFQAAAA=",
+        "AQAAAAIAAAADAAAAAAAAAFQAAAB=",
+        "AQAAAAIAAAADAAAAAAAAAFQAAA",
+    ] {
+        match load(5, "base64", data) {
+            Err(Error::ElementError { err, .. }) => {
+                assert!(matches!(*err, Error::Base64DecodingError(_)), "{:?}", err)
+            }
+            result => panic!("{}: unexpected {:?}", data, result),
+        }
+    }
+}
+
+#[test]
+fn test_load_limits() {
+    fn exceeded<T: std::fmt::Debug>(result: tiled::Result<T>) -> (LoadLimit, u64) {
+        match result.unwrap_err().inner() {
+            Error::LimitExceeded { limit, max } => (*limit, *max),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+    fn loader_with(limits: LoadLimits) -> Loader {
+        let mut loader = Loader::new();
+        loader.set_limits(limits);
+        loader
+    }
+
+    assert_eq!(Loader::new().limits(), LoadLimits::default());
+
+    // The layer decompresses to 100x100 GIDs of 4 bytes.
+    let path = "assets/tiled_base64_zlib.tmx";
+    let fits = LoadLimits {
+        max_decompressed_bytes: Some(40_000),
+        ..LoadLimits::default()
+    };
+    loader_with(fits).load_tmx_map(path).unwrap();
+    let too_small = LoadLimits {
+        max_decompressed_bytes: Some(39_999),
+        ..LoadLimits::default()
+    };
+    assert_eq!(
+        exceeded(loader_with(too_small).load_tmx_map(path)),
+        (LoadLimit::DecompressedBytes, 39_999)
+    );
+    // Deferred data is decoded with the limits of the loader that loaded it.
+    let mut loader = loader_with(too_small);
+    loader.set_defer_tile_data(true);
+    let map = loader.load_tmx_map(path).unwrap();
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert_eq!(
+        exceeded(layer.ensure_decoded()),
+        (LoadLimit::DecompressedBytes, 39_999)
+    );
+
+    // Three 8x8 layers.
+    let path = "assets/tiled_group_layers.tmx";
+    let tiles = |max| LoadLimits {
+        max_tiles: Some(max),
+        ..LoadLimits::default()
+    };
+    loader_with(tiles(192)).load_tmx_map(path).unwrap();
+    assert_eq!(
+        exceeded(loader_with(tiles(191)).load_tmx_map(path)),
+        (LoadLimit::Tiles, 191)
+    );
+    // Each map is counted on its own.
+    let mut loader = loader_with(tiles(192));
+    loader.load_tmx_map(path).unwrap();
+    loader.load_tmx_map(path).unwrap();
+
+    // The map has seven 32x32 chunks.
+    let path = "assets/tiled_base64_zlib_infinite.tmx";
+    loader_with(tiles(7 * 1024)).load_tmx_map(path).unwrap();
+    assert_eq!(
+        exceeded(loader_with(tiles(7 * 1024 - 1)).load_tmx_map(path)),
+        (LoadLimit::Tiles, 7 * 1024 - 1)
+    );
+
+    // group-3 is nested in group-2.
+    let path = "assets/tiled_group_layers.tmx";
+    let depth = |max| LoadLimits {
+        max_group_depth: Some(max),
+        ..LoadLimits::default()
+    };
+    loader_with(depth(2)).load_tmx_map(path).unwrap();
+    assert_eq!(
+        exceeded(loader_with(depth(1)).load_tmx_map(path)),
+        (LoadLimit::GroupDepth, 1)
+    );
+    let mut loader = loader_with(depth(1));
+    let events: tiled::Result<Vec<MapEvent>> = loader.map_events(path).unwrap().collect();
+    assert_eq!(exceeded(events), (LoadLimit::GroupDepth, 1));
+    let mut loader = loader_with(depth(2));
+    let events: tiled::Result<Vec<MapEvent>> = loader.map_events(path).unwrap().collect();
+    events.unwrap();
+
+    #[cfg(feature = "json")]
+    {
+        let path = "assets/tiled_group_layers.tmj";
+        loader_with(depth(2)).load_json_map(path).unwrap();
+        assert_eq!(
+            exceeded(loader_with(depth(1)).load_json_map(path)),
+            (LoadLimit::GroupDepth, 1)
+        );
+        loader_with(tiles(192)).load_json_map(path).unwrap();
+        assert_eq!(
+            exceeded(loader_with(tiles(191)).load_json_map(path)),
+            (LoadLimit::Tiles, 191)
+        );
+    }
+}
+
+#[test]
+fn test_strict_mode() {
+    let mut loader = Loader::new();
+    assert!(!loader.strict());
+    // Unknown elements are ignored unless the loader is strict.
+    let path = "assets/tiled_unknown_elements.tmx";
+    let map = loader.load_tmx_map(path).unwrap();
+    assert_eq!(map.layers().len(), 1);
+    assert!(loader.take_warnings().is_empty());
+
+    loader.set_strict(true);
+    let err = loader.load_tmx_map(path).unwrap_err();
+    assert!(matches!(
+        err.inner(),
+        Error::UnknownElement { element, parent } if element == "layre" && parent == "map"
+    ));
+    assert!(matches!(err, Error::ElementError { line: 9, .. }));
+    let events: tiled::Result<Vec<MapEvent>> = loader.map_events(path).unwrap().collect();
+    assert!(matches!(
+        events.unwrap_err().inner(),
+        Error::UnknownElement { .. }
+    ));
+
+    loader.set_error_policy(ErrorPolicy::SkipAndWarn);
+    let map = loader.load_tmx_map(path).unwrap();
+    assert_eq!(map.layers().len(), 1);
+    // Warnings are cleared when the next file starts loading, rather than piling up.
+    loader.load_tmx_map(path).unwrap();
+    let warnings = loader.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].kind,
+        WarningKind::UnknownElement {
+            element: "layre".to_owned(),
+            parent: "map".to_owned(),
+        }
+    );
+    assert_eq!(warnings[0].location.as_ref().unwrap().line, 9);
+
+    // Unknown attributes are errors too, including in the files a map refers to.
+    let tileset = std::fs::read_to_string("assets/tilesheet.tsx")
+        .unwrap()
+        .replace("tilewidth=", "tilewdith=\"32\" tilewidth=");
+    let reader = MemoryResourceReader::new()
+        .with_file(
+            "map.tmx",
+            std::fs::read("assets/tiled_base64_external.tmx").unwrap(),
+        )
+        .with_file("tilesheet.tsx", tileset.into_bytes());
+    let mut loader =
+        Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader.clone());
+    loader.load_tmx_map("map.tmx").unwrap();
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    loader.set_strict(true);
+    let err = loader.load_tmx_map("map.tmx").unwrap_err();
+    assert!(matches!(
+        err.inner(),
+        Error::UnknownAttribute { element, attribute }
+            if element == "tileset" && attribute == "tilewdith"
+    ));
+
+    // The assets only use elements and attributes of the format.
+    for entry in std::fs::read_dir("assets").unwrap() {
+        let path = entry.unwrap().path();
+        let mut loader = Loader::new();
+        loader.set_strict(true);
+        let result = match path.extension().and_then(|e| e.to_str()) {
+            Some("tmx") if !path.ends_with("tiled_unknown_elements.tmx") => {
+                loader.load_tmx_map(&path).map(drop)
+            }
+            Some("tsx") if !path.ends_with("tiled_extensions.tsx") => {
+                loader.load_tsx_tileset(&path).map(drop)
+            }
+            _ => continue,
+        };
+        if let Err(err) = result {
+            assert!(
+                !matches!(
+                    err.inner(),
+                    Error::UnknownElement { .. } | Error::UnknownAttribute { .. }
+                ),
+                "{}: {}",
+                path.display(),
+                err
+            );
+        }
+    }
+}
+
+#[test]
+fn test_preserve_extensions() {
+    let path = "assets/tiled_extensions.tsx";
+    let mut loader = Loader::new();
+    assert!(!loader.preserve_extensions());
+    let tileset = loader.load_tsx_tileset(path).unwrap();
+    assert!(tileset.extensions.is_empty());
+    assert!(tileset.get_tile(3).unwrap().extensions.is_empty());
+
+    let mut loader = Loader::new();
+    loader.set_preserve_extensions(true);
+    let tileset = loader.load_tsx_tileset(path).unwrap();
+    assert_eq!(
+        tileset.extensions.attribute("editor:color"),
+        Some("#ff0000")
+    );
+    // Known attributes and namespace declarations aren't extensions.
+    assert_eq!(tileset.extensions.attributes().count(), 1);
+    assert_eq!(
+        tileset.extensions.element_names().collect::<Vec<_>>(),
+        ["editor:notes"]
+    );
+    let tile = tileset.get_tile(3).unwrap();
+    assert_eq!(tile.extensions.attribute("editor:locked"), Some("1"));
+    assert_eq!(tile.extensions.element_names().count(), 0);
+    let object = &tile.collision.as_ref().unwrap().object_data()[0];
+    assert!(object.extensions.attributes().next().is_none());
+    assert_eq!(
+        object.extensions.element_names().collect::<Vec<_>>(),
+        ["editor:hint"]
+    );
+    assert_eq!(tileset.image.as_ref().unwrap().width, 448);
+
+    // Writing the tileset back keeps its extensions.
+    let mut tsx = Vec::new();
+    tileset.write_tsx(&mut tsx, path).unwrap();
+    let tsx = String::from_utf8(tsx).unwrap();
+    assert!(tsx.contains("Keep the grass tiles together"));
+    assert!(tsx.contains("<![CDATA[<everything>]]>"));
+    let reader = MemoryResourceReader::new().with_file(path, tsx.into_bytes());
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    loader.set_preserve_extensions(true);
+    let written = loader.load_tsx_tileset(path).unwrap();
+    assert_eq!(written.extensions, tileset.extensions);
+    let written_tile = written.get_tile(3).unwrap();
+    assert_eq!(written_tile.extensions, tile.extensions);
+    assert_eq!(
+        written_tile.collision.as_ref().unwrap().object_data()[0].extensions,
+        object.extensions
+    );
+
+    // Unknown elements of maps are kept as well.
+    let mut loader = Loader::new();
+    loader.set_preserve_extensions(true);
+    let map = loader
+        .load_tmx_map("assets/tiled_unknown_elements.tmx")
+        .unwrap();
+    assert_eq!(map.layers().len(), 1);
+    assert_eq!(
+        map.extensions.element_names().collect::<Vec<_>>(),
+        ["layre"]
+    );
+    assert!(map.extensions.attributes().next().is_none());
+}
+
+/// Writes `map` as if it were stored at `path`, then loads it back along with the files next to it.
+fn write_and_reload(map: &Map, path: &str, options: WriteOptions) -> (String, Map) {
+    let mut tmx = Vec::new();
+    map.write_tmx(&mut tmx, path, options).unwrap();
+    let tmx = String::from_utf8(tmx).unwrap();
+    let mut reader = MemoryResourceReader::new().with_file(path, tmx.clone().into_bytes());
+    for file in [
+        "tilesheet.tsx",
+        "tilesheet_template.tsx",
+        "tiled_object_template.tx",
+    ] {
+        let file = format!("assets/{}", file);
+        reader = reader.with_file(&file, std::fs::read(&file).unwrap());
+    }
+    let map = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader)
+        .load_tmx_map(path)
+        .unwrap();
+    (tmx, map)
+}
+
+#[test]
+fn test_write_tmx() {
+    let options = WriteOptions::default();
+    for name in [
+        "tiled_base64_zlib",
+        "tiled_base64_zlib_infinite",
+        "tiled_csv",
+        "tiled_group_layers",
+        "tiled_image_layers",
+        "tiled_object_template",
+        "tiled_text",
+    ] {
+        let path = format!("assets/{}.tmx", name);
+        let map = Loader::new().load_tmx_map(&path).unwrap();
+        let (tmx, written) = write_and_reload(&map, "assets/written.tmx", options);
+        assert_eq!(written, map, "{}", name);
+        assert!(!tmx.contains("compression="), "{}", name);
+    }
+
+    // Tile data is written as CSV, with the chunks of infinite layers being 16x16 tiles.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    let (tmx, _) = write_and_reload(&map, "assets/written.tmx", options);
+    assert!(tmx.contains(r#"<data encoding="csv">"#));
+    assert!(tmx.contains(r#"<chunk x="-32" y="0" width="16" height="16">"#));
+    assert!(!tmx.contains("<editorsettings>"));
+
+    // External tilesets and templates are referred to rather than embedded.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_object_template.tmx")
+        .unwrap();
+    let (tmx, _) = write_and_reload(&map, "assets/written.tmx", options);
+    assert!(tmx.contains(r#"<tileset firstgid="1" source="tilesheet.tsx"/>"#));
+    assert!(tmx.contains(r#"<object id="1" template="tiled_object_template.tx" x="32" y="32"/>"#));
+
+    // Tilesets embedded in JSON maps stay embedded.
+    #[cfg(feature = "json")]
+    {
+        let map = Loader::new()
+            .load_json_map("assets/tiled_base64_zlib_infinite.tmj")
+            .unwrap();
+        let mut tmx = Vec::new();
+        map.write_tmx(&mut tmx, "assets/written.tmx", options)
+            .unwrap();
+        let tmx = String::from_utf8(tmx).unwrap();
+        assert!(tmx.contains(r#"<tileset firstgid="1" name="tilesheet""#));
+        assert!(tmx.contains(r#"<tileset firstgid="85" source="tilesheet.tsj"/>"#));
+    }
+}
+
+#[test]
+fn test_write_tmx_preserve_formatting() {
+    let options = WriteOptions {
+        preserve_formatting: true,
+        ..Default::default()
+    };
+
+    // Maps without anything the writer doesn't keep are written exactly as Tiled wrote them.
+    for name in ["tiled_text", "tiled_hexagonal"] {
+        let path = format!("assets/{}.tmx", name);
+        let map = Loader::new().load_tmx_map(&path).unwrap();
+        let (tmx, written) = write_and_reload(&map, &path, options);
+        assert_eq!(tmx, std::fs::read_to_string(&path).unwrap(), "{}", name);
+        assert_eq!(written, map, "{}", name);
+    }
+
+    // Line endings are kept, whichever way the file was checked out.
+    for name in ["tiled_text", "tiled_hexagonal"] {
+        let path = format!("assets/{}.tmx", name);
+        let lf = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("\r\n", "\n");
+        for source in [lf.clone(), lf.replace('\n', "\r\n")] {
+            let map = Loader::new()
+                .load_tmx_map_from_reader(Cursor::new(source.clone()), &path)
+                .unwrap();
+            let mut tmx = Vec::new();
+            map.write_tmx(&mut tmx, &path, options).unwrap();
+            assert_eq!(String::from_utf8(tmx).unwrap(), source, "{}", name);
+        }
+    }
+
+    // Compressed data is written back as it was read, in chunks of the same size.
+    let path = "assets/tiled_base64_zlib_infinite.tmx";
+    let mut map = Loader::new().load_tmx_map(path).unwrap();
+    let (tmx, written) = write_and_reload(&map, path, options);
+    assert_eq!(written, map);
+    let tmx = tmx.replace("\r\n", "\n");
+    assert!(tmx.contains(r#"<data encoding="base64" compression="zlib">"#));
+    assert!(tmx.contains(
+        r#"<chunk x="-32" y="0" width="32" height="32">
+   eJztzTENAAAMw7BiGH+wg9CjryPldrJ142t8Pp/P5/P5fD6fz+fz+w/olSQB
+  </chunk>"#
+    ));
+    assert!(tmx.contains(
+        r#"<editorsettings>
+  <chunksize width="32" height="32"/>
+ </editorsettings>"#
+    ));
+
+    // Modified data is encoded the same way again.
+    map.layers_mut()
+        .find_map(LayerData::as_infinite_tile_layer_mut)
+        .unwrap()
+        .set_tile(-32, 0, Some(LayerTileData::new(0, 1)));
+    let (tmx, written) = write_and_reload(&map, path, options);
+    assert_eq!(written, map);
+    assert!(!tmx.contains("eJztzTENAAAMw7BiGH+wg9CjryPldrJ142t8Pp/P5/P5fD6fz+fz+w/olSQB"));
+    assert!(tmx.contains(r#"<data encoding="base64" compression="zlib">"#));
+}
+
+#[test]
+fn test_write_tmx_tile_data_encoding() {
+    let encodings = [
+        (TileDataEncoding::Csv, r#"<data encoding="csv">"#),
+        (TileDataEncoding::Base64, r#"<data encoding="base64">"#),
+        (
+            TileDataEncoding::Base64Zlib,
+            r#"<data encoding="base64" compression="zlib">"#,
+        ),
+        (
+            TileDataEncoding::Base64Gzip,
+            r#"<data encoding="base64" compression="gzip">"#,
+        ),
+        #[cfg(feature = "zstd")]
+        (
+            TileDataEncoding::Base64Zstd,
+            r#"<data encoding="base64" compression="zstd">"#,
+        ),
+    ];
+    for (encoding, data) in encodings {
+        let options = WriteOptions {
+            tile_data_encoding: encoding,
+            ..Default::default()
+        };
+        for name in ["tiled_csv", "tiled_base64_zlib_infinite", "tiled_flipped"] {
+            let path = format!("assets/{}.tmx", name);
+            let map = Loader::new().load_tmx_map(&path).unwrap();
+            let (tmx, written) = write_and_reload(&map, "assets/written.tmx", options);
+            assert_eq!(written, map, "{} as {:?}", name, encoding);
+            assert!(tmx.contains(data), "{} as {:?}", name, encoding);
+        }
+    }
+
+    // Layers keep the encoding of their source when formatting is preserved, but the ones whose
+    // encoding can't be written back use the encoding given.
+    let options = WriteOptions {
+        preserve_formatting: true,
+        tile_data_encoding: TileDataEncoding::Base64Gzip,
+    };
+    let map = Loader::new().load_tmx_map("assets/tiled_csv.tmx").unwrap();
+    let (tmx, _) = write_and_reload(&map, "assets/written.tmx", options);
+    assert!(tmx.contains(r#"<data encoding="csv">"#));
+    let map = Loader::new().load_tmx_map("assets/tiled_xml.tmx").unwrap();
+    let (tmx, written) = write_and_reload(&map, "assets/written.tmx", options);
+    assert_eq!(written, map);
+    assert!(tmx.contains(r#"<data encoding="base64" compression="gzip">"#));
+}
+
+#[test]
+fn test_versions() {
+    let mut loader = Loader::new();
+    let map = loader.load_tmx_map("assets/tiled_text.tmx").unwrap();
+    assert_eq!(map.version(), Version::new(1, 10, 0));
+    assert_eq!(map.tiled_version, Some(Version::new(1, 10, 2)));
+    let map = loader
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    let tileset = map.tilesets()[0].as_ref();
+    assert_eq!(tileset.version, Some(Version::new(1, 4, 0)));
+    assert_eq!(tileset.tiled_version, Some(Version::new(1, 4, 0)));
+    // Embedded tilesets are saved with the versions of their map.
+    let map = loader.load_tmx_map("assets/tiled_base64_zlib.tmx").unwrap();
+    assert_eq!(map.tilesets()[0].version, Some(map.version()));
+    assert_eq!(map.tilesets()[0].tiled_version, map.tiled_version);
+
+    assert_eq!("1.10".parse::<Version>().unwrap(), Version::new(1, 10, 0));
+    assert_eq!(
+        "1.11.0-beta".parse::<Version>().unwrap(),
+        Version::new(1, 11, 0)
+    );
+    assert_eq!(
+        "2020.05.20".parse::<Version>().unwrap(),
+        Version::new(2020, 5, 20)
+    );
+    assert!("1.10.2.1".parse::<Version>().is_err());
+    assert!("one".parse::<Version>().is_err());
+    assert_eq!(Version::new(1, 10, 0).to_string(), "1.10");
+    assert_eq!(Version::new(1, 10, 2).to_string(), "1.10.2");
+    assert!(Version::new(1, 9, 2) < Version::new(1, 10, 0));
+}
+
+#[test]
+fn test_check_compatibility() {
+    let mut loader = Loader::new();
+    for path in [
+        "assets/tiled_base64_zlib.tmx",
+        "assets/tiled_base64_external.tmx",
+        // Saved by a development snapshot of Tiled, versioned by its date.
+        "assets/tiled_base64_zlib_infinite.tmx",
+    ] {
+        let map = loader.load_tmx_map(path).unwrap();
+        assert_eq!(loader.check_compatibility(&map), vec![], "{}", path);
+    }
+
+    let map = std::fs::read_to_string("assets/tiled_base64_zlib.tmx")
+        .unwrap()
+        .replace(r#" version="1.4""#, r#" version="1.12""#)
+        .replace(r#"tiledversion="1.4.0""#, r#"tiledversion="1.12.1""#)
+        .replace(r#"compression="zlib""#, r#"compression="lz4""#);
+    let reader = MemoryResourceReader::new().with_file("map.tmx", map.into_bytes());
+    let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+    loader.set_defer_tile_data(true);
+    let map = loader.load_tmx_map("map.tmx").unwrap();
+    let issues = loader.check_compatibility(&map);
+    assert_eq!(
+        issues,
+        vec![
+            CompatibilityIssue::UnsupportedFormatVersion {
+                tileset: None,
+                version: Version::new(1, 12, 0),
+            },
+            CompatibilityIssue::NewerTiledVersion {
+                tileset: None,
+                version: Version::new(1, 12, 1),
+            },
+            CompatibilityIssue::UnsupportedCompression {
+                layer: 1,
+                compression: "lz4".to_owned(),
+            },
+        ]
+    );
+    assert_eq!(
+        issues[0].to_string(),
+        "the map was saved in version 1.12 of the TMX format, which is not supported"
+    );
+}
+
+#[test]
+fn test_legacy_xml_tile_data() {
+    for defer in [false, true] {
+        let mut loader = Loader::new();
+        loader.set_defer_tile_data(defer);
+        let map = loader.load_tmx_map("assets/tiled_xml.tmx").unwrap();
+        let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+        let tile = |x, y| layer.get_tile(x, y).map(|tile| tile.id());
+        assert_eq!(tile(0, 0), Some(29));
+        assert_eq!(tile(0, 1), Some(43));
+        assert_eq!(tile(1, 3), Some(0));
+        assert_eq!(tile(4, 4), Some(72));
+        assert_eq!(tile(9, 6), Some(73));
+        assert_eq!(tile(10, 0), None);
+        assert_eq!(tile(0, 7), None);
+        let count = (0..100)
+            .flat_map(|y| (0..100).map(move |x| (x, y)))
+            .filter(|&(x, y)| tile(x, y).is_some())
+            .count();
+        assert_eq!(count, 70);
+    }
+
+    // Chunks of infinite layers can list their tiles the same way.
+    let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.2" orientation="orthogonal" renderorder="right-down" width="4" height="4" tilewidth="32" tileheight="32" infinite="1" nextlayerid="2" nextobjectid="1">
+ <tileset firstgid="1" name="tilesheet" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tileset>
+ <layer id="1" name="Tile Layer 1" width="4" height="4">
+  <data>
+   <chunk x="-2" y="0" width="2" height="2">
+    <tile gid="3"/>
+    <tile/>
+    <tile gid="0"/>
+    <tile gid="5"/>
+   </chunk>
+  </data>
+ </layer>
+</map>"#;
+    let map = Loader::new()
+        .load_tmx_map_from_reader(Cursor::new(data), "assets/legacy_xml_infinite.tmx")
+        .unwrap();
+    let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+        TileLayer::Infinite(layer) => layer,
+        _ => panic!("expected an infinite layer"),
+    };
+    let tile = |x, y| layer.get_tile_data(x, y).map(|tile| tile.id());
+    assert_eq!(
+        [tile(-2, 0), tile(-1, 0), tile(-2, 1), tile(-1, 1)],
+        [Some(2), None, None, Some(4)]
+    );
+}
+
+#[test]
+fn test_malformed_csv_data() {
+    let tmx = std::fs::read_to_string("assets/tiled_csv.tmx")
+        .unwrap()
+        .replacen("35,35,35,35,35,33", "35,35,3x5,35,35,33", 1);
+    for defer in [false, true] {
+        let mut loader = Loader::new();
+        loader.set_defer_tile_data(defer);
+        let err = match loader
+            .load_tmx_map_from_reader(Cursor::new(tmx.clone()), "assets/tiled_csv.tmx")
+        {
+            Ok(map) => match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+                TileLayer::Finite(layer) => layer.ensure_decoded().unwrap_err(),
+                TileLayer::Infinite(_) => panic!("expected a finite layer"),
+            },
+            Err(err) => err,
+        };
+        assert!(
+            matches!(err.inner(), Error::MalformedAttributes(message) if message.contains("'3x5'")),
+            "{:?}",
+            err
+        );
+    }
+}
+
+#[test]
+fn test_compact_tile_data() {
+    for path in [
+        "assets/tiled_csv.tmx",
+        "assets/tiled_base64_zlib.tmx",
+        "assets/tiled_flipped.tmx",
+    ] {
+        let expected = Loader::new().load_tmx_map(path).unwrap();
+        for defer in [false, true] {
+            let mut loader = Loader::new();
+            loader.set_compact_tile_data(true);
+            loader.set_defer_tile_data(defer);
+            assert!(loader.compact_tile_data());
+            let map = loader.load_tmx_map(path).unwrap();
+
+            let expected = as_finite(expected.get_layer(0).unwrap().as_tile_layer().unwrap());
+            let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+            for y in 0..expected.height() as i32 {
+                for x in 0..expected.width() as i32 {
+                    assert_eq!(
+                        expected.get_tile(x, y).map(|tile| *tile),
+                        layer.get_tile(x, y).map(|tile| *tile)
+                    );
+                    assert_eq!(expected.get_tile_data(x, y), layer.get_tile_data(x, y));
+                }
+            }
+            assert_eq!(*expected, *layer);
+        }
+    }
+
+    let mut loader = Loader::new();
+    loader.set_compact_tile_data(true);
+    let mut map = loader.load_tmx_map("assets/tiled_csv.tmx").unwrap();
+    let tile = *as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap())
+        .get_tile(0, 0)
+        .unwrap();
+    let tiles = map
+        .get_layer_mut(0)
+        .unwrap()
+        .as_finite_tile_layer_mut()
+        .unwrap();
+    assert!(tiles.set_tile(1, 0, Some(tile)));
+    let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert_eq!(layer.get_tile(1, 0).map(|tile| *tile), Some(tile));
+}
+
+#[test]
+fn test_image_layers() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_image_layers.tmx")
+        .unwrap();
+    assert_eq!(r.layers().len(), 2);
+    let mut image_layers = r.layers().map(|layer| {
+        if let LayerType::Image(img) = layer.layer_type() {
+            (img, layer)
+        } else {
+            panic!("Found layer that isn't an image layer")
+        }
+    });
+    {
+        let first = image_layers.next().unwrap();
+        assert_eq!(first.1.name, "Image Layer 1");
+        assert!(
+            first.0.image.is_none(),
+            "{}'s image should be None",
+            first.1.name
+        );
+        assert!(!first.0.repeat_x && !first.0.repeat_y);
+    }
+    {
+        let second = image_layers.next().unwrap();
+        assert_eq!(second.1.name, "Image Layer 2");
+        let image = second
+            .0
+            .image
+            .as_ref()
+            .unwrap_or_else(|| panic!("{}'s image shouldn't be None", second.1.name));
+        assert_eq!(image.source, PathBuf::from("assets/tilesheet.png"));
+        assert_eq!(image.width, 448);
+        assert_eq!(image.height, 192);
+        assert!(second.0.repeat_x);
+        assert!(!second.0.repeat_y);
+    }
+}
+
+#[test]
+fn test_tile_property() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    let prop_value: String = if let Some(&PropertyValue::StringValue(ref v)) = r.tilesets()[0]
+        .get_tile(1)
+        .unwrap()
+        .properties
+        .get("a tile property")
+    {
+        v.clone()
+    } else {
+        String::new()
+    };
+    assert_eq!("123", prop_value);
+}
+
+#[test]
+fn test_layer_property() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    let prop_value: String = if let Some(&PropertyValue::StringValue(ref v)) =
+        r.get_layer(0).unwrap().properties.get("prop3")
+    {
+        v.clone()
+    } else {
+        String::new()
+    };
+    assert_eq!("Line 1\r\nLine 2\r\nLine 3,\r\n  etc\r\n   ", prop_value);
+}
+
+#[test]
+fn test_object_group_property() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_object_groups.tmx")
+        .unwrap();
+    let group_layer = r.get_layer(1).unwrap();
+    let group_layer = group_layer.as_group_layer().unwrap();
+    let sub_layer = group_layer.get_layer(0).unwrap();
+    let prop_value: bool = if let Some(&PropertyValue::BoolValue(ref v)) =
+        sub_layer.properties.get("an object group property")
+    {
+        *v
+    } else {
+        false
+    };
+    assert!(prop_value);
+}
+#[test]
+fn test_tileset_property() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    let prop_value: String = if let Some(&PropertyValue::StringValue(ref v)) =
+        r.tilesets()[0].properties.get("tileset property")
+    {
+        v.clone()
+    } else {
+        String::new()
+    };
+    assert_eq!("tsp", prop_value);
+}
+
+#[test]
+fn test_flipped() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_flipped.tmx")
+        .unwrap();
+    let layer = r.get_layer(0).unwrap().as_tile_layer().unwrap();
+
+    let t1 = layer.get_tile(0, 0).unwrap();
+    let t2 = layer.get_tile(1, 0).unwrap();
+    let t3 = layer.get_tile(0, 1).unwrap();
+    let t4 = layer.get_tile(1, 1).unwrap();
+    assert_eq!(t1.id(), t2.id());
+    assert_eq!(t2.id(), t3.id());
+    assert_eq!(t3.id(), t4.id());
+    assert!(t1.flip_d);
+    assert!(t1.flip_h);
+    assert!(t1.flip_v);
+    assert!(!t2.flip_d);
+    assert!(!t2.flip_h);
+    assert!(t2.flip_v);
+    assert!(!t3.flip_d);
+    assert!(t3.flip_h);
+    assert!(!t3.flip_v);
+    assert!(t4.flip_d);
+    assert!(!t4.flip_h);
+    assert!(!t4.flip_v);
+}
+
+#[test]
+fn test_ldk_export() {
+    let r = Loader::new()
+        .load_tmx_map("assets/ldk_tiled_export.tmx")
+        .unwrap();
+    let layer = as_finite(r.get_layer(0).unwrap().as_tile_layer().unwrap());
+    {
+        assert_eq!(layer.width(), 8);
+        assert_eq!(layer.height(), 8);
+    }
+    assert!(layer.get_tile(0, 0).is_none());
+    assert_eq!(layer.get_tile(0, 1).unwrap().id(), 0);
+}
+
+#[test]
+fn test_parallax_layers() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_parallax.tmx")
+        .unwrap();
+    assert_eq!(r.parallax_origin_x, 160.0);
+    assert_eq!(r.parallax_origin_y, -32.5);
+    for (i, layer) in r.layers().enumerate() {
+        match i {
+            0 => {
+                assert_eq!(layer.name, "Background");
+                assert_eq!(layer.parallax_x, 0.5);
+                assert_eq!(layer.parallax_y, 0.75);
+            }
+            1 => {
+                assert_eq!(layer.name, "Middle");
+                assert_eq!(layer.parallax_x, 1.0);
+                assert_eq!(layer.parallax_y, 1.0);
+            }
+            2 => {
+                assert_eq!(layer.name, "Foreground");
+                assert_eq!(layer.parallax_x, 2.0);
+                assert_eq!(layer.parallax_y, 2.0);
+            }
+            _ => panic!("unexpected layer"),
+        }
+    }
+}
+
+#[test]
+fn test_object_property() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_object_property.tmx")
+        .unwrap();
+    let layer = r.get_layer(1).unwrap();
+    let prop_value = if let Some(PropertyValue::ObjectValue(v)) = layer
+        .as_object_layer()
+        .unwrap()
+        .get_object(0)
+        .unwrap()
+        .properties
+        .get("object property")
+    {
+        *v
+    } else {
+        0
+    };
+    assert_eq!(3, prop_value);
+}
+
+#[test]
+fn test_background_color() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_image_layers.tmx")
+        .unwrap();
+    let color = r.background_color.unwrap();
+    assert_eq!(
+        color,
+        Color {
+            alpha: 0x80,
+            red: 0x20,
+            green: 0x40,
+            blue: 0x60
+        }
+    );
+    assert_eq!(u32::from(color), 0x80204060);
+    assert_eq!(Color::from(0x80204060), color);
+    assert_eq!(
+        <[f32; 4]>::from(color),
+        [
+            0x20 as f32 / 255.0,
+            0x40 as f32 / 255.0,
+            0x60 as f32 / 255.0,
+            0x80 as f32 / 255.0
+        ]
+    );
+
+    // Colors without an alpha channel are opaque.
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    assert_eq!(u32::from(r.background_color.unwrap()), 0xffff00ff);
+}
+
+#[test]
+fn test_tint_color() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_image_layers.tmx")
+        .unwrap();
+    assert_eq!(
+        r.get_layer(0).unwrap().tint_color,
+        Some(Color {
+            alpha: 0x12,
+            red: 0x34,
+            green: 0x56,
+            blue: 0x78
+        })
+    );
+    assert_eq!(
+        r.get_layer(1).unwrap().tint_color,
+        Some(Color {
+            alpha: 0xFF,
+            red: 0x12,
+            green: 0x34,
+            blue: 0x56
+        })
+    );
+
+    // Combining two tints, as done for layers nested in groups, multiplies their channels.
+    let tint = r.get_layer(0).unwrap().tint_color.unwrap();
+    let group_tint = r.get_layer(1).unwrap().tint_color.unwrap();
+    assert_eq!(
+        tint.multiply(group_tint),
+        Color {
+            alpha: 0x12,
+            red: 0x04,
+            green: 0x12,
+            blue: 0x28
+        }
+    );
+}
+
+#[test]
+fn test_group_layers() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_group_layers.tmx")
+        .unwrap();
+
+    // Depth = 0
+    let layer_tile_1 = r.get_layer(0).unwrap();
+    let layer_group_1 = r.get_layer(1).unwrap();
+    let layer_group_2 = r.get_layer(2).unwrap();
+
+    assert_eq!(
+        Some(&PropertyValue::StringValue("value1".to_string())),
+        layer_tile_1.properties.get("key")
+    );
+    assert_eq!(
+        Some(&PropertyValue::ColorValue(Color {
+            alpha: 0x12,
+            red: 0x34,
+            green: 0x56,
+            blue: 0x78
+        })),
+        layer_group_1.properties.get("key")
+    );
+    assert_eq!(
+        Some(&PropertyValue::StringValue("value5".to_string())),
+        layer_group_2.properties.get("key")
+    );
+
+    // Depth = 1
+    let layer_group_1 = layer_group_1.as_group_layer().unwrap();
+    let layer_tile_2 = layer_group_1.get_layer(0).unwrap();
+    let layer_group_2 = layer_group_2.as_group_layer().unwrap();
+    let layer_group_3 = layer_group_2.get_layer(0).unwrap();
+    assert_eq!(
+        Some(&PropertyValue::StringValue("value2".to_string())),
+        layer_tile_2.properties.get("key")
+    );
+    assert_eq!(
+        Some(&PropertyValue::StringValue("value6".to_string())),
+        layer_group_3.properties.get("key")
+    );
+
+    // Depth = 2
+    let layer_group_3 = layer_group_3.as_group_layer().unwrap();
+    let layer_tile_3 = layer_group_3.get_layer(0).unwrap();
+    assert_eq!(
+        Some(&PropertyValue::StringValue("value3".to_string())),
+        layer_tile_3.properties.get("key")
+    );
+}
+
+#[test]
+fn test_object_template_property() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_object_template.tmx")
+        .unwrap();
+
+    let object_layer = r.get_layer(1).unwrap().as_object_layer().unwrap();
+    let object = object_layer.get_object(0).unwrap(); // The templated object
+    let object_nt = object_layer.get_object(1).unwrap(); // The non-templated object
+
+    // Test core properties
+    assert_eq!(
+        object.shape,
+        ObjectShape::Rect {
+            width: 32.0,
+            height: 32.0
+        }
+    );
+    assert_eq!(object.x, 32.0);
+    assert_eq!(object.y, 32.0);
+
+    // Test properties are copied over
+    assert_eq!(
+        Some(&PropertyValue::IntValue(1)),
+        object.properties.get("property")
+    );
+
+    // Test tileset handling
+    assert_eq!(
+        object.get_tile().unwrap().get_tileset().name,
+        "tilesheet_template"
+    );
+    assert_eq!(
+        object_nt.get_tile().unwrap().get_tileset().name,
+        "tilesheet"
+    );
+    assert!(matches!(
+        object.get_tile().unwrap().tileset_location(),
+        TilesetLocation::Template(..)
+    ));
+    assert_eq!(
+        object_nt.get_tile().unwrap().tileset_location(),
+        &TilesetLocation::Map(0)
+    );
+    assert_eq!(object.get_tile().unwrap().id(), 44);
+    assert_eq!(object_nt.get_tile().unwrap().id(), 44);
+}
+
+#[test]
+fn test_templates() {
+    let mut loader = Loader::new();
+    let map = loader.load_tmx_map("assets/templates/example.tmx").unwrap();
+
+    assert_eq!(loader.cache().templates.len(), 3);
+    assert_eq!(
+        if let LayerType::Tiles(x) = map.get_layer(0).unwrap().layer_type() {
+            x
+        } else {
+            panic!()
+        }
+        .get_tile(0, 0)
+        .unwrap()
+        .get_tileset()
+        .image
+        .as_ref()
+        .unwrap()
+        .source
+        .canonicalize()
+        .unwrap(),
+        PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/tilesheet.png"))
+            .canonicalize()
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_reading_wang_sets() {
+    let mut loader = Loader::new();
+    let map = loader
+        .load_tmx_map("assets/tiled_csv_wangsets.tmx")
+        .unwrap();
+
+    // We will pick some random data from the wangsets for tessting
+    let tileset = map.tilesets().get(0).unwrap();
+    assert_eq!(tileset.wang_sets.len(), 3);
+    let wangset_2 = tileset.wang_sets.get(1).unwrap();
+    let tile_10 = wangset_2.wang_tiles.get(&10).unwrap();
+    assert_eq!(tile_10.wang_id, WangId([2u8, 2, 0, 2, 0, 2, 2, 2]));
+    let wangset_3 = tileset.wang_sets.get(2).unwrap();
+    let color_2 = wangset_3.wang_colors.get(1).unwrap();
+    let readed_damage = color_2.properties.get("Damage").unwrap();
+    let damage_value = &PropertyValue::FloatValue(32.1);
+    assert_eq!(readed_damage, damage_value);
+}
+
+#[test]
+fn test_wang_set_accessors() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_csv_wangsets.tmx")
+        .unwrap();
+    let wang_set = &map.tilesets()[0].wang_sets[1];
+
+    let wang_id = wang_set.get_wang_tile(10).unwrap().wang_id;
+    assert_eq!(wang_id.edges(), [2, 0, 0, 2]);
+    assert_eq!(wang_id.corners(), [2, 2, 2, 2]);
+    assert!(wang_id.has_color(2));
+    assert!(!wang_id.has_color(0));
+    assert!(wang_set.get_wang_tile(u32::MAX).is_none());
+
+    assert!(wang_set.get_color(0).is_none());
+    assert_eq!(wang_set.get_color(2), wang_set.wang_colors.get(1));
+    let out_of_range = wang_set.wang_colors.len() as u8 + 1;
+    assert!(wang_set.get_color(out_of_range).is_none());
+}
+
+#[test]
+fn test_legacy_terrain_as_wang_set() {
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tiled_legacy_terrain.tsx")
+        .unwrap();
+    assert_eq!(tileset.wang_sets.len(), 1);
+    let wang_set = &tileset.wang_sets[0];
+    assert_eq!(wang_set.wang_set_type, WangSetType::Corner);
+
+    assert_eq!(wang_set.wang_colors.len(), 2);
+    assert_eq!(wang_set.wang_colors[0].name, "Grass");
+    assert_eq!(wang_set.wang_colors[0].tile, Some(14));
+    assert_eq!(wang_set.wang_colors[1].tile, None);
+    assert_eq!(
+        wang_set.wang_colors[1].properties.get("swimmable"),
+        Some(&PropertyValue::BoolValue(true))
+    );
+
+    assert_eq!(
+        wang_set.get_wang_tile(0).unwrap().wang_id,
+        WangId([0, 1, 0, 1, 0, 1, 0, 1])
+    );
+    // Terrain corners are listed as top-left, top-right, bottom-left, bottom-right.
+    let wang_id = wang_set.get_wang_tile(1).unwrap().wang_id;
+    assert_eq!(wang_id.corners(), [2, 2, 0, 1]);
+    assert_eq!(tileset.get_tile(1).unwrap().probability, 0.5);
+}
+
+#[test]
+fn test_tsx_round_trip() {
+    for path in [
+        "assets/tilesheet.tsx",
+        "assets/tilesheet_wangsets.tsx",
+        "assets/templates/grass_walls.tsx",
+        "assets/tiled_tileset_options.tsx",
+        "assets/tiled_tile_collision.tsx",
+    ] {
+        let tileset = Loader::new().load_tsx_tileset(path).unwrap();
+
+        let mut data = Vec::new();
+        tileset.write_tsx(&mut data, path).unwrap();
+        let reader = InMemoryResourceReader {
+            path: PathBuf::from(path),
+            data,
+        };
+        let written = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader)
+            .load_tsx_tileset(path)
+            .unwrap();
+
+        assert_eq!(tileset, written, "{} changed after being written", path);
+    }
+}
+
+#[test]
+fn test_tx_round_trip() {
+    // Templates can only be loaded through the maps using them.
+    for (map_path, path) in [
+        (
+            "assets/tiled_object_template.tmx",
+            "assets/tiled_object_template.tx",
+        ),
+        (
+            "assets/templates/example.tmx",
+            "assets/templates/simple_figure.tx",
+        ),
+    ] {
+        let mut loader = Loader::new();
+        loader.load_tmx_map(map_path).unwrap();
+        let template = loader.cache().get_template(Path::new(path)).unwrap();
+        assert!(template.tileset_source.is_some());
+
+        let mut data = Vec::new();
+        template.write_tx(&mut data, path).unwrap();
+        let reader = InMemoryResourceReader {
+            path: PathBuf::from(path),
+            data,
+        };
+        let mut loader = Loader::with_cache_and_reader(tiled::DefaultResourceCache::new(), reader);
+        loader.load_tmx_map(map_path).unwrap();
+        let written = loader.cache().get_template(Path::new(path)).unwrap();
+
+        assert_eq!(template.object, written.object, "{} changed", path);
+        assert_eq!(template.tileset, written.tileset, "{} changed", path);
+        assert_eq!(template.tileset_source, written.tileset_source);
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_maps_match_tmx() {
+    for name in [
+        "tiled_group_layers",
+        "tiled_base64_zlib_infinite",
+        "tiled_image_layers",
+        "tiled_object_groups",
+        "tiled_object_template",
+        "tiled_text",
+    ] {
+        let tmx = Loader::new()
+            .load_tmx_map(format!("assets/{}.tmx", name))
+            .unwrap();
+        let tmj = Loader::new()
+            .load_json_map(format!("assets/{}.tmj", name))
+            .unwrap();
+        assert_eq!(tmx, tmj, "{} differs between formats", name);
+    }
+
+    for name in ["tilesheet", "tiled_tileset_options"] {
+        let tsx = Loader::new()
+            .load_tsx_tileset(format!("assets/{}.tsx", name))
+            .unwrap();
+        let tsj = Loader::new()
+            .load_json_tileset(format!("assets/{}.tsj", name))
+            .unwrap();
+        assert_eq!(tsx, tsj, "{} differs between formats", name);
+    }
+}
+
+#[cfg(feature = "world")]
+#[test]
+fn test_world_patterns() {
+    let mut loader = Loader::new();
+    let world = loader
+        .load_world("assets/world/world_pattern.world")
+        .unwrap();
+
+    assert_eq!(world.patterns.len(), 1);
+    let positions: Vec<_> = world
+        .maps
+        .iter()
+        .map(|map| (map.source.clone(), map.x, map.y, map.width))
+        .collect();
+    assert_eq!(
+        positions,
+        [
+            (
+                PathBuf::from("assets/world/../tiled_base64.tmx"),
+                -3200,
+                0,
+                Some(3200)
+            ),
+            (
+                PathBuf::from("assets/world/map_0_-1.tmx"),
+                0,
+                -480,
+                Some(640)
+            ),
+            (PathBuf::from("assets/world/map_0_0.tmx"), 0, 0, Some(640)),
+            (PathBuf::from("assets/world/map_1_0.tmx"), 640, 0, Some(640)),
+        ]
+    );
+
+    for world_map in &world.maps {
+        loader.load_tmx_map(&world_map.source).unwrap();
+    }
+
+    // Readers that can't list files don't match any patterns.
+    let mut loader = Loader::with_cache_and_reader(
+        tiled::DefaultResourceCache::new(),
+        InMemoryResourceReader {
+            path: PathBuf::new(),
+            data: Vec::new(),
+        },
+    );
+    let world = loader
+        .load_world("assets/world/world_pattern.world")
+        .unwrap();
+    assert_eq!(world.maps.len(), 1);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_project_property_types() {
+    use tiled::{EnumStorageType, PropertyType};
+
+    let mut loader = Loader::new();
+    assert!(loader.project().is_none());
+    let project = loader.load_project("assets/tiled.tiled-project").unwrap();
+
+    assert_eq!(project.folders, [PathBuf::from("assets/.")]);
+    assert_eq!(
+        project.extensions_path,
+        Some(PathBuf::from("assets/extensions"))
+    );
+    assert_eq!(project.property_types.len(), 3);
+
+    let direction = match project.get_property_type("Direction") {
+        Some(PropertyType::Enum(direction)) => direction,
+        x => panic!("Unexpected property type: {:?}", x),
+    };
+    assert_eq!(direction.id, 1);
+    assert_eq!(direction.storage_type, EnumStorageType::String);
+    assert_eq!(direction.values, ["North", "East", "South", "West"]);
+    assert!(!direction.values_as_flags);
+
+    let access = match project.get_property_type("Access") {
+        Some(PropertyType::Enum(access)) => access,
+        x => panic!("Unexpected property type: {:?}", x),
+    };
+    assert_eq!(access.storage_type, EnumStorageType::Int);
+    assert!(access.values_as_flags);
+
+    let door = match project.get_property_type("Door") {
+        Some(PropertyType::Class(door)) => door,
+        x => panic!("Unexpected property type: {:?}", x),
+    };
+    assert_eq!(door.id, 3);
+    assert_eq!(door.use_as, ["property", "object"]);
+    assert_eq!(
+        door.color,
+        Some(Color {
+            alpha: 0xff,
+            red: 0xa0,
+            green: 0xa0,
+            blue: 0xa4
+        })
+    );
+    assert_eq!(
+        door.members.get("locked"),
+        Some(&PropertyValue::BoolValue(false))
+    );
+    assert_eq!(
+        door.members.get("facing"),
+        Some(&PropertyValue::StringValue("North".to_owned()))
+    );
+
+    assert!(loader.project().is_some());
+}
+
+#[test]
+fn test_class_property() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_class_property.tmx")
+        .unwrap();
+
+    assert_eq!(
+        map.properties.get("empty door"),
+        Some(&PropertyValue::ClassValue {
+            property_type: "Door".to_owned(),
+            members: HashMap::new(),
+        })
+    );
+    let key = PropertyValue::ClassValue {
+        property_type: "Key".to_owned(),
+        members: HashMap::from([(
+            "name".into(),
+            PropertyValue::StringValue("golden key".to_owned()),
+        )]),
+    };
+    assert_eq!(
+        map.properties.get("front door"),
+        Some(&PropertyValue::ClassValue {
+            property_type: "Door".to_owned(),
+            members: HashMap::from([
+                ("locked".into(), PropertyValue::BoolValue(true)),
+                ("key".into(), key),
+            ]),
+        })
+    );
+    assert_eq!(
+        map.properties.get("plain"),
+        Some(&PropertyValue::StringValue("after the classes".to_owned()))
+    );
+
+    // JSON class values don't store the types of their members, nor those of nested classes.
+    #[cfg(feature = "json")]
+    {
+        let json_map = Loader::new()
+            .load_json_map("assets/tiled_class_property.tmj")
+            .unwrap();
+        let mut properties = map.properties.clone();
+        if let Some(PropertyValue::ClassValue { members, .. }) = properties.get_mut("front door") {
+            if let Some(PropertyValue::ClassValue { property_type, .. }) = members.get_mut("key") {
+                property_type.clear();
+            }
+        }
+        assert_eq!(json_map.properties, properties);
+    }
+}
+
+#[test]
+fn test_map_class() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_class_property.tmx")
+        .unwrap();
+    assert_eq!(map.user_type.as_deref(), Some("dungeon"));
+
+    #[cfg(feature = "json")]
+    {
+        let json_map = Loader::new()
+            .load_json_map("assets/tiled_class_property.tmj")
+            .unwrap();
+        assert_eq!(json_map.user_type.as_deref(), Some("dungeon"));
+    }
+
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    assert_eq!(map.user_type, None);
+}
+
+#[test]
+fn test_user_types() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_classes.tmx")
+        .unwrap();
+    assert_eq!(map.user_type.as_deref(), Some("interior"));
+
+    let tileset = &map.tilesets()[0];
+    assert_eq!(
+        tileset.get_tile(0).unwrap().user_type.as_deref(),
+        Some("floor")
+    );
+    assert_eq!(
+        tileset.get_tile(1).unwrap().user_type.as_deref(),
+        Some("wall")
+    );
+
+    let layer = map.get_layer(0).unwrap();
+    assert_eq!(layer.user_type.as_deref(), Some("ground"));
+    assert_eq!(map.get_layer(1).unwrap().user_type, None);
+
+    let objects = match map.get_layer(1).unwrap().layer_type() {
+        LayerType::Objects(layer) => layer,
+        _ => panic!(),
+    };
+    let user_types: Vec<_> = objects
+        .objects()
+        .map(|object| object.user_type.clone())
+        .collect();
+    assert_eq!(
+        user_types,
+        [
+            Some("spawn".to_owned()),
+            Some("chest".to_owned()),
+            // Objects inherit the class of their template unless they set their own.
+            Some("wall".to_owned()),
+            Some("door".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn test_enum_property_without_project() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_enum_property.tmx")
+        .unwrap();
+
+    assert_eq!(
+        map.properties.get("facing"),
+        Some(&PropertyValue::StringValue("South".to_owned()))
+    );
+    assert_eq!(
+        map.properties.get("access"),
+        Some(&PropertyValue::IntValue(5))
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_enum_property_resolution() {
+    let mut loader = Loader::new();
+    loader.load_project("assets/tiled.tiled-project").unwrap();
+
+    for (map, is_json) in [
+        (
+            loader
+                .load_tmx_map("assets/tiled_enum_property.tmx")
+                .unwrap(),
+            false,
+        ),
+        (
+            loader
+                .load_json_map("assets/tiled_enum_property.tmj")
+                .unwrap(),
+            true,
+        ),
+    ] {
+        assert_eq!(
+            map.properties.get("facing"),
+            Some(&PropertyValue::EnumValue {
+                type_name: "Direction".to_owned(),
+                value: "South".to_owned(),
+                flags: Vec::new(),
+            })
+        );
+        assert_eq!(
+            map.properties.get("access"),
+            Some(&PropertyValue::EnumValue {
+                type_name: "Access".to_owned(),
+                value: "Player,Npc".to_owned(),
+                flags: vec!["Player".to_owned(), "Npc".to_owned()],
+            })
+        );
+        assert_eq!(
+            map.properties.get("no access"),
+            Some(&PropertyValue::EnumValue {
+                type_name: "Access".to_owned(),
+                value: String::new(),
+                flags: Vec::new(),
+            })
+        );
+        // Types the project doesn't define are left alone.
+        assert_eq!(
+            map.properties.get("unknown"),
+            Some(&PropertyValue::StringValue("South".to_owned()))
+        );
+
+        let object = map
+            .get_layer(1)
+            .unwrap()
+            .as_object_layer()
+            .unwrap()
+            .get_object(0)
+            .unwrap();
+        let members = match object.properties.get("front door") {
+            Some(PropertyValue::ClassValue { members, .. }) => members,
+            x => panic!("Unexpected property value: {:?}", x),
+        };
+        // JSON class members don't store their type, so they can't be resolved.
+        let facing = if is_json {
+            PropertyValue::StringValue("West".to_owned())
+        } else {
+            PropertyValue::EnumValue {
+                type_name: "Direction".to_owned(),
+                value: "West".to_owned(),
+                flags: Vec::new(),
+            }
+        };
+        assert_eq!(members.get("facing"), Some(&facing));
+    }
+}
+
+#[test]
+fn test_deferred_tile_data() {
+    let mut loader = Loader::new();
+    loader.set_defer_tile_data(true);
+    let deferred = loader.load_tmx_map("assets/tiled_base64_zlib.tmx").unwrap();
+    let deferred_infinite = loader
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+
+    let layer = deferred.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert!(!layer.is_decoded());
+    layer.ensure_decoded().unwrap();
+    assert!(layer.is_decoded());
+
+    // Accessing the tiles of a layer decodes it implicitly.
+    let infinite_layer = deferred_infinite
+        .get_layer(0)
+        .unwrap()
+        .as_tile_layer()
+        .unwrap();
+    assert!(!infinite_layer.is_decoded());
+    assert!(infinite_layer.get_tile(0, 0).is_some());
+    assert!(infinite_layer.is_decoded());
+
+    let mut loader = Loader::new();
+    assert_eq!(
+        deferred,
+        loader.load_tmx_map("assets/tiled_base64_zlib.tmx").unwrap()
+    );
+    assert_eq!(
+        deferred_infinite,
+        loader
+            .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+            .unwrap()
+    );
+
+    let path = PathBuf::from("assets/broken_data.tmx");
+    let mut data = String::new();
+    std::fs::File::open("assets/tiled_base64_zlib.tmx")
+        .unwrap()
+        .read_to_string(&mut data)
+        .unwrap();
+    let start = data
+        .find("<data encoding=\"base64\" compression=\"zlib\">")
+        .unwrap();
+    let end = data[start..].find("</data>").unwrap() + start;
+    data.replace_range(
+        start..end,
+        "<data encoding=\"base64\" compression=\"zlib\">not base64!",
+    );
+    let mut loader = Loader::with_cache_and_reader(
+        tiled::DefaultResourceCache::new(),
+        InMemoryResourceReader {
+            path: path.clone(),
+            data: data.into_bytes(),
+        },
+    );
+    assert!(loader.load_tmx_map(&path).is_err());
+    loader.set_defer_tile_data(true);
+    let map = loader.load_tmx_map(&path).unwrap();
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert!(layer.get_tile(0, 0).is_none());
+    assert!(layer.ensure_decoded().is_err());
+}
+
+#[test]
+fn test_map_editing() {
+    let mut map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib.tmx")
+        .unwrap();
+
+    let layer_count = map.layers().len();
+    map.properties
+        .insert("edited".into(), PropertyValue::BoolValue(true));
+    let layer = map.get_layer_mut(0).unwrap();
+    layer.properties.insert(
+        "name".into(),
+        PropertyValue::StringValue("ground".to_owned()),
+    );
+    let tiles = layer.as_finite_tile_layer_mut().unwrap();
+    let mut tile = LayerTileData::new(0, 5);
+    tile.flip_h = true;
+    assert!(tiles.set_tile(3, 4, Some(tile)));
+    assert!(tiles.set_tile(0, 0, None));
+    assert!(!tiles.set_tile(-1, 0, None));
+    assert!(!tiles.set_tile(0, 100, None));
+
+    let mut infinite = LayerData::new_infinite_tile_layer("infinite");
+    infinite
+        .as_infinite_tile_layer_mut()
+        .unwrap()
+        .set_tile(-20, 35, Some(tile));
+    map.add_layer(infinite);
+
+    let mut objects = LayerData::new_object_layer("objects");
+    let object_layer = objects.as_object_layer_mut().unwrap();
+    object_layer.add_object(ObjectData::new_shape(
+        1,
+        8.0,
+        16.0,
+        ObjectShape::Point(8.0, 16.0),
+    ));
+    object_layer.add_object(ObjectData::new_shape(
+        2,
+        0.0,
+        0.0,
+        ObjectShape::Rect {
+            width: 4.0,
+            height: 4.0,
+        },
+    ));
+    object_layer.object_data_mut()[1].name = "box".to_owned();
+    assert_eq!(object_layer.remove_object(0).unwrap().id(), 1);
+    assert!(object_layer.remove_object(1).is_none());
+    map.insert_layer(0, objects);
+
+    assert_eq!(map.layers().len(), layer_count + 2);
+    assert_eq!(map.properties["edited"], PropertyValue::BoolValue(true));
+
+    let objects = map.get_layer(0).unwrap().as_object_layer().unwrap();
+    assert_eq!(objects.objects().len(), 1);
+    assert_eq!(objects.get_object(0).unwrap().name, "box");
+
+    let layer = map.get_layer(1).unwrap();
+    assert_eq!(
+        layer.properties["name"],
+        PropertyValue::StringValue("ground".to_owned())
+    );
+    let tiles = layer.as_tile_layer().unwrap();
+    assert!(tiles.get_tile(0, 0).is_none());
+    let tile = tiles.get_tile(3, 4).unwrap();
+    assert_eq!(tile.id(), 5);
+    assert!(tile.flip_h);
+
+    let infinite = map
+        .get_layer(layer_count + 1)
+        .unwrap()
+        .as_tile_layer()
+        .unwrap();
+    assert_eq!(infinite.get_tile(-20, 35).unwrap().id(), 5);
+    assert!(infinite.get_tile(-20, 34).is_none());
+
+    let removed = map.remove_layer(0).unwrap();
+    assert_eq!(removed.name, "objects");
+    assert!(map.remove_layer(layer_count + 1).is_none());
+    assert_eq!(map.layers().len(), layer_count + 1);
+}
+
+#[test]
+fn test_builders() {
+    let tileset = TilesetBuilder::new("collection", 16, 24)
+        .columns(2)
+        .offset(1, -2)
+        .property("kind", PropertyValue::StringValue("props".to_owned()))
+        .tile(
+            4,
+            TileData {
+                user_type: Some("crate".to_owned()),
+                ..Default::default()
+            },
+        )
+        .tile(9, TileData::default())
+        .build();
+    assert_eq!(tileset.tilecount, 2);
+    assert_eq!(tileset.columns, 2);
+    assert_eq!((tileset.offset_x, tileset.offset_y), (1, -2));
+    assert_eq!(
+        tileset.get_tile(4).unwrap().user_type.as_deref(),
+        Some("crate")
+    );
+
+    let mut group = LayerData::new_group_layer("group");
+    let mut nested = LayerData::new_infinite_tile_layer("nested");
+    nested
+        .as_infinite_tile_layer_mut()
+        .unwrap()
+        .set_tile(-3, 2, Some(LayerTileData::new(0, 9)));
+    group.as_group_layer_mut().unwrap().add_layer(nested);
+
+    let map = MapBuilder::new(Orientation::Isometric, 5, 5, 32, 16)
+        .infinite(true)
+        .background_color(Color {
+            alpha: 255,
+            red: 10,
+            green: 20,
+            blue: 30,
+        })
+        .property("level", PropertyValue::IntValue(3))
+        .tileset(tileset.clone())
+        .layer(LayerData::new_object_layer("objects"))
+        .layer(group)
+        .build()
+        .unwrap();
+    assert!(map.infinite());
+    assert_eq!(map.orientation, Orientation::Isometric);
+    assert_eq!(map.tilesets().len(), 1);
+    assert_eq!(map.properties["level"], PropertyValue::IntValue(3));
+    let ids: Vec<u32> = map.layers().map(|layer| layer.id()).collect();
+    assert_eq!(ids, [1, 2]);
+    let group = map.get_layer(1).unwrap().as_group_layer().unwrap();
+    let nested = group.get_layer(0).unwrap();
+    assert_eq!(nested.id(), 3);
+    let tile = nested.as_tile_layer().unwrap().get_tile(-3, 2).unwrap();
+    assert_eq!(tile.get_tileset().name, "collection");
+
+    // Tiles must refer to tilesets of the map, and tile layers must match the map's infiniteness.
+    let mut layer = LayerData::new_finite_tile_layer("tiles", 2, 2);
+    layer
+        .as_finite_tile_layer_mut()
+        .unwrap()
+        .set_tile(1, 1, Some(LayerTileData::new(1, 0)));
+    assert!(matches!(
+        MapBuilder::new(Orientation::Orthogonal, 2, 2, 16, 16)
+            .tileset(tileset.clone())
+            .layer(layer.clone())
+            .build(),
+        Err(Error::InvalidTileFound)
+    ));
+    assert!(MapBuilder::new(Orientation::Orthogonal, 2, 2, 16, 16)
+        .tileset(tileset.clone())
+        .tileset(tileset)
+        .infinite(true)
+        .layer(layer)
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_orthogonal_coordinates() {
+    let mut layer = LayerData::new_finite_tile_layer("offset", 4, 4);
+    layer.offset_x = 8.0;
+    layer.offset_y = -4.0;
+    let map = MapBuilder::new(Orientation::Orthogonal, 4, 4, 16, 24)
+        .layer(layer)
+        .build()
+        .unwrap();
+
+    assert_eq!(map.tile_to_pixel(0, 0), (0.0, 0.0));
+    assert_eq!(map.tile_to_pixel(3, -2), (48.0, -48.0));
+    assert_eq!(map.pixel_to_tile(47.9, 24.0), (2, 1));
+    assert_eq!(map.pixel_to_tile(-0.5, -0.5), (-1, -1));
+
+    let layer = map.get_layer(0).unwrap();
+    assert_eq!(layer.tile_to_pixel(1, 1), (24.0, 20.0));
+    assert_eq!(layer.pixel_to_tile(8.0, -4.0), (0, 0));
+    assert_eq!(layer.pixel_to_tile(7.9, 19.9), (-1, 0));
+}
+
+#[test]
+fn test_isometric_coordinates() {
+    let map = MapBuilder::new(Orientation::Isometric, 4, 6, 32, 16)
+        .build()
+        .unwrap();
+
+    assert_eq!(map.iso_to_screen(0.0, 0.0), (96.0, 0.0));
+    assert_eq!(map.iso_to_screen(2.0, 0.0), (128.0, 16.0));
+    assert_eq!(map.iso_to_screen(0.0, 6.0), (0.0, 48.0));
+    assert_eq!(map.iso_to_screen(4.0, 6.0), (64.0, 80.0));
+
+    // The center of tile (1, 2) lies in it, half a tile below its top corner.
+    let (x, y) = map.iso_to_screen(1.0, 2.0);
+    let (tile_x, tile_y) = map.screen_to_iso(x, y + 8.0);
+    assert_eq!((tile_x.floor(), tile_y.floor()), (1.0, 2.0));
+    assert_eq!(map.screen_to_iso(x, y), (1.0, 2.0));
+}
+
+#[test]
+fn test_hexagonal_coordinates() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_hexagonal.tmx")
+        .unwrap();
+    assert_eq!(map.hex_side_length, 12);
+    let layout = HexLayout::from_map(&map);
+    assert_eq!(layout.stagger_axis, StaggerAxis::Y);
+    assert_eq!(layout.stagger_index, StaggerIndex::Odd);
+
+    for stagger_axis in [StaggerAxis::X, StaggerAxis::Y] {
+        for stagger_index in [StaggerIndex::Odd, StaggerIndex::Even] {
+            let layout = HexLayout {
+                tile_width: 32,
+                tile_height: 28,
+                side_length: 12,
+                stagger_axis,
+                stagger_index,
+            };
+            for x in -3..4 {
+                for y in -3..4 {
+                    let (px, py) = layout.tile_to_pixel(x, y);
+                    assert_eq!(layout.pixel_to_tile(px + 16.0, py + 14.0), (x, y));
+                }
+            }
+        }
+    }
+
+    let layout = HexLayout {
+        tile_width: 32,
+        tile_height: 28,
+        side_length: 12,
+        stagger_axis: StaggerAxis::X,
+        stagger_index: StaggerIndex::Even,
+    };
+    // Even columns are shifted down by half a tile.
+    assert_eq!(layout.tile_to_pixel(0, 0), (0.0, 14.0));
+    assert_eq!(layout.tile_to_pixel(1, 0), (22.0, 0.0));
+    assert_eq!(layout.tile_to_pixel(2, 1), (44.0, 42.0));
+}
+
+#[test]
+fn test_staggered_coordinates() {
+    for stagger_axis in [StaggerAxis::X, StaggerAxis::Y] {
+        for stagger_index in [StaggerIndex::Odd, StaggerIndex::Even] {
+            let layout = StaggeredLayout {
+                tile_width: 64,
+                tile_height: 32,
+                stagger_axis,
+                stagger_index,
+            };
+            for x in -3..4 {
+                for y in -3..4 {
+                    let (px, py) = layout.tile_to_pixel(x, y);
+                    let center = (px + 32.0, py + 16.0);
+                    assert_eq!(layout.pixel_to_tile(center.0, center.1), (x, y));
+                    // Points near the corners of the diamond's bounding box belong to neighbors.
+                    assert_ne!(layout.pixel_to_tile(px + 2.0, py + 2.0), (x, y));
+                    assert_ne!(layout.pixel_to_tile(px + 62.0, py + 30.0), (x, y));
+                    // Points near the tips of the diamond belong to it.
+                    assert_eq!(layout.pixel_to_tile(px + 32.0, py + 2.0), (x, y));
+                    assert_eq!(layout.pixel_to_tile(px + 3.0, py + 16.0), (x, y));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_map_projection() {
+    let maps = [
+        MapBuilder::new(Orientation::Orthogonal, 8, 8, 32, 32),
+        MapBuilder::new(Orientation::Isometric, 8, 8, 64, 32),
+        MapBuilder::new(Orientation::Staggered, 8, 8, 64, 32).stagger_axis(StaggerAxis::X),
+        MapBuilder::new(Orientation::Hexagonal, 8, 8, 32, 28)
+            .hex_side_length(12)
+            .stagger_index(StaggerIndex::Even),
+    ];
+    for map in maps {
+        let map = map.build().unwrap();
+        for x in 0..8 {
+            for y in 0..8 {
+                let (px, py) = map.project((x as f32, y as f32));
+                let center = match map.orientation {
+                    // The position is the top corner of the diamond.
+                    Orientation::Isometric => (px, py + map.tile_height as f32 / 2.0),
+                    _ => (
+                        px + map.tile_width as f32 / 2.0,
+                        py + map.tile_height as f32 / 2.0,
+                    ),
+                };
+                let (tx, ty) = map.unproject(center);
+                assert_eq!((tx.floor(), ty.floor()), (x as f32, y as f32));
+            }
+        }
+    }
+    let iso = MapBuilder::new(Orientation::Isometric, 8, 8, 64, 32)
+        .build()
+        .unwrap();
+    assert_eq!(iso.project((1.5, 0.5)), iso.iso_to_screen(1.5, 0.5));
+}
+
+#[test]
+fn test_tile_transform() {
+    // Applies a transform to a point of the unit square the same way a renderer would, by
+    // mirroring around and then rotating around its center.
+    fn apply(transform: TileTransform, (x, y): (f32, f32)) -> (f32, f32) {
+        let (mut x, mut y) = (x - 0.5, y - 0.5);
+        if transform.flip_h {
+            x = -x;
+        }
+        for _ in 0..transform.rotation / 90 {
+            let rotated = (-y, x);
+            x = rotated.0;
+            y = rotated.1;
+        }
+        (x + 0.5, y + 0.5)
+    }
+
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_flipped.tmx")
+        .unwrap();
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    let transform = |x, y| layer.get_tile(x, y).unwrap().transform();
+    let expected = |rotation, flip_h| TileTransform { rotation, flip_h };
+    assert_eq!(transform(0, 0), expected(90, true));
+    assert_eq!(transform(1, 0), expected(180, true));
+    assert_eq!(transform(0, 1), expected(0, true));
+    assert_eq!(transform(1, 1), expected(270, true));
+
+    let mut seen = Vec::new();
+    for bits in 0..8 {
+        let mut tile = LayerTileData::new(0, 0);
+        tile.flip_d = bits & 1 != 0;
+        tile.flip_h = bits & 2 != 0;
+        tile.flip_v = bits & 4 != 0;
+        let transform = tile.transform();
+        assert!(!seen.contains(&transform));
+        seen.push(transform);
+
+        let matrix = tile.transform_matrix();
+        for corner in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0), (0.25, 0.5)] {
+            let drawn = (
+                matrix[0][0] * corner.0 + matrix[0][1] * corner.1 + matrix[0][2],
+                matrix[1][0] * corner.0 + matrix[1][1] * corner.1 + matrix[1][2],
+            );
+            assert_eq!(drawn, apply(transform, corner));
+        }
+    }
+}
+
+#[test]
+fn test_tile_image_rect() {
+    let tileset = TilesetBuilder::new("spaced", 16, 8)
+        .margin(2)
+        .spacing(1)
+        .image(Image {
+            source: PathBuf::from("spaced.png"),
+            width: 2 + 16 * 3 + 2,
+            height: 2 + 8 * 2 + 1,
+            transparent_colour: None,
+            handle: None,
+        })
+        .build();
+    assert_eq!(tileset.columns, 3);
+    assert_eq!(tileset.tilecount, 6);
+    assert_eq!(tileset.tile_image_rect(0), Some((2, 2, 16, 8)));
+    assert_eq!(tileset.tile_image_rect(2), Some((36, 2, 16, 8)));
+    assert_eq!(tileset.tile_image_rect(4), Some((19, 11, 16, 8)));
+    assert_eq!(tileset.tile_image_rect(6), None);
+
+    let collection = Loader::new()
+        .load_tsx_tileset("assets/tilesheet.tsx")
+        .map(|mut tileset| {
+            tileset.image = None;
+            tileset
+        })
+        .unwrap();
+    assert_eq!(collection.tile_image_rect(0), None);
+}
+
+#[test]
+fn test_animation_state() {
+    let frames = [
+        Frame {
+            tile_id: 4,
+            duration: 100,
+        },
+        Frame {
+            tile_id: 9,
+            duration: 250,
+        },
+        Frame {
+            tile_id: 2,
+            duration: 100,
+        },
+    ];
+    let mut state = AnimationState::new(&frames);
+    assert_eq!(state.current_frame_index(), 0);
+    assert_eq!(state.current_tile_id(), Some(frames[0].tile_id));
+
+    let total: u32 = frames.iter().map(|frame| frame.duration).sum();
+    state.advance(frames[0].duration - 1);
+    assert_eq!(state.current_frame_index(), 0);
+    state.advance(1);
+    assert_eq!(state.current_tile_id(), Some(9));
+    state.advance(300);
+    assert_eq!(state.current_tile_id(), Some(2));
+    // Advancing by whole loops ends up on the same frame.
+    state.advance(total * 3 + 60);
+    assert_eq!(state.current_tile_id(), Some(4));
+    assert_eq!(state.current_frame().unwrap().duration, 100);
+    state.reset();
+    assert_eq!(state.current_frame_index(), 0);
+
+    let mut state = AnimationState::new(&[
+        Frame {
+            tile_id: 1,
+            duration: 10,
+        },
+        Frame {
+            tile_id: 2,
+            duration: 0,
+        },
+        Frame {
+            tile_id: 3,
+            duration: 5,
+        },
+    ]);
+    state.advance(12);
+    assert_eq!(state.current_tile_id(), Some(3));
+    state.advance(3);
+    assert_eq!(state.current_tile_id(), Some(1));
+    state.advance(u32::MAX);
+    assert_eq!(state.current_tile_id(), Some(1));
+
+    let mut empty = AnimationState::new(&[]);
+    empty.advance(100);
+    assert_eq!(empty.current_tile_id(), None);
+}
+
+#[test]
+fn test_tile_collision_shapes() {
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tiled_tile_collision.tsx")
+        .unwrap();
+    let tile = tileset.get_tile(3).unwrap();
+    let shapes: Vec<ResolvedShape> = tile.collision_shapes().collect();
+    assert_eq!(shapes.len(), 6);
+
+    assert_eq!(
+        shapes[0],
+        ResolvedShape::Rect {
+            x: 2.0,
+            y: 4.0,
+            width: 8.0,
+            height: 6.0
+        }
+    );
+    // Rotated rectangles become polygons.
+    match &shapes[1] {
+        ResolvedShape::Polygon { points } => {
+            let expected = [(16.0, 0.0), (16.0, 10.0), (12.0, 10.0), (12.0, 0.0)];
+            for (point, expected) in points.iter().zip(expected.iter()) {
+                assert!((point.0 - expected.0).abs() < 1e-4);
+                assert!((point.1 - expected.1).abs() < 1e-4);
+            }
+        }
+        shape => panic!("Expected a polygon, got {:?}", shape),
+    }
+    assert_eq!(
+        shapes[2],
+        ResolvedShape::Ellipse {
+            x: 4.0,
+            y: 12.0,
+            width: 6.0,
+            height: 8.0,
+            rotation: 0.0
+        }
+    );
+    assert_eq!(
+        shapes[3],
+        ResolvedShape::Polygon {
+            points: vec![(20.0, 20.0), (24.0, 20.0), (24.0, 24.0)]
+        }
+    );
+    assert_eq!(
+        shapes[4],
+        ResolvedShape::Polyline {
+            points: vec![(1.0, 30.0), (11.0, 28.0)]
+        }
+    );
+    assert_eq!(shapes[5], ResolvedShape::Point(16.0, 16.0));
+
+    assert_eq!(
+        tileset
+            .get_tile(4)
+            .map(|tile| tile.collision_shapes().count()),
+        Some(0)
+    );
 }
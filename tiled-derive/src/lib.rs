@@ -0,0 +1,142 @@
+//! Derive macros for the [`tiled`](https://docs.rs/tiled) crate.
+//!
+//! This crate is not meant to be used directly: Enable the `derive` feature of `tiled` instead,
+//! which re-exports the macros defined here.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr, Type};
+
+/// Derives `tiled::FromProperties` and `tiled::FromPropertyValue` for a struct with named fields.
+///
+/// See the documentation of `tiled::FromProperties` for the attributes supported.
+#[proc_macro_derive(FromProperties, attributes(tiled))]
+pub fn derive_from_properties(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// How a field is read from the properties.
+struct FieldOptions {
+    name: String,
+    default: bool,
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions {
+        name: field
+            .ident
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        default: false,
+    };
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("tiled"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                options.name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                options.default = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename` or `default`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+/// Returns the type wrapped by an [`Option`], if the type given is one.
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "FromProperties can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromProperties can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut initializers = Vec::new();
+    for field in fields {
+        let options = field_options(field)?;
+        let ident = &field.ident;
+        let name = &options.name;
+        let value = match option_inner_type(&field.ty) {
+            Some(ty) => quote! {
+                ::tiled::optional_property::<#ty>(properties, #name)?
+            },
+            None if options.default => {
+                let ty = &field.ty;
+                quote! {
+                    ::tiled::optional_property::<#ty>(properties, #name)?.unwrap_or_default()
+                }
+            }
+            None => {
+                let ty = &field.ty;
+                quote! { ::tiled::required_property::<#ty>(properties, #name)? }
+            }
+        };
+        initializers.push(quote! { #ident: #value });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::tiled::FromProperties for #ident #ty_generics #where_clause {
+            fn from_properties(properties: &::tiled::Properties) -> ::tiled::Result<Self> {
+                Ok(Self {
+                    #(#initializers,)*
+                })
+            }
+        }
+
+        impl #impl_generics ::tiled::FromPropertyValue for #ident #ty_generics #where_clause {
+            const TYPE_NAME: &'static str = "class";
+
+            fn from_property_value(value: &::tiled::PropertyValue) -> Option<Self> {
+                match value {
+                    ::tiled::PropertyValue::ClassValue { members, .. } => {
+                        <Self as ::tiled::FromProperties>::from_properties(members).ok()
+                    }
+                    _ => None,
+                }
+            }
+        }
+    })
+}